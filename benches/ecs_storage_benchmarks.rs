@@ -0,0 +1,77 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_citybuilder_game::ecs::{Mut, StorageStrategy, World};
+use rust_citybuilder_game::grid_game_components::{GridPositionComponent, RenderComponent};
+use std::any::TypeId;
+
+/// A world of `entity_count` entities with a position, half of them also drawn, in the given storage
+fn create_storage_world(entity_count: usize, storage: StorageStrategy) -> World {
+    let mut world = World::new();
+    world.register_storage::<GridPositionComponent>(storage);
+    world.register_storage::<RenderComponent>(storage);
+    for i in 0..entity_count {
+        let entity = world.create_entity();
+        let _ = world.add_component(entity, GridPositionComponent { x: i as i32, y: 0 });
+        if i % 2 == 0 {
+            let _ = world.add_component(entity, RenderComponent { symbol: 'x', color: "black".to_string() });
+        }
+    }
+    world
+}
+
+fn storage_name(storage: StorageStrategy) -> &'static str {
+    match storage {
+        StorageStrategy::Dense => "dense",
+        StorageStrategy::Sparse => "sparse",
+    }
+}
+
+/// Component lookups while iterating a query, which dominate system updates
+fn bench_query_iteration(c: &mut Criterion) {
+    for storage in [StorageStrategy::Dense, StorageStrategy::Sparse] {
+        let world = create_storage_world(10_000, storage);
+        c.bench_function(&format!("query_iteration_{}_10000_entities", storage_name(storage)), |b| {
+            b.iter(|| {
+                for (mut render, position) in world.iter_entities::<Mut<RenderComponent>, GridPositionComponent>() {
+                    if let Some(render) = render.get_mut() {
+                        render.symbol = if position.x % 3 == 0 { 'x' } else { 'o' };
+                    }
+                }
+            })
+        });
+    }
+}
+
+/// Queries recomputed after a pool changed, including putting their entities in ID order
+fn bench_query_rebuild(c: &mut Criterion) {
+    let query = [TypeId::of::<GridPositionComponent>(), TypeId::of::<RenderComponent>()];
+    for storage in [StorageStrategy::Dense, StorageStrategy::Sparse] {
+        let mut world = create_storage_world(10_000, storage);
+        c.bench_function(&format!("query_rebuild_{}_10000_entities", storage_name(storage)), |b| {
+            b.iter(|| {
+                world.remove_component::<RenderComponent>(0);
+                let _ = world.add_component(0, RenderComponent { symbol: 'x', color: String::new() });
+                black_box(world.entities_with_components(&query).len())
+            })
+        });
+    }
+}
+
+/// Adding and removing components on a full pool
+fn bench_component_churn(c: &mut Criterion) {
+    for storage in [StorageStrategy::Dense, StorageStrategy::Sparse] {
+        let mut world = create_storage_world(10_000, storage);
+        c.bench_function(&format!("component_churn_{}_10000_entities", storage_name(storage)), |b| {
+            b.iter(|| {
+                for entity in (1..10_000).step_by(2) {
+                    let _ = world.add_component(entity, RenderComponent { symbol: 'y', color: String::new() });
+                }
+                for entity in (1..10_000).step_by(2) {
+                    black_box(world.remove_component::<RenderComponent>(entity));
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_query_iteration, bench_query_rebuild, bench_component_churn);
+criterion_main!(benches);
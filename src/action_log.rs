@@ -0,0 +1,239 @@
+/// Append-only log of player commands with the tick they were given on, stored as JSON lines next to a save
+/// so designers can study how a city was built and replays and undo can work from the same record
+use crate::autotile::TileKind;
+use crate::construction::BuildingKind;
+use crate::economy::ZoneType;
+use crate::stable_id::StableId;
+use crate::terrain::TerrainEdit;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Logs of earlier sessions kept next to a save (`city.actions.1.jsonl` is the latest)
+pub const ROTATED_LOGS: u32 = 5;
+
+/// A command the player gave the simulation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PlayerAction {
+    Move { dx: i32, dy: i32 },
+    Build { kind: BuildingKind, x: i32, y: i32 },
+    PlaceTile { kind: TileKind, x: i32, y: i32 },
+    Terraform { edit: TerrainEdit, x: i32, y: i32 },
+    /// `target` names the building marked, which the tile alone doesn't once the city has changed
+    Demolish {
+        x: i32,
+        y: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target: Option<StableId>,
+    },
+    Zone { zone: ZoneType, tiles: Vec<(i32, i32)> },
+    CopyBlueprint { name: String, tiles: Vec<(i32, i32)> },
+    StampBlueprint { name: String, x: i32, y: i32 },
+    SetTaxRate { zone: ZoneType, rate: u32 },
+    TakeLoan { amount: i64 },
+    RepayLoan { index: usize, amount: i64 },
+    EnactPolicy { name: String },
+    RepealPolicy { name: String },
+}
+
+/// One line of the log: an action and the number of updates run before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionRecord {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub action: PlayerAction,
+}
+
+// First line of a log file, naming the seed the session's stable IDs came from
+#[derive(Serialize, Deserialize)]
+struct LogHeader {
+    stable_id_seed: u64,
+}
+
+/// Player commands in the order they were applied
+#[derive(Debug, Clone, Default)]
+pub struct ActionLog {
+    records: Vec<ActionRecord>,
+    // Seed of the session's stable IDs, written at the top of the file so a replay hands out the
+    // same IDs the logged commands name
+    stable_id_seed: Option<u64>,
+    // Records already written to disk, and whether records already written were dropped since
+    persisted: usize,
+    rewrite: bool,
+}
+
+impl ActionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, tick: u64, action: PlayerAction) {
+        self.records.push(ActionRecord { tick, action });
+    }
+
+    pub fn records(&self) -> &[ActionRecord] {
+        &self.records
+    }
+
+    pub fn set_stable_id_seed(&mut self, seed: u64) {
+        self.stable_id_seed = Some(seed);
+    }
+
+    /// Seed of the stable IDs of the session that wrote the log, if it recorded one
+    pub fn stable_id_seed(&self) -> Option<u64> {
+        self.stable_id_seed
+    }
+
+    /// Keep only the first `len` records, as after rewinding to an earlier tick; the next append
+    /// rewrites the log on disk when written records were dropped
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.persisted {
+            self.persisted = 0;
+            self.rewrite = true;
+        }
+        self.records.truncate(len);
+    }
+
+    /// Records from `tick` onwards
+    pub fn since(&self, tick: u64) -> &[ActionRecord] {
+        &self.records[self.records.partition_point(|record| record.tick < tick)..]
+    }
+
+    /// Records as JSON lines, one action per line
+    pub fn to_jsonl(records: &[ActionRecord]) -> Result<String, Box<dyn Error>> {
+        let mut text = String::new();
+        for record in records {
+            text.push_str(&serde_json::to_string(record)?);
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Path of the log stored next to a save file (`city.sav` -> `city.actions.jsonl`)
+    pub fn path_for_save(save_path: &Path) -> PathBuf {
+        save_path.with_extension("actions.jsonl")
+    }
+
+    /// Path of an earlier session's log (`city.sav`, 1 -> `city.actions.1.jsonl`)
+    pub fn rotated_path_for_save(save_path: &Path, generation: u32) -> PathBuf {
+        save_path.with_extension(format!("actions.{}.jsonl", generation))
+    }
+
+    /// Append the records not yet written to the log next to the given save file
+    /// A log that was never written starts a new file with the stable ID seed, rotating the previous
+    /// session's log out of the way; returns the number of records written
+    pub fn append_alongside(&mut self, save_path: &Path) -> Result<usize, Box<dyn Error>> {
+        if self.persisted == self.records.len() && !self.rewrite {
+            return Ok(0);
+        }
+        let path = Self::path_for_save(save_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if self.persisted == 0 && !self.rewrite && path.exists() {
+            Self::rotate(save_path)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(self.persisted > 0)
+            .write(true)
+            .truncate(self.persisted == 0)
+            .open(&path)?;
+
+        let unwritten = &self.records[self.persisted..];
+        let mut text = String::new();
+        if let (0, Some(stable_id_seed)) = (self.persisted, self.stable_id_seed) {
+            text.push_str(&serde_json::to_string(&LogHeader { stable_id_seed })?);
+            text.push('\n');
+        }
+        text.push_str(&Self::to_jsonl(unwritten)?);
+        file.write_all(text.as_bytes())?;
+        let written = unwritten.len();
+        self.persisted = self.records.len();
+        self.rewrite = false;
+        Ok(written)
+    }
+
+    // Shift the kept logs one generation back, dropping the oldest, and move the current log to generation 1
+    fn rotate(save_path: &Path) -> Result<(), Box<dyn Error>> {
+        for generation in (1..ROTATED_LOGS).rev() {
+            let older = Self::rotated_path_for_save(save_path, generation);
+            if older.exists() {
+                fs::rename(&older, Self::rotated_path_for_save(save_path, generation + 1))?;
+            }
+        }
+        fs::rename(Self::path_for_save(save_path), Self::rotated_path_for_save(save_path, 1))?;
+        Ok(())
+    }
+
+    /// Load the log stored next to the given save file; a save without one has an empty log
+    pub fn load_alongside(save_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = Self::path_for_save(save_path);
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        Self::load(&path)
+    }
+
+    /// Load a log file, e.g. `saves/city.actions.jsonl` or one exported from `/api/v1/actions`
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty()).peekable();
+        let stable_id_seed = lines.next_if(|line| serde_json::from_str::<LogHeader>(line).is_ok())
+            .and_then(|line| serde_json::from_str::<LogHeader>(line).ok())
+            .map(|header| header.stable_id_seed);
+        let records = lines.map(serde_json::from_str).collect::<Result<Vec<ActionRecord>, _>>()?;
+        Ok(Self { persisted: records.len(), records, stable_id_seed, rewrite: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_load_alongside() {
+        let save_path = std::env::temp_dir().join(format!("action_log_test_{}.sav", std::process::id()));
+        let mut log = ActionLog::new();
+        log.set_stable_id_seed(42);
+        log.push(0, PlayerAction::Build { kind: BuildingKind::House, x: 4, y: 6 });
+        log.push(3, PlayerAction::SetTaxRate { zone: ZoneType::Commercial, rate: 12 });
+        assert_eq!(log.append_alongside(&save_path).unwrap(), 2);
+
+        // Later records are appended without rewriting earlier ones
+        log.push(7, PlayerAction::Zone { zone: ZoneType::Residential, tiles: vec![(1, 6), (2, 6)] });
+        assert_eq!(log.append_alongside(&save_path).unwrap(), 1);
+
+        let text = fs::read_to_string(ActionLog::path_for_save(&save_path)).unwrap();
+        assert_eq!(text.lines().take(2).collect::<Vec<_>>(), vec![
+            r#"{"stable_id_seed":42}"#,
+            r#"{"tick":0,"type":"Build","kind":"House","x":4,"y":6}"#,
+        ]);
+        let loaded = ActionLog::load_alongside(&save_path).unwrap();
+        assert_eq!(loaded.records(), log.records());
+        assert_eq!(loaded.stable_id_seed(), Some(42));
+        assert_eq!(loaded.since(3).len(), 2);
+
+        // Rewinding past written records rewrites the file in place
+        log.truncate(1);
+        assert_eq!(log.append_alongside(&save_path).unwrap(), 1);
+        assert_eq!(ActionLog::load_alongside(&save_path).unwrap().records(), log.records());
+        assert!(!ActionLog::rotated_path_for_save(&save_path, 1).exists());
+        log.push(3, PlayerAction::SetTaxRate { zone: ZoneType::Commercial, rate: 12 });
+        log.push(7, PlayerAction::Zone { zone: ZoneType::Residential, tiles: vec![(1, 6), (2, 6)] });
+        assert_eq!(log.append_alongside(&save_path).unwrap(), 2);
+        assert_eq!(fs::read_to_string(ActionLog::path_for_save(&save_path)).unwrap(), text);
+
+        // The next session, e.g. after a server restart, keeps the earlier log under a numbered name
+        let mut next_session = ActionLog::new();
+        next_session.push(0, PlayerAction::TakeLoan { amount: 100 });
+        assert_eq!(next_session.append_alongside(&save_path).unwrap(), 1);
+        assert_eq!(ActionLog::load_alongside(&save_path).unwrap().records(), next_session.records());
+        assert_eq!(fs::read_to_string(ActionLog::rotated_path_for_save(&save_path, 1)).unwrap(), text);
+        fs::remove_file(ActionLog::rotated_path_for_save(&save_path, 1)).unwrap();
+        fs::remove_file(ActionLog::path_for_save(&save_path)).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+/// Citizen agents that walk between destinations using grid pathfinding
+use crate::avoidance::LocalAvoidance;
+use crate::construction::BuildingComponent;
+use crate::ecs::{Component, Entity, World};
+use crate::economy::{ZoneComponent, ZoneType};
+use crate::grid_game_components::GridPositionComponent;
+use crate::pathfinding::{blocked_tiles, PathComponent, PathRequestComponent, PathRequestStatus};
+use crate::stable_id::{EntityRef, EntityRefs};
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+
+/// Number of updates an agent waits after reaching a destination
+pub const AGENT_DWELL_TICKS: u32 = 3;
+
+/// State machine of an agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgentState {
+    /// Ready to plan a path to the next destination
+    Idle,
+    /// Waiting for the path planner to answer its request
+    Planning { destination: (i32, i32) },
+    /// Following a path towards a destination
+    Moving { destination: (i32, i32) },
+    /// Staying at a destination for a few updates
+    Waiting { ticks_left: u32 },
+    /// No path to the destination was found; retries after a few updates
+    Stuck { destination: (i32, i32), retry_in: u32 },
+}
+
+/// Component for citizens and vehicles that travel around the map
+#[derive(Clone, Debug)]
+pub struct AgentComponent {
+    pub name: String,
+    pub state: AgentState,
+    /// Destinations visited in order, looping back to the first
+    pub destinations: Vec<(i32, i32)>,
+    pub next_destination: usize,
+    /// Residential building on the last destination, once one stands there
+    pub home: Option<EntityRef>,
+}
+
+impl AgentComponent {
+    pub fn new(name: &str, destinations: Vec<(i32, i32)>) -> Self {
+        Self {
+            name: name.to_string(),
+            state: AgentState::Idle,
+            destinations,
+            next_destination: 0,
+            home: None,
+        }
+    }
+
+    fn current_destination(&self) -> Option<(i32, i32)> {
+        self.destinations.get(self.next_destination).copied()
+    }
+
+    fn advance_destination(&mut self) {
+        if !self.destinations.is_empty() {
+            self.next_destination = (self.next_destination + 1) % self.destinations.len();
+        }
+    }
+}
+
+impl Component for AgentComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+
+    fn remap_entities(&mut self, entity_remap: &HashMap<Entity, Entity>) {
+        if let Some(home) = &mut self.home {
+            home.remap(entity_remap);
+        }
+    }
+}
+
+impl EntityRefs for AgentComponent {
+    fn entity_refs(&self) -> Vec<&EntityRef> {
+        self.home.iter().collect()
+    }
+
+    fn entity_refs_mut(&mut self) -> Vec<&mut EntityRef> {
+        self.home.iter_mut().collect()
+    }
+}
+
+/// System driving the agent state machine and moving agents one tile per update
+/// Paths are requested from the budgeted `PathPlanningSystem`
+pub struct AgentSystem;
+
+impl AgentSystem {
+    /// Give agents without a home the residential building standing on their last destination, if it has
+    /// a stable ID; returns how many moved in
+    pub fn assign_homes(world: &mut World) -> usize {
+        let houses = world.entities_with_components(&[
+            TypeId::of::<BuildingComponent>(),
+            TypeId::of::<ZoneComponent>(),
+            TypeId::of::<GridPositionComponent>(),
+        ]);
+        let homes: HashMap<(i32, i32), EntityRef> = houses.into_iter()
+            .filter(|&house| world.get_component::<ZoneComponent>(house).is_some_and(|zone| zone.zone_type == ZoneType::Residential))
+            .filter_map(|house| {
+                let pos = world.get_component::<GridPositionComponent>(house)?;
+                Some(((pos.x, pos.y), EntityRef::to(world, house)?))
+            })
+            .collect();
+
+        let mut moved_in = 0;
+        for entity in world.entities_with_components(&[TypeId::of::<AgentComponent>()]) {
+            let Some(mut agent) = world.get_component_mut::<AgentComponent>(entity) else { continue };
+            if agent.home.is_some() {
+                continue;
+            }
+            if let Some(home) = agent.destinations.last().and_then(|tile| homes.get(tile)) {
+                agent.home = Some(*home);
+                moved_in += 1;
+            }
+        }
+        moved_in
+    }
+
+    /// Update every agent, starting a tick of `avoidance`; the caller keeps it between ticks, so travellers
+    /// remember how long they have waited for a tile
+    pub fn update(world: &mut World, avoidance: &mut LocalAvoidance) {
+        avoidance.begin(world);
+        Self::update_where(world, avoidance, |_, _| true);
+    }
+
+    /// Update only the agents `should_update` picks, e.g. those in fully simulated regions
+    /// Moves go through `avoidance` after the path is followed and before the position is written
+    pub fn update_where(world: &mut World, avoidance: &mut LocalAvoidance, should_update: impl Fn(&World, Entity) -> bool) {
+        let blocked = blocked_tiles(world);
+        let agents: Vec<Entity> = world.entities_with_components(&[
+            TypeId::of::<AgentComponent>(),
+            TypeId::of::<GridPositionComponent>(),
+        ]).into_iter().filter(|entity| should_update(world, *entity)).collect();
+
+        for entity in agents {
+            let Some(state) = world.get_component::<AgentComponent>(entity).map(|agent| agent.state) else { continue };
+            let new_state = Self::next_state(world, entity, state, &blocked, avoidance);
+            if let Some(mut agent) = world.get_component_mut::<AgentComponent>(entity) {
+                agent.state = new_state;
+            }
+        }
+    }
+
+    fn request_path(world: &mut World, entity: Entity, destination: (i32, i32)) -> AgentState {
+        world.remove_component::<PathComponent>(entity);
+        match world.add_component(entity, PathRequestComponent::new(destination)) {
+            Ok(()) => AgentState::Planning { destination },
+            Err(_) => AgentState::Idle,
+        }
+    }
+
+    fn next_state(
+        world: &mut World,
+        entity: Entity,
+        state: AgentState,
+        blocked: &HashSet<(i32, i32)>,
+        avoidance: &mut LocalAvoidance,
+    ) -> AgentState {
+        match state {
+            AgentState::Idle | AgentState::Stuck { retry_in: 0, .. } => {
+                let destination = world.get_component::<AgentComponent>(entity).and_then(|a| a.current_destination());
+                match destination {
+                    Some(destination) => Self::request_path(world, entity, destination),
+                    None => AgentState::Idle,
+                }
+            }
+            AgentState::Stuck { destination, retry_in } => AgentState::Stuck { destination, retry_in: retry_in - 1 },
+            AgentState::Planning { destination } => {
+                let status = world.get_component::<PathRequestComponent>(entity).map(|request| request.status);
+                match status {
+                    Some(PathRequestStatus::Pending) => state,
+                    Some(PathRequestStatus::Found) => {
+                        world.remove_component::<PathRequestComponent>(entity);
+                        AgentState::Moving { destination }
+                    }
+                    Some(PathRequestStatus::Failed) => {
+                        world.remove_component::<PathRequestComponent>(entity);
+                        AgentState::Stuck { destination, retry_in: AGENT_DWELL_TICKS }
+                    }
+                    // The request disappeared, ask again
+                    None => Self::request_path(world, entity, destination),
+                }
+            }
+            AgentState::Moving { destination } => {
+                let next = world.get_component::<PathComponent>(entity).and_then(|path| path.next_waypoint());
+                match next {
+                    Some(next) if blocked.contains(&next) => Self::request_path(world, entity, destination),
+                    Some(next) => {
+                        let from = world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y));
+                        if !from.is_some_and(|from| avoidance.request(entity, from, next, next == destination)) {
+                            return state;
+                        }
+                        if let Some(mut pos) = world.get_component_mut::<GridPositionComponent>(entity) {
+                            pos.x = next.0;
+                            pos.y = next.1;
+                        }
+                        if let Some(mut path) = world.get_component_mut::<PathComponent>(entity) {
+                            path.current += 1;
+                        }
+                        state
+                    }
+                    None => {
+                        world.remove_component::<PathComponent>(entity);
+                        if let Some(mut agent) = world.get_component_mut::<AgentComponent>(entity) {
+                            agent.advance_destination();
+                        }
+                        AgentState::Waiting { ticks_left: AGENT_DWELL_TICKS }
+                    }
+                }
+            }
+            AgentState::Waiting { ticks_left } if ticks_left <= 1 => AgentState::Idle,
+            AgentState::Waiting { ticks_left } => AgentState::Waiting { ticks_left: ticks_left - 1 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avoidance::AVOIDANCE_PATIENCE;
+    use crate::budgeted_system::{BudgetedSystem, WorkBudget};
+    use crate::grid_game_components::ObstacleComponent;
+    use crate::pathfinding::PathPlanningSystem;
+
+    fn spawn_agent(world: &mut World, start: (i32, i32), destinations: Vec<(i32, i32)>) -> Entity {
+        world.spawn((
+            GridPositionComponent { x: start.0, y: start.1 },
+            AgentComponent::new("Citizen", destinations),
+        )).unwrap()
+    }
+
+    fn run_frame(world: &mut World, planner: &mut PathPlanningSystem, avoidance: &mut LocalAvoidance) {
+        AgentSystem::update(world, avoidance);
+        planner.run_slice(world, &mut WorkBudget::unlimited());
+    }
+
+    fn state(world: &World, agent: Entity) -> AgentState {
+        world.get_component::<AgentComponent>(agent).unwrap().state
+    }
+
+    #[test]
+    fn test_agent_walks_to_destination_and_waits() {
+        let mut world = World::new();
+        let mut planner = PathPlanningSystem::new(5, 5);
+        let mut avoidance = LocalAvoidance::default();
+        let agent = spawn_agent(&mut world, (0, 0), vec![(2, 0), (0, 0)]);
+
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        assert_eq!(state(&world, agent), AgentState::Planning { destination: (2, 0) });
+        assert_eq!(world.get_component::<PathComponent>(agent).unwrap().waypoints.len(), 3);
+
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        assert_eq!(state(&world, agent), AgentState::Moving { destination: (2, 0) });
+        assert!(!world.has_component::<PathRequestComponent>(agent));
+
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        assert_eq!(world.get_component::<GridPositionComponent>(agent).unwrap().x, 2);
+
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        assert_eq!(state(&world, agent), AgentState::Waiting { ticks_left: AGENT_DWELL_TICKS });
+        assert!(!world.has_component::<PathComponent>(agent));
+        assert_eq!(world.get_component::<AgentComponent>(agent).unwrap().next_destination, 1);
+    }
+
+    #[test]
+    fn test_agent_waiting_for_a_tile_runs_out_of_patience_across_updates() {
+        let mut world = World::new();
+        let mut planner = PathPlanningSystem::new(5, 5);
+        let mut avoidance = LocalAvoidance::default();
+        let agent = spawn_agent(&mut world, (0, 0), vec![(2, 0)]);
+        spawn_agent(&mut world, (1, 0), Vec::new());
+
+        // Planning, then starting to move, then waiting for the idle citizen's tile
+        for _ in 0..2 + AVOIDANCE_PATIENCE {
+            run_frame(&mut world, &mut planner, &mut avoidance);
+        }
+        assert_eq!(world.get_component::<GridPositionComponent>(agent).unwrap().x, 0);
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        assert_eq!(world.get_component::<GridPositionComponent>(agent).unwrap().x, 1);
+        assert_eq!(avoidance.stats().forced, 1);
+    }
+
+    #[test]
+    fn test_agent_stuck_without_path() {
+        let mut world = World::new();
+        let mut planner = PathPlanningSystem::new(5, 5);
+        let mut avoidance = LocalAvoidance::default();
+        let agent = spawn_agent(&mut world, (0, 0), vec![(4, 0)]);
+        for y in 0..5 {
+            world.spawn((GridPositionComponent { x: 2, y }, ObstacleComponent { block_movement: true })).unwrap();
+        }
+
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        run_frame(&mut world, &mut planner, &mut avoidance);
+        assert_eq!(state(&world, agent), AgentState::Stuck { destination: (4, 0), retry_in: AGENT_DWELL_TICKS });
+
+        for _ in 0..=AGENT_DWELL_TICKS {
+            run_frame(&mut world, &mut planner, &mut avoidance);
+        }
+        assert_eq!(state(&world, agent), AgentState::Planning { destination: (4, 0) });
+    }
+}
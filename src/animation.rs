@@ -0,0 +1,122 @@
+/// Smooth movement between tiles: logic moves entities a whole tile at once, rendering follows over a short tween
+use crate::ecs::{Component, Entity, InvalidComponent, World};
+use crate::core::math::Vector2d;
+use crate::core::math::transform2d_component::Transform2dComponent;
+use std::any::{Any, TypeId};
+
+/// Time a one-tile move takes to play out on screen, in seconds
+pub const MOVE_ANIMATION_SECONDS: f32 = 0.1;
+
+/// Center of a tile in world units
+pub fn tile_center(x: i32, y: i32, cell_size: f32) -> Vector2d {
+    Vector2d::new((x as f32 + 0.5) * cell_size, (y as f32 + 0.5) * cell_size)
+}
+
+/// Component for an entity whose rendered position is catching up with its tile position
+/// Created when a move succeeds and removed by `MoveAnimationSystem` once it finishes
+#[derive(Clone, Debug)]
+pub struct MoveAnimation {
+    pub from: Vector2d,
+    pub to: Vector2d,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+impl MoveAnimation {
+    pub fn new(from: Vector2d, to: Vector2d) -> Self {
+        Self { from, to, elapsed: 0.0, duration: MOVE_ANIMATION_SECONDS }
+    }
+
+    /// Fraction of the animation played, from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+
+    /// Interpolated position, eased so the move starts quickly and settles into the tile
+    pub fn position(&self) -> Vector2d {
+        let t = 1.0 - (1.0 - self.progress()).powi(2);
+        self.from + (self.to - self.from) * t
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+impl Component for MoveAnimation {
+    fn validate(&self) -> bool {
+        self.duration > 0.0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// System that advances move animations and writes the interpolated position into the entity's transform
+pub struct MoveAnimationSystem;
+
+impl MoveAnimationSystem {
+    pub fn update(world: &mut World, delta_seconds: f32) {
+        for entity in world.entities_with_components(&[TypeId::of::<MoveAnimation>()]) {
+            let (position, finished) = {
+                let Some(mut animation) = world.get_component_mut::<MoveAnimation>(entity) else { continue };
+                animation.elapsed += delta_seconds.max(0.0);
+                (animation.position(), animation.is_finished())
+            };
+
+            if !world.has_component::<Transform2dComponent>(entity) && world.add_component(entity, Transform2dComponent::new()).is_err() {
+                continue;
+            }
+            if let Some(mut transform) = world.get_component_mut::<Transform2dComponent>(entity) {
+                transform.set_translation(position);
+            }
+            if finished {
+                world.remove_component::<MoveAnimation>(entity);
+            }
+        }
+    }
+
+    /// Start animating an entity towards a tile, continuing from wherever it is currently drawn
+    pub fn start(world: &mut World, entity: Entity, to: Vector2d, fallback_from: Vector2d) -> Result<(), InvalidComponent> {
+        let from = world.get_component::<MoveAnimation>(entity)
+            .map(|animation| animation.position())
+            .or_else(|| world.get_component::<Transform2dComponent>(entity).map(|transform| transform.translation()))
+            .unwrap_or(fallback_from);
+        world.add_component(entity, MoveAnimation::new(from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_animation_eases_to_target_and_finishes() {
+        let mut world = World::new();
+        let entity = world.create_entity();
+        MoveAnimationSystem::start(&mut world, entity, tile_center(2, 1, 32.0), tile_center(1, 1, 32.0)).unwrap();
+
+        MoveAnimationSystem::update(&mut world, MOVE_ANIMATION_SECONDS / 2.0);
+        let halfway = world.get_component::<Transform2dComponent>(entity).unwrap().translation();
+        assert!(halfway.x > 56.0 && halfway.x < 80.0);
+        assert!((halfway.y - 48.0).abs() < 0.001);
+
+        // A new move mid-animation starts from the drawn position, not the old tile
+        MoveAnimationSystem::start(&mut world, entity, tile_center(3, 1, 32.0), tile_center(2, 1, 32.0)).unwrap();
+        assert_eq!(world.get_component::<MoveAnimation>(entity).unwrap().from, halfway);
+
+        MoveAnimationSystem::update(&mut world, MOVE_ANIMATION_SECONDS);
+        assert!(!world.has_component::<MoveAnimation>(entity));
+        let settled = world.get_component::<Transform2dComponent>(entity).unwrap().translation();
+        assert!((settled.x - 112.0).abs() < 0.001);
+    }
+}
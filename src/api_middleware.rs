@@ -0,0 +1,355 @@
+/// Checks run on every game server request before it reaches the simulation: per-client rate limiting,
+/// the role the endpoint needs and validation of command bodies, all answered with machine-readable errors
+use crate::auth::{AuthConfig, Authenticator, Role};
+use crate::rendering::web_service_manager::BufferedResponse;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Response};
+
+/// Requests a client may send in one burst
+pub const RATE_LIMIT_BURST: f64 = 120.0;
+/// Requests per second a client may sustain
+pub const RATE_LIMIT_PER_SECOND: f64 = 40.0;
+// Past this many tracked clients, buckets that refilled completely are dropped
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+/// A rejected request: HTTP status, a stable code for programs and a message for people
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: u16,
+    pub code: &'static str,
+    pub message: String,
+    pub retry_after: Option<Duration>,
+}
+
+impl ApiError {
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self { status: 400, code, message: message.into(), retry_after: None }
+    }
+
+    pub fn not_found(code: &'static str, message: impl Into<String>) -> Self {
+        Self { status: 404, code, message: message.into(), retry_after: None }
+    }
+
+    pub fn unauthorized() -> Self {
+        Self { status: 401, code: "unauthorized", message: "Unknown access token".to_string(), retry_after: None }
+    }
+
+    pub fn forbidden(required: Role) -> Self {
+        Self {
+            status: 403,
+            code: "forbidden",
+            message: format!("This endpoint needs the {} role", required.name()),
+            retry_after: None,
+        }
+    }
+
+    pub fn rate_limited(retry_after: Duration) -> Self {
+        Self {
+            status: 429,
+            code: "rate_limited",
+            message: "Too many requests, slow down".to_string(),
+            retry_after: Some(retry_after),
+        }
+    }
+
+    /// Error body in the shape of the other API failures, plus the code
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({"success": false, "code": self.code, "error": self.message})
+    }
+
+    pub fn to_response(&self) -> BufferedResponse {
+        let mut response = Response::from_string(self.to_json().to_string()).with_status_code(self.status);
+        if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]) {
+            response = response.with_header(header);
+        }
+        if let Some(retry_after) = self.retry_after {
+            let seconds = retry_after.as_secs_f64().ceil().max(1.0).to_string();
+            if let Ok(header) = Header::from_bytes(&b"Retry-After"[..], seconds.as_bytes()) {
+                response = response.with_header(header);
+            }
+        }
+        response
+    }
+}
+
+/// Token bucket per client: each request takes a token and tokens refill at a steady rate
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    burst: f64,
+    per_second: f64,
+    buckets: HashMap<String, (f64, Instant)>,
+}
+
+impl RateLimiter {
+    pub fn new(burst: f64, per_second: f64) -> Self {
+        Self { burst, per_second, buckets: HashMap::new() }
+    }
+
+    /// Take a token for `client`, or report how long until one is available
+    pub fn check(&mut self, client: &str, now: Instant) -> Result<(), ApiError> {
+        if self.buckets.len() > MAX_TRACKED_CLIENTS {
+            let (burst, per_second) = (self.burst, self.per_second);
+            self.buckets.retain(|_, (tokens, last)| {
+                *tokens + now.saturating_duration_since(*last).as_secs_f64() * per_second < burst
+            });
+        }
+
+        let (tokens, last) = self.buckets.entry(client.to_string()).or_insert((self.burst, now));
+        *tokens = (*tokens + now.saturating_duration_since(*last).as_secs_f64() * self.per_second).min(self.burst);
+        *last = now;
+        if *tokens < 1.0 {
+            return Err(ApiError::rate_limited(Duration::from_secs_f64((1.0 - *tokens) / self.per_second)));
+        }
+        *tokens -= 1.0;
+        Ok(())
+    }
+}
+
+/// JSON type a body field must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Array,
+    Object,
+}
+
+impl FieldType {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::Integer => value.is_i64() || value.is_u64(),
+            FieldType::Number => value.is_number(),
+            FieldType::Boolean => value.is_boolean(),
+            FieldType::Array => value.is_array(),
+            FieldType::Object => value.is_object(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FieldType::String => "a string",
+            FieldType::Integer => "an integer",
+            FieldType::Number => "a number",
+            FieldType::Boolean => "a boolean",
+            FieldType::Array => "an array",
+            FieldType::Object => "an object",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FieldRule {
+    name: &'static str,
+    field_type: FieldType,
+    required: bool,
+    allowed: &'static [&'static str],
+}
+
+/// Expected shape of a command's JSON body; fields not listed are ignored
+#[derive(Debug, Clone, Default)]
+pub struct BodySchema {
+    fields: Vec<FieldRule>,
+}
+
+impl BodySchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn required(mut self, name: &'static str, field_type: FieldType) -> Self {
+        self.fields.push(FieldRule { name, field_type, required: true, allowed: &[] });
+        self
+    }
+
+    pub fn optional(mut self, name: &'static str, field_type: FieldType) -> Self {
+        self.fields.push(FieldRule { name, field_type, required: false, allowed: &[] });
+        self
+    }
+
+    /// A required string field limited to a set of values
+    pub fn one_of(mut self, name: &'static str, allowed: &'static [&'static str]) -> Self {
+        self.fields.push(FieldRule { name, field_type: FieldType::String, required: true, allowed });
+        self
+    }
+
+    /// Parse a raw body and check it against the schema
+    pub fn validate(&self, body: &[u8]) -> Result<Value, ApiError> {
+        let value: Value = serde_json::from_slice(body)
+            .map_err(|e| ApiError::bad_request("invalid_json", format!("Body is not valid JSON: {}", e)))?;
+        let Some(object) = value.as_object() else {
+            return Err(ApiError::bad_request("invalid_body", "Body must be a JSON object"));
+        };
+
+        for rule in &self.fields {
+            match object.get(rule.name) {
+                None | Some(Value::Null) if rule.required => {
+                    return Err(ApiError::bad_request("missing_field", format!("Missing field '{}'", rule.name)));
+                }
+                None | Some(Value::Null) => {}
+                Some(field) if !rule.field_type.matches(field) => {
+                    return Err(ApiError::bad_request(
+                        "invalid_field",
+                        format!("Field '{}' must be {}", rule.name, rule.field_type.name()),
+                    ));
+                }
+                Some(field) => {
+                    let text = field.as_str().unwrap_or_default();
+                    if !rule.allowed.is_empty() && !rule.allowed.contains(&text) {
+                        return Err(ApiError::bad_request(
+                            "invalid_field",
+                            format!("Field '{}' must be one of: {}", rule.name, rule.allowed.join(", ")),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// Schema of a command endpoint's body; `None` for requests without a body to check
+pub fn command_schema(method: &Method, url: &str) -> Option<BodySchema> {
+    use FieldType::*;
+    let path = url.split('?').next().unwrap_or(url);
+    let schema = BodySchema::new();
+    let schema = match (method, path) {
+        (Method::Post, "/move") => schema.one_of("direction", &["up", "down", "left", "right"]).optional("clientId", String),
+        (Method::Post, "/api/v1/input") => schema.required("events", Array),
+        (Method::Post, "/api/v1/connect") => schema.optional("clientId", String),
+        (Method::Post, "/api/v1/heartbeat") => schema.required("clientId", String).optional("pong", Integer),
+        (Method::Post, "/api/v1/disconnect") => schema.required("clientId", String),
+        (Method::Post, "/api/v1/events/subscribe") => schema.required("clientId", String).required("topics", Array).optional("unsubscribe", Boolean),
+        (Method::Post, "/api/v1/events") => schema.required("clientId", String).required("topic", String).optional("payload", Object),
+        (Method::Post, "/api/v1/preload") => schema.required("clientId", String).optional("loaded", Integer).optional("failed", Integer),
+        (Method::Post, "/api/v1/capture") => schema
+            .required("clientId", String)
+            .required("width", Integer)
+            .required("height", Integer)
+            .required("rgba", String),
+        (Method::Put, "/api/v1/settings") => schema
+            .optional("key_bindings", Object)
+            .optional("ui_scale", Number)
+            .optional("theme", String)
+            .optional("autosave_interval_seconds", Integer),
+        (Method::Post, "/api/v1/budget/taxes") => schema.required("zone", String).required("rate", Integer),
+        (Method::Post, "/api/v1/budget/loans") => schema.required("amount", Integer),
+        (Method::Post, "/api/v1/budget/loans/repay") => schema.required("index", Integer).required("amount", Integer),
+        (Method::Post, "/api/v1/policies/enact") | (Method::Post, "/api/v1/policies/repeal") => schema.required("policy", String),
+        (Method::Post, "/api/v1/build") => schema.required("kind", String).required("x", Integer).required("y", Integer),
+        (Method::Post, "/api/v1/tiles") => schema.one_of("kind", &["road", "wall", "bridge", "tunnel"]).required("x", Integer).required("y", Integer),
+        (Method::Post, "/api/v1/selection") => schema
+            .one_of("tool", &["zone", "demolish", "blueprint", "terrain"])
+            .required("path", Array)
+            .optional("shape", String)
+            .optional("zone", String)
+            .optional("edit", String)
+            .optional("name", String)
+            .optional("preview", Boolean),
+        (Method::Post, "/debug/step") => schema.one_of("action", &["pause", "resume", "step"]).optional("ticks", Integer),
+        (Method::Post, "/debug/restore") => schema.required("tick", Integer),
+        (Method::Post, "/debug/tracker") => schema.optional("keyframe_interval", Integer).optional("history_limit", Integer),
+        (Method::Post, "/api/v1/watchdog") => schema.optional("level", Integer),
+        (Method::Post, "/api/v1/tool") => schema.optional("tool", String),
+        (Method::Post, "/api/v1/demolish") => schema.required("x", Integer).required("y", Integer),
+        (Method::Post, "/api/v1/blueprints/copy") => schema
+            .required("name", String)
+            .required("x1", Integer)
+            .required("y1", Integer)
+            .required("x2", Integer)
+            .required("y2", Integer),
+        (Method::Post, "/api/v1/blueprints/stamp") => schema
+            .required("name", String)
+            .required("x", Integer)
+            .required("y", Integer),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+/// Rate limiting, authorization and body validation for the game server
+#[derive(Debug, Clone)]
+pub struct ApiMiddleware {
+    limiter: RateLimiter,
+    auth: Authenticator,
+}
+
+impl Default for ApiMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiMiddleware {
+    pub fn new() -> Self {
+        Self::with_auth(AuthConfig::default())
+    }
+
+    pub fn with_auth(auth: AuthConfig) -> Self {
+        Self { limiter: RateLimiter::new(RATE_LIMIT_BURST, RATE_LIMIT_PER_SECOND), auth: Authenticator::new(auth) }
+    }
+
+    /// Check one request from `client` (its remote address) carrying `token`, returning the request's role
+    /// Errors should be sent back as-is
+    pub fn check(&mut self, client: &str, token: Option<&str>, method: &Method, url: &str, body: &[u8], now: Instant) -> Result<Role, ApiError> {
+        self.limiter.check(client, now)?;
+        let role = self.auth.authorize(client, token, method, url)?;
+        if let Some(schema) = command_schema(method, url) {
+            schema.validate(body)?;
+        }
+        Ok(role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_per_client() {
+        let mut limiter = RateLimiter::new(3.0, 2.0);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check("10.0.0.1", start).is_ok());
+        }
+        let error = limiter.check("10.0.0.1", start).unwrap_err();
+        assert_eq!((error.status, error.code), (429, "rate_limited"));
+        assert_eq!(error.retry_after, Some(Duration::from_millis(500)));
+
+        // Other clients have their own bucket, and tokens come back over time
+        assert!(limiter.check("10.0.0.2", start).is_ok());
+        assert!(limiter.check("10.0.0.1", start + Duration::from_millis(500)).is_ok());
+        assert!(limiter.check("10.0.0.1", start + Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn test_command_bodies_are_validated() {
+        let mut middleware = ApiMiddleware::new();
+        let now = Instant::now();
+        let mut check = |method: Method, url: &str, body: &str| {
+            middleware.check("client", None, &method, url, body.as_bytes(), now).map(|_| ()).map_err(|error| error.code)
+        };
+
+        assert_eq!(check(Method::Post, "/move", r#"{"direction": "up"}"#), Ok(()));
+        assert_eq!(check(Method::Post, "/move", r#"{"direction": "sideways"}"#), Err("invalid_field"));
+        assert_eq!(check(Method::Post, "/move", "{direction"), Err("invalid_json"));
+        assert_eq!(check(Method::Post, "/api/v1/build", r#"{"kind": "house", "x": 4}"#), Err("missing_field"));
+        assert_eq!(check(Method::Post, "/api/v1/build", r#"{"kind": "house", "x": 4, "y": "6"}"#), Err("invalid_field"));
+        assert_eq!(check(Method::Post, "/api/v1/connect", "[]"), Err("invalid_body"));
+        assert_eq!(check(Method::Post, "/api/v1/tiles", r#"{"kind": "bridge", "x": 2, "y": 3}"#), Ok(()));
+        assert_eq!(check(Method::Post, "/api/v1/selection", r#"{"tool": "terrain", "edit": "raise", "path": [[1, 1]]}"#), Ok(()));
+        assert_eq!(check(Method::Put, "/api/v1/settings?client=c1", r#"{"ui_scale": 1.5}"#), Ok(()));
+        assert_eq!(check(Method::Get, "/api/v1/stats", ""), Ok(()));
+        assert_eq!(check(Method::Post, "/api/v1/policies/enact", r#"{"policy": "free_transit"}"#), Ok(()));
+        assert_eq!(check(Method::Post, "/api/v1/policies/repeal", r#"{"policy": 3}"#), Err("invalid_field"));
+        assert_eq!(check(Method::Post, "/api/v1/policies/repeal", "{}"), Err("missing_field"));
+
+        let error = ApiError::bad_request("missing_field", "Missing field 'y'");
+        assert_eq!(error.to_json()["code"], "missing_field");
+        assert_eq!(error.to_response().status_code().0, 400);
+    }
+}
@@ -0,0 +1,185 @@
+/// Application builder: the entry points shared by the command line and embedding applications
+use crate::action_log::ActionLog;
+use crate::agents::AgentComponent;
+use crate::game_rules::GameMode;
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::grid_game_systems::{GridGameWorld, GRID_HEIGHT, GRID_WIDTH};
+use crate::shutdown::ShutdownToken;
+use crate::simulation::{self, MonthlySample, Scenario};
+use crate::web_ecs_game::WebEcsGameDemo;
+use std::time::{Duration, Instant};
+
+/// Address the game server listens on unless configured otherwise
+pub const DEFAULT_ADDRESS: &str = "localhost:8085";
+
+/// Timing of a headless simulation run
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub ticks: u32,
+    pub entities: usize,
+    pub elapsed: Duration,
+}
+
+impl BenchReport {
+    /// Average wall time of one tick
+    pub fn per_tick(&self) -> Duration {
+        self.elapsed / self.ticks.max(1)
+    }
+}
+
+/// Where replaying an action log left the city
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    pub actions: usize,
+    pub ticks: u64,
+    pub state_hash: u64,
+}
+
+/// Configures and starts the game
+#[derive(Debug, Clone)]
+pub struct App {
+    address: String,
+    mode: GameMode,
+    scenario: Option<Scenario>,
+    shutdown: ShutdownToken,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_ADDRESS.to_string(),
+            mode: GameMode::City,
+            scenario: None,
+            shutdown: ShutdownToken::new(),
+        }
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Listen on a full address, e.g. "0.0.0.0:3000"
+    pub fn address(mut self, address: &str) -> Self {
+        self.address = address.to_string();
+        self
+    }
+
+    /// Listen on another port of the same host, localhost unless `address` chose another
+    pub fn port(mut self, port: u16) -> Self {
+        let host = self.address.rsplit_once(':').map_or(self.address.as_str(), |(host, _)| host);
+        self.address = format!("{}:{}", host, port);
+        self
+    }
+
+    /// Rules the served game plays by
+    pub fn mode(mut self, mode: GameMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Scenario the served game starts from
+    pub fn scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = Some(scenario);
+        self
+    }
+
+    /// Stop serving once this token is requested (signals are always honored once trapped)
+    pub fn shutdown(mut self, shutdown: ShutdownToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn get_address(&self) -> &str {
+        &self.address
+    }
+
+    /// Serve the web game until shutdown is requested or the server fails
+    pub fn serve(self) -> Result<(), String> {
+        let mut game = WebEcsGameDemo::new(&self.address).with_mode(self.mode);
+        if let Some(scenario) = &self.scenario {
+            game = game.with_scenario(scenario)?;
+        }
+        game.run_until(&self.shutdown)
+    }
+
+    /// Rebuild a city headless from its action log, on the default map in this app's mode
+    pub fn replay(self, log: &ActionLog) -> Result<ReplayReport, String> {
+        let mut game = GridGameWorld::new();
+        game.set_rules(self.mode.rules());
+        game.initialize_game();
+        game.replay_log(log)?;
+        Ok(ReplayReport { actions: log.records().len(), ticks: game.tick, state_hash: game.world.state_hash() })
+    }
+
+    /// Run the default map for a number of ticks without any clients
+    pub fn bench(self, ticks: u32) -> Result<BenchReport, String> {
+        let mut game = GridGameWorld::new();
+        game.initialize_game();
+        Self::run_ticks(&mut game, ticks)
+    }
+
+    /// Run the default map crowded with extra commuting citizens
+    pub fn stress(self, citizens: u32, ticks: u32) -> Result<BenchReport, String> {
+        let mut game = GridGameWorld::new();
+        game.initialize_game();
+        game.world.spawn_batch((0..citizens as i32).map(|i| {
+            let start = (i % GRID_WIDTH, GRID_HEIGHT - 1);
+            let destination = ((i * 7 + 3) % GRID_WIDTH, 0);
+            (
+                GridPositionComponent { x: start.0, y: start.1 },
+                AgentComponent::new("Citizen", vec![destination, start]),
+                RenderComponent { symbol: 'c', color: "cyan".to_string() },
+            )
+        })).map_err(|e| e.to_string())?;
+        Self::run_ticks(&mut game, ticks)
+    }
+
+    /// Run a scenario for whole in-game years at full speed, sampling the city once per month
+    pub fn simulate(self, scenario: &Scenario, years: u32) -> Result<Vec<MonthlySample>, String> {
+        let mut game = scenario.build_world()?;
+        simulation::simulate(&mut game, years)
+    }
+
+    fn run_ticks(game: &mut GridGameWorld, ticks: u32) -> Result<BenchReport, String> {
+        let start = Instant::now();
+        for _ in 0..ticks {
+            game.update()?;
+        }
+        Ok(BenchReport {
+            ticks,
+            entities: game.world.get_all_entities().len(),
+            elapsed: start.elapsed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_log::PlayerAction;
+    use crate::construction::BuildingKind;
+
+    #[test]
+    fn test_builder_and_headless_runs() {
+        let app = App::new().port(3000);
+        assert_eq!(app.get_address(), "localhost:3000");
+        assert_eq!(App::new().address("0.0.0.0:8085").port(3000).get_address(), "0.0.0.0:3000");
+        assert_eq!(App::new().get_address(), DEFAULT_ADDRESS);
+
+        let bench = App::new().bench(5).unwrap();
+        assert_eq!(bench.ticks, 5);
+
+        let stress = App::new().stress(50, 3).unwrap();
+        assert_eq!(stress.entities, bench.entities + 50);
+        assert!(stress.per_tick() <= stress.elapsed);
+
+        let mut log = ActionLog::new();
+        log.set_stable_id_seed(7);
+        log.push(3, PlayerAction::Build { kind: BuildingKind::House, x: 4, y: 6 });
+        let first = App::new().replay(&log).unwrap();
+        assert_eq!((first.actions, first.ticks), (1, 3));
+        assert_eq!(App::new().replay(&log).unwrap(), first);
+    }
+}
@@ -0,0 +1,158 @@
+/// Asset preloading: the manifest of every texture the game can draw, sent to browsers when they connect, and the
+/// loading progress they report back, which gates the state polls until a client has its textures
+use crate::autotile::TileKind;
+use crate::catalog::BuildingCatalog;
+use crate::core::math::projection::Projection;
+use crate::tools::Tool;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Directory textures are served from, one `<id>.png` per texture
+pub const TEXTURE_DIRECTORY: &str = "web/textures";
+
+/// A texture to preload and where to fetch it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AssetEntry {
+    pub id: String,
+    pub url: String,
+}
+
+/// Every texture the active catalog, tile atlases and tool ghosts refer to, in ID order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AssetManifest {
+    pub textures: Vec<AssetEntry>,
+}
+
+impl AssetManifest {
+    /// Manifest of a catalog's buildings and the tile atlases of a projection
+    pub fn build(catalog: &BuildingCatalog, projection: Projection) -> Self {
+        let mut ids: BTreeSet<String> = BTreeSet::new();
+        for definition in catalog.definitions() {
+            ids.insert(definition.sprite.clone());
+            ids.extend(Tool::Place(definition.kind).ghost_texture());
+        }
+        for kind in TileKind::all() {
+            ids.insert(projection.atlas_id(kind.atlas_id()));
+        }
+        ids.extend([Tool::Paste, Tool::Bulldoze].iter().filter_map(Tool::ghost_texture));
+        let textures = ids.into_iter()
+            .filter(|id| !id.is_empty())
+            .map(|id| AssetEntry { url: format!("/textures/{}.png", id), id })
+            .collect();
+        Self { textures }
+    }
+
+    /// Only the textures that have a file in `directory`; the others are drawn as placeholders without a fetch
+    pub fn present_in(mut self, directory: &Path) -> Self {
+        self.textures.retain(|entry| directory.join(format!("{}.png", entry.id)).is_file());
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+/// How far a client is through its manifest; textures that failed to load count as done, drawn as placeholders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreloadProgress {
+    pub loaded: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl PreloadProgress {
+    pub fn is_complete(&self) -> bool {
+        self.loaded + self.failed >= self.total
+    }
+
+    /// Share of the manifest done, from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        ((self.loaded + self.failed) as f32 / self.total as f32).min(1.0)
+    }
+}
+
+/// Preload progress of every connected client
+#[derive(Debug, Clone, Default)]
+pub struct PreloadTracker {
+    clients: BTreeMap<String, PreloadProgress>,
+}
+
+impl PreloadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for a client to load `total` textures; a client that already finished keeps its progress
+    pub fn start(&mut self, client_id: &str, total: usize) {
+        let progress = self.clients.entry(client_id.to_string()).or_default();
+        if !progress.is_complete() || progress.total != total {
+            *progress = PreloadProgress { loaded: 0, failed: 0, total };
+        }
+    }
+
+    /// Record a progress report; counts can only grow and never past the manifest
+    pub fn report(&mut self, client_id: &str, loaded: usize, failed: usize) -> Result<PreloadProgress, String> {
+        let progress = self.clients.get_mut(client_id).ok_or_else(|| format!("Client '{}' has no preload in progress", client_id))?;
+        progress.loaded = loaded.max(progress.loaded).min(progress.total);
+        progress.failed = failed.max(progress.failed).min(progress.total - progress.loaded);
+        Ok(*progress)
+    }
+
+    pub fn progress(&self, client_id: &str) -> Option<PreloadProgress> {
+        self.clients.get(client_id).copied()
+    }
+
+    /// Whether a client may be sent the game; clients that never connected aren't gated
+    pub fn is_ready(&self, client_id: &str) -> bool {
+        self.clients.get(client_id).is_none_or(PreloadProgress::is_complete)
+    }
+
+    pub fn forget(&mut self, client_id: &str) {
+        self.clients.remove(client_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_and_preload_gate() {
+        let manifest = AssetManifest::build(BuildingCatalog::builtin(), Projection::Isometric);
+        let ids: Vec<&str> = manifest.textures.iter().map(|entry| entry.id.as_str()).collect();
+        assert!(ids.contains(&"road_tiles_iso") && ids.contains(&"building_house") && ids.contains(&"selection"));
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(manifest.textures[0].url, format!("/textures/{}.png", ids[0]));
+
+        let directory = std::env::temp_dir().join(format!("citybuilder-textures-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("selection.png"), b"").unwrap();
+        let present = manifest.clone().present_in(&directory);
+        let _ = std::fs::remove_dir_all(&directory);
+        assert_eq!(present.textures.iter().map(|entry| entry.id.as_str()).collect::<Vec<_>>(), vec!["selection"]);
+
+        let mut tracker = PreloadTracker::new();
+        assert!(tracker.is_ready("unknown"));
+        tracker.start("c1", 4);
+        assert!(!tracker.is_ready("c1"));
+        assert_eq!(tracker.report("c1", 2, 0).unwrap().fraction(), 0.5);
+        // A late report doesn't lose progress
+        tracker.report("c1", 1, 0).unwrap();
+        assert_eq!(tracker.report("c1", 3, 5).unwrap(), PreloadProgress { loaded: 3, failed: 1, total: 4 });
+        assert!(tracker.is_ready("c1"));
+        assert!(tracker.report("c2", 1, 0).is_err());
+
+        // Reconnecting with the same manifest doesn't load it again
+        tracker.start("c1", 4);
+        assert!(tracker.is_ready("c1"));
+    }
+}
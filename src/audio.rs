@@ -0,0 +1,305 @@
+/// Audio mixing: every sound plays on a bus (music, effects or UI) with its own volume, positional sounds fade
+/// with their distance from the listener, and music ducks while a notification sounds
+/// The server does the mixing and clients play the commands it buffers, so the levels are the same in every tab
+use crate::events::{EventQueue, GameEvent};
+use crate::notifications::Notification;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Tiles from the listener within which positional sounds play at full volume
+pub const FULL_VOLUME_DISTANCE: f32 = 2.0;
+/// Tiles from the listener beyond which positional sounds are silent
+pub const HEARING_DISTANCE: f32 = 14.0;
+/// Music volume while ducked, relative to its bus volume
+pub const DUCK_GAIN: f32 = 0.35;
+/// Seconds music stays ducked after a notification
+pub const DUCK_SECONDS: f32 = 2.5;
+
+/// Mixer group with its own volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioBus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+/// Volumes from 0 to 1; each bus is scaled by the master volume
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub ui: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { master: 1.0, music: 0.6, sfx: 0.8, ui: 0.8 }
+    }
+}
+
+impl AudioSettings {
+    pub fn bus_volume(&self, bus: AudioBus) -> f32 {
+        let volume = match bus {
+            AudioBus::Music => self.music,
+            AudioBus::Sfx => self.sfx,
+            AudioBus::Ui => self.ui,
+        };
+        self.master * volume
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let volumes = [("Master", self.master), ("Music", self.music), ("Effects", self.sfx), ("UI", self.ui)];
+        match volumes.iter().find(|(_, volume)| !(0.0..=1.0).contains(volume)) {
+            Some((name, volume)) => Err(format!("{} volume {} is outside 0 to 1", name, volume)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Where a sound comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoundSource {
+    /// Heard the same everywhere, like music and UI clicks
+    Global,
+    /// A point on the map, in tiles
+    At { x: f32, y: f32 },
+}
+
+/// Volume of a positional sound at a distance in tiles: full up close, falling linearly to silence
+pub fn attenuation(distance: f32) -> f32 {
+    ((HEARING_DISTANCE - distance) / (HEARING_DISTANCE - FULL_VOLUME_DISTANCE)).clamp(0.0, 1.0)
+}
+
+/// What a client should do with its audio; gains are final, with bus, distance and ducking applied
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum AudioCommand {
+    Play { voice: u64, sound: String, gain: f32, looping: bool },
+    /// Ramp a voice to a gain over some seconds; 0 changes it at once
+    SetGain { voice: u64, gain: f32, fade_seconds: f32 },
+    Stop { voice: u64 },
+}
+
+/// A command with a sequence id for client polling
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BufferedAudioCommand {
+    pub id: u64,
+    #[serde(flatten)]
+    pub command: AudioCommand,
+}
+
+// A looping sound still playing; its gain is remixed when the volumes, listener or ducking change
+struct Voice {
+    bus: AudioBus,
+    source: SoundSource,
+    gain: f32,
+    mixed_gain: f32,
+}
+
+/// Mixes sounds into commands for clients, who poll them like notifications
+pub struct AudioMixer {
+    settings: AudioSettings,
+    listener: (f32, f32),
+    duck_remaining: f32,
+    voices: BTreeMap<u64, Voice>,
+    next_voice: u64,
+    commands: VecDeque<BufferedAudioCommand>,
+    capacity: usize,
+    next_id: u64,
+}
+
+impl AudioMixer {
+    /// Create a mixer keeping at most `capacity` commands for clients
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            settings: AudioSettings::default(),
+            listener: (0.0, 0.0),
+            duck_remaining: 0.0,
+            voices: BTreeMap::new(),
+            next_voice: 1,
+            commands: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_id: 1,
+        }
+    }
+
+    pub fn settings(&self) -> &AudioSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: AudioSettings) {
+        self.settings = settings;
+        self.remix();
+    }
+
+    /// Move the point positional sounds are heard from, in tiles
+    pub fn set_listener(&mut self, x: f32, y: f32) {
+        if self.listener != (x, y) {
+            self.listener = (x, y);
+            self.remix();
+        }
+    }
+
+    pub fn is_ducked(&self) -> bool {
+        self.duck_remaining > 0.0
+    }
+
+    /// Lower the music for a while, extending a duck already in progress
+    pub fn duck(&mut self, seconds: f32) {
+        let was_ducked = self.is_ducked();
+        self.duck_remaining = self.duck_remaining.max(seconds);
+        if !was_ducked {
+            self.remix();
+        }
+    }
+
+    /// Start a sound and return its voice; one-shot sounds are mixed once, looping ones until stopped
+    pub fn play(&mut self, sound: &str, bus: AudioBus, source: SoundSource, gain: f32, looping: bool) -> u64 {
+        let voice = self.next_voice;
+        self.next_voice += 1;
+        let mixed_gain = self.mix(bus, source, gain);
+        if looping {
+            self.voices.insert(voice, Voice { bus, source, gain, mixed_gain });
+        }
+        self.push(AudioCommand::Play { voice, sound: sound.to_string(), gain: mixed_gain, looping });
+        voice
+    }
+
+    /// Ramp a looping voice to a new gain of its own, for crossfades and ambience following the listener
+    pub fn fade(&mut self, voice: u64, gain: f32, seconds: f32) {
+        let Some(playing) = self.voices.get(&voice) else {
+            return;
+        };
+        let mixed_gain = self.mix(playing.bus, playing.source, gain);
+        if let Some(playing) = self.voices.get_mut(&voice) {
+            playing.gain = gain;
+            playing.mixed_gain = mixed_gain;
+        }
+        self.push(AudioCommand::SetGain { voice, gain: mixed_gain, fade_seconds: seconds });
+    }
+
+    pub fn stop(&mut self, voice: u64) {
+        if self.voices.remove(&voice).is_some() {
+            self.push(AudioCommand::Stop { voice });
+        }
+    }
+
+    /// Advance ducking by a frame
+    pub fn update(&mut self, delta_seconds: f32) {
+        if self.is_ducked() {
+            self.duck_remaining = (self.duck_remaining - delta_seconds).max(0.0);
+            if !self.is_ducked() {
+                self.remix();
+            }
+        }
+    }
+
+    /// Final gain of a sound: its own gain scaled by its bus, its distance and any ducking
+    fn mix(&self, bus: AudioBus, source: SoundSource, gain: f32) -> f32 {
+        let distance = match source {
+            SoundSource::Global => 1.0,
+            SoundSource::At { x, y } => attenuation((x - self.listener.0).hypot(y - self.listener.1)),
+        };
+        let ducking = if bus == AudioBus::Music && self.is_ducked() { DUCK_GAIN } else { 1.0 };
+        gain * self.settings.bus_volume(bus) * distance * ducking
+    }
+
+    fn remix(&mut self) {
+        let changed: Vec<(u64, f32)> = self.voices.iter()
+            .map(|(voice, playing)| (*voice, self.mix(playing.bus, playing.source, playing.gain)))
+            .filter(|(voice, gain)| (self.voices[voice].mixed_gain - gain).abs() > 0.001)
+            .collect();
+        for (voice, gain) in changed {
+            if let Some(playing) = self.voices.get_mut(&voice) {
+                playing.mixed_gain = gain;
+            }
+            self.push(AudioCommand::SetGain { voice, gain, fade_seconds: 0.0 });
+        }
+    }
+
+    fn push(&mut self, command: AudioCommand) {
+        if self.commands.len() >= self.capacity {
+            self.commands.pop_front();
+        }
+        self.commands.push_back(BufferedAudioCommand { id: self.next_id, command });
+        self.next_id += 1;
+    }
+
+    /// Get all commands with an id greater than `last_seen_id`
+    pub fn since(&self, last_seen_id: u64) -> Vec<BufferedAudioCommand> {
+        self.commands.iter().filter(|entry| entry.id > last_seen_id).cloned().collect()
+    }
+}
+
+impl Default for AudioMixer {
+    fn default() -> Self {
+        Self::new(200)
+    }
+}
+
+/// Plays the sounds of a frame's gameplay events and notifications
+pub struct AudioSystem;
+
+impl AudioSystem {
+    /// Read pending events and notifications without consuming them
+    pub fn update(mixer: &mut AudioMixer, events: &EventQueue<GameEvent>, notifications: &EventQueue<Notification>, delta_seconds: f32) {
+        mixer.update(delta_seconds);
+        for event in events.iter() {
+            let (sound, x, y) = match event {
+                GameEvent::BuildingPlaced { x, y, .. } => ("build", *x, *y),
+                GameEvent::BuildingDemolished { x, y, .. } => ("demolish", *x, *y),
+                _ => continue,
+            };
+            mixer.play(sound, AudioBus::Sfx, SoundSource::At { x: x as f32, y: y as f32 }, 1.0, false);
+        }
+        if !notifications.is_empty() {
+            mixer.play("notification", AudioBus::Ui, SoundSource::Global, 1.0, false);
+            mixer.duck(DUCK_SECONDS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gain_of(command: &AudioCommand) -> f32 {
+        match command {
+            AudioCommand::Play { gain, .. } | AudioCommand::SetGain { gain, .. } => *gain,
+            AudioCommand::Stop { .. } => panic!("Stop has no gain"),
+        }
+    }
+
+    #[test]
+    fn test_buses_distance_and_ducking() {
+        let mut mixer = AudioMixer::default();
+        mixer.set_settings(AudioSettings { master: 0.5, music: 0.8, sfx: 1.0, ui: 1.0 });
+        let music = mixer.play("theme", AudioBus::Music, SoundSource::Global, 1.0, true);
+        mixer.play("build", AudioBus::Sfx, SoundSource::At { x: 1.0, y: 1.0 }, 1.0, false);
+        mixer.play("build", AudioBus::Sfx, SoundSource::At { x: 8.0, y: 0.0 }, 1.0, false);
+        mixer.play("build", AudioBus::Sfx, SoundSource::At { x: 30.0, y: 0.0 }, 1.0, false);
+        let gains: Vec<f32> = mixer.since(0).iter().map(|entry| gain_of(&entry.command)).collect();
+        assert_eq!(gains, vec![0.4, 0.5, 0.25, 0.0]);
+
+        // A notification ducks the music until its time is up
+        let mut events = EventQueue::new();
+        events.push(GameEvent::BuildingPlaced { x: 0, y: 0, kind: "house".to_string() });
+        let mut notifications = EventQueue::new();
+        notifications.push(Notification::info("Road built"));
+        let seen = mixer.since(0).last().unwrap().id;
+        AudioSystem::update(&mut mixer, &events, &notifications, 0.1);
+        let commands: Vec<AudioCommand> = mixer.since(seen).into_iter().map(|entry| entry.command).collect();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[2], AudioCommand::SetGain { voice: music, gain: 0.4 * DUCK_GAIN, fade_seconds: 0.0 });
+
+        let seen = mixer.since(0).last().unwrap().id;
+        mixer.update(DUCK_SECONDS);
+        assert!(!mixer.is_ducked());
+        assert_eq!(mixer.since(seen)[0].command, AudioCommand::SetGain { voice: music, gain: 0.4, fade_seconds: 0.0 });
+        mixer.stop(music);
+        assert_eq!(mixer.since(0).last().unwrap().command, AudioCommand::Stop { voice: music });
+        assert!(AudioSettings { ui: 1.5, ..AudioSettings::default() }.validate().is_err());
+    }
+}
@@ -0,0 +1,178 @@
+/// Token authentication for the game server: each request gets a role from the token it carries, and every
+/// endpoint needs a role, so a LAN client of a hosted game can play without reaching the debug and admin endpoints
+/// Tokens come in an `Authorization: Bearer <token>` header or the `citybuilder_token` cookie the pages set
+use crate::api_middleware::ApiError;
+use crate::input::{InputEvent, Key};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use tiny_http::Method;
+
+/// Cookie the client pages store their token in, so page loads and images carry it too
+pub const TOKEN_COOKIE: &str = "citybuilder_token";
+/// Posts that only keep a session alive, which observers send as well
+const SESSION_ENDPOINTS: &[&str] = &[
+    "/api/v1/connect",
+    "/api/v1/heartbeat",
+    "/api/v1/disconnect",
+    "/api/v1/preload",
+    "/api/v1/events/subscribe",
+];
+/// Keys that open the developer console or drive the frame debugger
+const ADMIN_KEYS: &[Key] = &[Key::Backquote, Key::F8, Key::F10];
+
+/// What a client may do; each role may do everything the ones before it may
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Role {
+    /// Watches the game: reads state but sends no commands
+    Observer,
+    /// Plays the game
+    Player,
+    /// Also uses the debug endpoints, the console and the watchdog override
+    Admin,
+}
+
+impl Role {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Observer => "observer",
+            Role::Player => "player",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// Who gets which role, the `auth` section of `GameConfig`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Role of requests without a token
+    pub anonymous: Role,
+    /// Requests from this machine are admins without a token, so local development needs no setup
+    /// Off by default: behind a proxy on the same machine every client would look local
+    pub trust_localhost: bool,
+    /// Role of each token
+    pub tokens: BTreeMap<String, Role>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self { anonymous: Role::Player, trust_localhost: false, tokens: BTreeMap::new() }
+    }
+}
+
+/// Role an endpoint needs
+pub fn required_role(method: &Method, url: &str) -> Role {
+    let path = url.split('?').next().unwrap_or(url);
+    // The inspector dumps any entity's components, which is debugging information like the console
+    if path.starts_with("/debug/") || path.starts_with("/api/v1/console") || path.starts_with("/api/v1/inspect") || path == "/metrics" {
+        return Role::Admin;
+    }
+    match method {
+        Method::Get | Method::Head | Method::Options => Role::Observer,
+        Method::Post if path == "/api/v1/watchdog" => Role::Admin,
+        Method::Post if SESSION_ENDPOINTS.contains(&path) => Role::Observer,
+        _ => Role::Player,
+    }
+}
+
+/// Whether an input event reaches the console or the frame debugger, which only admins may use
+/// While the console is open it takes every key and all typed text
+pub fn is_admin_input(event: &InputEvent, console_open: bool) -> bool {
+    match event {
+        InputEvent::KeyPress { key } | InputEvent::KeyRelease { key } => console_open || ADMIN_KEYS.contains(key),
+        InputEvent::TextInput { .. } => console_open,
+        _ => false,
+    }
+}
+
+/// The token of a request, from its `Authorization` header or else its cookie
+pub fn request_token(authorization: Option<&str>, cookie: Option<&str>) -> Option<String> {
+    if let Some(token) = authorization.and_then(|header| header.strip_prefix("Bearer ")) {
+        return Some(token.trim().to_string());
+    }
+    cookie?.split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == TOKEN_COOKIE)
+        .map(|(_, token)| token.to_string())
+}
+
+/// Gives requests their role and turns away those without the role an endpoint needs
+#[derive(Debug, Clone, Default)]
+pub struct Authenticator {
+    config: AuthConfig,
+}
+
+impl Authenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        Self { config }
+    }
+
+    /// Role of a request from `client` (its IP address); a token that isn't configured is refused outright
+    pub fn role(&self, client: &str, token: Option<&str>) -> Result<Role, ApiError> {
+        let local = self.config.trust_localhost && client.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback());
+        let role = match token.filter(|token| !token.is_empty()) {
+            Some(token) => *self.config.tokens.get(token).ok_or_else(ApiError::unauthorized)?,
+            None => self.config.anonymous,
+        };
+        Ok(if local { Role::Admin } else { role })
+    }
+
+    /// Check a request against the role its endpoint needs, returning the request's role
+    pub fn authorize(&self, client: &str, token: Option<&str>, method: &Method, url: &str) -> Result<Role, ApiError> {
+        let role = self.role(client, token)?;
+        let required = required_role(method, url);
+        if role < required {
+            return Err(ApiError::forbidden(required));
+        }
+        Ok(role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_gate_endpoints() {
+        let config = AuthConfig {
+            trust_localhost: true,
+            tokens: BTreeMap::from([("s3cret".to_string(), Role::Admin), ("watch".to_string(), Role::Observer)]),
+            ..AuthConfig::default()
+        };
+        let auth = Authenticator::new(config);
+        let lan = "192.168.1.20";
+
+        assert_eq!(auth.authorize(lan, None, &Method::Post, "/api/v1/build"), Ok(Role::Player));
+        let error = auth.authorize(lan, None, &Method::Post, "/debug/restore").unwrap_err();
+        assert_eq!((error.status, error.code), (403, "forbidden"));
+        assert!(auth.authorize(lan, None, &Method::Get, "/api/v1/console").is_err());
+        assert_eq!(auth.authorize(lan, Some("s3cret"), &Method::Post, "/debug/step"), Ok(Role::Admin));
+        assert_eq!(auth.authorize(lan, Some("wrong"), &Method::Get, "/").unwrap_err().status, 401);
+
+        // Observers keep their session alive but can't play
+        assert!(auth.authorize(lan, Some("watch"), &Method::Post, "/api/v1/heartbeat").is_ok());
+        assert!(auth.authorize(lan, Some("watch"), &Method::Post, "/api/v1/build").is_err());
+        assert!(auth.authorize(lan, Some("watch"), &Method::Post, "/api/v1/capture").is_err());
+
+        // The entity inspector is an admin tool, whatever the query
+        for token in [None, Some("watch")] {
+            for url in ["/api/v1/inspect", "/api/v1/inspect?x=3&y=4"] {
+                let error = auth.authorize(lan, token, &Method::Get, url).unwrap_err();
+                assert_eq!((error.status, error.code), (403, "forbidden"), "{:?} {}", token, url);
+            }
+        }
+        assert_eq!(auth.authorize(lan, Some("s3cret"), &Method::Get, "/api/v1/inspect?x=3&y=4"), Ok(Role::Admin));
+        assert_eq!(auth.authorize("127.0.0.1", None, &Method::Get, "/debug/memory"), Ok(Role::Admin));
+
+        assert_eq!(request_token(Some("Bearer s3cret"), None).as_deref(), Some("s3cret"));
+        assert_eq!(request_token(None, Some("theme=dark; citybuilder_token=watch")).as_deref(), Some("watch"));
+        assert!(is_admin_input(&InputEvent::KeyPress { key: Key::Backquote }, false));
+        assert!(!is_admin_input(&InputEvent::KeyPress { key: Key::W }, false));
+        // An open console would take a player's typing and Enter as a command
+        assert!(is_admin_input(&InputEvent::TextInput { text: "quit".to_string() }, true));
+        assert!(is_admin_input(&InputEvent::KeyPress { key: Key::Enter }, true));
+        assert!(!is_admin_input(&InputEvent::TextInput { text: "quit".to_string() }, false));
+        assert!(!AuthConfig::default().trust_localhost);
+    }
+}
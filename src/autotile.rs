@@ -0,0 +1,399 @@
+/// Autotiling for roads, bridges, tunnels and walls: each tile picks its sprite variant (end, corner, T-junction,
+/// crossroads) from a bitmask of connecting neighbors, and placing or removing a tile updates its neighbors
+use crate::core::math::{Transform2d, Vector2d};
+use crate::core::math::projection::Projection;
+use crate::ecs::{Component, Entity, InvalidComponent, World};
+use crate::grid_game_components::{GridPositionComponent, ObstacleComponent, RenderComponent};
+use crate::rendering::RenderCommand;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashMap;
+
+/// Width and height of a tilemap chunk in tiles
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Neighbor bits of a tile mask
+pub const NORTH: u8 = 1;
+pub const EAST: u8 = 2;
+pub const SOUTH: u8 = 4;
+pub const WEST: u8 = 8;
+
+/// Tile kinds that connect to their neighbors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TileKind {
+    Road,
+    Wall,
+    /// Road carried over water
+    Bridge,
+    /// Road bored through raised ground
+    Tunnel,
+}
+
+impl TileKind {
+    /// All kinds in a stable order
+    pub fn all() -> [TileKind; 4] {
+        [TileKind::Road, TileKind::Wall, TileKind::Bridge, TileKind::Tunnel]
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "road" => Some(TileKind::Road),
+            "wall" => Some(TileKind::Wall),
+            "bridge" => Some(TileKind::Bridge),
+            "tunnel" => Some(TileKind::Tunnel),
+            _ => None,
+        }
+    }
+
+    /// Money paid when the tile is placed
+    pub fn cost(&self) -> i64 {
+        match self {
+            TileKind::Road => 10,
+            TileKind::Wall => 5,
+            TileKind::Bridge => 60,
+            TileKind::Tunnel => 90,
+        }
+    }
+
+    /// Whether vehicles drive on the tile; roads, bridges and tunnels join into one network
+    pub fn is_road(&self) -> bool {
+        matches!(self, TileKind::Road | TileKind::Bridge | TileKind::Tunnel)
+    }
+
+    /// Whether a tile of this kind joins up with a neighboring tile of `other`
+    pub fn connects_to(&self, other: TileKind) -> bool {
+        *self == other || (self.is_road() && other.is_road())
+    }
+
+    /// Texture atlas holding the 16 variants, indexed by neighbor mask
+    pub fn atlas_id(&self) -> &'static str {
+        match self {
+            TileKind::Road => "road_tiles",
+            TileKind::Wall => "wall_tiles",
+            TileKind::Bridge => "bridge_tiles",
+            TileKind::Tunnel => "tunnel_tiles",
+        }
+    }
+
+    fn render(&self) -> RenderComponent {
+        match self {
+            TileKind::Road => RenderComponent { symbol: '=', color: "gray".to_string() },
+            TileKind::Wall => RenderComponent { symbol: '#', color: "brown".to_string() },
+            TileKind::Bridge => RenderComponent { symbol: 'b', color: "gray".to_string() },
+            TileKind::Tunnel => RenderComponent { symbol: 'n', color: "gray".to_string() },
+        }
+    }
+}
+
+/// Shape of a tile variant before rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileShape {
+    Isolated,
+    /// Connected to the north only
+    End,
+    /// Connected north and south
+    Straight,
+    /// Connected north and east
+    Corner,
+    /// Connected north, east and south
+    TJunction,
+    Crossroads,
+}
+
+/// Sprite variant of a tile: its shape turned clockwise by `rotation` quarter turns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileVariant {
+    pub mask: u8,
+    pub shape: TileShape,
+    pub rotation: u8,
+}
+
+// Shape and quarter turns for every neighbor mask (N = 1, E = 2, S = 4, W = 8)
+const VARIANTS: [(TileShape, u8); 16] = [
+    (TileShape::Isolated, 0),
+    (TileShape::End, 0),
+    (TileShape::End, 1),
+    (TileShape::Corner, 0),
+    (TileShape::End, 2),
+    (TileShape::Straight, 0),
+    (TileShape::Corner, 1),
+    (TileShape::TJunction, 0),
+    (TileShape::End, 3),
+    (TileShape::Corner, 3),
+    (TileShape::Straight, 1),
+    (TileShape::TJunction, 3),
+    (TileShape::Corner, 2),
+    (TileShape::TJunction, 2),
+    (TileShape::TJunction, 1),
+    (TileShape::Crossroads, 0),
+];
+
+impl TileVariant {
+    pub fn from_mask(mask: u8) -> Self {
+        let (shape, rotation) = VARIANTS[(mask & 0xF) as usize];
+        Self { mask: mask & 0xF, shape, rotation }
+    }
+
+    /// Index into the tile atlas, which holds one pre-rotated tile per mask
+    pub fn atlas_index(&self) -> u32 {
+        self.mask as u32
+    }
+}
+
+/// Component of an autotiled road or wall tile, kept in step with its neighbors
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutotileComponent {
+    pub kind: TileKind,
+    pub variant: TileVariant,
+}
+
+impl Component for AutotileComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+type ChunkCells = Vec<Option<(TileKind, Entity)>>;
+
+/// Autotiled tiles by position, stored in chunks with a cached tilemap layer per chunk and kind
+#[derive(Debug, Clone, Default)]
+pub struct AutotileMap {
+    chunks: HashMap<(i32, i32), ChunkCells>,
+    layers: HashMap<(i32, i32), Vec<RenderCommand>>,
+    cell_size: f32,
+}
+
+impl AutotileMap {
+    pub fn new(cell_size: f32) -> Self {
+        Self { cell_size, ..Self::default() }
+    }
+
+    /// Kind and entity of the tile at a position
+    pub fn get(&self, x: i32, y: i32) -> Option<(TileKind, Entity)> {
+        let (chunk, index) = Self::locate(x, y);
+        self.chunks.get(&chunk).and_then(|cells| cells[index])
+    }
+
+    /// Positions of the four orthogonal neighbors, in mask bit order
+    pub fn neighbors(x: i32, y: i32) -> [(u8, i32, i32); 4] {
+        [(NORTH, x, y - 1), (EAST, x + 1, y), (SOUTH, x, y + 1), (WEST, x - 1, y)]
+    }
+
+    /// Bitmask of the neighbors holding a tile that connects to `(x, y)`
+    pub fn mask(&self, x: i32, y: i32) -> u8 {
+        let Some((kind, _)) = self.get(x, y) else {
+            return 0;
+        };
+        Self::neighbors(x, y).iter()
+            .filter(|(_, nx, ny)| self.get(*nx, *ny).is_some_and(|(neighbor, _)| kind.connects_to(neighbor)))
+            .fold(0, |mask, (bit, _, _)| mask | bit)
+    }
+
+    /// Put or clear a tile; returns the tiles whose variant may have changed (it and its neighbors)
+    pub fn set(&mut self, x: i32, y: i32, tile: Option<(TileKind, Entity)>) -> Vec<(i32, i32)> {
+        let (chunk, index) = Self::locate(x, y);
+        let cells = self.chunks.entry(chunk).or_insert_with(|| vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize]);
+        cells[index] = tile;
+        if cells.iter().all(Option::is_none) {
+            self.chunks.remove(&chunk);
+        }
+
+        let mut affected = vec![(x, y)];
+        affected.extend(Self::neighbors(x, y).iter().map(|(_, nx, ny)| (*nx, *ny)));
+        affected.retain(|(ax, ay)| self.get(*ax, *ay).is_some());
+
+        // Only the chunks around the change are rebuilt
+        let mut chunks: Vec<(i32, i32)> = std::iter::once(chunk)
+            .chain(affected.iter().map(|(ax, ay)| Self::locate(*ax, *ay).0))
+            .collect();
+        chunks.sort();
+        chunks.dedup();
+        for chunk in chunks {
+            self.rebuild_layers(chunk);
+        }
+        affected
+    }
+
+    /// Tilemap layers of every chunk, in chunk order
+    pub fn layers(&self) -> Vec<RenderCommand> {
+        let mut chunks: Vec<&(i32, i32)> = self.layers.keys().collect();
+        chunks.sort();
+        chunks.into_iter().flat_map(|chunk| self.layers[chunk].iter().cloned()).collect()
+    }
+
+    fn locate(x: i32, y: i32) -> ((i32, i32), usize) {
+        let chunk = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let index = y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+        (chunk, index as usize)
+    }
+
+    fn rebuild_layers(&mut self, chunk: (i32, i32)) {
+        let Some(cells) = self.chunks.get(&chunk) else {
+            self.layers.remove(&chunk);
+            return;
+        };
+        let origin = (chunk.0 * CHUNK_SIZE, chunk.1 * CHUNK_SIZE);
+        let layers = TileKind::all().into_iter()
+            .filter(|kind| cells.iter().any(|cell| cell.is_some_and(|(tile, _)| tile == *kind)))
+            .map(|kind| {
+                let tiles = (0..CHUNK_SIZE * CHUNK_SIZE).map(|index| {
+                    let (x, y) = (origin.0 + index % CHUNK_SIZE, origin.1 + index / CHUNK_SIZE);
+                    match cells[index as usize] {
+                        Some((tile, _)) if tile == kind => Some(TileVariant::from_mask(self.mask(x, y)).atlas_index()),
+                        _ => None,
+                    }
+                }).collect();
+                RenderCommand::DrawTilemapLayer {
+                    atlas_id: kind.atlas_id().to_string(),
+                    atlas_columns: 4,
+                    tile_size: Vector2d::new(self.cell_size, self.cell_size),
+                    columns: CHUNK_SIZE as u32,
+                    tiles,
+                    transform: Transform2d::translation(Vector2d::new(
+                        origin.0 as f32 * self.cell_size,
+                        origin.1 as f32 * self.cell_size,
+                    )),
+                    projection: Projection::TopDown,
+                    z_order: 0,
+                }
+            })
+            .collect();
+        self.layers.insert(chunk, layers);
+    }
+}
+
+/// Places and removes autotiled entities, keeping every tile's variant matched to its neighbors
+pub struct AutotileSystem;
+
+impl AutotileSystem {
+    /// Spawn a tile entity at a position; walls also block movement
+    pub fn place(world: &mut World, map: &mut AutotileMap, kind: TileKind, x: i32, y: i32) -> Result<Entity, InvalidComponent> {
+        let entity = if kind == TileKind::Wall {
+            world.spawn((
+                GridPositionComponent { x, y },
+                AutotileComponent { kind, variant: TileVariant::from_mask(0) },
+                kind.render(),
+                ObstacleComponent { block_movement: true },
+            ))?
+        } else {
+            world.spawn((
+                GridPositionComponent { x, y },
+                AutotileComponent { kind, variant: TileVariant::from_mask(0) },
+                kind.render(),
+            ))?
+        };
+        let affected = map.set(x, y, Some((kind, entity)));
+        Self::refresh(world, map, &affected);
+        Ok(entity)
+    }
+
+    /// Forget tiles whose entities were destroyed, e.g. by demolition, and reconnect their neighbors
+    pub fn update(world: &mut World, map: &mut AutotileMap) {
+        let removed: Vec<(i32, i32)> = map.chunks.iter()
+            .flat_map(|(chunk, cells)| cells.iter().enumerate().filter_map(move |(index, cell)| {
+                let (_, entity) = (*cell)?;
+                let x = chunk.0 * CHUNK_SIZE + index as i32 % CHUNK_SIZE;
+                let y = chunk.1 * CHUNK_SIZE + index as i32 / CHUNK_SIZE;
+                Some((x, y, entity))
+            }))
+            .filter(|(_, _, entity)| !world.get_all_entities().contains(entity))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        for (x, y) in removed {
+            let affected = map.set(x, y, None);
+            Self::refresh(world, map, &affected);
+        }
+    }
+
+    fn refresh(world: &mut World, map: &AutotileMap, tiles: &[(i32, i32)]) {
+        for &(x, y) in tiles {
+            if let Some((_, entity)) = map.get(x, y) {
+                if let Some(mut tile) = world.get_component_mut::<AutotileComponent>(entity) {
+                    tile.variant = TileVariant::from_mask(map.mask(x, y));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(world: &World, map: &AutotileMap, x: i32, y: i32) -> (TileShape, u8) {
+        let entity = map.get(x, y).unwrap().1;
+        let tile = world.get_component::<AutotileComponent>(entity).unwrap();
+        (tile.variant.shape, tile.variant.rotation)
+    }
+
+    #[test]
+    fn test_variants_follow_neighbors() {
+        let mut world = World::new();
+        let mut map = AutotileMap::new(32.0);
+
+        AutotileSystem::place(&mut world, &mut map, TileKind::Road, 5, 5).unwrap();
+        assert_eq!(variant(&world, &map, 5, 5), (TileShape::Isolated, 0));
+
+        // A road east turns both into ends facing each other
+        AutotileSystem::place(&mut world, &mut map, TileKind::Road, 6, 5).unwrap();
+        assert_eq!(variant(&world, &map, 5, 5), (TileShape::End, 1));
+        assert_eq!(variant(&world, &map, 6, 5), (TileShape::End, 3));
+
+        // Roads north, south and west of (5, 5) make a crossroads
+        for (x, y) in [(5, 4), (5, 6), (4, 5)] {
+            AutotileSystem::place(&mut world, &mut map, TileKind::Road, x, y).unwrap();
+        }
+        assert_eq!(variant(&world, &map, 5, 5), (TileShape::Crossroads, 0));
+
+        // Walls don't connect to roads, bridges do
+        AutotileSystem::place(&mut world, &mut map, TileKind::Wall, 6, 6).unwrap();
+        assert_eq!(variant(&world, &map, 6, 6), (TileShape::Isolated, 0));
+        AutotileSystem::place(&mut world, &mut map, TileKind::Bridge, 7, 5).unwrap();
+        assert_eq!(variant(&world, &map, 7, 5), (TileShape::End, 3));
+
+        // Removing the west road turns the crossroads into a T-junction
+        let west = map.get(4, 5).unwrap().1;
+        world.destroy_entity(west);
+        AutotileSystem::update(&mut world, &mut map);
+        assert_eq!(map.get(4, 5), None);
+        assert_eq!(variant(&world, &map, 5, 5), (TileShape::TJunction, 0));
+    }
+
+    #[test]
+    fn test_lookup_table_and_chunks() {
+        // Every mask maps back to itself when its base shape is rotated
+        for mask in 0..16u8 {
+            let variant = TileVariant::from_mask(mask);
+            let base = match variant.shape {
+                TileShape::Isolated => 0,
+                TileShape::End => NORTH,
+                TileShape::Straight => NORTH | SOUTH,
+                TileShape::Corner => NORTH | EAST,
+                TileShape::TJunction => NORTH | EAST | SOUTH,
+                TileShape::Crossroads => 0xF,
+            };
+            let rotated = (0..variant.rotation).fold(base, |bits, _| ((bits << 1) | (bits >> 3)) & 0xF);
+            assert_eq!(rotated, mask, "mask {}", mask);
+        }
+
+        // Tiles on both sides of a chunk border connect, and each chunk gets its own layer
+        let mut world = World::new();
+        let mut map = AutotileMap::new(32.0);
+        AutotileSystem::place(&mut world, &mut map, TileKind::Road, CHUNK_SIZE - 1, 0).unwrap();
+        AutotileSystem::place(&mut world, &mut map, TileKind::Road, CHUNK_SIZE, 0).unwrap();
+        assert_eq!(map.mask(CHUNK_SIZE - 1, 0), EAST);
+        assert_eq!(map.layers().len(), 2);
+        match &map.layers()[0] {
+            RenderCommand::DrawTilemapLayer { tiles, .. } => assert_eq!(tiles[(CHUNK_SIZE - 1) as usize], Some(EAST as u32)),
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+/// Local avoidance for agents sharing tiles: each tick, a citizen or van only steps onto a tile no other traveller
+/// stands on, so they don't pile up on screen, and waits otherwise
+/// A traveller that has waited `AVOIDANCE_PATIENCE` ticks goes anyway, so head-on meetings at junctions can't
+/// lock both sides forever
+use crate::agents::AgentComponent;
+use crate::ecs::{Entity, World};
+use crate::grid_game_components::GridPositionComponent;
+use crate::logistics::DeliveryComponent;
+use serde::Serialize;
+use std::any::TypeId;
+use std::collections::HashMap;
+
+/// Ticks a traveller waits for an occupied tile before moving onto it regardless
+pub const AVOIDANCE_PATIENCE: u32 = 3;
+
+/// What avoidance did in the last tick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AvoidanceStats {
+    /// Moves granted onto a free tile
+    pub moved: u32,
+    /// Moves held back because the tile was taken
+    pub waited: u32,
+    /// Moves onto a taken tile after running out of patience
+    pub forced: u32,
+}
+
+/// Tile reservations of the travellers, rebuilt every tick by `begin`
+#[derive(Debug, Clone)]
+pub struct LocalAvoidance {
+    /// Off, every move is granted, for comparing performance with and without avoidance
+    pub enabled: bool,
+    occupants: HashMap<(i32, i32), u32>,
+    waiting: HashMap<Entity, u32>,
+    stats: AvoidanceStats,
+}
+
+impl LocalAvoidance {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, occupants: HashMap::new(), waiting: HashMap::new(), stats: AvoidanceStats::default() }
+    }
+
+    /// Start a tick: every citizen and van holds the tile it stands on
+    pub fn begin(&mut self, world: &World) {
+        self.occupants.clear();
+        self.stats = AvoidanceStats::default();
+        if !self.enabled {
+            self.waiting.clear();
+            return;
+        }
+        let mut travellers = world.entities_with_components(&[TypeId::of::<AgentComponent>(), TypeId::of::<GridPositionComponent>()]);
+        travellers.extend(world.entities_with_components(&[TypeId::of::<DeliveryComponent>(), TypeId::of::<GridPositionComponent>()]));
+        for entity in &travellers {
+            if let Some(pos) = world.get_component::<GridPositionComponent>(*entity) {
+                *self.occupants.entry((pos.x, pos.y)).or_default() += 1;
+            }
+        }
+        // Forget the patience of travellers that are gone
+        self.waiting.retain(|entity, _| travellers.contains(entity));
+    }
+
+    /// Ask to move a traveller from one tile to the next; a granted move takes over the reservation
+    /// Destinations are always granted, since buildings hold any number of citizens
+    pub fn request(&mut self, entity: Entity, from: (i32, i32), to: (i32, i32), is_destination: bool) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let taken = self.occupants.get(&to).is_some_and(|count| *count > 0);
+        if taken && !is_destination {
+            let waited = self.waiting.entry(entity).or_default();
+            if *waited < AVOIDANCE_PATIENCE {
+                *waited += 1;
+                self.stats.waited += 1;
+                return false;
+            }
+            self.stats.forced += 1;
+        } else {
+            self.stats.moved += 1;
+        }
+        self.waiting.remove(&entity);
+        if let Some(count) = self.occupants.get_mut(&from) {
+            *count = count.saturating_sub(1);
+        }
+        *self.occupants.entry(to).or_default() += 1;
+        true
+    }
+
+    pub fn stats(&self) -> AvoidanceStats {
+        self.stats
+    }
+}
+
+impl Default for LocalAvoidance {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_travellers_wait_for_taken_tiles_then_push_through() {
+        let mut world = World::new();
+        let a = world.spawn((GridPositionComponent { x: 0, y: 0 }, AgentComponent::new("A", vec![(3, 0)]))).unwrap();
+        let b = world.spawn((GridPositionComponent { x: 1, y: 0 }, AgentComponent::new("B", vec![(0, 0)]))).unwrap();
+        let mut avoidance = LocalAvoidance::default();
+
+        // Head-on: each wants the other's tile, so both wait until their patience runs out
+        for _ in 0..AVOIDANCE_PATIENCE {
+            avoidance.begin(&world);
+            assert!(!avoidance.request(a, (0, 0), (1, 0), false));
+            assert!(!avoidance.request(b, (1, 0), (0, 0), false));
+        }
+        avoidance.begin(&world);
+        assert!(avoidance.request(a, (0, 0), (1, 0), false));
+        // A left its tile, so B steps onto it without forcing
+        assert!(avoidance.request(b, (1, 0), (0, 0), false));
+        assert_eq!(avoidance.stats(), AvoidanceStats { moved: 1, waited: 0, forced: 1 });
+
+        avoidance.begin(&world);
+        assert!(avoidance.request(a, (0, 0), (1, 0), true));
+        avoidance.enabled = false;
+        avoidance.begin(&world);
+        assert!(avoidance.request(b, (1, 0), (0, 0), false));
+    }
+}
@@ -0,0 +1,228 @@
+/// Blueprints: building layouts copied from a region of the map and stamped elsewhere
+use crate::catalog::BuildingCatalog;
+use crate::construction::{BuildingComponent, BuildingKind, UnderConstructionComponent};
+use crate::ecs::World;
+use crate::grid_game_components::GridPositionComponent;
+use crate::core::math::{Transform2d, Vector2d};
+use crate::rendering::RenderCommand;
+use crate::signing::{self, SigningConfig};
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory the console exports shared blueprints to and imports them from
+pub const BLUEPRINT_DIRECTORY: &str = "blueprints";
+
+/// A single building in a blueprint, relative to the blueprint origin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlueprintEntry {
+    pub dx: i32,
+    pub dy: i32,
+    pub kind: BuildingKind,
+}
+
+/// Serializable layout of buildings that can be stamped onto the map
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub entries: Vec<BlueprintEntry>,
+}
+
+impl Blueprint {
+    /// Capture the buildings and construction sites inside a rectangle (corners inclusive, any order)
+    pub fn capture(world: &World, name: &str, corner_a: (i32, i32), corner_b: (i32, i32)) -> Self {
+        let min = (corner_a.0.min(corner_b.0), corner_a.1.min(corner_b.1));
+        let max = (corner_a.0.max(corner_b.0), corner_a.1.max(corner_b.1));
+
+        let mut entries = Vec::new();
+        for entity in world.entities_with_components(&[TypeId::of::<GridPositionComponent>()]) {
+            let kind = world.get_component::<BuildingComponent>(entity).map(|b| b.kind)
+                .or_else(|| world.get_component::<UnderConstructionComponent>(entity).map(|site| site.kind));
+            let Some(kind) = kind else { continue };
+            let Some(pos) = world.get_component::<GridPositionComponent>(entity) else { continue };
+
+            if pos.x >= min.0 && pos.x <= max.0 && pos.y >= min.1 && pos.y <= max.1 {
+                entries.push(BlueprintEntry { dx: pos.x - min.0, dy: pos.y - min.1, kind });
+            }
+        }
+        entries.sort_by_key(|entry| (entry.dy, entry.dx));
+
+        Self {
+            name: name.to_string(),
+            width: (max.0 - min.0 + 1) as u32,
+            height: (max.1 - min.1 + 1) as u32,
+            entries,
+        }
+    }
+
+    /// Total cost of constructing every building in the blueprint at the catalog's prices
+    pub fn total_cost(&self, catalog: &BuildingCatalog) -> i64 {
+        self.entries.iter().map(|entry| catalog.get(entry.kind).cost).sum()
+    }
+
+    /// Absolute tiles and kinds when stamped with its top-left corner at `origin`
+    pub fn placements(&self, origin: (i32, i32)) -> Vec<(i32, i32, BuildingKind)> {
+        self.entries.iter()
+            .map(|entry| (origin.0 + entry.dx, origin.1 + entry.dy, entry.kind))
+            .collect()
+    }
+
+    /// Ghost preview of the blueprint stamped at `origin`
+    pub fn ghost_commands(&self, origin: (i32, i32), cell_size: f32, valid: bool, z_order: i32) -> Vec<RenderCommand> {
+        self.placements(origin)
+            .into_iter()
+            .map(|(x, y, kind)| RenderCommand::DrawGhost {
+                texture_id: format!("building_{:?}", kind).to_lowercase(),
+                transform: Transform2d::translation(Vector2d::new(
+                    (x as f32 + 0.5) * cell_size,
+                    (y as f32 + 0.5) * cell_size,
+                )),
+                size: Vector2d::new(cell_size, cell_size),
+                valid,
+                z_order,
+            })
+            .collect()
+    }
+
+    /// Compact single-line form for sharing through the clipboard
+    pub fn to_clipboard_string(&self) -> Result<String, Box<dyn Error>> {
+        Ok(ron::to_string(self)?)
+    }
+
+    /// Parse a blueprint pasted from the clipboard
+    pub fn from_clipboard_string(text: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(text.trim())?)
+    }
+
+    /// Save the blueprint as a RON asset
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Load a blueprint from a RON asset
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(ron::from_str(&content)?)
+    }
+
+    /// Save the blueprint for sharing, signed and maybe encrypted with the workshop key
+    pub fn save_sealed(&self, path: &Path, config: &SigningConfig) -> Result<(), Box<dyn Error>> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        signing::write_sealed(path, &content, config)
+    }
+
+    /// Load a shared blueprint, refusing it unless its signature is valid
+    pub fn load_sealed(path: &Path, config: &SigningConfig) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&signing::read_sealed(path, config)?)?)
+    }
+
+    /// Path of a shared blueprint file in `directory`; only a plain file name is accepted, so the console
+    /// can't read or write anywhere else
+    pub fn shared_path(directory: &Path, file: &str) -> Result<PathBuf, String> {
+        let plain = !file.is_empty()
+            && !file.starts_with('.')
+            && file.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' || ch == '.');
+        match plain {
+            true => Ok(directory.join(file)),
+            false => Err(format!("'{}' is not a file name; blueprints are shared from {}/", file, directory.display())),
+        }
+    }
+}
+
+/// Named blueprints available to the player
+#[derive(Debug, Clone, Default)]
+pub struct BlueprintLibrary {
+    blueprints: HashMap<String, Blueprint>,
+}
+
+impl BlueprintLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a blueprint, replacing any with the same name
+    pub fn insert(&mut self, blueprint: Blueprint) {
+        self.blueprints.insert(blueprint.name.clone(), blueprint);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Blueprint> {
+        self.blueprints.get(name)
+    }
+
+    /// All blueprints sorted by name
+    pub fn all(&self) -> Vec<&Blueprint> {
+        let mut blueprints: Vec<&Blueprint> = self.blueprints.values().collect();
+        blueprints.sort_by(|a, b| a.name.cmp(&b.name));
+        blueprints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_with_buildings() -> World {
+        let mut world = World::new();
+        for (x, y, kind) in [(2, 2, BuildingKind::House), (3, 2, BuildingKind::Shop), (7, 7, BuildingKind::Factory)] {
+            let entity = world.create_entity();
+            world.add_component(entity, GridPositionComponent { x, y }).unwrap();
+            kind.spawn_final(&mut world, entity).unwrap();
+        }
+        let site = world.create_entity();
+        world.add_component(site, GridPositionComponent { x: 2, y: 3 }).unwrap();
+        world.add_component(site, UnderConstructionComponent::new(BuildingKind::School)).unwrap();
+        world
+    }
+
+    #[test]
+    fn test_capture_region() {
+        let world = world_with_buildings();
+        let blueprint = Blueprint::capture(&world, "block", (3, 3), (1, 1));
+
+        assert_eq!((blueprint.width, blueprint.height), (3, 3));
+        assert_eq!(blueprint.entries, vec![
+            BlueprintEntry { dx: 1, dy: 1, kind: BuildingKind::House },
+            BlueprintEntry { dx: 2, dy: 1, kind: BuildingKind::Shop },
+            BlueprintEntry { dx: 1, dy: 2, kind: BuildingKind::School },
+        ]);
+        assert_eq!(blueprint.total_cost(&BuildingCatalog::default()), 200 + 300 + 500);
+        assert_eq!(blueprint.placements((5, 0))[0], (6, 1, BuildingKind::House));
+        assert_eq!(blueprint.ghost_commands((5, 0), 32.0, true, 50).len(), 3);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let world = world_with_buildings();
+        let blueprint = Blueprint::capture(&world, "block", (2, 2), (3, 3));
+        let path = std::env::temp_dir().join(format!("blueprint_test_{}.ron", std::process::id()));
+
+        blueprint.save(&path).unwrap();
+        assert_eq!(Blueprint::load(&path).unwrap(), blueprint);
+        // Plain saves aren't signed, so they only load as shared files while signing is off
+        assert!(Blueprint::load_sealed(&path, &SigningConfig::with_key("workshop")).is_err());
+        assert_eq!(Blueprint::load_sealed(&path, &SigningConfig::disabled()).unwrap(), blueprint);
+        let config = SigningConfig { encrypt: true, ..SigningConfig::with_key("workshop") };
+        blueprint.save_sealed(&path, &config).unwrap();
+        assert_eq!(Blueprint::load_sealed(&path, &config).unwrap(), blueprint);
+        let _ = fs::remove_file(path);
+
+        // Shared files are plain names inside the blueprint directory
+        let directory = Path::new(BLUEPRINT_DIRECTORY);
+        assert_eq!(Blueprint::shared_path(directory, "block.ron"), Ok(directory.join("block.ron")));
+        for file in ["", "../city.sav", "..", ".hidden", "nested/block.ron", "/etc/passwd", "C:\\block.ron"] {
+            assert!(Blueprint::shared_path(directory, file).is_err(), "{}", file);
+        }
+        
+        let text = blueprint.to_clipboard_string().unwrap();
+        assert!(!text.contains('\n'));
+        assert_eq!(Blueprint::from_clipboard_string(&text).unwrap(), blueprint);
+        assert!(Blueprint::from_clipboard_string("money 10").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+/// Time-sliced systems that spread long-running work over several frames
+use crate::ecs::World;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Per-frame allowance of work units and (optionally) wall-clock time
+#[derive(Debug, Clone)]
+pub struct WorkBudget {
+    units_remaining: u32,
+    units_used: u32,
+    deadline: Option<Instant>,
+}
+
+impl WorkBudget {
+    /// Budget limited to a number of work units
+    pub fn new(units: u32) -> Self {
+        Self {
+            units_remaining: units,
+            units_used: 0,
+            deadline: None,
+        }
+    }
+
+    /// Budget with no limit, for running work to completion
+    pub fn unlimited() -> Self {
+        Self::new(u32::MAX)
+    }
+
+    /// Additionally stop once `limit` has elapsed from now
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.deadline = Some(Instant::now() + limit);
+        self
+    }
+
+    /// Spend work units; returns false (spending nothing) once the budget is exhausted
+    pub fn try_consume(&mut self, units: u32) -> bool {
+        if self.is_exhausted() || self.units_remaining < units {
+            return false;
+        }
+        self.units_remaining -= units;
+        self.units_used += units;
+        true
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.units_remaining == 0 || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub fn units_used(&self) -> u32 {
+        self.units_used
+    }
+}
+
+/// Outcome of running one slice of a budgeted system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceResult {
+    /// All pending work is done
+    Finished,
+    /// Work remains and will resume next frame
+    Pending,
+}
+
+/// A system whose work can be split across frames
+/// Implementations keep their in-progress state (e.g. an A* open list) between calls
+pub trait BudgetedSystem {
+    fn name(&self) -> &'static str;
+
+    /// Do as much work as the budget allows
+    fn run_slice(&mut self, world: &mut World, budget: &mut WorkBudget) -> SliceResult;
+}
+
+/// Budget given to a system every frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetConfig {
+    pub work_units: u32,
+    pub time_limit: Option<Duration>,
+}
+
+impl BudgetConfig {
+    pub fn units(work_units: u32) -> Self {
+        Self { work_units, time_limit: None }
+    }
+
+    fn budget(&self) -> WorkBudget {
+        let budget = WorkBudget::new(self.work_units);
+        match self.time_limit {
+            Some(limit) => budget.with_time_limit(limit),
+            None => budget,
+        }
+    }
+}
+
+/// What a budgeted system did during the last frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct SliceReport {
+    pub name: &'static str,
+    pub units_used: u32,
+    pub result: SliceResult,
+}
+
+/// Runs budgeted systems once per frame, each with its own configured budget, in the order they were registered
+#[derive(Default)]
+pub struct BudgetedScheduler {
+    systems: Vec<Box<dyn BudgetedSystem>>,
+    budgets: HashMap<&'static str, BudgetConfig>,
+}
+
+impl BudgetedScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a system with its per-frame budget
+    pub fn add_system(&mut self, system: Box<dyn BudgetedSystem>, config: BudgetConfig) {
+        self.budgets.insert(system.name(), config);
+        self.systems.push(system);
+    }
+
+    /// Change the budget of a registered system
+    pub fn set_budget(&mut self, name: &str, config: BudgetConfig) -> Result<(), String> {
+        let name = self.systems.iter()
+            .map(|system| system.name())
+            .find(|system_name| *system_name == name)
+            .ok_or_else(|| format!("Unknown budgeted system '{}'", name))?;
+        self.budgets.insert(name, config);
+        Ok(())
+    }
+
+    pub fn budget(&self, name: &str) -> Option<BudgetConfig> {
+        self.budgets.get(name).copied()
+    }
+
+    /// Give every system one slice of work; systems that share a name still run in registration order
+    pub fn run_frame(&mut self, world: &mut World) -> Vec<SliceReport> {
+        self.systems.iter_mut()
+            .map(|system| {
+                let mut budget = self.budgets.get(system.name())
+                    .map(|config| config.budget())
+                    .unwrap_or_else(WorkBudget::unlimited);
+                let result = system.run_slice(world, &mut budget);
+                SliceReport { name: system.name(), units_used: budget.units_used(), result }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts to a target, one work unit per increment
+    struct CountingSystem {
+        count: u32,
+        target: u32,
+    }
+
+    impl BudgetedSystem for CountingSystem {
+        fn name(&self) -> &'static str {
+            "CountingSystem"
+        }
+
+        fn run_slice(&mut self, _world: &mut World, budget: &mut WorkBudget) -> SliceResult {
+            while self.count < self.target {
+                if !budget.try_consume(1) {
+                    return SliceResult::Pending;
+                }
+                self.count += 1;
+            }
+            SliceResult::Finished
+        }
+    }
+
+    #[test]
+    fn test_work_budget() {
+        let mut budget = WorkBudget::new(3);
+        assert!(budget.try_consume(2));
+        assert!(!budget.try_consume(2));
+        assert!(budget.try_consume(1));
+        assert!(budget.is_exhausted());
+        assert_eq!(budget.units_used(), 3);
+
+        let budget = WorkBudget::unlimited().with_time_limit(Duration::ZERO);
+        assert!(budget.is_exhausted());
+    }
+
+    #[test]
+    fn test_scheduler_resumes_work() {
+        let mut world = World::new();
+        let mut scheduler = BudgetedScheduler::new();
+        scheduler.add_system(Box::new(CountingSystem { count: 0, target: 25 }), BudgetConfig::units(10));
+
+        let reports = scheduler.run_frame(&mut world);
+        assert_eq!(reports[0].units_used, 10);
+        assert_eq!(reports[0].result, SliceResult::Pending);
+
+        scheduler.set_budget("CountingSystem", BudgetConfig::units(100)).unwrap();
+        assert!(scheduler.set_budget("Missing", BudgetConfig::units(1)).is_err());
+
+        let reports = scheduler.run_frame(&mut world);
+        assert_eq!(reports[0].units_used, 15);
+        assert_eq!(reports[0].result, SliceResult::Finished);
+    }
+}
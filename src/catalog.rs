@@ -0,0 +1,216 @@
+/// Data-driven building catalog: costs, footprints, sprites, goods and unlock requirements live in RON data
+/// (`data/buildings.ron`) instead of code, and unlocks open up as the city grows
+use crate::construction::{BuildingComponent, BuildingKind};
+use crate::economy::{ZoneComponent, ZoneType};
+use crate::ecs::World;
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The catalog shipped with the game
+pub const BUILTIN_CATALOG: &str = include_str!("../data/buildings.ron");
+
+fn single_tile() -> (u32, u32) {
+    (1, 1)
+}
+
+/// What the city needs before a building can be placed; met by every city when empty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnlockRequirement {
+    /// Residents living in finished housing
+    pub population: u32,
+    /// Kinds of which at least one finished building must stand
+    pub buildings: Vec<BuildingKind>,
+}
+
+impl UnlockRequirement {
+    pub fn is_met(&self, progress: &CityProgress) -> bool {
+        progress.population >= self.population && self.buildings.iter().all(|kind| progress.built.contains(kind))
+    }
+}
+
+/// One building's entry in the catalog
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildingDefinition {
+    pub kind: BuildingKind,
+    /// Name shown in the build menu
+    pub name: String,
+    /// Money paid up front when the construction site is placed
+    pub cost: i64,
+    /// Number of progress ticks needed to finish the building
+    pub build_ticks: u32,
+    /// Tiles covered as (width, height)
+    #[serde(default = "single_tile")]
+    pub footprint: (u32, u32),
+    pub sprite: String,
+    /// Goods made per month, by name
+    #[serde(default)]
+    pub produces: BTreeMap<String, u32>,
+    /// Goods used per month, by name
+    #[serde(default)]
+    pub consumes: BTreeMap<String, u32>,
+    #[serde(default)]
+    pub unlock: UnlockRequirement,
+}
+
+/// The city's state as far as unlock requirements are concerned
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CityProgress {
+    pub population: u32,
+    pub built: HashSet<BuildingKind>,
+}
+
+impl CityProgress {
+    pub fn measure(world: &World) -> Self {
+        let mut progress = Self::default();
+        for entity in world.entities_with_components(&[TypeId::of::<ZoneComponent>()]) {
+            if let Some(zone) = world.get_component::<ZoneComponent>(entity) {
+                if zone.zone_type == ZoneType::Residential {
+                    progress.population += zone.population;
+                }
+            }
+        }
+        for entity in world.entities_with_components(&[TypeId::of::<BuildingComponent>()]) {
+            if let Some(building) = world.get_component::<BuildingComponent>(entity) {
+                progress.built.insert(building.kind);
+            }
+        }
+        progress
+    }
+}
+
+/// Every building kind's definition, plus which kinds the player has unlocked so far
+/// Unlocks are permanent: a shrinking city keeps what it earned
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildingCatalog {
+    definitions: Vec<BuildingDefinition>,
+    unlocked: HashSet<BuildingKind>,
+}
+
+impl BuildingCatalog {
+    /// Check the definitions and unlock everything without requirements
+    /// Fails unless each building kind is defined exactly once with a usable footprint and build time
+    pub fn new(definitions: Vec<BuildingDefinition>) -> Result<Self, String> {
+        for kind in BuildingKind::all() {
+            match definitions.iter().filter(|definition| definition.kind == kind).count() {
+                0 => return Err(format!("Catalog has no entry for {:?}", kind)),
+                1 => {}
+                _ => return Err(format!("{:?} is defined twice", kind)),
+            }
+        }
+        if let Some(definition) = definitions.iter().find(|definition| definition.footprint.0 == 0 || definition.footprint.1 == 0 || definition.build_ticks == 0) {
+            return Err(format!("{:?} needs a footprint and build time of at least one", definition.kind));
+        }
+
+        let mut definitions = definitions;
+        definitions.sort_by_key(|definition| BuildingKind::all().iter().position(|kind| *kind == definition.kind));
+        let mut catalog = Self { definitions, unlocked: HashSet::new() };
+        catalog.evaluate_unlocks(&CityProgress::default());
+        Ok(catalog)
+    }
+
+    /// Parse a RON list of building definitions
+    pub fn from_ron(text: &str) -> Result<Self, Box<dyn Error>> {
+        let definitions: Vec<BuildingDefinition> = ron::from_str(text)?;
+        Ok(Self::new(definitions)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    /// The catalog shipped with the game, parsed once
+    pub fn builtin() -> &'static BuildingCatalog {
+        static BUILTIN: OnceLock<BuildingCatalog> = OnceLock::new();
+        BUILTIN.get_or_init(|| Self::from_ron(BUILTIN_CATALOG).expect("The built-in building catalog is valid"))
+    }
+
+    pub fn get(&self, kind: BuildingKind) -> &BuildingDefinition {
+        self.definitions.iter()
+            .find(|definition| definition.kind == kind)
+            .expect("Catalogs define every building kind")
+    }
+
+    /// All definitions in build menu order
+    pub fn definitions(&self) -> &[BuildingDefinition] {
+        &self.definitions
+    }
+
+    pub fn is_unlocked(&self, kind: BuildingKind) -> bool {
+        self.unlocked.contains(&kind)
+    }
+
+    /// Unlock every kind whose requirement the city now meets, returning the newly unlocked kinds
+    pub fn evaluate_unlocks(&mut self, progress: &CityProgress) -> Vec<BuildingKind> {
+        let newly_unlocked: Vec<BuildingKind> = self.definitions.iter()
+            .filter(|definition| !self.unlocked.contains(&definition.kind) && definition.unlock.is_met(progress))
+            .map(|definition| definition.kind)
+            .collect();
+        self.unlocked.extend(newly_unlocked.iter().copied());
+        newly_unlocked
+    }
+
+    /// Unlock a kind regardless of its requirement, returning whether it was still locked
+    pub fn unlock(&mut self, kind: BuildingKind) -> bool {
+        self.unlocked.insert(kind)
+    }
+
+    /// Why a kind cannot be placed yet, or `None` when it is unlocked
+    pub fn locked_reason(&self, kind: BuildingKind) -> Option<String> {
+        if self.is_unlocked(kind) {
+            return None;
+        }
+        let requirement = &self.get(kind).unlock;
+        let mut needs = Vec::new();
+        if requirement.population > 0 {
+            needs.push(format!("{} population", requirement.population));
+        }
+        needs.extend(requirement.buildings.iter().map(|building| format!("a {}", self.get(*building).name)));
+        Some(format!("{} is locked: needs {}", self.get(kind).name, needs.join(" and ")))
+    }
+}
+
+impl Default for BuildingCatalog {
+    fn default() -> Self {
+        Self::builtin().clone()
+    }
+}
+
+/// System that unlocks catalog entries as the city reaches their requirements
+pub struct UnlockSystem;
+
+impl UnlockSystem {
+    pub fn update(world: &World, catalog: &mut BuildingCatalog) -> Vec<BuildingKind> {
+        catalog.evaluate_unlocks(&CityProgress::measure(world))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalog_unlocks_with_progress() {
+        let mut catalog = BuildingCatalog::default();
+        assert_eq!(catalog.definitions().len(), BuildingKind::all().len());
+        assert_eq!(catalog.get(BuildingKind::House).cost, 200);
+        assert!(catalog.is_unlocked(BuildingKind::House));
+        assert!(!catalog.is_unlocked(BuildingKind::School));
+        assert_eq!(catalog.locked_reason(BuildingKind::School).unwrap(), "School is locked: needs 16 population and a Clinic");
+
+        let mut progress = CityProgress { population: 20, built: HashSet::new() };
+        assert_eq!(catalog.evaluate_unlocks(&progress), vec![BuildingKind::Clinic]);
+        progress.built.insert(BuildingKind::Clinic);
+        assert_eq!(catalog.evaluate_unlocks(&progress), vec![BuildingKind::School]);
+        // Unlocks stay when the city shrinks
+        assert!(catalog.evaluate_unlocks(&CityProgress::default()).is_empty());
+        assert!(catalog.is_unlocked(BuildingKind::School));
+
+        let missing = BuildingCatalog::from_ron("[(kind: House, name: \"House\", cost: 1, build_ticks: 1, sprite: \"house\")]");
+        assert_eq!(missing.unwrap_err().to_string(), "Catalog has no entry for Shop");
+    }
+}
@@ -0,0 +1,153 @@
+/// Timeline export in the Chrome trace-event format: the server keeps the system spans of its recent ticks, with
+/// the gameplay events and notifications each system raised, and serves them at `/debug/trace?frames=600` as JSON
+/// that `chrome://tracing` and Perfetto open, so frame spikes can be examined with standard tooling
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Ticks kept for export, a minute at 60 ticks per second
+pub const TRACE_FRAMES: usize = 3600;
+
+/// A system stage of one tick, relative to the start of the tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceSpan {
+    pub name: &'static str,
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Something that happened at an instant of a tick, e.g. a gameplay event or a notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceMarker {
+    pub name: String,
+    pub category: &'static str,
+    /// Relative to the start of the tick
+    pub at: Duration,
+    pub detail: String,
+}
+
+/// The spans and markers of one tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameTrace {
+    pub tick: u64,
+    /// Since the recorder started
+    pub start: Duration,
+    pub duration: Duration,
+    pub spans: Vec<TraceSpan>,
+    pub markers: Vec<TraceMarker>,
+}
+
+/// The most recent ticks, oldest first
+#[derive(Debug, Clone)]
+pub struct TraceRecorder {
+    frames: VecDeque<FrameTrace>,
+    capacity: usize,
+    epoch: Instant,
+}
+
+impl TraceRecorder {
+    pub fn new(capacity: usize) -> Self {
+        Self { frames: VecDeque::new(), capacity: capacity.max(1), epoch: Instant::now() }
+    }
+
+    /// Record a tick that started at `start` and ran `systems` one after the other
+    pub fn record(&mut self, tick: u64, start: Instant, systems: &[(&'static str, Duration)], markers: Vec<TraceMarker>) {
+        let mut offset = Duration::ZERO;
+        let spans: Vec<TraceSpan> = systems.iter().map(|&(name, duration)| {
+            let span = TraceSpan { name, start: offset, duration };
+            offset += duration;
+            span
+        }).collect();
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(FrameTrace {
+            tick,
+            start: start.saturating_duration_since(self.epoch),
+            duration: offset,
+            spans,
+            markers,
+        });
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &FrameTrace> {
+        self.frames.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The last `frames` ticks as a Chrome trace: a complete event per tick with its systems nested inside, and
+    /// an instant event per marker; times are in microseconds
+    pub fn chrome_trace(&self, frames: usize) -> Value {
+        let micros = |duration: Duration| duration.as_secs_f64() * 1_000_000.0;
+        let mut events = vec![
+            json!({"name": "process_name", "ph": "M", "pid": 1, "tid": 1, "args": {"name": "citybuilder server"}}),
+            json!({"name": "thread_name", "ph": "M", "pid": 1, "tid": 1, "args": {"name": "simulation"}}),
+        ];
+        for frame in self.frames.iter().skip(self.frames.len().saturating_sub(frames)) {
+            events.push(json!({
+                "name": format!("tick {}", frame.tick), "cat": "tick", "ph": "X", "pid": 1, "tid": 1,
+                "ts": micros(frame.start), "dur": micros(frame.duration), "args": {"tick": frame.tick},
+            }));
+            events.extend(frame.spans.iter().map(|span| json!({
+                "name": span.name, "cat": "system", "ph": "X", "pid": 1, "tid": 1,
+                "ts": micros(frame.start + span.start), "dur": micros(span.duration), "args": {"tick": frame.tick},
+            })));
+            events.extend(frame.markers.iter().map(|marker| json!({
+                "name": marker.name, "cat": marker.category, "ph": "i", "s": "t", "pid": 1, "tid": 1,
+                "ts": micros(frame.start + marker.at), "args": {"tick": frame.tick, "detail": marker.detail},
+            })));
+        }
+        json!({"traceEvents": events, "displayTimeUnit": "ms"})
+    }
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self::new(TRACE_FRAMES)
+    }
+}
+
+/// Name of a value's variant from its `Debug` text, e.g. "BuildingPlaced" for a `GameEvent::BuildingPlaced`
+pub fn variant_name(debug: &str) -> &str {
+    debug.split([' ', '(', '{']).next().unwrap_or(debug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chrome_trace_nests_systems_in_their_tick_with_markers() {
+        let mut recorder = TraceRecorder::new(2);
+        let start = Instant::now();
+        let systems = [("input", Duration::from_micros(100)), ("budget", Duration::from_micros(300))];
+        for tick in 1..=3 {
+            let marker = TraceMarker {
+                name: variant_name("MoneyEarned { amount: 5 }").to_string(),
+                category: "event",
+                at: Duration::from_micros(400),
+                detail: "MoneyEarned { amount: 5 }".to_string(),
+            };
+            recorder.record(tick, start, &systems, vec![marker]);
+        }
+        assert_eq!(recorder.len(), 2);
+
+        let trace = recorder.chrome_trace(1);
+        let events = trace["traceEvents"].as_array().unwrap();
+        let spans: Vec<&Value> = events.iter().filter(|event| event["ph"] == "X").collect();
+        assert_eq!(spans.len(), 3);
+        assert_eq!((spans[0]["name"].as_str(), spans[0]["dur"].as_f64()), (Some("tick 3"), Some(400.0)));
+        let budget_offset = spans[2]["ts"].as_f64().unwrap() - spans[0]["ts"].as_f64().unwrap();
+        assert!((budget_offset - 100.0).abs() < 0.01, "{}", budget_offset);
+        let marker = events.iter().find(|event| event["ph"] == "i").unwrap();
+        assert_eq!((marker["name"].as_str(), marker["cat"].as_str()), (Some("MoneyEarned"), Some("event")));
+        assert_eq!(recorder.chrome_trace(600)["traceEvents"].as_array().unwrap().len(), 2 + 2 * 4);
+    }
+}
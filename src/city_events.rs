@@ -0,0 +1,280 @@
+/// Random city events such as festivals, strikes and economic booms, rolled from a data-driven table
+/// (`data/city_events.ron`) by the `EventsDirector`
+/// An event adds its effects to the economy's modifier stack for a number of days and takes them away when it
+/// ends; the director's state is saved alongside the city, so events carry on where they were after a load
+use crate::catalog::{CityProgress, UnlockRequirement};
+use crate::ecs::World;
+use crate::economy::Economy;
+use crate::events::EventQueue;
+use crate::modifiers::ModifierEffect;
+use crate::notifications::Notification;
+use crate::signing::{self, SigningConfig};
+use crate::soak::SeededRng;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The event table shipped with the game
+pub const BUILTIN_CITY_EVENTS: &str = include_str!("../data/city_events.ron");
+/// In-game days between two rolls for a new event
+pub const ROLL_INTERVAL_DAYS: u32 = 10;
+/// Chance in percent that a roll starts an event
+pub const ROLL_CHANCE_PERCENT: i32 = 25;
+
+/// One entry of the event table
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CityEventDefinition {
+    /// Identifies the event in saves and as the source of its modifiers
+    pub name: String,
+    pub title: String,
+    /// Shown when the event starts
+    pub message: String,
+    /// Relative chance of being picked among the events the city qualifies for
+    pub weight: u32,
+    pub days: u32,
+    /// Announced as a warning rather than as news
+    #[serde(default)]
+    pub warning: bool,
+    /// Population and buildings the city needs before the event can happen
+    #[serde(default)]
+    pub requires: UnlockRequirement,
+    /// First in-game month the event can happen in
+    #[serde(default)]
+    pub min_month: u32,
+    pub effects: Vec<ModifierEffect>,
+}
+
+impl CityEventDefinition {
+    fn is_possible(&self, progress: &CityProgress, month: u32) -> bool {
+        self.weight > 0 && month >= self.min_month && self.requires.is_met(progress)
+    }
+}
+
+/// An event in progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveCityEvent {
+    pub name: String,
+    pub title: String,
+    pub remaining_ticks: u32,
+    pub effects: Vec<ModifierEffect>,
+}
+
+/// What of the director goes into a save
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DirectorState {
+    ticks: u32,
+    rolls: u64,
+    active: Vec<ActiveCityEvent>,
+}
+
+/// Rolls for a new event every few days and ends events once their days are up
+#[derive(Debug, Clone)]
+pub struct EventsDirector {
+    /// Off, no new events are rolled; events started by hand still run their days
+    pub enabled: bool,
+    table: Vec<CityEventDefinition>,
+    ticks_per_day: u32,
+    interval_days: u32,
+    chance_percent: i32,
+    state: DirectorState,
+}
+
+impl EventsDirector {
+    pub fn new(table: Vec<CityEventDefinition>, ticks_per_day: u32) -> Self {
+        Self {
+            enabled: true,
+            table,
+            ticks_per_day: ticks_per_day.max(1),
+            interval_days: ROLL_INTERVAL_DAYS,
+            chance_percent: ROLL_CHANCE_PERCENT,
+            state: DirectorState::default(),
+        }
+    }
+
+    pub fn from_ron(text: &str, ticks_per_day: u32) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(ron::from_str(text)?, ticks_per_day))
+    }
+
+    /// The event table shipped with the game, parsed once
+    pub fn builtin_table() -> &'static [CityEventDefinition] {
+        static BUILTIN: OnceLock<Vec<CityEventDefinition>> = OnceLock::new();
+        BUILTIN.get_or_init(|| ron::from_str(BUILTIN_CITY_EVENTS).expect("The built-in city event table is valid"))
+    }
+
+    /// Roll every `interval_days` days, starting an event `chance_percent` percent of the time
+    pub fn with_rolls(mut self, interval_days: u32, chance_percent: i32) -> Self {
+        self.interval_days = interval_days.max(1);
+        self.chance_percent = chance_percent;
+        self
+    }
+
+    pub fn active(&self) -> &[ActiveCityEvent] {
+        &self.state.active
+    }
+
+    /// Path of the event file saved next to a city save
+    pub fn events_path_for_save(save_path: &Path) -> PathBuf {
+        save_path.with_extension("events.ron")
+    }
+
+    /// Write the events in progress and the roll schedule next to the given save file
+    pub fn save_alongside(&self, save_path: &Path, signing: &SigningConfig) -> Result<(), Box<dyn Error>> {
+        let content = ron::ser::to_string_pretty(&self.state, ron::ser::PrettyConfig::default())?;
+        signing::write_sealed(&Self::events_path_for_save(save_path), &content, signing)
+    }
+
+    /// Pick up the events in progress from the given save, putting their modifiers back in place; a save
+    /// without an event file leaves the director as it is
+    pub fn load_alongside(&mut self, save_path: &Path, economy: &mut Economy, signing: &SigningConfig) -> Result<(), Box<dyn Error>> {
+        let path = Self::events_path_for_save(save_path);
+        if !path.exists() {
+            return Ok(());
+        }
+        let state: DirectorState = ron::from_str(&signing::read_sealed(&path, signing)?)?;
+        for event in &self.state.active {
+            economy.modifiers.remove_source(&event.name);
+        }
+        for event in &state.active {
+            Self::apply(event, economy);
+        }
+        self.state = state;
+        Ok(())
+    }
+
+    /// Start the named event now, whatever the city's state; false when it's unknown or already running
+    pub fn start(&mut self, name: &str, economy: &mut Economy, notifications: &mut EventQueue<Notification>) -> bool {
+        if self.state.active.iter().any(|event| event.name == name) {
+            return false;
+        }
+        let Some(definition) = self.table.iter().find(|definition| definition.name == name) else {
+            return false;
+        };
+        let event = ActiveCityEvent {
+            name: definition.name.clone(),
+            title: definition.title.clone(),
+            remaining_ticks: definition.days * self.ticks_per_day,
+            effects: definition.effects.clone(),
+        };
+        let message = format!("{}: {}", definition.title, definition.message);
+        notifications.push(if definition.warning { Notification::warning(&message) } else { Notification::info(&message) });
+        Self::apply(&event, economy);
+        self.state.active.push(event);
+        true
+    }
+
+    /// Advance one tick: end the events whose days are up, then roll for a new one when it's time
+    /// `seed` makes the rolls repeatable, so the same seed and city always get the same events
+    pub fn update(
+        &mut self,
+        world: &World,
+        economy: &mut Economy,
+        seed: u64,
+        month: u32,
+        notifications: &mut EventQueue<Notification>,
+    ) {
+        for event in &mut self.state.active {
+            event.remaining_ticks = event.remaining_ticks.saturating_sub(1);
+            if event.remaining_ticks == 0 {
+                economy.modifiers.remove_source(&event.name);
+                notifications.push(Notification::info(&format!("{} is over", event.title)));
+            }
+        }
+        self.state.active.retain(|event| event.remaining_ticks > 0);
+        if !self.enabled {
+            return;
+        }
+
+        self.state.ticks += 1;
+        if self.state.ticks < self.interval_days * self.ticks_per_day {
+            return;
+        }
+        self.state.ticks = 0;
+        if let Some(name) = self.roll(world, seed, month) {
+            self.start(&name, economy, notifications);
+        }
+    }
+
+    /// Name of the event a roll picks, if any
+    fn roll(&mut self, world: &World, seed: u64, month: u32) -> Option<String> {
+        let mut rng = SeededRng::new(seed ^ self.state.rolls.wrapping_mul(0x2545_F491_4F6C_DD1D));
+        self.state.rolls += 1;
+        if rng.below(100) >= self.chance_percent {
+            return None;
+        }
+        let progress = CityProgress::measure(world);
+        let candidates: Vec<&CityEventDefinition> = self.table.iter()
+            .filter(|definition| definition.is_possible(&progress, month))
+            .filter(|definition| !self.state.active.iter().any(|event| event.name == definition.name))
+            .collect();
+        let total: u32 = candidates.iter().map(|definition| definition.weight).sum();
+        let mut pick = rng.below(total as i32) as u32;
+        candidates.into_iter()
+            .find(|definition| {
+                if pick < definition.weight {
+                    return true;
+                }
+                pick -= definition.weight;
+                false
+            })
+            .map(|definition| definition.name.clone())
+    }
+
+    fn apply(event: &ActiveCityEvent, economy: &mut Economy) {
+        economy.modifiers.push_effects(&event.name, &event.effects);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::economy::ZoneType;
+    use crate::modifiers::ModifierTarget;
+
+    #[test]
+    fn test_events_start_apply_modifiers_and_end() {
+        let table = EventsDirector::builtin_table().to_vec();
+        let mut director = EventsDirector::new(table, 2).with_rolls(1, 100);
+        let (world, mut economy, mut notifications) = (World::new(), Economy::default(), EventQueue::new());
+
+        // An empty city qualifies for nothing, so rolls come up empty
+        director.update(&world, &mut economy, 7, 12, &mut notifications);
+        director.update(&world, &mut economy, 7, 12, &mut notifications);
+        assert!(director.active().is_empty());
+
+        assert!(director.start("festival", &mut economy, &mut notifications));
+        assert!(!director.start("festival", &mut economy, &mut notifications));
+        let commercial = ModifierTarget::TaxIncome(ZoneType::Commercial);
+        assert_eq!(economy.modifiers.apply(commercial, 100), 120);
+
+        let dir = std::env::temp_dir().join(format!("city_events_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save_path = dir.join("city.sav");
+        director.save_alongside(&save_path, &SigningConfig::disabled()).unwrap();
+        let mut loaded = EventsDirector::new(EventsDirector::builtin_table().to_vec(), 2);
+        let mut loaded_economy = Economy::default();
+        loaded.load_alongside(&save_path, &mut loaded_economy, &SigningConfig::disabled()).unwrap();
+        assert_eq!(loaded.active(), director.active());
+        assert_eq!(loaded_economy.modifiers, economy.modifiers);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Ten days of two ticks each
+        for _ in 0..20 {
+            director.update(&world, &mut economy, 7, 12, &mut notifications);
+        }
+        assert!(director.active().is_empty());
+        assert_eq!(economy.modifiers.apply(commercial, 100), 100);
+        assert!(notifications.iter().any(|notification| notification.message == "Summer Festival is over"));
+
+        // Switched off, the director only runs the events started by hand
+        let fair = r#"[(name: "fair", title: "Fair", message: "A fair comes to town", weight: 1, days: 5, effects: [])]"#;
+        let mut quiet = EventsDirector::from_ron(fair, 1).unwrap().with_rolls(1, 100);
+        quiet.enabled = false;
+        quiet.update(&world, &mut economy, 7, 12, &mut notifications);
+        assert!(quiet.active().is_empty());
+        quiet.enabled = true;
+        quiet.update(&world, &mut economy, 7, 12, &mut notifications);
+        assert_eq!(quiet.active().len(), 1);
+    }
+}
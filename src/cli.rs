@@ -0,0 +1,182 @@
+/// Command line parsing: subcommands with typed options, run through the `App` builder
+use crate::game_rules::GameMode;
+use clap::{Parser, Subcommand};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Rust City Builder Game
+#[derive(Debug, Parser)]
+#[command(after_help = EXAMPLES)]
+struct Cli {
+    /// What to run; the web game is served when none is given
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+const EXAMPLES: &str = "\
+Examples:
+  cargo run                                    Start the web ECS game
+  cargo run serve --port 3000                  Serve the game on port 3000 of the configured host
+  cargo run serve --headless                   Serve without the rendering/input device servers
+  cargo run serve --mode puzzle                Serve the puzzle mode: reach the far corner in 30 moves
+  cargo run replay saves/city.actions.jsonl    Rebuild a city from its action log
+  cargo run bench --ticks 500                  Time 500 headless ticks
+  cargo run simulate --scenario x.ron --years 5 --csv out.csv
+                                               Sample 5 years of city metrics into out.csv
+  cargo run server 0.0.0.0:3000                Start the HTTP server on all interfaces, port 3000";
+
+/// A parsed command line
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum Command {
+    /// Serve the web ECS game; ports default to game.ron's `ports` section, and taken ports move to free ones
+    /// listed in services.json
+    #[command(alias = "ecs-game")]
+    Serve {
+        /// Address to listen on, e.g. 0.0.0.0:3000
+        #[arg(long, conflicts_with = "port")]
+        address: Option<String>,
+        /// Port to listen on, on the configured host
+        #[arg(long)]
+        port: Option<u16>,
+        /// Don't start the rendering and input device servers
+        #[arg(long)]
+        headless: bool,
+        /// Rules to play by: city, sandbox or puzzle
+        #[arg(long, default_value = "city")]
+        mode: GameMode,
+    },
+    /// Run with a native window
+    Native,
+    /// Rebuild a city from an action log, or replay a crash bundle directory up to its panic
+    Replay { path: PathBuf },
+    /// Time ticks of the default map headless
+    Bench {
+        #[arg(long, default_value_t = 1000)]
+        ticks: u32,
+    },
+    /// Time the default map crowded with extra citizens
+    Stress {
+        #[arg(long, default_value_t = 1000)]
+        citizens: u32,
+        #[arg(long, default_value_t = 100)]
+        ticks: u32,
+    },
+    /// Run the economy and citizens headless for in-game years, sampling metrics monthly
+    Simulate {
+        /// Scenario to start from instead of the default map
+        #[arg(long)]
+        scenario: Option<PathBuf>,
+        #[arg(long, default_value_t = 5)]
+        years: u32,
+        /// File to write the samples to as CSV instead of stdout
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+    /// Run a seeded simulation twice and check both runs stay identical
+    Soak {
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+        #[arg(long, default_value_t = 5000)]
+        ticks: u32,
+    },
+    /// Serve the web game starting from a scenario file
+    Scenario { path: PathBuf },
+    /// Rewrite a debug recording as RON text or binary; an output ending in .ron is text, anything else binary
+    ConvertRecording { input: PathBuf, output: PathBuf },
+    /// Start the hello world HTTP server, on port 8080 of the configured host unless an address is given
+    #[command(name = "server")]
+    HelloServer { address: Option<String> },
+    /// Demonstrate the rendering system
+    Render,
+    /// Start the interactive web rendering client
+    WebRender,
+}
+
+impl Command {
+    /// Parse a whole command line, program name first; no subcommand serves the web game
+    /// `--help` and mistakes come back as a `clap::Error`, whose `exit` prints them
+    pub fn parse_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let cli = Cli::try_parse_from(args)?;
+        Ok(cli.command.unwrap_or(Command::Serve { address: None, port: None, headless: false, mode: GameMode::City }))
+    }
+
+    /// Whether the command needs the global web rendering and input devices
+    pub fn uses_web_devices(&self) -> bool {
+        match self {
+            Command::Serve { headless, .. } => !headless,
+            Command::Scenario { .. } | Command::Render | Command::WebRender => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the command serves until stopped, so Ctrl+C and SIGTERM should stop it cleanly
+    pub fn serves(&self) -> bool {
+        matches!(self, Command::Serve { .. } | Command::Scenario { .. } | Command::HelloServer { .. } | Command::WebRender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+
+    fn parse(line: &str) -> Result<Command, clap::Error> {
+        Command::parse_from(std::iter::once("citybuilder").chain(line.split_whitespace()))
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        assert_eq!(parse("").unwrap(), Command::Serve { address: None, port: None, headless: false, mode: GameMode::City });
+        assert_eq!(parse("serve --headless --port 3000").unwrap(), Command::Serve {
+            address: None,
+            port: Some(3000),
+            headless: true,
+            mode: GameMode::City,
+        });
+        assert_eq!(parse("ecs-game --address 0.0.0.0:80 --mode sandbox").unwrap(), Command::Serve {
+            address: Some("0.0.0.0:80".to_string()),
+            port: None,
+            headless: false,
+            mode: GameMode::Sandbox,
+        });
+        assert_eq!(parse("stress --ticks 5").unwrap(), Command::Stress { citizens: 1000, ticks: 5 });
+        assert_eq!(parse("soak --seed 9").unwrap(), Command::Soak { seed: 9, ticks: 5000 });
+        assert_eq!(parse("simulate --scenario x.ron --years 2 --csv out.csv").unwrap(), Command::Simulate {
+            scenario: Some(PathBuf::from("x.ron")),
+            years: 2,
+            csv: Some(PathBuf::from("out.csv")),
+        });
+        assert_eq!(parse("replay saves/city.actions.jsonl").unwrap(), Command::Replay { path: PathBuf::from("saves/city.actions.jsonl") });
+        assert_eq!(parse("convert-recording session.ron session.cbrc").unwrap(), Command::ConvertRecording {
+            input: PathBuf::from("session.ron"),
+            output: PathBuf::from("session.cbrc"),
+        });
+        assert_eq!(parse("server 0.0.0.0:3000").unwrap(), Command::HelloServer { address: Some("0.0.0.0:3000".to_string()) });
+        assert!(!parse("serve --headless").unwrap().uses_web_devices());
+        assert!(parse("render").unwrap().uses_web_devices());
+        assert!(parse("server").unwrap().serves());
+        assert!(!parse("bench").unwrap().serves());
+    }
+
+    #[test]
+    fn test_parse_errors_and_help() {
+        let kind = |line: &str| parse(line).unwrap_err().kind();
+        assert_eq!(kind("fly"), ErrorKind::InvalidSubcommand);
+        assert_eq!(kind("serve --port 99999"), ErrorKind::ValueValidation);
+        assert_eq!(kind("serve --mode chess"), ErrorKind::ValueValidation);
+        assert_eq!(kind("bench --ticks"), ErrorKind::InvalidValue);
+        assert_eq!(kind("bench --verbose"), ErrorKind::UnknownArgument);
+        assert_eq!(kind("replay"), ErrorKind::MissingRequiredArgument);
+        assert_eq!(kind("load city.ron"), ErrorKind::InvalidSubcommand);
+        assert_eq!(kind("serve --port 1 --address a:1"), ErrorKind::ArgumentConflict);
+
+        let help = parse("simulate --help").unwrap_err();
+        assert_eq!(help.kind(), ErrorKind::DisplayHelp);
+        assert!(help.to_string().contains("--years"), "{}", help);
+        assert!(parse("--help").unwrap_err().to_string().contains("Examples:"));
+    }
+}
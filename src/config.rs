@@ -0,0 +1,31 @@
+/// Server configuration read once at startup from `game.ron`; every section falls back to its defaults when the
+/// file or the section is missing
+use crate::auth::AuthConfig;
+use crate::service_discovery::PortsConfig;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// File the game server reads its configuration from
+pub const GAME_CONFIG_FILE: &str = "game.ron";
+
+/// Settings of a hosted game
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// Access tokens and the role of each
+    pub auth: AuthConfig,
+    /// Ports of the game, rendering and input servers
+    pub ports: PortsConfig,
+}
+
+impl GameConfig {
+    /// Read the configuration from a RON file; defaults when the file doesn't exist
+    pub fn load_or_default(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+}
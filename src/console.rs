@@ -0,0 +1,188 @@
+/// Developer console and the single-line text field it types into
+use crate::input::input_state::{Input, InputContext};
+use crate::input::{InputEvent, Key};
+use serde::Serialize;
+
+/// Number of lines kept in the console log
+pub const CONSOLE_LOG_LINES: usize = 50;
+
+/// Single-line text field fed by `TextInput` events while the TextEntry context is active
+#[derive(Debug, Clone, Serialize)]
+pub struct TextField {
+    pub text: String,
+    pub max_length: usize,
+}
+
+impl TextField {
+    pub fn new(max_length: usize) -> Self {
+        Self { text: String::new(), max_length }
+    }
+
+    /// Apply this frame's text entry input, consuming it; returns the line submitted with Enter
+    pub fn handle_input(&mut self, input: &mut Input) -> Option<String> {
+        let events: Vec<(usize, InputEvent)> = input.events_for(InputContext::TextEntry)
+            .into_iter()
+            .map(|(index, event)| (index, event.clone()))
+            .collect();
+
+        let mut submitted = None;
+        for (index, event) in events {
+            match event {
+                InputEvent::TextInput { text } | InputEvent::ClipboardPaste { text } => {
+                    for ch in text.chars().filter(|ch| !ch.is_control()) {
+                        if self.text.chars().count() < self.max_length {
+                            self.text.push(ch);
+                        }
+                    }
+                }
+                InputEvent::KeyPress { key: Key::Backspace } => {
+                    self.text.pop();
+                }
+                InputEvent::KeyPress { key: Key::Enter } => {
+                    submitted = Some(std::mem::take(&mut self.text));
+                }
+                InputEvent::KeyPress { .. } | InputEvent::KeyRelease { .. } => {}
+                // Pointer input is left to other systems
+                _ => continue,
+            }
+            input.consume(index);
+        }
+        submitted
+    }
+}
+
+/// In-game developer console toggled with the backquote key
+#[derive(Debug, Clone, Serialize)]
+pub struct DeveloperConsole {
+    pub open: bool,
+    pub field: TextField,
+    log: Vec<String>,
+}
+
+impl Default for DeveloperConsole {
+    fn default() -> Self {
+        Self {
+            open: false,
+            field: TextField::new(120),
+            log: Vec::new(),
+        }
+    }
+}
+
+impl DeveloperConsole {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, input: &mut Input) {
+        self.open = true;
+        input.push_context(InputContext::TextEntry);
+    }
+
+    pub fn close(&mut self, input: &mut Input) {
+        self.open = false;
+        self.field.text.clear();
+        input.remove_context(InputContext::TextEntry);
+    }
+
+    /// Handle this frame's input; returns a submitted command line
+    pub fn update(&mut self, input: &mut Input) -> Option<String> {
+        if !self.open {
+            if let Some(index) = Self::find_key_press(input, InputContext::Gameplay, &[Key::Backquote]) {
+                input.consume(index);
+                self.open(input);
+            }
+            return None;
+        }
+
+        if let Some(index) = Self::find_key_press(input, InputContext::TextEntry, &[Key::Backquote, Key::Escape]) {
+            input.consume(index);
+            self.close(input);
+            return None;
+        }
+        self.field.handle_input(input)
+    }
+
+    /// Append a line to the log, dropping the oldest lines beyond `CONSOLE_LOG_LINES`
+    pub fn print(&mut self, line: &str) {
+        self.log.push(line.to_string());
+        if self.log.len() > CONSOLE_LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+
+    pub fn log(&self) -> &[String] {
+        &self.log
+    }
+
+    fn find_key_press(input: &Input, context: InputContext, keys: &[Key]) -> Option<usize> {
+        input.events_for(context)
+            .into_iter()
+            .find(|(_, event)| matches!(event, InputEvent::KeyPress { key } if keys.contains(key)))
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> InputEvent {
+        InputEvent::TextInput { text: text.to_string() }
+    }
+
+    fn press(key: Key) -> InputEvent {
+        InputEvent::KeyPress { key }
+    }
+
+    #[test]
+    fn test_text_field_editing() {
+        let mut input = Input::new();
+        input.push_context(InputContext::TextEntry);
+        let mut field = TextField::new(5);
+
+        input.begin_frame(&[text("Cit"), text("y\u{7}!x")]);
+        assert_eq!(field.handle_input(&mut input), None);
+        assert_eq!(field.text, "City!");
+        assert!(input.events_for(InputContext::TextEntry).is_empty());
+
+        input.begin_frame(&[press(Key::Backspace), press(Key::Enter)]);
+        assert_eq!(field.handle_input(&mut input), Some("City".to_string()));
+        assert!(field.text.is_empty());
+    }
+
+    #[test]
+    fn test_console_toggle_and_submit() {
+        let mut input = Input::new();
+        let mut console = DeveloperConsole::new();
+
+        input.begin_frame(&[press(Key::Backquote)]);
+        assert_eq!(console.update(&mut input), None);
+        assert!(console.open);
+        assert_eq!(input.active_context(), InputContext::TextEntry);
+
+        // Typing does not reach gameplay
+        input.begin_frame(&[text("help"), press(Key::W), press(Key::Enter)]);
+        assert_eq!(input.movement_step(), (0, 0));
+        assert_eq!(console.update(&mut input), Some("help".to_string()));
+
+        input.begin_frame(&[press(Key::Escape)]);
+        console.update(&mut input);
+        assert!(!console.open);
+        assert_eq!(input.active_context(), InputContext::Gameplay);
+
+        for line in 0..CONSOLE_LOG_LINES + 5 {
+            console.print(&line.to_string());
+        }
+        assert_eq!(console.log().len(), CONSOLE_LOG_LINES);
+        assert_eq!(console.log()[0], "5");
+    }
+}
@@ -0,0 +1,421 @@
+/// Building construction: the buildable kinds, construction sites and the build queue
+use crate::catalog::BuildingCatalog;
+use crate::ecs::{Component, Entity, InvalidComponent, Tags, World};
+use crate::economy::{Economy, ServiceUpkeepComponent, ZoneComponent, ZoneType};
+use crate::events::{EventQueue, GameEvent};
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::notifications::Notification;
+use crate::services::{ServiceBuildingComponent, ServiceType};
+use crate::core::math::{Color, ShapeType, Vector2d};
+use crate::rendering::RenderCommand;
+use crate::floating_text::FloatingText;
+use crate::logistics::Inventory;
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+
+/// Workers available for construction even before anyone lives in the city
+pub const BASE_CONSTRUCTION_CREW: u32 = 4;
+
+/// Kinds of buildings the player can construct
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BuildingKind {
+    House,
+    Shop,
+    Factory,
+    FireStation,
+    PoliceStation,
+    Clinic,
+    School,
+    Farm,
+    FoodFactory,
+    Warehouse,
+}
+
+impl BuildingKind {
+    /// All building kinds in a stable order
+    pub fn all() -> [BuildingKind; 10] {
+        [
+            BuildingKind::House,
+            BuildingKind::Shop,
+            BuildingKind::Factory,
+            BuildingKind::FireStation,
+            BuildingKind::PoliceStation,
+            BuildingKind::Clinic,
+            BuildingKind::School,
+            BuildingKind::Farm,
+            BuildingKind::FoodFactory,
+            BuildingKind::Warehouse,
+        ]
+    }
+
+    /// Parse a building kind from its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "house" => Some(BuildingKind::House),
+            "shop" => Some(BuildingKind::Shop),
+            "factory" => Some(BuildingKind::Factory),
+            "firestation" | "fire_station" => Some(BuildingKind::FireStation),
+            "policestation" | "police_station" => Some(BuildingKind::PoliceStation),
+            "clinic" => Some(BuildingKind::Clinic),
+            "school" => Some(BuildingKind::School),
+            "farm" => Some(BuildingKind::Farm),
+            "foodfactory" | "food_factory" => Some(BuildingKind::FoodFactory),
+            "warehouse" => Some(BuildingKind::Warehouse),
+            _ => None,
+        }
+    }
+
+    /// Money paid up front when the construction site is placed, per the built-in catalog
+    pub fn cost(&self) -> i64 {
+        BuildingCatalog::builtin().get(*self).cost
+    }
+
+    /// Number of progress ticks needed to finish the building, per the built-in catalog
+    pub fn build_ticks(&self) -> u32 {
+        BuildingCatalog::builtin().get(*self).build_ticks
+    }
+
+    /// Workers the site occupies while it is being built
+    pub fn workers_required(&self) -> u32 {
+        match self {
+            BuildingKind::House | BuildingKind::Shop | BuildingKind::Farm | BuildingKind::Warehouse => 2,
+            BuildingKind::Factory | BuildingKind::FoodFactory => 3,
+            _ => 4,
+        }
+    }
+
+    /// Material cost paid from the treasury for every tick of progress
+    pub fn materials_per_tick(&self) -> i64 {
+        match self {
+            BuildingKind::House | BuildingKind::Shop | BuildingKind::Factory | BuildingKind::Farm
+                | BuildingKind::FoodFactory | BuildingKind::Warehouse => 10,
+            _ => 20,
+        }
+    }
+
+    /// Symbol used by the text grid once the building is finished
+    pub fn symbol(&self) -> char {
+        match self {
+            BuildingKind::House => 'H',
+            BuildingKind::Shop => 'S',
+            BuildingKind::Factory => 'I',
+            BuildingKind::FireStation => 'F',
+            BuildingKind::PoliceStation => 'P',
+            BuildingKind::Clinic => 'C',
+            BuildingKind::School => 'E',
+            BuildingKind::Farm => 'A',
+            BuildingKind::FoodFactory => 'K',
+            BuildingKind::Warehouse => 'W',
+        }
+    }
+
+    /// Add the components of the finished building to an entity
+    /// Returns the number of citizens that moved in
+    pub fn spawn_final(&self, world: &mut World, entity: Entity) -> Result<u32, InvalidComponent> {
+        let (zone, service) = match self {
+            BuildingKind::House => (Some((ZoneType::Residential, 4)), None),
+            BuildingKind::Shop => (Some((ZoneType::Commercial, 3)), None),
+            BuildingKind::Factory => (Some((ZoneType::Industrial, 5)), None),
+            BuildingKind::FireStation => (None, Some(ServiceType::Fire)),
+            BuildingKind::PoliceStation => (None, Some(ServiceType::Police)),
+            BuildingKind::Clinic => (None, Some(ServiceType::Health)),
+            BuildingKind::School => (None, Some(ServiceType::Education)),
+            BuildingKind::Farm => (Some((ZoneType::Industrial, 3)), None),
+            BuildingKind::FoodFactory => (Some((ZoneType::Industrial, 4)), None),
+            BuildingKind::Warehouse => (Some((ZoneType::Industrial, 2)), None),
+        };
+
+        world.add_component(entity, BuildingComponent { kind: *self })?;
+        world.add_component(entity, RenderComponent {
+            symbol: self.symbol(),
+            color: if service.is_some() { "blue" } else { "green" }.to_string(),
+        })?;
+
+        if let Some(inventory) = Inventory::for_building(*self) {
+            world.add_component(entity, inventory)?;
+        }
+
+        if let Some(service_type) = service {
+            world.add_component(entity, ServiceBuildingComponent::new(service_type, 3))?;
+            world.add_component(entity, ServiceUpkeepComponent { monthly_cost: 100 })?;
+            world.add_component(entity, Tags::new(&["service"]))?;
+        }
+
+        match zone {
+            Some((zone_type, population)) => {
+                world.add_component(entity, ZoneComponent::new(zone_type, population))?;
+                Ok(if zone_type == ZoneType::Residential { population } else { 0 })
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+/// Component for a finished building, remembering what was built
+#[derive(Clone, Debug)]
+pub struct BuildingComponent {
+    pub kind: BuildingKind,
+}
+
+impl Component for BuildingComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Component for a building site that is still being constructed
+#[derive(Clone, Debug)]
+pub struct UnderConstructionComponent {
+    pub kind: BuildingKind,
+    pub progress: u32,
+    pub required_ticks: u32,
+    /// Set while the site cannot progress, so the stall is only reported once
+    pub stalled: bool,
+}
+
+impl UnderConstructionComponent {
+    pub fn new(kind: BuildingKind) -> Self {
+        Self {
+            kind,
+            progress: 0,
+            required_ticks: kind.build_ticks(),
+            stalled: false,
+        }
+    }
+
+    /// Completion in the range 0.0..=1.0
+    pub fn fraction(&self) -> f32 {
+        if self.required_ticks == 0 {
+            1.0
+        } else {
+            (self.progress as f32 / self.required_ticks as f32).min(1.0)
+        }
+    }
+}
+
+impl Component for UnderConstructionComponent {
+    fn validate(&self) -> bool {
+        self.progress <= self.required_ticks
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// System that advances construction sites and swaps in finished buildings
+pub struct ConstructionSystem;
+
+impl ConstructionSystem {
+    /// Workers available for construction: the base crew plus all residents
+    pub fn available_workers(world: &World) -> u32 {
+        let residents: u32 = world.entities_with_components(&[TypeId::of::<ZoneComponent>()])
+            .into_iter()
+            .filter_map(|entity| world.get_component::<ZoneComponent>(entity)
+                .filter(|zone| zone.zone_type == ZoneType::Residential)
+                .map(|zone| zone.population))
+            .sum();
+        BASE_CONSTRUCTION_CREW + residents
+    }
+
+    /// Construction sites in build queue order (oldest first)
+    pub fn build_queue(world: &World) -> Vec<Entity> {
+        let mut sites = world.entities_with_components(&[TypeId::of::<UnderConstructionComponent>()]);
+        sites.sort_unstable();
+        sites
+    }
+
+    /// Advance every site that has enough workers and materials by one tick
+    /// Returns the entities that were completed this tick
+    pub fn update(
+        world: &mut World,
+        economy: &mut Economy,
+        events: &mut EventQueue<GameEvent>,
+        notifications: &mut EventQueue<Notification>,
+    ) -> Vec<Entity> {
+        let mut workers = Self::available_workers(world);
+        let mut completed = Vec::new();
+        let mut stalled = Vec::new();
+
+        for entity in Self::build_queue(world) {
+            let finished_kind = {
+                let Some(mut site) = world.get_component_mut::<UnderConstructionComponent>(entity) else { continue };
+                let workers_needed = site.kind.workers_required();
+                let materials = site.kind.materials_per_tick();
+
+                if workers < workers_needed || economy.treasury.balance < materials {
+                    if !site.stalled {
+                        site.stalled = true;
+                        let reason = if workers < workers_needed { "workers" } else { "materials" };
+                        notifications.push(
+                            Notification::warning(&format!("Construction of {:?} stalled: not enough {}", site.kind, reason))
+                                .for_entity(entity)
+                        );
+                        stalled.push((entity, reason));
+                    }
+                    continue;
+                }
+
+                workers -= workers_needed;
+                economy.treasury.balance -= materials;
+                site.stalled = false;
+                site.progress += 1;
+
+                if site.progress >= site.required_ticks { Some(site.kind) } else { None }
+            };
+
+            if let Some(kind) = finished_kind {
+                world.remove_component::<UnderConstructionComponent>(entity);
+                world.remove_component::<RenderComponent>(entity);
+                let housed = match kind.spawn_final(world, entity) {
+                    Ok(housed) => housed,
+                    Err(error) => {
+                        notifications.push(Notification::critical(&error.to_string()).for_entity(entity));
+                        0
+                    }
+                };
+
+                let tile = world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y));
+                if let Some((x, y)) = tile {
+                    notifications.push(Notification::info(&format!("{:?} completed", kind)).at_tile(x, y));
+                }
+                if housed > 0 {
+                    events.push(GameEvent::CitizensHoused { count: housed });
+                    if let Some((x, y)) = tile {
+                        let _ = FloatingText::spawn(world, &format!("+{} population", housed), Color::green(), FloatingText::above_tile(x, y));
+                    }
+                }
+                completed.push(entity);
+            }
+        }
+
+        for (entity, reason) in stalled {
+            let tile = world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y));
+            if let Some((x, y)) = tile {
+                let _ = FloatingText::spawn(world, &format!("No {}!", reason), Color::red(), FloatingText::above_tile(x, y));
+            }
+        }
+
+        completed
+    }
+
+    /// Build progress bar overlay commands for every construction site
+    pub fn progress_bar_commands(world: &World, cell_size: f32, z_order: i32) -> Vec<RenderCommand> {
+        let bar_height = cell_size * 0.15;
+        let mut commands = Vec::new();
+
+        for entity in Self::build_queue(world) {
+            if let (Some(site), Some(pos)) = (
+                world.get_component::<UnderConstructionComponent>(entity),
+                world.get_component::<GridPositionComponent>(entity),
+            ) {
+                let left = pos.x as f32 * cell_size;
+                let center_y = (pos.y as f32 + 1.0) * cell_size - bar_height / 2.0;
+                let filled = cell_size * site.fraction();
+
+                commands.extend(RenderCommand::shape(ShapeType::Rectangle { width: cell_size, height: bar_height })
+                    .at(Vector2d::new(left + cell_size / 2.0, center_y))
+                    .fill(Color::new(0.2, 0.2, 0.2, 0.8))
+                    .z_order(z_order)
+                    .build());
+                if filled > 0.0 {
+                    commands.extend(RenderCommand::shape(ShapeType::Rectangle { width: filled, height: bar_height })
+                        .at(Vector2d::new(left + filled / 2.0, center_y))
+                        .fill(Color::green())
+                        .z_order(z_order + 1)
+                        .build());
+                }
+            }
+        }
+
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorld;
+
+    fn world_with_site(kind: BuildingKind) -> (World, Entity) {
+        let mut world = World::new();
+        let site = world.create_entity();
+        world.add_component(site, GridPositionComponent { x: 2, y: 2 }).unwrap();
+        world.add_component(site, UnderConstructionComponent::new(kind)).unwrap();
+        (world, site)
+    }
+
+    #[test]
+    fn test_construction_completes_and_spawns_building() {
+        let mut test = TestWorld::new();
+        let site = test.spawn_at(2, 2, (UnderConstructionComponent::new(BuildingKind::House),));
+
+        let completed = test.tick(BuildingKind::House.build_ticks(), |test| {
+            ConstructionSystem::update(&mut test.world, &mut test.economy, &mut test.events, &mut test.notifications)
+        });
+
+        assert!(completed[..completed.len() - 1].iter().all(Vec::is_empty));
+        assert_eq!(completed.last(), Some(&vec![site]));
+        assert!(!test.world.has_component::<UnderConstructionComponent>(site));
+        assert_eq!(test.component::<ZoneComponent>(site).population, 4);
+        assert_eq!(test.component::<RenderComponent>(site).symbol, 'H');
+        assert_eq!(test.component::<BuildingComponent>(site).kind, BuildingKind::House);
+        assert_eq!(test.economy.treasury.balance, 10_000 - 5 * BuildingKind::House.materials_per_tick());
+        assert_eq!(test.take_events(), vec![GameEvent::CitizensHoused { count: 4 }]);
+        assert_eq!(test.messages().len(), 1);
+    }
+
+    #[test]
+    fn test_sites_stall_without_workers() {
+        let mut test = TestWorld::new();
+        let first = test.spawn_at(2, 2, (UnderConstructionComponent::new(BuildingKind::School),));
+        let second = test.spawn_at(3, 2, (UnderConstructionComponent::new(BuildingKind::House),));
+        test.tick(2, |test| {
+            ConstructionSystem::update(&mut test.world, &mut test.economy, &mut test.events, &mut test.notifications)
+        });
+
+        // The school uses the whole base crew, so the house waits in the queue
+        assert_eq!(test.component::<UnderConstructionComponent>(first).progress, 2);
+        assert_eq!(test.component::<UnderConstructionComponent>(second).progress, 0);
+        assert_eq!(test.messages().len(), 1);
+    }
+
+    #[test]
+    fn test_sites_stall_without_materials() {
+        let (mut world, site) = world_with_site(BuildingKind::Factory);
+        let mut economy = Economy::new(5);
+        let mut events = EventQueue::new();
+        let mut notifications = EventQueue::new();
+        ConstructionSystem::update(&mut world, &mut economy, &mut events, &mut notifications);
+
+        let site = world.get_component::<UnderConstructionComponent>(site).unwrap();
+        assert_eq!(site.progress, 0);
+        assert!(site.stalled);
+        assert_eq!(economy.treasury.balance, 5);
+    }
+
+    #[test]
+    fn test_progress_bar_commands() {
+        let (world, site) = world_with_site(BuildingKind::Shop);
+        assert_eq!(ConstructionSystem::progress_bar_commands(&world, 32.0, 10).len(), 1);
+
+        world.get_component_mut::<UnderConstructionComponent>(site).unwrap().progress = 3;
+        assert_eq!(ConstructionSystem::progress_bar_commands(&world, 32.0, 10).len(), 2);
+    }
+}
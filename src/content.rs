@@ -0,0 +1,240 @@
+/// Content packs: the base game's data directory plus mod directories, whose building catalogs, prefabs,
+/// palettes and string tables merge in load order so later packs override earlier ones
+use crate::catalog::{BuildingCatalog, BuildingDefinition};
+use crate::core::math::Color;
+use crate::prefab::{PrefabDefinition, PrefabLibrary};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of the base game's content pack
+pub const BASE_CONTENT_DIRECTORY: &str = "data";
+
+/// Directory holding one subdirectory per mod; mods load in name order after the base game
+pub const MODS_DIRECTORY: &str = "mods";
+
+/// Optional `pack.ron` naming a pack; without one the pack is named after its directory
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PackManifest {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+/// A loaded pack and the content files it provided
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContentPack {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub path: PathBuf,
+    pub files: Vec<String>,
+}
+
+/// Palettes for color blindness: zone and overlay colors a kind of color blindness confuses are swapped for
+/// colors from the Okabe-Ito set, which stay apart under it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorVision {
+    #[default]
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl ColorVision {
+    /// Replacement for a named zone or overlay color, if this palette changes it
+    pub fn substitute(self, name: &str) -> Option<Color> {
+        let (r, g, b) = match (self, name) {
+            (ColorVision::Deuteranopia | ColorVision::Protanopia, "green") => (0.0, 0.45, 0.70),
+            (ColorVision::Deuteranopia | ColorVision::Protanopia, "blue") => (0.80, 0.47, 0.65),
+            (ColorVision::Deuteranopia | ColorVision::Protanopia, "yellow") => (0.94, 0.89, 0.26),
+            (ColorVision::Deuteranopia, "red") => (0.84, 0.37, 0.0),
+            // Red looks dark to protanopes, so it brightens to orange
+            (ColorVision::Protanopia, "red") => (0.90, 0.62, 0.0),
+            (ColorVision::Tritanopia, "green") => (0.0, 0.62, 0.45),
+            (ColorVision::Tritanopia, "blue") => (0.80, 0.47, 0.65),
+            (ColorVision::Tritanopia, "yellow") => (0.84, 0.37, 0.0),
+            _ => return None,
+        };
+        Some(Color::rgb(r, g, b))
+    }
+}
+
+/// Named colors for `RenderComponent`s; names a pack doesn't define fall back to `Color::from_name`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    colors: BTreeMap<String, Color>,
+    vision: ColorVision,
+}
+
+impl Palette {
+    /// The pack's color, or the built-in one, unless the color-vision palette replaces it
+    pub fn color(&self, name: &str) -> Option<Color> {
+        self.vision.substitute(name)
+            .or_else(|| self.colors.get(name).copied())
+            .or_else(|| Color::from_name(name))
+    }
+
+    pub fn color_vision(&self) -> ColorVision {
+        self.vision
+    }
+
+    pub fn set_color_vision(&mut self, vision: ColorVision) {
+        self.vision = vision;
+    }
+}
+
+/// UI text by key, e.g. `"menu.build": "Build"`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct StringTable {
+    strings: BTreeMap<String, String>,
+}
+
+impl StringTable {
+    /// Text for a key, or the key itself when no pack defines it
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).map(|text| text.as_str()).unwrap_or(key)
+    }
+}
+
+/// Everything the active packs define, merged and checked for broken references
+#[derive(Debug, Clone, Default)]
+pub struct Content {
+    pub packs: Vec<ContentPack>,
+    pub catalog: BuildingCatalog,
+    pub prefabs: PrefabLibrary,
+    pub palette: Palette,
+    pub strings: StringTable,
+}
+
+impl Content {
+    /// The base pack followed by every mod directory in name order
+    pub fn discover(base: &Path, mods: &Path) -> Result<Self, String> {
+        let mut directories = vec![base.to_path_buf()];
+        if let Ok(entries) = fs::read_dir(mods) {
+            let mut mod_directories: Vec<PathBuf> = entries.filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect();
+            mod_directories.sort();
+            directories.extend(mod_directories);
+        }
+        Self::load(&directories)
+    }
+
+    /// Merge packs in order on top of the built-in catalog: buildings by kind, prefabs by name,
+    /// colors and strings by key, then check that every reference still resolves
+    pub fn load(directories: &[PathBuf]) -> Result<Self, String> {
+        let mut buildings: BTreeMap<String, BuildingDefinition> = BuildingCatalog::builtin().definitions().iter()
+            .map(|definition| (format!("{:?}", definition.kind), definition.clone()))
+            .collect();
+        let mut prefabs: BTreeMap<String, PrefabDefinition> = BTreeMap::new();
+        let mut colors: BTreeMap<String, (f32, f32, f32, f32)> = BTreeMap::new();
+        let mut strings = BTreeMap::new();
+        let mut packs = Vec::new();
+
+        for directory in directories {
+            let manifest: PackManifest = read_file(directory, "pack.ron")?.unwrap_or_default();
+            let mut files = Vec::new();
+            if let Some(definitions) = read_file::<Vec<BuildingDefinition>>(directory, "buildings.ron")? {
+                buildings.extend(definitions.into_iter().map(|definition| (format!("{:?}", definition.kind), definition)));
+                files.push("buildings.ron".to_string());
+            }
+            if let Some(definitions) = read_file::<Vec<PrefabDefinition>>(directory, "prefabs.ron")? {
+                prefabs.extend(definitions.into_iter().map(|definition| (definition.name.clone(), definition)));
+                files.push("prefabs.ron".to_string());
+            }
+            if let Some(palette) = read_file::<BTreeMap<String, (f32, f32, f32, f32)>>(directory, "palette.ron")? {
+                colors.extend(palette);
+                files.push("palette.ron".to_string());
+            }
+            if let Some(table) = read_file::<BTreeMap<String, String>>(directory, "strings.ron")? {
+                strings.extend(table);
+                files.push("strings.ron".to_string());
+            }
+            let directory_name = directory.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            packs.push(ContentPack {
+                name: if manifest.name.is_empty() { directory_name } else { manifest.name },
+                version: manifest.version,
+                description: manifest.description,
+                path: directory.clone(),
+                files,
+            });
+        }
+
+        let palette = Palette {
+            colors: colors.into_iter().map(|(name, (r, g, b, a))| (name, Color::new(r, g, b, a))).collect(),
+            vision: ColorVision::default(),
+        };
+        let catalog = BuildingCatalog::new(buildings.into_values().collect())?;
+        let prefabs = PrefabLibrary::resolve(prefabs.into_values().collect())?;
+        for name in prefabs.names() {
+            if let Some(color) = prefabs.get(name).and_then(|prefab| prefab.color.as_ref()) {
+                if palette.color(color).is_none() {
+                    return Err(format!("Prefab '{}' uses unknown color '{}'", name, color));
+                }
+            }
+        }
+        Ok(Self { packs, catalog, prefabs, palette, strings: StringTable { strings } })
+    }
+}
+
+// Parse a pack file, or `None` when the pack doesn't have it
+fn read_file<T: DeserializeOwned>(directory: &Path, file: &str) -> Result<Option<T>, String> {
+    let path = directory.join(file);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    ron::from_str(&text).map(Some).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::construction::BuildingKind;
+
+    #[test]
+    fn test_later_packs_override_and_references_are_checked() {
+        let root = std::env::temp_dir().join(format!("content_test_{}", std::process::id()));
+        let (base, mods) = (root.join("base"), root.join("mods"));
+        let pack = |name: &str, files: &[(&str, &str)]| {
+            let directory = mods.join(name);
+            fs::create_dir_all(&directory).unwrap();
+            for (file, text) in files {
+                fs::write(directory.join(file), text).unwrap();
+            }
+        };
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("strings.ron"), r#"{"menu.build": "Build", "menu.demolish": "Demolish"}"#).unwrap();
+        fs::write(base.join("prefabs.ron"), r#"#![enable(implicit_some)] [(name: "house", kind: House, color: "green")]"#).unwrap();
+        pack("a_cheap_houses", &[
+            ("pack.ron", r#"(name: "Cheap Houses", version: "1.0")"#),
+            ("buildings.ron", r#"[(kind: House, name: "Hut", cost: 50, build_ticks: 2, sprite: "building_hut")]"#),
+            ("palette.ron", r#"{"moss": (0.3, 0.5, 0.2, 1.0)}"#),
+        ]);
+        pack("b_overrides", &[
+            ("strings.ron", r#"{"menu.build": "Construct"}"#),
+            ("prefabs.ron", r#"#![enable(implicit_some)] [(name: "house", kind: House, color: "moss")]"#),
+        ]);
+
+        let content = Content::discover(&base, &mods).unwrap();
+        let names: Vec<&str> = content.packs.iter().map(|pack| pack.name.as_str()).collect();
+        assert_eq!(names, vec!["base", "Cheap Houses", "b_overrides"]);
+        assert_eq!(content.catalog.get(BuildingKind::House).cost, 50);
+        assert_eq!(content.catalog.get(BuildingKind::Shop).cost, 300);
+        assert_eq!(content.strings.get("menu.build"), "Construct");
+        assert_eq!(content.strings.get("menu.demolish"), "Demolish");
+        assert_eq!(content.prefabs.get("house").unwrap().color.as_deref(), Some("moss"));
+        assert_eq!(content.palette.color("moss"), Some(Color::new(0.3, 0.5, 0.2, 1.0)));
+
+        // A broken reference names what is missing
+        pack("c_broken", &[("prefabs.ron", r#"#![enable(implicit_some)] [(name: "villa", extends: "manor")]"#)]);
+        assert_eq!(Content::discover(&base, &mods).unwrap_err(), "Prefab 'villa' extends unknown prefab 'manor'");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
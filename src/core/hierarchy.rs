@@ -1,5 +1,6 @@
 use std::any::Any;
-use crate::ecs::{Component, Entity};
+use std::collections::HashMap;
+use crate::ecs::{Component, Entity, World};
 
 /// Component that manages parent-child relationships between entities
 #[derive(Debug, Clone, PartialEq)]
@@ -109,6 +110,12 @@ impl Component for HierarchyComponent {
     fn clone_box(&self) -> Box<dyn Component> {
         Box::new(self.clone())
     }
+
+    fn remap_entities(&mut self, entity_remap: &HashMap<Entity, Entity>) {
+        let remap = |entity: Entity| entity_remap.get(&entity).copied().unwrap_or(entity);
+        self.parent = self.parent.map(remap);
+        self.children = self.children.iter().map(|&child| remap(child)).collect();
+    }
 }
 
 impl Default for HierarchyComponent {
@@ -117,6 +124,137 @@ impl Default for HierarchyComponent {
     }
 }
 
+/// Change to parent/child links, so transform propagation and spatial systems can update their caches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HierarchyEvent {
+    /// An entity moved under a new parent, or was detached when `new_parent` is None
+    ParentChanged { child: Entity, old_parent: Option<Entity>, new_parent: Option<Entity> },
+    /// An entity was destroyed together with its parent
+    Despawned { entity: Entity },
+}
+
+/// Iterator from an entity's parent up to the root of its tree
+pub struct Ancestors<'w> {
+    world: &'w World,
+    next: Option<Entity>,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Entity> {
+        let current = self.next?;
+        self.next = Hierarchy::parent(self.world, current);
+        Some(current)
+    }
+}
+
+/// Hierarchy operations on a world that keep both sides of every parent/child link in sync
+pub struct Hierarchy;
+
+impl Hierarchy {
+    /// Parent of an entity, if it has one
+    pub fn parent(world: &World, entity: Entity) -> Option<Entity> {
+        world.get_component::<HierarchyComponent>(entity).and_then(|hierarchy| hierarchy.parent())
+    }
+
+    /// Direct children of an entity, in insertion order
+    pub fn children(world: &World, entity: Entity) -> impl Iterator<Item = Entity> {
+        world.get_component::<HierarchyComponent>(entity)
+            .map(|hierarchy| hierarchy.children().to_vec())
+            .unwrap_or_default()
+            .into_iter()
+    }
+
+    /// Ancestors of an entity, nearest first
+    pub fn ancestors(world: &World, entity: Entity) -> Ancestors<'_> {
+        Ancestors { world, next: Self::parent(world, entity) }
+    }
+
+    /// All entities below an entity, parents before their children
+    pub fn descendants(world: &World, entity: Entity) -> Vec<Entity> {
+        let mut descendants = Vec::new();
+        let mut stack: Vec<Entity> = Self::children(world, entity).collect();
+        stack.reverse();
+        while let Some(current) = stack.pop() {
+            descendants.push(current);
+            let first_child = stack.len();
+            stack.extend(Self::children(world, current));
+            stack[first_child..].reverse();
+        }
+        descendants
+    }
+
+    /// Move `child` under `new_parent`, or detach it with None
+    /// Fails if either entity does not exist or the move would make an entity its own ancestor
+    pub fn set_parent(
+        world: &mut World,
+        child: Entity,
+        new_parent: Option<Entity>,
+        events: &mut Vec<HierarchyEvent>,
+    ) -> Result<(), String> {
+        if !world.get_all_entities().contains(&child) {
+            return Err(format!("Entity {} does not exist", child));
+        }
+        if let Some(parent) = new_parent {
+            if !world.get_all_entities().contains(&parent) {
+                return Err(format!("Parent entity {} does not exist", parent));
+            }
+            if parent == child || Self::ancestors(world, parent).any(|ancestor| ancestor == child) {
+                return Err(format!("Parenting entity {} to {} would create a cycle", child, parent));
+            }
+        }
+
+        let old_parent = Self::parent(world, child);
+        if old_parent == new_parent {
+            return Ok(());
+        }
+        if let Some(old_parent) = old_parent {
+            if let Some(mut hierarchy) = world.get_component_mut::<HierarchyComponent>(old_parent) {
+                hierarchy.remove_child(child);
+            }
+        }
+        if let Some(parent) = new_parent {
+            if !world.has_component::<HierarchyComponent>(parent) {
+                world.add_component(parent, HierarchyComponent::new()).map_err(|e| e.to_string())?;
+            }
+            if let Some(mut hierarchy) = world.get_component_mut::<HierarchyComponent>(parent) {
+                hierarchy.add_child(child);
+            }
+        }
+        if !world.has_component::<HierarchyComponent>(child) {
+            world.add_component(child, HierarchyComponent::new()).map_err(|e| e.to_string())?;
+        }
+        if let Some(mut hierarchy) = world.get_component_mut::<HierarchyComponent>(child) {
+            hierarchy.set_parent(new_parent);
+        }
+
+        events.push(HierarchyEvent::ParentChanged { child, old_parent, new_parent });
+        Ok(())
+    }
+
+    /// Destroy an entity and everything below it, e.g. a ferry and the vehicles it carries
+    /// Returns the destroyed entities, the given entity first
+    pub fn despawn_recursive(world: &mut World, entity: Entity, events: &mut Vec<HierarchyEvent>) -> Vec<Entity> {
+        if !world.get_all_entities().contains(&entity) {
+            return Vec::new();
+        }
+        if let Some(parent) = Self::parent(world, entity) {
+            if let Some(mut hierarchy) = world.get_component_mut::<HierarchyComponent>(parent) {
+                hierarchy.remove_child(entity);
+            }
+        }
+
+        let mut despawned = vec![entity];
+        despawned.extend(Self::descendants(world, entity));
+        for &despawned_entity in &despawned {
+            world.destroy_entity(despawned_entity);
+            events.push(HierarchyEvent::Despawned { entity: despawned_entity });
+        }
+        despawned
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,4 +382,66 @@ mod tests {
         let downcast = cloned.as_any().downcast_ref::<HierarchyComponent>().unwrap();
         assert_eq!(downcast, &hierarchy);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_set_parent_and_iteration() {
+        let mut world = World::new();
+        let mut events = Vec::new();
+        let ferry = world.create_entity();
+        let truck = world.create_entity();
+        let crate_entity = world.create_entity();
+        let car = world.create_entity();
+
+        Hierarchy::set_parent(&mut world, truck, Some(ferry), &mut events).unwrap();
+        Hierarchy::set_parent(&mut world, crate_entity, Some(truck), &mut events).unwrap();
+        Hierarchy::set_parent(&mut world, car, Some(ferry), &mut events).unwrap();
+        assert_eq!(Hierarchy::children(&world, ferry).collect::<Vec<_>>(), vec![truck, car]);
+        assert_eq!(Hierarchy::ancestors(&world, crate_entity).collect::<Vec<_>>(), vec![truck, ferry]);
+        assert_eq!(Hierarchy::descendants(&world, ferry), vec![truck, crate_entity, car]);
+
+        // A parent can't move under its own descendant
+        assert!(Hierarchy::set_parent(&mut world, ferry, Some(crate_entity), &mut events).is_err());
+        assert!(Hierarchy::set_parent(&mut world, ferry, Some(ferry), &mut events).is_err());
+
+        // Reparenting unlinks the old parent and reports the move
+        events.clear();
+        Hierarchy::set_parent(&mut world, car, Some(truck), &mut events).unwrap();
+        assert_eq!(Hierarchy::children(&world, ferry).collect::<Vec<_>>(), vec![truck]);
+        assert_eq!(events, vec![HierarchyEvent::ParentChanged { child: car, old_parent: Some(ferry), new_parent: Some(truck) }]);
+
+        Hierarchy::set_parent(&mut world, car, None, &mut events).unwrap();
+        assert_eq!(Hierarchy::parent(&world, car), None);
+        assert_eq!(Hierarchy::ancestors(&world, car).count(), 0);
+    }
+
+    #[test]
+    fn test_despawn_recursive() {
+        let mut world = World::new();
+        let mut events = Vec::new();
+        let root = world.create_entity();
+        let ferry = world.create_entity();
+        let truck = world.create_entity();
+        let bystander = world.create_entity();
+        Hierarchy::set_parent(&mut world, ferry, Some(root), &mut events).unwrap();
+        Hierarchy::set_parent(&mut world, truck, Some(ferry), &mut events).unwrap();
+        events.clear();
+
+        assert_eq!(Hierarchy::despawn_recursive(&mut world, ferry, &mut events), vec![ferry, truck]);
+        assert_eq!(world.get_all_entities(), &vec![root, bystander]);
+        assert_eq!(Hierarchy::children(&world, root).count(), 0);
+        assert_eq!(events.len(), 2);
+        assert!(Hierarchy::despawn_recursive(&mut world, ferry, &mut events).is_empty());
+    }
+
+    #[test]
+    fn test_remap_entities() {
+        let mut hierarchy = HierarchyComponent::with_parent(1);
+        hierarchy.add_child(2);
+        hierarchy.add_child(3);
+
+        // Entity 3 is outside the merged scene and keeps its ID
+        hierarchy.remap_entities(&HashMap::from([(1, 10), (2, 11)]));
+        assert_eq!(hierarchy.parent(), Some(10));
+        assert_eq!(hierarchy.children(), &[11, 3]);
+    }
+}
@@ -2,6 +2,7 @@ use std::any::Any;
 use std::collections::HashMap;
 use crate::ecs::Component;
 use super::super::input::{Key, MouseButton, InputEvent};
+use super::super::input::input_state::{KeyChord, Modifiers};
 use crate::core::math::Vector2d;
 use serde::{Deserialize, Serialize};
 
@@ -120,6 +121,27 @@ impl InputComponent {
         self.mouse_button_states.get(button).map_or(false, |state| state.is_just_released())
     }
 
+    /// State of a key chord from its keys' states: just pressed when the last key went down this frame,
+    /// just released when one of its keys was let go; a held modifier the chord doesn't use keeps it released
+    pub fn chord_state(&self, chord: &KeyChord) -> ButtonState {
+        let state = |key: &Key| self.key_states.get(key).copied().unwrap_or(ButtonState::Released);
+        let held: std::collections::HashSet<Key> = [Key::Control, Key::Shift, Key::Alt].into_iter()
+            .filter(|key| state(key).is_down())
+            .collect();
+        let states: Vec<ButtonState> = chord.all_keys().iter().map(state).collect();
+        if chord.matches(|key| state(key).is_down(), Modifiers::held(&held)) {
+            if states.iter().any(ButtonState::is_just_pressed) {
+                ButtonState::JustPressed
+            } else {
+                ButtonState::Held
+            }
+        } else if states.iter().all(|state| state.is_down() || state.is_just_released()) && states.iter().any(ButtonState::is_just_released) {
+            ButtonState::JustReleased
+        } else {
+            ButtonState::Released
+        }
+    }
+
     /// Get the current mouse position
     pub fn get_mouse_position(&self) -> Vector2d {
         self.mouse_position
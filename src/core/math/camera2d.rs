@@ -1,6 +1,9 @@
 use std::any::Any;
 use crate::ecs::Component;
+use std::collections::BTreeMap;
 use super::{vector2d::Vector2d, angle2d::Angle2d, transform2d::Transform2d};
+use super::draw_order::{DrawSortKey, LayerSort};
+use super::projection::Projection;
 
 /// Camera2d component that defines the view transformation for 2D rendering
 /// Position and rotation are now handled by the Transform2dComponent
@@ -12,6 +15,12 @@ pub struct Camera2d {
     /// View bounds for culling (in camera space)
     view_width: f32,
     view_height: f32,
+    /// Physical pixels per view unit (CSS pixel) on the client display
+    device_pixel_ratio: f32,
+    /// Sorting of layers that don't use plain z-order
+    layer_sorts: BTreeMap<i32, LayerSort>,
+    /// How the tile grid is laid out on screen
+    projection: Projection,
 }
 
 #[allow(dead_code)] // Core component implementation for 2D camera system
@@ -22,6 +31,9 @@ impl Camera2d {
             scale: 1.0,
             view_width: 1920.0,  // Default screen width
             view_height: 1080.0, // Default screen height
+            device_pixel_ratio: 1.0,
+            layer_sorts: BTreeMap::new(),
+            projection: Projection::TopDown,
         }
     }
 
@@ -31,6 +43,9 @@ impl Camera2d {
             scale,
             view_width: 1920.0,
             view_height: 1080.0,
+            device_pixel_ratio: 1.0,
+            layer_sorts: BTreeMap::new(),
+            projection: Projection::TopDown,
         }
     }
 
@@ -44,6 +59,31 @@ impl Camera2d {
         self.scale = scale.max(0.001); // Prevent zero or negative scale
     }
 
+    /// How draws on a layer are ordered; isometric views y-sort unless told otherwise, so nearer tiles cover farther ones
+    pub fn layer_sort(&self, layer: i32) -> LayerSort {
+        self.layer_sorts.get(&layer).copied().unwrap_or(match self.projection {
+            Projection::TopDown => LayerSort::ZOrder,
+            Projection::Isometric => LayerSort::YSort,
+        })
+    }
+
+    pub fn set_layer_sort(&mut self, layer: i32, sort: LayerSort) {
+        self.layer_sorts.insert(layer, sort);
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Sort draws back to front by their keys, each layer the way this camera is configured for it
+    pub fn sort_draws<T>(&self, draws: &mut [(DrawSortKey, T)]) {
+        draws.sort_by(|(a, _), (b, _)| a.compare(b, self.layer_sort(a.layer)));
+    }
+
     /// Gets the view dimensions
     pub fn view_dimensions(&self) -> (f32, f32) {
         (self.view_width, self.view_height)
@@ -55,6 +95,49 @@ impl Camera2d {
         self.view_height = height;
     }
 
+    /// Gets the device pixel ratio of the client display
+    pub fn device_pixel_ratio(&self) -> f32 {
+        self.device_pixel_ratio
+    }
+
+    /// Sets the client viewport size (in CSS pixels) and its device pixel ratio
+    pub fn set_viewport(&mut self, width: f32, height: f32, device_pixel_ratio: f32) {
+        self.set_view_dimensions(width, height);
+        self.device_pixel_ratio = if device_pixel_ratio.is_finite() && device_pixel_ratio > 0.0 {
+            device_pixel_ratio
+        } else {
+            1.0
+        };
+    }
+
+    /// Size of the client backing store in physical pixels
+    pub fn backing_size(&self) -> (u32, u32) {
+        (
+            (self.view_width * self.device_pixel_ratio).round() as u32,
+            (self.view_height * self.device_pixel_ratio).round() as u32,
+        )
+    }
+
+    /// Largest scale at which content of the given size fits the view
+    pub fn fit_scale(&self, content_width: f32, content_height: f32) -> f32 {
+        if content_width <= 0.0 || content_height <= 0.0 {
+            return self.scale;
+        }
+        (self.view_width / content_width).min(self.view_height / content_height).max(0.001)
+    }
+
+    /// Transform from content space to physical screen pixels: scaled by the camera
+    /// and the device pixel ratio, and centered in the view
+    pub fn screen_transform(&self, content_width: f32, content_height: f32) -> Transform2d {
+        let pixels = self.scale * self.device_pixel_ratio;
+        let (backing_width, backing_height) = self.backing_size();
+        let offset = Vector2d::new(
+            (backing_width as f32 - content_width * pixels) / 2.0,
+            (backing_height as f32 - content_height * pixels) / 2.0,
+        );
+        Transform2d::translation(offset) * Transform2d::scale(pixels)
+    }
+
     /// Zooms the camera by the given factor
     pub fn zoom(&mut self, factor: f32) {
         self.scale *= factor;
@@ -138,7 +221,8 @@ impl Component for Camera2d {
         // Check that all values are finite and scale is positive
         self.scale.is_finite() && self.scale > 0.0 &&
         self.view_width.is_finite() && self.view_width > 0.0 &&
-        self.view_height.is_finite() && self.view_height > 0.0
+        self.view_height.is_finite() && self.view_height > 0.0 &&
+        self.device_pixel_ratio.is_finite() && self.device_pixel_ratio > 0.0
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -270,7 +354,31 @@ mod tests {
             scale: f32::NAN,
             view_width: 100.0,
             view_height: 100.0,
+            device_pixel_ratio: 1.0,
+            layer_sorts: BTreeMap::new(),
+            projection: Projection::TopDown,
         };
         assert!(!invalid_camera.validate());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_viewport_and_screen_transform() {
+        let mut camera = Camera2d::new();
+        camera.set_viewport(800.0, 400.0, 2.0);
+        assert_eq!(camera.view_dimensions(), (800.0, 400.0));
+        assert_eq!(camera.backing_size(), (1600, 800));
+
+        // A 10x8 grid of 32px cells is limited by the height
+        let scale = camera.fit_scale(320.0, 256.0);
+        assert!(approx_eq(scale, 400.0 / 256.0));
+        camera.set_scale(scale);
+
+        // Content is centered in physical pixels
+        let transform = camera.screen_transform(320.0, 256.0);
+        assert!(vector_approx_eq(transform.transform_point(Vector2d::zero()), Vector2d::new(300.0, 0.0)));
+        assert!(vector_approx_eq(transform.transform_point(Vector2d::new(320.0, 256.0)), Vector2d::new(1300.0, 800.0)));
+
+        camera.set_viewport(800.0, 400.0, 0.0);
+        assert_eq!(camera.device_pixel_ratio(), 1.0);
+    }
+}
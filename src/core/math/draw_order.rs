@@ -0,0 +1,87 @@
+use std::any::Any;
+use std::cmp::Ordering;
+use crate::ecs::{Component, Entity};
+
+/// Layer of tiles and other ground cover
+#[allow(dead_code)]
+pub const GROUND_LAYER: i32 = 0;
+/// Layer of buildings, citizens and players unless a `RenderLayer` says otherwise
+#[allow(dead_code)]
+pub const OBJECT_LAYER: i32 = 1;
+/// Layer of effects and markers drawn above everything in the world
+#[allow(dead_code)]
+pub const OVERLAY_LAYER: i32 = 2;
+
+/// How draws within one layer are ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)] // Chosen per layer through Camera2d
+pub enum LayerSort {
+    /// By z-order alone
+    #[default]
+    ZOrder,
+    /// Lower on screen draws later, for isometric-style depth; z-order only breaks ties
+    YSort,
+}
+
+/// Everything a draw is ordered by; the entity ID makes the order total, so equal draws never swap between frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawSortKey {
+    pub layer: i32,
+    pub z_order: i32,
+    /// Screen-space depth used by y-sorted layers
+    pub y: f32,
+    pub entity: Entity,
+}
+
+#[allow(dead_code)]
+impl DrawSortKey {
+    /// Compare by (layer, y when y-sorting, z-order, entity)
+    pub fn compare(&self, other: &Self, sort: LayerSort) -> Ordering {
+        self.layer.cmp(&other.layer)
+            .then_with(|| match sort {
+                LayerSort::YSort => self.y.total_cmp(&other.y),
+                LayerSort::ZOrder => Ordering::Equal,
+            })
+            .then_with(|| self.z_order.cmp(&other.z_order))
+            .then_with(|| self.entity.cmp(&other.entity))
+    }
+}
+
+/// Component placing an entity's draws on a layer other than `OBJECT_LAYER`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderLayer(pub i32);
+
+impl Component for RenderLayer {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(layer: i32, z_order: i32, y: f32, entity: Entity) -> DrawSortKey {
+        DrawSortKey { layer, z_order, y, entity }
+    }
+
+    #[test]
+    fn test_order_is_total_and_layered() {
+        let a = key(OBJECT_LAYER, 1, 50.0, 3);
+        let b = key(OBJECT_LAYER, 1, 10.0, 7);
+        let overlay = key(OVERLAY_LAYER, 0, 0.0, 1);
+        assert_eq!(a.compare(&b, LayerSort::ZOrder), Ordering::Less);
+        assert_eq!(b.compare(&a, LayerSort::ZOrder), Ordering::Greater);
+        assert_eq!(a.compare(&b, LayerSort::YSort), Ordering::Greater);
+        assert_eq!(overlay.compare(&a, LayerSort::YSort), Ordering::Greater);
+        assert_eq!(a.compare(&a, LayerSort::ZOrder), Ordering::Equal);
+    }
+}
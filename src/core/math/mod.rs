@@ -5,6 +5,8 @@ pub mod transform2d_component;
 pub mod camera2d;
 pub mod sprite2d;
 pub mod shape2d;
+pub mod draw_order;
+pub mod projection;
 
 // Only re-export commonly used types - others can be imported directly
 pub use vector2d::Vector2d;
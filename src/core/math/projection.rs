@@ -0,0 +1,105 @@
+use super::vector2d::Vector2d;
+
+/// How grid tiles are laid out in world space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)] // Chosen on Camera2d
+pub enum Projection {
+    /// Square tiles seen from straight above
+    #[default]
+    TopDown,
+    /// 2:1 dimetric diamonds `2 * cell_size` wide and `cell_size` high, the classic isometric look
+    Isometric,
+}
+
+#[allow(dead_code)]
+impl Projection {
+    /// World position of a point in tile coordinates; whole numbers are tile corners
+    /// Isometric tile (0, 0) has its top corner at the origin, so columns run down-right and rows down-left
+    pub fn tile_to_world(self, tile: Vector2d, cell_size: f32) -> Vector2d {
+        match self {
+            Projection::TopDown => tile * cell_size,
+            Projection::Isometric => Vector2d::new(
+                (tile.x - tile.y) * cell_size,
+                (tile.x + tile.y) * cell_size / 2.0,
+            ),
+        }
+    }
+
+    /// Inverse of `tile_to_world`
+    pub fn world_to_tile(self, world: Vector2d, cell_size: f32) -> Vector2d {
+        match self {
+            Projection::TopDown => world / cell_size,
+            Projection::Isometric => {
+                let (across, down) = (world.x / cell_size, 2.0 * world.y / cell_size);
+                Vector2d::new((down + across) / 2.0, (down - across) / 2.0)
+            }
+        }
+    }
+
+    /// Size of one tile's image: the square, or the bounding box of the diamond
+    pub fn tile_size(self, cell_size: f32) -> Vector2d {
+        match self {
+            Projection::TopDown => Vector2d::new(cell_size, cell_size),
+            Projection::Isometric => Vector2d::new(2.0 * cell_size, cell_size),
+        }
+    }
+
+    /// Top-left corner of a tilemap cell's image, for tiles of `tile_size` (see `tile_size`)
+    pub fn cell_origin(self, column: u32, row: u32, tile_size: Vector2d) -> Vector2d {
+        let (column, row) = (column as f32, row as f32);
+        match self {
+            Projection::TopDown => Vector2d::new(column * tile_size.x, row * tile_size.y),
+            Projection::Isometric => Vector2d::new(
+                (column - row - 1.0) * tile_size.x / 2.0,
+                (column + row) * tile_size.y / 2.0,
+            ),
+        }
+    }
+
+    /// Top-left corner and size of the box around a grid of `columns` x `rows` tiles
+    pub fn grid_bounds(self, columns: u32, rows: u32, cell_size: f32) -> (Vector2d, Vector2d) {
+        let (columns, rows) = (columns as f32, rows as f32);
+        match self {
+            Projection::TopDown => (Vector2d::zero(), Vector2d::new(columns, rows) * cell_size),
+            Projection::Isometric => (
+                Vector2d::new(-rows * cell_size, 0.0),
+                Vector2d::new((columns + rows) * cell_size, (columns + rows) * cell_size / 2.0),
+            ),
+        }
+    }
+
+    /// Atlas to draw tiles from; isometric atlases hold diamond tiles and are registered
+    /// under the top-down atlas's ID with an `_iso` suffix
+    pub fn atlas_id(self, atlas_id: &str) -> String {
+        match self {
+            Projection::TopDown => atlas_id.to_string(),
+            Projection::Isometric => format!("{}_iso", atlas_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isometric_round_trip_and_layout() {
+        let iso = Projection::Isometric;
+        assert_eq!(iso.tile_to_world(Vector2d::new(1.0, 0.0), 32.0), Vector2d::new(32.0, 16.0));
+        assert_eq!(iso.tile_to_world(Vector2d::new(0.0, 1.0), 32.0), Vector2d::new(-32.0, 16.0));
+        for tile in [Vector2d::new(3.5, 1.25), Vector2d::new(0.0, 7.0), Vector2d::new(9.0, 0.5)] {
+            assert_eq!(iso.world_to_tile(iso.tile_to_world(tile, 32.0), 32.0), tile);
+            assert_eq!(Projection::TopDown.world_to_tile(Projection::TopDown.tile_to_world(tile, 32.0), 32.0), tile);
+        }
+
+        // A cell's image spans the diamond around its tile
+        let size = iso.tile_size(32.0);
+        let top_corner = iso.tile_to_world(Vector2d::new(2.0, 1.0), 32.0);
+        assert_eq!(iso.cell_origin(2, 1, size), top_corner - Vector2d::new(32.0, 0.0));
+
+        let (min, size) = iso.grid_bounds(10, 8, 32.0);
+        assert_eq!(min.x, iso.tile_to_world(Vector2d::new(0.0, 8.0), 32.0).x);
+        assert_eq!(min.y + size.y, iso.tile_to_world(Vector2d::new(10.0, 8.0), 32.0).y);
+        assert_eq!(iso.atlas_id("road"), "road_iso");
+    }
+}
@@ -277,26 +277,13 @@ impl Component for Shape2d {
 
         // Validate fill
         let fill_valid = match &self.fill {
-            FillStyle::Solid(color) => {
-                color.r.is_finite() && color.g.is_finite() &&
-                color.b.is_finite() && color.a.is_finite() &&
-                color.r >= 0.0 && color.r <= 1.0 &&
-                color.g >= 0.0 && color.g <= 1.0 &&
-                color.b >= 0.0 && color.b <= 1.0 &&
-                color.a >= 0.0 && color.a <= 1.0
-            },
+            FillStyle::Solid(color) => color.is_valid(),
             FillStyle::None => true,
         };
 
         // Validate stroke
         let stroke_valid = if let Some(stroke) = &self.stroke {
-            stroke.color.r.is_finite() && stroke.color.g.is_finite() &&
-            stroke.color.b.is_finite() && stroke.color.a.is_finite() &&
-            stroke.color.r >= 0.0 && stroke.color.r <= 1.0 &&
-            stroke.color.g >= 0.0 && stroke.color.g <= 1.0 &&
-            stroke.color.b >= 0.0 && stroke.color.b <= 1.0 &&
-            stroke.color.a >= 0.0 && stroke.color.a <= 1.0 &&
-            stroke.width.is_finite() && stroke.width > 0.0
+            stroke.color.is_valid() && stroke.width.is_finite() && stroke.width > 0.0
         } else {
             true
         };
@@ -57,6 +57,28 @@ impl Color {
         Self::new(0.0, 0.0, 0.0, 0.0)
     }
 
+    /// Parses one of the color names used by render components, e.g. "brown" or "cyan"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "white" => Some(Self::white()),
+            "black" => Some(Self::black()),
+            "red" => Some(Self::red()),
+            "green" => Some(Self::green()),
+            "blue" => Some(Self::blue()),
+            "yellow" => Some(Self::yellow()),
+            "cyan" => Some(Self::rgb(0.0, 1.0, 1.0)),
+            "orange" => Some(Self::rgb(1.0, 0.65, 0.0)),
+            "brown" => Some(Self::rgb(0.65, 0.16, 0.16)),
+            "gray" | "grey" => Some(Self::rgb(0.5, 0.5, 0.5)),
+            _ => None,
+        }
+    }
+
+    /// Whether every channel is a finite value from 0.0 to 1.0
+    pub fn is_valid(&self) -> bool {
+        [self.r, self.g, self.b, self.a].iter().all(|channel| (0.0..=1.0).contains(channel))
+    }
+
     /// Converts to RGBA tuple
     pub fn as_tuple(&self) -> (f32, f32, f32, f32) {
         (self.r, self.g, self.b, self.a)
@@ -184,12 +206,7 @@ impl Component for Sprite2d {
         self.size.x.is_finite() && self.size.y.is_finite() &&
         self.size.x > 0.0 && self.size.y > 0.0 &&
         // Check that color values are finite and in valid range
-        self.color.r.is_finite() && self.color.g.is_finite() &&
-        self.color.b.is_finite() && self.color.a.is_finite() &&
-        self.color.r >= 0.0 && self.color.r <= 1.0 &&
-        self.color.g >= 0.0 && self.color.g <= 1.0 &&
-        self.color.b >= 0.0 && self.color.b <= 1.0 &&
-        self.color.a >= 0.0 && self.color.a <= 1.0 &&
+        self.color.is_valid() &&
         // Check UV coordinates are finite
         self.uv_rect.0.x.is_finite() && self.uv_rect.0.y.is_finite() &&
         self.uv_rect.1.x.is_finite() && self.uv_rect.1.y.is_finite()
@@ -235,6 +252,10 @@ mod tests {
 
         let red = Color::red();
         assert_eq!(red.as_tuple(), (1.0, 0.0, 0.0, 1.0));
+
+        assert_eq!(Color::from_name("Red"), Some(red));
+        assert_eq!(Color::from_name("grey"), Color::from_name("gray"));
+        assert_eq!(Color::from_name("chartreuse"), None);
     }
 
     #[test]
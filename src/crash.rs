@@ -0,0 +1,272 @@
+/// Crash recovery: a panic hook remembers where the simulation panicked, and a guarded update writes the world
+/// state, the last recorded ticks and the tail of the action log to a crash bundle directory, which
+/// `cargo run replay <bundle>` plays back, checking it against the recorded ticks, to reproduce the panic
+use crate::action_log::{ActionLog, ActionRecord};
+use crate::content::Content;
+use crate::debug_tracker::{StateDiff, WorldState};
+use crate::grid_game_systems::GridGameWorld;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory crash bundles are written to
+pub const CRASH_DIRECTORY: &str = "crashes";
+/// Recorded ticks kept in a bundle
+pub const CRASH_FRAMES: usize = 60;
+/// Player commands kept in a bundle
+pub const CRASH_ACTIONS: usize = 500;
+/// File in a bundle describing the crash; the world state, recorded ticks and actions sit next to it
+pub const CRASH_FILE: &str = "crash.ron";
+
+static INSTALL_HOOK: Once = Once::new();
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Remember the message and location of every panic for crash bundles, then report it as before
+/// Installing more than once keeps the first hook
+pub fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let location = info.location().map(|location| format!(" at {}:{}", location.file(), location.line())).unwrap_or_default();
+            if let Ok(mut last) = LAST_PANIC.lock() {
+                *last = Some(format!("{}{}", panic_text(info.payload()), location));
+            }
+            previous(info);
+        }));
+    });
+}
+
+// Message of a panic payload, which is a `&str` or a `String` for `panic!` with or without arguments
+fn panic_text(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|text| text.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// One tick of play kept for a crash bundle: only what is cheap enough to record on every served tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickRecord {
+    pub tick: u64,
+    /// Seconds the tick advanced the simulation
+    pub delta_seconds: f32,
+    /// Length of the action log when the tick ended
+    pub actions_logged: usize,
+    /// State hash the tick ended with
+    pub state_hash: u64,
+}
+
+/// What a bundle says about the crash and how to run the session up to it again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Panic message and location
+    pub message: String,
+    /// Tick the panic happened in
+    pub tick: u64,
+    /// Seed of the session's stable IDs, so replayed entities get the same IDs
+    pub stable_id_seed: u64,
+    /// Seconds the last update advanced, which the replay steps every tick by
+    pub delta_seconds: f32,
+    /// Older player commands not kept in the bundle; the replay is only faithful when this is 0
+    pub actions_dropped: usize,
+}
+
+/// Everything written to a crash bundle
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashBundle {
+    pub report: CrashReport,
+    pub state: WorldState,
+    pub frames: Vec<TickRecord>,
+    pub actions: Vec<ActionRecord>,
+}
+
+impl CrashBundle {
+    /// Capture a game as a panic left it
+    pub fn capture(game: &GridGameWorld, message: &str) -> Self {
+        let records = game.actions.records();
+        let actions_dropped = records.len().saturating_sub(CRASH_ACTIONS);
+        // Games recording crash frames keep every tick; the others only have the ticks stepped while paused
+        let mut frames: Vec<TickRecord> = game.crash_frames().cloned().collect();
+        if frames.is_empty() {
+            frames = game.debug_tracker.frames()
+                .map(|frame| TickRecord {
+                    tick: frame.tick,
+                    delta_seconds: frame.delta_seconds,
+                    actions_logged: game.debug_tracker.actions_logged(frame.tick).unwrap_or(records.len()),
+                    state_hash: frame.final_hash(),
+                })
+                .collect();
+        }
+        Self {
+            report: CrashReport {
+                message: message.to_string(),
+                tick: game.tick,
+                stable_id_seed: game.stable_id_seed,
+                delta_seconds: game.delta_seconds(),
+                actions_dropped,
+            },
+            state: WorldState::capture(&game.world),
+            frames: frames[frames.len().saturating_sub(CRASH_FRAMES)..].to_vec(),
+            actions: records[actions_dropped..].to_vec(),
+        }
+    }
+
+    /// Write the bundle to a new directory inside `directory`; returns the bundle's directory
+    pub fn write(&self, directory: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let bundle = directory.join(format!("crash-{}-tick{}", seconds, self.report.tick));
+        fs::create_dir_all(&bundle)?;
+        let pretty = ron::ser::PrettyConfig::default();
+        let report_path = bundle.join(CRASH_FILE);
+        fs::write(&report_path, ron::ser::to_string_pretty(&self.report, pretty.clone())?)?;
+        fs::write(bundle.join("world.ron"), ron::ser::to_string_pretty(&self.state, pretty.clone())?)?;
+        fs::write(bundle.join("frames.ron"), ron::ser::to_string_pretty(&self.frames, pretty)?)?;
+        fs::write(ActionLog::path_for_save(&report_path), ActionLog::to_jsonl(&self.actions)?)?;
+        Ok(bundle)
+    }
+
+    /// Read a bundle written by `write`
+    pub fn load(bundle: &Path) -> Result<Self, Box<dyn Error>> {
+        let report_path = bundle.join(CRASH_FILE);
+        Ok(Self {
+            report: ron::from_str(&fs::read_to_string(&report_path)?)?,
+            state: ron::from_str(&fs::read_to_string(bundle.join("world.ron"))?)?,
+            frames: ron::from_str(&fs::read_to_string(bundle.join("frames.ron"))?)?,
+            actions: ActionLog::load_alongside(&report_path)?.records().to_vec(),
+        })
+    }
+}
+
+/// Whether a path is a crash bundle directory
+pub fn is_bundle(path: &Path) -> bool {
+    path.join(CRASH_FILE).is_file()
+}
+
+/// Run part of the simulation; if it panics, write a crash bundle to `directory`, print how to replay it and
+/// carry on panicking
+pub fn guard<T>(game: &mut GridGameWorld, directory: &Path, run: impl FnOnce(&mut GridGameWorld) -> T) -> T {
+    let payload = match panic::catch_unwind(AssertUnwindSafe(|| run(&mut *game))) {
+        Ok(value) => return value,
+        Err(payload) => payload,
+    };
+    let message = LAST_PANIC.lock().ok().and_then(|mut last| last.take()).unwrap_or_else(|| panic_text(payload.as_ref()));
+    let crash = CrashBundle::capture(game, &message);
+    match crash.write(directory) {
+        Ok(bundle) => {
+            eprintln!(
+                "💥 The simulation panicked in tick {}: {}\nA crash bundle was written to {}\nReplay it with: cargo run replay {}",
+                game.tick, message, bundle.display(), bundle.display()
+            );
+            if crash.report.actions_dropped > 0 {
+                eprintln!(
+                    "⚠️ The bundle keeps the last {} player commands; the first {} were dropped, so its replay may differ",
+                    CRASH_ACTIONS, crash.report.actions_dropped
+                );
+            }
+        }
+        Err(e) => eprintln!("💥 The simulation panicked in tick {} and the crash bundle could not be written: {}", game.tick, e),
+    }
+    panic::resume_unwind(payload)
+}
+
+/// How a replayed bundle ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayOutcome {
+    /// The replay panicked in the given tick with the given message
+    Reproduced { tick: u64, message: String },
+    /// The replay reached the crash tick without panicking; `diff` is how its world differs from the one the
+    /// panic left, which is empty when the panic came after the tick's systems had all run
+    Survived { tick: u64, diff: StateDiff },
+    /// The replayed world ended a recorded tick in a different state than the session did, so the replay no
+    /// longer shows what happened; `changed` is what the replayed tick changed
+    Diverged { tick: u64, changed: StateDiff },
+}
+
+/// Start the default map with `content` and the session's stable ID seed and run it to the crash tick, applying
+/// the bundle's player commands at the ticks they were given on
+/// Recorded ticks are replayed with their own time steps and must end in their recorded state, and a replay
+/// surviving to the crash tick is compared with the world the bundle saved
+pub fn replay(bundle: &CrashBundle, content: &Content) -> Result<ReplayOutcome, String> {
+    let report = &bundle.report;
+    let mut game = GridGameWorld::new();
+    game.stable_id_seed = report.stable_id_seed;
+    game.initialize_game();
+    game.apply_content(content);
+    let frames: BTreeMap<u64, &TickRecord> = bundle.frames.iter().map(|frame| (frame.tick, frame)).collect();
+
+    let mut actions = bundle.actions.iter().peekable();
+    while game.tick < report.tick {
+        while let Some(record) = actions.next_if(|record| record.tick <= game.tick) {
+            // Commands that failed in the session fail here too, and were logged all the same
+            let _ = game.replay_action(&record.action);
+        }
+        let frame = frames.get(&(game.tick + 1));
+        game.set_fixed_timestep(Some(frame.map_or(report.delta_seconds, |frame| frame.delta_seconds)));
+        let before = frame.map(|_| WorldState::capture(&game.world));
+        match panic::catch_unwind(AssertUnwindSafe(|| game.update())) {
+            Ok(result) => result?,
+            Err(payload) => {
+                let message = LAST_PANIC.lock().ok().and_then(|mut last| last.take()).unwrap_or_else(|| panic_text(payload.as_ref()));
+                return Ok(ReplayOutcome::Reproduced { tick: game.tick, message });
+            }
+        }
+        if let (Some(frame), Some(before)) = (frame, before) {
+            if game.world.state_hash() != frame.state_hash {
+                return Ok(ReplayOutcome::Diverged { tick: game.tick, changed: before.diff(&WorldState::capture(&game.world)) });
+            }
+        }
+    }
+    Ok(ReplayOutcome::Survived { tick: game.tick, diff: bundle.state.diff(&WorldState::capture(&game.world)) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_log::PlayerAction;
+
+    #[test]
+    fn test_guard_writes_a_bundle_that_replays() {
+        install_panic_hook();
+        let mut game = GridGameWorld::new();
+        game.set_fixed_timestep(Some(0.1));
+        game.record_crash_frames(true);
+        game.initialize_game();
+        game.update().unwrap();
+        game.run_console_command("money 500");
+        game.move_player(1, 0);
+
+        let directory = std::env::temp_dir().join(format!("crash_test_{}", std::process::id()));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| guard(&mut game, &directory, |game| {
+            game.update().unwrap();
+            panic!("tick {} went wrong", game.tick);
+        })));
+        assert!(result.is_err());
+
+        let bundle_path = fs::read_dir(&directory).unwrap().next().unwrap().unwrap().path();
+        assert!(is_bundle(&bundle_path));
+        let bundle = CrashBundle::load(&bundle_path).unwrap();
+        assert_eq!(bundle.report.tick, 2);
+        assert!(bundle.report.message.starts_with("tick 2 went wrong at "), "{}", bundle.report.message);
+        assert_eq!(bundle.actions.last().map(|record| &record.action), Some(&PlayerAction::Move { dx: 1, dy: 0 }));
+        assert_eq!(bundle.state, WorldState::capture(&game.world));
+        // Ticks of normal play are kept, not only stepped ones
+        assert_eq!(bundle.frames.iter().map(|frame| frame.tick).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(bundle.frames[1].actions_logged, bundle.actions.len());
+        assert!(bundle.frames[0].actions_logged < bundle.frames[1].actions_logged);
+        let _ = fs::remove_dir_all(&directory);
+
+        // The panic came from outside the simulation, so replaying the session runs clean to the crash tick,
+        // through the recorded ticks' states to the world the panic left
+        assert_eq!(replay(&bundle, &Content::default()), Ok(ReplayOutcome::Survived { tick: 2, diff: StateDiff::default() }));
+
+        // A replay leaving the recorded states says so instead of running on
+        let mut tampered = bundle.clone();
+        tampered.frames[0].state_hash ^= 1;
+        assert!(matches!(replay(&tampered, &Content::default()), Ok(ReplayOutcome::Diverged { tick: 1, .. })));
+    }
+}
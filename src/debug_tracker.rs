@@ -0,0 +1,320 @@
+/// Debug tracker: a record of every frame-stepped tick with the state hash after each system, so a
+/// system-order bug can be traced to the system that first changed the state, and keyframe copies of the
+/// world and the game's resources every few ticks, from which any recorded tick can be restored
+use crate::ecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// How often the tracker keeps a copy of the world and how much history it keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DebugTrackerConfig {
+    /// Ticks from one keyframe to the next; 1 keeps a copy of every tick
+    pub keyframe_interval: u64,
+    /// Stepped ticks kept before the oldest is dropped
+    pub history_limit: usize,
+}
+
+impl Default for DebugTrackerConfig {
+    fn default() -> Self {
+        Self { keyframe_interval: 10, history_limit: 600 }
+    }
+}
+
+/// What the tracker's history holds and roughly how much memory it takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DebugMemoryUsage {
+    pub frames: usize,
+    pub keyframes: usize,
+    pub approx_bytes: usize,
+}
+
+/// The world's state hash after one system ran
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemCheckpoint {
+    pub system: String,
+    pub state_hash: u64,
+}
+
+/// The registered components of every entity, and the resources kept outside the world by name,
+/// as their `Debug` text
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldState {
+    pub entities: BTreeMap<Entity, BTreeMap<String, String>>,
+    #[serde(default)]
+    pub resources: BTreeMap<String, String>,
+}
+
+impl WorldState {
+    pub fn capture(world: &World) -> Self {
+        let entities = world.registered_state().into_iter()
+            .map(|(entity, components)| {
+                (entity, components.into_iter().map(|(name, text)| (name.to_string(), text)).collect())
+            })
+            .collect();
+        Self { entities, resources: BTreeMap::new() }
+    }
+
+    /// The same state with the resources' text added
+    pub fn with_resources(mut self, resources: BTreeMap<String, String>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// What changed between this state and a later one
+    pub fn diff(&self, after: &WorldState) -> StateDiff {
+        let mut diff = StateDiff::default();
+        for (entity, components) in &after.entities {
+            let Some(before) = self.entities.get(entity) else {
+                diff.spawned.push(*entity);
+                continue;
+            };
+            let names: BTreeSet<&String> = before.keys().chain(components.keys()).collect();
+            diff.changed.extend(names.into_iter()
+                .filter(|name| before.get(*name) != components.get(*name))
+                .map(|name| (*entity, name.clone())));
+        }
+        diff.despawned = self.entities.keys().filter(|entity| !after.entities.contains_key(entity)).copied().collect();
+        diff
+    }
+}
+
+/// Entities created and destroyed, and the components added, removed or modified on the others
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub spawned: Vec<Entity>,
+    pub despawned: Vec<Entity>,
+    pub changed: Vec<(Entity, String)>,
+}
+
+/// One stepped tick: the hash going in, a checkpoint per system and what the tick changed,
+/// plus the world state it left behind on keyframes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub tick: u64,
+    /// Seconds the tick advanced the simulation
+    pub delta_seconds: f32,
+    pub initial_hash: u64,
+    pub checkpoints: Vec<SystemCheckpoint>,
+    pub diff: StateDiff,
+    pub state: Option<WorldState>,
+}
+
+impl FrameRecord {
+    /// Systems that changed the world's registered state, in the order they ran
+    pub fn changed_by(&self) -> Vec<&str> {
+        let mut previous = self.initial_hash;
+        self.checkpoints.iter()
+            .filter(|checkpoint| std::mem::replace(&mut previous, checkpoint.state_hash) != checkpoint.state_hash)
+            .map(|checkpoint| checkpoint.system.as_str())
+            .collect()
+    }
+
+    /// State hash the tick ended with
+    pub fn final_hash(&self) -> u64 {
+        self.checkpoints.last().map_or(self.initial_hash, |checkpoint| checkpoint.state_hash)
+    }
+}
+
+// A recorded tick, with a copy of the world and resources it left behind on keyframes and the length
+// of the action log then, so the player commands between two ticks can be replayed
+struct TrackedFrame<R> {
+    record: FrameRecord,
+    world: Option<(World, R)>,
+    actions_logged: usize,
+}
+
+/// The most recent stepped ticks, oldest first; `R` is the copy of the resources outside the world
+/// that a keyframe keeps with it
+pub struct DebugTracker<R = ()> {
+    config: DebugTrackerConfig,
+    frames: VecDeque<TrackedFrame<R>>,
+}
+
+impl<R> Default for DebugTracker<R> {
+    fn default() -> Self {
+        Self { config: DebugTrackerConfig::default(), frames: VecDeque::new() }
+    }
+}
+
+impl<R: Clone> DebugTracker<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: DebugTrackerConfig) -> Self {
+        Self { config, frames: VecDeque::new() }
+    }
+
+    pub fn config(&self) -> DebugTrackerConfig {
+        self.config
+    }
+
+    /// Change the cadence and cap; history beyond the new cap is dropped straight away
+    pub fn set_config(&mut self, config: DebugTrackerConfig) {
+        self.config = config;
+        self.prune();
+    }
+
+    /// Whether the tick about to be recorded is a keyframe, so the caller knows to copy its resources;
+    /// the first tick after a gap always is, since replays can't cross unrecorded ticks
+    pub fn keyframe_due(&self, tick: u64) -> bool {
+        let kept = self.frames.iter().filter(|tracked| tracked.record.tick < tick);
+        let since_keyframe = kept.clone().rev().take_while(|tracked| tracked.world.is_none()).count() as u64;
+        since_keyframe + 1 >= self.config.keyframe_interval.max(1)
+            || kept.clone().last().is_none_or(|previous| previous.record.tick + 1 != tick)
+            || kept.clone().all(|tracked| tracked.world.is_none())
+    }
+
+    /// Add a tick, the world after it and the action log's length; stepping on from a restored tick
+    /// replaces the recorded ticks after it
+    /// Frames given resources are keyframes: the world is copied and the frame keeps its state
+    pub fn record(&mut self, mut frame: FrameRecord, world: &World, resources: Option<R>, actions_logged: usize) {
+        self.frames.retain(|tracked| tracked.record.tick < frame.tick);
+        if resources.is_none() {
+            frame.state = None;
+        }
+        let world = resources.map(|resources| (world.snapshot(), resources));
+        self.frames.push_back(TrackedFrame { record: frame, world, actions_logged });
+        self.prune();
+    }
+
+    fn prune(&mut self) {
+        while self.frames.len() > self.config.history_limit.max(1) {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &FrameRecord> {
+        self.frames.iter().map(|tracked| &tracked.record)
+    }
+
+    pub fn frame(&self, tick: u64) -> Option<&FrameRecord> {
+        self.frames().find(|frame| frame.tick == tick)
+    }
+
+    pub fn latest(&self) -> Option<&FrameRecord> {
+        self.frames.back().map(|tracked| &tracked.record)
+    }
+
+    /// Length of the action log when a tick was recorded
+    pub fn actions_logged(&self, tick: u64) -> Option<usize> {
+        self.frames.iter().find(|tracked| tracked.record.tick == tick).map(|tracked| tracked.actions_logged)
+    }
+
+    /// A copy of the world and resources at the latest kept keyframe at or before a tick, with the keyframe's tick
+    pub fn restore_world_state(&self, tick: u64) -> Option<(u64, World, R)> {
+        self.frames.iter().rev()
+            .filter(|tracked| tracked.record.tick <= tick)
+            .find_map(|tracked| {
+                tracked.world.as_ref().map(|(world, resources)| (tracked.record.tick, world.snapshot(), resources.clone()))
+            })
+    }
+
+    pub fn memory_usage(&self) -> DebugMemoryUsage {
+        let text_bytes = |state: &WorldState| -> usize {
+            state.entities.values()
+                .flat_map(|components| components.iter())
+                .chain(state.resources.iter())
+                .map(|(name, text)| name.len() + text.len())
+                .sum()
+        };
+        let approx_bytes = self.frames.iter().map(|tracked| {
+            std::mem::size_of::<TrackedFrame<R>>()
+                + tracked.record.checkpoints.iter().map(|checkpoint| std::mem::size_of::<SystemCheckpoint>() + checkpoint.system.len()).sum::<usize>()
+                + tracked.record.state.as_ref().map_or(0, text_bytes)
+                + tracked.world.as_ref().map_or(0, |(world, _)| world.memory_report().total_bytes)
+        }).sum();
+        DebugMemoryUsage {
+            frames: self.frames.len(),
+            keyframes: self.frames.iter().filter(|tracked| tracked.world.is_some()).count(),
+            approx_bytes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_game_components::GridPositionComponent;
+
+    fn frame(tick: u64) -> FrameRecord {
+        FrameRecord {
+            tick,
+            delta_seconds: 0.1,
+            initial_hash: 1,
+            checkpoints: vec![
+                SystemCheckpoint { system: "input".to_string(), state_hash: 1 },
+                SystemCheckpoint { system: "movement".to_string(), state_hash: 2 },
+                SystemCheckpoint { system: "render".to_string(), state_hash: 2 },
+                SystemCheckpoint { system: "agents".to_string(), state_hash: 3 },
+            ],
+            diff: StateDiff::default(),
+            state: Some(WorldState::default()),
+        }
+    }
+
+    #[test]
+    fn test_keyframe_cadence_and_pruning() {
+        let mut tracker = DebugTracker::with_config(DebugTrackerConfig { keyframe_interval: 4, history_limit: 20 });
+        for tick in 0..25 {
+            let resources = tracker.keyframe_due(tick).then_some(tick);
+            tracker.record(frame(tick), &World::new(), resources, 0);
+        }
+        assert_eq!(tracker.len(), 20);
+        assert_eq!(tracker.frames().next().unwrap().tick, 5);
+        assert!(tracker.frame(4).is_none());
+        assert_eq!(tracker.latest().unwrap().changed_by(), vec!["movement", "agents"]);
+        let keyframes: Vec<u64> = tracker.frames().filter(|frame| frame.state.is_some()).map(|frame| frame.tick).collect();
+        assert_eq!(keyframes, vec![8, 12, 16, 20, 24]);
+        assert_eq!(tracker.memory_usage().keyframes, 5);
+
+        // Ticks restore from the nearest kept keyframe before them
+        assert_eq!(tracker.restore_world_state(11).map(|(keyframe, _, resources)| (keyframe, resources)), Some((8, 8)));
+        assert!(tracker.restore_world_state(6).is_none());
+
+        // Stepping on from a restored tick branches the history
+        assert!(!tracker.keyframe_due(10));
+        tracker.record(frame(10), &World::new(), None, 0);
+        assert_eq!(tracker.latest().unwrap().tick, 10);
+        assert_eq!(tracker.len(), 6);
+        tracker.set_config(DebugTrackerConfig { keyframe_interval: 1, history_limit: 2 });
+        assert_eq!(tracker.frames().map(|frame| frame.tick).collect::<Vec<_>>(), vec![9, 10]);
+    }
+
+    #[test]
+    fn test_diff_and_restore_recorded_world() {
+        let mut world = World::new();
+        world.register_component::<GridPositionComponent>("GridPosition");
+        let moved = world.spawn((GridPositionComponent { x: 0, y: 0 },)).unwrap();
+        let removed = world.spawn((GridPositionComponent { x: 5, y: 5 },)).unwrap();
+        let before = WorldState::capture(&world);
+
+        world.get_component_mut::<GridPositionComponent>(moved).unwrap().x = 1;
+        world.destroy_entity(removed);
+        let added = world.spawn((GridPositionComponent { x: 2, y: 2 },)).unwrap();
+        let after = WorldState::capture(&world);
+        assert_eq!(before.diff(&after), StateDiff {
+            spawned: vec![added],
+            despawned: vec![removed],
+            changed: vec![(moved, "GridPosition".to_string())],
+        });
+
+        let recorded_hash = world.state_hash();
+        let mut tracker = DebugTracker::new();
+        tracker.record(FrameRecord { diff: before.diff(&after), state: Some(after), ..frame(7) }, &world, Some(()), 0);
+        world.get_component_mut::<GridPositionComponent>(moved).unwrap().x = 9;
+        let (keyframe, restored, ()) = tracker.restore_world_state(7).unwrap();
+        assert_eq!(keyframe, 7);
+        assert_eq!(restored.get_component::<GridPositionComponent>(moved).unwrap().x, 1);
+        assert_eq!(restored.state_hash(), recorded_hash);
+        assert!(tracker.restore_world_state(6).is_none());
+    }
+}
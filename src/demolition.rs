@@ -0,0 +1,203 @@
+/// Demolition of buildings and construction sites, leaving rubble behind
+use crate::catalog::BuildingCatalog;
+use crate::construction::{BuildingComponent, BuildingKind, UnderConstructionComponent};
+use crate::ecs::{Component, Entity, World};
+use crate::economy::Economy;
+use crate::events::{EventQueue, GameEvent};
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::notifications::Notification;
+use std::any::{Any, TypeId};
+
+/// Marker component for buildings that will be demolished on the next update
+#[derive(Clone, Debug)]
+pub struct MarkedForDemolitionComponent;
+
+impl Component for MarkedForDemolitionComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Component for the rubble left on a tile after demolition
+/// Rubble does not block movement or placement and is cleared when building over it
+#[derive(Clone, Debug)]
+pub struct RubbleComponent {
+    pub previous_kind: BuildingKind,
+}
+
+impl Component for RubbleComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// System that replaces marked buildings with rubble and refunds part of their cost
+pub struct DemolitionSystem {
+    /// Fraction of the original building cost paid back on demolition
+    pub refund_fraction: f32,
+}
+
+impl DemolitionSystem {
+    pub fn new(refund_fraction: f32) -> Self {
+        Self {
+            refund_fraction: refund_fraction.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Money refunded when demolishing a building of the given kind, a share of its cost in the active catalog
+    pub fn refund_for(&self, catalog: &BuildingCatalog, kind: BuildingKind) -> i64 {
+        (catalog.get(kind).cost as f32 * self.refund_fraction) as i64
+    }
+
+    /// Kind of building on an entity, finished or still under construction
+    pub fn building_kind(world: &World, entity: Entity) -> Option<BuildingKind> {
+        world.get_component::<BuildingComponent>(entity).map(|b| b.kind)
+            .or_else(|| world.get_component::<UnderConstructionComponent>(entity).map(|site| site.kind))
+    }
+
+    /// Demolish all marked buildings, returning the rubble entities that replaced them
+    pub fn update(
+        &self,
+        world: &mut World,
+        catalog: &BuildingCatalog,
+        economy: &mut Economy,
+        events: &mut EventQueue<GameEvent>,
+        notifications: &mut EventQueue<Notification>,
+    ) -> Vec<Entity> {
+        let mut rubble = Vec::new();
+
+        for entity in world.entities_with_components(&[TypeId::of::<MarkedForDemolitionComponent>()]) {
+            let kind = Self::building_kind(world, entity);
+            let position = world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y));
+            let (Some(kind), Some((x, y))) = (kind, position) else {
+                // Nothing demolishable here, just drop the mark
+                world.remove_component::<MarkedForDemolitionComponent>(entity);
+                continue;
+            };
+
+            let refund = self.refund_for(catalog, kind);
+            economy.treasury.balance += refund;
+            rubble.extend(Self::leave_rubble(world, entity, kind, (x, y), notifications));
+
+            events.push(GameEvent::BuildingDemolished { x, y, kind: format!("{:?}", kind), refund });
+            notifications.push(
+                Notification::info(&format!("{:?} demolished, refunded {}", kind, refund)).at_tile(x, y)
+            );
+        }
+
+        rubble
+    }
+
+    /// Destroy a building or construction site outright, e.g. in a disaster: it leaves rubble but refunds nothing
+    /// Returns the rubble, or `None` when there was no building on the entity
+    pub fn wreck(
+        world: &mut World,
+        entity: Entity,
+        events: &mut EventQueue<GameEvent>,
+        notifications: &mut EventQueue<Notification>,
+    ) -> Option<Entity> {
+        let kind = Self::building_kind(world, entity)?;
+        let (x, y) = world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y))?;
+        let remains = Self::leave_rubble(world, entity, kind, (x, y), notifications);
+        events.push(GameEvent::BuildingDemolished { x, y, kind: format!("{:?}", kind), refund: 0 });
+        notifications.push(Notification::warning(&format!("{:?} destroyed", kind)).at_tile(x, y));
+        remains
+    }
+
+    // Replace a building entity with rubble on its tile
+    fn leave_rubble(
+        world: &mut World,
+        entity: Entity,
+        kind: BuildingKind,
+        (x, y): (i32, i32),
+        notifications: &mut EventQueue<Notification>,
+    ) -> Option<Entity> {
+        // Destroying the entity frees the tile along with all building components
+        world.destroy_entity(entity);
+
+        let remains = world.spawn((
+            GridPositionComponent { x, y },
+            RubbleComponent { previous_kind: kind },
+            RenderComponent { symbol: '%', color: "gray".to_string() },
+        ));
+        match remains {
+            Ok(remains) => Some(remains),
+            Err(error) => {
+                notifications.push(Notification::critical(&error.to_string()).at_tile(x, y));
+                None
+            }
+        }
+    }
+}
+
+impl Default for DemolitionSystem {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy::ZoneComponent;
+
+    #[test]
+    fn test_demolish_building_leaves_rubble() {
+        let mut world = World::new();
+        let house = world.create_entity();
+        world.add_component(house, GridPositionComponent { x: 4, y: 2 }).unwrap();
+        BuildingKind::House.spawn_final(&mut world, house).unwrap();
+        world.add_component(house, MarkedForDemolitionComponent).unwrap();
+
+        let system = DemolitionSystem::new(0.25);
+        let mut economy = Economy::new(0);
+        let mut events = EventQueue::new();
+        let mut notifications = EventQueue::new();
+        let rubble = system.update(&mut world, BuildingCatalog::builtin(), &mut economy, &mut events, &mut notifications);
+
+        assert_eq!(rubble.len(), 1);
+        assert!(!world.has_component::<ZoneComponent>(house));
+        assert_eq!(world.get_component::<RubbleComponent>(rubble[0]).unwrap().previous_kind, BuildingKind::House);
+        assert_eq!(economy.treasury.balance, 50);
+        assert_eq!(events.drain(), vec![GameEvent::BuildingDemolished {
+            x: 4, y: 2, kind: "House".to_string(), refund: 50,
+        }]);
+        assert_eq!(notifications.len(), 1);
+    }
+
+    #[test]
+    fn test_demolish_construction_site() {
+        let mut world = World::new();
+        let site = world.create_entity();
+        world.add_component(site, GridPositionComponent { x: 0, y: 0 }).unwrap();
+        world.add_component(site, UnderConstructionComponent::new(BuildingKind::School)).unwrap();
+        world.add_component(site, MarkedForDemolitionComponent).unwrap();
+
+        // Refunds follow the active catalog's prices, e.g. a content pack's
+        let catalog = BuildingCatalog::new(BuildingCatalog::builtin().definitions().iter().cloned().map(|mut definition| {
+            definition.cost *= 3;
+            definition
+        }).collect()).unwrap();
+        let mut economy = Economy::new(0);
+        DemolitionSystem::default().update(&mut world, &catalog, &mut economy, &mut EventQueue::new(), &mut EventQueue::new());
+
+        assert!(!world.has_component::<UnderConstructionComponent>(site));
+        assert_eq!(economy.treasury.balance, catalog.get(BuildingKind::School).cost / 2);
+    }
+}
@@ -0,0 +1,181 @@
+/// Structural diffs: the operations turning one value into another, so replays store only what changed
+/// Collections diff element by element (by index for `Vec`, by key for `HashMap`) instead of being replaced whole
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A value that can describe its changes to another value and apply such a description
+pub trait Diffable {
+    type Diff;
+
+    /// What turns `self` into `target`, or `None` when they are equal
+    fn diff(&self, target: &Self) -> Option<Self::Diff>;
+
+    /// Apply a diff made against an equal value; false, leaving `self` unchanged, when it doesn't fit
+    fn apply_diff(&mut self, diff: &Self::Diff) -> bool;
+}
+
+// Plain values diff as their new value
+macro_rules! diff_by_value {
+    ($($value:ty),*) => {
+        $(impl Diffable for $value {
+            type Diff = $value;
+
+            fn diff(&self, target: &Self) -> Option<Self::Diff> {
+                (self != target).then(|| target.clone())
+            }
+
+            fn apply_diff(&mut self, diff: &Self::Diff) -> bool {
+                *self = diff.clone();
+                true
+            }
+        })*
+    };
+}
+
+diff_by_value!(bool, char, i32, i64, u32, u64, usize, f32, f64, String, (i32, i32));
+
+/// One step of a `Vec` diff; indices refer to the vector as earlier steps left it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VecOp<T: Diffable> {
+    Update { index: usize, diff: T::Diff },
+    Insert { index: usize, value: T },
+    Remove { index: usize },
+}
+
+impl<T: Diffable + Clone> Diffable for Vec<T> {
+    type Diff = Vec<VecOp<T>>;
+
+    /// Updates elements both vectors have, then appends the target's extra elements or removes the surplus from the end
+    fn diff(&self, target: &Self) -> Option<Self::Diff> {
+        let mut ops: Vec<VecOp<T>> = self.iter().zip(target)
+            .enumerate()
+            .filter_map(|(index, (current, wanted))| current.diff(wanted).map(|diff| VecOp::Update { index, diff }))
+            .collect();
+        ops.extend((self.len()..target.len()).map(|index| VecOp::Insert { index, value: target[index].clone() }));
+        ops.extend((target.len()..self.len()).rev().map(|index| VecOp::Remove { index }));
+        (!ops.is_empty()).then_some(ops)
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> bool {
+        let mut result = self.clone();
+        for op in diff {
+            let applied = match op {
+                VecOp::Update { index, diff } => result.get_mut(*index).is_some_and(|element| element.apply_diff(diff)),
+                VecOp::Insert { index, value } if *index <= result.len() => {
+                    result.insert(*index, value.clone());
+                    true
+                }
+                VecOp::Remove { index } if *index < result.len() => {
+                    result.remove(*index);
+                    true
+                }
+                _ => false,
+            };
+            if !applied {
+                return false;
+            }
+        }
+        *self = result;
+        true
+    }
+}
+
+/// One step of a `HashMap` diff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MapOp<K, V: Diffable> {
+    Update { key: K, diff: V::Diff },
+    Insert { key: K, value: V },
+    Remove { key: K },
+}
+
+impl<K: Eq + Hash + Clone + Ord, V: Diffable + Clone> Diffable for HashMap<K, V> {
+    type Diff = Vec<MapOp<K, V>>;
+
+    /// Operations sorted by key, so equal maps always give equal diffs
+    fn diff(&self, target: &Self) -> Option<Self::Diff> {
+        let mut keys: Vec<&K> = self.keys().chain(target.keys().filter(|key| !self.contains_key(key))).collect();
+        keys.sort();
+        let ops: Vec<MapOp<K, V>> = keys.into_iter()
+            .filter_map(|key| match (self.get(key), target.get(key)) {
+                (Some(current), Some(wanted)) => current.diff(wanted).map(|diff| MapOp::Update { key: key.clone(), diff }),
+                (None, Some(wanted)) => Some(MapOp::Insert { key: key.clone(), value: wanted.clone() }),
+                (Some(_), None) => Some(MapOp::Remove { key: key.clone() }),
+                (None, None) => None,
+            })
+            .collect();
+        (!ops.is_empty()).then_some(ops)
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> bool {
+        let mut result = self.clone();
+        for op in diff {
+            let applied = match op {
+                MapOp::Update { key, diff } => result.get_mut(key).is_some_and(|value| value.apply_diff(diff)),
+                MapOp::Insert { key, value } => result.insert(key.clone(), value.clone()).is_none(),
+                MapOp::Remove { key } => result.remove(key).is_some(),
+            };
+            if !applied {
+                return false;
+            }
+        }
+        *self = result;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinding::PathComponent;
+    use crate::soak::SeededRng;
+
+    fn random_vec(rng: &mut SeededRng) -> Vec<(i32, i32)> {
+        (0..rng.below(8)).map(|_| (rng.below(4), rng.below(4))).collect()
+    }
+
+    fn random_map(rng: &mut SeededRng) -> HashMap<String, Vec<u32>> {
+        (0..rng.below(6)).map(|_| (format!("key{}", rng.below(8)), (0..rng.below(4)).map(|_| rng.below(3) as u32).collect())).collect()
+    }
+
+    #[test]
+    fn test_diff_then_apply_reaches_the_target() {
+        let mut rng = SeededRng::new(7);
+        for _ in 0..500 {
+            let (mut current, target) = (random_vec(&mut rng), random_vec(&mut rng));
+            match current.diff(&target) {
+                Some(diff) => assert!(current.apply_diff(&diff)),
+                None => assert_eq!(current, target),
+            }
+            assert_eq!(current, target);
+
+            let (mut current, target) = (random_map(&mut rng), random_map(&mut rng));
+            if let Some(diff) = current.diff(&target) {
+                assert!(current.apply_diff(&diff));
+            }
+            assert_eq!(current, target);
+        }
+    }
+
+    #[test]
+    fn test_mismatched_diffs_are_rejected_whole() {
+        let mut waypoints = vec![(0, 0), (1, 0)];
+        let diff = waypoints.diff(&vec![(0, 0), (1, 1), (2, 1)]).unwrap();
+        assert_eq!(diff, vec![VecOp::Update { index: 1, diff: (1, 1) }, VecOp::Insert { index: 2, value: (2, 1) }]);
+
+        let mut shorter = vec![(5, 5)];
+        assert!(!shorter.apply_diff(&diff));
+        assert_eq!(shorter, vec![(5, 5)]);
+
+        let mut stock: HashMap<String, u32> = HashMap::from([("wood".to_string(), 3)]);
+        assert!(!stock.apply_diff(&vec![MapOp::Remove { key: "stone".to_string() }]));
+        assert!(waypoints.apply_diff(&diff));
+        assert_eq!(waypoints.len(), 3);
+
+        // Components holding collections replay through the same operations
+        let mut path = PathComponent::new(vec![(0, 0), (1, 0)]);
+        let target = PathComponent { waypoints: vec![(0, 0), (0, 1), (0, 2)], current: 1 };
+        assert!(path.apply_diff(&path.diff(&target).unwrap()));
+        assert_eq!((path.waypoints, path.current), (target.waypoints, target.current));
+    }
+}
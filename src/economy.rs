@@ -0,0 +1,534 @@
+/// City economy: zone taxation, treasury, loans and the monthly budget cycle
+use crate::ecs::{Component, World};
+use crate::events::{EventQueue, GameEvent};
+use crate::notifications::Notification;
+use crate::logistics::Inventory;
+use crate::modifiers::{ModifierStack, ModifierTarget};
+use crate::policies::EnactedPolicy;
+use crate::stable_id::StableIdSystem;
+use crate::trade::{TradeMarket, TradeReport, TradeSystem};
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+
+/// Taxes of a building short of inputs are divided by this
+pub const SHORTAGE_INCOME_DIVISOR: i64 = 2;
+
+/// Zone categories that can be taxed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ZoneType {
+    Residential,
+    Commercial,
+    Industrial,
+}
+
+impl ZoneType {
+    /// All zone types in a stable order
+    pub fn all() -> [ZoneType; 3] {
+        [ZoneType::Residential, ZoneType::Commercial, ZoneType::Industrial]
+    }
+
+    /// Monthly tax base per resident/worker at a 100% tax rate
+    pub fn tax_base(&self) -> i64 {
+        match self {
+            ZoneType::Residential => 10,
+            ZoneType::Commercial => 15,
+            ZoneType::Industrial => 12,
+        }
+    }
+
+    /// Parse a zone type from its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "residential" => Some(ZoneType::Residential),
+            "commercial" => Some(ZoneType::Commercial),
+            "industrial" => Some(ZoneType::Industrial),
+            _ => None,
+        }
+    }
+}
+
+/// Component for a zoned tile and the people living or working on it
+#[derive(Clone, Debug)]
+pub struct ZoneComponent {
+    pub zone_type: ZoneType,
+    pub population: u32,
+}
+
+impl ZoneComponent {
+    pub fn new(zone_type: ZoneType, population: u32) -> Self {
+        Self { zone_type, population }
+    }
+}
+
+impl Component for ZoneComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Component for buildings that cost money to run each month
+#[derive(Clone, Debug)]
+pub struct ServiceUpkeepComponent {
+    pub monthly_cost: i64,
+}
+
+impl Component for ServiceUpkeepComponent {
+    fn validate(&self) -> bool {
+        self.monthly_cost >= 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Tax rates in percent for each zone type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxRates {
+    pub residential: u32,
+    pub commercial: u32,
+    pub industrial: u32,
+}
+
+impl TaxRates {
+    /// Highest tax rate the city council allows
+    pub const MAX_RATE: u32 = 20;
+
+    /// Get the tax rate for a zone type
+    pub fn rate(&self, zone_type: ZoneType) -> u32 {
+        match zone_type {
+            ZoneType::Residential => self.residential,
+            ZoneType::Commercial => self.commercial,
+            ZoneType::Industrial => self.industrial,
+        }
+    }
+
+    /// Check a requested rate is a whole percentage from 0 to `MAX_RATE`
+    pub fn checked_rate(rate: f64) -> Result<u32, String> {
+        if rate.fract() != 0.0 || !(0.0..=Self::MAX_RATE as f64).contains(&rate) {
+            return Err(format!("Tax rate must be a whole number from 0 to {}, got {}", Self::MAX_RATE, rate));
+        }
+        Ok(rate as u32)
+    }
+
+    /// Set the tax rate for a zone type, clamped to `MAX_RATE`
+    pub fn set_rate(&mut self, zone_type: ZoneType, rate: u32) {
+        let rate = rate.min(Self::MAX_RATE);
+        match zone_type {
+            ZoneType::Residential => self.residential = rate,
+            ZoneType::Commercial => self.commercial = rate,
+            ZoneType::Industrial => self.industrial = rate,
+        }
+    }
+}
+
+impl Default for TaxRates {
+    fn default() -> Self {
+        Self {
+            residential: 9,
+            commercial: 9,
+            industrial: 9,
+        }
+    }
+}
+
+/// An outstanding loan repaid in monthly installments
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Loan {
+    pub principal: i64,
+    pub remaining: i64,
+    /// Monthly interest in percent of the remaining amount
+    pub monthly_interest_percent: u32,
+    pub monthly_payment: i64,
+}
+
+/// The city's money and debts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Treasury {
+    pub balance: i64,
+    pub loans: Vec<Loan>,
+}
+
+impl Treasury {
+    /// Maximum number of loans the city may hold at once
+    pub const MAX_LOANS: usize = 3;
+    /// Largest amount a single loan may borrow
+    pub const MAX_LOAN_AMOUNT: i64 = 100_000;
+
+    pub fn new(balance: i64) -> Self {
+        Self {
+            balance,
+            loans: Vec::new(),
+        }
+    }
+
+    /// Take a loan, adding the amount to the balance immediately
+    pub fn take_loan(&mut self, amount: i64, monthly_interest_percent: u32, months: u32) -> Result<(), String> {
+        if amount <= 0 {
+            return Err("Loan amount must be positive".to_string());
+        }
+        if amount > Self::MAX_LOAN_AMOUNT {
+            return Err(format!("Cannot borrow more than {} at once", Self::MAX_LOAN_AMOUNT));
+        }
+        if months == 0 {
+            return Err("Loan term must be at least one month".to_string());
+        }
+        if self.loans.len() >= Self::MAX_LOANS {
+            return Err(format!("Cannot hold more than {} loans", Self::MAX_LOANS));
+        }
+
+        let balance = self.balance.checked_add(amount).ok_or("The treasury cannot hold that much money")?;
+        // Round the installment up so the loan is always repaid within its term
+        let monthly_payment = amount / months as i64 + (amount % months as i64 != 0) as i64;
+        self.loans.push(Loan {
+            principal: amount,
+            remaining: amount,
+            monthly_interest_percent,
+            monthly_payment,
+        });
+        self.balance = balance;
+        Ok(())
+    }
+
+    /// Repay part of a loan early; returns the amount actually repaid
+    pub fn repay_loan(&mut self, index: usize, amount: i64) -> Result<i64, String> {
+        if amount <= 0 {
+            return Err("Repayment amount must be positive".to_string());
+        }
+        let loan = self.loans.get_mut(index)
+            .ok_or_else(|| format!("No loan with index {}", index))?;
+
+        let repaid = amount.min(loan.remaining);
+        if repaid > self.balance {
+            return Err("Insufficient funds to repay loan".to_string());
+        }
+
+        loan.remaining -= repaid;
+        self.balance -= repaid;
+        if loan.remaining == 0 {
+            self.loans.remove(index);
+        }
+        Ok(repaid)
+    }
+
+    /// Total outstanding debt
+    pub fn total_debt(&self) -> i64 {
+        self.loans.iter().map(|loan| loan.remaining).sum()
+    }
+}
+
+/// Summary of one month of city finances
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetReport {
+    pub month: u32,
+    pub residential_income: i64,
+    pub commercial_income: i64,
+    pub industrial_income: i64,
+    pub service_expenses: i64,
+    pub loan_payments: i64,
+    /// Monthly costs of the policies in force
+    #[serde(default)]
+    pub policy_expenses: i64,
+    /// Goods traded through the map's external connections
+    #[serde(default)]
+    pub trade: TradeReport,
+    pub net: i64,
+    pub balance: i64,
+}
+
+impl BudgetReport {
+    /// Total tax income of the month
+    pub fn total_income(&self) -> i64 {
+        self.residential_income + self.commercial_income + self.industrial_income
+    }
+}
+
+/// Economy state owned by the game: treasury, tax settings and the latest report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Economy {
+    pub treasury: Treasury,
+    pub tax_rates: TaxRates,
+    pub last_report: Option<BudgetReport>,
+    /// Prices of the world outside the map
+    #[serde(default)]
+    pub market: TradeMarket,
+    /// Temporary changes to income, upkeep and happiness from city events
+    #[serde(default)]
+    pub modifiers: ModifierStack,
+    /// Policies in force, paid for every month
+    #[serde(default)]
+    pub policies: Vec<EnactedPolicy>,
+}
+
+impl Economy {
+    pub fn new(starting_balance: i64) -> Self {
+        Self {
+            treasury: Treasury::new(starting_balance),
+            tax_rates: TaxRates::default(),
+            last_report: None,
+            market: TradeMarket::default(),
+            modifiers: ModifierStack::new(),
+            policies: Vec::new(),
+        }
+    }
+}
+
+impl Default for Economy {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+/// System that settles the city budget once per in-game month
+#[derive(Debug, Clone)]
+pub struct BudgetSystem {
+    ticks_per_month: u32,
+    ticks: u32,
+    month: u32,
+}
+
+impl BudgetSystem {
+    /// Create a budget system settling every `ticks_per_month` updates
+    pub fn new(ticks_per_month: u32) -> Self {
+        Self {
+            ticks_per_month: ticks_per_month.max(1),
+            ticks: 0,
+            month: 0,
+        }
+    }
+
+    /// Number of months settled so far
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    /// Updates making up one in-game month
+    pub fn ticks_per_month(&self) -> u32 {
+        self.ticks_per_month
+    }
+
+    /// Advance one tick, settling the budget when a month has passed
+    pub fn update(
+        &mut self,
+        world: &World,
+        economy: &mut Economy,
+        events: &mut EventQueue<GameEvent>,
+        notifications: &mut EventQueue<Notification>,
+    ) -> Option<BudgetReport> {
+        // Timed modifiers run out between settlements
+        economy.modifiers.tick();
+        self.ticks += 1;
+        if self.ticks < self.ticks_per_month {
+            return None;
+        }
+        self.ticks = 0;
+        self.month += 1;
+
+        let report = Self::settle_month(self.month, world, economy);
+
+        if report.total_income() > 0 {
+            events.push(GameEvent::MoneyEarned { amount: report.total_income() });
+        }
+        if report.net < 0 {
+            notifications.push(Notification::warning(&format!("Budget deficit of {} this month", -report.net)));
+        }
+        if report.balance < 0 {
+            notifications.push(Notification::critical("The city treasury is bankrupt"));
+        }
+        if report.trade.shortage > 0 {
+            notifications.push(Notification::warning(&format!("Shops were short of {} goods this month", report.trade.shortage)));
+        }
+        events.push(GameEvent::BudgetReport(report.clone()));
+
+        economy.last_report = Some(report.clone());
+        Some(report)
+    }
+
+    /// Compute income and expenses for a month and apply them to the treasury
+    pub fn settle_month(month: u32, world: &World, economy: &mut Economy) -> BudgetReport {
+        let mut income = [0i64; 3];
+        for entity in world.entities_with_components(&[TypeId::of::<ZoneComponent>()]) {
+            if let Some(zone) = world.get_component::<ZoneComponent>(entity) {
+                let rate = economy.tax_rates.rate(zone.zone_type) as i64;
+                let index = ZoneType::all().iter().position(|z| *z == zone.zone_type).unwrap_or(0);
+                let mut tax = zone.population as i64 * zone.zone_type.tax_base() * rate / 100;
+                // Stores left without goods sell half as much
+                if world.get_component::<Inventory>(entity).is_some_and(|inventory| !inventory.shortages.is_empty()) {
+                    tax /= SHORTAGE_INCOME_DIVISOR;
+                }
+                let target = ModifierTarget::TaxIncome(zone.zone_type);
+                income[index] += economy.modifiers.apply_at(target, StableIdSystem::get(world, entity), tax);
+            }
+        }
+
+        let service_expenses: i64 = world.entities_with_components(&[TypeId::of::<ServiceUpkeepComponent>()])
+            .into_iter()
+            .filter_map(|entity| {
+                let cost = world.get_component::<ServiceUpkeepComponent>(entity).map(|s| s.monthly_cost)?;
+                Some(economy.modifiers.apply_at(ModifierTarget::ServiceUpkeep, StableIdSystem::get(world, entity), cost))
+            })
+            .sum();
+
+        let mut loan_payments = 0;
+        for loan in &mut economy.treasury.loans {
+            let interest = loan.remaining * loan.monthly_interest_percent as i64 / 100;
+            let installment = loan.monthly_payment.min(loan.remaining);
+            loan.remaining -= installment;
+            loan_payments += installment + interest;
+        }
+        economy.treasury.loans.retain(|loan| loan.remaining > 0);
+
+        let policy_expenses: i64 = economy.policies.iter().map(|policy| policy.monthly_cost).sum();
+
+        let trade = TradeSystem::settle(world, &mut economy.market);
+
+        let net = income.iter().sum::<i64>() + trade.income - service_expenses - loan_payments - policy_expenses;
+        economy.treasury.balance += net;
+
+        BudgetReport {
+            month,
+            residential_income: income[0],
+            commercial_income: income[1],
+            industrial_income: income[2],
+            service_expenses,
+            loan_payments,
+            policy_expenses,
+            trade,
+            net,
+            balance: economy.treasury.balance,
+        }
+    }
+}
+
+impl Default for BudgetSystem {
+    fn default() -> Self {
+        Self::new(30)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_with_zones() -> World {
+        let mut world = World::new();
+        let house = world.create_entity();
+        world.add_component(house, ZoneComponent::new(ZoneType::Residential, 100)).unwrap();
+        let shop = world.create_entity();
+        world.add_component(shop, ZoneComponent::new(ZoneType::Commercial, 20)).unwrap();
+        let fire_station = world.create_entity();
+        world.add_component(fire_station, ServiceUpkeepComponent { monthly_cost: 50 }).unwrap();
+        world
+    }
+
+    #[test]
+    fn test_tax_rate_clamping() {
+        let mut rates = TaxRates::default();
+        rates.set_rate(ZoneType::Industrial, 50);
+        assert_eq!(rates.rate(ZoneType::Industrial), TaxRates::MAX_RATE);
+        assert_eq!(TaxRates::checked_rate(12.0), Ok(12));
+        assert!(TaxRates::checked_rate(-1.0).is_err());
+        assert!(TaxRates::checked_rate(7.5).is_err());
+        assert!(TaxRates::checked_rate(4294967301.0).is_err());
+        assert_eq!(ZoneType::from_name("Commercial"), Some(ZoneType::Commercial));
+        assert_eq!(ZoneType::from_name("farm"), None);
+    }
+
+    #[test]
+    fn test_settle_month() {
+        let world = world_with_zones();
+        let mut economy = Economy::new(1000);
+        economy.tax_rates.set_rate(ZoneType::Residential, 10);
+        economy.tax_rates.set_rate(ZoneType::Commercial, 10);
+
+        let report = BudgetSystem::settle_month(1, &world, &mut economy);
+
+        assert_eq!(report.residential_income, 100); // 100 * 10 * 10%
+        assert_eq!(report.commercial_income, 30); // 20 * 15 * 10%
+        assert_eq!(report.service_expenses, 50);
+        assert_eq!(report.net, 80);
+        assert_eq!(economy.treasury.balance, 1080);
+    }
+
+    #[test]
+    fn test_budget_system_runs_monthly() {
+        let world = world_with_zones();
+        let mut economy = Economy::default();
+        let mut events = EventQueue::new();
+        let mut notifications = EventQueue::new();
+        let mut system = BudgetSystem::new(3);
+
+        assert!(system.update(&world, &mut economy, &mut events, &mut notifications).is_none());
+        assert!(system.update(&world, &mut economy, &mut events, &mut notifications).is_none());
+        let report = system.update(&world, &mut economy, &mut events, &mut notifications);
+
+        assert!(report.is_some());
+        assert_eq!(system.month(), 1);
+        assert!(events.iter().any(|e| matches!(e, GameEvent::BudgetReport(_))));
+        assert_eq!(economy.last_report, report);
+    }
+
+    #[test]
+    fn test_deficit_raises_notification() {
+        let mut world = World::new();
+        let station = world.create_entity();
+        world.add_component(station, ServiceUpkeepComponent { monthly_cost: 500 }).unwrap();
+
+        let mut economy = Economy::new(100);
+        let mut events = EventQueue::new();
+        let mut notifications = EventQueue::new();
+        let mut system = BudgetSystem::new(1);
+        system.update(&world, &mut economy, &mut events, &mut notifications);
+
+        assert_eq!(economy.treasury.balance, -400);
+        assert_eq!(notifications.len(), 2); // Deficit and bankruptcy
+    }
+
+    #[test]
+    fn test_loans() {
+        let mut treasury = Treasury::new(0);
+        assert!(treasury.take_loan(1000, 1, 10).is_ok());
+        assert_eq!(treasury.balance, 1000);
+        assert_eq!(treasury.total_debt(), 1000);
+        assert!(treasury.take_loan(0, 1, 10).is_err());
+        assert!(treasury.take_loan(i64::MAX, 1, 24).is_err());
+        assert!(treasury.take_loan(Treasury::MAX_LOAN_AMOUNT + 1, 1, 24).is_err());
+        assert_eq!((treasury.balance, treasury.loans.len()), (1000, 1));
+        let mut full = Treasury::new(i64::MAX - 10);
+        assert!(full.take_loan(100, 1, 24).is_err());
+        assert!(full.loans.is_empty());
+
+        assert_eq!(treasury.repay_loan(0, 400), Ok(400));
+        assert_eq!(treasury.total_debt(), 600);
+        assert_eq!(treasury.repay_loan(0, 5000), Ok(600));
+        assert!(treasury.loans.is_empty());
+        assert!(treasury.repay_loan(0, 1).is_err());
+    }
+
+    #[test]
+    fn test_monthly_loan_installments() {
+        let world = World::new();
+        let mut economy = Economy::new(0);
+        economy.treasury.take_loan(300, 10, 3).unwrap();
+
+        let report = BudgetSystem::settle_month(1, &world, &mut economy);
+        assert_eq!(report.loan_payments, 130); // 100 installment + 30 interest
+        assert_eq!(economy.treasury.total_debt(), 200);
+    }
+}
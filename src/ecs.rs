@@ -1,7 +1,9 @@
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::marker::PhantomData;
-use std::cell::{RefCell, Ref, RefMut};
+use std::cell::{Cell, RefCell, Ref, RefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::Serialize;
 
 /// Entity is just a unique identifier
 #[allow(dead_code)] // Used across modules but compiler doesn't always see it
@@ -9,12 +11,17 @@ pub type Entity = u32;
 
 /// Component trait for validation, getters, setters, and utility functions
 pub trait Component: Any + Send + Sync {
-    /// Validates the component state
-    #[allow(dead_code)] // Framework method, may be used by component implementations
+    /// Validates the component state; `World::add_component` and bundle spawning reject invalid components
     fn validate(&self) -> bool {
         true // Default implementation
     }
     
+    /// Called just before the component is stored on an entity, e.g. to register it in a spatial index
+    fn on_insert(&self, _world: &mut World, _entity: Entity) {}
+    
+    /// Called after the component was removed from an entity, replaced or destroyed with it
+    fn on_remove(&self, _world: &mut World, _entity: Entity) {}
+    
     /// Convert to Any trait object for type erasure
     fn as_any(&self) -> &dyn Any;
     
@@ -24,8 +31,32 @@ pub trait Component: Any + Send + Sync {
     /// Create a deep copy of this component for diffing purposes
     #[allow(dead_code)] // Framework method for future diffing system
     fn clone_box(&self) -> Box<dyn Component>;
+    
+    /// Rewrite entity references after the component moved to another world (see `World::merge`)
+    /// Components that store entity IDs must override this; references missing from the map are kept
+    fn remap_entities(&mut self, _entity_remap: &HashMap<Entity, Entity>) {}
 }
 
+/// A component that failed `Component::validate`, or that a bundle holds twice, with the entity and type it was meant for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidComponent {
+    pub entity: Entity,
+    pub component: &'static str,
+    /// The bundle held more than one component of this type
+    pub duplicate: bool,
+}
+
+impl std::fmt::Display for InvalidComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.duplicate {
+            true => write!(f, "Bundle for entity {} holds {} more than once", self.entity, self.component),
+            false => write!(f, "Invalid {} for entity {}", self.component, self.entity),
+        }
+    }
+}
+
+impl std::error::Error for InvalidComponent {}
+
 /// Mut<T> wrapper to explicitly mark components that should be accessed mutably
 #[allow(dead_code)] // Framework type for future mutable access patterns
 pub struct Mut<T> {
@@ -64,52 +95,421 @@ impl<T: Component + 'static> AccessMode for Mut<T> {
     }
 }
 
+/// How a component pool stores its components, chosen per type with `World::register_storage`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum StorageStrategy {
+    /// Hash table keyed by entity; suits components most entities have
+    #[default]
+    Dense,
+    /// Sparse set: an entity-indexed slot table over a packed array, for components few entities carry
+    /// at a time (construction sites, demolition marks), so queries over them only visit the holders
+    Sparse,
+}
+
+type ComponentCell = RefCell<Box<dyn Component>>;
+
+/// Stamps for pool and entity list changes, unique across worlds so a replaced pool never matches a stale stamp
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+// Component storage behind a pool
+enum PoolStorage {
+    Dense(HashMap<Entity, ComponentCell>),
+    Sparse {
+        // Index into `dense` for each entity ID, `None` for entities without the component
+        slots: Vec<Option<usize>>,
+        dense: Vec<(Entity, ComponentCell)>,
+    },
+}
+
 /// Storage for a specific component type using RefCell for interior mutability
 #[allow(dead_code)] // Framework storage component, part of ECS design
 pub struct ComponentPool {
-    components: HashMap<Entity, RefCell<Box<dyn Component>>>,
+    storage: PoolStorage,
+    // Type name and size of the stored component, for memory reports
+    component_name: &'static str,
+    component_size: usize,
+    high_water_mark: usize,
+    // Changes whenever an entity gains or loses this component, invalidating cached queries
+    generation: u64,
+}
+
+/// Memory use of one component pool
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentPoolStats {
+    pub component: &'static str,
+    pub storage: StorageStrategy,
+    pub count: usize,
+    /// Approximate heap use: the pool's allocated slots plus the boxed components
+    pub approx_bytes: usize,
+    /// Highest count the pool has held
+    pub high_water_mark: usize,
+}
+
+/// Memory use of all component pools, plus components left behind by destroyed entities
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryReport {
+    pub entities: usize,
+    pub pools: Vec<ComponentPoolStats>,
+    pub total_bytes: usize,
+    pub orphaned_components: Vec<(Entity, &'static str)>,
 }
 
 #[allow(dead_code)] // Framework implementation, part of ECS design  
 impl ComponentPool {
     pub fn new() -> Self {
         Self {
-            components: HashMap::new(),
+            storage: PoolStorage::Dense(HashMap::new()),
+            component_name: "unknown",
+            component_size: 0,
+            high_water_mark: 0,
+            generation: next_generation(),
+        }
+    }
+    
+    /// Create a pool for components of type T
+    pub fn for_type<T: Component + 'static>() -> Self {
+        Self {
+            component_name: std::any::type_name::<T>(),
+            component_size: std::mem::size_of::<T>(),
+            ..Self::new()
         }
     }
     
-    pub fn insert(&mut self, entity: Entity, component: Box<dyn Component>) {
-        self.components.insert(entity, RefCell::new(component));
+    /// An empty pool for the same component type and storage strategy
+    pub fn empty_like(&self) -> Self {
+        let mut pool = Self {
+            component_name: self.component_name,
+            component_size: self.component_size,
+            ..Self::new()
+        };
+        pool.set_storage(self.storage());
+        pool
+    }
+    
+    /// A pool holding clones of every stored component
+    pub fn copy(&self) -> Self {
+        let mut pool = self.empty_like();
+        for entity in self.entities() {
+            if let Some(component) = self.get(entity) {
+                pool.insert(entity, component.clone_box());
+            }
+        }
+        pool
+    }
+    
+    pub fn storage(&self) -> StorageStrategy {
+        match self.storage {
+            PoolStorage::Dense(_) => StorageStrategy::Dense,
+            PoolStorage::Sparse { .. } => StorageStrategy::Sparse,
+        }
+    }
+    
+    /// Switch storage strategy, moving the stored components over
+    pub fn set_storage(&mut self, strategy: StorageStrategy) {
+        if self.storage() == strategy {
+            return;
+        }
+        let components = self.drain();
+        self.storage = match strategy {
+            StorageStrategy::Dense => PoolStorage::Dense(HashMap::with_capacity(components.len())),
+            StorageStrategy::Sparse => PoolStorage::Sparse { slots: Vec::new(), dense: Vec::with_capacity(components.len()) },
+        };
+        for (entity, component) in components {
+            self.insert(entity, component);
+        }
+    }
+    
+    /// Store a component, returning the one it replaced
+    pub fn insert(&mut self, entity: Entity, component: Box<dyn Component>) -> Option<Box<dyn Component>> {
+        let replaced = match &mut self.storage {
+            PoolStorage::Dense(components) => components.insert(entity, RefCell::new(component)).map(RefCell::into_inner),
+            PoolStorage::Sparse { slots, dense } => {
+                let slot = entity as usize;
+                if slot >= slots.len() {
+                    slots.resize(slot + 1, None);
+                }
+                match slots[slot] {
+                    Some(index) => Some(std::mem::replace(&mut dense[index].1, RefCell::new(component)).into_inner()),
+                    None => {
+                        slots[slot] = Some(dense.len());
+                        dense.push((entity, RefCell::new(component)));
+                        None
+                    }
+                }
+            }
+        };
+        if replaced.is_none() {
+            self.generation = next_generation();
+        }
+        self.high_water_mark = self.high_water_mark.max(self.len());
+        replaced
+    }
+    
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            PoolStorage::Dense(components) => components.len(),
+            PoolStorage::Sparse { dense, .. } => dense.len(),
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Make room for `additional` more components
+    pub fn reserve(&mut self, additional: usize) {
+        match &mut self.storage {
+            PoolStorage::Dense(components) => components.reserve(additional),
+            PoolStorage::Sparse { dense, .. } => dense.reserve(additional),
+        }
+    }
+    
+    pub fn stats(&self) -> ComponentPoolStats {
+        let slot_size = std::mem::size_of::<(Entity, ComponentCell)>();
+        let allocated = match &self.storage {
+            PoolStorage::Dense(components) => components.capacity() * slot_size,
+            PoolStorage::Sparse { slots, dense } => slots.capacity() * std::mem::size_of::<Option<usize>>() + dense.capacity() * slot_size,
+        };
+        ComponentPoolStats {
+            component: self.component_name,
+            storage: self.storage(),
+            count: self.len(),
+            approx_bytes: allocated + self.len() * self.component_size,
+            high_water_mark: self.high_water_mark,
+        }
+    }
+    
+    fn cell(&self, entity: Entity) -> Option<&ComponentCell> {
+        match &self.storage {
+            PoolStorage::Dense(components) => components.get(&entity),
+            PoolStorage::Sparse { slots, dense } => {
+                let index = (*slots.get(entity as usize)?)?;
+                Some(&dense[index].1)
+            }
+        }
     }
     
     pub fn get(&self, entity: Entity) -> Option<Ref<'_, Box<dyn Component>>> {
-        self.components.get(&entity).map(|c| c.borrow())
+        self.cell(entity).map(|c| c.borrow())
     }
     
     pub fn get_mut(&self, entity: Entity) -> Option<RefMut<'_, Box<dyn Component>>> {
-        self.components.get(&entity).map(|c| c.borrow_mut())
+        self.cell(entity).map(|c| c.borrow_mut())
     }
     
-    pub fn remove(&mut self, entity: Entity) -> Option<RefCell<Box<dyn Component>>> {
-        self.components.remove(&entity)
+    pub fn remove(&mut self, entity: Entity) -> Option<ComponentCell> {
+        let removed = match &mut self.storage {
+            PoolStorage::Dense(components) => components.remove(&entity),
+            PoolStorage::Sparse { slots, dense } => {
+                let index = slots.get_mut(entity as usize)?.take()?;
+                // The last component fills the gap, so the packed array stays contiguous
+                let (_, removed) = dense.swap_remove(index);
+                if let Some((moved, _)) = dense.get(index) {
+                    slots[*moved as usize] = Some(index);
+                }
+                Some(removed)
+            }
+        };
+        if removed.is_some() {
+            self.generation = next_generation();
+        }
+        removed
+    }
+    
+    /// Stamp of the pool's last membership change
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
     
     pub fn contains(&self, entity: Entity) -> bool {
-        self.components.contains_key(&entity)
+        self.cell(entity).is_some()
+    }
+    
+    /// Entities with a component in this pool, in no particular order; queries sort what they return
+    pub fn entities(&self) -> Box<dyn Iterator<Item = Entity> + '_> {
+        match &self.storage {
+            PoolStorage::Dense(components) => Box::new(components.keys().copied()),
+            PoolStorage::Sparse { dense, .. } => Box::new(dense.iter().map(|(entity, _)| *entity)),
+        }
+    }
+    
+    /// Take every component out of the pool, in ascending ID order so their insert hooks run in the same order
+    /// every run
+    pub fn drain(&mut self) -> Vec<(Entity, Box<dyn Component>)> {
+        self.generation = next_generation();
+        let mut drained: Vec<(Entity, Box<dyn Component>)> = match &mut self.storage {
+            PoolStorage::Dense(components) => components.drain().map(|(entity, cell)| (entity, cell.into_inner())).collect(),
+            PoolStorage::Sparse { slots, dense } => {
+                slots.clear();
+                dense.drain(..).map(|(entity, cell)| (entity, cell.into_inner())).collect()
+            }
+        };
+        drained.sort_unstable_by_key(|(entity, _)| *entity);
+        drained
+    }
+}
+
+/// Human-readable entity name, looked up with `World::find_by_name`
+/// Replace it with `add_component` instead of editing it in place so the name index stays current
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Name(pub String);
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Self(name.to_string())
     }
     
-    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
-        self.components.keys().copied()
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
+impl Component for Name {
+    fn validate(&self) -> bool {
+        !self.0.is_empty()
+    }
+    
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Lightweight string markers for grouping entities, e.g. "service" or "landmark"
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Tags(Vec<String>);
+
+impl Tags {
+    pub fn new(tags: &[&str]) -> Self {
+        let mut result = Self::default();
+        for tag in tags {
+            result.insert(tag);
+        }
+        result
+    }
+    
+    pub fn insert(&mut self, tag: &str) {
+        if !self.has(tag) {
+            self.0.push(tag.to_string());
+        }
+    }
+    
+    pub fn remove(&mut self, tag: &str) -> bool {
+        let before = self.0.len();
+        self.0.retain(|existing| existing != tag);
+        self.0.len() != before
+    }
+    
+    pub fn has(&self, tag: &str) -> bool {
+        self.0.iter().any(|existing| existing == tag)
+    }
+    
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|tag| tag.as_str())
+    }
+}
+
+impl Component for Tags {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Group of components inserted together, implemented for tuples of up to eight components
+pub trait Bundle: 'static {
+    /// Component types of the bundle, in the order `into_components` returns them
+    fn component_types() -> Vec<TypeId>;
+    
+    /// Empty pools for the bundle's component types, in `component_types` order
+    fn empty_pools() -> Vec<ComponentPool>;
+    
+    /// Box the components, in `component_types` order
+    fn into_components(self) -> Vec<Box<dyn Component>>;
+}
+
+macro_rules! impl_bundle {
+    ($($component:ident),+) => {
+        impl<$($component: Component + 'static),+> Bundle for ($($component,)+) {
+            fn component_types() -> Vec<TypeId> {
+                vec![$(TypeId::of::<$component>()),+]
+            }
+            
+            fn empty_pools() -> Vec<ComponentPool> {
+                vec![$(ComponentPool::for_type::<$component>()),+]
+            }
+            
+            #[allow(non_snake_case)]
+            fn into_components(self) -> Vec<Box<dyn Component>> {
+                let ($($component,)+) = self;
+                vec![$(Box::new($component) as Box<dyn Component>),+]
+            }
+        }
+    };
+}
+
+impl_bundle!(C1);
+impl_bundle!(C1, C2);
+impl_bundle!(C1, C2, C3);
+impl_bundle!(C1, C2, C3, C4);
+impl_bundle!(C1, C2, C3, C4, C5);
+impl_bundle!(C1, C2, C3, C4, C5, C6);
+impl_bundle!(C1, C2, C3, C4, C5, C6, C7);
+impl_bundle!(C1, C2, C3, C4, C5, C6, C7, C8);
+
+/// Declares a struct whose fields are inserted together as a bundle, e.g.
+/// `bundle! { pub struct HouseBundle { position: GridPositionComponent, building: BuildingComponent } }`
+/// This is the declarative stand-in for `#[derive(Bundle)]`; every field must be a `Component`
+#[macro_export]
+macro_rules! bundle {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident { $($field_vis:vis $field:ident: $ty:ty),+ $(,)? }) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty),+
+        }
+        
+        impl $crate::ecs::Bundle for $name {
+            fn component_types() -> Vec<std::any::TypeId> {
+                vec![$(std::any::TypeId::of::<$ty>()),+]
+            }
+            
+            fn empty_pools() -> Vec<$crate::ecs::ComponentPool> {
+                vec![$($crate::ecs::ComponentPool::for_type::<$ty>()),+]
+            }
+            
+            fn into_components(self) -> Vec<Box<dyn $crate::ecs::Component>> {
+                vec![$(Box::new(self.$field) as Box<dyn $crate::ecs::Component>),+]
+            }
+        }
+    };
+}
+
 /// System trait with Dependencies and Iterators associated types as specified
 #[allow(dead_code)] // Framework trait for system architecture
 pub trait System {
     type Dependencies;
-    type Iterators;
+    /// Entity iterators borrowing the world for one update
+    type Iterators<'w>;
 
-    fn update(&mut self, iterators: Self::Iterators);
+    fn update(&mut self, iterators: Self::Iterators<'_>);
 }
 
 /// Helper trait for system dependency resolution 
@@ -155,17 +555,17 @@ pub trait SystemMarker {
 
 /// Entity Iterator that returns component tuples (variable number of components 0-64)
 #[allow(dead_code)] // Framework iterator for ECS queries
-pub struct EntIt<T> {
-    world: *const World,
+pub struct EntIt<'w, T> {
+    world: &'w World,
     entities: Vec<Entity>,
     index: usize,
     _phantom: PhantomData<T>,
 }
 
 /// Implementation for EntIt with 2 components (main case from problem statement)
-impl<A1: AccessMode, A2: AccessMode> EntIt<(A1, A2)> {
+impl<'w, A1: AccessMode, A2: AccessMode> EntIt<'w, (A1, A2)> {
     #[allow(dead_code)] // Framework method for ECS query system
-    fn new_2(world: *const World, entities: Vec<Entity>) -> Self {
+    fn new_2(world: &'w World, entities: Vec<Entity>) -> Self {
         Self {
             world,
             entities,
@@ -176,9 +576,9 @@ impl<A1: AccessMode, A2: AccessMode> EntIt<(A1, A2)> {
 }
 
 /// Implementation for EntIt with 4 components (extended case from problem statement)
-impl<A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> EntIt<(A1, A2, A3, A4)> {
+impl<'w, A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> EntIt<'w, (A1, A2, A3, A4)> {
     #[allow(dead_code)] // Framework method for ECS query system
-    fn new_4(world: *const World, entities: Vec<Entity>) -> Self {
+    fn new_4(world: &'w World, entities: Vec<Entity>) -> Self {
         Self {
             world,
             entities,
@@ -189,8 +589,8 @@ impl<A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> EntIt<(A1,
 }
 
 /// Iterator implementation for 2 components
-impl<A1: AccessMode, A2: AccessMode> Iterator for EntIt<(A1, A2)> {
-    type Item = (EntityComponentRef<A1::Component>, EntityComponentRef<A2::Component>);
+impl<'w, A1: AccessMode, A2: AccessMode> Iterator for EntIt<'w, (A1, A2)> {
+    type Item = (EntityComponentRef<'w, A1::Component>, EntityComponentRef<'w, A2::Component>);
     
     fn next(&mut self) -> Option<Self::Item> {
         if self.index >= self.entities.len() {
@@ -200,35 +600,19 @@ impl<A1: AccessMode, A2: AccessMode> Iterator for EntIt<(A1, A2)> {
         let entity = self.entities[self.index];
         self.index += 1;
         
-        unsafe {
-            let world = &*self.world;
-            
-            // Get first component
-            let comp1 = if A1::is_mutable() {
-                EntityComponentRef::Mutable(world.get_component_mut_raw::<A1::Component>(entity)?)
-            } else {
-                EntityComponentRef::Immutable(world.get_component_raw::<A1::Component>(entity)?)
-            };
-            
-            // Get second component
-            let comp2 = if A2::is_mutable() {
-                EntityComponentRef::Mutable(world.get_component_mut_raw::<A2::Component>(entity)?)
-            } else {
-                EntityComponentRef::Immutable(world.get_component_raw::<A2::Component>(entity)?)
-            };
-            
-            Some((comp1, comp2))
-        }
+        let comp1 = self.world.component_ref::<A1>(entity)?;
+        let comp2 = self.world.component_ref::<A2>(entity)?;
+        Some((comp1, comp2))
     }
 }
 
 /// Iterator implementation for 4 components
-impl<A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> Iterator for EntIt<(A1, A2, A3, A4)> {
+impl<'w, A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> Iterator for EntIt<'w, (A1, A2, A3, A4)> {
     type Item = (
-        EntityComponentRef<A1::Component>, 
-        EntityComponentRef<A2::Component>,
-        EntityComponentRef<A3::Component>,
-        EntityComponentRef<A4::Component>
+        EntityComponentRef<'w, A1::Component>, 
+        EntityComponentRef<'w, A2::Component>,
+        EntityComponentRef<'w, A3::Component>,
+        EntityComponentRef<'w, A4::Component>
     );
     
     fn next(&mut self) -> Option<Self::Item> {
@@ -239,75 +623,114 @@ impl<A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> Iterator fo
         let entity = self.entities[self.index];
         self.index += 1;
         
-        unsafe {
-            let world = &*self.world;
-            
-            // Get components
-            let comp1 = if A1::is_mutable() {
-                EntityComponentRef::Mutable(world.get_component_mut_raw::<A1::Component>(entity)?)
-            } else {
-                EntityComponentRef::Immutable(world.get_component_raw::<A1::Component>(entity)?)
-            };
-            
-            let comp2 = if A2::is_mutable() {
-                EntityComponentRef::Mutable(world.get_component_mut_raw::<A2::Component>(entity)?)
-            } else {
-                EntityComponentRef::Immutable(world.get_component_raw::<A2::Component>(entity)?)
-            };
-            
-            let comp3 = if A3::is_mutable() {
-                EntityComponentRef::Mutable(world.get_component_mut_raw::<A3::Component>(entity)?)
-            } else {
-                EntityComponentRef::Immutable(world.get_component_raw::<A3::Component>(entity)?)
-            };
-            
-            let comp4 = if A4::is_mutable() {
-                EntityComponentRef::Mutable(world.get_component_mut_raw::<A4::Component>(entity)?)
-            } else {
-                EntityComponentRef::Immutable(world.get_component_raw::<A4::Component>(entity)?)
-            };
-            
-            Some((comp1, comp2, comp3, comp4))
-        }
+        let comp1 = self.world.component_ref::<A1>(entity)?;
+        let comp2 = self.world.component_ref::<A2>(entity)?;
+        let comp3 = self.world.component_ref::<A3>(entity)?;
+        let comp4 = self.world.component_ref::<A4>(entity)?;
+        Some((comp1, comp2, comp3, comp4))
     }
 }
 
 /// Wrapper for component references that can be either mutable or immutable
+/// It owns the RefCell guard, so the component's borrow ends when the wrapper is dropped
 #[allow(dead_code)] // Framework enum for component access patterns
-pub enum EntityComponentRef<T: Component> {
-    Immutable(*const T),
-    Mutable(*mut T),
+pub enum EntityComponentRef<'w, T: Component> {
+    Immutable(Ref<'w, T>),
+    Mutable(RefMut<'w, T>),
 }
 
 #[allow(dead_code)] // Framework implementation for component access
-impl<T: Component> EntityComponentRef<T> {
+impl<T: Component> EntityComponentRef<'_, T> {
     /// Get an immutable reference to the component
     pub fn get(&self) -> &T {
-        unsafe {
-            match self {
-                EntityComponentRef::Immutable(ptr) => &**ptr,
-                EntityComponentRef::Mutable(ptr) => &**ptr,
-            }
+        match self {
+            EntityComponentRef::Immutable(component) => component,
+            EntityComponentRef::Mutable(component) => component,
         }
     }
     
     /// Get a mutable reference to the component (only works for Mutable variants)
     pub fn get_mut(&mut self) -> Option<&mut T> {
-        unsafe {
-            match self {
-                EntityComponentRef::Immutable(_) => None,
-                EntityComponentRef::Mutable(ptr) => Some(&mut **ptr),
-            }
+        match self {
+            EntityComponentRef::Immutable(_) => None,
+            EntityComponentRef::Mutable(component) => Some(component),
         }
     }
 }
 
+impl<T: Component> std::ops::Deref for EntityComponentRef<'_, T> {
+    type Target = T;
+    
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
 /// World contains entities, components, and systems
 #[allow(dead_code)] // Core ECS World struct, used across modules but compiler analysis can miss it
 pub struct World {
     next_entity_id: Entity,
     entities: Vec<Entity>,
-    component_pools: HashMap<TypeId, ComponentPool>,
+    // Ordered so per-pool work, such as removing a destroyed entity's components, runs in the same order every run
+    component_pools: BTreeMap<TypeId, ComponentPool>,
+    // Debug mode: panic when components are attached to entities that do not exist
+    leak_checks: bool,
+    // Name -> entity index for `find_by_name`, kept in step with the Name pool
+    names: HashMap<String, Entity>,
+    // Component types covered by `state_hash`, sorted by their stable name
+    registry: Vec<RegisteredComponent>,
+    // Stamp of the last change to `entities`
+    entities_generation: u64,
+    // Query results by component signature, valid while the pools and entity list they came from are unchanged
+    query_cache: RefCell<HashMap<Vec<TypeId>, CachedQuery>>,
+    query_cache_stats: Cell<QueryCacheStats>,
+}
+
+struct CachedQuery {
+    entities_generation: u64,
+    // Generation of each queried pool, `None` for pools that didn't exist
+    pool_generations: Vec<Option<u64>>,
+    entities: Vec<Entity>,
+}
+
+/// How often `entities_with_components` was answered from the query cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Writes the `Debug` form of a type-erased component of one registered type
+type StateFormatter = fn(&dyn Component, &mut dyn std::fmt::Write) -> std::fmt::Result;
+
+/// Feeds formatted text straight into an FNV-1a hash
+struct FnvWriter(u64);
+
+impl std::fmt::Write for FnvWriter {
+    fn write_str(&mut self, text: &str) -> std::fmt::Result {
+        self.0 = fnv1a(text.as_bytes(), self.0);
+        Ok(())
+    }
+}
+
+/// A component type registered for state hashing under a name that doesn't depend on its Rust path
+#[derive(Clone, Copy)]
+struct RegisteredComponent {
+    name: &'static str,
+    type_id: TypeId,
+    format: StateFormatter,
+}
+
+/// FNV-1a offset basis, the starting value for `fnv1a`
+pub const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+
+/// 64-bit FNV-1a, stable across runs, platforms and compiler versions
+pub fn fnv1a(bytes: &[u8], mut hash: u64) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    hash
 }
 
 #[allow(dead_code)] // Core ECS World implementation, used across modules
@@ -317,25 +740,369 @@ impl World {
         Self {
             next_entity_id: 0,
             entities: Vec::new(),
-            component_pools: HashMap::new(),
+            component_pools: BTreeMap::new(),
+            leak_checks: false,
+            names: HashMap::new(),
+            registry: Vec::new(),
+            entities_generation: next_generation(),
+            query_cache: RefCell::new(HashMap::new()),
+            query_cache_stats: Cell::new(QueryCacheStats::default()),
+        }
+    }
+    
+    /// A copy of every entity and component, e.g. to restore the world to this state later
+    pub fn snapshot(&self) -> World {
+        World {
+            next_entity_id: self.next_entity_id,
+            entities: self.entities.clone(),
+            component_pools: self.component_pools.iter().map(|(type_id, pool)| (*type_id, pool.copy())).collect(),
+            leak_checks: self.leak_checks,
+            names: self.names.clone(),
+            registry: self.registry.clone(),
+            entities_generation: next_generation(),
+            query_cache: RefCell::new(HashMap::new()),
+            query_cache_stats: Cell::new(QueryCacheStats::default()),
         }
     }
     
+    /// Enable debug assertions for components attached to missing or destroyed entities,
+    /// which would otherwise linger in their pool unnoticed
+    pub fn set_leak_checks(&mut self, enabled: bool) {
+        self.leak_checks = enabled;
+    }
+    
     /// Create a new entity and return its ID
     pub fn create_entity(&mut self) -> Entity {
         let entity = self.next_entity_id;
         self.next_entity_id += 1;
         self.entities.push(entity);
+        self.entities_generation = next_generation();
         entity
     }
     
-    /// Add a component to an entity
-    pub fn add_component<T: Component + 'static>(&mut self, entity: Entity, component: T) {
+    /// Create an entity with every component of a bundle, e.g. `world.spawn((position, sprite, building))`
+    /// Nothing is created when a component fails validation
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Result<Entity, InvalidComponent> {
+        let components = bundle.into_components();
+        Self::validate_bundle::<B>(self.next_entity_id, &components)?;
+        let entity = self.create_entity();
+        self.insert_components::<B>(entity, components);
+        Ok(entity)
+    }
+    
+    /// Add every component of a bundle to an existing entity, replacing components of the same type
+    /// Nothing is added when a component fails validation
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) -> Result<(), InvalidComponent> {
+        if self.leak_checks {
+            debug_assert!(
+                self.entities.contains(&entity),
+                "{} added to entity {}, which does not exist",
+                std::any::type_name::<B>(),
+                entity,
+            );
+        }
+        let components = bundle.into_components();
+        Self::validate_bundle::<B>(entity, &components)?;
+        self.insert_components::<B>(entity, components);
+        Ok(())
+    }
+    
+    fn validate_bundle<B: Bundle>(entity: Entity, components: &[Box<dyn Component>]) -> Result<(), InvalidComponent> {
+        Self::check_unique_types::<B>(entity)?;
+        match components.iter().zip(B::empty_pools()).find(|(component, _)| !component.validate()) {
+            Some((_, pool)) => Err(InvalidComponent { entity, component: pool.component_name, duplicate: false }),
+            None => Ok(()),
+        }
+    }
+    
+    /// A bundle holding the same component type twice would have one silently overwrite the other
+    fn check_unique_types<B: Bundle>(entity: Entity) -> Result<(), InvalidComponent> {
+        let types = B::component_types();
+        match types.iter().enumerate().find(|(index, type_id)| types[..*index].contains(type_id)) {
+            Some((index, _)) => Err(InvalidComponent { entity, component: B::empty_pools()[index].component_name, duplicate: true }),
+            None => Ok(()),
+        }
+    }
+    
+    fn insert_components<B: Bundle>(&mut self, entity: Entity, components: Vec<Box<dyn Component>>) {
+        let named = B::component_types().contains(&TypeId::of::<Name>());
+        if named {
+            self.unindex_name(entity);
+        }
+        for component in &components {
+            component.on_insert(self, entity);
+        }
+        let mut replaced = Vec::new();
+        for ((type_id, empty), component) in B::component_types().into_iter().zip(B::empty_pools()).zip(components) {
+            replaced.extend(self.component_pools.entry(type_id).or_insert(empty).insert(entity, component));
+        }
+        if named {
+            self.index_names(&[entity]);
+        }
+        for component in replaced {
+            component.on_remove(self, entity);
+        }
+    }
+    
+    /// Create one entity per bundle, e.g. thousands of citizens at once
+    /// Entity IDs are reserved up front and each pool is looked up once for the whole batch;
+    /// nothing is created when any component fails validation or the bundle repeats a component type
+    pub fn spawn_batch<B: Bundle, I: IntoIterator<Item = B>>(&mut self, bundles: I) -> Result<Vec<Entity>, InvalidComponent> {
+        let first = self.next_entity_id;
+        Self::check_unique_types::<B>(first)?;
+        let batch: Vec<Vec<Box<dyn Component>>> = bundles.into_iter().map(Bundle::into_components).collect();
+        for (offset, components) in batch.iter().enumerate() {
+            Self::validate_bundle::<B>(first + offset as Entity, components)?;
+        }
+        self.next_entity_id += batch.len() as Entity;
+        let spawned: Vec<Entity> = (first..self.next_entity_id).collect();
+        self.entities.extend_from_slice(&spawned);
+        self.entities_generation = next_generation();
+        for (entity, components) in spawned.iter().zip(&batch) {
+            for component in components {
+                component.on_insert(self, *entity);
+            }
+        }
+        
+        // Take the pools out of the map so they can all be filled in one pass
+        let mut pools: Vec<(TypeId, ComponentPool)> = B::component_types().into_iter()
+            .zip(B::empty_pools())
+            .map(|(type_id, empty)| (type_id, self.component_pools.remove(&type_id).unwrap_or(empty)))
+            .collect();
+        for (_, pool) in &mut pools {
+            pool.reserve(batch.len());
+        }
+        
+        for (entity, components) in spawned.iter().zip(batch) {
+            for ((_, pool), component) in pools.iter_mut().zip(components) {
+                pool.insert(*entity, component);
+            }
+        }
+        self.component_pools.extend(pools);
+        if B::component_types().contains(&TypeId::of::<Name>()) {
+            self.index_names(&spawned);
+        }
+        Ok(spawned)
+    }
+    
+    /// Destroy an entity and remove all of its components
+    /// Returns false if the entity does not exist
+    pub fn destroy_entity(&mut self, entity: Entity) -> bool {
+        let Some(index) = self.entities.iter().position(|&e| e == entity) else {
+            return false;
+        };
+        self.entities.remove(index);
+        self.entities_generation = next_generation();
+        self.unindex_name(entity);
+        let removed: Vec<Box<dyn Component>> = self.component_pools.values_mut()
+            .filter_map(|pool| pool.remove(entity))
+            .map(RefCell::into_inner)
+            .collect();
+        for component in removed {
+            component.on_remove(self, entity);
+        }
+        true
+    }
+    
+    /// Move every entity of another world into this one, e.g. a scene prepared on a loader thread
+    /// Entities already in `entity_remap` are merged into the given existing entities; all others
+    /// get fresh IDs, which are added to the map. Components then rewrite their entity references
+    pub fn merge(&mut self, other: World, entity_remap: &mut HashMap<Entity, Entity>) {
+        for &entity in &other.entities {
+            entity_remap.entry(entity).or_insert_with(|| self.create_entity());
+        }
+        
+        let named: Vec<Entity> = other.component_pools.get(&TypeId::of::<Name>())
+            .map(|pool| pool.entities().filter_map(|entity| entity_remap.get(&entity).copied()).collect())
+            .unwrap_or_default();
+        for &entity in &named {
+            self.unindex_name(entity);
+        }
+        
+        for (type_id, mut pool) in other.component_pools {
+            let mut merged = Vec::new();
+            for (entity, mut component) in pool.drain() {
+                let Some(&new_entity) = entity_remap.get(&entity) else { continue };
+                component.remap_entities(entity_remap);
+                component.on_insert(self, new_entity);
+                merged.push((new_entity, component));
+            }
+            
+            let target = self.component_pools.entry(type_id).or_insert_with(|| pool.empty_like());
+            let replaced: Vec<(Entity, Box<dyn Component>)> = merged.into_iter()
+                .filter_map(|(entity, component)| target.insert(entity, component).map(|old| (entity, old)))
+                .collect();
+            for (entity, component) in replaced {
+                component.on_remove(self, entity);
+            }
+        }
+        self.index_names(&named);
+    }
+    
+    /// Add a component to an entity, replacing one of the same type
+    /// Fails without changing the entity when the component doesn't pass `Component::validate`
+    pub fn add_component<T: Component + 'static>(&mut self, entity: Entity, component: T) -> Result<(), InvalidComponent> {
+        if self.leak_checks {
+            debug_assert!(
+                self.entities.contains(&entity),
+                "{} added to entity {}, which does not exist",
+                std::any::type_name::<T>(),
+                entity,
+            );
+        }
+        if !component.validate() {
+            return Err(InvalidComponent { entity, component: std::any::type_name::<T>(), duplicate: false });
+        }
         let type_id = TypeId::of::<T>();
+        let named = type_id == TypeId::of::<Name>();
+        if named {
+            self.unindex_name(entity);
+        }
+        component.on_insert(self, entity);
         let pool = self.component_pools
             .entry(type_id)
-            .or_insert_with(ComponentPool::new);
-        pool.insert(entity, Box::new(component));
+            .or_insert_with(ComponentPool::for_type::<T>);
+        let replaced = pool.insert(entity, Box::new(component));
+        if named {
+            self.index_names(&[entity]);
+        }
+        if let Some(replaced) = replaced {
+            replaced.on_remove(self, entity);
+        }
+        Ok(())
+    }
+    
+    /// Entity with the given `Name`; names are meant to be unique, duplicates resolve to one of the holders
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        let indexed = self.names.get(name).copied()
+            .filter(|&entity| self.get_component::<Name>(entity).is_some_and(|current| current.as_str() == name));
+        // The index misses names edited in place and duplicates left behind by a removal
+        indexed.or_else(|| {
+            self.entities_with_components(&[TypeId::of::<Name>()]).into_iter()
+                .find(|&entity| self.get_component::<Name>(entity).is_some_and(|current| current.as_str() == name))
+        })
+    }
+    
+    /// Entities carrying a tag, in creation order
+    pub fn find_by_tag(&self, tag: &str) -> Vec<Entity> {
+        self.entities_with_components(&[TypeId::of::<Tags>()]).into_iter()
+            .filter(|&entity| self.get_component::<Tags>(entity).is_some_and(|tags| tags.has(tag)))
+            .collect()
+    }
+    
+    fn index_names(&mut self, entities: &[Entity]) {
+        for &entity in entities {
+            let name = self.get_component::<Name>(entity).map(|name| name.0.clone());
+            if let Some(name) = name {
+                self.names.insert(name, entity);
+            }
+        }
+    }
+    
+    fn unindex_name(&mut self, entity: Entity) {
+        self.names.retain(|_, indexed| *indexed != entity);
+    }
+    
+    /// Include a component type in `state_hash`, identified by a name that must stay the same across builds
+    /// Its `Debug` output is what gets hashed, so it must not contain unordered collections
+    pub fn register_component<T: Component + std::fmt::Debug + 'static>(&mut self, name: &'static str) {
+        let type_id = TypeId::of::<T>();
+        if let Some(existing) = self.registry.iter().find(|entry| entry.name == name || entry.type_id == type_id) {
+            assert!(existing.name == name && existing.type_id == type_id,
+                "Component name '{}' is registered for two types", name);
+            return;
+        }
+        let format: StateFormatter = |component, out| match component.as_any().downcast_ref::<T>() {
+            Some(component) => write!(out, "{:?}", component),
+            None => Ok(()),
+        };
+        let index = self.registry.partition_point(|entry| entry.name < name);
+        self.registry.insert(index, RegisteredComponent { name, type_id, format });
+    }
+    
+    /// Choose how components of type T are stored; components already stored are moved over
+    pub fn register_storage<T: Component + 'static>(&mut self, strategy: StorageStrategy) {
+        self.component_pools
+            .entry(TypeId::of::<T>())
+            .or_insert_with(ComponentPool::for_type::<T>)
+            .set_storage(strategy);
+    }
+    
+    /// Names of the component types covered by `state_hash`, sorted
+    pub fn registered_components(&self) -> Vec<&'static str> {
+        self.registry.iter().map(|entry| entry.name).collect()
+    }
+    
+    /// Deterministic hash of all entities and their registered components
+    /// Equal worlds hash equally regardless of pool iteration order; unregistered components are ignored
+    pub fn state_hash(&self) -> u64 {
+        let mut entities = self.entities.clone();
+        entities.sort_unstable();
+        
+        let mut hash = FnvWriter(FNV_OFFSET);
+        for entity in entities {
+            hash.0 = fnv1a(&entity.to_le_bytes(), hash.0);
+            for entry in &self.registry {
+                let Some(component) = self.component_pools.get(&entry.type_id).and_then(|pool| pool.get(entity)) else {
+                    continue;
+                };
+                hash.0 = fnv1a(entry.name.as_bytes(), hash.0);
+                // Writing to the hasher can't fail
+                let _ = (entry.format)(component.as_ref(), &mut hash);
+            }
+        }
+        hash.0
+    }
+    
+    /// The registered components of every entity as their `Debug` text, by entity and component name
+    /// The readable counterpart of `state_hash`
+    pub fn registered_state(&self) -> BTreeMap<Entity, BTreeMap<&'static str, String>> {
+        self.entities.iter().map(|&entity| {
+            let components = self.registry.iter()
+                .filter_map(|entry| {
+                    let component = self.component_pools.get(&entry.type_id).and_then(|pool| pool.get(entity))?;
+                    let mut text = String::new();
+                    let _ = (entry.format)(component.as_ref(), &mut text);
+                    Some((entry.name, text))
+                })
+                .collect();
+            (entity, components)
+        }).collect()
+    }
+    
+    /// Type names of the components an entity has that `register_component` doesn't cover, sorted
+    pub fn unregistered_components(&self, entity: Entity) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = self.component_pools.iter()
+            .filter(|(type_id, _)| !self.registry.iter().any(|entry| entry.type_id == **type_id))
+            .filter(|(_, pool)| pool.get(entity).is_some())
+            .map(|(_, pool)| pool.component_name)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+    
+    /// Per-pool component counts and approximate memory use, largest pools first
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut pools: Vec<ComponentPoolStats> = self.component_pools.values().map(|pool| pool.stats()).collect();
+        pools.sort_by(|a, b| b.approx_bytes.cmp(&a.approx_bytes).then(a.component.cmp(b.component)));
+        MemoryReport {
+            entities: self.entities.len(),
+            total_bytes: pools.iter().map(|pool| pool.approx_bytes).sum(),
+            pools,
+            orphaned_components: self.orphaned_components(),
+        }
+    }
+    
+    /// Components stored for entities that no longer exist, as (entity, component type)
+    pub fn orphaned_components(&self) -> Vec<(Entity, &'static str)> {
+        let live: std::collections::HashSet<Entity> = self.entities.iter().copied().collect();
+        let mut orphaned: Vec<(Entity, &'static str)> = self.component_pools.values()
+            .flat_map(|pool| pool.entities()
+                .filter(|entity| !live.contains(entity))
+                .map(|entity| (entity, pool.component_name)))
+            .collect();
+        orphaned.sort();
+        orphaned
     }
     
     /// Get a component from an entity (immutable)
@@ -358,33 +1125,31 @@ impl World {
         Some(RefMut::map(component, |c| c.as_any_mut().downcast_mut::<T>().unwrap()))
     }
     
-    /// Get raw pointer to component (for internal iterator use)
-    unsafe fn get_component_raw<T: Component + 'static>(&self, entity: Entity) -> Option<*const T> {
-        let type_id = TypeId::of::<T>();
-        let pool = self.component_pools.get(&type_id)?;
-        let component = pool.get(entity)?;
-        let raw_ptr = component.as_any().downcast_ref::<T>()? as *const T;
-        std::mem::forget(component); // Prevent Drop from running
-        Some(raw_ptr)
-    }
-    
-    /// Get raw mutable pointer to component (for internal iterator use)
-    unsafe fn get_component_mut_raw<T: Component + 'static>(&self, entity: Entity) -> Option<*mut T> {
-        let type_id = TypeId::of::<T>();
-        let pool = self.component_pools.get(&type_id)?;
-        let mut component = pool.get_mut(entity)?;
-        let raw_ptr = component.as_any_mut().downcast_mut::<T>()? as *mut T;
-        std::mem::forget(component); // Prevent Drop from running
-        Some(raw_ptr)
+    /// Borrow a component the way the access mode asks for, for the entity iterators
+    fn component_ref<A: AccessMode>(&self, entity: Entity) -> Option<EntityComponentRef<'_, A::Component>> {
+        let pool = self.component_pools.get(&A::component_type_id())?;
+        if A::is_mutable() {
+            let component = RefMut::filter_map(pool.get_mut(entity)?, |c| c.as_any_mut().downcast_mut::<A::Component>()).ok()?;
+            Some(EntityComponentRef::Mutable(component))
+        } else {
+            let component = Ref::filter_map(pool.get(entity)?, |c| c.as_any().downcast_ref::<A::Component>()).ok()?;
+            Some(EntityComponentRef::Immutable(component))
+        }
     }
     
     /// Remove a component from an entity
     pub fn remove_component<T: Component + 'static>(&mut self, entity: Entity) -> bool {
         let type_id = TypeId::of::<T>();
-        if let Some(pool) = self.component_pools.get_mut(&type_id) {
-            pool.remove(entity).is_some()
-        } else {
-            false
+        if type_id == TypeId::of::<Name>() {
+            self.unindex_name(entity);
+        }
+        let removed = self.component_pools.get_mut(&type_id).and_then(|pool| pool.remove(entity));
+        match removed {
+            Some(component) => {
+                component.into_inner().on_remove(self, entity);
+                true
+            }
+            None => false,
         }
     }
     
@@ -398,38 +1163,69 @@ impl World {
         }
     }
     
-    /// Get entities that have all specified component types
+    /// Get entities that have all specified component types, in ascending ID order
     pub fn entities_with_components(&self, component_types: &[TypeId]) -> Vec<Entity> {
+        let mut result = Vec::new();
+        self.entities_with_components_into(component_types, &mut result);
+        result
+    }
+    
+    /// Like `entities_with_components`, but fills a caller's buffer (e.g. from a `FrameArena`) instead of allocating
+    /// Results are cached by component signature until an entity gains or loses one of the queried components
+    pub fn entities_with_components_into(&self, component_types: &[TypeId], result: &mut Vec<Entity>) {
+        result.clear();
         if component_types.is_empty() {
-            return self.entities.clone();
+            result.extend_from_slice(&self.entities);
+            return;
         }
         
-        let mut result = Vec::new();
+        let pools: Vec<Option<&ComponentPool>> = component_types.iter()
+            .map(|type_id| self.component_pools.get(type_id))
+            .collect();
+        let pool_generations: Vec<Option<u64>> = pools.iter().map(|pool| pool.map(ComponentPool::generation)).collect();
+        let mut stats = self.query_cache_stats.get();
+        let mut cache = self.query_cache.borrow_mut();
+        if let Some(cached) = cache.get(component_types)
+            .filter(|cached| cached.entities_generation == self.entities_generation && cached.pool_generations == pool_generations) {
+            result.extend_from_slice(&cached.entities);
+            stats.hits += 1;
+            self.query_cache_stats.set(stats);
+            return;
+        }
+        stats.misses += 1;
+        self.query_cache_stats.set(stats);
         
-        for &entity in &self.entities {
-            let has_all = component_types.iter().all(|&type_id| {
-                self.component_pools
-                    .get(&type_id)
-                    .map_or(false, |pool| pool.contains(entity))
-            });
-            
-            if has_all {
-                result.push(entity);
+        // The smallest pool drives the query, so rare components are cheap to look up
+        if let Some(pools) = pools.into_iter().collect::<Option<Vec<&ComponentPool>>>() {
+            if let Some(driver) = pools.iter().min_by_key(|pool| pool.len()) {
+                result.extend(driver.entities()
+                    .filter(|&entity| pools.iter().all(|pool| pool.contains(entity)))
+                    // Entity IDs only grow, so the live entity list stays sorted
+                    .filter(|entity| self.entities.binary_search(entity).is_ok()));
+                result.sort_unstable();
             }
         }
-        
-        result
+        cache.insert(component_types.to_vec(), CachedQuery {
+            entities_generation: self.entities_generation,
+            pool_generations,
+            entities: result.clone(),
+        });
+    }
+    
+    /// Query cache hits and misses since the world was created
+    pub fn query_cache_stats(&self) -> QueryCacheStats {
+        self.query_cache_stats.get()
     }
     
     /// Create iterator for entities with 2 components
-    pub fn iter_entities<A1: AccessMode, A2: AccessMode>(&self) -> EntIt<(A1, A2)> {
+    pub fn iter_entities<A1: AccessMode, A2: AccessMode>(&self) -> EntIt<'_, (A1, A2)> {
         let type_ids = vec![A1::component_type_id(), A2::component_type_id()];
         let entities = self.entities_with_components(&type_ids);
-        EntIt::<(A1, A2)>::new_2(self as *const World, entities)
+        EntIt::<(A1, A2)>::new_2(self, entities)
     }
     
     /// Create iterator for entities with 4 components  
-    pub fn iter_entities_4<A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode>(&self) -> EntIt<(A1, A2, A3, A4)> {
+    pub fn iter_entities_4<A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode>(&self) -> EntIt<'_, (A1, A2, A3, A4)> {
         let type_ids = vec![
             A1::component_type_id(), 
             A2::component_type_id(),
@@ -437,7 +1233,7 @@ impl World {
             A4::component_type_id()
         ];
         let entities = self.entities_with_components(&type_ids);
-        EntIt::<(A1, A2, A3, A4)>::new_4(self as *const World, entities)
+        EntIt::<(A1, A2, A3, A4)>::new_4(self, entities)
     }
     
     /// Get all entities in the world (for compatibility with legacy code)
@@ -512,9 +1308,9 @@ mod tests {
 
     impl System for SampleSystem {
         type Dependencies = (TimeSystem, InputSystem, PhysicsSystem);
-        type Iterators = EntIt<(Mut<PositionComponent>, VelocityComponent)>;
+        type Iterators<'w> = EntIt<'w, (Mut<PositionComponent>, VelocityComponent)>;
 
-        fn update(&mut self, iterators: Self::Iterators) {
+        fn update(&mut self, iterators: Self::Iterators<'_>) {
             // Implementation of the update logic
             for (_position, _velocity) in iterators {
                 // Can access components directly as tuples
@@ -528,8 +1324,8 @@ mod tests {
         
         // Create an entity with components
         let entity = world.create_entity();
-        world.add_component(entity, PositionComponent { x: 0.0, y: 0.0 });
-        world.add_component(entity, VelocityComponent { dx: 1.0, dy: 2.0 });
+        world.add_component(entity, PositionComponent { x: 0.0, y: 0.0 }).unwrap();
+        world.add_component(entity, VelocityComponent { dx: 1.0, dy: 2.0 }).unwrap();
         
         // Test the new iterator API
         let iter = world.iter_entities::<Mut<PositionComponent>, VelocityComponent>();
@@ -538,4 +1334,407 @@ mod tests {
         let mut sample_system = SampleSystem;
         sample_system.update(iter);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_iterator_borrows_end_with_items() {
+        let mut world = World::new();
+        let entity = world.create_entity();
+        world.add_component(entity, PositionComponent { x: 0.0, y: 0.0 }).unwrap();
+        world.add_component(entity, VelocityComponent { dx: 1.0, dy: 2.0 }).unwrap();
+        
+        for _ in 0..3 {
+            for (mut position, velocity) in world.iter_entities::<Mut<PositionComponent>, VelocityComponent>() {
+                let position = position.get_mut().unwrap();
+                position.x += velocity.dx;
+            }
+        }
+        
+        // Dropped items release their borrows, so the components can be borrowed mutably again
+        assert_eq!(world.get_component_mut::<VelocityComponent>(entity).unwrap().dx, 1.0);
+        assert_eq!(world.get_component::<PositionComponent>(entity).unwrap().x, 3.0);
+        let (mut position, _) = world.iter_entities::<Mut<PositionComponent>, VelocityComponent>().next().unwrap();
+        assert!(position.get_mut().is_some());
+        assert!(world.component_pools[&TypeId::of::<PositionComponent>()].cell(entity).unwrap().try_borrow().is_err());
+        drop(position);
+        assert!(world.get_component_mut::<PositionComponent>(entity).is_some());
+    }
+
+    #[test]
+    fn test_destroy_entity() {
+        let mut world = World::new();
+        let entity = world.create_entity();
+        let other = world.create_entity();
+        world.add_component(entity, PositionComponent { x: 1.0, y: 2.0 }).unwrap();
+        world.add_component(entity, VelocityComponent { dx: 0.0, dy: 0.0 }).unwrap();
+        world.add_component(other, PositionComponent { x: 3.0, y: 4.0 }).unwrap();
+        
+        assert!(world.destroy_entity(entity));
+        assert!(!world.destroy_entity(entity));
+        assert!(!world.has_component::<PositionComponent>(entity));
+        assert!(!world.has_component::<VelocityComponent>(entity));
+        assert_eq!(world.get_all_entities(), &vec![other]);
+        assert_eq!(world.entities_with_components(&[TypeId::of::<PositionComponent>()]), vec![other]);
+    }
+
+    #[derive(Clone, Debug)]
+    struct FollowComponent {
+        pub target: Entity,
+    }
+
+    impl Component for FollowComponent {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn clone_box(&self) -> Box<dyn Component> {
+            Box::new(self.clone())
+        }
+
+        fn remap_entities(&mut self, entity_remap: &HashMap<Entity, Entity>) {
+            if let Some(&target) = entity_remap.get(&self.target) {
+                self.target = target;
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge_world_remaps_entities() {
+        let mut world = World::new();
+        let existing = world.create_entity();
+        world.add_component(existing, PositionComponent { x: 0.0, y: 0.0 }).unwrap();
+
+        // Build the scene on another thread, as a background loader would
+        let scene = std::thread::spawn(|| {
+            let mut scene = World::new();
+            let leader = scene.create_entity();
+            scene.add_component(leader, PositionComponent { x: 5.0, y: 1.0 }).unwrap();
+            let follower = scene.create_entity();
+            scene.add_component(follower, FollowComponent { target: leader }).unwrap();
+            let anchor = scene.create_entity();
+            scene.add_component(anchor, VelocityComponent { dx: 1.0, dy: 0.0 }).unwrap();
+            scene
+        }).join().unwrap();
+
+        // Scene entity 2 is spliced onto the existing entity
+        let mut remap = HashMap::from([(2, existing)]);
+        world.merge(scene, &mut remap);
+
+        let (leader, follower) = (remap[&0], remap[&1]);
+        assert_eq!(world.get_all_entities(), &vec![existing, leader, follower]);
+        assert_ne!(leader, existing);
+        assert_eq!(world.get_component::<PositionComponent>(leader).unwrap().x, 5.0);
+        assert_eq!(world.get_component::<FollowComponent>(follower).unwrap().target, leader);
+        assert!(world.has_component::<PositionComponent>(existing));
+        assert!(world.has_component::<VelocityComponent>(existing));
+        assert_eq!(world.create_entity(), follower + 1);
+    }
+
+    #[test]
+    fn test_memory_report_and_orphans() {
+        let mut world = World::new();
+        for i in 0..3 {
+            let entity = world.create_entity();
+            world.add_component(entity, PositionComponent { x: i as f32, y: 0.0 }).unwrap();
+        }
+        world.add_component(0, VelocityComponent { dx: 0.0, dy: 1.0 }).unwrap();
+        world.destroy_entity(1);
+        world.destroy_entity(2);
+
+        let report = world.memory_report();
+        assert_eq!(report.entities, 1);
+        let positions = report.pools.iter().find(|pool| pool.component.ends_with("PositionComponent")).unwrap();
+        assert_eq!((positions.count, positions.high_water_mark), (1, 3));
+        assert!(positions.approx_bytes >= std::mem::size_of::<PositionComponent>());
+        assert_eq!(report.total_bytes, report.pools.iter().map(|pool| pool.approx_bytes).sum::<usize>());
+
+        // Without leak checks a component can be attached to a destroyed entity and linger
+        world.add_component(2, VelocityComponent { dx: 0.0, dy: 0.0 }).unwrap();
+        let orphaned = world.orphaned_components();
+        assert_eq!(orphaned.len(), 1);
+        assert_eq!(orphaned[0].0, 2);
+        assert!(orphaned[0].1.ends_with("VelocityComponent"));
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    #[cfg(debug_assertions)]
+    fn test_leak_checks_flag_destroyed_entities() {
+        let mut world = World::new();
+        world.set_leak_checks(true);
+        let entity = world.create_entity();
+        world.destroy_entity(entity);
+        world.add_component(entity, PositionComponent { x: 0.0, y: 0.0 }).unwrap();
+    }
+
+    #[test]
+    fn test_spawn_batch() {
+        let mut world = World::new();
+        let existing = world.create_entity();
+        world.add_component(existing, PositionComponent { x: -1.0, y: 0.0 }).unwrap();
+
+        let citizens = world.spawn_batch((0..10_000).map(|i| (
+            PositionComponent { x: i as f32, y: 0.0 },
+            VelocityComponent { dx: 1.0, dy: 0.0 },
+        ))).unwrap();
+        assert_eq!(citizens.len(), 10_000);
+        assert_eq!(citizens[0], existing + 1);
+        assert_eq!(world.get_all_entities().len(), 10_001);
+        assert_eq!(world.get_component::<PositionComponent>(citizens[42]).unwrap().x, 42.0);
+        assert_eq!(world.get_component::<PositionComponent>(existing).unwrap().x, -1.0);
+        assert_eq!(world.entities_with_components(&[
+            TypeId::of::<PositionComponent>(),
+            TypeId::of::<VelocityComponent>(),
+        ]).len(), 10_000);
+
+        // IDs keep counting after the batch
+        assert_eq!(world.create_entity(), citizens[9_999] + 1);
+        assert!(world.spawn_batch(Vec::<(PositionComponent,)>::new()).unwrap().is_empty());
+        
+        // A bundle repeating a component type is rejected before anything is created
+        let error = world.spawn_batch(vec![(PositionComponent { x: 0.0, y: 0.0 }, PositionComponent { x: 1.0, y: 0.0 })]).unwrap_err();
+        assert!(error.duplicate && error.component.ends_with("PositionComponent"), "{}", error);
+        assert!(world.spawn_batch(Vec::<(PositionComponent, PositionComponent)>::new()).is_err());
+        assert!(world.spawn((VelocityComponent { dx: 0.0, dy: 0.0 }, VelocityComponent { dx: 0.0, dy: 0.0 })).is_err());
+        assert_eq!(world.get_all_entities().len(), 10_002);
+    }
+
+    // Keeps a count of its live instances, the way a spatial index would register them
+    #[derive(Clone, Debug)]
+    struct CountedComponent {
+        radius: i32,
+        live: std::sync::Arc<std::sync::atomic::AtomicI32>,
+    }
+
+    impl Component for CountedComponent {
+        fn validate(&self) -> bool {
+            self.radius > 0
+        }
+
+        fn on_insert(&self, _world: &mut World, _entity: Entity) {
+            self.live.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        fn on_remove(&self, _world: &mut World, _entity: Entity) {
+            self.live.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn clone_box(&self) -> Box<dyn Component> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_validation_and_insert_hooks() {
+        let live = std::sync::Arc::new(std::sync::atomic::AtomicI32::new(0));
+        let counted = |radius| CountedComponent { radius, live: live.clone() };
+        let live_count = || live.load(std::sync::atomic::Ordering::SeqCst);
+        let mut world = World::new();
+
+        let entity = world.spawn((PositionComponent { x: 0.0, y: 0.0 }, counted(2))).unwrap();
+        world.add_component(entity, counted(3)).unwrap();
+        assert_eq!(live_count(), 1);
+
+        // Invalid components are rejected with context and leave the world as it was
+        let error = world.add_component(entity, counted(0)).unwrap_err();
+        assert_eq!(error.entity, entity);
+        assert!(error.to_string().contains("CountedComponent"));
+        assert_eq!(world.get_component::<CountedComponent>(entity).unwrap().radius, 3);
+        assert!(world.spawn((PositionComponent { x: 1.0, y: 1.0 }, counted(-1))).is_err());
+        assert!(world.spawn_batch(vec![(counted(1),), (counted(0),)]).is_err());
+        assert_eq!(world.get_all_entities(), &vec![entity]);
+
+        let other = world.spawn((counted(1),)).unwrap();
+        assert_eq!(live_count(), 2);
+        assert!(world.remove_component::<CountedComponent>(entity));
+        world.destroy_entity(other);
+        assert_eq!(live_count(), 0);
+    }
+
+    #[test]
+    fn test_sparse_storage() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..6).map(|i| world.spawn((PositionComponent { x: i as f32, y: 0.0 },)).unwrap()).collect();
+        for &entity in &entities[..3] {
+            world.add_component(entity, VelocityComponent { dx: 1.0, dy: 0.0 }).unwrap();
+        }
+        world.register_storage::<VelocityComponent>(StorageStrategy::Sparse);
+        
+        // Removing from the middle moves the last component into the gap
+        assert!(world.remove_component::<VelocityComponent>(entities[0]));
+        world.add_component(entities[4], VelocityComponent { dx: 2.0, dy: 0.0 }).unwrap();
+        assert_eq!(world.get_component::<VelocityComponent>(entities[2]).unwrap().dx, 1.0);
+        let query = [TypeId::of::<PositionComponent>(), TypeId::of::<VelocityComponent>()];
+        assert_eq!(world.entities_with_components(&query), vec![entities[1], entities[2], entities[4]]);
+        
+        world.destroy_entity(entities[1]);
+        assert_eq!(world.entities_with_components(&query), vec![entities[2], entities[4]]);
+        let velocities = world.memory_report().pools.into_iter().find(|pool| pool.component.ends_with("VelocityComponent")).unwrap();
+        assert_eq!((velocities.storage, velocities.count), (StorageStrategy::Sparse, 2));
+    }
+
+    #[test]
+    fn test_query_cache_invalidation() {
+        let mut world = World::new();
+        let a = world.spawn((PositionComponent { x: 0.0, y: 0.0 }, VelocityComponent { dx: 1.0, dy: 0.0 })).unwrap();
+        let b = world.spawn((PositionComponent { x: 1.0, y: 0.0 },)).unwrap();
+        let query = [TypeId::of::<PositionComponent>(), TypeId::of::<VelocityComponent>()];
+        assert_eq!(world.entities_with_components(&query), vec![a]);
+        
+        // Editing components in place keeps the cached list
+        world.get_component_mut::<PositionComponent>(a).unwrap().x = 5.0;
+        assert_eq!(world.entities_with_components(&query), vec![a]);
+        assert_eq!(world.query_cache_stats(), QueryCacheStats { hits: 1, misses: 1 });
+        
+        world.add_component(b, VelocityComponent { dx: 0.0, dy: 1.0 }).unwrap();
+        assert_eq!(world.entities_with_components(&query), vec![a, b]);
+        world.remove_component::<VelocityComponent>(a);
+        assert_eq!(world.entities_with_components(&query), vec![b]);
+        world.destroy_entity(b);
+        assert!(world.entities_with_components(&query).is_empty());
+        assert_eq!(world.query_cache_stats(), QueryCacheStats { hits: 1, misses: 4 });
+    }
+
+    #[test]
+    fn test_iteration_order_is_deterministic() {
+        let run = |storage: StorageStrategy| {
+            let mut world = World::new();
+            world.register_storage::<VelocityComponent>(storage);
+            let entities: Vec<Entity> = (0..64).map(|_| world.create_entity()).collect();
+            // Components arrive out of ID order and some leave again, shuffling hashed and packed storage
+            for &entity in entities.iter().rev() {
+                world.add_component(entity, PositionComponent { x: entity as f32, y: 0.0 }).unwrap();
+            }
+            for &entity in entities.iter().filter(|entity| *entity % 3 != 0) {
+                world.add_component(entity, VelocityComponent { dx: 1.0, dy: 0.0 }).unwrap();
+            }
+            for &entity in entities.iter().filter(|entity| *entity % 5 == 0) {
+                world.remove_component::<VelocityComponent>(entity);
+            }
+            
+            let mut visited: Vec<Entity> = world.iter_entities::<PositionComponent, VelocityComponent>()
+                .map(|(position, _)| position.x as Entity)
+                .collect();
+            visited.extend(world.component_pools.get_mut(&TypeId::of::<VelocityComponent>()).unwrap().drain().into_iter().map(|(entity, _)| entity));
+            visited
+        };
+        
+        for storage in [StorageStrategy::Dense, StorageStrategy::Sparse] {
+            let first = run(storage);
+            assert_eq!(first, run(storage));
+            let (queried, drained) = first.split_at(first.len() / 2);
+            assert_eq!(queried, drained);
+            assert!(queried.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        
+        // Hashed pools reserve ahead and report the slots they allocated
+        let mut pool = ComponentPool::for_type::<PositionComponent>();
+        let empty = pool.stats().approx_bytes;
+        pool.reserve(100);
+        assert!(pool.stats().approx_bytes >= empty + 100 * std::mem::size_of::<(Entity, ComponentCell)>());
+    }
+
+    #[test]
+    fn test_find_by_name_and_tag() {
+        let mut world = World::new();
+        let city_hall = world.spawn((Name::new("city_hall"), Tags::new(&["landmark", "service"]))).unwrap();
+        let park = world.spawn((Name::new("park"), Tags::new(&["landmark"]))).unwrap();
+        let unnamed = world.create_entity();
+        assert_eq!(world.find_by_name("city_hall"), Some(city_hall));
+        assert_eq!(world.find_by_name("harbor"), None);
+        assert_eq!(world.find_by_tag("landmark"), vec![city_hall, park]);
+        assert_eq!(world.find_by_tag("service"), vec![city_hall]);
+
+        // Renaming through add_component moves the index entry
+        world.add_component(park, Name::new("central_park")).unwrap();
+        assert_eq!(world.find_by_name("park"), None);
+        assert_eq!(world.find_by_name("central_park"), Some(park));
+
+        // Edits in place are still found, just without the index
+        world.get_component_mut::<Name>(park).unwrap().0 = "old_park".to_string();
+        assert_eq!(world.find_by_name("central_park"), None);
+        assert_eq!(world.find_by_name("old_park"), Some(park));
+
+        world.destroy_entity(city_hall);
+        assert_eq!(world.find_by_name("city_hall"), None);
+        world.add_component(unnamed, Name::new("harbor")).unwrap();
+        world.remove_component::<Name>(unnamed);
+        assert_eq!(world.find_by_name("harbor"), None);
+
+        let named = world.spawn_batch((0..3).map(|i| (Name::new(&format!("house_{}", i)),))).unwrap();
+        assert_eq!(world.find_by_name("house_2"), Some(named[2]));
+    }
+
+    crate::bundle! {
+        struct MoverBundle {
+            position: PositionComponent,
+            velocity: VelocityComponent,
+        }
+    }
+
+    #[test]
+    fn test_spawn_bundle() {
+        let mut world = World::new();
+        let tuple = world.spawn((PositionComponent { x: 1.0, y: 2.0 }, VelocityComponent { dx: 3.0, dy: 4.0 })).unwrap();
+        assert!(world.has_component::<PositionComponent>(tuple));
+        assert_eq!(world.get_component::<VelocityComponent>(tuple).unwrap().dx, 3.0);
+
+        let named = world.spawn(MoverBundle {
+            position: PositionComponent { x: 5.0, y: 0.0 },
+            velocity: VelocityComponent { dx: 0.0, dy: 1.0 },
+        }).unwrap();
+        assert_eq!(world.get_component::<PositionComponent>(named).unwrap().x, 5.0);
+        assert_eq!(world.get_all_entities(), &vec![tuple, named]);
+
+        // Inserting into an existing entity replaces components of the same type
+        world.insert_bundle(tuple, (PositionComponent { x: 9.0, y: 9.0 },)).unwrap();
+        assert_eq!(world.get_component::<PositionComponent>(tuple).unwrap().x, 9.0);
+        assert!(world.has_component::<VelocityComponent>(tuple));
+    }
+
+    #[test]
+    fn test_state_hash_is_stable() {
+        let build = |velocity_first: bool| {
+            let mut world = World::new();
+            world.register_component::<PositionComponent>("position");
+            world.register_component::<VelocityComponent>("velocity");
+            let a = world.create_entity();
+            let b = world.create_entity();
+            if velocity_first {
+                world.add_component(b, VelocityComponent { dx: 1.0, dy: 0.0 }).unwrap();
+                world.add_component(a, PositionComponent { x: 1.0, y: 2.0 }).unwrap();
+                world.add_component(b, PositionComponent { x: 3.0, y: 4.0 }).unwrap();
+            } else {
+                world.add_component(a, PositionComponent { x: 1.0, y: 2.0 }).unwrap();
+                world.add_component(b, PositionComponent { x: 3.0, y: 4.0 }).unwrap();
+                world.add_component(b, VelocityComponent { dx: 1.0, dy: 0.0 }).unwrap();
+            }
+            (world, a)
+        };
+
+        // Insertion order doesn't matter, component data does
+        let (first, a) = build(false);
+        let (second, _) = build(true);
+        assert_eq!(first.state_hash(), second.state_hash());
+        assert_eq!(first.registered_components(), vec!["position", "velocity"]);
+
+        first.get_component_mut::<PositionComponent>(a).unwrap().x = 1.5;
+        assert_ne!(first.state_hash(), second.state_hash());
+
+        // Unregistered components are not part of the state
+        let (mut third, a) = build(false);
+        third.add_component(a, Name::new("unhashed")).unwrap();
+        assert_eq!(third.state_hash(), second.state_hash());
+    }
+}
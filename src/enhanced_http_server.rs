@@ -1,10 +1,16 @@
-use tiny_http::{Server, Request, Response, Header};
+use tiny_http::{Request, Response, Header};
 use std::path::Path;
 use std::fs;
 use std::error::Error;
 use std::thread;
 use std::time::Duration;
-use crate::rendering::*;
+use rust_citybuilder_game::rendering::*;
+use rust_citybuilder_game::rendering::web_service_manager::bind_with_fallback;
+use rust_citybuilder_game::service_discovery::{self, PortsConfig, WEB_SERVICE};
+use rust_citybuilder_game::shutdown::{shutdown_signalled, SHUTDOWN_POLL_INTERVAL};
+
+/// Port the `web-render` client's pages are served on, on the host of the `ports` section
+pub const WEB_RENDER_PORT: u16 = 8082;
 
 /// Enhanced HTTP server that can serve static files from the web directory
 pub struct EnhancedHttpServer {
@@ -23,18 +29,25 @@ impl EnhancedHttpServer {
     
     /// Start the HTTP server and handle requests
     pub fn start(&self) -> Result<(), Box<dyn Error>> {
-        let server = Server::http(&self.address)
+        // A taken port moves to a free one, so the address printed here is the one to open
+        let (server, bound) = bind_with_fallback(&self.address)
             .map_err(|e| format!("Failed to start server: {}", e))?;
+        service_discovery::register_service(WEB_SERVICE, bound);
         
-        println!("🌐 Enhanced HTTP server started on http://{}", self.address);
+        println!("🌐 Enhanced HTTP server started on http://{}", bound);
         println!("📁 Serving files from: {}", self.web_root);
-        println!("📡 Open http://{} in your browser to see the JavaScript rendering library client", self.address);
+        println!("📡 Open http://{} in your browser to see the JavaScript rendering library client", bound);
+        println!("{}", service_discovery::registered_services().summary());
         println!("");
         
-        for request in server.incoming_requests() {
-            self.handle_request(request);
+        // Poll so Ctrl+C stops the loop between requests instead of killing a response mid-write
+        while !shutdown_signalled() {
+            if let Some(request) = server.recv_timeout(SHUTDOWN_POLL_INTERVAL)? {
+                self.handle_request(request);
+            }
         }
         
+        println!("🛑 Enhanced HTTP server on {} stopped", self.address);
         Ok(())
     }
     
@@ -165,12 +178,12 @@ pub fn start_rendering_server(address: &str) -> Result<(), Box<dyn Error>> {
 }
 
 /// Demonstrate the rendering system with a web client
-pub fn demonstrate_rendering_with_web_client() {
+pub fn demonstrate_rendering_with_web_client(ports: &PortsConfig) {
     println!("🎨 Enhanced Rendering System with Web Client");
     println!("===========================================");
     
     // Start the rendering server
-    if let Err(e) = start_rendering_server("localhost:8082") {
+    if let Err(e) = start_rendering_server(&ports.address(WEB_RENDER_PORT)) {
         eprintln!("Failed to start rendering server: {}", e);
     }
 }
\ No newline at end of file
@@ -0,0 +1,198 @@
+/// Pub/sub bridge between ECS events and web clients: clients subscribe to event types by name and get every
+/// published event of those types in their message queue, and post events back that become ECS events
+/// Only event types on the bridge's allowlists cross it, one list per direction
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Events kept per subscriber between deliveries; the oldest are dropped beyond this
+pub const BRIDGE_QUEUE_LIMIT: usize = 256;
+
+/// An event on the wire: the name of its type (the enum variant) and its fields
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgedEvent {
+    pub topic: String,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+impl BridgedEvent {
+    /// Split an externally tagged enum value, e.g. `{"MoneyEarned": {"amount": 5}}`, into its topic and payload
+    pub fn encode<T: Serialize>(event: &T) -> Result<Self, String> {
+        match serde_json::to_value(event).map_err(|e| e.to_string())? {
+            Value::String(topic) => Ok(Self { topic, payload: Value::Null }),
+            Value::Object(object) if object.len() == 1 => {
+                let (topic, payload) = object.into_iter().next().expect("object has one entry");
+                Ok(Self { topic, payload })
+            }
+            other => Err(format!("Event {} has no type name to publish it under", other)),
+        }
+    }
+
+    /// Rebuild the enum value the event was encoded from
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, String> {
+        let value = match &self.payload {
+            Value::Null => Value::String(self.topic.clone()),
+            payload => Value::Object([(self.topic.clone(), payload.clone())].into_iter().collect()),
+        };
+        serde_json::from_value(value).map_err(|e| format!("Invalid {} event: {}", self.topic, e))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Subscriber {
+    topics: BTreeSet<String>,
+    pending: VecDeque<BridgedEvent>,
+}
+
+/// Allowlists of the event types published to and accepted from clients, and each client's subscriptions
+#[derive(Debug, Clone, Default)]
+pub struct EventBridge {
+    published: BTreeSet<String>,
+    accepted: BTreeSet<String>,
+    subscribers: BTreeMap<String, Subscriber>,
+    dropped: u64,
+}
+
+impl EventBridge {
+    pub fn new(published: &[&str], accepted: &[&str]) -> Self {
+        Self {
+            published: published.iter().map(|topic| topic.to_string()).collect(),
+            accepted: accepted.iter().map(|topic| topic.to_string()).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Let clients subscribe to an event type
+    pub fn publish_topic(&mut self, topic: &str) {
+        self.published.insert(topic.to_string());
+    }
+
+    /// Let clients post an event type
+    pub fn accept_topic(&mut self, topic: &str) {
+        self.accepted.insert(topic.to_string());
+    }
+
+    pub fn published_topics(&self) -> Vec<&str> {
+        self.published.iter().map(String::as_str).collect()
+    }
+
+    pub fn accepted_topics(&self) -> Vec<&str> {
+        self.accepted.iter().map(String::as_str).collect()
+    }
+
+    /// Subscribe a client to event types; nothing is subscribed if any of them isn't published
+    /// Returns every type the client is now subscribed to
+    pub fn subscribe(&mut self, client_id: &str, topics: &[&str]) -> Result<Vec<String>, String> {
+        if let Some(topic) = topics.iter().find(|topic| !self.published.contains(**topic)) {
+            return Err(format!("Event type '{}' is not published to clients", topic));
+        }
+        let subscriber = self.subscribers.entry(client_id.to_string()).or_default();
+        subscriber.topics.extend(topics.iter().map(|topic| topic.to_string()));
+        Ok(subscriber.topics.iter().cloned().collect())
+    }
+
+    pub fn unsubscribe(&mut self, client_id: &str, topics: &[&str]) {
+        if let Some(subscriber) = self.subscribers.get_mut(client_id) {
+            subscriber.topics.retain(|topic| !topics.contains(&topic.as_str()));
+        }
+    }
+
+    /// Drop a client's subscriptions and undelivered events, e.g. when it disconnects
+    pub fn forget(&mut self, client_id: &str) {
+        self.subscribers.remove(client_id);
+    }
+
+    pub fn subscriptions(&self, client_id: &str) -> Vec<&str> {
+        self.subscribers.get(client_id)
+            .map(|subscriber| subscriber.topics.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Queue events for the clients subscribed to their types; events nobody subscribed to are skipped unencoded
+    pub fn publish<'a, T: Serialize + 'a>(&mut self, events: impl IntoIterator<Item = &'a T>) {
+        if self.subscribers.values().all(|subscriber| subscriber.topics.is_empty()) {
+            return;
+        }
+        for event in events {
+            let Ok(event) = BridgedEvent::encode(event) else { continue };
+            if !self.published.contains(&event.topic) {
+                continue;
+            }
+            for subscriber in self.subscribers.values_mut().filter(|subscriber| subscriber.topics.contains(&event.topic)) {
+                if subscriber.pending.len() >= BRIDGE_QUEUE_LIMIT {
+                    subscriber.pending.pop_front();
+                    self.dropped += 1;
+                }
+                subscriber.pending.push_back(event.clone());
+            }
+        }
+    }
+
+    /// Take the events waiting for a client, oldest first
+    pub fn take(&mut self, client_id: &str) -> Vec<BridgedEvent> {
+        self.subscribers.get_mut(client_id)
+            .map(|subscriber| subscriber.pending.drain(..).collect())
+            .unwrap_or_default()
+    }
+
+    /// Clients with events waiting
+    pub fn pending_clients(&self) -> Vec<&str> {
+        self.subscribers.iter()
+            .filter(|(_, subscriber)| !subscriber.pending.is_empty())
+            .map(|(client_id, _)| client_id.as_str())
+            .collect()
+    }
+
+    /// Events dropped from full queues since the bridge was created
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Turn an event posted by a client into an ECS event, if its type is accepted
+    pub fn accept<T: DeserializeOwned>(&self, event: &BridgedEvent) -> Result<T, String> {
+        if !self.accepted.contains(&event.topic) {
+            return Err(format!("Event type '{}' is not accepted from clients", event.topic));
+        }
+        event.decode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::GameEvent;
+
+    #[test]
+    fn test_bridge_forwards_subscribed_events_both_ways() {
+        let mut bridge = EventBridge::new(&["MoneyEarned", "BuildingPlaced"], &["CitizensHoused"]);
+        assert!(bridge.subscribe("c1", &["MoneyEarned", "LifetimeExpired"]).is_err());
+        assert!(bridge.subscriptions("c1").is_empty());
+        assert_eq!(bridge.subscribe("c1", &["MoneyEarned"]).unwrap(), vec!["MoneyEarned"]);
+        bridge.subscribe("c2", &["BuildingPlaced"]).unwrap();
+
+        let events = [
+            GameEvent::MoneyEarned { amount: 5 },
+            GameEvent::BuildingPlaced { x: 1, y: 2, kind: "house".to_string() },
+            GameEvent::CitizensHoused { count: 3 },
+        ];
+        bridge.publish(events.iter());
+        assert_eq!(bridge.pending_clients(), vec!["c1", "c2"]);
+        let delivered = bridge.take("c1");
+        assert_eq!(delivered, vec![BridgedEvent { topic: "MoneyEarned".to_string(), payload: serde_json::json!({"amount": 5}) }]);
+        assert_eq!(delivered[0].decode::<GameEvent>().unwrap(), events[0]);
+        assert!(bridge.take("c1").is_empty());
+
+        bridge.forget("c2");
+        assert!(bridge.take("c2").is_empty());
+
+        // Posted events only become ECS events when their type is accepted
+        let housed = BridgedEvent { topic: "CitizensHoused".to_string(), payload: serde_json::json!({"count": 2}) };
+        assert_eq!(bridge.accept::<GameEvent>(&housed).unwrap(), GameEvent::CitizensHoused { count: 2 });
+        let money = BridgedEvent::encode(&GameEvent::MoneyEarned { amount: 1000 }).unwrap();
+        assert!(bridge.accept::<GameEvent>(&money).unwrap_err().contains("not accepted"));
+        let malformed = BridgedEvent { topic: "CitizensHoused".to_string(), payload: serde_json::json!({"count": "many"}) };
+        assert!(bridge.accept::<GameEvent>(&malformed).is_err());
+    }
+}
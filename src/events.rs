@@ -0,0 +1,97 @@
+/// Event channel used by gameplay systems to communicate without holding references to each other
+use crate::ecs::Entity;
+use crate::economy::BudgetReport;
+use serde::{Deserialize, Serialize};
+
+/// Gameplay events emitted by systems during a frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// The player moved from one tile to another
+    PlayerMoved { from: (i32, i32), to: (i32, i32) },
+    /// A building was placed on a tile
+    BuildingPlaced { x: i32, y: i32, kind: String },
+    /// Money was added to the city treasury
+    MoneyEarned { amount: i64 },
+    /// A building was demolished and part of its cost refunded
+    BuildingDemolished { x: i32, y: i32, kind: String, refund: i64 },
+    /// Citizens moved into housing
+    CitizensHoused { count: u32 },
+    /// The monthly budget was settled
+    BudgetReport(BudgetReport),
+    /// An entity's `Lifetime` ran out and it was despawned; `event` is the name its lifetime gave
+    LifetimeExpired { entity: Entity, event: String },
+    /// A scenario trigger's condition was met and its actions ran
+    TriggerFired { name: String },
+}
+
+/// Frame-local queue of events
+/// Producers push events during a frame and consumers read them before the queue is cleared
+/// Events are delivered in the order they were pushed, so every consumer sees the same sequence on every run
+#[derive(Debug, Clone)]
+pub struct EventQueue<T> {
+    events: Vec<T>,
+}
+
+impl<T> EventQueue<T> {
+    /// Create an empty event queue
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    /// Push a new event onto the queue
+    pub fn push(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    /// Iterate over the pending events without consuming them
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter()
+    }
+
+    /// Remove and return all pending events
+    pub fn drain(&mut self) -> Vec<T> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Discard all pending events
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Number of pending events
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if no events are pending
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<T> Default for EventQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_queue_push_and_drain() {
+        let mut queue = EventQueue::new();
+        assert!(queue.is_empty());
+
+        queue.push(GameEvent::MoneyEarned { amount: 100 });
+        queue.push(GameEvent::CitizensHoused { count: 4 });
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.iter().count(), 2);
+
+        let events = queue.drain();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], GameEvent::MoneyEarned { amount: 100 });
+        assert!(queue.is_empty());
+    }
+}
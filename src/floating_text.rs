@@ -0,0 +1,155 @@
+/// Floating texts: short messages like "+10 population" that rise from a tile and fade out, spawned with one call
+/// A `Lifetime` despawns them once they have faded, so gameplay code never has to clean them up
+use crate::core::math::{Color, Vector2d};
+use crate::ecs::{Component, Entity, InvalidComponent, World};
+use crate::lifetime::Lifetime;
+use crate::rendering::rendering_device::RenderCommand;
+use std::any::{Any, TypeId};
+
+/// Seconds a text stays on screen
+pub const FLOATING_TEXT_SECONDS: f32 = 1.5;
+/// Tiles a text rises over its lifetime
+pub const FLOATING_TEXT_RISE: f32 = 0.75;
+/// Text height relative to a tile
+pub const FLOATING_TEXT_SIZE: f32 = 0.4;
+
+/// Component for a message drifting up from a point on the map
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatingText {
+    pub text: String,
+    pub color: Color,
+    /// Where the text starts, in tiles
+    pub origin: Vector2d,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+impl FloatingText {
+    pub fn new(text: &str, color: Color, origin: Vector2d) -> Self {
+        Self { text: text.to_string(), color, origin, elapsed: 0.0, duration: FLOATING_TEXT_SECONDS }
+    }
+
+    /// Point just above the top edge of a tile, where texts about a building start
+    pub fn above_tile(x: i32, y: i32) -> Vector2d {
+        Vector2d::new(x as f32 + 0.5, y as f32)
+    }
+
+    /// Spawn a text rising from `origin`, despawned when it has faded
+    pub fn spawn(world: &mut World, text: &str, color: Color, origin: Vector2d) -> Result<Entity, InvalidComponent> {
+        world.spawn((FloatingText::new(text, color, origin), Lifetime::seconds(FLOATING_TEXT_SECONDS)))
+    }
+
+    /// Fraction of the animation played, from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+
+    /// Current position in tiles, rising quickly at first and slowing down
+    pub fn position(&self) -> Vector2d {
+        let t = 1.0 - (1.0 - self.progress()).powi(2);
+        self.origin - Vector2d::new(0.0, FLOATING_TEXT_RISE * t)
+    }
+
+    /// Opacity: solid for the first half, then fading out
+    pub fn alpha(&self) -> f32 {
+        (2.0 - 2.0 * self.progress()).min(1.0)
+    }
+}
+
+impl Component for FloatingText {
+    fn validate(&self) -> bool {
+        self.duration > 0.0 && self.origin.x.is_finite() && self.origin.y.is_finite()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// System that animates floating texts and draws them
+pub struct FloatingTextSystem;
+
+impl FloatingTextSystem {
+    pub fn update(world: &mut World, delta_seconds: f32) {
+        for entity in world.entities_with_components(&[TypeId::of::<FloatingText>()]) {
+            if let Some(mut text) = world.get_component_mut::<FloatingText>(entity) {
+                text.elapsed += delta_seconds.max(0.0);
+            }
+        }
+    }
+
+    /// Texts in spawn order with their current position and opacity
+    pub fn visible(world: &World) -> Vec<FloatingText> {
+        let mut entities = world.entities_with_components(&[TypeId::of::<FloatingText>()]);
+        entities.sort_unstable();
+        entities.into_iter()
+            .filter_map(|entity| world.get_component::<FloatingText>(entity).map(|text| text.clone()))
+            .collect()
+    }
+
+    /// The last `limit` texts of `visible`, or all of them without a limit
+    pub fn newest(world: &World, limit: Option<usize>) -> Vec<FloatingText> {
+        let mut texts = Self::visible(world);
+        texts.drain(..texts.len().saturating_sub(limit.unwrap_or(usize::MAX)));
+        texts
+    }
+
+    /// Build text overlay commands in world units
+    pub fn render_commands(world: &World, cell_size: f32, z_order: i32) -> Vec<RenderCommand> {
+        Self::visible(world).into_iter()
+            .filter_map(|text| {
+                let alpha = text.alpha();
+                RenderCommand::text(&text.text, cell_size * FLOATING_TEXT_SIZE)
+                    .at(text.position() * cell_size)
+                    .color(Color::new(text.color.r, text.color.g, text.color.b, text.color.a * alpha))
+                    .z_order(z_order)
+                    .build()
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventQueue;
+    use crate::lifetime::LifetimeSystem;
+
+    #[test]
+    fn test_text_rises_fades_and_despawns() {
+        let mut world = World::new();
+        let mut events = EventQueue::new();
+        let entity = FloatingText::spawn(&mut world, "+10 population", Color::green(), FloatingText::above_tile(3, 2)).unwrap();
+        assert_eq!(world.get_component::<FloatingText>(entity).unwrap().position(), Vector2d::new(3.5, 2.0));
+
+        FloatingTextSystem::update(&mut world, FLOATING_TEXT_SECONDS * 0.75);
+        LifetimeSystem::update(&mut world, &mut events, FLOATING_TEXT_SECONDS * 0.75);
+        let commands = FloatingTextSystem::render_commands(&world, 40.0, 5);
+        let RenderCommand::DrawText { text, position, color, size, z_order } = &commands[0] else {
+            panic!("Expected a text command, got {:?}", commands);
+        };
+        assert_eq!((text.as_str(), *size, *z_order), ("+10 population", 16.0, 5));
+        assert!((color.a - 0.5).abs() < 1e-5);
+        assert!(position.y < 80.0 && position.y > 80.0 - FLOATING_TEXT_RISE * 40.0);
+
+        FloatingTextSystem::update(&mut world, FLOATING_TEXT_SECONDS * 0.25);
+        LifetimeSystem::update(&mut world, &mut events, FLOATING_TEXT_SECONDS * 0.25);
+        assert!(FloatingTextSystem::visible(&world).is_empty());
+
+        // Over the limit, the oldest texts are left out
+        FloatingText::spawn(&mut world, "old", Color::red(), FloatingText::above_tile(0, 0)).unwrap();
+        FloatingText::spawn(&mut world, "new", Color::red(), FloatingText::above_tile(0, 0)).unwrap();
+        let newest: Vec<String> = FloatingTextSystem::newest(&world, Some(1)).into_iter().map(|text| text.text).collect();
+        assert_eq!(newest, vec!["new".to_string()]);
+        assert_eq!(FloatingTextSystem::newest(&world, None).len(), 2);
+    }
+}
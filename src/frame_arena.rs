@@ -0,0 +1,179 @@
+/// Per-tick scratch memory: temporary collections borrow their buffers from the arena and hand them back when
+/// dropped, so a frame's entity lists and sort buffers reuse last frame's capacity instead of hitting the allocator
+/// Stable Rust can't place a `Vec` in a custom allocator, so the arena recycles whole buffers rather than bumping a pointer
+use crate::ecs::{Entity, World};
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+/// Buffers keeping more than this many bytes of capacity are freed on reset rather than kept for the next frame
+pub const DEFAULT_MAX_RETAINED_BYTES: usize = 4 * 1024 * 1024;
+
+/// Buffer counts for one frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameArenaStats {
+    /// Buffers handed out since the last reset
+    pub borrowed: usize,
+    /// Of those, buffers that had to be freshly allocated
+    pub allocated: usize,
+    /// Bytes of capacity held for reuse after the last reset
+    pub retained_bytes: usize,
+}
+
+struct FreeBuffer {
+    buffer: Box<dyn Any + Send>,
+    bytes: usize,
+}
+
+/// Scratch buffer pool, reset once per tick
+pub struct FrameArena {
+    free: RefCell<HashMap<TypeId, Vec<FreeBuffer>>>,
+    stats: Cell<FrameArenaStats>,
+    last_frame: FrameArenaStats,
+    max_retained_bytes: usize,
+}
+
+impl Default for FrameArena {
+    fn default() -> Self {
+        Self::with_max_retained_bytes(DEFAULT_MAX_RETAINED_BYTES)
+    }
+}
+
+impl FrameArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_retained_bytes(max_retained_bytes: usize) -> Self {
+        Self {
+            free: RefCell::new(HashMap::new()),
+            stats: Cell::new(FrameArenaStats::default()),
+            last_frame: FrameArenaStats::default(),
+            max_retained_bytes,
+        }
+    }
+
+    /// An empty vector backed by a recycled buffer when one is free
+    pub fn vec<T: Send + 'static>(&self) -> FrameVec<'_, T> {
+        let recycled = self.free.borrow_mut()
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|buffers| buffers.pop())
+            .and_then(|free| free.buffer.downcast::<Vec<T>>().ok());
+        let mut stats = self.stats.get();
+        stats.borrowed += 1;
+        if recycled.is_none() {
+            stats.allocated += 1;
+        }
+        self.stats.set(stats);
+        FrameVec { arena: self, items: recycled.map(|buffer| *buffer).unwrap_or_default() }
+    }
+
+    /// An arena vector holding `items`
+    pub fn collect<T: Send + 'static>(&self, items: impl IntoIterator<Item = T>) -> FrameVec<'_, T> {
+        let mut result = self.vec();
+        result.extend(items);
+        result
+    }
+
+    /// `World::entities_with_components` into an arena buffer
+    pub fn entities_with_components(&self, world: &World, component_types: &[TypeId]) -> FrameVec<'_, Entity> {
+        let mut result = self.vec();
+        world.entities_with_components_into(component_types, &mut result);
+        result
+    }
+
+    /// Start a new frame: free buffers past the retention limit and keep the finished frame's counts
+    /// Taking `&mut self` guarantees no `FrameVec` from the finished frame is still alive
+    pub fn reset(&mut self) {
+        let mut retained_bytes = 0;
+        for buffers in self.free.get_mut().values_mut() {
+            buffers.retain(|free| {
+                let keep = retained_bytes + free.bytes <= self.max_retained_bytes;
+                if keep {
+                    retained_bytes += free.bytes;
+                }
+                keep
+            });
+        }
+        self.last_frame = FrameArenaStats { retained_bytes, ..self.stats.get() };
+        self.stats.set(FrameArenaStats::default());
+    }
+
+    /// Counts for the frame finished by the last reset
+    pub fn last_frame_stats(&self) -> FrameArenaStats {
+        self.last_frame
+    }
+
+    fn give_back<T: Send + 'static>(&self, mut items: Vec<T>) {
+        if items.capacity() == 0 {
+            return;
+        }
+        items.clear();
+        let bytes = items.capacity() * std::mem::size_of::<T>();
+        self.free.borrow_mut()
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(FreeBuffer { buffer: Box::new(items), bytes });
+    }
+}
+
+/// A vector whose buffer returns to its `FrameArena` when dropped; derefs to `Vec<T>`
+pub struct FrameVec<'a, T: Send + 'static> {
+    arena: &'a FrameArena,
+    items: Vec<T>,
+}
+
+impl<T: Send + 'static> Deref for FrameVec<'_, T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.items
+    }
+}
+
+impl<T: Send + 'static> DerefMut for FrameVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.items
+    }
+}
+
+impl<T: Send + 'static> Drop for FrameVec<'_, T> {
+    fn drop(&mut self) {
+        self.arena.give_back(std::mem::take(&mut self.items));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_game_components::GridPositionComponent;
+
+    #[test]
+    fn test_buffers_are_reused_across_frames() {
+        let mut world = World::new();
+        for x in 0..100 {
+            world.spawn((GridPositionComponent { x, y: 0 },)).unwrap();
+        }
+        let mut arena = FrameArena::new();
+        for _ in 0..3 {
+            {
+                let entities = arena.entities_with_components(&world, &[TypeId::of::<GridPositionComponent>()]);
+                assert_eq!(entities.len(), 100);
+                let positions = arena.collect(entities.iter().map(|entity| world.get_component::<GridPositionComponent>(*entity).unwrap().x));
+                assert_eq!(positions.last(), Some(&99));
+            }
+            arena.reset();
+        }
+        // Only the first frame allocated
+        let stats = arena.last_frame_stats();
+        assert_eq!((stats.borrowed, stats.allocated), (2, 0));
+        assert!(stats.retained_bytes >= 100 * std::mem::size_of::<Entity>() + 100 * std::mem::size_of::<i32>());
+
+        let mut small = FrameArena::with_max_retained_bytes(16);
+        small.collect(0..100u32);
+        small.reset();
+        assert_eq!(small.last_frame_stats().retained_bytes, 0);
+        assert_eq!(small.vec::<u32>().capacity(), 0);
+    }
+}
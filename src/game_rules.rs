@@ -0,0 +1,193 @@
+/// Game rules: which player moves are allowed, what follows a move and when the game ends, so variants such as
+/// puzzle and sandbox modes plug into the same `GridGameWorld` and web shell, and rules are testable without HTTP
+use crate::ecs::{Entity, World};
+use crate::grid_game_components::{GridPositionComponent, ObstacleComponent, PlayerComponent};
+use crate::grid_game_systems::{GRID_HEIGHT, GRID_WIDTH};
+use serde::Serialize;
+use std::any::TypeId;
+use std::str::FromStr;
+
+/// Why a player move was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveBlocked {
+    OutOfBounds,
+    Obstacle { x: i32, y: i32 },
+    /// Refused by a rule of the game mode, with the reason shown to the player
+    Rule(String),
+}
+
+/// How a game ended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum GameOutcome {
+    Won(String),
+    Lost(String),
+}
+
+/// The rules a `GridGameWorld` plays by
+pub trait GameRules: Send {
+    fn name(&self) -> &'static str;
+
+    /// Check a one-tile player step before it is made
+    fn validate_move(&self, world: &World, player: Entity, from: (i32, i32), to: (i32, i32)) -> Result<(), MoveBlocked>;
+
+    /// React to a move that was made
+    fn on_moved(&mut self, _world: &mut World, _player: Entity, _from: (i32, i32), _to: (i32, i32)) {}
+
+    /// How the game ended, once it has; checked after every update
+    fn outcome(&self, _world: &World) -> Option<GameOutcome> {
+        None
+    }
+}
+
+/// Players stay on the map
+pub fn check_bounds(to: (i32, i32)) -> Result<(), MoveBlocked> {
+    if (0..GRID_WIDTH).contains(&to.0) && (0..GRID_HEIGHT).contains(&to.1) {
+        Ok(())
+    } else {
+        Err(MoveBlocked::OutOfBounds)
+    }
+}
+
+/// Obstacles such as walls and buildings block the tile they stand on
+pub fn check_obstacles(world: &World, to: (i32, i32)) -> Result<(), MoveBlocked> {
+    let blocked = world.entities_with_components(&[TypeId::of::<ObstacleComponent>(), TypeId::of::<GridPositionComponent>()])
+        .into_iter()
+        .any(|entity| world.get_component::<GridPositionComponent>(entity).is_some_and(|pos| (pos.x, pos.y) == to));
+    if blocked {
+        Err(MoveBlocked::Obstacle { x: to.0, y: to.1 })
+    } else {
+        Ok(())
+    }
+}
+
+/// The city builder: walk anywhere on the map that isn't built over, with no end
+pub struct CityRules;
+
+impl GameRules for CityRules {
+    fn name(&self) -> &'static str {
+        "city"
+    }
+
+    fn validate_move(&self, world: &World, _player: Entity, _from: (i32, i32), to: (i32, i32)) -> Result<(), MoveBlocked> {
+        check_bounds(to)?;
+        check_obstacles(world, to)
+    }
+}
+
+/// Free building: players walk through obstacles to reach any tile
+pub struct SandboxRules;
+
+impl GameRules for SandboxRules {
+    fn name(&self) -> &'static str {
+        "sandbox"
+    }
+
+    fn validate_move(&self, _world: &World, _player: Entity, _from: (i32, i32), to: (i32, i32)) -> Result<(), MoveBlocked> {
+        check_bounds(to)
+    }
+}
+
+/// Reach the goal tile within a number of moves
+pub struct PuzzleRules {
+    pub goal: (i32, i32),
+    pub move_limit: u32,
+    pub moves: u32,
+}
+
+impl PuzzleRules {
+    pub fn new(goal: (i32, i32), move_limit: u32) -> Self {
+        Self { goal, move_limit, moves: 0 }
+    }
+}
+
+impl GameRules for PuzzleRules {
+    fn name(&self) -> &'static str {
+        "puzzle"
+    }
+
+    fn validate_move(&self, world: &World, _player: Entity, _from: (i32, i32), to: (i32, i32)) -> Result<(), MoveBlocked> {
+        if self.moves >= self.move_limit {
+            return Err(MoveBlocked::Rule("No moves left".to_string()));
+        }
+        check_bounds(to)?;
+        check_obstacles(world, to)
+    }
+
+    fn on_moved(&mut self, _world: &mut World, _player: Entity, _from: (i32, i32), _to: (i32, i32)) {
+        self.moves += 1;
+    }
+
+    fn outcome(&self, world: &World) -> Option<GameOutcome> {
+        let at_goal = world.entities_with_components(&[TypeId::of::<PlayerComponent>(), TypeId::of::<GridPositionComponent>()])
+            .into_iter()
+            .any(|entity| world.get_component::<GridPositionComponent>(entity).is_some_and(|pos| (pos.x, pos.y) == self.goal));
+        if at_goal {
+            Some(GameOutcome::Won(format!("Reached the goal in {} moves", self.moves)))
+        } else if self.moves >= self.move_limit {
+            Some(GameOutcome::Lost(format!("Out of moves after {}", self.move_limit)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Game modes selectable when starting the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameMode {
+    #[default]
+    City,
+    Sandbox,
+    /// Reach the far corner of the map within 30 moves
+    Puzzle,
+}
+
+impl GameMode {
+    pub fn rules(self) -> Box<dyn GameRules> {
+        match self {
+            GameMode::City => Box::new(CityRules),
+            GameMode::Sandbox => Box::new(SandboxRules),
+            GameMode::Puzzle => Box::new(PuzzleRules::new((GRID_WIDTH - 1, GRID_HEIGHT - 1), 30)),
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "city" => Ok(GameMode::City),
+            "sandbox" => Ok(GameMode::Sandbox),
+            "puzzle" => Ok(GameMode::Puzzle),
+            other => Err(format!("Unknown game mode '{}'", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modes_judge_moves_without_a_game_loop() {
+        let mut world = World::new();
+        world.spawn((GridPositionComponent { x: 2, y: 0 }, ObstacleComponent { block_movement: true })).unwrap();
+        let player = world.spawn((GridPositionComponent { x: 1, y: 0 }, PlayerComponent { name: "Hero".to_string() })).unwrap();
+
+        assert_eq!(CityRules.validate_move(&world, player, (1, 0), (2, 0)), Err(MoveBlocked::Obstacle { x: 2, y: 0 }));
+        assert_eq!(CityRules.validate_move(&world, player, (1, 0), (1, -1)), Err(MoveBlocked::OutOfBounds));
+        assert_eq!(SandboxRules.validate_move(&world, player, (1, 0), (2, 0)), Ok(()));
+
+        let mut puzzle = PuzzleRules::new((1, 1), 2);
+        assert_eq!(puzzle.outcome(&world), None);
+        puzzle.on_moved(&mut world, player, (1, 0), (0, 0));
+        world.get_component_mut::<GridPositionComponent>(player).unwrap().y = 1;
+        assert_eq!(puzzle.outcome(&world), Some(GameOutcome::Won("Reached the goal in 1 moves".to_string())));
+
+        world.get_component_mut::<GridPositionComponent>(player).unwrap().y = 0;
+        puzzle.on_moved(&mut world, player, (1, 1), (1, 0));
+        assert_eq!(puzzle.validate_move(&world, player, (1, 0), (1, 1)), Err(MoveBlocked::Rule("No moves left".to_string())));
+        assert!(matches!(puzzle.outcome(&world), Some(GameOutcome::Lost(_))));
+        assert_eq!("puzzle".parse::<GameMode>().map(|mode| mode.rules().name()), Ok("puzzle"));
+    }
+}
@@ -0,0 +1,241 @@
+/// Golden-frame regression tests for the rendering pipeline: predefined scenes (grid, sprites in z-order, stroked
+/// shapes, text) are rendered on the headless device and compared with the command lists and images stored in
+/// `data/golden`, so a change to the render protocol or the rasterizer shows up as a failing test
+/// Run the tests with `UPDATE_GOLDEN=1` to rewrite the goldens after an intended change
+use crate::core::math::camera2d::Camera2d;
+use crate::core::math::draw_order::DrawSortKey;
+use crate::core::math::{Color, ShapeType, Transform2d, Vector2d};
+use crate::rendering::{HeadlessRenderingDevice, ImageBuffer, RenderCommand, RenderingDevice};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the golden command lists and images are kept
+pub const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/golden");
+
+/// Where frames that don't match their golden are saved, outside the source tree
+pub fn actual_dir() -> PathBuf {
+    std::env::temp_dir().join("citybuilder-golden")
+}
+
+/// How far a rendered frame may stray from its golden image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference allowed in any channel of a pixel
+    pub channel: u8,
+    /// Pixels allowed to differ by more than `channel`
+    pub max_pixels: usize,
+}
+
+impl Default for Tolerance {
+    /// Room for rounding in blending and anti-aliasing, but not for a shape moving
+    fn default() -> Self {
+        Self { channel: 2, max_pixels: 4 }
+    }
+}
+
+/// Commands rendered into a frame of a fixed size
+#[derive(Debug, Clone)]
+pub struct GoldenScene {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub commands: Vec<RenderCommand>,
+}
+
+impl GoldenScene {
+    pub fn new(name: &'static str, width: u32, height: u32, commands: Vec<RenderCommand>) -> Self {
+        Self { name, width, height, commands }
+    }
+
+    pub fn render(&self) -> Result<ImageBuffer, Box<dyn Error>> {
+        let mut device = HeadlessRenderingDevice::new(self.width, self.height)?;
+        device.initialize()?;
+        for command in &self.commands {
+            device.execute_command(command.clone())?;
+        }
+        device.capture_frame()
+    }
+
+    /// The commands one per line, as stored in the golden command list
+    pub fn command_list(&self) -> String {
+        self.commands.iter().map(|command| format!("{:?}\n", command)).collect()
+    }
+
+    pub fn commands_path(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("{}.commands.txt", self.name))
+    }
+
+    pub fn image_path(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("{}.png", self.name))
+    }
+}
+
+/// The scenes checked against goldens
+pub fn scenes() -> Vec<GoldenScene> {
+    vec![grid_scene(), sprites_scene(), shapes_scene(), text_scene()]
+}
+
+fn grid_scene() -> GoldenScene {
+    GoldenScene::new("grid", 64, 48, vec![
+        RenderCommand::Clear { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
+        RenderCommand::DrawGrid {
+            width: 4,
+            height: 3,
+            cell_size: 16.0,
+            line_color: (0.2, 0.2, 0.2, 1.0),
+            background_color: (0.9, 0.95, 0.9, 1.0),
+        },
+    ])
+}
+
+/// Overlapping sprites submitted out of order, then ordered by the pipeline's draw sort
+fn sprites_scene() -> GoldenScene {
+    let sprite = |x: f32, color: Color, z_order: i32| {
+        RenderCommand::sprite("building", Vector2d::new(24.0, 24.0))
+            .at(Vector2d::new(x, 24.0))
+            .color(color)
+            .z_order(z_order)
+            .build()
+            .expect("Golden sprites are valid")
+    };
+    let mut draws: Vec<(DrawSortKey, RenderCommand)> = [
+        (2, sprite(40.0, Color::blue(), 2)),
+        (0, sprite(24.0, Color::red(), 0)),
+        (1, sprite(32.0, Color::green(), 1)),
+    ].into_iter()
+        .enumerate()
+        .map(|(entity, (z_order, command))| (DrawSortKey { layer: 0, z_order, y: 0.0, entity: entity as u32 }, command))
+        .collect();
+    Camera2d::new().sort_draws(&mut draws);
+
+    let mut commands = vec![RenderCommand::Clear { r: 1.0, g: 1.0, b: 1.0, a: 1.0 }];
+    commands.extend(draws.into_iter().map(|(_, command)| command));
+    GoldenScene::new("sprites_z_order", 64, 48, commands)
+}
+
+fn shapes_scene() -> GoldenScene {
+    let stroke = Color::black();
+    let shapes = [
+        RenderCommand::shape(ShapeType::Rectangle { width: 20.0, height: 14.0 })
+            .at(Vector2d::new(14.0, 12.0))
+            .fill(Color::yellow())
+            .stroke(stroke, 2.0),
+        RenderCommand::shape(ShapeType::Circle { radius: 9.0 })
+            .at(Vector2d::new(44.0, 14.0))
+            .fill(Color::new(0.2, 0.6, 1.0, 0.8))
+            .stroke(stroke, 1.0),
+        RenderCommand::shape(ShapeType::Triangle {
+            vertex1: Vector2d::new(0.0, -10.0),
+            vertex2: Vector2d::new(10.0, 8.0),
+            vertex3: Vector2d::new(-10.0, 8.0),
+        })
+            .transform(Transform2d::translation(Vector2d::new(32.0, 36.0)))
+            .no_fill()
+            .stroke(Color::red(), 2.0),
+    ];
+    let mut commands = vec![RenderCommand::Clear { r: 1.0, g: 1.0, b: 1.0, a: 1.0 }];
+    commands.extend(shapes.into_iter().map(|shape| shape.build().expect("Golden shapes are valid")));
+    GoldenScene::new("shapes_with_strokes", 64, 48, commands)
+}
+
+fn text_scene() -> GoldenScene {
+    GoldenScene::new("text", 64, 32, vec![
+        RenderCommand::Clear { r: 1.0, g: 1.0, b: 1.0, a: 1.0 },
+        RenderCommand::text("CITY 42", 10.0)
+            .at(Vector2d::new(32.0, 16.0))
+            .build()
+            .expect("Golden text is valid"),
+    ])
+}
+
+/// Compare two frames, describing how they differ when that's beyond the tolerance
+pub fn compare_frames(expected: &ImageBuffer, actual: &ImageBuffer, tolerance: Tolerance) -> Result<(), String> {
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return Err(format!("Frame is {}x{}, golden is {}x{}", actual.width, actual.height, expected.width, expected.height));
+    }
+    let differing: Vec<usize> = expected.pixels.chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+        .enumerate()
+        .filter(|(_, (a, b))| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > tolerance.channel))
+        .map(|(index, _)| index)
+        .collect();
+    if differing.len() <= tolerance.max_pixels {
+        return Ok(());
+    }
+    let first = differing[0] as u32;
+    let (x, y) = (first % expected.width, first / expected.width);
+    Err(format!(
+        "{} pixels differ (up to {} allowed), first at ({}, {}): golden {:?}, rendered {:?}",
+        differing.len(), tolerance.max_pixels, x, y, expected.pixel(x, y), actual.pixel(x, y),
+    ))
+}
+
+/// Check a scene against its goldens in `dir`, or rewrite them when `update` is set
+/// On a mismatch the rendered frame is saved as `<name>.actual.png` in `actual_dir()` for a look
+pub fn check_scene(scene: &GoldenScene, dir: &Path, tolerance: Tolerance, update: bool) -> Result<(), String> {
+    let frame = scene.render().map_err(|e| format!("{}: rendering failed: {}", scene.name, e))?;
+    let commands = scene.command_list();
+    if update {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        fs::write(scene.commands_path(dir), &commands).map_err(|e| e.to_string())?;
+        return frame.save_png(&scene.image_path(dir)).map_err(|e| e.to_string());
+    }
+
+    let missing = |path: &Path, e: std::io::Error| {
+        format!("{}: no golden at {} ({}); run with UPDATE_GOLDEN=1 to record it", scene.name, path.display(), e)
+    };
+    let commands_path = scene.commands_path(dir);
+    let golden_commands = fs::read_to_string(&commands_path).map_err(|e| missing(&commands_path, e))?;
+    if let Some((line, (golden, rendered))) = golden_commands.lines().zip(commands.lines()).enumerate()
+        .find(|(_, (golden, rendered))| golden != rendered)
+    {
+        return Err(format!("{}: command {} changed\n  golden:   {}\n  rendered: {}", scene.name, line + 1, golden, rendered));
+    }
+    if golden_commands.lines().count() != commands.lines().count() {
+        return Err(format!(
+            "{}: {} commands rendered, golden has {}", scene.name, commands.lines().count(), golden_commands.lines().count(),
+        ));
+    }
+
+    let image_path = scene.image_path(dir);
+    let png = fs::read(&image_path).map_err(|e| missing(&image_path, e))?;
+    let golden = ImageBuffer::from_png(&png).map_err(|e| format!("{}: {}", scene.name, e))?;
+    compare_frames(&golden, &frame, tolerance).map_err(|e| {
+        let actual = actual_dir().join(format!("{}.actual.png", scene.name));
+        match fs::create_dir_all(actual_dir()).map_err(|e| e.into()).and_then(|_| frame.save_png(&actual)) {
+            Ok(()) => format!("{}: {} (rendered frame saved to {})", scene.name, e, actual.display()),
+            Err(save_error) => format!("{}: {} (saving the rendered frame failed: {})", scene.name, e, save_error),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scenes_match_their_goldens() {
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+        let failures: Vec<String> = scenes().iter()
+            .filter_map(|scene| check_scene(scene, Path::new(GOLDEN_DIR), Tolerance::default(), update).err())
+            .collect();
+        assert!(failures.is_empty(), "Golden frames differ:\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_frames_compare_within_tolerance() {
+        let golden = grid_scene().render().unwrap();
+        assert_eq!(ImageBuffer::from_png(&golden.to_png()).unwrap(), golden);
+
+        let mut nudged = golden.clone();
+        nudged.pixels[0] = nudged.pixels[0].saturating_add(2);
+        assert!(compare_frames(&golden, &nudged, Tolerance::default()).is_ok());
+
+        let mut moved = golden.clone();
+        moved.pixels[..40].fill(255);
+        let error = compare_frames(&golden, &moved, Tolerance::default()).unwrap_err();
+        assert!(error.starts_with("10 pixels differ"), "{}", error);
+        assert!(compare_frames(&golden, &ImageBuffer::new(1, 1).unwrap(), Tolerance::default()).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+/// Dirty-rectangle updates for the text grid sent to web clients
+/// Updates are numbered and clients acknowledge the last one they applied, so a poll only carries the runs of
+/// cells that changed since then, and a lost response costs a full grid instead of a corrupted one
+use crate::core::math::camera2d::Camera2d;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+/// Above this share of changed cells a full grid is smaller than the patches describing it
+pub const FULL_REFRESH_RATIO: f32 = 0.5;
+/// Grids kept per client while waiting for acknowledgements; past this the client gets a full grid
+pub const MAX_UNACKED_UPDATES: usize = 16;
+
+/// A run of changed cells on one row, starting at tile (x, y)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GridPatch {
+    pub x: usize,
+    pub y: usize,
+    pub text: String,
+}
+
+/// What a client needs to bring its grid up to date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridUpdate {
+    /// Redraw everything from this grid text
+    Full(String),
+    /// Overwrite these runs; empty when nothing changed
+    Patches(Vec<GridPatch>),
+}
+
+struct SentGrid {
+    seq: u64,
+    rows: Vec<Vec<char>>,
+    // Camera scale, view size and pixel ratio the grid was drawn with
+    view: [u32; 4],
+}
+
+/// Grids sent to each client, from the last one it acknowledged to the newest
+#[derive(Default)]
+pub struct GridDiffTracker {
+    sent: HashMap<String, VecDeque<SentGrid>>,
+    next_seq: u64,
+}
+
+impl GridDiffTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The update taking `client` from the grid of update `ack` to `grid`, and the number `grid` is remembered under
+    /// Without an ack the client is assumed to have applied the latest update. Falls back to a full refresh for a new
+    /// client, an ack of a forgotten update, `MAX_UNACKED_UPDATES` unacknowledged updates, a changed camera or grid
+    /// size, or when most cells changed
+    pub fn update(&mut self, client: &str, ack: Option<u64>, grid: &str, camera: &Camera2d) -> (u64, GridUpdate) {
+        let rows: Vec<Vec<char>> = grid.split('\n').map(|row| row.chars().collect()).collect();
+        let (view_width, view_height) = camera.view_dimensions();
+        let view = [camera.scale(), view_width, view_height, camera.device_pixel_ratio()].map(f32::to_bits);
+
+        // Grids older than the acknowledged one can't be needed again
+        let sent = self.sent.entry(client.to_string()).or_default();
+        let base = match ack {
+            Some(ack) => {
+                sent.retain(|grid| grid.seq >= ack);
+                sent.front().filter(|grid| grid.seq == ack)
+            }
+            None => {
+                sent.drain(..sent.len().saturating_sub(1));
+                sent.back()
+            }
+        };
+        let patches = base
+            .filter(|grid| grid.view == view && sent.len() < MAX_UNACKED_UPDATES)
+            .and_then(|grid| diff_rows(&grid.rows, &rows));
+        if patches.is_none() {
+            // A full grid doesn't build on anything sent before
+            sent.clear();
+        }
+        self.next_seq += 1;
+        sent.push_back(SentGrid { seq: self.next_seq, rows, view });
+        let update = match patches {
+            Some(patches) => GridUpdate::Patches(patches),
+            None => GridUpdate::Full(grid.to_string()),
+        };
+        (self.next_seq, update)
+    }
+
+    /// Send `client` a full grid next time, e.g. after its page reloaded
+    pub fn invalidate(&mut self, client: &str) {
+        self.sent.remove(client);
+    }
+}
+
+/// Changed runs between two grids, or None when a full refresh is the better choice
+fn diff_rows(old: &[Vec<char>], new: &[Vec<char>]) -> Option<Vec<GridPatch>> {
+    if old.len() != new.len() || old.iter().zip(new).any(|(a, b)| a.len() != b.len()) {
+        return None;
+    }
+    let total_cells: usize = new.iter().map(Vec::len).sum();
+    let mut changed_cells = 0;
+    let mut patches = Vec::new();
+    for (y, (old_row, new_row)) in old.iter().zip(new).enumerate() {
+        let mut x = 0;
+        while x < new_row.len() {
+            if old_row[x] == new_row[x] {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < new_row.len() && old_row[x] != new_row[x] {
+                x += 1;
+            }
+            changed_cells += x - start;
+            patches.push(GridPatch { x: start, y, text: new_row[start..x].iter().collect() });
+        }
+    }
+    if changed_cells as f32 > total_cells as f32 * FULL_REFRESH_RATIO {
+        return None;
+    }
+    Some(patches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_changed_runs_are_sent() {
+        let mut tracker = GridDiffTracker::new();
+        let mut camera = Camera2d::new();
+        let update = |tracker: &mut GridDiffTracker, client: &str, grid: &str, camera: &Camera2d| tracker.update(client, None, grid, camera).1;
+        assert_eq!(update(&mut tracker, "a", "....\n....", &camera), GridUpdate::Full("....\n....".to_string()));
+        assert_eq!(update(&mut tracker, "a", "....\n....", &camera), GridUpdate::Patches(Vec::new()));
+        assert_eq!(update(&mut tracker, "a", ".@@.\n...#", &camera), GridUpdate::Patches(vec![
+            GridPatch { x: 1, y: 0, text: "@@".to_string() },
+            GridPatch { x: 3, y: 1, text: "#".to_string() },
+        ]));
+
+        // Other clients, camera changes, resized grids and mostly changed grids get the whole grid
+        assert!(matches!(update(&mut tracker, "b", ".@@.\n...#", &camera), GridUpdate::Full(_)));
+        assert!(matches!(update(&mut tracker, "a", "#####\n#####", &camera), GridUpdate::Full(_)));
+        assert!(matches!(update(&mut tracker, "a", ".....\n.....", &camera), GridUpdate::Full(_)));
+        camera.zoom(2.0);
+        assert!(matches!(update(&mut tracker, "a", ".....\n.....", &camera), GridUpdate::Full(_)));
+        tracker.invalidate("a");
+        assert!(matches!(update(&mut tracker, "a", ".....\n.....", &camera), GridUpdate::Full(_)));
+    }
+
+    #[test]
+    fn test_patches_build_on_the_acknowledged_grid() {
+        let mut tracker = GridDiffTracker::new();
+        let camera = Camera2d::new();
+        let (first, _) = tracker.update("a", None, "....\n....", &camera);
+        // The response to this update is lost, so the client still shows the first grid
+        let (lost, _) = tracker.update("a", Some(first), ".@..\n....", &camera);
+        let (_, update) = tracker.update("a", Some(first), "..@.\n....", &camera);
+        assert_eq!(update, GridUpdate::Patches(vec![GridPatch { x: 2, y: 0, text: "@".to_string() }]));
+        let (_, update) = tracker.update("a", Some(lost), "..@.\n....", &camera);
+        assert_eq!(update, GridUpdate::Patches(vec![GridPatch { x: 1, y: 0, text: ".@".to_string() }]));
+
+        // Grids older than the acknowledged one are pruned, and an ack of one is answered with the whole grid
+        assert!(matches!(tracker.update("a", Some(first), "....\n....", &camera).1, GridUpdate::Full(_)));
+
+        // A client that stops acknowledging gets a full grid once the backlog fills up
+        let (acked, _) = tracker.update("b", None, "....\n....", &camera);
+        for _ in 1..MAX_UNACKED_UPDATES {
+            assert!(matches!(tracker.update("b", Some(acked), "....\n....", &camera).1, GridUpdate::Patches(_)));
+        }
+        assert!(matches!(tracker.update("b", Some(acked), "....\n....", &camera).1, GridUpdate::Full(_)));
+        assert_eq!(tracker.sent["b"].len(), 1);
+    }
+}
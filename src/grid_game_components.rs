@@ -68,13 +68,36 @@ impl Component for ObstacleComponent {
     }
 }
 
-/// Input component for handling user input
+/// Local player slot steering a player entity; the devices assigned to the slot in their
+/// `DeviceProfile` move it. Player entities without one belong to slot 0
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerSlotComponent {
+    pub slot: u32,
+}
+
+impl Component for PlayerSlotComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Per-entity input for local multiplayer splits; shared input lives in the `Input` resource
 #[derive(Clone, Debug)]
 pub struct InputComponent {
     pub move_up: bool,
     pub move_down: bool,
     pub move_left: bool,
     pub move_right: bool,
+    /// Text typed by this player, drained by whoever reads it
+    pub text_buffer: String,
 }
 
 impl Component for InputComponent {
@@ -98,9 +121,27 @@ impl InputComponent {
             move_down: false,
             move_left: false,
             move_right: false,
+            text_buffer: String::new(),
         }
     }
     
+    /// Append composed text from a `TextInput` event
+    pub fn push_text(&mut self, text: &str) {
+        self.text_buffer.push_str(text);
+    }
+    
+    /// Take the buffered text, leaving the buffer empty
+    pub fn take_text(&mut self) -> String {
+        std::mem::take(&mut self.text_buffer)
+    }
+    
+    /// Grid step requested by the movement flags
+    pub fn movement_step(&self) -> (i32, i32) {
+        let dx = self.move_right as i32 - self.move_left as i32;
+        let dy = self.move_down as i32 - self.move_up as i32;
+        (dx, dy)
+    }
+    
     pub fn clear(&mut self) {
         self.move_up = false;
         self.move_down = false;
@@ -164,5 +205,10 @@ mod tests {
         input.clear();
         assert!(!input.move_up);
         assert!(!input.move_right);
+        
+        input.push_text("Ne");
+        input.push_text("w");
+        assert_eq!(input.take_text(), "New");
+        assert!(input.text_buffer.is_empty());
     }
 }
\ No newline at end of file
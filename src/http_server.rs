@@ -1,5 +1,11 @@
 use tiny_http::{Server, Request, Response, Header};
 use std::io;
+use rust_citybuilder_game::rendering::web_service_manager::bind_with_fallback;
+use rust_citybuilder_game::service_discovery::{self, WEB_SERVICE};
+use rust_citybuilder_game::shutdown::{shutdown_signalled, SHUTDOWN_POLL_INTERVAL};
+
+/// Port the `server` subcommand listens on when no address is given, on the host of the `ports` section
+pub const HELLO_SERVER_PORT: u16 = 8080;
 
 /// Simple HTTP server that serves a hello world webpage
 pub struct HelloWorldServer {
@@ -7,13 +13,14 @@ pub struct HelloWorldServer {
 }
 
 impl HelloWorldServer {
-    /// Create a new HTTP server listening on the specified address
+    /// Create a new HTTP server listening on the specified address, or on a free port when it is taken
     pub fn new(address: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let server = Server::http(address)
+        let (server, bound) = bind_with_fallback(address)
             .map_err(|e| format!("Failed to start server: {}", e))?;
+        service_discovery::register_service(WEB_SERVICE, bound);
         
-        println!("HTTP server started on http://{}", address);
-        println!("Visit http://{} in your browser to see the hello world page", address);
+        println!("HTTP server started on http://{}", bound);
+        println!("Visit http://{} in your browser to see the hello world page", bound);
         
         Ok(Self { server })
     }
@@ -22,13 +29,16 @@ impl HelloWorldServer {
     pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
         println!("Server is running... Press Ctrl+C to stop");
         
-        for request in self.server.incoming_requests() {
+        // Poll so Ctrl+C stops the loop between requests instead of killing a response mid-write
+        while !shutdown_signalled() {
+            let Some(request) = self.server.recv_timeout(SHUTDOWN_POLL_INTERVAL)? else { continue };
             match self.handle_request(request) {
                 Ok(_) => {},
                 Err(e) => eprintln!("Error handling request: {}", e),
             }
         }
         
+        println!("Server stopped");
         Ok(())
     }
     
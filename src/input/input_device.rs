@@ -28,6 +28,21 @@ pub enum InputEvent {
     TouchRelease { touch_id: u32, position: Vector2d },
     /// Touch screen movement event
     TouchMove { touch_id: u32, position: Vector2d, delta: Vector2d },
+    /// Composed text typed by the user (layout and IME aware, may hold several characters)
+    TextInput { text: String },
+    /// Client viewport changed size (in CSS pixels)
+    WindowResize { width: u32, height: u32, device_pixel_ratio: f32 },
+    /// Client page was hidden or shown again
+    VisibilityChange { visible: bool },
+    /// Text pasted from the client clipboard
+    ClipboardPaste { text: String },
+}
+
+/// An input event tagged with the device that produced it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceEvent {
+    pub device_id: u32,
+    pub event: InputEvent,
 }
 
 /// Keyboard key identifiers
@@ -42,7 +57,7 @@ pub enum Key {
     // Function keys
     F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
     // Special keys
-    Space, Enter, Escape, Tab, Shift, Control, Alt, Backspace, Delete,
+    Space, Enter, Escape, Tab, Shift, Control, Alt, Backspace, Delete, Backquote,
     // Custom key for unknown keys
     Unknown(String),
 }
@@ -139,6 +154,7 @@ impl Key {
             "alt" => Key::Alt,
             "backspace" => Key::Backspace,
             "delete" | "del" => Key::Delete,
+            "backquote" | "`" => Key::Backquote,
             "f1" => Key::F1, "f2" => Key::F2, "f3" => Key::F3, "f4" => Key::F4,
             "f5" => Key::F5, "f6" => Key::F6, "f7" => Key::F7, "f8" => Key::F8,
             "f9" => Key::F9, "f10" => Key::F10, "f11" => Key::F11, "f12" => Key::F12,
@@ -175,6 +191,7 @@ impl Key {
             Key::Alt => "Alt".to_string(),
             Key::Backspace => "Backspace".to_string(),
             Key::Delete => "Delete".to_string(),
+            Key::Backquote => "Backquote".to_string(),
             Key::F1 => "F1".to_string(), Key::F2 => "F2".to_string(), Key::F3 => "F3".to_string(),
             Key::F4 => "F4".to_string(), Key::F5 => "F5".to_string(), Key::F6 => "F6".to_string(),
             Key::F7 => "F7".to_string(), Key::F8 => "F8".to_string(), Key::F9 => "F9".to_string(),
@@ -1,9 +1,34 @@
 use std::sync::{Arc, Mutex, OnceLock};
 use std::error::Error;
 use std::collections::HashMap;
-use super::{InputDevice, InputEvent, Key, MouseButton};
+use super::{DeviceEvent, InputDevice, InputEvent, Key, MouseButton};
+use super::input_state::KeyBindings;
 use crate::core::math::Vector2d;
 
+/// Per-device input settings; devices without one use the defaults
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceProfile {
+    /// Events of disabled devices are drained and dropped
+    pub enabled: bool,
+    /// Bindings used for this device instead of the player's own
+    pub bindings: Option<KeyBindings>,
+    /// Local player slot the device steers, e.g. 1 for player 2's gamepad
+    pub player: u32,
+}
+
+impl Default for DeviceProfile {
+    fn default() -> Self {
+        Self { enabled: true, bindings: None, player: 0 }
+    }
+}
+
+/// A device became usable or went away, e.g. a gamepad plugged in or a browser tab closed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceConnectionEvent {
+    Connected { device_id: u32 },
+    Disconnected { device_id: u32 },
+}
+
 /// Global input manager that can be accessed from anywhere in the application
 /// This is not an ECS system - it's a globally accessible service
 /// Can handle multiple input devices for split-screen games or multiple input sources
@@ -15,6 +40,11 @@ pub struct InputManager {
     key_states: HashMap<Key, bool>,
     mouse_button_states: HashMap<MouseButton, bool>,
     mouse_position: Vector2d,
+    // Settings by device ID, kept while a device is unplugged so they apply again when it returns
+    profiles: HashMap<u32, DeviceProfile>,
+    // Readiness at the last check, to report devices connecting and disconnecting
+    ready: HashMap<u32, bool>,
+    connection_events: Vec<DeviceConnectionEvent>,
 }
 
 impl InputManager {
@@ -28,22 +58,31 @@ impl InputManager {
             key_states: HashMap::new(),
             mouse_button_states: HashMap::new(),
             mouse_position: Vector2d::new(0.0, 0.0),
+            profiles: HashMap::new(),
+            ready: HashMap::new(),
+            connection_events: Vec::new(),
         }
     }
     
     /// Add an input device to the manager
-    pub fn add_device(&mut self, device: Box<dyn InputDevice>) -> Result<u32, Box<dyn Error>> {
+    pub fn add_device(&mut self, mut device: Box<dyn InputDevice>) -> Result<u32, Box<dyn Error>> {
         let device_id = device.device_id();
         
         if self.device_map.contains_key(&device_id) {
             return Err(format!("Device with ID {} already exists", device_id).into());
         }
         
+        // Devices added after start-up are initialized right away
+        if self.is_initialized {
+            device.initialize()?;
+        }
+        
         let device_arc = Arc::new(Mutex::new(device));
         let index = self.devices.len();
         
         self.devices.push(device_arc);
         self.device_map.insert(device_id, index);
+        self.check_readiness()?;
         
         println!("Added input device with ID: {}", device_id);
         Ok(device_id)
@@ -61,17 +100,24 @@ impl InputManager {
         }
         
         self.is_initialized = true;
+        self.check_readiness()?;
         println!("Input manager initialized with {} devices", self.devices.len());
         Ok(())
     }
     
     /// Poll for input events from all devices
     pub fn poll_events(&mut self) -> Result<Vec<InputEvent>, Box<dyn Error>> {
+        Ok(self.poll_device_events()?.into_iter().map(|event| event.event).collect())
+    }
+    
+    /// Poll for input events from all devices, in device registration order, tagged with their device IDs
+    pub fn poll_device_events(&mut self) -> Result<Vec<DeviceEvent>, Box<dyn Error>> {
         if !self.is_initialized {
             return Ok(Vec::new());
         }
         
         self.event_buffer.clear();
+        self.check_readiness()?;
         
         // Collect events from all devices first
         let mut all_events = Vec::new();
@@ -80,17 +126,21 @@ impl InputManager {
             let mut device = device.lock().map_err(|e| format!("Failed to lock device: {}", e))?;
             
             if device.is_ready() {
+                let device_id = device.device_id();
                 let events = device.poll_events()?;
-                all_events.extend(events);
+                // Disabled devices are still drained, so their input doesn't pile up until they are enabled
+                if self.device_profile(device_id).enabled {
+                    all_events.extend(events.into_iter().map(|event| DeviceEvent { device_id, event }));
+                }
             }
         }
         
         // Update internal state based on events
         for event in &all_events {
-            self.update_state_from_event(event);
+            self.update_state_from_event(&event.event);
         }
         
-        self.event_buffer = all_events.clone();
+        self.event_buffer = all_events.iter().map(|event| event.event.clone()).collect();
         Ok(all_events)
     }
     
@@ -189,6 +239,9 @@ impl InputManager {
             // Remove from devices vector and update indices in device_map
             self.devices.remove(index);
             self.device_map.remove(&device_id);
+            if self.ready.remove(&device_id) == Some(true) {
+                self.connection_events.push(DeviceConnectionEvent::Disconnected { device_id });
+            }
             
             // Update indices for devices that were shifted
             for (_id, idx) in self.device_map.iter_mut() {
@@ -204,6 +257,48 @@ impl InputManager {
         }
     }
     
+    /// Settings of a device, the defaults when none were set
+    pub fn device_profile(&self, device_id: u32) -> DeviceProfile {
+        self.profiles.get(&device_id).cloned().unwrap_or_default()
+    }
+    
+    /// Settings of every device that has some, by device ID
+    pub fn device_profiles(&self) -> &HashMap<u32, DeviceProfile> {
+        &self.profiles
+    }
+    
+    /// Configure a device, which doesn't have to be plugged in yet
+    pub fn set_device_profile(&mut self, device_id: u32, profile: DeviceProfile) {
+        self.profiles.insert(device_id, profile);
+    }
+    
+    pub fn set_device_enabled(&mut self, device_id: u32, enabled: bool) {
+        self.profiles.entry(device_id).or_default().enabled = enabled;
+    }
+    
+    /// Devices connected and disconnected since the last call, oldest first
+    pub fn drain_connection_events(&mut self) -> Vec<DeviceConnectionEvent> {
+        std::mem::take(&mut self.connection_events)
+    }
+    
+    /// Record devices whose readiness changed since the last check
+    fn check_readiness(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.is_initialized {
+            return Ok(());
+        }
+        for device in &self.devices {
+            let device = device.lock().map_err(|e| format!("Failed to lock device: {}", e))?;
+            let (device_id, ready) = (device.device_id(), device.is_ready());
+            if self.ready.insert(device_id, ready).unwrap_or(false) != ready {
+                self.connection_events.push(match ready {
+                    true => DeviceConnectionEvent::Connected { device_id },
+                    false => DeviceConnectionEvent::Disconnected { device_id },
+                });
+            }
+        }
+        Ok(())
+    }
+    
     /// Shutdown the input manager and all devices
     pub fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
         if !self.is_initialized {
@@ -220,6 +315,7 @@ impl InputManager {
         self.event_buffer.clear();
         self.key_states.clear();
         self.mouse_button_states.clear();
+        self.ready.clear();
         self.is_initialized = false;
         
         println!("Input manager shut down successfully");
@@ -272,6 +368,13 @@ pub fn initialize_global_input_manager() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Shut down the global input manager and its devices, e.g. on Ctrl+C
+pub fn shutdown_global_input_manager() -> Result<(), Box<dyn Error>> {
+    let manager_arc = get_global_input_manager()?;
+    let mut manager = manager_arc.lock().map_err(|e| format!("Failed to lock global manager: {}", e))?;
+    manager.shutdown()
+}
+
 /// Get a reference to the global input manager
 pub fn get_global_input_manager() -> Result<Arc<Mutex<InputManager>>, Box<dyn Error>> {
     GLOBAL_INPUT_MANAGER.get()
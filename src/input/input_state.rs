@@ -0,0 +1,612 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::mem;
+use super::{DeviceEvent, InputEvent, Key, MouseButton};
+use super::input_device::GamepadButton;
+use super::input_manager::{DeviceProfile, InputManager};
+use crate::core::math::Vector2d;
+use serde::{Deserialize, Serialize};
+
+/// Input contexts in ascending priority; systems of higher contexts see input first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InputContext {
+    Gameplay,
+    Menu,
+    TextEntry,
+}
+
+impl InputContext {
+    fn is_keyboard(event: &InputEvent) -> bool {
+        matches!(event, InputEvent::KeyPress { .. } | InputEvent::KeyRelease { .. })
+    }
+}
+
+/// Modifier keys a chord requires; any other modifier held at the same time keeps the chord from matching
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers { ctrl: false, shift: false, alt: false };
+    pub const CTRL: Modifiers = Modifiers { ctrl: true, shift: false, alt: false };
+    pub const SHIFT: Modifiers = Modifiers { ctrl: false, shift: true, alt: false };
+
+    /// Modifiers among the held keys
+    pub fn held(keys_down: &HashSet<Key>) -> Self {
+        Self {
+            ctrl: keys_down.contains(&Key::Control),
+            shift: keys_down.contains(&Key::Shift),
+            alt: keys_down.contains(&Key::Alt),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Track a modifier press or release, for systems walking a frame's events in order
+    pub fn apply(&mut self, event: &InputEvent) {
+        let (key, down) = match event {
+            InputEvent::KeyPress { key } => (key, true),
+            InputEvent::KeyRelease { key } => (key, false),
+            _ => return,
+        };
+        match key {
+            Key::Control => self.ctrl = down,
+            Key::Shift => self.shift = down,
+            Key::Alt => self.alt = down,
+            _ => {}
+        }
+    }
+
+    fn keys(&self) -> Vec<Key> {
+        [(self.ctrl, Key::Control), (self.shift, Key::Shift), (self.alt, Key::Alt)]
+            .into_iter()
+            .filter_map(|(required, key)| required.then_some(key))
+            .collect()
+    }
+}
+
+/// Keys pressed together to trigger an action: modifiers plus up to two other keys, e.g. Ctrl+Z or G+R
+/// A chord without keys is active while exactly its modifiers are held, e.g. Shift for straight drags
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyChord {
+    #[serde(default)]
+    pub modifiers: Modifiers,
+    #[serde(default)]
+    pub keys: Vec<Key>,
+}
+
+impl KeyChord {
+    /// Most keys besides modifiers a chord may combine
+    pub const MAX_KEYS: usize = 2;
+
+    pub fn new(modifiers: Modifiers, keys: &[Key]) -> Self {
+        Self { modifiers, keys: keys.to_vec() }
+    }
+
+    /// Modifier and other keys that all have to be down
+    pub fn all_keys(&self) -> Vec<Key> {
+        let mut keys = self.modifiers.keys();
+        keys.extend(self.keys.iter().cloned());
+        keys
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.keys.len() <= Self::MAX_KEYS && !(self.keys.is_empty() && self.modifiers.is_empty())
+    }
+
+    /// Whether the chord is complete with these keys down and `modifiers` held
+    pub fn matches(&self, is_down: impl Fn(&Key) -> bool, modifiers: Modifiers) -> bool {
+        self.is_valid() && modifiers == self.modifiers && self.keys.iter().all(is_down)
+    }
+}
+
+/// Actions bound to key chords instead of single movement keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    Undo,
+    Redo,
+    /// Held while dragging to keep road and wall drags on one row or column
+    StraightLine,
+}
+
+/// Keys bound to each gameplay action; several keys may trigger the same action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub move_up: Vec<Key>,
+    pub move_down: Vec<Key>,
+    pub move_left: Vec<Key>,
+    pub move_right: Vec<Key>,
+    /// Chords for the other actions; settings files saved before chords existed get the defaults
+    #[serde(default = "KeyBindings::default_actions")]
+    pub actions: BTreeMap<Action, Vec<KeyChord>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            move_up: vec![Key::W, Key::ArrowUp],
+            move_down: vec![Key::S, Key::ArrowDown],
+            move_left: vec![Key::A, Key::ArrowLeft],
+            move_right: vec![Key::D, Key::ArrowRight],
+            actions: Self::default_actions(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn default_actions() -> BTreeMap<Action, Vec<KeyChord>> {
+        BTreeMap::from([
+            (Action::Undo, vec![KeyChord::new(Modifiers::CTRL, &[Key::Z])]),
+            (Action::Redo, vec![
+                KeyChord::new(Modifiers::CTRL, &[Key::Y]),
+                KeyChord::new(Modifiers { ctrl: true, shift: true, alt: false }, &[Key::Z]),
+            ]),
+            (Action::StraightLine, vec![KeyChord::new(Modifiers::SHIFT, &[])]),
+        ])
+    }
+
+    /// Whether a key triggers any movement action
+    pub fn is_bound(&self, key: &Key) -> bool {
+        [&self.move_up, &self.move_down, &self.move_left, &self.move_right].iter().any(|keys| keys.contains(key))
+    }
+
+    pub fn chords(&self, action: Action) -> &[KeyChord] {
+        self.actions.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Snapshot of the input state for a single frame
+#[derive(Debug, Clone, Default)]
+pub struct InputFrame {
+    /// Keys held down at the end of the frame
+    pub keys_down: HashSet<Key>,
+    /// Keys that received a press event during the frame
+    pub keys_pressed: HashSet<Key>,
+    /// Keys that received a release event during the frame
+    pub keys_released: HashSet<Key>,
+    pub mouse_buttons_down: HashSet<MouseButton>,
+    pub mouse_buttons_pressed: HashSet<MouseButton>,
+    pub mouse_position: Vector2d,
+    /// Raw events applied during the frame, in arrival order
+    pub events: Vec<InputEvent>,
+    /// ID of the device that produced each of `events`
+    pub devices: Vec<u32>,
+    /// Events claimed by a higher-priority system, by index into `events`
+    pub consumed: HashSet<usize>,
+    /// Text typed during the frame
+    pub text: String,
+}
+
+impl InputFrame {
+    /// Start the next frame: held state carries over, per-frame state is cleared
+    fn next(&self) -> Self {
+        Self {
+            keys_down: self.keys_down.clone(),
+            mouse_buttons_down: self.mouse_buttons_down.clone(),
+            mouse_position: self.mouse_position,
+            ..Self::default()
+        }
+    }
+
+    fn apply(&mut self, device_id: u32, event: &InputEvent) {
+        match event {
+            InputEvent::KeyPress { key } => {
+                self.keys_down.insert(key.clone());
+                self.keys_pressed.insert(key.clone());
+            }
+            InputEvent::KeyRelease { key } => {
+                self.keys_down.remove(key);
+                self.keys_released.insert(key.clone());
+            }
+            InputEvent::MousePress { button, position } => {
+                self.mouse_buttons_down.insert(button.clone());
+                self.mouse_buttons_pressed.insert(button.clone());
+                self.mouse_position = *position;
+            }
+            InputEvent::MouseRelease { button, position } => {
+                self.mouse_buttons_down.remove(button);
+                self.mouse_position = *position;
+            }
+            InputEvent::MouseMove { position, .. } | InputEvent::MouseWheel { position, .. } => {
+                self.mouse_position = *position;
+            }
+            InputEvent::TextInput { text } => {
+                self.text.push_str(text);
+            }
+            _ => {}
+        }
+        self.events.push(event.clone());
+        self.devices.push(device_id);
+    }
+}
+
+/// Global input state shared by all systems, updated once per frame by `InputSystem`
+/// Double-buffered: the previous frame stays readable for edge detection and replays
+#[derive(Debug, Clone)]
+pub struct Input {
+    current: InputFrame,
+    previous: InputFrame,
+    /// Active contexts; Gameplay is always at the bottom
+    contexts: Vec<InputContext>,
+    bindings: KeyBindings,
+    /// Device settings copied from the input manager each frame
+    device_profiles: HashMap<u32, DeviceProfile>,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self {
+            current: InputFrame::default(),
+            previous: InputFrame::default(),
+            contexts: vec![InputContext::Gameplay],
+            bindings: KeyBindings::default(),
+            device_profiles: HashMap::new(),
+        }
+    }
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Activate a context such as an open menu
+    pub fn push_context(&mut self, context: InputContext) {
+        if !self.contexts.contains(&context) {
+            self.contexts.push(context);
+        }
+    }
+
+    /// Deactivate the most recently pushed context; Gameplay is never popped
+    pub fn pop_context(&mut self) -> Option<InputContext> {
+        if self.contexts.len() > 1 {
+            self.contexts.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Deactivate a specific context wherever it is on the stack
+    pub fn remove_context(&mut self, context: InputContext) -> bool {
+        match self.contexts.iter().rposition(|active| *active == context) {
+            Some(index) if index > 0 => {
+                self.contexts.remove(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_context_active(&self, context: InputContext) -> bool {
+        self.contexts.contains(&context)
+    }
+
+    /// Highest-priority active context
+    pub fn active_context(&self) -> InputContext {
+        self.contexts.iter().copied().max().unwrap_or(InputContext::Gameplay)
+    }
+
+    /// Unconsumed events visible to a context, with their indices for `consume`
+    /// Text entry owns the keyboard, so lower contexts never see key events while it is active
+    pub fn events_for(&self, context: InputContext) -> Vec<(usize, &InputEvent)> {
+        if !self.is_context_active(context) {
+            return Vec::new();
+        }
+        let keyboard_taken = context < InputContext::TextEntry && self.is_context_active(InputContext::TextEntry);
+        self.current.events.iter()
+            .enumerate()
+            .filter(|(index, _)| !self.current.consumed.contains(index))
+            .filter(|(_, event)| !(keyboard_taken && InputContext::is_keyboard(event)))
+            .collect()
+    }
+
+    /// Mark an event as handled so lower-priority systems skip it
+    pub fn consume(&mut self, index: usize) {
+        self.current.consumed.insert(index);
+    }
+
+    /// Check for an unconsumed press of a key that a context can see
+    pub fn is_key_just_pressed_for(&self, context: InputContext, key: &Key) -> bool {
+        self.events_for(context)
+            .iter()
+            .any(|(_, event)| matches!(event, InputEvent::KeyPress { key: pressed } if pressed == key))
+    }
+
+    /// Swap buffers and apply the events received since the last frame, as if from the queued input device
+    pub fn begin_frame(&mut self, events: &[InputEvent]) {
+        let events: Vec<DeviceEvent> = events.iter()
+            .map(|event| DeviceEvent { device_id: super::QUEUED_INPUT_DEVICE_ID, event: event.clone() })
+            .collect();
+        self.begin_frame_from_devices(&events);
+    }
+
+    /// Swap buffers and apply the events the devices produced since the last frame
+    pub fn begin_frame_from_devices(&mut self, events: &[DeviceEvent]) {
+        let next = self.current.next();
+        self.previous = mem::replace(&mut self.current, next);
+        for event in events {
+            self.current.apply(event.device_id, &event.event);
+        }
+    }
+
+    /// Device that produced the event at `index` of this frame's events
+    pub fn event_device(&self, index: usize) -> Option<u32> {
+        self.current.devices.get(index).copied()
+    }
+
+    pub fn current(&self) -> &InputFrame {
+        &self.current
+    }
+
+    pub fn previous(&self) -> &InputFrame {
+        &self.previous
+    }
+
+    /// Check if a key is held down (continuous input)
+    pub fn is_key_pressed(&self, key: &Key) -> bool {
+        self.current.keys_down.contains(key)
+    }
+
+    /// Check if a key was pressed this frame, even if it was released again (discrete input)
+    pub fn is_key_just_pressed(&self, key: &Key) -> bool {
+        self.current.keys_pressed.contains(key)
+    }
+
+    pub fn is_key_just_released(&self, key: &Key) -> bool {
+        self.current.keys_released.contains(key)
+    }
+
+    pub fn is_mouse_button_pressed(&self, button: &MouseButton) -> bool {
+        self.current.mouse_buttons_down.contains(button)
+    }
+
+    pub fn is_mouse_button_just_pressed(&self, button: &MouseButton) -> bool {
+        self.current.mouse_buttons_pressed.contains(button)
+    }
+
+    pub fn mouse_position(&self) -> Vector2d {
+        self.current.mouse_position
+    }
+
+    /// Text typed this frame
+    pub fn text(&self) -> &str {
+        &self.current.text
+    }
+
+    /// Events received this frame
+    pub fn events(&self) -> &[InputEvent] {
+        &self.current.events
+    }
+
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    /// Replace the key bindings, e.g. with a player's saved settings
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+    }
+
+    /// Whether one of the action's chords is complete at the end of this frame
+    pub fn is_action_pressed(&self, action: Action) -> bool {
+        Self::chord_down(&self.bindings, action, &self.current)
+    }
+
+    /// Whether one of the action's chords was completed this frame, counting keys tapped within the frame
+    /// Modifiers have to be held as the last key goes down, so Ctrl+Shift+Z never also fires Ctrl+Z
+    pub fn is_action_just_pressed(&self, action: Action) -> bool {
+        let frame = &self.current;
+        let is_down = |key: &Key| frame.keys_down.contains(key) || frame.keys_pressed.contains(key);
+        let mut modifiers_down = frame.keys_down.clone();
+        modifiers_down.extend(frame.keys_pressed.iter().filter(|key| matches!(key, Key::Control | Key::Shift | Key::Alt)).cloned());
+        let modifiers = Modifiers::held(&modifiers_down);
+        self.bindings.chords(action).iter().any(|chord| {
+            chord.matches(is_down, modifiers) && chord.all_keys().iter().any(|key| frame.keys_pressed.contains(key))
+        })
+    }
+
+    /// Whether a chord of the action was complete last frame and no longer is
+    pub fn is_action_just_released(&self, action: Action) -> bool {
+        Self::chord_down(&self.bindings, action, &self.previous) && !self.is_action_pressed(action)
+    }
+
+    /// Modifier keys held at the end of this frame
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers::held(&self.current.keys_down)
+    }
+
+    fn chord_down(bindings: &KeyBindings, action: Action, frame: &InputFrame) -> bool {
+        let modifiers = Modifiers::held(&frame.keys_down);
+        bindings.chords(action).iter().any(|chord| chord.matches(|key| frame.keys_down.contains(key), modifiers))
+    }
+
+    /// Use the device settings of an input manager, e.g. to split devices between local players
+    pub fn set_device_profiles(&mut self, profiles: HashMap<u32, DeviceProfile>) {
+        self.device_profiles = profiles;
+    }
+
+    /// One-tile movement step for the first local player
+    pub fn movement_step(&self) -> (i32, i32) {
+        self.movement_step_for_player(0)
+    }
+
+    /// One-tile movement step from the bound movement keys (or D-pad) pressed this frame on a local player's
+    /// devices and left to gameplay; a device's profile can replace the player's key bindings
+    pub fn movement_step_for_player(&self, player: u32) -> (i32, i32) {
+        // Up, down, left, right
+        let mut pressed = [false; 4];
+        for (index, event) in self.events_for(InputContext::Gameplay) {
+            let profile = self.current.devices.get(index).and_then(|device| self.device_profiles.get(device));
+            if profile.map_or(0, |profile| profile.player) != player {
+                continue;
+            }
+            let bindings = profile.and_then(|profile| profile.bindings.as_ref()).unwrap_or(&self.bindings);
+            let direction = match event {
+                InputEvent::KeyPress { key } => [&bindings.move_up, &bindings.move_down, &bindings.move_left, &bindings.move_right]
+                    .iter()
+                    .position(|keys| keys.contains(key)),
+                InputEvent::GamepadPress { button, .. } => [GamepadButton::DPadUp, GamepadButton::DPadDown, GamepadButton::DPadLeft, GamepadButton::DPadRight]
+                    .iter()
+                    .position(|pad| pad == button),
+                _ => None,
+            };
+            if let Some(direction) = direction {
+                pressed[direction] = true;
+            }
+        }
+        let [up, down, left, right] = pressed.map(i32::from);
+        (right - left, down - up)
+    }
+}
+
+/// Stage zero of every frame: drains the registered input devices through the manager into the `Input` resource
+/// Nothing else polls devices, so every system sees the same merged events, tagged with their device IDs
+pub struct InputSystem;
+
+impl InputSystem {
+    pub fn update(input: &mut Input, devices: &mut InputManager) -> Result<(), Box<dyn Error>> {
+        let events = devices.poll_device_events()?;
+        input.set_device_profiles(devices.device_profiles().clone());
+        input.begin_frame_from_devices(&events);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputQueue, QueuedInputDevice};
+
+    #[test]
+    fn test_double_buffered_key_state() {
+        let mut input = Input::new();
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::W }]);
+        assert!(input.is_key_pressed(&Key::W));
+        assert!(input.is_key_just_pressed(&Key::W));
+        assert_eq!(input.movement_step(), (0, -1));
+
+        input.begin_frame(&[]);
+        assert!(input.is_key_pressed(&Key::W));
+        assert!(!input.is_key_just_pressed(&Key::W));
+        assert!(input.previous().keys_pressed.contains(&Key::W));
+        assert_eq!(input.movement_step(), (0, 0));
+
+        input.begin_frame(&[InputEvent::KeyRelease { key: Key::W }]);
+        assert!(!input.is_key_pressed(&Key::W));
+        assert!(input.is_key_just_released(&Key::W));
+    }
+
+    #[test]
+    fn test_tap_within_one_frame() {
+        let mut input = Input::new();
+        let (keyboard, mouse) = (InputQueue::new(), InputQueue::new());
+        let mut devices = InputManager::new();
+        devices.add_device(Box::new(QueuedInputDevice::new(keyboard.clone(), 1))).unwrap();
+        devices.add_device(Box::new(QueuedInputDevice::new(mouse.clone(), 2))).unwrap();
+        devices.initialize().unwrap();
+        mouse.push(InputEvent::MousePress { button: MouseButton::Left, position: Vector2d::new(4.0, 2.0) });
+        keyboard.push(InputEvent::KeyPress { key: Key::ArrowRight });
+        keyboard.push(InputEvent::KeyRelease { key: Key::ArrowRight });
+        InputSystem::update(&mut input, &mut devices).unwrap();
+
+        // Events are merged device by device and keep their device IDs
+        assert_eq!(input.events().len(), 3);
+        assert_eq!((input.event_device(0), input.event_device(2)), (Some(1), Some(2)));
+        assert_eq!(input.text(), "");
+        assert_eq!(input.movement_step(), (1, 0));
+        assert!(!input.is_key_pressed(&Key::ArrowRight));
+        assert!(input.is_mouse_button_just_pressed(&MouseButton::Left));
+        assert_eq!(input.mouse_position(), Vector2d::new(4.0, 2.0));
+    }
+
+    #[test]
+    fn test_rebound_movement_keys() {
+        let mut input = Input::new();
+        input.set_bindings(KeyBindings { move_up: vec![Key::I], ..KeyBindings::default() });
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::W }]);
+        assert_eq!(input.movement_step(), (0, 0));
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::I }, InputEvent::KeyPress { key: Key::D }]);
+        assert_eq!(input.movement_step(), (1, -1));
+    }
+
+    #[test]
+    fn test_modifier_chords() {
+        let mut input = Input::new();
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::Control }, InputEvent::KeyPress { key: Key::Z }]);
+        assert!(input.is_action_just_pressed(Action::Undo));
+        assert!(input.is_action_pressed(Action::Undo));
+        input.begin_frame(&[]);
+        assert!(!input.is_action_just_pressed(Action::Undo));
+        assert!(input.is_action_pressed(Action::Undo));
+        input.begin_frame(&[InputEvent::KeyRelease { key: Key::Z }]);
+        assert!(input.is_action_just_released(Action::Undo));
+
+        // An extra modifier picks the other chord; a tap within the frame still counts
+        input.begin_frame(&[
+            InputEvent::KeyPress { key: Key::Shift },
+            InputEvent::KeyPress { key: Key::Z },
+            InputEvent::KeyRelease { key: Key::Z },
+        ]);
+        assert!(input.is_action_just_pressed(Action::Redo));
+        assert!(!input.is_action_just_pressed(Action::Undo));
+        assert!(!input.is_action_pressed(Action::StraightLine));
+        input.begin_frame(&[InputEvent::KeyRelease { key: Key::Control }]);
+        assert!(input.is_action_pressed(Action::StraightLine));
+
+        // Two-key chords fire once both keys are down
+        let mut bindings = KeyBindings::default();
+        bindings.actions.insert(Action::Undo, vec![KeyChord::new(Modifiers::NONE, &[Key::G, Key::R])]);
+        input.set_bindings(bindings);
+        input.begin_frame(&[InputEvent::KeyRelease { key: Key::Shift }, InputEvent::KeyPress { key: Key::G }]);
+        assert!(!input.is_action_just_pressed(Action::Undo));
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::R }]);
+        assert!(input.is_action_just_pressed(Action::Undo));
+    }
+
+    #[test]
+    fn test_menu_consumes_input_before_gameplay() {
+        let mut input = Input::new();
+        input.push_context(InputContext::Menu);
+        assert_eq!(input.active_context(), InputContext::Menu);
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::ArrowUp }, InputEvent::KeyPress { key: Key::D }]);
+
+        // The menu handles the arrow key; D is left for gameplay
+        let menu_events: Vec<usize> = input.events_for(InputContext::Menu).iter()
+            .filter(|(_, event)| matches!(event, InputEvent::KeyPress { key: Key::ArrowUp }))
+            .map(|(index, _)| *index)
+            .collect();
+        for index in menu_events {
+            input.consume(index);
+        }
+        assert_eq!(input.movement_step(), (1, 0));
+        assert!(input.is_key_just_pressed(&Key::ArrowUp));
+
+        // Consumption does not carry over to the next frame
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::W }]);
+        assert_eq!(input.movement_step(), (0, -1));
+    }
+
+    #[test]
+    fn test_text_entry_owns_keyboard() {
+        let mut input = Input::new();
+        input.push_context(InputContext::TextEntry);
+        input.begin_frame(&[
+            InputEvent::KeyPress { key: Key::W },
+            InputEvent::MousePress { button: MouseButton::Left, position: Vector2d::zero() },
+        ]);
+        assert_eq!(input.movement_step(), (0, 0));
+        assert_eq!(input.events_for(InputContext::Gameplay).len(), 1);
+        assert_eq!(input.events_for(InputContext::TextEntry).len(), 2);
+        assert!(input.events_for(InputContext::Menu).is_empty());
+
+        assert_eq!(input.pop_context(), Some(InputContext::TextEntry));
+        assert_eq!(input.pop_context(), None);
+        assert_eq!(input.movement_step(), (0, -1));
+    }
+}
@@ -1,12 +1,16 @@
 pub mod input_device;
 pub mod input_manager;
+pub mod input_state;
+pub mod queued_input_device;
 pub mod web_client_input_device;
 
 pub use input_device::{
-    InputDevice, InputEvent, Key, MouseButton
+    DeviceEvent, InputDevice, InputEvent, Key, MouseButton
 };
 pub use input_manager::{
     initialize_global_input_manager, get_global_input_manager,
-    add_global_input_device, poll_global_input_events, is_global_key_pressed
+    add_global_input_device, poll_global_input_events, is_global_key_pressed,
+    shutdown_global_input_manager
 };
+pub use queued_input_device::{InputQueue, QueuedInputDevice, QUEUED_INPUT_DEVICE_ID};
 pub use web_client_input_device::WebClientInputDevice;
\ No newline at end of file
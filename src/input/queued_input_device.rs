@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use super::{InputDevice, InputEvent, Key, MouseButton};
+use crate::core::math::Vector2d;
+
+/// Device ID of the queue a game world feeds with events posted to its HTTP API
+pub const QUEUED_INPUT_DEVICE_ID: u32 = 0;
+
+/// Shared handle for pushing events into a `QueuedInputDevice`; clones push into the same queue
+#[derive(Debug, Clone, Default)]
+pub struct InputQueue {
+    events: Arc<Mutex<Vec<InputEvent>>>,
+}
+
+impl InputQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue an event for the device's next poll
+    pub fn push(&self, event: InputEvent) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event);
+        }
+    }
+
+    fn take(&self) -> Vec<InputEvent> {
+        self.events.lock().map(|mut events| mem::take(&mut *events)).unwrap_or_default()
+    }
+}
+
+/// Input device for events that arrive through code rather than hardware, such as HTTP commands and tests
+pub struct QueuedInputDevice {
+    queue: InputQueue,
+    device_id: u32,
+    is_initialized: bool,
+    keys_down: HashSet<Key>,
+    mouse_buttons_down: HashSet<MouseButton>,
+    mouse_position: Vector2d,
+}
+
+impl QueuedInputDevice {
+    pub fn new(queue: InputQueue, device_id: u32) -> Self {
+        Self {
+            queue,
+            device_id,
+            is_initialized: false,
+            keys_down: HashSet::new(),
+            mouse_buttons_down: HashSet::new(),
+            mouse_position: Vector2d::zero(),
+        }
+    }
+}
+
+impl InputDevice for QueuedInputDevice {
+    fn initialize(&mut self) -> Result<(), Box<dyn Error>> {
+        self.is_initialized = true;
+        Ok(())
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<InputEvent>, Box<dyn Error>> {
+        let events = self.queue.take();
+        for event in &events {
+            match event {
+                InputEvent::KeyPress { key } => {
+                    self.keys_down.insert(key.clone());
+                }
+                InputEvent::KeyRelease { key } => {
+                    self.keys_down.remove(key);
+                }
+                InputEvent::MousePress { button, position } => {
+                    self.mouse_buttons_down.insert(button.clone());
+                    self.mouse_position = *position;
+                }
+                InputEvent::MouseRelease { button, position } => {
+                    self.mouse_buttons_down.remove(button);
+                    self.mouse_position = *position;
+                }
+                InputEvent::MouseMove { position, .. } | InputEvent::MouseWheel { position, .. } => {
+                    self.mouse_position = *position;
+                }
+                _ => {}
+            }
+        }
+        Ok(events)
+    }
+
+    fn is_key_pressed(&self, key: &Key) -> bool {
+        self.keys_down.contains(key)
+    }
+
+    fn is_mouse_button_pressed(&self, button: &MouseButton) -> bool {
+        self.mouse_buttons_down.contains(button)
+    }
+
+    fn get_mouse_position(&self) -> Vector2d {
+        self.mouse_position
+    }
+
+    fn is_ready(&self) -> bool {
+        self.is_initialized
+    }
+
+    fn device_name(&self) -> &str {
+        "Queued Input"
+    }
+
+    fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        self.is_initialized = false;
+        self.queue.take();
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 use super::{InputDevice, InputEvent, Key, MouseButton};
 use crate::core::math::Vector2d;
-use crate::rendering::web_service_manager::WebServiceManager;
+use crate::rendering::web_service_manager::{ConnectionStatus, WebServiceManager};
 use serde::{Serialize, Deserialize};
 
 /// Message types for input communication with web client
@@ -21,6 +21,48 @@ pub enum InputMessage {
     MouseMove { x: f32, y: f32, delta_x: f32, delta_y: f32 },
     /// Mouse wheel scroll event from web client
     MouseWheel { delta: f32, x: f32, y: f32 },
+    /// Text typed into the web client
+    TextInput { text: String },
+    /// Browser window resized
+    Resize { width: u32, height: u32, device_pixel_ratio: f32 },
+    /// Browser tab hidden or shown
+    VisibilityChange { visible: bool },
+    /// Text pasted into the web client
+    Paste { text: String },
+}
+
+impl InputMessage {
+    /// Convert the wire message into an input event
+    pub fn to_event(&self) -> InputEvent {
+        match self {
+            InputMessage::KeyPress { key } => InputEvent::KeyPress { key: Key::from_string(key) },
+            InputMessage::KeyRelease { key } => InputEvent::KeyRelease { key: Key::from_string(key) },
+            InputMessage::MousePress { button, x, y } => InputEvent::MousePress {
+                button: MouseButton::from_string(button),
+                position: Vector2d::new(*x, *y),
+            },
+            InputMessage::MouseRelease { button, x, y } => InputEvent::MouseRelease {
+                button: MouseButton::from_string(button),
+                position: Vector2d::new(*x, *y),
+            },
+            InputMessage::MouseMove { x, y, delta_x, delta_y } => InputEvent::MouseMove {
+                position: Vector2d::new(*x, *y),
+                delta: Vector2d::new(*delta_x, *delta_y),
+            },
+            InputMessage::MouseWheel { delta, x, y } => InputEvent::MouseWheel {
+                delta: *delta,
+                position: Vector2d::new(*x, *y),
+            },
+            InputMessage::TextInput { text } => InputEvent::TextInput { text: text.clone() },
+            InputMessage::Resize { width, height, device_pixel_ratio } => InputEvent::WindowResize {
+                width: *width,
+                height: *height,
+                device_pixel_ratio: *device_pixel_ratio,
+            },
+            InputMessage::VisibilityChange { visible } => InputEvent::VisibilityChange { visible: *visible },
+            InputMessage::Paste { text } => InputEvent::ClipboardPaste { text: text.clone() },
+        }
+    }
 }
 
 /// Web client input device that receives input from a web client
@@ -92,6 +134,15 @@ impl WebClientInputDevice {
         }
     }
     
+    /// Distinguish a device nobody connected to yet from one whose clients dropped out
+    pub fn connection_status(&self) -> ConnectionStatus {
+        if let Ok(service) = self.web_service.lock() {
+            service.connection_status()
+        } else {
+            ConnectionStatus::NeverConnected
+        }
+    }
+    
     /// Process incoming messages from web clients
     fn process_web_messages(&mut self) -> Result<(), Box<dyn Error>> {
         // Collect messages first to avoid borrowing conflicts
@@ -122,43 +173,27 @@ impl WebClientInputDevice {
     
     /// Process an individual input message and update state
     fn process_input_message(&mut self, message: InputMessage) -> Result<(), Box<dyn Error>> {
-        let event = match message {
-            InputMessage::KeyPress { key } => {
-                let key_enum = Key::from_string(&key);
-                self.key_states.insert(key_enum.clone(), true);
-                InputEvent::KeyPress { key: key_enum }
+        let event = message.to_event();
+        match &event {
+            InputEvent::KeyPress { key } => {
+                self.key_states.insert(key.clone(), true);
             }
-            InputMessage::KeyRelease { key } => {
-                let key_enum = Key::from_string(&key);
-                self.key_states.insert(key_enum.clone(), false);
-                InputEvent::KeyRelease { key: key_enum }
+            InputEvent::KeyRelease { key } => {
+                self.key_states.insert(key.clone(), false);
             }
-            InputMessage::MousePress { button, x, y } => {
-                let button_enum = MouseButton::from_string(&button);
-                let position = Vector2d::new(x, y);
-                self.mouse_button_states.insert(button_enum.clone(), true);
-                self.mouse_position = position;
-                InputEvent::MousePress { button: button_enum, position }
+            InputEvent::MousePress { button, position } => {
+                self.mouse_button_states.insert(button.clone(), true);
+                self.mouse_position = *position;
             }
-            InputMessage::MouseRelease { button, x, y } => {
-                let button_enum = MouseButton::from_string(&button);
-                let position = Vector2d::new(x, y);
-                self.mouse_button_states.insert(button_enum.clone(), false);
-                self.mouse_position = position;
-                InputEvent::MouseRelease { button: button_enum, position }
+            InputEvent::MouseRelease { button, position } => {
+                self.mouse_button_states.insert(button.clone(), false);
+                self.mouse_position = *position;
             }
-            InputMessage::MouseMove { x, y, delta_x, delta_y } => {
-                let position = Vector2d::new(x, y);
-                let delta = Vector2d::new(delta_x, delta_y);
-                self.mouse_position = position;
-                InputEvent::MouseMove { position, delta }
+            InputEvent::MouseMove { position, .. } | InputEvent::MouseWheel { position, .. } => {
+                self.mouse_position = *position;
             }
-            InputMessage::MouseWheel { delta, x, y } => {
-                let position = Vector2d::new(x, y);
-                self.mouse_position = position;
-                InputEvent::MouseWheel { delta, position }
-            }
-        };
+            _ => {}
+        }
         
         self.event_buffer.push(event);
         Ok(())
@@ -385,4 +420,25 @@ mod tests {
         device.simulate_mouse_release(MouseButton::Left, pos2);
         assert!(!device.is_mouse_button_pressed(&MouseButton::Left));
     }
+    
+    #[test]
+    fn test_text_input_message() {
+        let message: InputMessage = serde_json::from_str(r#"{"TextInput": {"text": "Nové"}}"#).unwrap();
+        assert_eq!(message.to_event(), InputEvent::TextInput { text: "Nové".to_string() });
+        
+        let message: InputMessage = serde_json::from_str(r#"{"KeyPress": {"key": "Backquote"}}"#).unwrap();
+        assert_eq!(message.to_event(), InputEvent::KeyPress { key: Key::Backquote });
+    }
+    
+    #[test]
+    fn test_browser_messages() {
+        let message: InputMessage = serde_json::from_str(r#"{"Resize": {"width": 800, "height": 600, "device_pixel_ratio": 2.0}}"#).unwrap();
+        assert_eq!(message.to_event(), InputEvent::WindowResize { width: 800, height: 600, device_pixel_ratio: 2.0 });
+        
+        let message: InputMessage = serde_json::from_str(r#"{"VisibilityChange": {"visible": false}}"#).unwrap();
+        assert_eq!(message.to_event(), InputEvent::VisibilityChange { visible: false });
+        
+        let message: InputMessage = serde_json::from_str(r#"{"Paste": {"text": "money 10"}}"#).unwrap();
+        assert_eq!(message.to_event(), InputEvent::ClipboardPaste { text: "money 10".to_string() });
+    }
 }
\ No newline at end of file
@@ -0,0 +1,211 @@
+/// Input latency across the web pipeline: each input batch is timed from the client's timestamp through the
+/// server receiving it and the tick applying it to the response frame being dispatched, and the recent samples
+/// of every stage are summarized as percentiles for /metrics, `/debug/latency` and the client's debug panel
+/// The network stage compares the client's clock with the server's, so it's only exact when both run on one machine
+use crate::metrics::Metrics;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Samples kept per stage
+pub const LATENCY_SAMPLES: usize = 512;
+
+/// Latest client timestamp accepted, 2100-01-01 in milliseconds since the Unix epoch
+pub const MAX_CLIENT_TIME_MILLIS: f64 = 4_102_444_800_000.0;
+
+/// How long the percentiles sent with input responses are reused before they're worked out again
+pub const REPORT_REFRESH: Duration = Duration::from_secs(1);
+
+/// A leg of an input batch's trip through the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum LatencyStage {
+    /// Client timestamp to the server receiving the request
+    Network,
+    /// Request received to the tick that applied the input finishing
+    Apply,
+    /// Tick finished to the response frame being handed back for sending
+    Dispatch,
+    /// Client timestamp, or the server receiving the request when there's none, to dispatch
+    Total,
+}
+
+impl LatencyStage {
+    pub const ALL: [LatencyStage; 4] = [LatencyStage::Network, LatencyStage::Apply, LatencyStage::Dispatch, LatencyStage::Total];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LatencyStage::Network => "network",
+            LatencyStage::Apply => "apply",
+            LatencyStage::Dispatch => "dispatch",
+            LatencyStage::Total => "total",
+        }
+    }
+}
+
+/// When one input batch reached each point of the pipeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputTiming {
+    pub client_sent: Option<SystemTime>,
+    pub received: SystemTime,
+    pub applied: SystemTime,
+    pub dispatched: SystemTime,
+}
+
+impl InputTiming {
+    /// A client timestamp in milliseconds since the Unix epoch, as `Date.now()` gives it
+    /// Timestamps that aren't numbers, are before the epoch or are past `MAX_CLIENT_TIME_MILLIS` are rejected
+    pub fn client_time(millis: f64) -> Option<SystemTime> {
+        if !(millis > 0.0 && millis <= MAX_CLIENT_TIME_MILLIS) {
+            return None;
+        }
+        UNIX_EPOCH.checked_add(Duration::try_from_secs_f64(millis / 1000.0).ok()?)
+    }
+
+    /// The time spent in each stage; a client clock running ahead of the server's counts as no network time
+    pub fn stages(&self) -> Vec<(LatencyStage, Duration)> {
+        let between = |from: SystemTime, to: SystemTime| to.duration_since(from).unwrap_or_default();
+        let mut stages = Vec::new();
+        if let Some(sent) = self.client_sent {
+            stages.push((LatencyStage::Network, between(sent, self.received)));
+        }
+        stages.push((LatencyStage::Apply, between(self.received, self.applied)));
+        stages.push((LatencyStage::Dispatch, between(self.applied, self.dispatched)));
+        let start = self.client_sent.map_or(self.received, |sent| sent.min(self.received));
+        stages.push((LatencyStage::Total, between(start, self.dispatched)));
+        stages
+    }
+}
+
+/// Percentiles of a stage's recent samples, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyPercentiles {
+    pub samples: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// The recent samples of every stage
+#[derive(Debug, Clone)]
+pub struct InputLatencyTracker {
+    samples: BTreeMap<LatencyStage, VecDeque<Duration>>,
+    capacity: usize,
+    // The last report and when it was worked out, for `recent_report`
+    cached_report: Option<(Instant, BTreeMap<&'static str, LatencyPercentiles>)>,
+}
+
+impl InputLatencyTracker {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: BTreeMap::new(), capacity: capacity.max(1), cached_report: None }
+    }
+
+    pub fn record(&mut self, timing: &InputTiming) {
+        for (stage, duration) in timing.stages() {
+            let samples = self.samples.entry(stage).or_default();
+            if samples.len() == self.capacity {
+                samples.pop_front();
+            }
+            samples.push_back(duration);
+        }
+    }
+
+    /// Nearest-rank percentiles of a stage, or none before its first sample
+    pub fn percentiles(&self, stage: LatencyStage) -> Option<LatencyPercentiles> {
+        let mut sorted: Vec<Duration> = self.samples.get(&stage)?.iter().copied().collect();
+        sorted.sort();
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let rank = |percent: usize| millis(sorted[((sorted.len() * percent).div_ceil(100)).clamp(1, sorted.len()) - 1]);
+        Some(LatencyPercentiles {
+            samples: sorted.len(),
+            p50_ms: rank(50),
+            p95_ms: rank(95),
+            p99_ms: rank(99),
+            max_ms: millis(*sorted.last()?),
+        })
+    }
+
+    /// Percentiles by stage name, for JSON
+    pub fn report(&self) -> BTreeMap<&'static str, LatencyPercentiles> {
+        LatencyStage::ALL.iter()
+            .filter_map(|stage| Some((stage.name(), self.percentiles(*stage)?)))
+            .collect()
+    }
+
+    /// The report as of at most `REPORT_REFRESH` ago, so answering every input batch doesn't sort every sample
+    pub fn recent_report(&mut self) -> BTreeMap<&'static str, LatencyPercentiles> {
+        match &self.cached_report {
+            Some((taken, report)) if taken.elapsed() < REPORT_REFRESH => report.clone(),
+            _ => {
+                let report = self.report();
+                self.cached_report = Some((Instant::now(), report.clone()));
+                report
+            }
+        }
+    }
+
+    /// Publish the percentiles as `citybuilder_input_latency_seconds{stage, quantile}` gauges
+    pub fn export(&self, metrics: &mut Metrics) {
+        for stage in LatencyStage::ALL {
+            let Some(percentiles) = self.percentiles(stage) else { continue };
+            for (quantile, millis) in [("0.5", percentiles.p50_ms), ("0.95", percentiles.p95_ms), ("0.99", percentiles.p99_ms)] {
+                metrics.set(
+                    "citybuilder_input_latency_seconds",
+                    "Input latency over the recent input batches by pipeline stage",
+                    &[("stage", stage.name()), ("quantile", quantile)],
+                    millis / 1000.0,
+                );
+            }
+        }
+    }
+}
+
+impl Default for InputLatencyTracker {
+    fn default() -> Self {
+        Self::new(LATENCY_SAMPLES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stages_and_percentiles() {
+        let mut tracker = InputLatencyTracker::new(100);
+        let received = UNIX_EPOCH + Duration::from_secs(1_000);
+        for millis in 1..=100 {
+            tracker.record(&InputTiming {
+                client_sent: InputTiming::client_time(1_000_000.0 - 5.0),
+                received,
+                applied: received + Duration::from_millis(millis),
+                dispatched: received + Duration::from_millis(millis + 2),
+            });
+        }
+
+        let apply = tracker.percentiles(LatencyStage::Apply).unwrap();
+        assert_eq!((apply.samples, apply.p50_ms, apply.p95_ms, apply.p99_ms, apply.max_ms), (100, 50.0, 95.0, 99.0, 100.0));
+        assert!((tracker.percentiles(LatencyStage::Network).unwrap().p50_ms - 5.0).abs() < 0.01);
+        assert!((tracker.percentiles(LatencyStage::Total).unwrap().max_ms - 107.0).abs() < 0.01);
+        assert_eq!(tracker.report().len(), 4);
+
+        // A client clock ahead of the server's doesn't make time negative
+        let ahead = InputTiming { client_sent: Some(received + Duration::from_secs(1)), received, applied: received, dispatched: received };
+        assert_eq!(ahead.stages()[0], (LatencyStage::Network, Duration::ZERO));
+        assert_eq!(InputTiming::client_time(f64::NAN), None);
+        assert_eq!(InputTiming::client_time(1e300), None);
+        assert_eq!(InputTiming::client_time(MAX_CLIENT_TIME_MILLIS + 1.0), None);
+
+        // Input responses reuse the report until it's a second old
+        assert_eq!(tracker.recent_report()["apply"].max_ms, 100.0);
+        let slow = received + Duration::from_secs(1);
+        tracker.record(&InputTiming { client_sent: None, received, applied: slow, dispatched: slow });
+        assert_eq!(tracker.recent_report()["apply"].max_ms, 100.0);
+        assert_eq!(tracker.report()["apply"].max_ms, 1000.0);
+
+        let mut metrics = Metrics::new();
+        tracker.export(&mut metrics);
+        assert_eq!(metrics.get("citybuilder_input_latency_seconds", &[("stage", "dispatch"), ("quantile", "0.5")]), Some(0.002));
+    }
+}
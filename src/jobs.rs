@@ -0,0 +1,177 @@
+/// Background worker threads for pure computations submitted by systems
+/// The World itself stays on the main thread; jobs receive owned inputs and return owned results
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Why a job did not produce a result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobError {
+    /// The job panicked while running
+    Panicked,
+    /// The pool shut down before the job finished
+    Disconnected,
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Panicked => write!(f, "job panicked"),
+            JobError::Disconnected => write!(f, "job pool shut down before the job finished"),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+/// Typed handle to the result of a submitted job
+pub struct JobHandle<T> {
+    receiver: Receiver<Result<T, JobError>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Poll for the result without blocking; `None` while the job is still running
+    /// Once a result has been returned, later polls report `Disconnected`
+    pub fn try_take(&self) -> Option<Result<T, JobError>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(JobError::Disconnected)),
+        }
+    }
+
+    /// Block until the job has finished
+    pub fn wait(self) -> Result<T, JobError> {
+        self.receiver.recv().unwrap_or(Err(JobError::Disconnected))
+    }
+}
+
+/// Fixed-size pool of worker threads
+pub struct JobPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl JobPool {
+    /// Create a pool with the given number of worker threads (at least one)
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("job-worker-{}", index))
+                    .spawn(move || loop {
+                        let job = match receiver.lock() {
+                            Ok(receiver) => receiver.recv(),
+                            Err(_) => break,
+                        };
+                        match job {
+                            Ok(job) => job(),
+                            // The pool was dropped
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("Failed to spawn job worker thread")
+            })
+            .collect();
+
+        Self {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Create a pool using all cores except the one running the main loop
+    pub fn with_available_parallelism() -> Self {
+        let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(2);
+        Self::new(cores.saturating_sub(1))
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Run a computation on a worker thread and return a handle to its result
+    pub fn submit<T, F>(&self, job: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (result_sender, receiver) = mpsc::channel();
+        let wrapped: Job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(job)).map_err(|_| JobError::Panicked);
+            // The handle may have been dropped; nobody is waiting for the result then
+            let _ = result_sender.send(result);
+        });
+
+        if let Some(sender) = &self.sender {
+            // Sending only fails when every worker has exited; the handle then reports Disconnected
+            let _ = sender.send(wrapped);
+        }
+        JobHandle { receiver }
+    }
+}
+
+impl Drop for JobPool {
+    fn drop(&mut self) {
+        // Closing the channel lets workers finish queued jobs and exit
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submit_and_wait() {
+        let pool = JobPool::new(2);
+        let handles: Vec<JobHandle<u64>> = (1..=4u64)
+            .map(|n| pool.submit(move || (1..=n).product()))
+            .collect();
+
+        let results: Vec<u64> = handles.into_iter().map(|handle| handle.wait().unwrap()).collect();
+        assert_eq!(results, vec![1, 2, 6, 24]);
+    }
+
+    #[test]
+    fn test_try_take_polls_until_ready() {
+        let pool = JobPool::new(1);
+        let (release, gate) = mpsc::channel::<()>();
+        let handle = pool.submit(move || {
+            gate.recv().unwrap();
+            "done"
+        });
+
+        assert!(handle.try_take().is_none());
+        release.send(()).unwrap();
+
+        let result = loop {
+            if let Some(result) = handle.try_take() {
+                break result;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(result, Ok("done"));
+    }
+
+    #[test]
+    fn test_panicking_job() {
+        let pool = JobPool::new(1);
+        let handle = pool.submit(|| -> u32 { panic!("job failure") });
+        assert_eq!(handle.wait(), Err(JobError::Panicked));
+
+        // The worker survives the panic
+        assert_eq!(pool.submit(|| 7).wait(), Ok(7));
+    }
+}
@@ -0,0 +1,241 @@
+/// Labor market: finished buildings offer the jobs the building catalog says they need workers for, and
+/// `JobMatchingSystem` gives each unemployed citizen the open job with the shortest commute from home
+use crate::agents::AgentComponent;
+use crate::autotile::AutotileMap;
+use crate::catalog::BuildingCatalog;
+use crate::construction::BuildingComponent;
+use crate::ecs::{Component, Entity, World};
+use crate::grid_game_components::GridPositionComponent;
+use crate::logistics::road_route;
+use crate::stable_id::{EntityRef, EntityRefs};
+use serde::Serialize;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// Longest commute in tiles a citizen accepts
+pub const MAX_COMMUTE: u32 = 20;
+/// Walking counts this many times the tiles, so citizens prefer jobs they can drive to
+pub const WALKING_PENALTY: u32 = 2;
+/// Updates between two rounds of matching
+pub const MATCH_INTERVAL: u32 = 10;
+
+/// Component for a citizen with a job
+#[derive(Debug, Clone, PartialEq)]
+pub struct Employment {
+    pub workplace: EntityRef,
+    /// Tiles from home to work, by road where there is one
+    pub commute: u32,
+}
+
+impl Component for Employment {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+
+    fn remap_entities(&mut self, entity_remap: &HashMap<Entity, Entity>) {
+        self.workplace.remap(entity_remap);
+    }
+}
+
+impl EntityRefs for Employment {
+    fn entity_refs(&self) -> Vec<&EntityRef> {
+        vec![&self.workplace]
+    }
+
+    fn entity_refs_mut(&mut self) -> Vec<&mut EntityRef> {
+        vec![&mut self.workplace]
+    }
+}
+
+/// State of the labor market after a round of matching
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LaborStats {
+    /// Citizens looking for or holding a job
+    pub workers: u32,
+    pub employed: u32,
+    /// Job slots offered by finished buildings
+    pub jobs: u32,
+    /// Share of workers without a job, 0.0 with no workers
+    pub unemployment_rate: f32,
+    /// Mean commute of employed citizens in tiles
+    pub average_commute: f32,
+}
+
+// A building offering jobs, with the slots still open
+struct Workplace {
+    entity: Entity,
+    position: (i32, i32),
+    open: u32,
+}
+
+/// System that matches unemployed citizens to open jobs every few updates
+#[derive(Debug, Clone)]
+pub struct JobMatchingSystem {
+    width: i32,
+    height: i32,
+    ticks: u32,
+    stats: LaborStats,
+}
+
+impl JobMatchingSystem {
+    /// Create a job matching system for a `width` x `height` map
+    pub fn new(width: i32, height: i32) -> Self {
+        Self { width, height, ticks: 0, stats: LaborStats::default() }
+    }
+
+    /// Statistics of the last round of matching
+    pub fn stats(&self) -> &LaborStats {
+        &self.stats
+    }
+
+    /// Advance one tick, matching when `MATCH_INTERVAL` updates have passed
+    /// Returns the citizens hired in this update
+    pub fn update(&mut self, world: &mut World, tiles: &AutotileMap, catalog: &BuildingCatalog) -> Vec<Entity> {
+        self.ticks += 1;
+        if self.ticks < MATCH_INTERVAL {
+            return Vec::new();
+        }
+        self.ticks = 0;
+        self.match_jobs(world, tiles, catalog)
+    }
+
+    /// Let go of citizens whose workplace is gone, then hire unemployed citizens in entity order, each taking the
+    /// open job with the shortest commute within `MAX_COMMUTE`
+    pub fn match_jobs(&mut self, world: &mut World, tiles: &AutotileMap, catalog: &BuildingCatalog) -> Vec<Entity> {
+        let mut workplaces = Self::workplaces(world, catalog);
+        let mut citizens = world.entities_with_components(&[TypeId::of::<AgentComponent>(), TypeId::of::<GridPositionComponent>()]);
+        citizens.sort_unstable();
+
+        let mut unemployed = Vec::new();
+        for &citizen in &citizens {
+            let workplace = world.get_component::<Employment>(citizen).and_then(|employment| employment.workplace.entity());
+            match workplace.and_then(|entity| workplaces.iter_mut().find(|workplace| workplace.entity == entity)) {
+                Some(workplace) if workplace.open > 0 => workplace.open -= 1,
+                // The workplace was demolished or has fewer jobs than workers
+                _ => {
+                    if world.has_component::<Employment>(citizen) {
+                        world.remove_component::<Employment>(citizen);
+                    }
+                    unemployed.push(citizen);
+                }
+            }
+        }
+
+        let mut hired = Vec::new();
+        for citizen in unemployed {
+            let Some(home) = Self::home(world, citizen) else { continue };
+            let best = workplaces.iter_mut()
+                .filter(|workplace| workplace.open > 0)
+                .filter_map(|workplace| {
+                    let commute = self.commute(tiles, home, workplace.position);
+                    (commute <= MAX_COMMUTE).then_some((commute, workplace))
+                })
+                .min_by_key(|(commute, workplace)| (*commute, workplace.entity));
+            let Some((commute, workplace)) = best else { continue };
+            let Some(workplace_ref) = EntityRef::to(world, workplace.entity) else { continue };
+            workplace.open -= 1;
+            if world.add_component(citizen, Employment { workplace: workplace_ref, commute }).is_ok() {
+                hired.push(citizen);
+            }
+        }
+
+        self.stats = Self::measure(world, &citizens, workplaces.iter().map(|workplace| workplace.open).sum());
+        hired
+    }
+
+    /// Tiles between two buildings by road when both have one, otherwise walked and counted `WALKING_PENALTY` times
+    pub fn commute(&self, tiles: &AutotileMap, from: (i32, i32), to: (i32, i32)) -> u32 {
+        match road_route(tiles, from, to, self.width, self.height) {
+            // Plus the step from each building onto the road
+            Some(route) => route.len() as u32 + 1,
+            None => ((from.0 - to.0).unsigned_abs() + (from.1 - to.1).unsigned_abs()) * WALKING_PENALTY,
+        }
+    }
+
+    // Where a citizen commutes from: their home, or the last stop of their routine before one is built
+    fn home(world: &World, citizen: Entity) -> Option<(i32, i32)> {
+        let agent = world.get_component::<AgentComponent>(citizen)?;
+        let house = agent.home.and_then(|home| home.entity())
+            .and_then(|house| world.get_component::<GridPositionComponent>(house).map(|pos| (pos.x, pos.y)));
+        house.or_else(|| agent.destinations.last().copied())
+    }
+
+    // Finished buildings with worker slots in the catalog, in entity order
+    fn workplaces(world: &World, catalog: &BuildingCatalog) -> Vec<Workplace> {
+        let mut entities = world.entities_with_components(&[TypeId::of::<BuildingComponent>(), TypeId::of::<GridPositionComponent>()]);
+        entities.sort_unstable();
+        entities.into_iter()
+            .filter_map(|entity| {
+                let kind = world.get_component::<BuildingComponent>(entity)?.kind;
+                let open = catalog.get(kind).consumes.get("workers").copied().filter(|slots| *slots > 0)?;
+                let pos = world.get_component::<GridPositionComponent>(entity)?;
+                Some(Workplace { entity, position: (pos.x, pos.y), open })
+            })
+            .collect()
+    }
+
+    fn measure(world: &World, citizens: &[Entity], open: u32) -> LaborStats {
+        let commutes: Vec<u32> = citizens.iter()
+            .filter_map(|citizen| world.get_component::<Employment>(*citizen).map(|employment| employment.commute))
+            .collect();
+        let workers = citizens.len() as u32;
+        let employed = commutes.len() as u32;
+        LaborStats {
+            workers,
+            employed,
+            jobs: employed + open,
+            unemployment_rate: if workers > 0 { (workers - employed) as f32 / workers as f32 } else { 0.0 },
+            average_commute: if employed > 0 { commutes.iter().sum::<u32>() as f32 / employed as f32 } else { 0.0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autotile::{AutotileSystem, TileKind};
+    use crate::construction::BuildingKind;
+    use crate::stable_id::StableIdSystem;
+
+    #[test]
+    fn test_citizens_take_the_nearest_open_job_and_lose_it_on_demolition() {
+        let catalog = BuildingCatalog::builtin();
+        let mut world = World::new();
+        let mut tiles = AutotileMap::new(32.0);
+        let shop = world.spawn((GridPositionComponent { x: 2, y: 0 },)).unwrap();
+        BuildingKind::Shop.spawn_final(&mut world, shop).unwrap();
+        let factory = world.spawn((GridPositionComponent { x: 5, y: 3 },)).unwrap();
+        BuildingKind::Factory.spawn_final(&mut world, factory).unwrap();
+        for x in 0..=2 {
+            AutotileSystem::place(&mut world, &mut tiles, TileKind::Road, x, 1).unwrap();
+        }
+        let citizens: Vec<Entity> = (0..5)
+            .map(|_| world.spawn((GridPositionComponent { x: 0, y: 0 }, AgentComponent::new("Citizen", vec![(0, 0)]))).unwrap())
+            .collect();
+        StableIdSystem::update(&mut world, 7, &[TypeId::of::<BuildingComponent>()]);
+
+        let mut labor = JobMatchingSystem::new(10, 8);
+        assert_eq!(labor.match_jobs(&mut world, &tiles, catalog), citizens);
+        // The shop's three jobs are a short drive away; the other two walk to the factory
+        let commutes: Vec<(Option<Entity>, u32)> = citizens.iter()
+            .map(|citizen| world.get_component::<Employment>(*citizen).map(|job| (job.workplace.entity(), job.commute)).unwrap())
+            .collect();
+        assert_eq!(commutes[0], (Some(shop), 4));
+        assert_eq!(commutes[3], (Some(factory), 8 * WALKING_PENALTY));
+        assert_eq!(labor.stats().jobs, 8);
+        assert_eq!(labor.stats().unemployment_rate, 0.0);
+
+        world.destroy_entity(factory);
+        assert!(labor.match_jobs(&mut world, &tiles, catalog).is_empty());
+        assert_eq!((labor.stats().employed, labor.stats().jobs), (3, 3));
+        assert!((labor.stats().unemployment_rate - 0.4).abs() < 1e-6);
+    }
+}
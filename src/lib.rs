@@ -7,4 +7,72 @@ pub mod input;
 pub mod game_components;
 pub mod player_movement_system;
 pub mod game_renderer;
-pub mod web_ecs_game;
\ No newline at end of file
+pub mod web_ecs_game;
+pub mod events;
+pub mod stats;
+pub mod notifications;
+pub mod economy;
+pub mod services;
+pub mod construction;
+pub mod demolition;
+pub mod blueprint;
+pub mod budgeted_system;
+pub mod jobs;
+pub mod console;
+pub mod pathfinding;
+pub mod agents;
+pub mod animation;
+pub mod prefab;
+pub mod app;
+pub mod cli;
+pub mod shutdown;
+pub mod settings;
+pub mod api_middleware;
+pub mod soak;
+pub mod metrics;
+pub mod autotile;
+pub mod selection;
+pub mod tools;
+pub mod action_log;
+pub mod world_view;
+pub mod frame_arena;
+pub mod grid_diff;
+pub mod simulation;
+pub mod render_effect;
+pub mod catalog;
+pub mod content;
+pub mod game_rules;
+pub mod debug_tracker;
+pub mod diffing;
+pub mod recording;
+pub mod audio;
+pub mod music;
+pub mod lifetime;
+pub mod floating_text;
+pub mod stable_id;
+pub mod regions;
+pub mod timeseries;
+pub mod trade;
+pub mod logistics;
+pub mod labor;
+pub mod terrain;
+pub mod signing;
+pub mod crash;
+pub mod watchdog;
+pub mod assets;
+pub mod system_info;
+pub mod event_bridge;
+pub mod config;
+pub mod auth;
+pub mod triggers;
+pub mod avoidance;
+pub mod modifiers;
+pub mod city_events;
+pub mod policies;
+#[cfg(test)]
+pub mod testing;
+#[cfg(test)]
+pub mod golden_frames;
+pub mod chrome_trace;
+pub mod input_latency;
+pub mod service_discovery;
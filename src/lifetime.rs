@@ -0,0 +1,122 @@
+/// Entity lifetimes: a `Lifetime` counts down in ticks or seconds and `LifetimeSystem` despawns the entity when it
+/// runs out, so particles, floating texts and temporary debug markers clean up after themselves
+use crate::ecs::{Component, Entity, World};
+use crate::events::{EventQueue, GameEvent};
+use std::any::{Any, TypeId};
+
+/// How long an entity has left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeToLive {
+    /// Updates, counted whether or not the game runs at a fixed timestep
+    Ticks(u32),
+    Seconds(f32),
+}
+
+/// Component for entities despawned once their time runs out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lifetime {
+    pub remaining: TimeToLive,
+    /// Raised as `GameEvent::LifetimeExpired` when the entity is despawned, for effects that end in something
+    pub on_expire: Option<String>,
+}
+
+impl Lifetime {
+    pub fn ticks(ticks: u32) -> Self {
+        Self { remaining: TimeToLive::Ticks(ticks), on_expire: None }
+    }
+
+    pub fn seconds(seconds: f32) -> Self {
+        Self { remaining: TimeToLive::Seconds(seconds), on_expire: None }
+    }
+
+    /// Raise an event named `event` when the entity expires
+    pub fn on_expire(mut self, event: &str) -> Self {
+        self.on_expire = Some(event.to_string());
+        self
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.remaining {
+            TimeToLive::Ticks(ticks) => ticks == 0,
+            TimeToLive::Seconds(seconds) => seconds <= 0.0,
+        }
+    }
+
+    /// Count down one update
+    pub fn advance(&mut self, delta_seconds: f32) {
+        match &mut self.remaining {
+            TimeToLive::Ticks(ticks) => *ticks = ticks.saturating_sub(1),
+            TimeToLive::Seconds(seconds) => *seconds -= delta_seconds.max(0.0),
+        }
+    }
+}
+
+impl Component for Lifetime {
+    fn validate(&self) -> bool {
+        match self.remaining {
+            TimeToLive::Ticks(_) => true,
+            TimeToLive::Seconds(seconds) => seconds.is_finite(),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// System that counts lifetimes down and despawns the entities whose time is up
+pub struct LifetimeSystem;
+
+impl LifetimeSystem {
+    /// Returns the despawned entities in ID order
+    pub fn update(world: &mut World, events: &mut EventQueue<GameEvent>, delta_seconds: f32) -> Vec<Entity> {
+        let mut expired = Vec::new();
+        for entity in world.entities_with_components(&[TypeId::of::<Lifetime>()]) {
+            let Some(mut lifetime) = world.get_component_mut::<Lifetime>(entity) else { continue };
+            lifetime.advance(delta_seconds);
+            if lifetime.is_expired() {
+                expired.push((entity, lifetime.on_expire.clone()));
+            }
+        }
+        expired.sort_by_key(|(entity, _)| *entity);
+        expired.into_iter()
+            .map(|(entity, event)| {
+                world.destroy_entity(entity);
+                if let Some(event) = event {
+                    events.push(GameEvent::LifetimeExpired { entity, event });
+                }
+                entity
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expired_entities_despawn_and_raise_their_event() {
+        let mut world = World::new();
+        let mut events = EventQueue::new();
+        let particle = world.spawn((Lifetime::ticks(2),)).unwrap();
+        let text = world.spawn((Lifetime::seconds(0.25).on_expire("income_shown"),)).unwrap();
+        let marker = world.spawn((Lifetime::seconds(10.0),)).unwrap();
+
+        assert!(LifetimeSystem::update(&mut world, &mut events, 0.1).is_empty());
+        assert_eq!(LifetimeSystem::update(&mut world, &mut events, 0.1), vec![particle]);
+        assert!(events.is_empty());
+        assert_eq!(LifetimeSystem::update(&mut world, &mut events, 0.1), vec![text]);
+        assert_eq!(events.drain(), vec![GameEvent::LifetimeExpired { entity: text, event: "income_shown".to_string() }]);
+        assert!(!world.has_component::<Lifetime>(particle) && !world.has_component::<Lifetime>(text));
+        assert!(matches!(world.get_component::<Lifetime>(marker).unwrap().remaining, TimeToLive::Seconds(seconds) if (seconds - 9.7).abs() < 1e-4));
+    }
+}
@@ -0,0 +1,443 @@
+/// Production chains: buildings keep their goods in an `Inventory`, turn inputs into outputs once a month per the
+/// building catalog (farm crops into factory food into shop sales), and `LogisticsSystem` sends delivery vans
+/// along the roads to move goods from producers and warehouses to the buildings that need them
+use crate::catalog::{BuildingCatalog, BuildingDefinition};
+use crate::construction::{BuildingComponent, BuildingKind};
+use crate::ecs::{Component, Entity, World};
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::autotile::AutotileMap;
+use crate::avoidance::LocalAvoidance;
+use crate::pathfinding::{PathAgentType, PathSearch, SearchStatus, SharedPathHeatmap};
+use serde::Serialize;
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Catalog resources that are physical goods kept in inventories and delivered by van; the others, like workers
+/// and services, are not moved around
+pub const MATERIALS: [&str; 3] = ["crops", "food", "goods"];
+/// Goods one delivery van carries
+pub const VAN_CAPACITY: u32 = 4;
+/// Months of input a building tries to keep in stock
+pub const STOCK_MONTHS: u32 = 2;
+/// Units a warehouse holds across all goods
+pub const WAREHOUSE_CAPACITY: u32 = 60;
+/// Units any other building holds across all goods
+pub const BUILDING_STORAGE: u32 = 20;
+
+/// Whether a catalog resource is a physical good
+pub fn is_material(resource: &str) -> bool {
+    MATERIALS.contains(&resource)
+}
+
+/// Component for the goods stored in a building
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Inventory {
+    pub stock: BTreeMap<String, u32>,
+    /// Units the building holds across all goods
+    pub capacity: u32,
+    /// Inputs missing at the last monthly production, which leave a shop without sales
+    pub shortages: BTreeSet<String>,
+}
+
+impl Inventory {
+    pub fn new(capacity: u32) -> Self {
+        Self { capacity, ..Self::default() }
+    }
+
+    /// Storage for a finished building, if it stores goods: warehouses, and buildings the built-in catalog
+    /// lists as making or using a good
+    pub fn for_building(kind: BuildingKind) -> Option<Self> {
+        if kind == BuildingKind::Warehouse {
+            return Some(Self::new(WAREHOUSE_CAPACITY));
+        }
+        let definition = BuildingCatalog::builtin().get(kind);
+        let trades_goods = definition.produces.keys().chain(definition.consumes.keys()).any(|resource| is_material(resource));
+        trades_goods.then(|| Self::new(BUILDING_STORAGE))
+    }
+
+    pub fn amount(&self, good: &str) -> u32 {
+        self.stock.get(good).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> u32 {
+        self.stock.values().sum()
+    }
+
+    /// Room left across all goods
+    pub fn free(&self) -> u32 {
+        self.capacity.saturating_sub(self.total())
+    }
+
+    /// Store up to `amount` of a good; returns how much fit
+    pub fn add(&mut self, good: &str, amount: u32) -> u32 {
+        let stored = amount.min(self.free());
+        if stored > 0 {
+            *self.stock.entry(good.to_string()).or_default() += stored;
+        }
+        stored
+    }
+
+    /// Take up to `amount` of a good; returns how much was there
+    pub fn take(&mut self, good: &str, amount: u32) -> u32 {
+        let Some(stock) = self.stock.get_mut(good) else { return 0 };
+        let taken = amount.min(*stock);
+        *stock -= taken;
+        if *stock == 0 {
+            self.stock.remove(good);
+        }
+        taken
+    }
+}
+
+impl Component for Inventory {
+    fn validate(&self) -> bool {
+        self.total() <= self.capacity
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Component for a van carrying goods between two buildings
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryComponent {
+    pub good: String,
+    pub amount: u32,
+    pub from: Entity,
+    pub to: Entity,
+    /// Road tiles still to drive, the next one first
+    pub route: Vec<(i32, i32)>,
+}
+
+impl Component for DeliveryComponent {
+    fn validate(&self) -> bool {
+        self.amount > 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+// A building that stores goods, as seen by the dispatcher
+struct Depot {
+    entity: Entity,
+    kind: BuildingKind,
+    position: (i32, i32),
+}
+
+/// System that runs monthly production and dispatches, drives and unloads delivery vans
+#[derive(Debug, Clone)]
+pub struct LogisticsSystem {
+    width: i32,
+    height: i32,
+    ticks_per_month: u32,
+    ticks: u32,
+    heatmap: Option<SharedPathHeatmap>,
+}
+
+impl LogisticsSystem {
+    /// Create a logistics system for a `width` x `height` map producing every `ticks_per_month` updates
+    pub fn new(width: i32, height: i32, ticks_per_month: u32) -> Self {
+        Self { width, height, ticks_per_month: ticks_per_month.max(1), ticks: 0, heatmap: None }
+    }
+
+    /// Record the route search of each dispatched van in a pathfinding heatmap as a truck search
+    pub fn with_heatmap(mut self, heatmap: SharedPathHeatmap) -> Self {
+        self.heatmap = Some(heatmap);
+        self
+    }
+
+    /// Advance one tick: drive the vans, produce when a month has passed, then send vans where goods are needed
+    /// Vans only drive onto tiles `avoidance` grants them; returns the vans dispatched
+    pub fn update(&mut self, world: &mut World, tiles: &AutotileMap, catalog: &BuildingCatalog, avoidance: &mut LocalAvoidance) -> Vec<Entity> {
+        Self::drive(world, avoidance);
+        self.ticks += 1;
+        if self.ticks >= self.ticks_per_month {
+            self.ticks = 0;
+            Self::produce(world, catalog);
+        }
+        self.dispatch(world, tiles, catalog)
+    }
+
+    /// Share of the buildings using goods that had all their inputs last month, 1.0 when none use any
+    pub fn supply_ratio(world: &World, catalog: &BuildingCatalog) -> f32 {
+        let (mut consumers, mut supplied) = (0, 0);
+        for depot in Self::depots(world) {
+            if !catalog.get(depot.kind).consumes.keys().any(|resource| is_material(resource)) {
+                continue;
+            }
+            consumers += 1;
+            if world.get_component::<Inventory>(depot.entity).is_some_and(|inventory| inventory.shortages.is_empty()) {
+                supplied += 1;
+            }
+        }
+        if consumers == 0 { 1.0 } else { supplied as f32 / consumers as f32 }
+    }
+
+    /// Turn each building's inputs into its outputs, recording the inputs it lacked
+    pub fn produce(world: &mut World, catalog: &BuildingCatalog) {
+        for depot in Self::depots(world) {
+            let definition = catalog.get(depot.kind);
+            let Some(mut inventory) = world.get_component_mut::<Inventory>(depot.entity) else { continue };
+            let inputs: Vec<(&String, &u32)> = definition.consumes.iter().filter(|(resource, _)| is_material(resource)).collect();
+            inventory.shortages = inputs.iter()
+                .filter(|(good, amount)| inventory.amount(good) < **amount)
+                .map(|(good, _)| good.to_string())
+                .collect();
+            if !inventory.shortages.is_empty() {
+                continue;
+            }
+            for (good, amount) in inputs {
+                inventory.take(good, *amount);
+            }
+            for (good, amount) in definition.produces.iter().filter(|(resource, _)| is_material(resource)) {
+                inventory.add(good, *amount);
+            }
+        }
+    }
+
+    // Move every van one road tile, unloading the ones that have arrived
+    fn drive(world: &mut World, avoidance: &mut LocalAvoidance) {
+        for van in world.entities_with_components(&[TypeId::of::<DeliveryComponent>(), TypeId::of::<GridPositionComponent>()]) {
+            let next = world.get_component::<DeliveryComponent>(van).map(|delivery| (delivery.route.first().copied(), delivery.route.len() == 1));
+            let from = world.get_component::<GridPositionComponent>(van).map(|pos| (pos.x, pos.y));
+            let (Some(next), Some(from)) = (next, from) else { continue };
+            match next {
+                (Some((x, y)), arriving) => {
+                    if !avoidance.request(van, from, (x, y), arriving) {
+                        continue;
+                    }
+                    if let Some(mut delivery) = world.get_component_mut::<DeliveryComponent>(van) {
+                        delivery.route.remove(0);
+                    }
+                    if let Some(mut pos) = world.get_component_mut::<GridPositionComponent>(van) {
+                        pos.x = x;
+                        pos.y = y;
+                    }
+                }
+                (None, _) => {
+                    // Goods for a building demolished on the way are lost
+                    let delivery = world.get_component::<DeliveryComponent>(van).map(|delivery| delivery.clone());
+                    if let Some(delivery) = delivery {
+                        if let Some(mut inventory) = world.get_component_mut::<Inventory>(delivery.to) {
+                            inventory.add(&delivery.good, delivery.amount);
+                        }
+                    }
+                    world.destroy_entity(van);
+                }
+            }
+        }
+    }
+
+    // Send vans to buildings short of an input, then move producers' surplus into warehouses
+    fn dispatch(&self, world: &mut World, tiles: &AutotileMap, catalog: &BuildingCatalog) -> Vec<Entity> {
+        let depots = Self::depots(world);
+        let mut incoming: BTreeMap<(Entity, String), u32> = BTreeMap::new();
+        for van in world.entities_with_components(&[TypeId::of::<DeliveryComponent>()]) {
+            if let Some(delivery) = world.get_component::<DeliveryComponent>(van) {
+                *incoming.entry((delivery.to, delivery.good.clone())).or_default() += delivery.amount;
+            }
+        }
+
+        let mut vans = Vec::new();
+        for consumer in &depots {
+            let definition = catalog.get(consumer.kind);
+            for (good, monthly) in definition.consumes.iter().filter(|(resource, _)| is_material(resource)) {
+                let (stored, free) = world.get_component::<Inventory>(consumer.entity)
+                    .map_or((0, 0), |inventory| (inventory.amount(good), inventory.free()));
+                let expected = stored + incoming.get(&(consumer.entity, good.clone())).copied().unwrap_or(0);
+                let wanted = (monthly * STOCK_MONTHS).saturating_sub(expected).min(free).min(VAN_CAPACITY);
+                if wanted == 0 {
+                    continue;
+                }
+                let sources = depots.iter().filter(|source| {
+                    source.entity != consumer.entity && (source.kind == BuildingKind::Warehouse || Self::makes(catalog.get(source.kind), good))
+                });
+                if let Some((van, loaded)) = self.send(world, tiles, sources, consumer, good, wanted) {
+                    *incoming.entry((consumer.entity, good.clone())).or_default() += loaded;
+                    vans.push(van);
+                }
+            }
+        }
+
+        for producer in depots.iter().filter(|depot| depot.kind != BuildingKind::Warehouse) {
+            for good in catalog.get(producer.kind).produces.keys().filter(|resource| is_material(resource)) {
+                if world.get_component::<Inventory>(producer.entity).is_none_or(|inventory| inventory.amount(good) < VAN_CAPACITY) {
+                    continue;
+                }
+                let warehouses: Vec<&Depot> = depots.iter()
+                    .filter(|depot| depot.kind == BuildingKind::Warehouse)
+                    .filter(|depot| {
+                        let free = world.get_component::<Inventory>(depot.entity).map_or(0, |inventory| inventory.free());
+                        let booked: u32 = incoming.iter().filter(|((to, _), _)| *to == depot.entity).map(|(_, amount)| amount).sum();
+                        free >= booked + VAN_CAPACITY
+                    })
+                    .collect();
+                for warehouse in warehouses {
+                    if let Some((van, loaded)) = self.send(world, tiles, std::iter::once(producer), warehouse, good, VAN_CAPACITY) {
+                        *incoming.entry((warehouse.entity, good.clone())).or_default() += loaded;
+                        vans.push(van);
+                        break;
+                    }
+                }
+            }
+        }
+        vans
+    }
+
+    // Load a van at the closest source by road holding the good and send it to `to`; returns the van and its load
+    fn send<'a>(
+        &self,
+        world: &mut World,
+        tiles: &AutotileMap,
+        sources: impl Iterator<Item = &'a Depot>,
+        to: &Depot,
+        good: &str,
+        amount: u32,
+    ) -> Option<(Entity, u32)> {
+        road_access(tiles, to.position)?;
+        // The closest source as (route length, source, search)
+        let mut best: Option<(usize, Entity, PathSearch)> = None;
+        for source in sources {
+            if world.get_component::<Inventory>(source.entity).is_none_or(|inventory| inventory.amount(good) == 0) {
+                continue;
+            }
+            let Some(search) = road_search(tiles, source.position, to.position, self.width, self.height) else { continue };
+            let SearchStatus::Found(route) = search.status() else { continue };
+            let length = route.len();
+            if best.as_ref().is_none_or(|(shortest, _, _)| length < *shortest) {
+                best = Some((length, source.entity, search));
+            }
+        }
+
+        let (_, from, search) = best?;
+        if let Some(Ok(mut heatmap)) = self.heatmap.as_ref().map(|heatmap| heatmap.lock()) {
+            heatmap.record(PathAgentType::Truck, &search);
+        }
+        let SearchStatus::Found(mut route) = search.status().clone() else { return None };
+        let loaded = world.get_component_mut::<Inventory>(from)?.take(good, amount);
+        let start = route.remove(0);
+        let van = world.spawn((
+            GridPositionComponent { x: start.0, y: start.1 },
+            DeliveryComponent { good: good.to_string(), amount: loaded, from, to: to.entity, route },
+            RenderComponent { symbol: 'v', color: "yellow".to_string() },
+        )).ok()?;
+        Some((van, loaded))
+    }
+
+    // Finished buildings with an inventory, in entity order
+    fn depots(world: &World) -> Vec<Depot> {
+        let mut entities = world.entities_with_components(&[
+            TypeId::of::<Inventory>(),
+            TypeId::of::<BuildingComponent>(),
+            TypeId::of::<GridPositionComponent>(),
+        ]);
+        entities.sort_unstable();
+        entities.into_iter()
+            .filter_map(|entity| {
+                let kind = world.get_component::<BuildingComponent>(entity)?.kind;
+                let pos = world.get_component::<GridPositionComponent>(entity)?;
+                Some(Depot { entity, kind, position: (pos.x, pos.y) })
+            })
+            .collect()
+    }
+
+    fn makes(definition: &BuildingDefinition, good: &str) -> bool {
+        definition.produces.contains_key(good)
+    }
+}
+
+/// First road tile next to a building, where its vans load and unload and its commuters arrive
+pub fn road_access(tiles: &AutotileMap, position: (i32, i32)) -> Option<(i32, i32)> {
+    AutotileMap::neighbors(position.0, position.1).into_iter()
+        .map(|(_, x, y)| (x, y))
+        .find(|(x, y)| tiles.get(*x, *y).is_some_and(|(kind, _)| kind.is_road()))
+}
+
+/// Shortest drive over road, bridge and tunnel tiles between the roads next to two buildings, including both ends
+pub fn road_route(tiles: &AutotileMap, from: (i32, i32), to: (i32, i32), width: i32, height: i32) -> Option<Vec<(i32, i32)>> {
+    match road_search(tiles, from, to, width, height)?.status() {
+        SearchStatus::Found(route) => Some(route.clone()),
+        _ => None,
+    }
+}
+
+/// The finished search behind `road_route`; `None` when either building has no road next to it
+pub fn road_search(tiles: &AutotileMap, from: (i32, i32), to: (i32, i32), width: i32, height: i32) -> Option<PathSearch> {
+    let (start, goal) = (road_access(tiles, from)?, road_access(tiles, to)?);
+    let mut search = PathSearch::new(start, goal, width, height);
+    search.step(u32::MAX, |x, y| !tiles.get(x, y).is_some_and(|(kind, _)| kind.is_road()));
+    Some(search)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autotile::{AutotileSystem, TileKind};
+
+    fn build(world: &mut World, kind: BuildingKind, x: i32, y: i32) -> Entity {
+        let entity = world.spawn((GridPositionComponent { x, y },)).unwrap();
+        kind.spawn_final(world, entity).unwrap();
+        entity
+    }
+
+    #[test]
+    fn test_inventory_respects_capacity() {
+        let mut inventory = Inventory::new(5);
+        assert_eq!(inventory.add("food", 3), 3);
+        assert_eq!(inventory.add("crops", 4), 2);
+        assert_eq!(inventory.take("food", 10), 3);
+        assert_eq!((inventory.amount("food"), inventory.total(), inventory.free()), (0, 2, 3));
+        assert!(Inventory::for_building(BuildingKind::House).is_none());
+        assert_eq!(Inventory::for_building(BuildingKind::Warehouse).unwrap().capacity, WAREHOUSE_CAPACITY);
+    }
+
+    #[test]
+    fn test_crops_travel_by_road_from_farm_to_food_factory() {
+        let catalog = BuildingCatalog::builtin();
+        let mut world = World::new();
+        let mut tiles = AutotileMap::new(32.0);
+        let farm = build(&mut world, BuildingKind::Farm, 0, 0);
+        let food_factory = build(&mut world, BuildingKind::FoodFactory, 4, 0);
+        let isolated_shop = build(&mut world, BuildingKind::Shop, 8, 6);
+        for x in 0..=4 {
+            AutotileSystem::place(&mut world, &mut tiles, TileKind::Road, x, 1).unwrap();
+        }
+
+        // The first harvest goes out straight away, while the factory and the shop go without inputs
+        let mut logistics = LogisticsSystem::new(10, 8, 1);
+        let vans = logistics.update(&mut world, &tiles, catalog, &mut LocalAvoidance::default());
+        assert!(world.get_component::<Inventory>(food_factory).unwrap().shortages.contains("crops"));
+        assert!(world.get_component::<Inventory>(isolated_shop).unwrap().shortages.contains("food"));
+        assert_eq!(vans.len(), 1);
+        let delivery = world.get_component::<DeliveryComponent>(vans[0]).unwrap().clone();
+        assert_eq!((delivery.good.as_str(), delivery.amount, delivery.from, delivery.to), ("crops", VAN_CAPACITY, farm, food_factory));
+
+        for _ in 0..delivery.route.len() + 1 {
+            logistics.update(&mut world, &tiles, catalog, &mut LocalAvoidance::default());
+        }
+        assert!(!world.has_component::<DeliveryComponent>(vans[0]));
+        assert!(world.get_component::<Inventory>(food_factory).unwrap().amount("crops") >= VAN_CAPACITY);
+        // The shop has no road, so no van can reach it
+        assert_eq!(world.get_component::<Inventory>(isolated_shop).unwrap().total(), 0);
+        assert!(LogisticsSystem::supply_ratio(&world, catalog) < 1.0);
+    }
+}
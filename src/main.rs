@@ -1,42 +1,194 @@
-mod ecs;
 mod http_server;
 mod enhanced_http_server;
-mod core;
-mod rendering;
-mod input;
 
-use http_server::start_hello_world_server;
+use http_server::{start_hello_world_server, HELLO_SERVER_PORT};
 use enhanced_http_server::demonstrate_rendering_with_web_client;
-use rendering::{WebServiceManager, WebClientRenderingDevice, initialize_global_rendering_manager, render_global_grid};
-use input::{initialize_global_input_manager, add_global_input_device, WebClientInputDevice};
-use rust_citybuilder_game::web_ecs_game::demonstrate_web_ecs_game;
+use rust_citybuilder_game::rendering::{WebServiceManager, WebClientRenderingDevice, initialize_global_rendering_manager, render_global_grid, shutdown_global_rendering_manager};
+// Input devices live in the library so the game's InputSystem drains the same global manager
+use rust_citybuilder_game::input::{initialize_global_input_manager, add_global_input_device, shutdown_global_input_manager, WebClientInputDevice};
+use rust_citybuilder_game::app::App;
+use rust_citybuilder_game::action_log::ActionLog;
+use rust_citybuilder_game::cli::Command;
+use rust_citybuilder_game::config::{GameConfig, GAME_CONFIG_FILE};
+use rust_citybuilder_game::content::{Content, BASE_CONTENT_DIRECTORY, MODS_DIRECTORY};
+use rust_citybuilder_game::crash::{self, CrashBundle, ReplayOutcome};
+use rust_citybuilder_game::recording;
+use rust_citybuilder_game::service_discovery::{self, PortsConfig, INPUT_SERVICE, RENDERING_SERVICE};
+use rust_citybuilder_game::shutdown::ShutdownController;
+use rust_citybuilder_game::simulation::{write_csv, Scenario};
+use rust_citybuilder_game::soak::SoakTest;
 use std::env;
+use std::fs::File;
+use std::path::Path;
 
 fn main() {
     println!("Welcome to Rust Citybuilder Game!");
+    // Guarded simulation updates write a crash bundle with the panic's message and location
+    crash::install_panic_hook();
     
-    // Initialize the global rendering manager at program start
-    let web_service = WebServiceManager::new("localhost:8081");
+    let command = Command::parse_from(env::args()).unwrap_or_else(|e| e.exit());
+    
+    let config = GameConfig::load_or_default(Path::new(GAME_CONFIG_FILE)).unwrap_or_else(|e| {
+        eprintln!("⚠️ Warning: Failed to read {}, using the default configuration: {}", GAME_CONFIG_FILE, e);
+        GameConfig::default()
+    });
+    
+    // Ctrl+C and SIGTERM end the server loops; devices are then shut down in reverse start order
+    // Other commands keep the default handling, so Ctrl+C still stops a long bench or replay
+    let mut shutdown = ShutdownController::new();
+    if command.serves() {
+        shutdown.install_signal_handlers();
+    }
+    if command.uses_web_devices() {
+        initialize_web_devices(&config.ports);
+        shutdown.on_shutdown("rendering", shutdown_global_rendering_manager);
+        shutdown.on_shutdown("input", shutdown_global_input_manager);
+    }
+    
+    let result = match command {
+        // `--headless` only decides whether the web devices above are started
+        Command::Serve { address, port, mode, .. } => {
+            println!("Starting Web ECS Game Demo...\n");
+            let address = address.unwrap_or_else(|| config.ports.address(port.unwrap_or(config.ports.game)));
+            App::new().address(&address).mode(mode).shutdown(shutdown.token()).serve()
+        }
+        Command::Bench { ticks } => App::new().bench(ticks).map(|report| {
+            println!("{} ticks, {} entities: {:?} total, {:?} per tick", report.ticks, report.entities, report.elapsed, report.per_tick());
+        }),
+        Command::Stress { citizens, ticks } => App::new().stress(citizens, ticks).map(|report| {
+            println!("{} ticks, {} entities: {:?} total, {:?} per tick", report.ticks, report.entities, report.elapsed, report.per_tick());
+        }),
+        Command::Soak { seed, ticks } => SoakTest::new(seed).ticks(ticks).run().and_then(|report| match report.divergence {
+            None => {
+                println!("Seed {}: {} ticks identical, final state hash {:016x}", seed, report.ticks, report.final_hash);
+                Ok(())
+            }
+            Some(divergence) => Err(format!(
+                "Seed {}: runs diverged at tick {} after system '{}' ({:016x} != {:016x})",
+                seed, divergence.tick, divergence.system, divergence.first_hash, divergence.second_hash
+            )),
+        }),
+        Command::Simulate { scenario, years, csv } => run_simulation(scenario.as_deref(), years, csv.as_deref()),
+        Command::Native => Err("No native rendering device is available in this build".to_string()),
+        Command::ConvertRecording { input, output } => recording::convert(&input, &output).map(|(from, to)| {
+            println!("Converted {} ({} bytes) to {} ({} bytes)", input.display(), from, output.display(), to);
+        }),
+        Command::Replay { path } if crash::is_bundle(&path) => replay_crash(&path),
+        Command::Scenario { path } => Scenario::load(&path)
+            .map_err(|e| format!("Cannot load scenario {}: {}", path.display(), e))
+            .and_then(|scenario| {
+                println!("Starting scenario {}...\n", path.display());
+                App::new().address(&config.ports.address(config.ports.game)).scenario(scenario).shutdown(shutdown.token()).serve()
+            }),
+        Command::Replay { path } => replay_log(&path),
+        Command::HelloServer { address } => {
+            println!("Starting HTTP server...\n");
+            let address = address.unwrap_or_else(|| config.ports.address(HELLO_SERVER_PORT));
+            start_hello_world_server(&address).map_err(|e| format!("Server error: {}", e))
+        }
+        Command::Render => {
+            println!("Demonstrating the Rendering System...\n");
+            demonstrate_rendering_system();
+            Ok(())
+        }
+        Command::WebRender => {
+            println!("Starting Web Rendering Client...\n");
+            demonstrate_rendering_with_web_client(&config.ports);
+            Ok(())
+        }
+    };
+    
+    let errors = shutdown.shutdown();
+    for e in &errors {
+        eprintln!("Shutdown error: {}", e);
+    }
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Run the balance simulation and write its monthly samples to a CSV file, or stdout without one
+fn run_simulation(scenario: Option<&Path>, years: u32, csv: Option<&Path>) -> Result<(), String> {
+    let scenario = match scenario {
+        Some(path) => Scenario::load(path).map_err(|e| format!("Cannot load scenario {}: {}", path.display(), e))?,
+        None => Scenario::default(),
+    };
+    let samples = App::new().simulate(&scenario, years)?;
+    match csv {
+        Some(path) => {
+            let mut file = File::create(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+            write_csv(&samples, &mut file).map_err(|e| e.to_string())?;
+            println!("Simulated {} months into {}", samples.len(), path.display());
+            Ok(())
+        }
+        None => write_csv(&samples, &mut std::io::stdout()).map_err(|e| e.to_string()),
+    }
+}
+
+/// Rebuild a city from an action log and report where it ended up
+fn replay_log(path: &Path) -> Result<(), String> {
+    let log = ActionLog::load(path).map_err(|e| format!("Cannot load action log {}: {}", path.display(), e))?;
+    let report = App::new().replay(&log)?;
+    println!("Replayed {} commands over {} ticks, final state hash {:016x}", report.actions, report.ticks, report.state_hash);
+    Ok(())
+}
+
+/// Replay a crash bundle's session up to the tick it panicked in
+fn replay_crash(path: &Path) -> Result<(), String> {
+    let bundle = CrashBundle::load(path).map_err(|e| format!("Cannot load crash bundle {}: {}", path.display(), e))?;
+    println!("Replaying {} to tick {}: {}", path.display(), bundle.report.tick, bundle.report.message);
+    if bundle.report.actions_dropped > 0 {
+        eprintln!("⚠️ Warning: the bundle lacks the first {} player commands, the replay may differ", bundle.report.actions_dropped);
+    }
+    let content = Content::discover(Path::new(BASE_CONTENT_DIRECTORY), Path::new(MODS_DIRECTORY)).unwrap_or_else(|e| {
+        eprintln!("⚠️ Warning: Failed to load content packs, using the built-in content: {}", e);
+        Content::default()
+    });
+    match crash::replay(&bundle, &content)? {
+        ReplayOutcome::Reproduced { tick, message } => println!("Reproduced the panic at tick {}: {}", tick, message),
+        ReplayOutcome::Survived { tick, diff } => println!(
+            "Reached tick {} without panicking; {} entities spawned, {} despawned and {} components changed from the crashed world",
+            tick, diff.spawned.len(), diff.despawned.len(), diff.changed.len()
+        ),
+        ReplayOutcome::Diverged { tick, changed } => println!(
+            "The replay left the recorded session in tick {}, changing: {:?}", tick, changed
+        ),
+    }
+    Ok(())
+}
+
+/// Start the global rendering manager and input manager backed by web client devices
+/// Each device's server registers the address it ended up on, for the game server's service discovery
+fn initialize_web_devices(ports: &PortsConfig) {
+    let web_service = WebServiceManager::new(&ports.address(ports.rendering));
     let device = Box::new(WebClientRenderingDevice::new(web_service));
+    let rendering_service = device.get_web_service();
     
     if let Err(e) = initialize_global_rendering_manager(device) {
         eprintln!("Warning: Failed to initialize global rendering manager: {}", e);
     } else {
         println!("Global rendering manager initialized successfully");
     }
+    if let Some(address) = rendering_service.lock().ok().and_then(|service| service.bound_address()) {
+        service_discovery::register_service(RENDERING_SERVICE, address);
+    }
     
-    // Initialize the global input manager
     match initialize_global_input_manager() {
         Ok(_) => {
             println!("Global input manager initialized successfully");
             
             // Add a web client input device for testing
-            let input_web_service = WebServiceManager::new("localhost:8086");
+            let input_web_service = WebServiceManager::new(&ports.address(ports.input));
             let input_device = Box::new(WebClientInputDevice::new(input_web_service, 1000));
+            let input_service = input_device.get_web_service();
             
             match add_global_input_device(input_device) {
                 Ok(device_id) => {
                     println!("Web client input device added with ID: {}", device_id);
+                    if let Some(address) = input_service.lock().ok().and_then(|service| service.bound_address()) {
+                        service_discovery::register_service(INPUT_SERVICE, address);
+                    }
                 }
                 Err(e) => {
                     eprintln!("Warning: Failed to add web client input device: {}", e);
@@ -47,71 +199,6 @@ fn main() {
             eprintln!("Warning: Failed to initialize global input manager: {}", e);
         }
     }
-    
-    // Check command line arguments to determine what to run
-    let args: Vec<String> = env::args().collect();
-    
-    if args.len() > 1 {
-        match args[1].as_str() {
-            "render" => {
-                println!("Demonstrating the Rendering System...\n");
-                demonstrate_rendering_system();
-            }
-            "web-render" => {
-                println!("Starting Web Rendering Client...\n");
-                demonstrate_rendering_with_web_client();
-            }
-            "ecs-game" => {
-                println!("Starting Web ECS Game Demo...\n");
-                demonstrate_web_ecs_game();
-            }
-            "server" => {
-                println!("Starting HTTP server...\n");
-                let address = args.get(2).map(|s| s.as_str()).unwrap_or("localhost:8080");
-                
-                if let Err(e) = start_hello_world_server(address) {
-                    eprintln!("Server error: {}", e);
-                    std::process::exit(1);
-                }
-            }
-            "help" | "--help" | "-h" => {
-                print_help();
-            }
-            _ => {
-                println!("Unknown command: {}", args[1]);
-                print_help();
-            }
-        }
-    } else {
-        // Default behavior: start the ECS game
-        println!("Starting Web ECS Game (default mode)...\n");
-        println!("Use 'cargo run help' to see available commands.\n");
-        
-        demonstrate_web_ecs_game();
-    }
-}
-
-fn print_help() {
-    println!("Rust City Builder Game");
-    println!("");
-    println!("USAGE:");
-    println!("    cargo run [COMMAND] [OPTIONS]");
-    println!("");
-    println!("COMMANDS:");
-    println!("    server [ADDRESS]    Start HTTP server (default: localhost:8080)");
-    println!("    render              Demonstrate Rendering System with Web Client");
-    println!("    web-render          Start Interactive Web Rendering Client");
-    println!("    ecs-game            Start Web ECS Game Demo (default)");
-    println!("    help                Show this help message");
-    println!("");
-    println!("EXAMPLES:");
-    println!("    cargo run                    # Start Web ECS game (default)");
-    println!("    cargo run ecs-game           # Start Web ECS game explicitly");
-    println!("    cargo run server             # Start HTTP server on localhost:8080");
-    println!("    cargo run server 0.0.0.0:3000  # Start HTTP server on all interfaces, port 3000");
-    println!("    cargo run render             # Run rendering system demonstration");
-    println!("    cargo run web-render         # Start interactive web rendering client");
-    println!("");
 }
 
 fn demonstrate_rendering_system() {
@@ -134,7 +221,10 @@ fn demonstrate_rendering_system() {
     thread::sleep(Duration::from_millis(500));
     
     println!("\n📡 Web Service Information:");
-    println!("   Web client available at: http://localhost:8081");
+    match service_discovery::registered_services().url(RENDERING_SERVICE) {
+        Some(url) => println!("   Web client available at: {}", url),
+        None => println!("   Web client is not available: the rendering service didn't start"),
+    }
     println!("   Open this URL in your browser to see the rendered grid");
     
     println!("\n🔧 Technical Details:");
@@ -0,0 +1,172 @@
+/// Counters and gauges exported in the Prometheus text format, for monitoring long-running servers
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Prometheus metric type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    fn name(self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MetricFamily {
+    kind: MetricKind,
+    help: &'static str,
+    // Rendered label set, e.g. `{method="GET"}`, to value
+    samples: BTreeMap<String, f64>,
+}
+
+/// Named metric families with labelled samples, rendered in a stable order
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    families: BTreeMap<&'static str, MetricFamily>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add to a counter, creating it at zero first
+    pub fn increment(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], by: f64) {
+        *self.sample(name, MetricKind::Counter, help, labels) += by;
+    }
+
+    /// Mirror a counter that is kept elsewhere, e.g. in atomics shared with worker threads
+    pub fn set_counter(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], total: f64) {
+        *self.sample(name, MetricKind::Counter, help, labels) = total;
+    }
+
+    /// Set a gauge to its current value
+    pub fn set(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+        *self.sample(name, MetricKind::Gauge, help, labels) = value;
+    }
+
+    pub fn get(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        self.families.get(name)?.samples.get(&Self::label_set(labels)).copied()
+    }
+
+    /// Every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for (name, family) in &self.families {
+            let _ = writeln!(text, "# HELP {} {}", name, family.help);
+            let _ = writeln!(text, "# TYPE {} {}", name, family.kind.name());
+            for (labels, value) in &family.samples {
+                let _ = writeln!(text, "{}{} {}", name, labels, value);
+            }
+        }
+        text
+    }
+
+    fn sample(&mut self, name: &'static str, kind: MetricKind, help: &'static str, labels: &[(&str, &str)]) -> &mut f64 {
+        let family = self.families.entry(name).or_insert_with(|| MetricFamily { kind, help, samples: BTreeMap::new() });
+        debug_assert_eq!(family.kind, kind, "Metric {} used as both counter and gauge", name);
+        family.samples.entry(Self::label_set(labels)).or_insert(0.0)
+    }
+
+    fn label_set(labels: &[(&str, &str)]) -> String {
+        if labels.is_empty() {
+            return String::new();
+        }
+        let pairs: Vec<String> = labels.iter()
+            .map(|(key, value)| {
+                let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+                format!("{}=\"{}\"", key, value)
+            })
+            .collect();
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+/// Tick timing of the game loop: ticks per second over the last second, tick and per-system durations
+#[derive(Debug, Clone, Default)]
+pub struct TickMetrics {
+    recent_ticks: VecDeque<Instant>,
+}
+
+impl TickMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one update that took `duration`, with the time spent in each system
+    pub fn record(&mut self, metrics: &mut Metrics, now: Instant, duration: Duration, systems: &[(&'static str, Duration)]) {
+        self.recent_ticks.push_back(now);
+        while self.recent_ticks.front().is_some_and(|tick| now.duration_since(*tick) > Duration::from_secs(1)) {
+            self.recent_ticks.pop_front();
+        }
+
+        metrics.increment("citybuilder_ticks_total", "Simulation updates run", &[], 1.0);
+        metrics.increment("citybuilder_tick_seconds_total", "Time spent in simulation updates", &[], duration.as_secs_f64());
+        metrics.set("citybuilder_tick_seconds", "Duration of the latest simulation update", &[], duration.as_secs_f64());
+        metrics.set("citybuilder_fps", "Simulation updates during the last second", &[], self.recent_ticks.len() as f64);
+        for (system, time) in systems {
+            metrics.increment(
+                "citybuilder_system_seconds_total",
+                "Time spent in each system during simulation updates",
+                &[("system", system)],
+                time.as_secs_f64(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_text_format() {
+        let mut metrics = Metrics::new();
+        metrics.increment("http_requests_total", "HTTP requests", &[("method", "GET")], 1.0);
+        metrics.increment("http_requests_total", "HTTP requests", &[("method", "GET")], 2.0);
+        metrics.increment("http_requests_total", "HTTP requests", &[("method", "POST")], 1.0);
+        metrics.set("entities", "Live entities", &[], 16.0);
+        metrics.set("entities", "Live entities", &[], 17.0);
+        metrics.set("odd", "Escaping", &[("label", "say \"hi\"")], 1.5);
+
+        assert_eq!(metrics.get("http_requests_total", &[("method", "GET")]), Some(3.0));
+        assert_eq!(metrics.render(), [
+            "# HELP entities Live entities",
+            "# TYPE entities gauge",
+            "entities 17",
+            "# HELP http_requests_total HTTP requests",
+            "# TYPE http_requests_total counter",
+            "http_requests_total{method=\"GET\"} 3",
+            "http_requests_total{method=\"POST\"} 1",
+            "# HELP odd Escaping",
+            "# TYPE odd gauge",
+            "odd{label=\"say \\\"hi\\\"\"} 1.5",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_tick_metrics() {
+        let mut metrics = Metrics::new();
+        let mut ticks = TickMetrics::new();
+        let start = Instant::now();
+        let systems = [("agents", Duration::from_millis(2)), ("budget", Duration::from_millis(1))];
+        for i in 0..3 {
+            ticks.record(&mut metrics, start + Duration::from_millis(600 * i), Duration::from_millis(3), &systems);
+        }
+
+        assert_eq!(metrics.get("citybuilder_ticks_total", &[]), Some(3.0));
+        // The first tick is more than a second older than the last
+        assert_eq!(metrics.get("citybuilder_fps", &[]), Some(2.0));
+        let agents = metrics.get("citybuilder_system_seconds_total", &[("system", "agents")]).unwrap();
+        assert!((agents - 0.006).abs() < 1e-9);
+    }
+}
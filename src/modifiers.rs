@@ -0,0 +1,208 @@
+/// Temporary percentage modifiers on the city's economy and happiness, stacked from whatever is in effect, e.g.
+/// +10% commercial income for 30 days
+/// Every modifier names its source, so whoever added it can take it away again, and applies to the whole city
+/// or to one building; modifiers on the same target add up, so two +10% income boosts make +20%
+use crate::economy::ZoneType;
+use crate::stable_id::StableId;
+use serde::{Deserialize, Serialize};
+
+/// What a modifier changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ModifierTarget {
+    /// Monthly tax income from one zone type
+    TaxIncome(ZoneType),
+    /// Monthly upkeep of the city services
+    ServiceUpkeep,
+    /// Residents' happiness
+    Happiness,
+}
+
+impl ModifierTarget {
+    /// How the target reads in the UI, e.g. "commercial income"
+    pub fn label(&self) -> String {
+        match self {
+            ModifierTarget::TaxIncome(zone_type) => format!("{} income", format!("{:?}", zone_type).to_lowercase()),
+            ModifierTarget::ServiceUpkeep => "service upkeep".to_string(),
+            ModifierTarget::Happiness => "happiness".to_string(),
+        }
+    }
+}
+
+/// A change to one target as data defines it, e.g. an effect of a city event or a policy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModifierEffect {
+    pub target: ModifierTarget,
+    pub percent: i32,
+}
+
+/// Where a modifier applies; buildings are named by stable ID so their modifiers survive a save and load
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ModifierScope {
+    #[default]
+    City,
+    Building(StableId),
+}
+
+/// A change of `percent` to one target, until its source takes it away or its ticks run out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Modifier {
+    pub source: String,
+    pub target: ModifierTarget,
+    pub percent: i32,
+    #[serde(default)]
+    pub scope: ModifierScope,
+    /// Ticks left before the modifier expires by itself; none lasts until removed
+    #[serde(default)]
+    pub remaining_ticks: Option<u32>,
+}
+
+impl Modifier {
+    /// A city-wide modifier lasting until removed
+    pub fn new(source: &str, target: ModifierTarget, percent: i32) -> Self {
+        Self { source: source.to_string(), target, percent, scope: ModifierScope::City, remaining_ticks: None }
+    }
+
+    pub fn on_building(mut self, id: StableId) -> Self {
+        self.scope = ModifierScope::Building(id);
+        self
+    }
+
+    /// Expire after this many ticks
+    pub fn lasting(mut self, ticks: u32) -> Self {
+        self.remaining_ticks = Some(ticks);
+        self
+    }
+
+    /// Whether the modifier changes `target` for a building, or for the city as a whole when `building` is none
+    fn applies(&self, target: ModifierTarget, building: Option<StableId>) -> bool {
+        self.target == target && match self.scope {
+            ModifierScope::City => true,
+            ModifierScope::Building(id) => building == Some(id),
+        }
+    }
+}
+
+/// The modifiers in effect
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModifierStack {
+    modifiers: Vec<Modifier>,
+}
+
+impl ModifierStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a city-wide modifier lasting until its source removes it
+    pub fn push(&mut self, source: &str, target: ModifierTarget, percent: i32) {
+        self.add(Modifier::new(source, target, percent));
+    }
+
+    /// Add city-wide modifiers for each effect, lasting until their source removes them
+    pub fn push_effects(&mut self, source: &str, effects: &[ModifierEffect]) {
+        for effect in effects {
+            self.push(source, effect.target, effect.percent);
+        }
+    }
+
+    pub fn add(&mut self, modifier: Modifier) {
+        self.modifiers.push(modifier);
+    }
+
+    /// Take away every modifier of a source, returning how many there were
+    pub fn remove_source(&mut self, source: &str) -> usize {
+        let before = self.modifiers.len();
+        self.modifiers.retain(|modifier| modifier.source != source);
+        before - self.modifiers.len()
+    }
+
+    /// Count down the modifiers with a duration, returning those that expired
+    pub fn tick(&mut self) -> Vec<Modifier> {
+        for ticks in self.modifiers.iter_mut().filter_map(|modifier| modifier.remaining_ticks.as_mut()) {
+            *ticks = ticks.saturating_sub(1);
+        }
+        let (expired, kept) = std::mem::take(&mut self.modifiers).into_iter()
+            .partition(|modifier| modifier.remaining_ticks == Some(0));
+        self.modifiers = kept;
+        expired
+    }
+
+    pub fn modifiers(&self) -> &[Modifier] {
+        &self.modifiers
+    }
+
+    /// Modifiers attached to one building, for the inspector
+    pub fn on_building(&self, id: StableId) -> Vec<&Modifier> {
+        self.modifiers.iter().filter(|modifier| modifier.scope == ModifierScope::Building(id)).collect()
+    }
+
+    /// Summed percentage of the city-wide modifiers on a target; never below -100, so nothing turns negative
+    pub fn percent(&self, target: ModifierTarget) -> i32 {
+        self.percent_at(target, None)
+    }
+
+    /// Summed percentage on a target for one building: the city-wide modifiers plus the building's own
+    pub fn percent_at(&self, target: ModifierTarget, building: Option<StableId>) -> i32 {
+        self.modifiers.iter()
+            .filter(|modifier| modifier.applies(target, building))
+            .map(|modifier| modifier.percent)
+            .sum::<i32>()
+            .max(-100)
+    }
+
+    /// An amount of money with the target's city-wide modifiers applied
+    pub fn apply(&self, target: ModifierTarget, amount: i64) -> i64 {
+        self.apply_at(target, None, amount)
+    }
+
+    /// An amount of money one building earns or costs with the target's modifiers applied
+    pub fn apply_at(&self, target: ModifierTarget, building: Option<StableId>, amount: i64) -> i64 {
+        amount * (100 + self.percent_at(target, building)) as i64 / 100
+    }
+
+    /// A ratio such as happiness with the target's modifiers for a building applied, kept within 0.0..=1.0
+    pub fn apply_ratio_at(&self, target: ModifierTarget, building: Option<StableId>, ratio: f32) -> f32 {
+        (ratio * (100 + self.percent_at(target, building)) as f32 / 100.0).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modifiers_stack_per_target_and_leave_with_their_source() {
+        let mut stack = ModifierStack::new();
+        let commercial = ModifierTarget::TaxIncome(ZoneType::Commercial);
+        stack.push("festival", commercial, 20);
+        stack.push("boom", commercial, 10);
+        stack.push("strike", ModifierTarget::Happiness, -150);
+
+        assert_eq!(stack.apply(commercial, 1000), 1300);
+        assert_eq!(stack.apply(ModifierTarget::TaxIncome(ZoneType::Industrial), 1000), 1000);
+        assert_eq!(stack.apply_ratio_at(ModifierTarget::Happiness, None, 0.5), 0.0);
+
+        assert_eq!(stack.remove_source("festival"), 1);
+        assert_eq!(stack.apply(commercial, 1000), 1100);
+    }
+
+    #[test]
+    fn test_building_modifiers_apply_to_their_building_and_expire() {
+        let mut stack = ModifierStack::new();
+        let commercial = ModifierTarget::TaxIncome(ZoneType::Commercial);
+        let (shop, other) = (StableId(1), StableId(2));
+        stack.add(Modifier::new("grand opening", commercial, 50).on_building(shop).lasting(2));
+        stack.push("boom", commercial, 10);
+
+        assert_eq!(stack.apply_at(commercial, Some(shop), 100), 160);
+        assert_eq!(stack.apply_at(commercial, Some(other), 100), 110);
+        assert_eq!(stack.on_building(shop).len(), 1);
+
+        assert!(stack.tick().is_empty());
+        let expired = stack.tick();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].source, "grand opening");
+        assert_eq!(stack.apply_at(commercial, Some(shop), 100), 110);
+        assert_eq!(commercial.label(), "commercial income");
+    }
+}
@@ -0,0 +1,266 @@
+/// Music and ambience: a shuffled playlist per mood that crossfades to a new track when the mood changes or a
+/// track ends, and ambient loops whose volume follows how much of the listener's surroundings they belong to
+use crate::audio::{AudioBus, AudioMixer, SoundSource};
+use crate::autotile::AutotileMap;
+use crate::ecs::World;
+use crate::grid_game_components::{GridPositionComponent, ObstacleComponent};
+use crate::soak::SeededRng;
+use serde::Serialize;
+use std::any::TypeId;
+use std::collections::HashSet;
+
+/// Seconds one track takes to fade into the next
+pub const CROSSFADE_SECONDS: f32 = 3.0;
+/// Tiles around the listener counted for ambience
+pub const AMBIENT_RADIUS: i32 = 4;
+
+/// What the city's situation calls for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MusicMood {
+    Calm,
+    /// The city is in trouble, e.g. the treasury is in debt
+    Crisis,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicTrack {
+    pub sound: String,
+    pub mood: MusicMood,
+    pub seconds: f32,
+}
+
+impl MusicTrack {
+    pub fn new(sound: &str, mood: MusicMood, seconds: f32) -> Self {
+        Self { sound: sound.to_string(), mood, seconds }
+    }
+}
+
+/// Tracks shipped with the game, streamed by clients from `web/audio/<sound>.ogg`
+pub fn default_playlist() -> Vec<MusicTrack> {
+    vec![
+        MusicTrack::new("music_morning", MusicMood::Calm, 150.0),
+        MusicTrack::new("music_streets", MusicMood::Calm, 180.0),
+        MusicTrack::new("music_evening", MusicMood::Calm, 165.0),
+        MusicTrack::new("music_alarm", MusicMood::Crisis, 120.0),
+        MusicTrack::new("music_rebuild", MusicMood::Crisis, 140.0),
+    ]
+}
+
+/// Shares of the tiles around the listener, from 0 to 1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Surroundings {
+    pub roads: f32,
+    /// Tiles with no road, wall or building on them
+    pub open: f32,
+}
+
+impl Surroundings {
+    /// Count the tile layers within `AMBIENT_RADIUS` of a tile, including tiles off the map as open land
+    pub fn around(world: &World, tiles: &AutotileMap, center: (i32, i32)) -> Self {
+        let obstacles: HashSet<(i32, i32)> = world.entities_with_components(&[TypeId::of::<ObstacleComponent>(), TypeId::of::<GridPositionComponent>()])
+            .into_iter()
+            .filter_map(|entity| world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y)))
+            .collect();
+        let (mut roads, mut open, mut total) = (0, 0, 0);
+        for y in center.1 - AMBIENT_RADIUS..=center.1 + AMBIENT_RADIUS {
+            for x in center.0 - AMBIENT_RADIUS..=center.0 + AMBIENT_RADIUS {
+                total += 1;
+                match tiles.get(x, y) {
+                    Some((kind, _)) if kind.is_road() => roads += 1,
+                    None if !obstacles.contains(&(x, y)) => open += 1,
+                    _ => {}
+                }
+            }
+        }
+        Self { roads: roads as f32 / total as f32, open: open as f32 / total as f32 }
+    }
+}
+
+/// An ambient loop and how loud the surroundings make it
+struct AmbientLoop {
+    sound: &'static str,
+    level: fn(&Surroundings) -> f32,
+    voice: Option<u64>,
+    gain: f32,
+}
+
+struct PlayingTrack {
+    track: usize,
+    voice: u64,
+    remaining: f32,
+}
+
+/// Plays the playlist and the ambience through the mixer's music and effects buses
+pub struct MusicSystem {
+    tracks: Vec<MusicTrack>,
+    rng: SeededRng,
+    mood: MusicMood,
+    // Tracks of the mood still to play before the playlist is shuffled again
+    queue: Vec<usize>,
+    current: Option<PlayingTrack>,
+    // Voices fading out, with the seconds until they are stopped
+    fading: Vec<(u64, f32)>,
+    ambience: Vec<AmbientLoop>,
+}
+
+impl MusicSystem {
+    pub fn new(tracks: Vec<MusicTrack>, seed: u64) -> Self {
+        Self {
+            tracks,
+            rng: SeededRng::new(seed),
+            mood: MusicMood::Calm,
+            queue: Vec::new(),
+            current: None,
+            fading: Vec::new(),
+            // Traffic hums near roads, birds sing over open land
+            ambience: vec![
+                AmbientLoop { sound: "ambient_traffic", level: |around| (around.roads * 3.0).min(1.0), voice: None, gain: 0.0 },
+                AmbientLoop { sound: "ambient_birds", level: |around| around.open * around.open, voice: None, gain: 0.0 },
+            ],
+        }
+    }
+
+    pub fn mood(&self) -> MusicMood {
+        self.mood
+    }
+
+    /// Sound of the track playing, if any
+    pub fn current_track(&self) -> Option<&str> {
+        self.current.as_ref().map(|playing| self.tracks[playing.track].sound.as_str())
+    }
+
+    /// Advance the playlist, crossfading on a mood change or a track's end, and follow the surroundings
+    pub fn update(&mut self, mixer: &mut AudioMixer, mood: MusicMood, surroundings: &Surroundings, delta_seconds: f32) {
+        self.fading.retain_mut(|(voice, remaining)| {
+            *remaining -= delta_seconds;
+            if *remaining <= 0.0 {
+                mixer.stop(*voice);
+            }
+            *remaining > 0.0
+        });
+
+        let finished = self.current.as_mut().is_some_and(|playing| {
+            playing.remaining -= delta_seconds;
+            playing.remaining <= CROSSFADE_SECONDS
+        });
+        if mood != self.mood || self.current.is_none() || finished {
+            if mood != self.mood {
+                self.mood = mood;
+                self.queue.clear();
+            }
+            self.next_track(mixer);
+        }
+
+        for ambient in &mut self.ambience {
+            let gain = (ambient.level)(surroundings);
+            match ambient.voice {
+                None if gain > 0.0 => {
+                    ambient.voice = Some(mixer.play(ambient.sound, AudioBus::Sfx, SoundSource::Global, gain, true));
+                    ambient.gain = gain;
+                }
+                Some(voice) if (gain - ambient.gain).abs() >= 0.05 => {
+                    mixer.fade(voice, gain, 1.0);
+                    ambient.gain = gain;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Fade the playing track out and the next one of the mood in
+    fn next_track(&mut self, mixer: &mut AudioMixer) {
+        if self.queue.is_empty() {
+            self.queue = (0..self.tracks.len()).filter(|&track| self.tracks[track].mood == self.mood).collect();
+            // Fisher-Yates, keeping the track just played from coming straight back
+            for index in (1..self.queue.len()).rev() {
+                self.queue.swap(index, self.rng.below(index as i32 + 1) as usize);
+            }
+            let playing = self.current.as_ref().map(|playing| playing.track);
+            let last = self.queue.len().saturating_sub(1);
+            if last > 0 && self.queue.last().copied() == playing {
+                self.queue.swap(0, last);
+            }
+        }
+        if let Some(previous) = self.current.take() {
+            mixer.fade(previous.voice, 0.0, CROSSFADE_SECONDS);
+            self.fading.push((previous.voice, CROSSFADE_SECONDS));
+        }
+        let Some(track) = self.queue.pop() else {
+            return;
+        };
+        let voice = mixer.play(&self.tracks[track].sound, AudioBus::Music, SoundSource::Global, 0.0, true);
+        mixer.fade(voice, 1.0, CROSSFADE_SECONDS);
+        self.current = Some(PlayingTrack { track, voice, remaining: self.tracks[track].seconds });
+    }
+}
+
+impl Default for MusicSystem {
+    fn default() -> Self {
+        Self::new(default_playlist(), 0x5EED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioCommand;
+    use crate::autotile::{AutotileSystem, TileKind};
+
+    #[test]
+    fn test_playlist_crossfades_on_mood_changes_and_track_ends() {
+        let tracks = vec![
+            MusicTrack::new("calm_a", MusicMood::Calm, 10.0),
+            MusicTrack::new("calm_b", MusicMood::Calm, 10.0),
+            MusicTrack::new("crisis", MusicMood::Crisis, 10.0),
+        ];
+        let mut mixer = AudioMixer::default();
+        let mut music = MusicSystem::new(tracks, 3);
+        let quiet = Surroundings::default();
+
+        music.update(&mut mixer, MusicMood::Calm, &quiet, 0.1);
+        let first = music.current_track().unwrap().to_string();
+        assert!(first.starts_with("calm"));
+        // The track is replaced a crossfade before its end, never by itself
+        for _ in 0..75 {
+            music.update(&mut mixer, MusicMood::Calm, &quiet, 0.1);
+        }
+        assert_ne!(music.current_track().unwrap(), first);
+
+        let seen = mixer.since(0).last().unwrap().id;
+        music.update(&mut mixer, MusicMood::Crisis, &quiet, 0.1);
+        assert_eq!(music.current_track(), Some("crisis"));
+        let commands: Vec<AudioCommand> = mixer.since(seen).into_iter().map(|entry| entry.command).collect();
+        assert!(matches!(commands[0], AudioCommand::SetGain { gain, fade_seconds, .. } if gain == 0.0 && fade_seconds == CROSSFADE_SECONDS));
+        assert!(matches!(&commands[1], AudioCommand::Play { sound, gain, .. } if sound == "crisis" && *gain == 0.0));
+
+        // The faded track stops once the crossfade is over
+        for _ in 0..31 {
+            music.update(&mut mixer, MusicMood::Crisis, &quiet, 0.1);
+        }
+        assert!(matches!(mixer.since(seen).last().unwrap().command, AudioCommand::Stop { .. }));
+    }
+
+    #[test]
+    fn test_ambience_follows_roads_and_open_land() {
+        let mut world = World::new();
+        let mut tiles = AutotileMap::new(32.0);
+        for x in 0..9 {
+            AutotileSystem::place(&mut world, &mut tiles, TileKind::Road, x, 4).unwrap();
+        }
+        let surroundings = Surroundings::around(&world, &tiles, (4, 4));
+        assert_eq!(surroundings.roads, 9.0 / 81.0);
+        assert_eq!(surroundings.open, 72.0 / 81.0);
+        assert_eq!(Surroundings::around(&world, &tiles, (4, 20)).open, 1.0);
+
+        let mut mixer = AudioMixer::default();
+        let mut music = MusicSystem::new(Vec::new(), 1);
+        music.update(&mut mixer, MusicMood::Calm, &surroundings, 0.1);
+        let sounds: Vec<String> = mixer.since(0).into_iter()
+            .filter_map(|entry| match entry.command {
+                AudioCommand::Play { sound, .. } => Some(sound),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sounds, vec!["ambient_traffic", "ambient_birds"]);
+    }
+}
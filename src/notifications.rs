@@ -0,0 +1,199 @@
+/// Player-facing notifications raised by simulation systems and delivered to web clients
+use crate::ecs::Entity;
+use crate::events::EventQueue;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How important a notification is, used by the client to style toasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotificationSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// What a notification refers to, so the client can focus the camera on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotificationLink {
+    Entity(Entity),
+    Tile { x: i32, y: i32 },
+}
+
+/// Notification event emitted by systems
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub severity: NotificationSeverity,
+    pub message: String,
+    pub link: Option<NotificationLink>,
+}
+
+impl Notification {
+    /// Create a notification without a link
+    pub fn new(severity: NotificationSeverity, message: &str) -> Self {
+        Self {
+            severity,
+            message: message.to_string(),
+            link: None,
+        }
+    }
+
+    /// Create an informational notification
+    pub fn info(message: &str) -> Self {
+        Self::new(NotificationSeverity::Info, message)
+    }
+
+    /// Create a warning notification
+    pub fn warning(message: &str) -> Self {
+        Self::new(NotificationSeverity::Warning, message)
+    }
+
+    /// Create a critical notification
+    pub fn critical(message: &str) -> Self {
+        Self::new(NotificationSeverity::Critical, message)
+    }
+
+    /// Link the notification to a tile
+    pub fn at_tile(mut self, x: i32, y: i32) -> Self {
+        self.link = Some(NotificationLink::Tile { x, y });
+        self
+    }
+
+    /// Link the notification to an entity
+    pub fn for_entity(mut self, entity: Entity) -> Self {
+        self.link = Some(NotificationLink::Entity(entity));
+        self
+    }
+}
+
+/// A notification stored server-side with a sequence id for client polling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferedNotification {
+    pub id: u64,
+    #[serde(flatten)]
+    pub notification: Notification,
+}
+
+/// Server-side buffer of recent notifications
+/// Clients poll with the last id they have seen and receive only newer entries
+pub struct NotificationBuffer {
+    entries: VecDeque<BufferedNotification>,
+    capacity: usize,
+    next_id: u64,
+}
+
+impl NotificationBuffer {
+    /// Create a buffer keeping at most `capacity` notifications
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_id: 1,
+        }
+    }
+
+    /// Store a notification, evicting the oldest one if the buffer is full
+    pub fn push(&mut self, notification: Notification) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(BufferedNotification { id, notification });
+        id
+    }
+
+    /// Move all pending notification events into the buffer
+    pub fn collect(&mut self, events: &mut EventQueue<Notification>) {
+        for notification in events.drain() {
+            self.push(notification);
+        }
+    }
+
+    /// Get all notifications with an id greater than `last_seen_id`
+    pub fn since(&self, last_seen_id: u64) -> Vec<BufferedNotification> {
+        self.entries.iter()
+            .filter(|entry| entry.id > last_seen_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of buffered notifications
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no notifications are buffered
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for NotificationBuffer {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_builders() {
+        let notification = Notification::warning("Budget deficit").at_tile(3, 4);
+        assert_eq!(notification.severity, NotificationSeverity::Warning);
+        assert_eq!(notification.link, Some(NotificationLink::Tile { x: 3, y: 4 }));
+
+        let notification = Notification::critical("Fire!").for_entity(7);
+        assert_eq!(notification.link, Some(NotificationLink::Entity(7)));
+    }
+
+    #[test]
+    fn test_buffer_since() {
+        let mut buffer = NotificationBuffer::new(10);
+        let first = buffer.push(Notification::info("one"));
+        let second = buffer.push(Notification::info("two"));
+
+        assert_eq!(buffer.since(0).len(), 2);
+        let newer = buffer.since(first);
+        assert_eq!(newer.len(), 1);
+        assert_eq!(newer[0].id, second);
+        assert!(buffer.since(second).is_empty());
+    }
+
+    #[test]
+    fn test_buffer_capacity() {
+        let mut buffer = NotificationBuffer::new(2);
+        buffer.push(Notification::info("one"));
+        buffer.push(Notification::info("two"));
+        buffer.push(Notification::info("three"));
+
+        assert_eq!(buffer.len(), 2);
+        let remaining = buffer.since(0);
+        assert_eq!(remaining[0].notification.message, "two");
+    }
+
+    #[test]
+    fn test_collect_from_events() {
+        let mut events = EventQueue::new();
+        events.push(Notification::info("queued"));
+
+        let mut buffer = NotificationBuffer::default();
+        buffer.collect(&mut events);
+
+        assert!(events.is_empty());
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let mut buffer = NotificationBuffer::default();
+        buffer.push(Notification::warning("Blocked").at_tile(1, 2));
+
+        let json = serde_json::to_value(buffer.since(0)).unwrap();
+        assert_eq!(json[0]["id"], 1);
+        assert_eq!(json[0]["severity"], "Warning");
+        assert_eq!(json[0]["link"]["Tile"]["x"], 1);
+    }
+}
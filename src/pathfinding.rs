@@ -0,0 +1,595 @@
+/// Grid pathfinding (A*), time-sliced path planning and the path data agents follow, plus a debug heatmap of the
+/// costs each kind of agent's last route search found
+use crate::budgeted_system::{BudgetedSystem, SliceResult, WorkBudget};
+use crate::diffing::{Diffable, VecOp};
+use crate::ecs::{Component, Entity, World};
+use crate::grid_game_components::{GridPositionComponent, ObstacleComponent};
+use crate::core::math::{Color, ShapeType, Vector2d};
+use crate::rendering::RenderCommand;
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Progress of an incremental path search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchStatus {
+    InProgress,
+    Found(Vec<(i32, i32)>),
+    NotFound,
+}
+
+/// Open-list entry: (estimated total cost, cost so far, tile), smallest first
+type OpenEntry = Reverse<(i32, i32, (i32, i32))>;
+
+/// A* search that can be paused after any number of node expansions and resumed later
+#[derive(Debug, Clone)]
+pub struct PathSearch {
+    start: (i32, i32),
+    goal: (i32, i32),
+    width: i32,
+    height: i32,
+    open: BinaryHeap<OpenEntry>,
+    came_from: HashMap<(i32, i32), (i32, i32)>,
+    cost: HashMap<(i32, i32), i32>,
+    status: SearchStatus,
+}
+
+impl PathSearch {
+    /// Start a 4-connected search between two tiles on a `width` x `height` grid
+    pub fn new(start: (i32, i32), goal: (i32, i32), width: i32, height: i32) -> Self {
+        let mut search = Self {
+            start,
+            goal,
+            width,
+            height,
+            open: BinaryHeap::new(),
+            came_from: HashMap::new(),
+            cost: HashMap::new(),
+            status: SearchStatus::InProgress,
+        };
+
+        if !search.in_bounds(start) || !search.in_bounds(goal) {
+            search.status = SearchStatus::NotFound;
+        } else {
+            search.cost.insert(start, 0);
+            search.open.push(Reverse((search.heuristic(start), 0, start)));
+        }
+        search
+    }
+
+    pub fn status(&self) -> &SearchStatus {
+        &self.status
+    }
+
+    fn in_bounds(&self, (x, y): (i32, i32)) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    fn heuristic(&self, (x, y): (i32, i32)) -> i32 {
+        (x - self.goal.0).abs() + (y - self.goal.1).abs()
+    }
+
+    /// Expand at most `max_expansions` nodes, returning the status afterwards
+    /// The returned path includes both the start and the goal tile
+    pub fn step(&mut self, max_expansions: u32, is_blocked: impl Fn(i32, i32) -> bool) -> SearchStatus {
+        if self.status == SearchStatus::InProgress && is_blocked(self.goal.0, self.goal.1) {
+            self.status = SearchStatus::NotFound;
+        }
+
+        let mut expansions = 0;
+        while self.status == SearchStatus::InProgress && expansions < max_expansions {
+            let Some(Reverse((_, current_cost, current))) = self.open.pop() else {
+                self.status = SearchStatus::NotFound;
+                break;
+            };
+
+            if current == self.goal {
+                let mut path = vec![current];
+                let mut node = current;
+                while let Some(previous) = self.came_from.get(&node) {
+                    path.push(*previous);
+                    node = *previous;
+                }
+                path.reverse();
+                self.status = SearchStatus::Found(path);
+                break;
+            }
+            if current_cost > self.cost.get(&current).copied().unwrap_or(i32::MAX) {
+                continue;
+            }
+            expansions += 1;
+
+            for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                let next = (current.0 + dx, current.1 + dy);
+                if !self.in_bounds(next) || is_blocked(next.0, next.1) {
+                    continue;
+                }
+
+                let next_cost = current_cost + 1;
+                if next_cost < self.cost.get(&next).copied().unwrap_or(i32::MAX) {
+                    self.cost.insert(next, next_cost);
+                    self.came_from.insert(next, current);
+                    self.open.push(Reverse((next_cost + self.heuristic(next), next_cost, next)));
+                }
+            }
+        }
+
+        self.status.clone()
+    }
+
+    /// Cost from the start of every tile reached so far
+    pub fn costs(&self) -> &HashMap<(i32, i32), i32> {
+        &self.cost
+    }
+
+    /// Tile the search started from
+    pub fn start(&self) -> (i32, i32) {
+        self.start
+    }
+
+    /// Tile the search is looking for
+    pub fn goal(&self) -> (i32, i32) {
+        self.goal
+    }
+}
+
+/// Find the shortest 4-connected path between two tiles on a `width` x `height` grid
+/// The returned path includes both `start` and `goal`; `None` if the goal is unreachable
+pub fn find_path(
+    start: (i32, i32),
+    goal: (i32, i32),
+    width: i32,
+    height: i32,
+    is_blocked: impl Fn(i32, i32) -> bool,
+) -> Option<Vec<(i32, i32)>> {
+    match PathSearch::new(start, goal, width, height).step(u32::MAX, is_blocked) {
+        SearchStatus::Found(path) => Some(path),
+        _ => None,
+    }
+}
+
+/// Tiles blocked by obstacles and buildings
+pub fn blocked_tiles(world: &World) -> HashSet<(i32, i32)> {
+    world.entities_with_components(&[TypeId::of::<ObstacleComponent>(), TypeId::of::<GridPositionComponent>()])
+        .into_iter()
+        .filter(|entity| world.get_component::<ObstacleComponent>(*entity).is_some_and(|o| o.block_movement))
+        .filter_map(|entity| world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y)))
+        .collect()
+}
+
+/// Outcome of a path request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathRequestStatus {
+    Pending,
+    /// A `PathComponent` was added to the entity
+    Found,
+    Failed,
+}
+
+/// Component asking the path planner for a path from the entity's tile to a goal
+#[derive(Clone, Debug)]
+pub struct PathRequestComponent {
+    pub goal: (i32, i32),
+    pub status: PathRequestStatus,
+}
+
+impl PathRequestComponent {
+    pub fn new(goal: (i32, i32)) -> Self {
+        Self { goal, status: PathRequestStatus::Pending }
+    }
+}
+
+impl Component for PathRequestComponent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Kinds of agents whose route searches the pathfinding heatmap can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PathAgentType {
+    /// Citizens walking to their destinations, planned by `PathPlanningSystem`
+    Citizen,
+    /// Delivery vans driving on roads, routed by `LogisticsSystem`
+    Truck,
+}
+
+impl PathAgentType {
+    pub fn all() -> [PathAgentType; 2] {
+        [PathAgentType::Citizen, PathAgentType::Truck]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PathAgentType::Citizen => "citizen",
+            PathAgentType::Truck => "truck",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// The cost from the start of every tile a finished route search reached, and the route it chose
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathCostField {
+    pub start: (i32, i32),
+    pub goal: (i32, i32),
+    pub costs: HashMap<(i32, i32), i32>,
+    /// Empty when the goal was unreachable
+    pub route: Vec<(i32, i32)>,
+}
+
+impl PathCostField {
+    pub fn from_search(search: &PathSearch) -> Self {
+        let route = match search.status() {
+            SearchStatus::Found(route) => route.clone(),
+            _ => Vec::new(),
+        };
+        Self { start: search.start(), goal: search.goal(), costs: search.costs().clone(), route }
+    }
+
+    /// Tiles shaded from green (cheap) to red (the most expensive tile reached), with the route outlined
+    pub fn heatmap_commands(&self, cell_size: f32, z_order: i32) -> Vec<RenderCommand> {
+        let max_cost = self.costs.values().copied().max().unwrap_or(0).max(1) as f32;
+        let center = |(x, y): (i32, i32)| Vector2d::new((x as f32 + 0.5) * cell_size, (y as f32 + 0.5) * cell_size);
+        let mut tiles: Vec<(&(i32, i32), &i32)> = self.costs.iter().collect();
+        tiles.sort_unstable();
+
+        let mut commands: Vec<RenderCommand> = tiles.into_iter()
+            .filter_map(|(tile, cost)| {
+                let heat = *cost as f32 / max_cost;
+                RenderCommand::shape(ShapeType::Rectangle { width: cell_size, height: cell_size })
+                    .at(center(*tile))
+                    .fill(Color::new(heat, 1.0 - heat, 0.0, 0.5))
+                    .z_order(z_order)
+                    .build()
+                    .ok()
+            })
+            .collect();
+        commands.extend(self.route.iter().filter_map(|tile| {
+            RenderCommand::shape(ShapeType::Rectangle { width: cell_size - 2.0, height: cell_size - 2.0 })
+                .at(center(*tile))
+                .no_fill()
+                .stroke(Color::white(), 2.0)
+                .z_order(z_order + 1)
+                .build()
+                .ok()
+        }));
+        commands
+    }
+}
+
+/// Debug overlay of route search costs, shown per agent type; searches are only recorded for the types shown
+#[derive(Debug, Clone, Default)]
+pub struct PathHeatmap {
+    enabled: BTreeSet<PathAgentType>,
+    fields: BTreeMap<PathAgentType, PathCostField>,
+}
+
+/// The heatmap shared between the game, which draws it, and the systems whose searches fill it
+pub type SharedPathHeatmap = Arc<Mutex<PathHeatmap>>;
+
+impl PathHeatmap {
+    pub fn is_enabled(&self, kind: PathAgentType) -> bool {
+        self.enabled.contains(&kind)
+    }
+
+    /// Show or hide a type's searches; hiding forgets its last search
+    pub fn set_enabled(&mut self, kind: PathAgentType, enabled: bool) {
+        if enabled {
+            self.enabled.insert(kind);
+        } else {
+            self.enabled.remove(&kind);
+            self.fields.remove(&kind);
+        }
+    }
+
+    pub fn enabled(&self) -> Vec<PathAgentType> {
+        self.enabled.iter().copied().collect()
+    }
+
+    /// Keep a finished search as the type's latest, if the type is shown
+    pub fn record(&mut self, kind: PathAgentType, search: &PathSearch) {
+        if self.is_enabled(kind) {
+            self.fields.insert(kind, PathCostField::from_search(search));
+        }
+    }
+
+    pub fn field(&self, kind: PathAgentType) -> Option<&PathCostField> {
+        self.fields.get(&kind)
+    }
+
+    /// Heatmaps of the shown types' latest searches
+    pub fn render_commands(&self, cell_size: f32, z_order: i32) -> Vec<RenderCommand> {
+        self.fields.values().flat_map(|field| field.heatmap_commands(cell_size, z_order)).collect()
+    }
+}
+
+/// Budgeted system answering path requests, one node expansion per work unit
+/// A search that runs out of budget is kept and resumed on the next frame
+pub struct PathPlanningSystem {
+    width: i32,
+    height: i32,
+    active: Option<(Entity, PathSearch)>,
+    heatmap: Option<SharedPathHeatmap>,
+}
+
+impl PathPlanningSystem {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self { width, height, active: None, heatmap: None }
+    }
+
+    /// Record finished searches in a pathfinding heatmap as citizen searches
+    pub fn with_heatmap(mut self, heatmap: SharedPathHeatmap) -> Self {
+        self.heatmap = Some(heatmap);
+        self
+    }
+
+    /// Entity whose search is currently in progress
+    pub fn active_request(&self) -> Option<Entity> {
+        self.active.as_ref().map(|(entity, _)| *entity)
+    }
+
+    fn next_search(&self, world: &World) -> Option<(Entity, PathSearch)> {
+        let mut requests = world.entities_with_components(&[
+            TypeId::of::<PathRequestComponent>(),
+            TypeId::of::<GridPositionComponent>(),
+        ]);
+        requests.sort_unstable();
+
+        requests.into_iter().find_map(|entity| {
+            let request = world.get_component::<PathRequestComponent>(entity)?;
+            if request.status != PathRequestStatus::Pending {
+                return None;
+            }
+            let pos = world.get_component::<GridPositionComponent>(entity)?;
+            Some((entity, PathSearch::new((pos.x, pos.y), request.goal, self.width, self.height)))
+        })
+    }
+}
+
+impl BudgetedSystem for PathPlanningSystem {
+    fn name(&self) -> &'static str {
+        "PathPlanningSystem"
+    }
+
+    fn run_slice(&mut self, world: &mut World, budget: &mut WorkBudget) -> SliceResult {
+        let blocked = blocked_tiles(world);
+
+        loop {
+            // Drop searches whose request was cancelled in the meantime
+            if let Some((entity, search)) = &self.active {
+                let still_pending = world.get_component::<PathRequestComponent>(*entity)
+                    .is_some_and(|request| request.status == PathRequestStatus::Pending && request.goal == search.goal());
+                if !still_pending {
+                    self.active = None;
+                }
+            }
+            if self.active.is_none() {
+                self.active = self.next_search(world);
+            }
+            let Some((entity, search)) = self.active.as_mut() else {
+                return SliceResult::Finished;
+            };
+            let entity = *entity;
+
+            let mut status = search.status().clone();
+            while status == SearchStatus::InProgress {
+                if !budget.try_consume(1) {
+                    return SliceResult::Pending;
+                }
+                status = search.step(1, |x, y| blocked.contains(&(x, y)));
+            }
+            if let Some(Ok(mut heatmap)) = self.heatmap.as_ref().map(|heatmap| heatmap.lock()) {
+                heatmap.record(PathAgentType::Citizen, search);
+            }
+
+            let request_status = match status {
+                SearchStatus::Found(waypoints) => match world.add_component(entity, PathComponent::new(waypoints)) {
+                    Ok(()) => PathRequestStatus::Found,
+                    Err(_) => PathRequestStatus::Failed,
+                },
+                _ => PathRequestStatus::Failed,
+            };
+            if let Some(mut request) = world.get_component_mut::<PathRequestComponent>(entity) {
+                request.status = request_status;
+            }
+            self.active = None;
+        }
+    }
+}
+
+/// Component holding the path an agent is currently following
+#[derive(Clone, Debug, Default)]
+pub struct PathComponent {
+    /// Tiles of the path, starting at the tile the path was planned from
+    pub waypoints: Vec<(i32, i32)>,
+    /// Index of the waypoint the agent is standing on
+    pub current: usize,
+}
+
+impl PathComponent {
+    pub fn new(waypoints: Vec<(i32, i32)>) -> Self {
+        Self { waypoints, current: 0 }
+    }
+
+    /// Next tile to step onto, if the path is not finished
+    pub fn next_waypoint(&self) -> Option<(i32, i32)> {
+        self.waypoints.get(self.current + 1).copied()
+    }
+
+    /// Tiles still ahead of the agent, including the one it stands on
+    pub fn remaining(&self) -> &[(i32, i32)] {
+        let start = self.current.min(self.waypoints.len());
+        &self.waypoints[start..]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_waypoint().is_none()
+    }
+
+    /// Line segments through the centers of the remaining tiles, for route inspection
+    pub fn route_commands(&self, cell_size: f32, z_order: i32) -> Vec<RenderCommand> {
+        let center = |(x, y): (i32, i32)| Vector2d::new((x as f32 + 0.5) * cell_size, (y as f32 + 0.5) * cell_size);
+
+        self.remaining()
+            .windows(2)
+            .filter_map(|segment| {
+                RenderCommand::shape(ShapeType::Line { start: center(segment[0]), end: center(segment[1]), thickness: 3.0 })
+                    .no_fill()
+                    .stroke(Color::yellow(), 3.0)
+                    .z_order(z_order)
+                    .build()
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+/// Changes to a path: waypoint edits and the agent's progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathDiff {
+    pub waypoints: Option<Vec<VecOp<(i32, i32)>>>,
+    pub current: Option<usize>,
+}
+
+impl Diffable for PathComponent {
+    type Diff = PathDiff;
+
+    fn diff(&self, target: &Self) -> Option<Self::Diff> {
+        let diff = PathDiff { waypoints: self.waypoints.diff(&target.waypoints), current: self.current.diff(&target.current) };
+        (diff.waypoints.is_some() || diff.current.is_some()).then_some(diff)
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> bool {
+        let mut waypoints = self.waypoints.clone();
+        if diff.waypoints.as_ref().is_some_and(|ops| !waypoints.apply_diff(ops)) {
+            return false;
+        }
+        self.waypoints = waypoints;
+        self.current = diff.current.unwrap_or(self.current);
+        true
+    }
+}
+
+impl Component for PathComponent {
+    fn validate(&self) -> bool {
+        self.waypoints.is_empty() || self.current < self.waypoints.len()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_path_around_wall() {
+        // Wall at x = 2 from y = 0 to y = 3, leaving y = 4 open
+        let blocked = |x: i32, y: i32| x == 2 && y < 4;
+        let path = find_path((0, 0), (4, 0), 5, 5, blocked).unwrap();
+
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(4, 0)));
+        assert_eq!(path.len(), 13);
+        assert!(path.iter().all(|&(x, y)| !blocked(x, y)));
+    }
+
+    #[test]
+    fn test_find_path_unreachable() {
+        assert!(find_path((0, 0), (4, 0), 5, 5, |x, _| x == 2).is_none());
+        assert!(find_path((0, 0), (9, 9), 5, 5, |_, _| false).is_none());
+    }
+
+    #[test]
+    fn test_search_resumes_across_steps() {
+        let blocked = |x: i32, y: i32| x == 2 && y < 4;
+        let mut search = PathSearch::new((0, 0), (4, 0), 5, 5);
+
+        assert_eq!(search.step(3, blocked), SearchStatus::InProgress);
+        let mut steps = 1;
+        while search.step(3, blocked) == SearchStatus::InProgress {
+            steps += 1;
+        }
+        assert!(steps > 1);
+        assert_eq!(search.status(), &SearchStatus::Found(find_path((0, 0), (4, 0), 5, 5, blocked).unwrap()));
+    }
+
+    #[test]
+    fn test_planning_system_respects_budget() {
+        let mut world = World::new();
+        let walker = world.create_entity();
+        world.add_component(walker, GridPositionComponent { x: 0, y: 0 }).unwrap();
+        world.add_component(walker, PathRequestComponent::new((9, 9))).unwrap();
+
+        let mut planner = PathPlanningSystem::new(10, 10);
+        assert_eq!(planner.run_slice(&mut world, &mut WorkBudget::new(5)), SliceResult::Pending);
+        assert_eq!(planner.active_request(), Some(walker));
+        assert!(!world.has_component::<PathComponent>(walker));
+
+        assert_eq!(planner.run_slice(&mut world, &mut WorkBudget::unlimited()), SliceResult::Finished);
+        assert_eq!(world.get_component::<PathRequestComponent>(walker).unwrap().status, PathRequestStatus::Found);
+        assert_eq!(world.get_component::<PathComponent>(walker).unwrap().waypoints.len(), 19);
+    }
+
+    #[test]
+    fn test_heatmap_records_searches_of_shown_types() {
+        let heatmap = SharedPathHeatmap::default();
+        let mut world = World::new();
+        let walker = world.create_entity();
+        world.add_component(walker, GridPositionComponent { x: 0, y: 0 }).unwrap();
+        world.add_component(walker, PathRequestComponent::new((4, 0))).unwrap();
+        let mut planner = PathPlanningSystem::new(5, 5).with_heatmap(heatmap.clone());
+
+        // Nothing is recorded while citizens aren't shown
+        planner.run_slice(&mut world, &mut WorkBudget::unlimited());
+        assert!(heatmap.lock().unwrap().field(PathAgentType::Citizen).is_none());
+
+        heatmap.lock().unwrap().set_enabled(PathAgentType::from_name("citizen").unwrap(), true);
+        world.add_component(walker, PathRequestComponent::new((4, 4))).unwrap();
+        planner.run_slice(&mut world, &mut WorkBudget::unlimited());
+        let heatmap = heatmap.lock().unwrap();
+        let field = heatmap.field(PathAgentType::Citizen).unwrap();
+        assert_eq!((field.start, field.goal, field.route.len()), ((0, 0), (4, 4), 9));
+        assert_eq!(field.costs[&(0, 0)], 0);
+        assert_eq!(field.costs[&(4, 4)], 8);
+        // A shaded square for each tile reached, and an outline for each tile of the route
+        assert_eq!(heatmap.render_commands(32.0, 4).len(), field.costs.len() + 9);
+        assert!(heatmap.field(PathAgentType::Truck).is_none());
+    }
+
+    #[test]
+    fn test_path_component_progress() {
+        let mut path = PathComponent::new(vec![(0, 0), (1, 0), (2, 0)]);
+        assert_eq!(path.next_waypoint(), Some((1, 0)));
+        assert_eq!(path.route_commands(32.0, 5).len(), 2);
+
+        path.current = 2;
+        assert!(path.is_finished());
+        assert_eq!(path.remaining(), &[(2, 0)]);
+        assert!(path.route_commands(32.0, 5).is_empty());
+    }
+}
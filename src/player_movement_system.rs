@@ -1,6 +1,7 @@
 use crate::ecs::World;
 use crate::game_components::{PlayerComponent, GridComponent, ObstacleComponent};
-use crate::input::{get_global_input_manager, Key};
+use crate::input::Key;
+use crate::input::input_state::Input;
 use crate::core::math::Vector2d;
 
 /// System for handling player movement based on input
@@ -11,37 +12,24 @@ impl PlayerMovementSystem {
         Self
     }
     
-    /// Update player movement based on input
-    pub fn update_player_movement(world: &World) {
-        // Get input manager
-        let input_manager = match get_global_input_manager() {
-            Ok(manager) => manager,
-            Err(_) => return, // No input manager available
-        };
-        
+    /// Update player movement based on the shared input resource
+    pub fn update_player_movement(world: &World, input: &Input) {
         // Check for movement input
         let mut movement = Vector2d::new(0.0, 0.0);
         
-        let manager_lock = match input_manager.lock() {
-            Ok(lock) => lock,
-            Err(_) => return,
-        };
-        
-        if manager_lock.is_key_pressed(&Key::W) || manager_lock.is_key_pressed(&Key::ArrowUp) {
+        if input.is_key_pressed(&Key::W) || input.is_key_pressed(&Key::ArrowUp) {
             movement.y -= 1.0; // Move up (negative Y)
         }
-        if manager_lock.is_key_pressed(&Key::S) || manager_lock.is_key_pressed(&Key::ArrowDown) {
+        if input.is_key_pressed(&Key::S) || input.is_key_pressed(&Key::ArrowDown) {
             movement.y += 1.0; // Move down (positive Y)
         }
-        if manager_lock.is_key_pressed(&Key::A) || manager_lock.is_key_pressed(&Key::ArrowLeft) {
+        if input.is_key_pressed(&Key::A) || input.is_key_pressed(&Key::ArrowLeft) {
             movement.x -= 1.0; // Move left (negative X)
         }
-        if manager_lock.is_key_pressed(&Key::D) || manager_lock.is_key_pressed(&Key::ArrowRight) {
+        if input.is_key_pressed(&Key::D) || input.is_key_pressed(&Key::ArrowRight) {
             movement.x += 1.0; // Move right (positive X)
         }
         
-        drop(manager_lock); // Release the lock
-        
         // If no movement input, return early
         if movement.x == 0.0 && movement.y == 0.0 {
             return;
@@ -150,9 +138,26 @@ mod tests {
     }
     
     #[test]
-    fn test_player_movement_without_input_manager() {
+    fn test_player_movement_without_input() {
         let world = World::new();
-        // This should not panic even without an input manager
-        PlayerMovementSystem::update_player_movement(&world);
+        // This should not panic with no input and no entities
+        PlayerMovementSystem::update_player_movement(&world, &Input::new());
+    }
+    
+    #[test]
+    fn test_player_moves_from_shared_input() {
+        use crate::game_components::{GridComponent, PlayerComponent};
+        use crate::input::InputEvent;
+        
+        let mut world = World::new();
+        let grid = world.create_entity();
+        world.add_component(grid, GridComponent::new(5, 5, 32.0)).unwrap();
+        let player = world.create_entity();
+        world.add_component(player, PlayerComponent::new(1, 1, 1.0)).unwrap();
+        
+        let mut input = Input::new();
+        input.begin_frame(&[InputEvent::KeyPress { key: Key::D }]);
+        PlayerMovementSystem::update_player_movement(&world, &input);
+        assert_eq!(world.get_component::<PlayerComponent>(player).unwrap().get_grid_position(), (2, 1));
     }
 }
\ No newline at end of file
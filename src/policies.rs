@@ -0,0 +1,137 @@
+/// City policies: ordinances such as free public transport or a recycling program, defined in data
+/// (`data/policies.ron`) with a cost to enact, a monthly cost and modifier effects
+/// Enacted policies live in the `Economy`, so they are saved with it and the budget pays for them every month
+use crate::economy::Economy;
+use crate::modifiers::ModifierEffect;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The policies shipped with the game
+pub const BUILTIN_POLICIES: &str = include_str!("../data/policies.ron");
+
+/// One policy the player can enact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDefinition {
+    /// Identifies the policy in requests and saves
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    /// Paid once when the policy is enacted
+    pub enact_cost: i64,
+    /// Paid every month the policy stays in force
+    pub monthly_cost: i64,
+    pub effects: Vec<ModifierEffect>,
+}
+
+/// A policy in force, with the terms it was enacted on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnactedPolicy {
+    pub name: String,
+    pub title: String,
+    pub monthly_cost: i64,
+}
+
+/// Source of a policy's modifiers, kept apart from city events of the same name
+pub fn policy_source(name: &str) -> String {
+    format!("policy:{}", name)
+}
+
+/// Every policy the player can choose from
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyCatalog {
+    definitions: Vec<PolicyDefinition>,
+}
+
+impl PolicyCatalog {
+    pub fn new(definitions: Vec<PolicyDefinition>) -> Self {
+        Self { definitions }
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(ron::from_str(text)?))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    /// The policies shipped with the game, parsed once
+    pub fn builtin() -> &'static PolicyCatalog {
+        static BUILTIN: OnceLock<PolicyCatalog> = OnceLock::new();
+        BUILTIN.get_or_init(|| Self::from_ron(BUILTIN_POLICIES).expect("The built-in policies are valid"))
+    }
+
+    pub fn definitions(&self) -> &[PolicyDefinition] {
+        &self.definitions
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PolicyDefinition> {
+        self.definitions.iter().find(|definition| definition.name == name)
+    }
+
+    /// Put a policy in force, paying its enact cost and adding its modifiers; returns the cost paid
+    pub fn enact(&self, name: &str, economy: &mut Economy) -> Result<i64, String> {
+        let definition = self.get(name).ok_or_else(|| format!("Unknown policy '{}'", name))?;
+        if economy.policies.iter().any(|policy| policy.name == name) {
+            return Err(format!("{} is already in force", definition.title));
+        }
+        if definition.enact_cost > economy.treasury.balance {
+            return Err(format!("Insufficient funds to enact {}", definition.title));
+        }
+        economy.treasury.balance -= definition.enact_cost;
+        economy.modifiers.push_effects(&policy_source(name), &definition.effects);
+        economy.policies.push(EnactedPolicy {
+            name: definition.name.clone(),
+            title: definition.title.clone(),
+            monthly_cost: definition.monthly_cost,
+        });
+        Ok(definition.enact_cost)
+    }
+
+    /// Take a policy out of force with its modifiers; the enact cost isn't refunded
+    pub fn repeal(&self, name: &str, economy: &mut Economy) -> Result<(), String> {
+        let index = economy.policies.iter()
+            .position(|policy| policy.name == name)
+            .ok_or_else(|| format!("Policy '{}' isn't in force", name))?;
+        economy.policies.remove(index);
+        economy.modifiers.remove_source(&policy_source(name));
+        Ok(())
+    }
+}
+
+impl Default for PolicyCatalog {
+    fn default() -> Self {
+        Self::builtin().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy::BudgetSystem;
+    use crate::ecs::World;
+    use crate::modifiers::ModifierTarget;
+
+    #[test]
+    fn test_enacted_policies_cost_money_and_apply_modifiers_until_repealed() {
+        let catalog = PolicyCatalog::default();
+        let mut economy = Economy::new(1_200);
+
+        assert_eq!(catalog.enact("free_transit", &mut economy), Ok(1_000));
+        assert_eq!(economy.treasury.balance, 200);
+        assert!(catalog.enact("free_transit", &mut economy).is_err());
+        assert!(catalog.enact("recycling", &mut economy).is_err());
+        assert_eq!(economy.modifiers.percent(ModifierTarget::Happiness), 10);
+
+        let report = BudgetSystem::settle_month(1, &World::new(), &mut economy);
+        assert_eq!(report.policy_expenses, 200);
+        assert_eq!(report.balance, 0);
+
+        catalog.repeal("free_transit", &mut economy).unwrap();
+        assert!(catalog.repeal("free_transit", &mut economy).is_err());
+        assert_eq!(economy.modifiers.percent(ModifierTarget::Happiness), 0);
+        assert_eq!(BudgetSystem::settle_month(2, &World::new(), &mut economy).policy_expenses, 0);
+    }
+}
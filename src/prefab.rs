@@ -0,0 +1,226 @@
+/// Building prefabs loaded from RON data files, where a prefab can extend another and override its fields
+use crate::construction::BuildingKind;
+use crate::core::math::Vector2d;
+use crate::core::math::sprite2d::Sprite2d;
+use crate::economy::ZoneComponent;
+use crate::ecs::{Entity, World};
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// A prefab as written in a data file; fields left out are inherited from the `extends` prefab
+///
+/// ```ron
+/// #![enable(implicit_some)]
+/// [
+///     (name: "house", kind: House, symbol: 'H', color: "green", sprite: "building_house", capacity: 4),
+///     (name: "large_house", extends: "house", sprite: "building_large_house", capacity: 8),
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrefabDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub extends: Option<String>,
+    #[serde(default)]
+    pub kind: Option<BuildingKind>,
+    #[serde(default)]
+    pub symbol: Option<char>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub sprite: Option<String>,
+    #[serde(default)]
+    pub capacity: Option<u32>,
+}
+
+impl PrefabDefinition {
+    /// Fill every field this definition leaves unset from its parent
+    fn inherit(&self, parent: &PrefabDefinition) -> PrefabDefinition {
+        PrefabDefinition {
+            name: self.name.clone(),
+            extends: None,
+            kind: self.kind.or(parent.kind),
+            symbol: self.symbol.or(parent.symbol),
+            color: self.color.clone().or_else(|| parent.color.clone()),
+            sprite: self.sprite.clone().or_else(|| parent.sprite.clone()),
+            capacity: self.capacity.or(parent.capacity),
+        }
+    }
+}
+
+/// A prefab with its inheritance chain merged in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefab {
+    pub name: String,
+    pub kind: BuildingKind,
+    pub symbol: char,
+    /// Color name for the text grid; the kind's default when unset
+    pub color: Option<String>,
+    pub sprite: String,
+    /// Residents or jobs of a zoned building; the kind's default when unset
+    pub capacity: Option<u32>,
+}
+
+/// Resolved prefabs by name
+#[derive(Debug, Clone, Default)]
+pub struct PrefabLibrary {
+    prefabs: HashMap<String, Prefab>,
+}
+
+impl PrefabLibrary {
+    /// Merge every definition with its ancestors
+    /// Fails on duplicate names, unknown parents, inheritance cycles and prefabs without a kind
+    pub fn resolve(definitions: Vec<PrefabDefinition>) -> Result<Self, String> {
+        let mut by_name: HashMap<String, PrefabDefinition> = HashMap::new();
+        for definition in definitions {
+            if by_name.contains_key(&definition.name) {
+                return Err(format!("Prefab '{}' is defined twice", definition.name));
+            }
+            by_name.insert(definition.name.clone(), definition);
+        }
+
+        let mut prefabs = HashMap::new();
+        for name in by_name.keys() {
+            let merged = Self::flatten(&by_name, name)?;
+            let kind = merged.kind.ok_or_else(|| format!("Prefab '{}' has no building kind", name))?;
+            prefabs.insert(name.clone(), Prefab {
+                name: name.clone(),
+                kind,
+                symbol: merged.symbol.unwrap_or_else(|| kind.symbol()),
+                color: merged.color,
+                sprite: merged.sprite.unwrap_or_else(|| format!("building_{:?}", kind).to_lowercase()),
+                capacity: merged.capacity,
+            });
+        }
+        Ok(Self { prefabs })
+    }
+
+    /// Parse and resolve a RON list of prefab definitions
+    pub fn from_ron(text: &str) -> Result<Self, Box<dyn Error>> {
+        let definitions: Vec<PrefabDefinition> = ron::from_str(text)?;
+        Ok(Self::resolve(definitions)?)
+    }
+
+    /// Load prefabs from a RON data file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::from_ron(&fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// All prefab names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.prefabs.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Create a finished building from a prefab at a tile
+    pub fn spawn(&self, world: &mut World, name: &str, x: i32, y: i32) -> Result<Entity, String> {
+        let prefab = self.get(name).ok_or_else(|| format!("Unknown prefab '{}'", name))?;
+        let entity = world.spawn((
+            GridPositionComponent { x, y },
+            Sprite2d::new(prefab.sprite.clone(), Vector2d::new(1.0, 1.0)),
+        )).map_err(|e| format!("Prefab '{}': {}", name, e))?;
+        if let Err(e) = prefab.kind.spawn_final(world, entity) {
+            world.destroy_entity(entity);
+            return Err(format!("Prefab '{}': {}", name, e));
+        }
+        if let Some(mut render) = world.get_component_mut::<RenderComponent>(entity) {
+            render.symbol = prefab.symbol;
+            if let Some(color) = &prefab.color {
+                render.color = color.clone();
+            }
+        }
+        if let (Some(capacity), Some(mut zone)) = (prefab.capacity, world.get_component_mut::<ZoneComponent>(entity)) {
+            zone.population = capacity;
+        }
+        Ok(entity)
+    }
+
+    /// Definition of `name` with all ancestors merged in, root first
+    fn flatten(by_name: &HashMap<String, PrefabDefinition>, name: &str) -> Result<PrefabDefinition, String> {
+        let mut chain: Vec<&PrefabDefinition> = Vec::new();
+        let mut next = Some(name);
+        while let Some(current) = next {
+            if chain.iter().any(|definition| definition.name == current) {
+                let cycle: Vec<&str> = chain.iter().map(|definition| definition.name.as_str()).collect();
+                return Err(format!("Prefab inheritance cycle: {} -> {}", cycle.join(" -> "), current));
+            }
+            let definition = by_name.get(current).ok_or_else(|| match chain.last() {
+                Some(child) => format!("Prefab '{}' extends unknown prefab '{}'", child.name, current),
+                None => format!("Unknown prefab '{}'", current),
+            })?;
+            chain.push(definition);
+            next = definition.extends.as_deref();
+        }
+
+        let root = chain.pop().map(|definition| definition.inherit(&PrefabDefinition::default())).unwrap_or_default();
+        Ok(chain.into_iter().rev().fold(root, |merged, definition| definition.inherit(&merged)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILDINGS: &str = r#"
+        #![enable(implicit_some)]
+        [
+            (name: "house", kind: House, color: "green", sprite: "building_house", capacity: 4),
+            (name: "large_house", extends: "house", sprite: "building_large_house", capacity: 8),
+            (name: "mansion", extends: "large_house", symbol: 'M'),
+        ]
+    "#;
+
+    #[test]
+    fn test_overrides_merge_down_the_chain() {
+        let library = PrefabLibrary::from_ron(BUILDINGS).unwrap();
+        assert_eq!(library.names(), vec!["house", "large_house", "mansion"]);
+
+        let mansion = library.get("mansion").unwrap();
+        assert_eq!(mansion.kind, BuildingKind::House);
+        assert_eq!(mansion.symbol, 'M');
+        assert_eq!(mansion.sprite, "building_large_house");
+        assert_eq!(mansion.capacity, Some(8));
+        assert_eq!(library.get("house").unwrap().symbol, 'H');
+
+        let mut world = World::new();
+        let entity = library.spawn(&mut world, "large_house", 2, 3).unwrap();
+        assert_eq!(world.get_component::<ZoneComponent>(entity).unwrap().population, 8);
+        assert_eq!(world.get_component::<Sprite2d>(entity).unwrap().texture_id(), "building_large_house");
+        assert!(library.spawn(&mut world, "castle", 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_invalid_inheritance() {
+        let definition = |name: &str, extends: Option<&str>| PrefabDefinition {
+            name: name.to_string(),
+            extends: extends.map(str::to_string),
+            kind: Some(BuildingKind::Shop),
+            ..Default::default()
+        };
+
+        let cycle = PrefabLibrary::resolve(vec![
+            definition("a", Some("b")),
+            definition("b", Some("c")),
+            definition("c", Some("a")),
+        ]).unwrap_err();
+        assert!(cycle.contains("cycle"), "{}", cycle);
+
+        let missing = PrefabLibrary::resolve(vec![definition("a", Some("ghost"))]).unwrap_err();
+        assert_eq!(missing, "Prefab 'a' extends unknown prefab 'ghost'");
+
+        let duplicate = PrefabLibrary::resolve(vec![definition("a", None), definition("a", None)]);
+        assert!(duplicate.is_err());
+
+        let no_kind = PrefabDefinition { name: "shed".to_string(), ..Default::default() };
+        assert_eq!(PrefabLibrary::resolve(vec![no_kind]).unwrap_err(), "Prefab 'shed' has no building kind");
+    }
+}
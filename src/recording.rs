@@ -0,0 +1,133 @@
+/// Debug recordings: the debug tracker's stepped ticks saved as RON text or a compact binary encoding
+/// The binary encoding is the frames serialized with bincode and compressed with zlib, so the component text
+/// that stays the same from tick to tick compresses down to a few bytes per frame
+use crate::debug_tracker::FrameRecord;
+use bincode::Options;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::fs;
+use std::path::Path;
+
+/// Bytes opening a binary recording
+pub const BINARY_MAGIC: &[u8; 4] = b"CBRC";
+
+const BINARY_VERSION: u8 = 4;
+
+/// Largest decompressed recording read, so a corrupt or hostile file can't exhaust memory
+const MAX_DECODED_BYTES: u64 = 1 << 30;
+
+/// How a recording is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Ron,
+    Binary,
+}
+
+impl RecordingFormat {
+    /// RON for `.ron` files, binary for anything else
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ron") => RecordingFormat::Ron,
+            _ => RecordingFormat::Binary,
+        }
+    }
+}
+
+pub fn encode(frames: &[FrameRecord], format: RecordingFormat) -> Result<Vec<u8>, String> {
+    match format {
+        RecordingFormat::Ron => ron::ser::to_string(frames).map(String::into_bytes).map_err(|e| e.to_string()),
+        RecordingFormat::Binary => {
+            let mut header = BINARY_MAGIC.to_vec();
+            header.push(BINARY_VERSION);
+            let mut encoder = ZlibEncoder::new(header, Compression::default());
+            bincode::DefaultOptions::new().serialize_into(&mut encoder, frames).map_err(|e| e.to_string())?;
+            encoder.finish().map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Decode a recording in either format, told apart by the binary magic bytes
+pub fn decode(bytes: &[u8]) -> Result<Vec<FrameRecord>, String> {
+    let Some(body) = bytes.strip_prefix(BINARY_MAGIC) else {
+        let text = std::str::from_utf8(bytes).map_err(|_| "Recording is neither binary nor RON text".to_string())?;
+        return ron::from_str(text).map_err(|e| e.to_string());
+    };
+    match body.split_first() {
+        Some((&BINARY_VERSION, compressed)) => bincode::DefaultOptions::new()
+            .with_limit(MAX_DECODED_BYTES)
+            .deserialize_from(ZlibDecoder::new(compressed))
+            .map_err(|e| format!("Recording is damaged: {}", e)),
+        Some((version, _)) => Err(format!("Unsupported recording version {}", version)),
+        None => Err("Recording is truncated".to_string()),
+    }
+}
+
+/// Save frames in the format the file extension asks for
+pub fn save(path: &Path, frames: &[FrameRecord]) -> Result<(), String> {
+    let bytes = encode(frames, RecordingFormat::from_path(path))?;
+    fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn load(path: &Path) -> Result<Vec<FrameRecord>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Rewrite a recording in the format of the output's extension, returning the input and output sizes in bytes
+pub fn convert(input: &Path, output: &Path) -> Result<(u64, u64), String> {
+    save(output, &load(input)?)?;
+    let size = |path: &Path| fs::metadata(path).map(|metadata| metadata.len()).map_err(|e| format!("{}: {}", path.display(), e));
+    Ok((size(input)?, size(output)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug_tracker::{StateDiff, SystemCheckpoint, WorldState};
+    use std::collections::BTreeMap;
+
+    fn frame(tick: u64, x: i32) -> FrameRecord {
+        let mut entities = BTreeMap::new();
+        for entity in 0..50 {
+            let position = if entity == 0 { x } else { entity as i32 };
+            entities.insert(entity, BTreeMap::from([
+                ("GridPosition".to_string(), format!("GridPositionComponent {{ x: {}, y: 3 }}", position)),
+                ("Render".to_string(), "RenderComponent { symbol: 'c', color: \"cyan\" }".to_string()),
+            ]));
+        }
+        FrameRecord {
+            tick,
+            delta_seconds: 1.0 / 60.0,
+            initial_hash: 0xDEAD_BEEF_0000_0000 + tick,
+            checkpoints: vec![SystemCheckpoint { system: "player_input".to_string(), state_hash: tick * 31 }],
+            diff: StateDiff { spawned: vec![], despawned: vec![7], changed: vec![(0, "GridPosition".to_string())] },
+            // Every fifth tick is a keyframe
+            state: tick.is_multiple_of(5).then_some(WorldState {
+                entities,
+                resources: BTreeMap::from([("economy".to_string(), format!("Economy {{ balance: {} }}", 100 * tick))]),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_binary_and_text_round_trip_and_convert() {
+        let frames: Vec<FrameRecord> = (1..=20).map(|tick| frame(tick, tick as i32)).collect();
+        let binary = encode(&frames, RecordingFormat::Binary).unwrap();
+        let text = encode(&frames, RecordingFormat::Ron).unwrap();
+        assert_eq!(decode(&binary).unwrap(), frames);
+        assert_eq!(decode(&text).unwrap(), frames);
+        assert!(binary.len() * 5 < text.len(), "{} binary bytes vs {} text bytes", binary.len(), text.len());
+        assert!(decode(&binary[..binary.len() / 2]).unwrap_err().starts_with("Recording is damaged"));
+        assert_eq!(decode(b"CBRC\x03").unwrap_err(), "Unsupported recording version 3");
+
+        let directory = std::env::temp_dir().join(format!("recording_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let (ron_path, binary_path) = (directory.join("session.ron"), directory.join("session.cbrc"));
+        save(&ron_path, &frames).unwrap();
+        let (text_size, binary_size) = convert(&ron_path, &binary_path).unwrap();
+        assert_eq!((text_size, binary_size), (text.len() as u64, binary.len() as u64));
+        assert_eq!(load(&binary_path).unwrap(), frames);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+/// Region-of-interest simulation: the map is cut into square chunks, and only chunks near the focus (the
+/// player, since the camera frames the whole map) or pinned as important run every tick; the rest tick at a
+/// reduced rate. Chunks switch on within one radius and off only beyond a larger one, so a player walking
+/// along a chunk border doesn't flip them back and forth
+/// An inactive chunk's citizens are folded into an `AbstractRegionState` and spawned again when it wakes up
+use crate::agents::AgentComponent;
+use crate::economy::{ZoneComponent, ZoneType};
+use crate::ecs::{Entity, InvalidComponent, World};
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::labor::Employment;
+use crate::stable_id::{EntityRef, StableId, StableIdSystem};
+use serde::Serialize;
+use std::any::TypeId;
+use std::collections::BTreeSet;
+
+/// Chunk coordinates, in chunks from the map origin
+pub type ChunkCoord = (i32, i32);
+
+/// Chunk size and activation distances, in tiles and chunks
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegionConfig {
+    /// Tiles along each side of a chunk
+    pub chunk_size: i32,
+    /// Chunks within this distance of the focus chunk become active
+    pub activate_radius: i32,
+    /// Active chunks stay active until they are farther than this from the focus chunk
+    pub deactivate_radius: i32,
+    /// Inactive chunks run one tick in this many
+    pub inactive_interval: u64,
+}
+
+impl Default for RegionConfig {
+    fn default() -> Self {
+        Self { chunk_size: 16, activate_radius: 2, deactivate_radius: 3, inactive_interval: 8 }
+    }
+}
+
+impl RegionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.chunk_size < 1 || self.inactive_interval < 1 {
+            return Err("Chunk size and inactive interval must be at least 1".to_string());
+        }
+        if self.deactivate_radius < self.activate_radius {
+            return Err(format!("Deactivate radius {} is inside activate radius {}", self.deactivate_radius, self.activate_radius));
+        }
+        Ok(())
+    }
+}
+
+/// Chunks that switched state in one update
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RegionChanges {
+    pub activated: Vec<ChunkCoord>,
+    pub deactivated: Vec<ChunkCoord>,
+}
+
+/// Which chunks of a map are fully simulated
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionActivation {
+    config: RegionConfig,
+    width: i32,
+    height: i32,
+    chunks_wide: i32,
+    chunks_high: i32,
+    active: BTreeSet<ChunkCoord>,
+    pinned: BTreeSet<ChunkCoord>,
+}
+
+impl RegionActivation {
+    /// Chunks covering a map of `width` by `height` tiles, all active until the first update
+    pub fn new(width: i32, height: i32, config: RegionConfig) -> Self {
+        let size = config.chunk_size.max(1);
+        let (chunks_wide, chunks_high) = ((width + size - 1) / size, (height + size - 1) / size);
+        let active = (0..chunks_high).flat_map(|y| (0..chunks_wide).map(move |x| (x, y))).collect();
+        Self { config, width, height, chunks_wide, chunks_high, active, pinned: BTreeSet::new() }
+    }
+
+    pub fn config(&self) -> &RegionConfig {
+        &self.config
+    }
+
+    pub fn chunk_of(&self, x: i32, y: i32) -> ChunkCoord {
+        (x.div_euclid(self.config.chunk_size), y.div_euclid(self.config.chunk_size))
+    }
+
+    /// Keep the chunk holding a tile active wherever the focus is, e.g. a district the player watches;
+    /// false for a tile off the map
+    pub fn pin(&mut self, x: i32, y: i32) -> bool {
+        if !(0..self.width).contains(&x) || !(0..self.height).contains(&y) {
+            return false;
+        }
+        let chunk = self.chunk_of(x, y);
+        self.pinned.insert(chunk);
+        self.active.insert(chunk);
+        true
+    }
+
+    pub fn unpin(&mut self, x: i32, y: i32) -> bool {
+        self.pinned.remove(&self.chunk_of(x, y))
+    }
+
+    pub fn is_active(&self, chunk: ChunkCoord) -> bool {
+        self.active.contains(&chunk)
+    }
+
+    pub fn is_tile_active(&self, x: i32, y: i32) -> bool {
+        self.is_active(self.chunk_of(x, y))
+    }
+
+    /// Whether a tile on the map lies in a chunk
+    pub fn contains(&self, chunk: ChunkCoord, x: i32, y: i32) -> bool {
+        (0..self.width).contains(&x) && (0..self.height).contains(&y) && self.chunk_of(x, y) == chunk
+    }
+
+    /// Active and inactive chunk counts
+    pub fn counts(&self) -> (usize, usize) {
+        let total = (self.chunks_wide * self.chunks_high) as usize;
+        (self.active.len(), total.saturating_sub(self.active.len()))
+    }
+
+    /// Whether entities on a tile run this tick; inactive chunks are staggered so they don't all run together
+    pub fn should_tick(&self, x: i32, y: i32, tick: u64) -> bool {
+        let chunk = self.chunk_of(x, y);
+        if self.is_active(chunk) {
+            return true;
+        }
+        let offset = (chunk.0 * 7 + chunk.1 * 13).rem_euclid(self.config.inactive_interval as i32) as u64;
+        (tick + offset).is_multiple_of(self.config.inactive_interval)
+    }
+
+    /// Activate chunks around the focus tile and deactivate distant ones
+    pub fn update(&mut self, focus: (i32, i32)) -> RegionChanges {
+        let center = self.chunk_of(focus.0, focus.1);
+        let mut changes = RegionChanges::default();
+        for y in 0..self.chunks_high {
+            for x in 0..self.chunks_wide {
+                let chunk = (x, y);
+                let distance = (x - center.0).abs().max((y - center.1).abs());
+                let was_active = self.active.contains(&chunk);
+                let radius = if was_active { self.config.deactivate_radius } else { self.config.activate_radius };
+                let active = distance <= radius || self.pinned.contains(&chunk);
+                if active && !was_active {
+                    self.active.insert(chunk);
+                    changes.activated.push(chunk);
+                } else if !active && was_active {
+                    self.active.remove(&chunk);
+                    changes.deactivated.push(chunk);
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// Citizens folded into a chunk's aggregate: how many stood on a tile with the same destinations
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommuterGroup {
+    pub tile: (i32, i32),
+    pub destinations: Vec<(i32, i32)>,
+    pub count: u32,
+    /// What each of them keeps when spawned again, one per citizen
+    pub citizens: Vec<FoldedCitizen>,
+}
+
+/// A folded citizen's own ID, home and job
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoldedCitizen {
+    pub id: Option<StableId>,
+    pub home: Option<EntityRef>,
+    /// Workplace and commute of the citizen's job
+    pub job: Option<(EntityRef, u32)>,
+}
+
+/// Cheap stand-in for an inactive chunk: totals kept current by formulas instead of simulating each citizen
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AbstractRegionState {
+    /// Residents of the chunk's residential buildings
+    pub population: u32,
+    /// Jobs in the chunk's commercial and industrial buildings
+    pub jobs: u32,
+    /// Residents the chunk's own jobs can employ
+    pub employed: u32,
+    /// Citizens despawned with the chunk, respawned when it activates
+    pub commuters: Vec<CommuterGroup>,
+    /// Reduced-rate ticks run since the chunk went inactive
+    pub ticks: u64,
+}
+
+impl AbstractRegionState {
+    /// Despawn the citizens standing in a chunk into a fresh aggregate
+    pub fn collapse(world: &mut World, regions: &RegionActivation, chunk: ChunkCoord) -> Self {
+        let mut commuters: Vec<CommuterGroup> = Vec::new();
+        for entity in world.entities_with_components(&[TypeId::of::<AgentComponent>(), TypeId::of::<GridPositionComponent>()]) {
+            let Some(pos) = world.get_component::<GridPositionComponent>(entity).map(|pos| (pos.x, pos.y)) else { continue };
+            if !regions.contains(chunk, pos.0, pos.1) {
+                continue;
+            }
+            let Some((destinations, home)) = world.get_component::<AgentComponent>(entity).map(|agent| (agent.destinations.clone(), agent.home)) else { continue };
+            let citizen = FoldedCitizen {
+                id: StableIdSystem::get(world, entity),
+                home,
+                job: world.get_component::<Employment>(entity).map(|employment| (employment.workplace, employment.commute)),
+            };
+            match commuters.iter_mut().find(|group| group.tile == pos && group.destinations == destinations) {
+                Some(group) => {
+                    group.count += 1;
+                    group.citizens.push(citizen);
+                }
+                None => commuters.push(CommuterGroup { tile: pos, destinations, count: 1, citizens: vec![citizen] }),
+            }
+            world.destroy_entity(entity);
+        }
+        commuters.sort_by(|a, b| (a.tile, &a.destinations).cmp(&(b.tile, &b.destinations)));
+        let mut state = Self { commuters, ..Self::default() };
+        state.estimate(world, regions, chunk);
+        state
+    }
+
+    /// Recount the chunk's homes and jobs from its buildings and settle employment, once per reduced-rate tick
+    pub fn estimate(&mut self, world: &World, regions: &RegionActivation, chunk: ChunkCoord) {
+        let (mut population, mut jobs) = (0, 0);
+        for entity in world.entities_with_components(&[TypeId::of::<ZoneComponent>(), TypeId::of::<GridPositionComponent>()]) {
+            let (Some(zone), Some(pos)) = (world.get_component::<ZoneComponent>(entity), world.get_component::<GridPositionComponent>(entity)) else { continue };
+            if !regions.contains(chunk, pos.x, pos.y) {
+                continue;
+            }
+            match zone.zone_type {
+                ZoneType::Residential => population += zone.population,
+                ZoneType::Commercial | ZoneType::Industrial => jobs += zone.population,
+            }
+        }
+        self.population = population;
+        self.jobs = jobs;
+        self.employed = population.min(jobs);
+        self.ticks += 1;
+    }
+
+    /// Total citizens held by the aggregate
+    pub fn citizens(&self) -> u32 {
+        self.commuters.iter().map(|group| group.count).sum()
+    }
+
+    /// Spawn concrete citizens matching the aggregate, where they stood and bound where they were going,
+    /// with the IDs, homes and jobs they had
+    pub fn expand(self, world: &mut World) -> Result<Vec<Entity>, InvalidComponent> {
+        let mut spawned = Vec::new();
+        for group in self.commuters {
+            for citizen in group.citizens {
+                let agent = AgentComponent { home: citizen.home, ..AgentComponent::new("Citizen", group.destinations.clone()) };
+                let entity = world.spawn((
+                    GridPositionComponent { x: group.tile.0, y: group.tile.1 },
+                    agent,
+                    RenderComponent { symbol: 'c', color: "cyan".to_string() },
+                ))?;
+                if let Some(id) = citizen.id {
+                    world.add_component(entity, id)?;
+                }
+                if let Some((workplace, commute)) = citizen.job {
+                    world.add_component(entity, Employment { workplace, commute })?;
+                }
+                spawned.push(entity);
+            }
+        }
+        Ok(spawned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_follow_the_focus_with_hysteresis() {
+        let config = RegionConfig { chunk_size: 4, activate_radius: 1, deactivate_radius: 2, inactive_interval: 4 };
+        let mut regions = RegionActivation::new(40, 8, config);
+        assert_eq!(regions.counts(), (20, 0));
+
+        // Chunks already on stay on out to the deactivate radius
+        let changes = regions.update((1, 1));
+        assert_eq!(regions.counts(), (6, 14));
+        assert!(changes.activated.is_empty() && changes.deactivated.contains(&(3, 0)));
+        assert!(regions.is_tile_active(8, 0) && !regions.is_tile_active(12, 0));
+
+        // Walking east switches chunks on one radius ahead and off one radius further behind
+        let changes = regions.update((13, 1));
+        assert_eq!(changes.activated, vec![(3, 0), (4, 0), (3, 1), (4, 1)]);
+        assert_eq!(changes.deactivated, vec![(0, 0), (0, 1)]);
+        assert_eq!(regions.update((9, 1)), RegionChanges::default());
+
+        // Far away, the pinned chunk stays on and the others around the start tick one time in four
+        assert!(regions.pin(1, 6));
+        assert!(!regions.pin(40, 6) && !regions.pin(1, -1));
+        regions.update((39, 0));
+        assert!(regions.is_tile_active(0, 4) && !regions.is_tile_active(8, 0));
+        let runs = (0..8).filter(|tick| regions.should_tick(8, 0, *tick)).count();
+        assert_eq!(runs, 2);
+        assert!((0..8).all(|tick| regions.should_tick(39, 0, tick)));
+        assert!(RegionConfig { deactivate_radius: 0, ..RegionConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_inactive_chunk_citizens_fold_into_aggregates_and_back() {
+        let regions = RegionActivation::new(8, 4, RegionConfig { chunk_size: 4, ..RegionConfig::default() });
+        let mut world = World::new();
+        world.spawn((GridPositionComponent { x: 5, y: 1 }, ZoneComponent::new(ZoneType::Residential, 6))).unwrap();
+        world.spawn((GridPositionComponent { x: 6, y: 2 }, ZoneComponent::new(ZoneType::Industrial, 4))).unwrap();
+        world.spawn((GridPositionComponent { x: 1, y: 1 }, ZoneComponent::new(ZoneType::Commercial, 9))).unwrap();
+        let route = vec![(6, 2), (5, 1)];
+        let citizens: Vec<Entity> = [(5, 1), (5, 1), (7, 0), (2, 2)].into_iter()
+            .map(|tile| world.spawn((GridPositionComponent { x: tile.0, y: tile.1 }, AgentComponent::new("Citizen", route.clone()))).unwrap())
+            .collect();
+        let (home, work) = (EntityRef::new(StableId(1), 0), EntityRef::new(StableId(2), 1));
+        world.get_component_mut::<AgentComponent>(citizens[1]).unwrap().home = Some(home);
+        world.add_component(citizens[1], StableId(3)).unwrap();
+        world.add_component(citizens[1], Employment { workplace: work, commute: 2 }).unwrap();
+
+        let state = AbstractRegionState::collapse(&mut world, &regions, (1, 0));
+        assert_eq!((state.population, state.jobs, state.employed, state.citizens()), (6, 4, 4, 3));
+        assert_eq!((state.commuters[0].tile, &state.commuters[0].destinations, state.commuters[0].count), ((5, 1), &route, 2));
+        assert_eq!(world.entities_with_components(&[TypeId::of::<AgentComponent>()]).len(), 1);
+
+        let spawned = state.expand(&mut world).unwrap();
+        assert_eq!(spawned.len(), 3);
+        let pos = world.get_component::<GridPositionComponent>(spawned[2]).unwrap();
+        assert_eq!((pos.x, pos.y), (7, 0));
+        assert_eq!(world.get_component::<AgentComponent>(spawned[0]).unwrap().destinations, route);
+
+        // The citizen with a home and a job gets them back, under the same ID
+        let returned = StableIdSystem::find(&world, StableId(3)).unwrap();
+        assert_eq!(world.get_component::<AgentComponent>(returned).unwrap().home, Some(home));
+        assert_eq!(world.get_component::<Employment>(returned).as_deref(), Some(&Employment { workplace: work, commute: 2 }));
+        assert_eq!(spawned.iter().filter(|entity| world.has_component::<Employment>(**entity)).count(), 1);
+    }
+}
@@ -0,0 +1,194 @@
+/// Per-entity render effects: tints, timed flashes and outlines applied on top of an entity's normal draw command,
+/// so highlighting, damage flashes and dimming need no extra sprites
+use crate::core::math::{Color, FillStyle, StrokeStyle};
+use crate::ecs::{Component, Entity, InvalidComponent, World};
+use crate::rendering::RenderCommand;
+use std::any::{Any, TypeId};
+
+/// Outline drawn around the entity the player selected
+pub const SELECTION_OUTLINE: Color = Color { r: 1.0, g: 0.85, b: 0.0, a: 1.0 };
+
+/// Stroke width of outlines, in world units
+pub const OUTLINE_WIDTH: f32 = 2.0;
+
+/// Seconds a damage flash takes to fade
+pub const DAMAGE_FLASH_SECONDS: f32 = 0.4;
+
+/// A color pulse fading out over `duration` seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flash {
+    pub color: Color,
+    pub remaining: f32,
+    pub duration: f32,
+}
+
+impl Flash {
+    /// How strongly the flash color shows, from 1.0 when started to 0.0 when done
+    pub fn strength(&self) -> f32 {
+        (self.remaining / self.duration).clamp(0.0, 1.0)
+    }
+}
+
+/// Component for entities drawn with effects on top of their normal color
+/// Removed by `RenderEffectSystem` once no effect is left
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderEffect {
+    /// Multiplied into the drawn color, e.g. gray to dim an unpowered building
+    pub tint: Option<Color>,
+    pub flash: Option<Flash>,
+    /// Outline drawn around shapes, e.g. for the selected entity
+    pub outline: Option<Color>,
+}
+
+impl Component for RenderEffect {
+    fn validate(&self) -> bool {
+        self.flash.is_none_or(|flash| flash.duration > 0.0)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+impl RenderEffect {
+    pub fn tinted(color: Color) -> Self {
+        Self { tint: Some(color), ..Self::default() }
+    }
+
+    pub fn flashing(color: Color, seconds: f32) -> Self {
+        Self { flash: Some(Flash { color, remaining: seconds, duration: seconds }), ..Self::default() }
+    }
+
+    pub fn outlined(color: Color) -> Self {
+        Self { outline: Some(color), ..Self::default() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tint.is_none() && self.flash.is_none() && self.outline.is_none()
+    }
+
+    /// `base` with the tint multiplied in and the flash blended over it
+    pub fn color(&self, base: Color) -> Color {
+        let mut color = match self.tint {
+            Some(tint) => Color::new(base.r * tint.r, base.g * tint.g, base.b * tint.b, base.a * tint.a),
+            None => base,
+        };
+        if let Some(flash) = self.flash {
+            let t = flash.strength();
+            color = Color::new(
+                color.r + (flash.color.r - color.r) * t,
+                color.g + (flash.color.g - color.g) * t,
+                color.b + (flash.color.b - color.b) * t,
+                color.a,
+            );
+        }
+        color
+    }
+
+    /// Encode the effects into a sprite or shape command; other commands pass through unchanged
+    pub fn apply(&self, command: RenderCommand) -> RenderCommand {
+        match command {
+            RenderCommand::DrawSprite { texture_id, transform, size, color, z_order, uv_rect } => RenderCommand::DrawSprite {
+                texture_id,
+                transform,
+                size,
+                color: self.color(color),
+                z_order,
+                uv_rect,
+            },
+            RenderCommand::DrawShape { shape_type, transform, fill, stroke, z_order } => RenderCommand::DrawShape {
+                shape_type,
+                transform,
+                fill: match fill {
+                    FillStyle::Solid(color) => FillStyle::Solid(self.color(color)),
+                    FillStyle::None => FillStyle::None,
+                },
+                stroke: self.outline.map(|color| StrokeStyle::new(color, OUTLINE_WIDTH)).or(stroke),
+                z_order,
+            },
+            other => other,
+        }
+    }
+
+    /// Change an entity's effects, adding the component when missing and removing it once empty
+    pub fn modify(world: &mut World, entity: Entity, change: impl FnOnce(&mut RenderEffect)) -> Result<(), InvalidComponent> {
+        let mut effect = world.get_component::<RenderEffect>(entity).map(|effect| effect.clone()).unwrap_or_default();
+        change(&mut effect);
+        if effect.is_empty() {
+            world.remove_component::<RenderEffect>(entity);
+            Ok(())
+        } else {
+            world.add_component(entity, effect)
+        }
+    }
+}
+
+/// System that fades timed flashes and drops effects with nothing left to draw
+pub struct RenderEffectSystem;
+
+impl RenderEffectSystem {
+    pub fn update(world: &mut World, delta_seconds: f32) {
+        for entity in world.entities_with_components(&[TypeId::of::<RenderEffect>()]) {
+            let empty = {
+                let Some(mut effect) = world.get_component_mut::<RenderEffect>(entity) else { continue };
+                if let Some(flash) = &mut effect.flash {
+                    flash.remaining -= delta_seconds.max(0.0);
+                    if flash.remaining <= 0.0 {
+                        effect.flash = None;
+                    }
+                }
+                effect.is_empty()
+            };
+            if empty {
+                world.remove_component::<RenderEffect>(entity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::{ShapeType, Transform2d};
+
+    #[test]
+    fn test_effects_encode_into_commands_and_decay() {
+        let mut world = World::new();
+        let entity = world.spawn((RenderEffect::tinted(Color::new(0.5, 0.5, 0.5, 1.0)),)).unwrap();
+        RenderEffect::modify(&mut world, entity, |effect| {
+            effect.flash = RenderEffect::flashing(Color::red(), 1.0).flash;
+            effect.outline = Some(SELECTION_OUTLINE);
+        }).unwrap();
+
+        let shape = RenderCommand::DrawShape {
+            shape_type: ShapeType::Rectangle { width: 10.0, height: 10.0 },
+            transform: Transform2d::identity(),
+            fill: FillStyle::Solid(Color::white()),
+            stroke: None,
+            z_order: 1,
+        };
+        let effect = world.get_component::<RenderEffect>(entity).unwrap().clone();
+        let RenderCommand::DrawShape { fill: FillStyle::Solid(color), stroke, .. } = effect.apply(shape.clone()) else { panic!("Shapes stay shapes") };
+        assert_eq!(color, Color::red());
+        assert_eq!(stroke, Some(StrokeStyle::new(SELECTION_OUTLINE, OUTLINE_WIDTH)));
+
+        // Half way through the flash the tinted color shows through
+        RenderEffectSystem::update(&mut world, 0.5);
+        let effect = world.get_component::<RenderEffect>(entity).unwrap().clone();
+        assert_eq!(effect.color(Color::white()), Color::new(0.75, 0.25, 0.25, 1.0));
+        RenderEffectSystem::update(&mut world, 0.5);
+        assert_eq!(world.get_component::<RenderEffect>(entity).unwrap().flash, None);
+
+        // Clearing the last effect removes the component
+        RenderEffect::modify(&mut world, entity, |effect| *effect = RenderEffect::default()).unwrap();
+        assert!(!world.has_component::<RenderEffect>(entity));
+    }
+}
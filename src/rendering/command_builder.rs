@@ -0,0 +1,409 @@
+/// Validated render commands: builders for the draw commands gameplay code makes most, a `validate` check every
+/// command can be put through, reusing the sprite, shape and transform components' own checks, and counters of the
+/// commands the rendering manager turned away
+use super::RenderCommand;
+use crate::core::math::shape2d::Shape2d;
+use crate::core::math::sprite2d::Sprite2d;
+use crate::core::math::transform2d_component::Transform2dComponent;
+use crate::core::math::{Color, FillStyle, ShapeType, StrokeStyle, Transform2d, Vector2d};
+use crate::ecs::Component;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+static REJECTED_COMMANDS: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+
+/// Why a render command can't be drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidRenderCommand {
+    /// Variant name, e.g. "DrawSprite"
+    pub command: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidRenderCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {}: {}", self.command, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidRenderCommand {}
+
+fn positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+fn finite(point: Vector2d) -> bool {
+    point.x.is_finite() && point.y.is_finite()
+}
+
+fn tuple_color(color: (f32, f32, f32, f32)) -> Color {
+    Color::new(color.0, color.1, color.2, color.3)
+}
+
+impl RenderCommand {
+    /// Variant name, for errors and the rejection counters
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderCommand::Clear { .. } => "Clear",
+            RenderCommand::SetViewport { .. } => "SetViewport",
+            RenderCommand::DrawGrid { .. } => "DrawGrid",
+            RenderCommand::DrawSprite { .. } => "DrawSprite",
+            RenderCommand::DrawShape { .. } => "DrawShape",
+            RenderCommand::DrawGhost { .. } => "DrawGhost",
+            RenderCommand::DrawTilemapLayer { .. } => "DrawTilemapLayer",
+            RenderCommand::DrawLineStrip { .. } => "DrawLineStrip",
+            RenderCommand::DrawNinePatch { .. } => "DrawNinePatch",
+            RenderCommand::DrawText { .. } => "DrawText",
+        }
+    }
+
+    /// Check the command only holds values a client can draw: finite transforms, positive sizes and colors
+    /// within 0.0 to 1.0
+    pub fn validate(&self) -> Result<(), InvalidRenderCommand> {
+        let problem = match self {
+            RenderCommand::Clear { r, g, b, a } => (!Color::new(*r, *g, *b, *a).is_valid()).then_some("color is out of range"),
+            RenderCommand::SetViewport { width, height, device_pixel_ratio, view_transform } => {
+                if *width == 0 || *height == 0 || !positive(*device_pixel_ratio) {
+                    Some("size and device pixel ratio must be positive")
+                } else {
+                    Self::check_transform(view_transform)
+                }
+            }
+            RenderCommand::DrawGrid { width, height, cell_size, line_color, background_color } => {
+                if *width == 0 || *height == 0 || !positive(*cell_size) {
+                    Some("grid and cell sizes must be positive")
+                } else if !tuple_color(*line_color).is_valid() || !tuple_color(*background_color).is_valid() {
+                    Some("color is out of range")
+                } else {
+                    None
+                }
+            }
+            RenderCommand::DrawSprite { texture_id, transform, size, color, uv_rect, .. } => {
+                let mut sprite = Sprite2d::with_color(texture_id.clone(), *size, *color);
+                sprite.set_uv_rect(uv_rect.0, uv_rect.1);
+                Self::check_texture(texture_id)
+                    .or_else(|| (!sprite.validate()).then_some("size must be positive and color and UVs in range"))
+                    .or_else(|| Self::check_transform(transform))
+            }
+            RenderCommand::DrawShape { shape_type, transform, fill, stroke, .. } => {
+                let mut shape = Shape2d::new(shape_type.clone(), Color::white());
+                shape.set_fill(fill.clone());
+                shape.set_stroke(stroke.clone());
+                (!shape.validate()).then_some("shape must have a positive size and colors in range")
+                    .or_else(|| Self::check_transform(transform))
+            }
+            RenderCommand::DrawGhost { texture_id, transform, size, .. } => Self::check_texture(texture_id)
+                .or_else(|| (!Sprite2d::new(texture_id.clone(), *size).validate()).then_some("size must be positive"))
+                .or_else(|| Self::check_transform(transform)),
+            RenderCommand::DrawTilemapLayer { atlas_id, atlas_columns, tile_size, columns, tiles, transform, .. } => {
+                if *atlas_columns == 0 || *columns == 0 || !positive(tile_size.x) || !positive(tile_size.y) {
+                    Some("atlas columns, columns and tile size must be positive")
+                } else if tiles.len() % *columns as usize != 0 {
+                    Some("tiles don't fill whole rows")
+                } else {
+                    Self::check_texture(atlas_id).or_else(|| Self::check_transform(transform))
+                }
+            }
+            RenderCommand::DrawLineStrip { points, color, width, .. } => {
+                if points.len() < 2 || !points.iter().all(|point| finite(*point)) {
+                    Some("needs at least two finite points")
+                } else if !positive(*width) || !color.is_valid() {
+                    Some("width must be positive and color in range")
+                } else {
+                    None
+                }
+            }
+            RenderCommand::DrawNinePatch { texture_id, transform, size, insets, color, .. } => {
+                let (left, top, right, bottom) = *insets;
+                if ![left, top, right, bottom].iter().all(|inset| inset.is_finite() && *inset >= 0.0) {
+                    Some("insets must be finite and not negative")
+                } else {
+                    Self::check_texture(texture_id)
+                        .or_else(|| (!Sprite2d::with_color(texture_id.clone(), *size, *color).validate()).then_some("size must be positive and color in range"))
+                        .or_else(|| Self::check_transform(transform))
+                }
+            }
+            RenderCommand::DrawText { position, color, size, .. } => {
+                (!finite(*position) || !positive(*size) || !color.is_valid()).then_some("needs a finite position, a positive size and a color in range")
+            }
+        };
+        match problem {
+            Some(reason) => Err(InvalidRenderCommand { command: self.name(), reason: reason.to_string() }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_transform(transform: &Transform2d) -> Option<&'static str> {
+        (!Transform2dComponent::from_transform(*transform).validate()).then_some("transform is not finite")
+    }
+
+    fn check_texture(texture_id: &str) -> Option<&'static str> {
+        texture_id.is_empty().then_some("texture ID is empty")
+    }
+
+    /// Build a sprite of `size` world units
+    pub fn sprite(texture_id: &str, size: Vector2d) -> SpriteBuilder {
+        SpriteBuilder {
+            texture_id: texture_id.to_string(),
+            size,
+            transform: Transform2d::identity(),
+            color: Color::white(),
+            uv_rect: (Vector2d::zero(), Vector2d::new(1.0, 1.0)),
+            z_order: 0,
+        }
+    }
+
+    /// Build a shape, filled white until told otherwise
+    pub fn shape(shape_type: ShapeType) -> ShapeBuilder {
+        ShapeBuilder { shape_type, transform: Transform2d::identity(), fill: FillStyle::Solid(Color::white()), stroke: None, z_order: 0 }
+    }
+
+    /// Build a black line of text `size` pixels tall
+    pub fn text(text: &str, size: f32) -> TextBuilder {
+        TextBuilder { text: text.to_string(), size, position: Vector2d::zero(), color: Color::black(), z_order: 0 }
+    }
+
+    /// Build an open, one pixel wide black line through `points`
+    pub fn line_strip(points: Vec<Vector2d>) -> LineStripBuilder {
+        LineStripBuilder { points, color: Color::black(), width: 1.0, closed: false, z_order: 0 }
+    }
+
+    /// Count a command the rendering manager turned away
+    pub fn record_rejection(&self) {
+        if let Ok(mut rejected) = REJECTED_COMMANDS.lock() {
+            *rejected.entry(self.name()).or_insert(0) += 1;
+        }
+    }
+}
+
+/// Commands rejected so far by variant name, for /metrics
+pub fn rejected_commands() -> BTreeMap<&'static str, u64> {
+    REJECTED_COMMANDS.lock().map(|rejected| rejected.clone()).unwrap_or_default()
+}
+
+/// Builder of a `DrawSprite` command
+#[derive(Debug, Clone)]
+pub struct SpriteBuilder {
+    texture_id: String,
+    size: Vector2d,
+    transform: Transform2d,
+    color: Color,
+    uv_rect: (Vector2d, Vector2d),
+    z_order: i32,
+}
+
+impl SpriteBuilder {
+    /// Center the sprite on a point
+    pub fn at(self, position: Vector2d) -> Self {
+        self.transform(Transform2d::translation(position))
+    }
+
+    pub fn transform(mut self, transform: Transform2d) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Draw only part of the texture, e.g. one cell of an atlas
+    pub fn uv_rect(mut self, min: Vector2d, max: Vector2d) -> Self {
+        self.uv_rect = (min, max);
+        self
+    }
+
+    pub fn z_order(mut self, z_order: i32) -> Self {
+        self.z_order = z_order;
+        self
+    }
+
+    pub fn build(self) -> Result<RenderCommand, InvalidRenderCommand> {
+        let command = RenderCommand::DrawSprite {
+            texture_id: self.texture_id,
+            transform: self.transform,
+            size: self.size,
+            color: self.color,
+            z_order: self.z_order,
+            uv_rect: self.uv_rect,
+        };
+        command.validate().map(|_| command)
+    }
+}
+
+/// Builder of a `DrawShape` command
+#[derive(Debug, Clone)]
+pub struct ShapeBuilder {
+    shape_type: ShapeType,
+    transform: Transform2d,
+    fill: FillStyle,
+    stroke: Option<StrokeStyle>,
+    z_order: i32,
+}
+
+impl ShapeBuilder {
+    /// Center the shape on a point
+    pub fn at(self, position: Vector2d) -> Self {
+        self.transform(Transform2d::translation(position))
+    }
+
+    pub fn transform(mut self, transform: Transform2d) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn fill(mut self, color: Color) -> Self {
+        self.fill = FillStyle::Solid(color);
+        self
+    }
+
+    /// Draw the outline only
+    pub fn no_fill(mut self) -> Self {
+        self.fill = FillStyle::None;
+        self
+    }
+
+    pub fn stroke(mut self, color: Color, width: f32) -> Self {
+        self.stroke = Some(StrokeStyle::new(color, width));
+        self
+    }
+
+    pub fn z_order(mut self, z_order: i32) -> Self {
+        self.z_order = z_order;
+        self
+    }
+
+    pub fn build(self) -> Result<RenderCommand, InvalidRenderCommand> {
+        let command = RenderCommand::DrawShape {
+            shape_type: self.shape_type,
+            transform: self.transform,
+            fill: self.fill,
+            stroke: self.stroke,
+            z_order: self.z_order,
+        };
+        command.validate().map(|_| command)
+    }
+}
+
+/// Builder of a `DrawText` command
+#[derive(Debug, Clone)]
+pub struct TextBuilder {
+    text: String,
+    size: f32,
+    position: Vector2d,
+    color: Color,
+    z_order: i32,
+}
+
+impl TextBuilder {
+    /// Center the text on a point
+    pub fn at(mut self, position: Vector2d) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn z_order(mut self, z_order: i32) -> Self {
+        self.z_order = z_order;
+        self
+    }
+
+    pub fn build(self) -> Result<RenderCommand, InvalidRenderCommand> {
+        let command = RenderCommand::DrawText {
+            text: self.text,
+            position: self.position,
+            color: self.color,
+            size: self.size,
+            z_order: self.z_order,
+        };
+        command.validate().map(|_| command)
+    }
+}
+
+/// Builder of a `DrawLineStrip` command
+#[derive(Debug, Clone)]
+pub struct LineStripBuilder {
+    points: Vec<Vector2d>,
+    color: Color,
+    width: f32,
+    closed: bool,
+    z_order: i32,
+}
+
+impl LineStripBuilder {
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Join the last point back to the first
+    pub fn closed(mut self) -> Self {
+        self.closed = true;
+        self
+    }
+
+    pub fn z_order(mut self, z_order: i32) -> Self {
+        self.z_order = z_order;
+        self
+    }
+
+    pub fn build(self) -> Result<RenderCommand, InvalidRenderCommand> {
+        let command = RenderCommand::DrawLineStrip {
+            points: self.points,
+            color: self.color,
+            width: self.width,
+            closed: self.closed,
+            z_order: self.z_order,
+        };
+        command.validate().map(|_| command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::rendering_manager::RenderingManager;
+    use crate::rendering::HeadlessRenderingDevice;
+
+    #[test]
+    fn test_builders_reject_invalid_commands() {
+        let sprite = RenderCommand::sprite("house", Vector2d::new(32.0, 32.0)).at(Vector2d::new(16.0, 16.0)).z_order(2).build().unwrap();
+        assert!(matches!(sprite, RenderCommand::DrawSprite { z_order: 2, .. }));
+        let error = RenderCommand::sprite("house", Vector2d::new(-1.0, 32.0)).build().unwrap_err();
+        assert_eq!(error.to_string(), "Invalid DrawSprite: size must be positive and color and UVs in range");
+
+        let circle = ShapeType::Circle { radius: 4.0 };
+        assert!(RenderCommand::shape(circle.clone()).fill(Color::red()).stroke(Color::black(), 1.0).build().is_ok());
+        assert_eq!(RenderCommand::shape(circle.clone()).at(Vector2d::new(f32::NAN, 0.0)).build().unwrap_err().reason, "transform is not finite");
+        assert!(RenderCommand::shape(circle).fill(Color::new(1.5, 0.0, 0.0, 1.0)).build().is_err());
+        assert!(RenderCommand::text("+10", 0.0).build().is_err());
+        assert!(RenderCommand::line_strip(vec![Vector2d::zero()]).build().is_err());
+        assert!(RenderCommand::line_strip(vec![Vector2d::zero(), Vector2d::new(4.0, 0.0)]).closed().width(2.0).build().is_ok());
+    }
+
+    #[test]
+    fn test_manager_rejects_invalid_commands_in_debug_mode() {
+        let mut manager = RenderingManager::new(Box::new(HeadlessRenderingDevice::new(8, 8).unwrap()));
+        manager.initialize().unwrap();
+        manager.set_validate_commands(true);
+        let before = rejected_commands().get("DrawText").copied().unwrap_or(0);
+        let text = RenderCommand::DrawText { text: "x".to_string(), position: Vector2d::zero(), color: Color::black(), size: f32::NAN, z_order: 0 };
+        let error = manager.execute_command(text.clone()).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid DrawText: needs a finite position") && error.contains("size: NaN"), "{}", error);
+        assert!(rejected_commands()["DrawText"] > before);
+
+        manager.set_validate_commands(false);
+        assert!(manager.execute_command(text).is_ok());
+    }
+}
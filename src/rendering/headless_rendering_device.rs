@@ -0,0 +1,336 @@
+use std::error::Error;
+use std::f32::consts::PI;
+use super::{RenderingDevice, RenderCommand, RenderResult};
+use super::image_buffer::ImageBuffer;
+use crate::core::math::{Color, FillStyle, ShapeType, Transform2d, Vector2d};
+use crate::core::math::projection::Projection;
+
+/// Segments used to approximate circles
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Software rendering device that rasterizes commands into an in-memory frame
+/// Used for screenshots and golden-image tests where no browser is available
+pub struct HeadlessRenderingDevice {
+    frame: ImageBuffer,
+    view_transform: Transform2d,
+    is_initialized: bool,
+}
+
+impl HeadlessRenderingDevice {
+    /// Create a device with a frame of the given size in pixels
+    pub fn new(width: u32, height: u32) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            frame: ImageBuffer::new(width, height)?,
+            view_transform: Transform2d::identity(),
+            is_initialized: false,
+        })
+    }
+
+    /// The frame rendered so far
+    pub fn frame(&self) -> &ImageBuffer {
+        &self.frame
+    }
+
+    /// Fill a polygon given in local coordinates, using the even-odd rule at pixel centers
+    fn fill_polygon(&mut self, transform: &Transform2d, points: &[Vector2d], color: Color) {
+        if points.len() < 3 || color.a <= 0.0 {
+            return;
+        }
+        let screen: Vec<Vector2d> = points.iter().map(|point| transform.transform_point(*point)).collect();
+
+        let min_x = screen.iter().map(|p| p.x).fold(f32::INFINITY, f32::min).floor().max(0.0) as i32;
+        let max_x = screen.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max).ceil().min(self.frame.width as f32) as i32;
+        let min_y = screen.iter().map(|p| p.y).fold(f32::INFINITY, f32::min).floor().max(0.0) as i32;
+        let max_y = screen.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max).ceil().min(self.frame.height as f32) as i32;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                if contains(&screen, Vector2d::new(x as f32 + 0.5, y as f32 + 0.5)) {
+                    self.frame.blend_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Fill an axis-aligned rectangle given by its top-left corner in local coordinates
+    fn fill_rect(&mut self, transform: &Transform2d, origin: Vector2d, size: Vector2d, color: Color) {
+        let corners = [
+            origin,
+            Vector2d::new(origin.x + size.x, origin.y),
+            Vector2d::new(origin.x + size.x, origin.y + size.y),
+            Vector2d::new(origin.x, origin.y + size.y),
+        ];
+        self.fill_polygon(transform, &corners, color);
+    }
+
+    /// Draw a line segment of the given width as a quad
+    fn stroke_segment(&mut self, transform: &Transform2d, start: Vector2d, end: Vector2d, width: f32, color: Color) {
+        let direction = end - start;
+        let length = direction.magnitude();
+        if length <= f32::EPSILON {
+            return;
+        }
+        // Slightly wider than requested so hairlines lying on pixel edges still cover the pixels beside them
+        let normal = Vector2d::new(-direction.y / length, direction.x / length) * (width.max(1.0) / 2.0 + 0.01);
+        self.fill_polygon(transform, &[start + normal, end + normal, end - normal, start - normal], color);
+    }
+
+    fn stroke_path(&mut self, transform: &Transform2d, points: &[Vector2d], width: f32, color: Color, closed: bool) {
+        for pair in points.windows(2) {
+            self.stroke_segment(transform, pair[0], pair[1], width, color);
+        }
+        if closed && points.len() > 2 {
+            self.stroke_segment(transform, points[points.len() - 1], points[0], width, color);
+        }
+    }
+
+    /// Outline of a shape in local coordinates; lines have no area and return `None`
+    fn shape_outline(shape_type: &ShapeType) -> Option<Vec<Vector2d>> {
+        match shape_type {
+            ShapeType::Circle { radius } => Some((0..CIRCLE_SEGMENTS)
+                .map(|i| {
+                    let angle = 2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32;
+                    Vector2d::new(angle.cos() * radius, angle.sin() * radius)
+                })
+                .collect()),
+            ShapeType::Rectangle { width, height } => Some(vec![
+                Vector2d::new(-width / 2.0, -height / 2.0),
+                Vector2d::new(width / 2.0, -height / 2.0),
+                Vector2d::new(width / 2.0, height / 2.0),
+                Vector2d::new(-width / 2.0, height / 2.0),
+            ]),
+            ShapeType::Triangle { vertex1, vertex2, vertex3 } => Some(vec![*vertex1, *vertex2, *vertex3]),
+            ShapeType::Polygon { vertices } => Some(vertices.clone()),
+            ShapeType::Line { .. } => None,
+        }
+    }
+
+    fn draw_command(&mut self, command: RenderCommand) -> Result<(), Box<dyn Error>> {
+        let view = self.view_transform;
+        match command {
+            RenderCommand::Clear { r, g, b, a } => self.frame.fill(Color::new(r, g, b, a)),
+            RenderCommand::SetViewport { width, height, device_pixel_ratio, view_transform } => {
+                let backing_width = (width as f32 * device_pixel_ratio).round() as u32;
+                let backing_height = (height as f32 * device_pixel_ratio).round() as u32;
+                if (backing_width, backing_height) != (self.frame.width, self.frame.height) {
+                    self.frame = ImageBuffer::new(backing_width, backing_height)?;
+                }
+                self.view_transform = view_transform;
+            }
+            RenderCommand::DrawGrid { width, height, cell_size, line_color, background_color } => {
+                let (r, g, b, a) = background_color;
+                self.frame.fill(Color::new(r, g, b, a));
+                let (r, g, b, a) = line_color;
+                let line_color = Color::new(r, g, b, a);
+                let (grid_width, grid_height) = (width as f32 * cell_size, height as f32 * cell_size);
+                for x in 0..=width {
+                    let x = x as f32 * cell_size;
+                    self.stroke_segment(&view, Vector2d::new(x, 0.0), Vector2d::new(x, grid_height), 1.0, line_color);
+                }
+                for y in 0..=height {
+                    let y = y as f32 * cell_size;
+                    self.stroke_segment(&view, Vector2d::new(0.0, y), Vector2d::new(grid_width, y), 1.0, line_color);
+                }
+            }
+            // Textures are not loaded headlessly; sprites render as their tinted placeholder rectangle
+            RenderCommand::DrawSprite { transform, size, color, .. } => {
+                self.fill_rect(&(view * transform), size * -0.5, size, color);
+            }
+            RenderCommand::DrawGhost { transform, size, valid, .. } => {
+                self.fill_rect(&(view * transform), size * -0.5, size, RenderCommand::ghost_color(valid));
+            }
+            RenderCommand::DrawShape { shape_type, transform, fill, stroke, .. } => {
+                let transform = view * transform;
+                if let ShapeType::Line { start, end, thickness } = &shape_type {
+                    let color = match (&stroke, &fill) {
+                        (Some(stroke), _) => stroke.color,
+                        (None, FillStyle::Solid(color)) => *color,
+                        (None, FillStyle::None) => Color::black(),
+                    };
+                    self.stroke_segment(&transform, *start, *end, *thickness, color);
+                    return Ok(());
+                }
+                let outline = Self::shape_outline(&shape_type).unwrap_or_default();
+                if let FillStyle::Solid(color) = fill {
+                    self.fill_polygon(&transform, &outline, color);
+                }
+                if let Some(stroke) = stroke {
+                    self.stroke_path(&transform, &outline, stroke.width, stroke.color, true);
+                }
+            }
+            RenderCommand::DrawTilemapLayer { tile_size, columns, tiles, transform, projection, .. } => {
+                let transform = view * transform;
+                for (cell, tile) in tiles.iter().enumerate() {
+                    let Some(tile) = tile else { continue };
+                    let origin = projection.cell_origin(cell as u32 % columns.max(1), cell as u32 / columns.max(1), tile_size);
+                    match projection {
+                        Projection::TopDown => self.fill_rect(&transform, origin, tile_size, placeholder_tile_color(*tile)),
+                        Projection::Isometric => {
+                            let (half_width, half_height) = (tile_size.x / 2.0, tile_size.y / 2.0);
+                            let diamond = [
+                                origin + Vector2d::new(half_width, 0.0),
+                                origin + Vector2d::new(tile_size.x, half_height),
+                                origin + Vector2d::new(half_width, tile_size.y),
+                                origin + Vector2d::new(0.0, half_height),
+                            ];
+                            self.fill_polygon(&transform, &diamond, placeholder_tile_color(*tile));
+                        }
+                    }
+                }
+            }
+            RenderCommand::DrawLineStrip { points, color, width, closed, .. } => {
+                self.stroke_path(&view, &points, width, color, closed);
+            }
+            RenderCommand::DrawNinePatch { transform, size, insets, color, .. } => {
+                let transform = view * transform;
+                let (left, top, right, bottom) = insets;
+                let fill = Color::new(color.r, color.g, color.b, color.a * 0.8);
+                self.fill_rect(&transform, Vector2d::zero(), size, fill);
+                let border = left.min(top).min(right).min(bottom).max(1.0);
+                let corners = [Vector2d::zero(), Vector2d::new(size.x, 0.0), size, Vector2d::new(0.0, size.y)];
+                self.stroke_path(&transform, &corners, border, Color::rgb(60.0 / 255.0, 60.0 / 255.0, 60.0 / 255.0), true);
+            }
+            // There are no fonts headlessly; each visible character is a block of the text's color
+            RenderCommand::DrawText { text, position, color, size, .. } => {
+                let advance = size * 0.6;
+                let characters = text.chars().count() as f32;
+                let left = position.x - advance * characters / 2.0;
+                for (index, character) in text.chars().enumerate() {
+                    if character.is_whitespace() {
+                        continue;
+                    }
+                    let origin = Vector2d::new(left + advance * index as f32 + advance * 0.1, position.y - size / 2.0);
+                    self.fill_rect(&view, origin, Vector2d::new(advance * 0.8, size), color);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RenderingDevice for HeadlessRenderingDevice {
+    fn initialize(&mut self) -> Result<(), Box<dyn Error>> {
+        self.is_initialized = true;
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: RenderCommand) -> Result<RenderResult, Box<dyn Error>> {
+        if !self.is_initialized {
+            return Err("Device not initialized".into());
+        }
+        self.draw_command(command)?;
+        Ok(RenderResult::Success)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.is_initialized
+    }
+
+    fn device_name(&self) -> &str {
+        "HeadlessRenderingDevice"
+    }
+
+    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        self.is_initialized = false;
+        Ok(())
+    }
+
+    fn capture_frame(&mut self) -> Result<ImageBuffer, Box<dyn Error>> {
+        Ok(self.frame.clone())
+    }
+}
+
+/// Even-odd point-in-polygon test
+fn contains(polygon: &[Vector2d], point: Vector2d) -> bool {
+    let mut inside = false;
+    let mut previous = polygon[polygon.len() - 1];
+    for current in polygon {
+        if (current.y > point.y) != (previous.y > point.y) {
+            let crossing = current.x + (point.y - current.y) / (previous.y - current.y) * (previous.x - current.x);
+            if point.x < crossing {
+                inside = !inside;
+            }
+        }
+        previous = *current;
+    }
+    inside
+}
+
+/// Same placeholder palette as the web client: hsl((tile * 47) % 360, 50%, 50%)
+fn placeholder_tile_color(tile: u32) -> Color {
+    let hue = ((tile * 47) % 360) as f32 / 60.0;
+    let (chroma, lightness) = (0.5, 0.5);
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    Color::rgb(r + m, g + m, b + m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::StrokeStyle;
+
+    fn device(width: u32, height: u32) -> HeadlessRenderingDevice {
+        let mut device = HeadlessRenderingDevice::new(width, height).unwrap();
+        device.initialize().unwrap();
+        device
+    }
+
+    #[test]
+    fn test_rasterizes_shapes_through_view_transform() {
+        let mut device = device(40, 40);
+        device.execute_command(RenderCommand::Clear { r: 1.0, g: 1.0, b: 1.0, a: 1.0 }).unwrap();
+        device.execute_command(RenderCommand::SetViewport {
+            width: 20,
+            height: 20,
+            device_pixel_ratio: 2.0,
+            view_transform: Transform2d::scale(2.0),
+        }).unwrap();
+        device.execute_command(RenderCommand::DrawShape {
+            shape_type: ShapeType::Rectangle { width: 4.0, height: 4.0 },
+            transform: Transform2d::translation(Vector2d::new(5.0, 5.0)),
+            fill: FillStyle::Solid(Color::red()),
+            stroke: Some(StrokeStyle::new(Color::blue(), 1.0)),
+            z_order: 0,
+        }).unwrap();
+
+        let frame = device.capture_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (40, 40));
+        // The 4x4 rectangle at (5, 5) covers pixels 6..14 after the 2x view scale
+        assert_eq!(frame.pixel(10, 10), Some([255, 0, 0, 255]));
+        assert_eq!(frame.pixel(6, 10), Some([0, 0, 255, 255]));
+        assert_eq!(frame.pixel(20, 20), Some([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_grid_and_line_strip() {
+        let mut device = device(64, 64);
+        device.execute_command(RenderCommand::DrawGrid {
+            width: 2,
+            height: 2,
+            cell_size: 32.0,
+            line_color: (0.0, 0.0, 0.0, 1.0),
+            background_color: (1.0, 1.0, 1.0, 1.0),
+        }).unwrap();
+        device.execute_command(RenderCommand::DrawLineStrip {
+            points: vec![Vector2d::new(8.0, 16.0), Vector2d::new(24.0, 16.0)],
+            color: Color::green(),
+            width: 2.0,
+            closed: false,
+            z_order: 1,
+        }).unwrap();
+
+        let frame = device.frame();
+        assert_eq!(frame.pixel(32, 10), Some([0, 0, 0, 255]));
+        assert_eq!(frame.pixel(10, 10), Some([255, 255, 255, 255]));
+        assert_eq!(frame.pixel(16, 16), Some([0, 255, 0, 255]));
+    }
+}
@@ -0,0 +1,286 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use crate::core::math::Color;
+
+/// Widest or tallest image accepted, e.g. from a client's frame capture
+pub const MAX_IMAGE_DIMENSION: u32 = 8192;
+
+/// RGBA8 image, e.g. a captured frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageBuffer {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major RGBA bytes, four per pixel
+    pub pixels: Vec<u8>,
+}
+
+impl ImageBuffer {
+    /// Create a transparent image
+    pub fn new(width: u32, height: u32) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            width,
+            height,
+            pixels: vec![0; Self::byte_len(width, height)?],
+        })
+    }
+
+    /// Wrap raw RGBA bytes, checking that they match the dimensions
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        let expected = Self::byte_len(width, height)?;
+        if pixels.len() != expected {
+            return Err(format!("Expected {} bytes for a {}x{} image, got {}", expected, width, height, pixels.len()).into());
+        }
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Decode base64 RGBA bytes, as uploaded by web clients from `getImageData`
+    pub fn from_rgba_base64(width: u32, height: u32, data: &str) -> Result<Self, Box<dyn Error>> {
+        Self::byte_len(width, height)?;
+        Self::from_rgba(width, height, decode_base64(data)?)
+    }
+
+    /// Bytes of RGBA pixels for the dimensions, which must be at most `MAX_IMAGE_DIMENSION` each
+    fn byte_len(width: u32, height: u32) -> Result<usize, Box<dyn Error>> {
+        if width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION {
+            return Err(format!("A {}x{} image is larger than {}x{}", width, height, MAX_IMAGE_DIMENSION, MAX_IMAGE_DIMENSION).into());
+        }
+        (width as usize).checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or_else(|| format!("A {}x{} image doesn't fit in memory", width, height).into())
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = ((y * self.width + x) * 4) as usize;
+        Some([self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]])
+    }
+
+    /// Fill the whole image with a color, replacing what was there
+    pub fn fill(&mut self, color: Color) {
+        let rgba = to_rgba8(color);
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+
+    /// Blend a color over a pixel ("source over"); pixels outside the image are ignored
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let i = ((y as u32 * self.width + x as u32) * 4) as usize;
+        let alpha = color.a.clamp(0.0, 1.0);
+        let source = [color.r, color.g, color.b];
+        for (channel, value) in source.iter().enumerate() {
+            let destination = self.pixels[i + channel] as f32 / 255.0;
+            let blended = value.clamp(0.0, 1.0) * alpha + destination * (1.0 - alpha);
+            self.pixels[i + channel] = (blended * 255.0).round() as u8;
+        }
+        let destination_alpha = self.pixels[i + 3] as f32 / 255.0;
+        self.pixels[i + 3] = ((alpha + destination_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+
+    /// Encode as PNG (uncompressed deflate, so no compression library is needed)
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(self.pixels.len() + self.height as usize);
+        for row in self.pixels.chunks_exact((self.width * 4).max(1) as usize) {
+            raw.push(0); // Filter type: none
+            raw.extend_from_slice(row);
+        }
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&self.width.to_be_bytes());
+        ihdr.extend_from_slice(&self.height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]); // 8-bit RGBA, no interlacing
+
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        write_chunk(&mut png, b"IHDR", &ihdr);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Decode a PNG as `to_png` writes it: 8-bit RGBA, stored deflate blocks and no row filters
+    pub fn from_png(png: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if png.len() < 8 || png[..8] != [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a] {
+            return Err("Not a PNG".into());
+        }
+        let (mut width, mut height, mut zlib) = (0, 0, Vec::new());
+        let mut rest = &png[8..];
+        while rest.len() >= 12 {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let data = rest.get(8..8 + len).ok_or("Truncated PNG chunk")?;
+            match &rest[4..8] {
+                b"IHDR" => {
+                    if data.len() != 13 || data[8..] != [8, 6, 0, 0, 0] {
+                        return Err("Only 8-bit RGBA PNGs without interlacing are supported".into());
+                    }
+                    width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+                    height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+                }
+                b"IDAT" => zlib.extend_from_slice(data),
+                _ => {}
+            }
+            rest = &rest[(12 + len).min(rest.len())..];
+        }
+
+        let raw = Self::inflate_stored(&zlib)?;
+        let row_len = (width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row_len * height as usize);
+        for row in raw.chunks(row_len + 1).take(height as usize) {
+            if row[0] != 0 || row.len() != row_len + 1 {
+                return Err("Only PNGs without row filters are supported".into());
+            }
+            pixels.extend_from_slice(&row[1..]);
+        }
+        Self::from_rgba(width, height, pixels)
+    }
+
+    /// Data of a zlib stream made of stored deflate blocks
+    fn inflate_stored(zlib: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut out = Vec::new();
+        let mut at = 2;
+        loop {
+            let header = *zlib.get(at).ok_or("Truncated deflate stream")?;
+            if header >> 1 != 0 {
+                return Err("Only stored deflate blocks are supported".into());
+            }
+            let len_bytes = zlib.get(at + 1..at + 3).ok_or("Truncated deflate block")?;
+            let len = u16::from_le_bytes([len_bytes[0], len_bytes[1]]) as usize;
+            out.extend_from_slice(zlib.get(at + 5..at + 5 + len).ok_or("Truncated deflate block")?);
+            at += 5 + len;
+            if header & 1 == 1 {
+                return Ok(out);
+            }
+        }
+    }
+
+    /// Write the image to a PNG file
+    pub fn save_png(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_png())?;
+        Ok(())
+    }
+}
+
+/// Convert a color to RGBA bytes
+pub fn to_rgba8(color: Color) -> [u8; 4] {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [byte(color.r), byte(color.g), byte(color.b), byte(color.a)]
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Zlib stream made of stored (uncompressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() { vec![&[]] } else { data.chunks(u16::MAX as usize).collect() };
+    for (index, block) in blocks.iter().enumerate() {
+        out.push(u8::from(index + 1 == blocks.len()));
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let value = |c: u8| -> Result<u32, Box<dyn Error>> {
+        Ok(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(format!("Invalid base64 character '{}'", c as char).into()),
+        } as u32)
+    };
+
+    let symbols: Vec<u8> = data.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=').collect();
+    let mut out = Vec::with_capacity(symbols.len() * 3 / 4);
+    for group in symbols.chunks(4) {
+        let mut bits = 0u32;
+        for (i, c) in group.iter().enumerate() {
+            bits |= value(*c)? << (18 - 6 * i);
+        }
+        let bytes = bits.to_be_bytes();
+        out.extend_from_slice(&bytes[1..group.len()]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_and_fill() {
+        let mut image = ImageBuffer::new(2, 2).unwrap();
+        image.fill(Color::white());
+        image.blend_pixel(1, 0, Color::new(1.0, 0.0, 0.0, 0.5));
+        image.blend_pixel(5, 5, Color::red());
+
+        assert_eq!(image.pixel(0, 0), Some([255, 255, 255, 255]));
+        assert_eq!(image.pixel(1, 0), Some([255, 128, 128, 255]));
+        assert_eq!(image.pixel(2, 0), None);
+    }
+
+    #[test]
+    fn test_png_encoding() {
+        let mut image = ImageBuffer::new(3, 2).unwrap();
+        image.fill(Color::blue());
+        let png = image.to_png();
+
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes([png[16], png[17], png[18], png[19]]), 3);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        // Known CRC of the empty IEND chunk
+        assert_eq!(&png[png.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn test_rgba_base64() {
+        let image = ImageBuffer::from_rgba_base64(1, 2, "/wAA/wAA//8=").unwrap();
+        assert_eq!(image.pixel(0, 0), Some([255, 0, 0, 255]));
+        assert_eq!(image.pixel(0, 1), Some([0, 0, 255, 255]));
+        assert!(ImageBuffer::from_rgba_base64(2, 2, "/wAA/w==").is_err());
+        assert!(ImageBuffer::from_rgba_base64(1, 1, "*wAA/w==").is_err());
+        assert!(ImageBuffer::new(MAX_IMAGE_DIMENSION + 1, 1).is_err());
+        assert!(ImageBuffer::from_rgba_base64(u32::MAX, u32::MAX, "").is_err());
+    }
+}
@@ -1,11 +1,20 @@
 use std::error::Error;
+use super::image_buffer::ImageBuffer;
 use crate::core::math::{Vector2d, Transform2d, Color, ShapeType, FillStyle, StrokeStyle};
+use crate::core::math::projection::Projection;
 
 /// Commands that can be sent to a rendering device
 #[derive(Debug, Clone)]
 pub enum RenderCommand {
     /// Clear the screen with a specified color
     Clear { r: f32, g: f32, b: f32, a: f32 },
+    /// Resize the client surface (CSS pixels) and set the view transform applied to later commands
+    SetViewport {
+        width: u32,
+        height: u32,
+        device_pixel_ratio: f32,
+        view_transform: Transform2d,
+    },
     /// Draw a grid with specified parameters
     DrawGrid {
         width: u32,
@@ -31,6 +40,64 @@ pub enum RenderCommand {
         stroke: Option<StrokeStyle>,
         z_order: i32,
     },
+    /// Draw a semi-transparent placement preview, tinted green when valid and red when not
+    DrawGhost {
+        texture_id: String,
+        transform: Transform2d,
+        size: Vector2d,
+        valid: bool,
+        z_order: i32,
+    },
+    /// Draw a layer of tiles from a texture atlas
+    /// `tiles` holds one atlas index per cell in row-major order, `None` for empty cells
+    /// `projection` places the cells: a square grid, or isometric diamonds of `tile_size` (see `Projection::cell_origin`)
+    DrawTilemapLayer {
+        atlas_id: String,
+        atlas_columns: u32,
+        tile_size: Vector2d,
+        columns: u32,
+        tiles: Vec<Option<u32>>,
+        transform: Transform2d,
+        projection: Projection,
+        z_order: i32,
+    },
+    /// Draw connected line segments through a list of points, e.g. paths and zone outlines
+    DrawLineStrip {
+        points: Vec<Vector2d>,
+        color: Color,
+        width: f32,
+        closed: bool,
+        z_order: i32,
+    },
+    /// Draw a texture scaled to `size` with its borders kept at their original size, for UI panels
+    /// `insets` are the border widths in texture pixels as (left, top, right, bottom)
+    DrawNinePatch {
+        texture_id: String,
+        transform: Transform2d,
+        size: Vector2d,
+        insets: (f32, f32, f32, f32),
+        color: Color,
+        z_order: i32,
+    },
+    /// Draw a line of text centered on `position`, `size` pixels tall
+    DrawText {
+        text: String,
+        position: Vector2d,
+        color: Color,
+        size: f32,
+        z_order: i32,
+    },
+}
+
+impl RenderCommand {
+    /// Tint used for ghost previews
+    pub fn ghost_color(valid: bool) -> Color {
+        if valid {
+            Color::new(0.0, 1.0, 0.0, 0.5)
+        } else {
+            Color::new(1.0, 0.0, 0.0, 0.5)
+        }
+    }
 }
 
 /// Result of a rendering operation
@@ -57,4 +124,9 @@ pub trait RenderingDevice: Send + Sync {
     
     /// Shutdown the rendering device
     fn shutdown(&mut self) -> Result<(), Box<dyn Error>>;
+    
+    /// Read back the last rendered frame, for screenshots and golden-image tests
+    fn capture_frame(&mut self) -> Result<ImageBuffer, Box<dyn Error>> {
+        Err(format!("{} does not support frame capture", self.device_name()).into())
+    }
 }
\ No newline at end of file
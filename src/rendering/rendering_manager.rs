@@ -1,12 +1,17 @@
 use std::sync::{Arc, Mutex, OnceLock};
 use std::error::Error;
-use super::{RenderingDevice, RenderCommand, RenderResult};
+use super::{ImageBuffer, RenderingDevice, RenderCommand, RenderResult};
+use crate::core::math::camera2d::Camera2d;
+use crate::core::math::projection::Projection;
+use crate::core::math::{Color, Transform2d, Vector2d};
 
 /// Global rendering manager that can be accessed from anywhere in the application
 /// This is not an ECS system - it's a globally accessible service
 pub struct RenderingManager {
     device: Arc<Mutex<Box<dyn RenderingDevice>>>,
     is_initialized: bool,
+    // Debug mode: commands are validated before the device sees them, and invalid ones are rejected
+    validate_commands: bool,
 }
 
 impl RenderingManager {
@@ -15,9 +20,15 @@ impl RenderingManager {
         Self {
             device: Arc::new(Mutex::new(device)),
             is_initialized: false,
+            validate_commands: cfg!(debug_assertions),
         }
     }
     
+    /// Turn command validation on or off; it's on in debug builds
+    pub fn set_validate_commands(&mut self, validate: bool) {
+        self.validate_commands = validate;
+    }
+    
     /// Initialize the rendering manager and its device
     pub fn initialize(&mut self) -> Result<(), Box<dyn Error>> {
         if self.is_initialized {
@@ -36,6 +47,12 @@ impl RenderingManager {
         if !self.is_initialized {
             return Err("Rendering manager not initialized".into());
         }
+        if self.validate_commands {
+            if let Err(error) = command.validate() {
+                command.record_rejection();
+                return Err(format!("{} in {:?}", error, command).into());
+            }
+        }
         
         let mut device = self.device.lock().map_err(|e| format!("Failed to lock device: {}", e))?;
         device.execute_command(command)
@@ -74,6 +91,68 @@ impl RenderingManager {
         self.execute_command(command)
     }
     
+    /// Render a tilemap layer of `columns` x (tiles / columns) cells from an atlas
+    pub fn draw_tilemap_layer(&self, atlas_id: &str, atlas_columns: u32, tile_size: Vector2d, columns: u32, tiles: Vec<Option<u32>>, z_order: i32) -> Result<RenderResult, Box<dyn Error>> {
+        if columns == 0 || !tiles.len().is_multiple_of(columns as usize) {
+            return Err(format!("{} tiles do not fill rows of {} columns", tiles.len(), columns).into());
+        }
+        
+        self.execute_command(RenderCommand::DrawTilemapLayer {
+            atlas_id: atlas_id.to_string(),
+            atlas_columns,
+            tile_size,
+            columns,
+            tiles,
+            transform: Transform2d::identity(),
+            projection: Projection::TopDown,
+            z_order,
+        })
+    }
+    
+    /// Render a path or, when `closed`, an outline through the given points
+    pub fn draw_line_strip(&self, points: Vec<Vector2d>, color: Color, width: f32, closed: bool, z_order: i32) -> Result<RenderResult, Box<dyn Error>> {
+        if points.len() < 2 {
+            return Err("A line strip needs at least two points".into());
+        }
+        
+        self.execute_command(RenderCommand::DrawLineStrip { points, color, width, closed, z_order })
+    }
+    
+    /// Render a scalable UI panel with its top-left corner at `position`
+    pub fn draw_nine_patch(&self, texture_id: &str, position: Vector2d, size: Vector2d, insets: (f32, f32, f32, f32), z_order: i32) -> Result<RenderResult, Box<dyn Error>> {
+        self.execute_command(RenderCommand::DrawNinePatch {
+            texture_id: texture_id.to_string(),
+            transform: Transform2d::translation(position),
+            size,
+            insets,
+            color: Color::white(),
+            z_order,
+        })
+    }
+    
+    /// Size the client surface to the camera viewport and fit content of the given size (in world units) to it
+    pub fn set_viewport(&self, camera: &Camera2d, content_width: f32, content_height: f32) -> Result<RenderResult, Box<dyn Error>> {
+        let (width, height) = camera.view_dimensions();
+        let command = RenderCommand::SetViewport {
+            width: width.round() as u32,
+            height: height.round() as u32,
+            device_pixel_ratio: camera.device_pixel_ratio(),
+            view_transform: camera.screen_transform(content_width, content_height),
+        };
+        
+        self.execute_command(command)
+    }
+    
+    /// Read back the device's last rendered frame
+    pub fn capture_frame(&self) -> Result<ImageBuffer, Box<dyn Error>> {
+        if !self.is_initialized {
+            return Err("Rendering manager not initialized".into());
+        }
+        
+        let mut device = self.device.lock().map_err(|e| format!("Failed to lock device: {}", e))?;
+        device.capture_frame()
+    }
+    
     /// Shutdown the rendering manager
     pub fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
         if !self.is_initialized {
@@ -104,6 +183,13 @@ pub fn initialize_global_rendering_manager(device: Box<dyn RenderingDevice>) ->
     Ok(())
 }
 
+/// Shut down the global rendering manager's device, e.g. on Ctrl+C
+pub fn shutdown_global_rendering_manager() -> Result<(), Box<dyn Error>> {
+    let manager_arc = get_global_rendering_manager()?;
+    let mut manager = manager_arc.lock().map_err(|e| format!("Failed to lock global manager: {}", e))?;
+    manager.shutdown()
+}
+
 /// Get a reference to the global rendering manager
 pub fn get_global_rendering_manager() -> Result<Arc<Mutex<RenderingManager>>, Box<dyn Error>> {
     GLOBAL_RENDERING_MANAGER.get()
@@ -118,6 +204,20 @@ pub fn render_global_grid(width: u32, height: u32, cell_size: f32) -> Result<Ren
     manager.render_grid(width, height, cell_size)
 }
 
+/// Convenience function to update the viewport using the global manager
+pub fn set_global_viewport(camera: &Camera2d, content_width: f32, content_height: f32) -> Result<RenderResult, Box<dyn Error>> {
+    let manager_arc = get_global_rendering_manager()?;
+    let manager = manager_arc.lock().map_err(|e| format!("Failed to lock global manager: {}", e))?;
+    manager.set_viewport(camera, content_width, content_height)
+}
+
+/// Convenience function to capture the last frame of the global manager's device
+pub fn capture_global_frame() -> Result<ImageBuffer, Box<dyn Error>> {
+    let manager_arc = get_global_rendering_manager()?;
+    let manager = manager_arc.lock().map_err(|e| format!("Failed to lock global manager: {}", e))?;
+    manager.capture_frame()
+}
+
 /// Convenience function to check if the global rendering system is ready
 pub fn is_global_rendering_ready() -> bool {
     if let Ok(manager_arc) = get_global_rendering_manager() {
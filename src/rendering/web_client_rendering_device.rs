@@ -1,7 +1,8 @@
 use std::error::Error;
 use std::sync::{Arc, Mutex};
-use super::{RenderingDevice, RenderCommand, RenderResult};
-use super::web_service_manager::WebServiceManager;
+use super::{ImageBuffer, RenderingDevice, RenderCommand, RenderResult};
+use super::web_service_manager::{ConnectionStatus, WebServiceManager};
+use crate::core::math::projection::Projection;
 
 /// Web client rendering device that communicates with a web client
 /// via the WebServiceManager to tell it what to draw and where
@@ -43,6 +44,15 @@ impl WebClientRenderingDevice {
             0
         }
     }
+    
+    /// Distinguish a device nobody connected to yet from one whose clients dropped out
+    pub fn connection_status(&self) -> ConnectionStatus {
+        if let Ok(service) = self.web_service.lock() {
+            service.connection_status()
+        } else {
+            ConnectionStatus::NeverConnected
+        }
+    }
 }
 
 impl RenderingDevice for WebClientRenderingDevice {
@@ -73,11 +83,79 @@ impl RenderingDevice for WebClientRenderingDevice {
             return Err("Web service is not running".into());
         }
         
+        // A clear starts a new frame, so replays to reconnecting clients start here
+        if matches!(command, RenderCommand::Clear { .. }) {
+            service.begin_frame();
+        }
+        
         // Convert RenderCommand to a JSON string for transmission to web client
-        let command_json = match command {
+        let command_json = Self::serialize_command(command);
+        
+        // Send the command to all connected web clients
+        service.send_render_command(&command_json)?;
+        
+        println!("Sent render command to web clients: {}", command_json);
+        Ok(RenderResult::Success)
+    }
+    
+    fn is_ready(&self) -> bool {
+        if !self.is_initialized {
+            return false;
+        }
+        
+        if let Ok(service) = self.web_service.lock() {
+            service.is_running() && service.client_count() > 0
+        } else {
+            false
+        }
+    }
+    
+    fn device_name(&self) -> &str {
+        &self.device_name
+    }
+    
+    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.is_initialized {
+            return Ok(());
+        }
+        
+        let mut service = self.web_service.lock()
+            .map_err(|e| format!("Failed to lock web service: {}", e))?;
+        
+        service.stop()?;
+        self.is_initialized = false;
+        
+        println!("WebClientRenderingDevice shut down successfully");
+        Ok(())
+    }
+    
+    /// Returns the latest canvas read back from a client and requests a fresh one,
+    /// so repeated captures follow the frames shown in the browser
+    fn capture_frame(&mut self) -> Result<ImageBuffer, Box<dyn Error>> {
+        let service = self.web_service.lock()
+            .map_err(|e| format!("Failed to lock web service: {}", e))?;
+        
+        service.request_frame_capture();
+        service.latest_capture()
+            .ok_or_else(|| "No client has captured a frame yet; a capture was requested".into())
+    }
+}
+
+impl WebClientRenderingDevice {
+    /// Convert a RenderCommand to the JSON string sent to web clients
+    pub fn serialize_command(command: RenderCommand) -> String {
+        match command {
             RenderCommand::Clear { r, g, b, a } => {
                 format!(r#"{{"type":"Clear","params":{{"r":{},"g":{},"b":{},"a":{}}}}}"#, r, g, b, a)
             }
+            RenderCommand::SetViewport { width, height, device_pixel_ratio, view_transform } => {
+                let matrix = view_transform.matrix();
+                format!(
+                    r#"{{"type":"SetViewport","params":{{"width":{},"height":{},"devicePixelRatio":{},"viewTransform":[{},{},{},{},{},{}]}}}}"#,
+                    width, height, device_pixel_ratio,
+                    matrix[0], matrix[1], matrix[2], matrix[3], matrix[4], matrix[5]
+                )
+            }
             RenderCommand::DrawGrid { width, height, cell_size, line_color, background_color } => {
                 format!(
                     r#"{{"type":"DrawGrid","params":{{"width":{},"height":{},"cellSize":{},"lineColor":[{},{},{},{}],"backgroundColor":[{},{},{},{}]}}}}"#,
@@ -106,6 +184,67 @@ impl RenderingDevice for WebClientRenderingDevice {
                     uv_min.x, uv_min.y, uv_max.x, uv_max.y
                 )
             }
+            RenderCommand::DrawGhost { texture_id, transform, size, valid, z_order } => {
+                let matrix = transform.matrix();
+                let color = RenderCommand::ghost_color(valid);
+                format!(
+                    r#"{{"type":"DrawGhost","params":{{"textureId":"{}","transform":[{},{},{},{},{},{}],"size":[{},{}],"color":[{},{},{},{}],"valid":{},"zOrder":{}}}}}"#,
+                    texture_id,
+                    matrix[0], matrix[1], matrix[2], matrix[3], matrix[4], matrix[5],
+                    size.x, size.y,
+                    color.r, color.g, color.b, color.a,
+                    valid,
+                    z_order
+                )
+            }
+            RenderCommand::DrawTilemapLayer { atlas_id, atlas_columns, tile_size, columns, tiles, transform, projection, z_order } => {
+                let matrix = transform.matrix();
+                let tiles_json: Vec<String> = tiles.iter()
+                    .map(|tile| tile.map_or("null".to_string(), |index| index.to_string()))
+                    .collect();
+                format!(
+                    r#"{{"type":"DrawTilemapLayer","params":{{"atlasId":"{}","atlasColumns":{},"tileSize":[{},{}],"columns":{},"tiles":[{}],"transform":[{},{},{},{},{},{}],"isometric":{},"zOrder":{}}}}}"#,
+                    atlas_id, atlas_columns,
+                    tile_size.x, tile_size.y,
+                    columns,
+                    tiles_json.join(","),
+                    matrix[0], matrix[1], matrix[2], matrix[3], matrix[4], matrix[5],
+                    projection == Projection::Isometric,
+                    z_order
+                )
+            }
+            RenderCommand::DrawLineStrip { points, color, width, closed, z_order } => {
+                let points_json: Vec<String> = points.iter()
+                    .map(|point| format!("[{},{}]", point.x, point.y))
+                    .collect();
+                format!(
+                    r#"{{"type":"DrawLineStrip","params":{{"points":[{}],"color":[{},{},{},{}],"width":{},"closed":{},"zOrder":{}}}}}"#,
+                    points_json.join(","),
+                    color.r, color.g, color.b, color.a,
+                    width, closed, z_order
+                )
+            }
+            RenderCommand::DrawNinePatch { texture_id, transform, size, insets, color, z_order } => {
+                let matrix = transform.matrix();
+                format!(
+                    r#"{{"type":"DrawNinePatch","params":{{"textureId":"{}","transform":[{},{},{},{},{},{}],"size":[{},{}],"insets":[{},{},{},{}],"color":[{},{},{},{}],"zOrder":{}}}}}"#,
+                    texture_id,
+                    matrix[0], matrix[1], matrix[2], matrix[3], matrix[4], matrix[5],
+                    size.x, size.y,
+                    insets.0, insets.1, insets.2, insets.3,
+                    color.r, color.g, color.b, color.a,
+                    z_order
+                )
+            }
+            RenderCommand::DrawText { text, position, color, size, z_order } => {
+                format!(
+                    r#"{{"type":"DrawText","params":{{"text":{},"position":[{},{}],"color":[{},{},{},{}],"size":{},"zOrder":{}}}}}"#,
+                    serde_json::Value::String(text),
+                    position.x, position.y,
+                    color.r, color.g, color.b, color.a,
+                    size, z_order
+                )
+            }
             RenderCommand::DrawShape { 
                 shape_type, 
                 transform, 
@@ -121,7 +260,7 @@ impl RenderingDevice for WebClientRenderingDevice {
                 } else {
                     "null".to_string()
                 };
-                
+            
                 format!(
                     r#"{{"type":"DrawShape","params":{{"shapeType":{},"transform":[{},{},{},{},{},{}],"fill":{},"stroke":{},"zOrder":{}}}}}"#,
                     shape_json,
@@ -131,48 +270,9 @@ impl RenderingDevice for WebClientRenderingDevice {
                     z_order
                 )
             }
-        };
-        
-        // Send the command to all connected web clients
-        service.send_render_command(&command_json)?;
-        
-        println!("Sent render command to web clients: {}", command_json);
-        Ok(RenderResult::Success)
-    }
-    
-    fn is_ready(&self) -> bool {
-        if !self.is_initialized {
-            return false;
-        }
-        
-        if let Ok(service) = self.web_service.lock() {
-            service.is_running() && service.client_count() > 0
-        } else {
-            false
         }
     }
     
-    fn device_name(&self) -> &str {
-        &self.device_name
-    }
-    
-    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
-        if !self.is_initialized {
-            return Ok(());
-        }
-        
-        let mut service = self.web_service.lock()
-            .map_err(|e| format!("Failed to lock web service: {}", e))?;
-        
-        service.stop()?;
-        self.is_initialized = false;
-        
-        println!("WebClientRenderingDevice shut down successfully");
-        Ok(())
-    }
-}
-
-impl WebClientRenderingDevice {
     /// Helper function to serialize ShapeType to JSON
     fn serialize_shape_type(shape_type: &crate::core::math::ShapeType) -> String {
         use crate::core::math::ShapeType;
@@ -230,6 +330,7 @@ mod tests {
         assert_eq!(device.device_name(), "WebClientRenderingDevice");
         assert!(!device.is_ready());
         assert_eq!(device.client_count(), 0);
+        assert_eq!(device.connection_status(), ConnectionStatus::NeverConnected);
     }
     
     #[test]
@@ -246,4 +347,58 @@ mod tests {
         // Should be able to shutdown after initialization
         assert!(device.shutdown().is_ok());
     }
+    
+    #[test]
+    fn test_serialize_extended_commands() {
+        use crate::core::math::{Color, Transform2d, Vector2d};
+        
+        let parse = |command: RenderCommand| -> serde_json::Value {
+            serde_json::from_str(&WebClientRenderingDevice::serialize_command(command)).unwrap()
+        };
+        
+        let tilemap = parse(RenderCommand::DrawTilemapLayer {
+            atlas_id: "terrain".to_string(),
+            atlas_columns: 8,
+            tile_size: Vector2d::new(32.0, 32.0),
+            columns: 2,
+            tiles: vec![Some(0), None, Some(9), Some(1)],
+            transform: Transform2d::identity(),
+            projection: Projection::Isometric,
+            z_order: 0,
+        });
+        assert_eq!(tilemap["type"], "DrawTilemapLayer");
+        assert_eq!(tilemap["params"]["tiles"], serde_json::json!([0, null, 9, 1]));
+        assert_eq!(tilemap["params"]["isometric"], true);
+        
+        let strip = parse(RenderCommand::DrawLineStrip {
+            points: vec![Vector2d::new(0.0, 0.0), Vector2d::new(10.0, 5.0)],
+            color: Color::yellow(),
+            width: 2.0,
+            closed: true,
+            z_order: 3,
+        });
+        assert_eq!(strip["params"]["points"], serde_json::json!([[0, 0], [10, 5]]));
+        assert_eq!(strip["params"]["closed"], true);
+        
+        let panel = parse(RenderCommand::DrawNinePatch {
+            texture_id: "panel".to_string(),
+            transform: Transform2d::translation(Vector2d::new(4.0, 8.0)),
+            size: Vector2d::new(200.0, 120.0),
+            insets: (6.0, 6.0, 6.0, 10.0),
+            color: Color::white(),
+            z_order: 50,
+        });
+        assert_eq!(panel["params"]["insets"], serde_json::json!([6, 6, 6, 10]));
+        assert_eq!(panel["params"]["transform"][4], 4);
+        
+        let text = parse(RenderCommand::DrawText {
+            text: "No \"power\"!".to_string(),
+            position: Vector2d::new(20.0, 12.0),
+            color: Color::red(),
+            size: 16.0,
+            z_order: 5,
+        });
+        assert_eq!(text["params"]["text"], "No \"power\"!");
+        assert_eq!(text["params"]["position"], serde_json::json!([20, 12]));
+    }
 }
\ No newline at end of file
@@ -1,15 +1,49 @@
-use tiny_http::Server;
+use tiny_http::{Method, Request, Response, Server};
+use std::io::{self, Cursor, Read};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
 use std::error::Error;
-use std::thread;
-use std::time::Duration;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
 use serde::{Serialize, Deserialize};
+use super::image_buffer::ImageBuffer;
+
+/// Render commands kept for replaying the current frame to (re)connecting clients
+pub const MAX_RETAINED_COMMANDS: usize = 1024;
+/// How often connected clients are pinged
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+/// Clients silent for longer than this are dropped
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Disconnected clients can resume their session for this long before they are removed from the registry
+pub const RECONNECT_WINDOW: Duration = Duration::from_secs(60);
+/// Threads reading requests off the socket, and as many again writing responses back
+pub const REQUEST_WORKERS: usize = 4;
+/// Requests waiting for the game thread before new ones are turned away with 503
+pub const REQUEST_QUEUE_CAPACITY: usize = 64;
+/// Largest request body read; bigger ones are answered with 413
+pub const MAX_BODY_BYTES: u64 = 16 * 1024 * 1024;
+/// Time a client gets to send its whole request body before it is answered with 408
+pub const BODY_READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Request bodies read at once, each on a thread of its own; requests with a body past these get 503
+pub const MAX_BODY_READERS: usize = 32;
+// How often idle request workers check whether the pool is stopping
+const WORKER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Ports after a taken one tried before letting the OS pick a free port
+pub const PORT_FALLBACK_ATTEMPTS: u16 = 10;
 
 /// Message sent from the web client to the server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientMessage {
     Connect { client_id: String },
+    /// A client that lost its connection (e.g. a refreshed tab) asks to resume its session
+    Reconnect { client_id: String },
+    Disconnect { client_id: String },
+    /// Answer to a heartbeat `Ping`
+    Pong { client_id: String, nonce: u64 },
+    /// Answer to `CaptureFrame`: the canvas pixels as base64 RGBA bytes
+    FrameCaptured { client_id: String, width: u32, height: u32, rgba: String },
     Acknowledge { command_id: String },
     Error { message: String },
 }
@@ -19,25 +53,479 @@ pub enum ClientMessage {
 pub enum ServerMessage {
     Welcome { client_id: String },
     RenderCommand { command_id: String, command: String },
+    /// Every command of the current frame, sent to a client that just (re)connected
+    FullFrame { commands: Vec<String> },
+    /// Heartbeat; the client answers with a `Pong` carrying the same nonce
+    Ping { nonce: u64 },
+    /// Ask the client to read back its canvas and answer with `FrameCaptured`
+    CaptureFrame,
+    /// A gameplay event of a type the client subscribed to
+    Event { topic: String, payload: serde_json::Value },
     Disconnect,
 }
 
+/// Whether a registered client currently has a live connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientState {
+    Connected,
+    Disconnected,
+}
+
+/// Connection status of a device's clients as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// No client has connected yet
+    NeverConnected,
+    /// At least one client is connected
+    Connected,
+    /// Clients were connected before but none are now, e.g. while a tab reloads
+    TemporarilyDisconnected,
+}
+
+/// Connection changes the game reacts to, e.g. by pausing while nobody is watching
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    ClientConnected { client_id: String },
+    ClientReconnected { client_id: String },
+    ClientDisconnected { client_id: String },
+    /// The last connected client went away
+    AllClientsDisconnected,
+}
+
 /// Status of a client connection
 #[derive(Debug, Clone)]
 pub struct ClientConnection {
     pub client_id: String,
-    pub connected_at: std::time::Instant,
-    pub last_activity: std::time::Instant,
+    pub connected_at: Instant,
+    pub last_activity: Instant,
+    pub state: ClientState,
+    /// Messages waiting to be delivered to this client
+    pub outbox: Vec<ServerMessage>,
+    /// Round-trip time measured by the last answered ping
+    pub rtt: Option<Duration>,
+    /// Unanswered ping: its nonce and when it was queued
+    pub pending_ping: Option<(u64, Instant)>,
+}
+
+impl ClientConnection {
+    /// Serializable snapshot for debugging endpoints
+    pub fn stats(&self, now: Instant) -> ClientStats {
+        ClientStats {
+            client_id: self.client_id.clone(),
+            connected: self.state == ClientState::Connected,
+            rtt_ms: self.rtt.map(|rtt| rtt.as_secs_f64() * 1000.0),
+            last_seen_ms_ago: now.saturating_duration_since(self.last_activity).as_millis() as u64,
+            connected_for_ms: now.saturating_duration_since(self.connected_at).as_millis() as u64,
+        }
+    }
+}
+
+/// Per-client connection health, as served by `/debug/clients`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClientStats {
+    pub client_id: String,
+    pub connected: bool,
+    pub rtt_ms: Option<f64>,
+    pub last_seen_ms_ago: u64,
+    pub connected_for_ms: u64,
+}
+
+/// Registered clients, their pending messages and the retained current frame
+#[derive(Debug, Default)]
+struct ClientRegistry {
+    clients: Vec<ClientConnection>,
+    events: Vec<ConnectionEvent>,
+    frame: Vec<String>,
+    /// Latest frame read back from a client's canvas
+    capture: Option<ImageBuffer>,
+    next_id: u64,
+    next_nonce: u64,
+}
+
+impl ClientRegistry {
+    fn register(&mut self, previous_id: Option<&str>) -> String {
+        let now = Instant::now();
+        let welcome = |client_id: &str, frame: &[String]| vec![
+            ServerMessage::Welcome { client_id: client_id.to_string() },
+            ServerMessage::FullFrame { commands: frame.to_vec() },
+        ];
+
+        if let Some(client) = previous_id.and_then(|id| self.clients.iter_mut().find(|client| client.client_id == id)) {
+            client.state = ClientState::Connected;
+            client.last_activity = now;
+            client.pending_ping = None;
+            client.outbox = welcome(&client.client_id, &self.frame);
+            self.events.push(ConnectionEvent::ClientReconnected { client_id: client.client_id.clone() });
+            return client.client_id.clone();
+        }
+
+        self.next_id += 1;
+        let client_id = format!("client_{}_{}", self.next_id, uuid::Uuid::new_v4().to_string());
+        self.clients.push(ClientConnection {
+            client_id: client_id.clone(),
+            connected_at: now,
+            last_activity: now,
+            state: ClientState::Connected,
+            outbox: welcome(&client_id, &self.frame),
+            rtt: None,
+            pending_ping: None,
+        });
+        self.events.push(ConnectionEvent::ClientConnected { client_id: client_id.clone() });
+        client_id
+    }
+
+    fn disconnect(&mut self, client_id: &str) -> bool {
+        let Some(client) = self.clients.iter_mut()
+            .find(|client| client.client_id == client_id && client.state == ClientState::Connected) else {
+            return false;
+        };
+        client.state = ClientState::Disconnected;
+        client.outbox.clear();
+        self.events.push(ConnectionEvent::ClientDisconnected { client_id: client_id.to_string() });
+        if self.connected_count() == 0 {
+            self.events.push(ConnectionEvent::AllClientsDisconnected);
+        }
+        true
+    }
+
+    /// Ping connected clients that are due, disconnect the ones that went silent
+    /// and forget the ones that stayed away past the reconnect window
+    fn heartbeat(&mut self, now: Instant) -> Vec<String> {
+        let stale: Vec<String> = self.clients.iter()
+            .filter(|client| client.state == ClientState::Connected)
+            .filter(|client| now.saturating_duration_since(client.last_activity) > CLIENT_TIMEOUT)
+            .map(|client| client.client_id.clone())
+            .collect();
+        for client_id in &stale {
+            self.disconnect(client_id);
+        }
+        self.clients.retain(|client| {
+            client.state == ClientState::Connected || now.saturating_duration_since(client.last_activity) <= RECONNECT_WINDOW
+        });
+
+        for client in self.clients.iter_mut().filter(|client| client.state == ClientState::Connected) {
+            let due = client.pending_ping
+                .is_none_or(|(_, sent)| now.saturating_duration_since(sent) >= HEARTBEAT_INTERVAL);
+            if due {
+                self.next_nonce += 1;
+                client.pending_ping = Some((self.next_nonce, now));
+                client.outbox.push(ServerMessage::Ping { nonce: self.next_nonce });
+            }
+        }
+        stale
+    }
+
+    fn record_pong(&mut self, client_id: &str, nonce: u64, now: Instant) -> Option<Duration> {
+        let client = self.clients.iter_mut().find(|client| client.client_id == client_id)?;
+        client.last_activity = now;
+        match client.pending_ping {
+            Some((pending, sent)) if pending == nonce => {
+                let rtt = now.saturating_duration_since(sent);
+                client.rtt = Some(rtt);
+                client.pending_ping = None;
+                Some(rtt)
+            }
+            // Late answers to an older ping only count as activity
+            _ => None,
+        }
+    }
+
+    fn connected_count(&self) -> usize {
+        self.clients.iter().filter(|client| client.state == ClientState::Connected).count()
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        if self.next_id == 0 {
+            ConnectionStatus::NeverConnected
+        } else if self.connected_count() > 0 {
+            ConnectionStatus::Connected
+        } else {
+            ConnectionStatus::TemporarilyDisconnected
+        }
+    }
+
+    fn send(&mut self, client_id: &str, message: ServerMessage) -> bool {
+        let Some(client) = self.clients.iter_mut().find(|client| client.client_id == client_id && client.state == ClientState::Connected) else {
+            return false;
+        };
+        client.outbox.push(message);
+        true
+    }
+
+    fn broadcast(&mut self, message: ServerMessage) {
+        for client in self.clients.iter_mut().filter(|client| client.state == ClientState::Connected) {
+            client.outbox.push(message.clone());
+        }
+    }
+}
+
+/// Fully buffered response, written back to the client by a worker thread
+pub type BufferedResponse = Response<Cursor<Vec<u8>>>;
+
+/// HTTP request whose body a worker already read, waiting to be answered by the game thread
+pub struct PendingRequest {
+    request: Request,
+    body: Cursor<Vec<u8>>,
+    received_at: SystemTime,
+    responses: Sender<(Request, BufferedResponse)>,
+}
+
+impl PendingRequest {
+    pub fn method(&self) -> &Method {
+        self.request.method()
+    }
+    
+    pub fn url(&self) -> &str {
+        self.request.url()
+    }
+    
+    /// Address of the connected client, when the socket reports one
+    pub fn remote_addr(&self) -> Option<std::net::SocketAddr> {
+        self.request.remote_addr().copied()
+    }
+    
+    /// Value of a request header, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.request.headers().iter()
+            .find(|header| header.field.as_str().as_str().eq_ignore_ascii_case(name))
+            .map(|header| header.value.as_str())
+    }
+    
+    /// When a worker took the request off the socket, for latency measurements
+    pub fn received_at(&self) -> SystemTime {
+        self.received_at
+    }
+    
+    /// The whole request body, without consuming it
+    pub fn body(&self) -> &[u8] {
+        self.body.get_ref()
+    }
+    
+    /// The request body, already in memory
+    pub fn as_reader(&mut self) -> &mut dyn Read {
+        &mut self.body
+    }
+    
+    /// Hand the response to a worker thread, so a slow client can't stall the caller
+    pub fn respond(self, response: BufferedResponse) -> io::Result<()> {
+        self.responses.send((self.request, response))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Request workers have stopped"))
+    }
+}
+
+/// Traffic counters of a request pool, updated by its worker threads
+#[derive(Debug, Default)]
+pub struct RequestPoolStats {
+    /// Requests handed to the game thread
+    pub accepted: AtomicU64,
+    /// Requests answered with 503 because the queue was full
+    pub rejected: AtomicU64,
+    /// Response body bytes written to clients
+    pub bytes_sent: AtomicU64,
+}
+
+/// Worker threads that accept requests, read their bodies and write responses,
+/// feeding the single game thread through a bounded queue
+pub struct RequestPool {
+    requests: Receiver<PendingRequest>,
+    workers: Vec<JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+    stats: Arc<RequestPoolStats>,
+}
+
+impl RequestPool {
+    /// Start `workers` reader and `workers` writer threads for a server
+    /// At most `capacity` requests wait for the game thread; further ones are answered with 503
+    pub fn start(server: Server, workers: usize, capacity: usize) -> Self {
+        let server = Arc::new(server);
+        let stopping = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(RequestPoolStats::default());
+        let body_readers = Arc::new(AtomicUsize::new(0));
+        let (request_tx, requests) = sync_channel(capacity);
+        let (response_tx, response_rx) = channel::<(Request, BufferedResponse)>();
+        let response_rx = Arc::new(Mutex::new(response_rx));
+        
+        let mut handles = Vec::new();
+        for _ in 0..workers.max(1) {
+            let (server, reader_stopping, reader_stats) = (server.clone(), stopping.clone(), stats.clone());
+            let (request_tx, response_tx, body_readers) = (request_tx.clone(), response_tx.clone(), body_readers.clone());
+            handles.push(thread::spawn(move || {
+                Self::read_requests(&server, &reader_stopping, &reader_stats, &body_readers, request_tx, response_tx)
+            }));
+            
+            let (response_rx, writer_stopping, writer_stats) = (response_rx.clone(), stopping.clone(), stats.clone());
+            handles.push(thread::spawn(move || Self::write_responses(&response_rx, &writer_stopping, &writer_stats)));
+        }
+        
+        Self { requests, workers: handles, stopping, stats }
+    }
+    
+    /// Counters shared with the worker threads, readable while the pool runs
+    pub fn stats(&self) -> Arc<RequestPoolStats> {
+        self.stats.clone()
+    }
+    
+    /// Next request for the game thread, waiting at most `timeout`
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<PendingRequest> {
+        self.requests.recv_timeout(timeout).ok()
+    }
+    
+    /// Stop accepting requests, write the responses already queued and join every worker
+    pub fn stop(self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        drop(self.requests);
+        for worker in self.workers {
+            let _ = worker.join();
+        }
+    }
+    
+    fn read_requests(
+        server: &Server,
+        stopping: &AtomicBool,
+        stats: &Arc<RequestPoolStats>,
+        body_readers: &Arc<AtomicUsize>,
+        requests: SyncSender<PendingRequest>,
+        responses: Sender<(Request, BufferedResponse)>,
+    ) {
+        while !stopping.load(Ordering::SeqCst) {
+            let mut request = match server.recv_timeout(WORKER_POLL_INTERVAL) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(_) => return,
+            };
+            let received_at = SystemTime::now();
+            
+            // Requests without a body are queued right away
+            let chunked = request.headers().iter().any(|header| header.field.equiv("Transfer-Encoding"));
+            if request.body_length().map_or(!chunked, |length| length == 0) {
+                let pending = PendingRequest { request, body: Cursor::new(Vec::new()), received_at, responses: responses.clone() };
+                if !Self::enqueue(pending, &requests, stats) {
+                    return;
+                }
+                continue;
+            }
+            if request.body_length().is_some_and(|length| length as u64 > MAX_BODY_BYTES) {
+                let _ = request.respond(Response::from_string("Request body too large").with_status_code(413));
+                continue;
+            }
+            
+            // Bodies are read on threads of their own, so a slow upload never holds up this reader
+            if body_readers.fetch_add(1, Ordering::SeqCst) >= MAX_BODY_READERS {
+                body_readers.fetch_sub(1, Ordering::SeqCst);
+                stats.rejected.fetch_add(1, Ordering::Relaxed);
+                let _ = request.respond(Response::from_string("Server busy").with_status_code(503));
+                continue;
+            }
+            let (requests, responses, stats, body_readers) = (requests.clone(), responses.clone(), stats.clone(), body_readers.clone());
+            thread::spawn(move || {
+                let body = Self::read_body(&mut request, received_at);
+                body_readers.fetch_sub(1, Ordering::SeqCst);
+                match body {
+                    Ok(body) => {
+                        let pending = PendingRequest { request, body: Cursor::new(body), received_at, responses };
+                        Self::enqueue(pending, &requests, &stats);
+                    }
+                    Err((status, message)) => {
+                        let _ = request.respond(Response::from_string(message).with_status_code(status));
+                    }
+                }
+            });
+        }
+    }
+    
+    /// Read a body of at most `MAX_BODY_BYTES` that arrives within `BODY_READ_TIMEOUT`, or the status to answer with
+    fn read_body(request: &mut Request, received_at: SystemTime) -> Result<Vec<u8>, (u16, String)> {
+        let mut reader = request.as_reader().take(MAX_BODY_BYTES + 1);
+        let mut body = Vec::new();
+        let mut chunk = [0; 8192];
+        loop {
+            if received_at.elapsed().unwrap_or_default() > BODY_READ_TIMEOUT {
+                return Err((408, "Request body took too long".to_string()));
+            }
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => body.extend_from_slice(&chunk[..read]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err((400, format!("Failed to read body: {}", e))),
+            }
+        }
+        match body.len() as u64 > MAX_BODY_BYTES {
+            true => Err((413, "Request body too large".to_string())),
+            false => Ok(body),
+        }
+    }
+    
+    /// Queue a request for the game thread, answering 503 when the queue is full; false once the pool is gone
+    fn enqueue(pending: PendingRequest, requests: &SyncSender<PendingRequest>, stats: &RequestPoolStats) -> bool {
+        match requests.try_send(pending) {
+            Ok(()) => {
+                stats.accepted.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Full(pending)) => {
+                stats.rejected.fetch_add(1, Ordering::Relaxed);
+                let _ = pending.request.respond(Response::from_string("Server busy").with_status_code(503));
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+    
+    fn write_responses(responses: &Mutex<Receiver<(Request, BufferedResponse)>>, stopping: &AtomicBool, stats: &RequestPoolStats) {
+        loop {
+            let next = match responses.lock() {
+                Ok(responses) => responses.recv_timeout(WORKER_POLL_INTERVAL),
+                Err(_) => return,
+            };
+            match next {
+                Ok((request, response)) => {
+                    let length = response.data_length().unwrap_or(0) as u64;
+                    if request.respond(response).is_ok() {
+                        stats.bytes_sent.fetch_add(length, Ordering::Relaxed);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) if !stopping.load(Ordering::SeqCst) => {}
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+/// Listen on `address`, e.g. "localhost:8081"; port 0 lets the OS pick a free port
+/// When the port is taken the next `PORT_FALLBACK_ATTEMPTS` ports are tried, then one the OS picks
+/// Returns the server with the address it actually listens on
+pub fn bind_with_fallback(address: &str) -> Result<(Server, SocketAddr), Box<dyn Error>> {
+    let (host, port) = address.rsplit_once(':').ok_or_else(|| format!("Address '{}' has no port", address))?;
+    let port: u16 = port.parse().map_err(|_| format!("Address '{}' has an invalid port", address))?;
+    let mut candidates: Vec<u16> = match port {
+        0 => Vec::new(),
+        _ => (0..=PORT_FALLBACK_ATTEMPTS).filter_map(|offset| port.checked_add(offset)).collect(),
+    };
+    candidates.push(0);
+    for candidate in candidates {
+        match Server::http(format!("{}:{}", host, candidate)) {
+            Ok(server) => {
+                let bound = server.server_addr().to_ip().ok_or_else(|| format!("{} is not an IP address", address))?;
+                return Ok((server, bound));
+            }
+            Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::AddrInUse) => continue,
+            Err(e) => return Err(format!("Failed to listen on {}: {}", address, e).into()),
+        }
+    }
+    Err(format!("No free port to listen on for {}", address).into())
 }
 
 /// Web service manager responsible for hosting the webpage and managing connections
 pub struct WebServiceManager {
     server: Option<Server>,
     address: String,
-    clients: Arc<Mutex<Vec<ClientConnection>>>,
-    message_sender: Option<Sender<ServerMessage>>,
+    // Where the server actually listens once started, which differs from `address` after a fallback
+    bound_address: Option<SocketAddr>,
+    registry: Arc<Mutex<ClientRegistry>>,
     message_receiver: Option<Receiver<ClientMessage>>,
     is_running: bool,
+    // Background request thread, joined when the service stops
+    worker: Option<JoinHandle<()>>,
 }
 
 impl WebServiceManager {
@@ -46,10 +534,11 @@ impl WebServiceManager {
         Self {
             server: None,
             address: address.to_string(),
-            clients: Arc::new(Mutex::new(Vec::new())),
-            message_sender: None,
+            bound_address: None,
+            registry: Arc::new(Mutex::new(ClientRegistry::default())),
             message_receiver: None,
             is_running: false,
+            worker: None,
         }
     }
     
@@ -59,49 +548,46 @@ impl WebServiceManager {
             return Ok(());
         }
         
-        let server = Server::http(&self.address)
+        let (server, bound) = bind_with_fallback(&self.address)
             .map_err(|e| format!("Failed to start web service: {}", e))?;
         
-        println!("Web service started on http://{}", self.address);
+        println!("Web service started on http://{}", bound);
         
-        let (tx, _rx) = channel();
         let (client_tx, client_rx) = channel();
         
         self.server = Some(server);
-        self.message_sender = Some(tx);
+        self.bound_address = Some(bound);
         self.message_receiver = Some(client_rx);
         self.is_running = true;
         
         // Start background thread to handle HTTP requests
-        let _server_address = self.address.clone();
-        let clients = self.clients.clone();
+        let registry = self.registry.clone();
         
-        thread::spawn(move || {
+        self.worker = Some(thread::spawn(move || {
             // This would be implemented to handle HTTP requests
             // For now, we'll simulate client connections
             thread::sleep(Duration::from_millis(100));
             
             // Simulate a client connection
-            let client_id = format!("client_{}", uuid::Uuid::new_v4().to_string().split('-').next().unwrap_or("unknown"));
-            
-            {
-                let mut clients_guard = clients.lock().unwrap();
-                clients_guard.push(ClientConnection {
-                    client_id: client_id.clone(),
-                    connected_at: std::time::Instant::now(),
-                    last_activity: std::time::Instant::now(),
-                });
-            }
+            let client_id = match registry.lock() {
+                Ok(mut registry) => registry.register(None),
+                Err(_) => return,
+            };
             
             // Send welcome message
             if client_tx.send(ClientMessage::Connect { client_id }).is_err() {
                 eprintln!("Failed to send client connect message");
             }
-        });
+        }));
         
         Ok(())
     }
     
+    /// The address the service listens on, known once it started
+    pub fn bound_address(&self) -> Option<SocketAddr> {
+        self.bound_address
+    }
+    
     /// Check if the web service is running
     pub fn is_running(&self) -> bool {
         self.is_running
@@ -109,33 +595,148 @@ impl WebServiceManager {
     
     /// Get the number of connected clients
     pub fn client_count(&self) -> usize {
-        if let Ok(clients) = self.clients.lock() {
-            clients.len()
+        if let Ok(registry) = self.registry.lock() {
+            registry.connected_count()
         } else {
             0
         }
     }
     
+    /// Whether clients never connected, are connected, or all dropped out for now
+    pub fn connection_status(&self) -> ConnectionStatus {
+        if let Ok(registry) = self.registry.lock() {
+            registry.status()
+        } else {
+            ConnectionStatus::NeverConnected
+        }
+    }
+    
+    /// Register a client, resuming its session when `previous_id` names a known client
+    /// The client is queued a welcome and a replay of the current frame
+    pub fn register_client(&self, previous_id: Option<&str>) -> Result<String, Box<dyn Error>> {
+        let mut registry = self.registry.lock().map_err(|e| format!("Failed to lock client registry: {}", e))?;
+        Ok(registry.register(previous_id))
+    }
+    
+    /// Mark a client as disconnected; its ID stays valid for reconnecting
+    pub fn disconnect_client(&self, client_id: &str) -> bool {
+        self.registry.lock().map(|mut registry| registry.disconnect(client_id)).unwrap_or(false)
+    }
+    
+    /// Take the connection events raised since the last call
+    pub fn drain_connection_events(&self) -> Vec<ConnectionEvent> {
+        self.registry.lock()
+            .map(|mut registry| std::mem::take(&mut registry.events))
+            .unwrap_or_default()
+    }
+    
+    /// Queue pings for clients that are due one and drop clients silent for longer than `CLIENT_TIMEOUT`
+    /// Returns the IDs of the dropped clients; they can still reconnect
+    pub fn heartbeat(&self, now: Instant) -> Vec<String> {
+        self.registry.lock().map(|mut registry| registry.heartbeat(now)).unwrap_or_default()
+    }
+    
+    /// Record a client's answer to a ping; returns the measured round-trip time
+    pub fn record_pong(&self, client_id: &str, nonce: u64, now: Instant) -> Option<Duration> {
+        self.registry.lock().ok()?.record_pong(client_id, nonce, now)
+    }
+    
+    /// Apply a message received from a client
+    pub fn handle_client_message(&self, message: &ClientMessage, now: Instant) {
+        match message {
+            ClientMessage::Reconnect { client_id } => {
+                let _ = self.register_client(Some(client_id));
+            }
+            ClientMessage::Disconnect { client_id } => {
+                self.disconnect_client(client_id);
+            }
+            ClientMessage::Pong { client_id, nonce } => {
+                self.record_pong(client_id, *nonce, now);
+            }
+            ClientMessage::FrameCaptured { client_id, width, height, rgba } => {
+                match ImageBuffer::from_rgba_base64(*width, *height, rgba) {
+                    Ok(image) => {
+                        if let Ok(mut registry) = self.registry.lock() {
+                            registry.capture = Some(image);
+                        }
+                    }
+                    Err(e) => eprintln!("Invalid frame capture from {}: {}", client_id, e),
+                }
+            }
+            ClientMessage::Connect { .. } | ClientMessage::Acknowledge { .. } | ClientMessage::Error { .. } => {}
+        }
+    }
+    
+    /// RTT and last-seen times of every registered client
+    pub fn client_stats(&self, now: Instant) -> Vec<ClientStats> {
+        self.registry.lock()
+            .map(|registry| registry.clients.iter().map(|client| client.stats(now)).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Whether a client ID was handed out by `register_client` and not yet evicted
+    pub fn is_registered(&self, client_id: &str) -> bool {
+        self.registry.lock().map(|registry| registry.clients.iter().any(|client| client.client_id == client_id)).unwrap_or(false)
+    }
+    
+    /// Take the messages waiting for a client
+    pub fn take_messages(&self, client_id: &str) -> Vec<ServerMessage> {
+        let Ok(mut registry) = self.registry.lock() else { return Vec::new() };
+        registry.clients.iter_mut()
+            .find(|client| client.client_id == client_id)
+            .map(|client| {
+                client.last_activity = Instant::now();
+                std::mem::take(&mut client.outbox)
+            })
+            .unwrap_or_default()
+    }
+    
+    /// Start a new frame: commands sent from now on replace the retained frame
+    pub fn begin_frame(&self) {
+        if let Ok(mut registry) = self.registry.lock() {
+            registry.frame.clear();
+        }
+    }
+    
+    /// Replace the retained frame without sending it, for servers that draw a frame only when asked
+    pub fn retain_frame(&self, mut commands: Vec<String>) {
+        commands.truncate(MAX_RETAINED_COMMANDS);
+        if let Ok(mut registry) = self.registry.lock() {
+            registry.frame = commands;
+        }
+    }
+    
+    /// Commands of the current frame, as replayed to reconnecting clients
+    pub fn current_frame(&self) -> Vec<String> {
+        self.registry.lock().map(|registry| registry.frame.clone()).unwrap_or_default()
+    }
+    
+    /// Ask connected clients to read back their canvas; the answer arrives as `FrameCaptured`
+    pub fn request_frame_capture(&self) {
+        if let Ok(mut registry) = self.registry.lock() {
+            registry.broadcast(ServerMessage::CaptureFrame);
+        }
+    }
+    
+    /// Latest frame captured by a client, if any has answered yet
+    pub fn latest_capture(&self) -> Option<ImageBuffer> {
+        self.registry.lock().ok()?.capture.clone()
+    }
+    
+    /// Queue a message for one connected client; returns false if it isn't connected
+    pub fn send_message(&self, client_id: &str, message: ServerMessage) -> bool {
+        self.registry.lock().map(|mut registry| registry.send(client_id, message)).unwrap_or(false)
+    }
+    
     /// Send a message to all connected clients
     pub fn broadcast_message(&self, message: ServerMessage) -> Result<(), Box<dyn Error>> {
         if !self.is_running {
             return Err("Web service not running".into());
         }
         
-        if let Some(sender) = &self.message_sender {
-            // For testing purposes, we ignore send failures as there might not be a receiver
-            match sender.send(message) {
-                Ok(_) => Ok(()),
-                Err(_) => {
-                    // In a real implementation, this would be a proper error
-                    // For testing, we'll just log and continue
-                    println!("Warning: No receiver for message (expected in tests)");
-                    Ok(())
-                }
-            }
-        } else {
-            Err("Message sender not initialized".into())
-        }
+        let mut registry = self.registry.lock().map_err(|e| format!("Failed to lock client registry: {}", e))?;
+        registry.broadcast(message);
+        Ok(())
     }
     
     /// Receive messages from clients (non-blocking)
@@ -149,17 +750,24 @@ impl WebServiceManager {
     
     /// Get connected clients info
     pub fn get_clients(&self) -> Vec<ClientConnection> {
-        if let Ok(clients) = self.clients.lock() {
-            clients.clone()
+        if let Ok(registry) = self.registry.lock() {
+            registry.clients.clone()
         } else {
             Vec::new()
         }
     }
     
-    /// Send a render command to all connected clients
+    /// Send a render command to all connected clients and retain it for replays
     pub fn send_render_command(&self, command: &str) -> Result<(), Box<dyn Error>> {
         let command_id = format!("cmd_{}", uuid::Uuid::new_v4().to_string().split('-').next().unwrap_or("unknown"));
         
+        if let Ok(mut registry) = self.registry.lock() {
+            registry.frame.push(command.to_string());
+            if registry.frame.len() > MAX_RETAINED_COMMANDS {
+                registry.frame.remove(0);
+            }
+        }
+        
         let message = ServerMessage::RenderCommand {
             command_id,
             command: command.to_string(),
@@ -177,13 +785,18 @@ impl WebServiceManager {
         // Send disconnect message to all clients
         let _ = self.broadcast_message(ServerMessage::Disconnect);
         
+        // Let the request thread finish before its registry and channel go away
+        if let Some(worker) = self.worker.take() {
+            worker.join().map_err(|_| "Web service thread panicked")?;
+        }
+        
         // Clear clients
-        if let Ok(mut clients) = self.clients.lock() {
-            clients.clear();
+        if let Ok(mut registry) = self.registry.lock() {
+            *registry = ClientRegistry::default();
         }
         
         self.server = None;
-        self.message_sender = None;
+        self.bound_address = None;
         self.message_receiver = None;
         self.is_running = false;
         
@@ -287,4 +900,203 @@ mod uuid {
             format!("{:x}", hasher.finish())
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::{SocketAddr, TcpStream};
+
+    fn pool_on_free_port(capacity: usize) -> (RequestPool, SocketAddr) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        (RequestPool::start(server, 2, capacity), address)
+    }
+
+    fn send(address: SocketAddr, body: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "POST /echo HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+        stream
+    }
+
+    fn read_response(mut stream: TcpStream) -> String {
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_request_pool_serves_past_slow_clients() {
+        let (pool, address) = pool_on_free_port(8);
+
+        // Clients that announce a body and never send it don't tie up the readers, even more of them than readers
+        let slow: Vec<TcpStream> = (0..3).map(|_| {
+            let mut slow = TcpStream::connect(address).unwrap();
+            write!(slow, "POST /slow HTTP/1.1\r\nHost: test\r\nContent-Length: 100\r\n\r\n").unwrap();
+            slow
+        }).collect();
+
+        let client = send(address, "hello");
+        let mut request = pool.recv_timeout(Duration::from_secs(5)).expect("fast request was stalled");
+        assert_eq!(request.method(), &Method::Post);
+        assert_eq!(request.url(), "/echo");
+        let mut body = String::new();
+        request.as_reader().read_to_string(&mut body).unwrap();
+        request.respond(Response::from_string(body.to_uppercase())).unwrap();
+        assert!(read_response(client).ends_with("HELLO"));
+
+        let mut huge = TcpStream::connect(address).unwrap();
+        write!(huge, "POST /huge HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", MAX_BODY_BYTES + 1).unwrap();
+        let response = read_response(huge);
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+        drop(slow);
+        pool.stop();
+    }
+
+    #[test]
+    fn test_request_pool_turns_away_requests_when_full() {
+        let (pool, address) = pool_on_free_port(1);
+        let queued = send(address, "first");
+        // Give a worker time to queue the first request so the second finds the queue full
+        thread::sleep(Duration::from_millis(200));
+        let rejected = read_response(send(address, "second"));
+        assert!(rejected.starts_with("HTTP/1.1 503"), "{}", rejected);
+
+        let request = pool.recv_timeout(Duration::from_secs(1)).unwrap();
+        request.respond(Response::from_string("ok")).unwrap();
+        assert!(read_response(queued).ends_with("ok"));
+        // Stopping joins the writers, so every counter is final
+        let stats = pool.stats();
+        pool.stop();
+        assert_eq!(stats.accepted.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.rejected.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.bytes_sent.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_connection_status_and_events() {
+        let manager = WebServiceManager::new("localhost:0");
+        assert_eq!(manager.connection_status(), ConnectionStatus::NeverConnected);
+
+        let first = manager.register_client(None).unwrap();
+        let second = manager.register_client(Some("unknown")).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(manager.client_count(), 2);
+        assert_eq!(manager.connection_status(), ConnectionStatus::Connected);
+
+        assert!(manager.disconnect_client(&first));
+        assert!(!manager.disconnect_client(&first));
+        assert!(manager.disconnect_client(&second));
+        assert_eq!(manager.client_count(), 0);
+        assert_eq!(manager.connection_status(), ConnectionStatus::TemporarilyDisconnected);
+
+        assert_eq!(manager.register_client(Some(&first)).unwrap(), first);
+        assert_eq!(manager.drain_connection_events(), vec![
+            ConnectionEvent::ClientConnected { client_id: first.clone() },
+            ConnectionEvent::ClientConnected { client_id: second.clone() },
+            ConnectionEvent::ClientDisconnected { client_id: first.clone() },
+            ConnectionEvent::ClientDisconnected { client_id: second },
+            ConnectionEvent::AllClientsDisconnected,
+            ConnectionEvent::ClientReconnected { client_id: first },
+        ]);
+        assert!(manager.drain_connection_events().is_empty());
+    }
+
+    #[test]
+    fn test_heartbeat_measures_rtt_and_drops_stale_clients() {
+        let manager = WebServiceManager::new("localhost:0");
+        let start = Instant::now();
+        let fast = manager.register_client(None).unwrap();
+        let silent = manager.register_client(None).unwrap();
+        manager.take_messages(&fast);
+
+        assert!(manager.heartbeat(start).is_empty());
+        let nonce = match manager.take_messages(&fast).as_slice() {
+            [ServerMessage::Ping { nonce }] => *nonce,
+            messages => panic!("Unexpected messages: {:?}", messages),
+        };
+        // Not due again until the interval has passed
+        manager.heartbeat(start + Duration::from_millis(10));
+        assert!(manager.take_messages(&fast).is_empty());
+
+        assert_eq!(manager.record_pong(&fast, nonce + 1, start + Duration::from_millis(20)), None);
+        assert_eq!(manager.record_pong(&fast, nonce, start + Duration::from_millis(30)), Some(Duration::from_millis(30)));
+        let stats = manager.client_stats(start + Duration::from_millis(40));
+        assert_eq!(stats[0].rtt_ms, Some(30.0));
+        assert_eq!(stats[0].last_seen_ms_ago, 10);
+        assert_eq!(stats[1].rtt_ms, None);
+
+        manager.handle_client_message(&ClientMessage::Pong { client_id: fast.clone(), nonce: 0 }, start + CLIENT_TIMEOUT);
+        manager.drain_connection_events();
+        let dropped = manager.heartbeat(start + CLIENT_TIMEOUT + Duration::from_millis(1));
+        assert_eq!(dropped, vec![silent.clone()]);
+        assert_eq!(manager.client_count(), 1);
+        assert_eq!(manager.drain_connection_events(), vec![ConnectionEvent::ClientDisconnected { client_id: silent.clone() }]);
+        assert_eq!(manager.get_clients().len(), 2);
+
+        // Past the reconnect window the silent client is evicted, and resuming its session starts a new one
+        let later = start + CLIENT_TIMEOUT + RECONNECT_WINDOW + Duration::from_millis(1);
+        manager.handle_client_message(&ClientMessage::Pong { client_id: fast.clone(), nonce: 0 }, later);
+        manager.heartbeat(later);
+        let clients: Vec<String> = manager.get_clients().into_iter().map(|client| client.client_id).collect();
+        assert_eq!(clients, vec![fast.clone()]);
+        assert_ne!(manager.register_client(Some(&silent)).unwrap(), silent);
+    }
+
+    #[test]
+    fn test_reconnect_replays_current_frame() {
+        let mut manager = WebServiceManager::new("localhost:0");
+        manager.is_running = true;
+        let client = manager.register_client(None).unwrap();
+        assert!(matches!(manager.take_messages(&client).as_slice(), [
+            ServerMessage::Welcome { .. },
+            ServerMessage::FullFrame { commands },
+        ] if commands.is_empty()));
+
+        manager.send_render_command("old").unwrap();
+        manager.begin_frame();
+        manager.send_render_command("grid").unwrap();
+        manager.send_render_command("player").unwrap();
+        assert_eq!(manager.take_messages(&client).len(), 3);
+
+        // A refreshed tab gets the whole current frame, not the commands it missed
+        manager.disconnect_client(&client);
+        manager.send_render_command("agent").unwrap();
+        manager.register_client(Some(&client)).unwrap();
+        match manager.take_messages(&client).as_slice() {
+            [ServerMessage::Welcome { client_id }, ServerMessage::FullFrame { commands }] => {
+                assert_eq!(client_id, &client);
+                assert_eq!(commands, &vec!["grid".to_string(), "player".to_string(), "agent".to_string()]);
+            }
+            messages => panic!("Unexpected messages: {:?}", messages),
+        }
+    }
+
+    #[test]
+    fn test_frame_capture_round_trip() {
+        let manager = WebServiceManager::new("localhost:0");
+        let client = manager.register_client(None).unwrap();
+        manager.take_messages(&client);
+
+        manager.request_frame_capture();
+        assert!(matches!(manager.take_messages(&client).as_slice(), [ServerMessage::CaptureFrame]));
+        assert!(manager.latest_capture().is_none());
+
+        let answer = ClientMessage::FrameCaptured { client_id: client, width: 1, height: 1, rgba: "/wAA/w==".to_string() };
+        manager.handle_client_message(&answer, Instant::now());
+        assert_eq!(manager.latest_capture().unwrap().pixel(0, 0), Some([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_bind_falls_back_from_a_taken_port() {
+        let (_taken, taken) = bind_with_fallback("127.0.0.1:0").unwrap();
+        assert_ne!(taken.port(), 0);
+
+        let (_server, bound) = bind_with_fallback(&taken.to_string()).unwrap();
+        assert_ne!(bound.port(), taken.port());
+        assert!(bind_with_fallback("127.0.0.1").is_err());
+        assert!(bind_with_fallback("127.0.0.1:http").is_err());
+    }
+}
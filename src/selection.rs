@@ -0,0 +1,274 @@
+/// Drag selection of map tiles: press, drag and release the mouse to select a rectangle or a lasso area,
+/// which the zoning, demolition, blueprint and terraforming tools then apply to every covered tile
+use crate::economy::ZoneType;
+use crate::grid_game_systems::{GRID_HEIGHT, GRID_WIDTH};
+use crate::terrain::TerrainTool;
+use crate::rendering::RenderCommand;
+use crate::core::math::{Transform2d, Vector2d};
+use std::collections::BTreeSet;
+
+/// How dragged tiles become a selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionShape {
+    /// Every tile between the press and release tiles
+    #[default]
+    Rectangle,
+    /// Every tile inside the closed path the cursor traced
+    Lasso,
+}
+
+impl SelectionShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "rectangle" | "rect" => Some(SelectionShape::Rectangle),
+            "lasso" => Some(SelectionShape::Lasso),
+            _ => None,
+        }
+    }
+}
+
+/// Tools that act on an area selection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AreaTool {
+    Zone(ZoneType),
+    Demolish,
+    /// Copy the selected buildings into the named blueprint
+    Blueprint(String),
+    /// Raise, lower or flood the selected tiles, or level them to the first tile's elevation
+    Terrain(TerrainTool),
+}
+
+impl AreaTool {
+    /// Short description for status messages
+    pub fn label(&self) -> String {
+        match self {
+            AreaTool::Zone(zone_type) => format!("{:?} zoning", zone_type).to_lowercase(),
+            AreaTool::Demolish => "demolition".to_string(),
+            AreaTool::Blueprint(name) => format!("copy to blueprint '{}'", name),
+            AreaTool::Terrain(tool) => format!("terrain {}", tool.name()),
+        }
+    }
+}
+
+/// Tiles covered by a finished drag, in row-major order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AreaSelection {
+    pub shape: SelectionShape,
+    pub tiles: Vec<(i32, i32)>,
+}
+
+impl AreaSelection {
+    /// Only the part of the rectangle on the map is selected; a rectangle beside the map selects nothing
+    pub fn rectangle(corner_a: (i32, i32), corner_b: (i32, i32)) -> Self {
+        let (min_x, max_x) = (corner_a.0.min(corner_b.0).max(0), corner_a.0.max(corner_b.0).min(GRID_WIDTH - 1));
+        let (min_y, max_y) = (corner_a.1.min(corner_b.1).max(0), corner_a.1.max(corner_b.1).min(GRID_HEIGHT - 1));
+        let tiles = (min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| (x, y))).collect();
+        Self { shape: SelectionShape::Rectangle, tiles }
+    }
+
+    /// Tiles from `from` towards `to` along whichever of the row or column the drag moved further on
+    pub fn straight_line(from: (i32, i32), to: (i32, i32)) -> Self {
+        let (from, to) = (clamp_near_map(from), clamp_near_map(to));
+        let end = if (to.0 - from.0).abs() >= (to.1 - from.1).abs() { (to.0, from.1) } else { (from.0, to.1) };
+        Self::rectangle(from, end)
+    }
+
+    /// Tiles on the path, closed back to its start, and every tile whose center lies inside it, on the map only
+    /// Points far off the map are moved to just beyond its edge first, which keeps the part on the map
+    pub fn lasso(path: &[(i32, i32)]) -> Self {
+        let path: Vec<(i32, i32)> = path.iter().map(|tile| clamp_near_map(*tile)).collect();
+        let path = path.as_slice();
+        let mut tiles: BTreeSet<(i32, i32)> = BTreeSet::new();
+        for (index, from) in path.iter().enumerate() {
+            tiles.extend(line(*from, path[(index + 1) % path.len()]));
+        }
+
+        if let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (
+            path.iter().map(|tile| tile.0).min(),
+            path.iter().map(|tile| tile.0).max(),
+            path.iter().map(|tile| tile.1).min(),
+            path.iter().map(|tile| tile.1).max(),
+        ) {
+            for y in min_y..=max_y {
+                tiles.extend((min_x..=max_x).map(|x| (x, y)).filter(|tile| inside(path, *tile)));
+            }
+        }
+
+        let on_map = |(x, y): &(i32, i32)| (0..GRID_WIDTH).contains(x) && (0..GRID_HEIGHT).contains(y);
+        let mut tiles: Vec<(i32, i32)> = tiles.into_iter().filter(on_map).collect();
+        tiles.sort_by_key(|(x, y)| (*y, *x));
+        Self { shape: SelectionShape::Lasso, tiles }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.tiles.contains(&(x, y))
+    }
+
+    /// Top-left and bottom-right tiles of the selection
+    pub fn bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        let min_x = self.tiles.iter().map(|tile| tile.0).min()?;
+        let max_x = self.tiles.iter().map(|tile| tile.0).max()?;
+        let min_y = self.tiles.iter().map(|tile| tile.1).min()?;
+        let max_y = self.tiles.iter().map(|tile| tile.1).max()?;
+        Some(((min_x, min_y), (max_x, max_y)))
+    }
+
+    /// Ghost preview of the selection, one tile at a time, tinted by `valid`
+    pub fn ghost_commands(&self, texture_id: &str, cell_size: f32, valid: impl Fn(i32, i32) -> bool, z_order: i32) -> Vec<RenderCommand> {
+        self.tiles.iter().map(|&(x, y)| RenderCommand::DrawGhost {
+            texture_id: texture_id.to_string(),
+            transform: Transform2d::translation(Vector2d::new((x as f32 + 0.5) * cell_size, (y as f32 + 0.5) * cell_size)),
+            size: Vector2d::new(cell_size, cell_size),
+            valid: valid(x, y),
+            z_order,
+        }).collect()
+    }
+}
+
+// The nearest tile on the map or in the ring of tiles around it, so far-off points can't make paths huge
+fn clamp_near_map((x, y): (i32, i32)) -> (i32, i32) {
+    (x.clamp(-1, GRID_WIDTH), y.clamp(-1, GRID_HEIGHT))
+}
+
+// Tiles on a straight line between two tiles, both ends included
+fn line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs());
+    if steps == 0 {
+        return vec![from];
+    }
+    (0..=steps).map(|step| {
+        let t = step as f32 / steps as f32;
+        (
+            (from.0 as f32 + (to.0 - from.0) as f32 * t).round() as i32,
+            (from.1 as f32 + (to.1 - from.1) as f32 * t).round() as i32,
+        )
+    }).collect()
+}
+
+// Even-odd test of a tile center against the polygon through the path's tile centers
+fn inside(path: &[(i32, i32)], (x, y): (i32, i32)) -> bool {
+    let mut inside = false;
+    for (index, &(xi, yi)) in path.iter().enumerate() {
+        let (xj, yj) = path[(index + path.len() - 1) % path.len()];
+        if (yi > y) != (yj > y) {
+            let crossing = xi as f32 + (y - yi) as f32 * (xj - xi) as f32 / (yj - yi) as f32;
+            if (x as f32) < crossing {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Turns mouse presses, drags and releases over tiles into area selections
+#[derive(Debug, Clone, Default)]
+pub struct DragSelector {
+    pub shape: SelectionShape,
+    /// Constrain the drag to one row or column, whatever the shape
+    pub straight: bool,
+    // Tiles visited since the press, without consecutive repeats; empty while not dragging
+    path: Vec<(i32, i32)>,
+}
+
+impl DragSelector {
+    pub fn new(shape: SelectionShape) -> Self {
+        Self { shape, straight: false, path: Vec::new() }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        !self.path.is_empty()
+    }
+
+    pub fn press(&mut self, tile: (i32, i32)) {
+        self.path = vec![tile];
+    }
+
+    pub fn drag(&mut self, tile: (i32, i32)) {
+        if self.is_dragging() && self.path.last() != Some(&tile) {
+            self.path.push(tile);
+        }
+    }
+
+    /// Finish the drag; releasing on the pressed tile without leaving it is a click, not a selection
+    pub fn release(&mut self, tile: (i32, i32)) -> Option<AreaSelection> {
+        self.drag(tile);
+        let path = std::mem::take(&mut self.path);
+        if path.len() < 2 {
+            return None;
+        }
+        Some(self.select(&path))
+    }
+
+    pub fn cancel(&mut self) {
+        self.path.clear();
+    }
+
+    /// The selection the drag would make if released now
+    pub fn preview(&self) -> Option<AreaSelection> {
+        self.is_dragging().then(|| self.select(&self.path))
+    }
+
+    fn select(&self, path: &[(i32, i32)]) -> AreaSelection {
+        if self.straight {
+            return AreaSelection::straight_line(path[0], path[path.len() - 1]);
+        }
+        match self.shape {
+            SelectionShape::Rectangle => AreaSelection::rectangle(path[0], path[path.len() - 1]),
+            SelectionShape::Lasso => AreaSelection::lasso(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rectangle_drag() {
+        let mut selector = DragSelector::new(SelectionShape::Rectangle);
+        selector.press((3, 2));
+        assert_eq!(selector.release((3, 2)), None);
+
+        selector.press((3, 2));
+        selector.drag((2, 2));
+        assert_eq!(selector.preview().unwrap().tiles, vec![(2, 2), (3, 2)]);
+        let selection = selector.release((1, 3)).unwrap();
+        assert_eq!(selection.tiles, vec![(1, 2), (2, 2), (3, 2), (1, 3), (2, 3), (3, 3)]);
+        assert_eq!(selection.bounds(), Some(((1, 2), (3, 3))));
+        assert!(!selector.is_dragging());
+
+        // Straight drags keep to the row or column the cursor moved further along
+        selector.straight = true;
+        selector.press((1, 1));
+        assert_eq!(selector.release((4, 2)).unwrap().tiles, vec![(1, 1), (2, 1), (3, 1), (4, 1)]);
+        selector.press((1, 1));
+        assert_eq!(selector.release((2, 3)).unwrap().tiles, vec![(1, 1), (1, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn test_lasso_covers_enclosed_tiles() {
+        let mut selector = DragSelector::new(SelectionShape::Lasso);
+        // A diamond traced through its corners; the gaps between them are filled in
+        selector.press((2, 0));
+        selector.drag((4, 2));
+        selector.drag((2, 4));
+        let selection = selector.release((0, 2)).unwrap();
+
+        assert!(selection.contains(2, 2));
+        assert!(selection.contains(3, 1));
+        assert!(!selection.contains(0, 0));
+        assert!(!selection.contains(4, 4));
+        assert_eq!(selection.tiles.len(), 13);
+    }
+
+    #[test]
+    fn test_selections_stay_on_the_map() {
+        let map_tiles = (GRID_WIDTH * GRID_HEIGHT) as usize;
+        assert_eq!(AreaSelection::rectangle((i32::MIN, -5), (i32::MAX, 1_000_000)).tiles.len(), map_tiles);
+        assert_eq!(AreaSelection::rectangle((-3, -3), (-1, 5)).tiles, vec![]);
+        assert_eq!(AreaSelection::straight_line((0, 0), (i32::MAX, 1)).tiles.len(), GRID_WIDTH as usize);
+        let lasso = AreaSelection::lasso(&[(-100, -100), (i32::MAX, -100), (i32::MAX, i32::MAX), (-100, i32::MAX)]);
+        assert_eq!(lasso.tiles.len(), map_tiles);
+        assert!(AreaSelection::lasso(&[(-50, -50), (-50, i32::MAX), (-40, 0)]).tiles.is_empty());
+    }
+}
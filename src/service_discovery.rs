@@ -0,0 +1,141 @@
+/// Service discovery: the game, rendering and input servers listen on the ports in the `ports` section of `game.ron`,
+/// moving to a free port when theirs is taken, and register where they actually listen; the game server prints the
+/// result as one startup summary, writes it to `services.json` for scripts and tools, and hands it to the pages it serves
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// File listing the URL of every running service, removed when the game server stops
+pub const DISCOVERY_FILE: &str = "services.json";
+
+pub const GAME_SERVICE: &str = "game";
+pub const RENDERING_SERVICE: &str = "rendering";
+pub const INPUT_SERVICE: &str = "input";
+/// The static page servers of the `web-render` and `server` subcommands
+pub const WEB_SERVICE: &str = "web";
+
+static SERVICES: Mutex<ServiceDirectory> = Mutex::new(ServiceDirectory { services: BTreeMap::new() });
+
+/// Ports the servers listen on, the `ports` section of `GameConfig`; port 0 lets the OS pick a free one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortsConfig {
+    /// Host every server binds, e.g. "0.0.0.0" to accept other machines
+    pub host: String,
+    pub game: u16,
+    pub rendering: u16,
+    pub input: u16,
+}
+
+impl Default for PortsConfig {
+    fn default() -> Self {
+        Self { host: "localhost".to_string(), game: 8085, rendering: 8081, input: 8086 }
+    }
+}
+
+impl PortsConfig {
+    /// A port on the configured host, e.g. "localhost:8081"
+    pub fn address(&self, port: u16) -> String {
+        format!("{}:{}", self.host, port)
+    }
+}
+
+/// Where each service actually listens
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServiceDirectory {
+    services: BTreeMap<String, SocketAddr>,
+}
+
+impl ServiceDirectory {
+    pub fn register(&mut self, name: &str, address: SocketAddr) {
+        self.services.insert(name.to_string(), address);
+    }
+
+    pub fn address(&self, name: &str) -> Option<SocketAddr> {
+        self.services.get(name).copied()
+    }
+
+    /// URL of a service; one listening on every interface is reached through localhost
+    pub fn url(&self, name: &str) -> Option<String> {
+        let address = self.address(name)?;
+        Some(match address.ip().is_unspecified() {
+            true => format!("http://localhost:{}", address.port()),
+            false => format!("http://{}", address),
+        })
+    }
+
+    /// URL of every service by name, as written to the discovery file and given to client pages
+    pub fn urls(&self) -> BTreeMap<String, String> {
+        self.services.keys().filter_map(|name| Some((name.clone(), self.url(name)?))).collect()
+    }
+
+    /// One line per service, for the console at startup
+    pub fn summary(&self) -> String {
+        let mut summary = String::from("🧭 Services:");
+        for (name, url) in self.urls() {
+            summary.push_str(&format!("\n   {:<10} {}", name, url));
+        }
+        summary
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(&self.urls())?)?;
+        Ok(())
+    }
+
+    /// Remove a file `save` wrote, unless another instance has written its own services there since
+    pub fn remove_saved(&self, path: &Path) {
+        let saved = fs::read_to_string(path).ok().and_then(|text| serde_json::from_str::<BTreeMap<String, String>>(&text).ok());
+        if saved == Some(self.urls()) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Record where a service of this process listens
+pub fn register_service(name: &str, address: SocketAddr) {
+    if let Ok(mut services) = SERVICES.lock() {
+        services.register(name, address);
+    }
+}
+
+/// The services of this process registered so far
+pub fn registered_services() -> ServiceDirectory {
+    SERVICES.lock().map(|services| services.clone()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_reports_urls_of_actual_ports() {
+        let mut services = ServiceDirectory::default();
+        services.register(GAME_SERVICE, "0.0.0.0:8085".parse().unwrap());
+        services.register(RENDERING_SERVICE, "127.0.0.1:40123".parse().unwrap());
+
+        assert_eq!(services.url(GAME_SERVICE).as_deref(), Some("http://localhost:8085"));
+        assert_eq!(services.url(INPUT_SERVICE), None);
+        assert!(services.summary().ends_with("rendering  http://127.0.0.1:40123"), "{}", services.summary());
+
+        let path = std::env::temp_dir().join(format!("citybuilder-services-{}.json", std::process::id()));
+        services.save(&path).unwrap();
+        let saved: BTreeMap<String, String> = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved, services.urls());
+
+        // Another instance's file is left alone
+        let mut other = services.clone();
+        other.register(GAME_SERVICE, "0.0.0.0:8095".parse().unwrap());
+        other.remove_saved(&path);
+        assert!(path.exists());
+        services.remove_saved(&path);
+        assert!(!path.exists());
+
+        let ports: PortsConfig = ron::from_str("(rendering: 0)").unwrap();
+        assert_eq!((ports.address(ports.rendering), ports.game), ("localhost:0".to_string(), 8085));
+    }
+}
@@ -0,0 +1,289 @@
+/// City services (fire, police, health, education) and their per-tile coverage
+use crate::ecs::{Component, World};
+use crate::grid_game_components::GridPositionComponent;
+use crate::content::Palette;
+use crate::core::math::{Color, ShapeType, Vector2d};
+use crate::rendering::RenderCommand;
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// Kinds of services provided by service buildings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ServiceType {
+    Fire,
+    Police,
+    Health,
+    Education,
+}
+
+impl ServiceType {
+    /// All service types in a stable order
+    pub fn all() -> [ServiceType; 4] {
+        [ServiceType::Fire, ServiceType::Police, ServiceType::Health, ServiceType::Education]
+    }
+
+    /// Parse a service type from its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "fire" => Some(ServiceType::Fire),
+            "police" => Some(ServiceType::Police),
+            "health" => Some(ServiceType::Health),
+            "education" => Some(ServiceType::Education),
+            _ => None,
+        }
+    }
+
+    /// How much full coverage of this service contributes to tile desirability
+    pub fn desirability_weight(&self) -> f32 {
+        match self {
+            ServiceType::Fire => 0.2,
+            ServiceType::Police => 0.25,
+            ServiceType::Health => 0.25,
+            ServiceType::Education => 0.3,
+        }
+    }
+
+    /// Heatmap color used for the coverage overlay, from the palette so color-vision palettes apply
+    pub fn overlay_color(&self, palette: &Palette) -> Color {
+        let name = match self {
+            ServiceType::Fire => "red",
+            ServiceType::Police => "blue",
+            ServiceType::Health => "green",
+            ServiceType::Education => "yellow",
+        };
+        palette.color(name).unwrap_or(Color::white())
+    }
+}
+
+/// Component for buildings that provide a service within a radius (in tiles)
+#[derive(Clone, Debug)]
+pub struct ServiceBuildingComponent {
+    pub service_type: ServiceType,
+    pub radius: u32,
+}
+
+impl ServiceBuildingComponent {
+    pub fn new(service_type: ServiceType, radius: u32) -> Self {
+        Self { service_type, radius }
+    }
+}
+
+impl Component for ServiceBuildingComponent {
+    fn validate(&self) -> bool {
+        self.radius > 0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// A service building's contribution to coverage, detached from the World
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverageSource {
+    pub service: ServiceType,
+    pub center: (i32, i32),
+    pub radius: u32,
+}
+
+/// Per-tile coverage layers, one per service type, with values in 0.0..=1.0
+#[derive(Debug, Clone)]
+pub struct CoverageMap {
+    width: u32,
+    height: u32,
+    layers: HashMap<ServiceType, Vec<f32>>,
+}
+
+impl CoverageMap {
+    /// Create an empty coverage map for a grid
+    pub fn new(width: u32, height: u32) -> Self {
+        let layers = ServiceType::all().iter()
+            .map(|service| (*service, vec![0.0; (width * height) as usize]))
+            .collect();
+        Self { width, height, layers }
+    }
+
+    /// Grid dimensions as (width, height)
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            None
+        } else {
+            Some((y as u32 * self.width + x as u32) as usize)
+        }
+    }
+
+    /// Coverage of a service at a tile (0.0 outside the map)
+    pub fn coverage(&self, service: ServiceType, x: i32, y: i32) -> f32 {
+        self.index(x, y)
+            .and_then(|i| self.layers.get(&service).map(|layer| layer[i]))
+            .unwrap_or(0.0)
+    }
+
+    /// Raw coverage values of a service layer in row-major order
+    pub fn layer(&self, service: ServiceType) -> &[f32] {
+        self.layers.get(&service).map(|layer| layer.as_slice()).unwrap_or(&[])
+    }
+
+    /// Build a coverage map from a list of service sources
+    /// Pure function of its inputs, so it can run on a worker thread
+    pub fn from_sources(width: u32, height: u32, sources: &[CoverageSource]) -> Self {
+        let mut coverage = Self::new(width, height);
+        for source in sources {
+            coverage.add_source(source.service, source.center, source.radius);
+        }
+        coverage
+    }
+
+    /// Reset all layers to zero coverage
+    pub fn clear(&mut self) {
+        for layer in self.layers.values_mut() {
+            layer.iter_mut().for_each(|value| *value = 0.0);
+        }
+    }
+
+    /// Add coverage around a tile, falling off linearly with distance up to `radius`
+    /// Overlapping buildings do not stack beyond full coverage
+    pub fn add_source(&mut self, service: ServiceType, center: (i32, i32), radius: u32) {
+        let r = radius as i32;
+        for y in (center.1 - r)..=(center.1 + r) {
+            for x in (center.0 - r)..=(center.0 + r) {
+                let Some(index) = self.index(x, y) else { continue };
+                let dx = (x - center.0) as f32;
+                let dy = (y - center.1) as f32;
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance > radius as f32 {
+                    continue;
+                }
+
+                let strength = 1.0 - distance / (radius as f32 + 1.0);
+                if let Some(layer) = self.layers.get_mut(&service) {
+                    layer[index] = layer[index].max(strength);
+                }
+            }
+        }
+    }
+
+    /// Combined desirability of a tile from all services (0.0..=1.0),
+    /// used as the service contribution to happiness and land value
+    pub fn desirability(&self, x: i32, y: i32) -> f32 {
+        ServiceType::all().iter()
+            .map(|service| self.coverage(*service, x, y) * service.desirability_weight())
+            .sum::<f32>()
+            .min(1.0)
+    }
+
+    /// Build heatmap overlay commands for one service layer
+    pub fn heatmap_commands(&self, service: ServiceType, palette: &Palette, cell_size: f32, z_order: i32) -> Vec<RenderCommand> {
+        let base_color = service.overlay_color(palette);
+        let mut commands = Vec::new();
+
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let value = self.coverage(service, x, y);
+                if value <= 0.0 {
+                    continue;
+                }
+
+                let center = Vector2d::new((x as f32 + 0.5) * cell_size, (y as f32 + 0.5) * cell_size);
+                commands.extend(RenderCommand::shape(ShapeType::Rectangle { width: cell_size, height: cell_size })
+                    .at(center)
+                    .fill(Color::new(base_color.r, base_color.g, base_color.b, 0.6 * value))
+                    .z_order(z_order)
+                    .build());
+            }
+        }
+
+        commands
+    }
+}
+
+/// System that rebuilds the coverage layers from all service buildings
+pub struct ServiceCoverageSystem;
+
+impl ServiceCoverageSystem {
+    pub fn update(world: &World, coverage: &mut CoverageMap) {
+        let (width, height) = coverage.dimensions();
+        *coverage = CoverageMap::from_sources(width, height, &Self::collect_sources(world));
+    }
+
+    /// Gather the coverage sources of all service buildings
+    pub fn collect_sources(world: &World) -> Vec<CoverageSource> {
+        world.entities_with_components(&[
+            TypeId::of::<ServiceBuildingComponent>(),
+            TypeId::of::<GridPositionComponent>(),
+        ])
+        .into_iter()
+        .filter_map(|entity| {
+            let service = world.get_component::<ServiceBuildingComponent>(entity)?;
+            let pos = world.get_component::<GridPositionComponent>(entity)?;
+            Some(CoverageSource { service: service.service_type, center: (pos.x, pos.y), radius: service.radius })
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_falloff() {
+        let mut coverage = CoverageMap::new(10, 10);
+        coverage.add_source(ServiceType::Fire, (5, 5), 2);
+
+        assert!((coverage.coverage(ServiceType::Fire, 5, 5) - 1.0).abs() < 0.001);
+        let near = coverage.coverage(ServiceType::Fire, 6, 5);
+        let far = coverage.coverage(ServiceType::Fire, 7, 5);
+        assert!(near > far && far > 0.0);
+        assert_eq!(coverage.coverage(ServiceType::Fire, 8, 5), 0.0);
+        assert_eq!(coverage.coverage(ServiceType::Police, 5, 5), 0.0);
+        assert_eq!(coverage.coverage(ServiceType::Fire, -1, 5), 0.0);
+    }
+
+    #[test]
+    fn test_coverage_system_uses_service_buildings() {
+        let mut world = World::new();
+        let station = world.create_entity();
+        world.add_component(station, GridPositionComponent { x: 1, y: 1 }).unwrap();
+        world.add_component(station, ServiceBuildingComponent::new(ServiceType::Police, 3)).unwrap();
+        let school = world.create_entity();
+        world.add_component(school, GridPositionComponent { x: 8, y: 6 }).unwrap();
+        world.add_component(school, ServiceBuildingComponent::new(ServiceType::Education, 1)).unwrap();
+
+        let mut coverage = CoverageMap::new(10, 8);
+        ServiceCoverageSystem::update(&world, &mut coverage);
+
+        assert!(coverage.coverage(ServiceType::Police, 2, 2) > 0.0);
+        assert!(coverage.coverage(ServiceType::Education, 8, 7) > 0.0);
+        assert_eq!(coverage.coverage(ServiceType::Education, 1, 1), 0.0);
+        assert!(coverage.desirability(1, 1) > coverage.desirability(5, 4));
+
+        let sources = ServiceCoverageSystem::collect_sources(&world);
+        assert_eq!(sources.len(), 2);
+        let rebuilt = CoverageMap::from_sources(10, 8, &sources);
+        assert_eq!(rebuilt.coverage(ServiceType::Police, 2, 2), coverage.coverage(ServiceType::Police, 2, 2));
+    }
+
+    #[test]
+    fn test_heatmap_commands() {
+        let mut coverage = CoverageMap::new(4, 4);
+        coverage.add_source(ServiceType::Health, (0, 0), 1);
+
+        let commands = coverage.heatmap_commands(ServiceType::Health, &Palette::default(), 32.0, 5);
+        assert_eq!(commands.len(), 3); // Center and two orthogonal neighbours
+        assert!(coverage.heatmap_commands(ServiceType::Fire, &Palette::default(), 32.0, 5).is_empty());
+    }
+}
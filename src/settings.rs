@@ -0,0 +1,222 @@
+/// Per-client player settings, persisted on the server so they follow a session across reloads and restarts
+use crate::audio::AudioSettings;
+use crate::content::ColorVision;
+use crate::grid_game_systems::GridGameWorld;
+use crate::input::input_state::{KeyBindings, KeyChord};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory the game server keeps settings files in
+pub const SETTINGS_DIRECTORY: &str = "settings";
+/// Settings files kept; saving past this removes the files changed longest ago
+pub const MAX_SETTINGS_FILES: usize = 256;
+
+/// Color theme of the browser UI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorTheme {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// Options for players with impaired vision
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Palette of zone and overlay colors
+    pub color_vision: ColorVision,
+    /// Label zone lots and coverage overlays with text rather than telling them apart by color alone
+    pub tile_labels: bool,
+}
+
+/// Settings a player can change; fields missing from a saved file keep their defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerSettings {
+    pub key_bindings: KeyBindings,
+    /// UI zoom factor applied by the browser to panels, toasts and overlay labels
+    pub ui_scale: f32,
+    pub theme: ColorTheme,
+    /// Seconds between autosaves; 0 turns autosave off
+    pub autosave_interval_seconds: u32,
+    pub audio: AudioSettings,
+    pub accessibility: AccessibilitySettings,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            key_bindings: KeyBindings::default(),
+            ui_scale: 1.0,
+            theme: ColorTheme::Dark,
+            autosave_interval_seconds: 300,
+            audio: AudioSettings::default(),
+            accessibility: AccessibilitySettings::default(),
+        }
+    }
+}
+
+impl PlayerSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.5..=3.0).contains(&self.ui_scale) {
+            return Err(format!("UI scale {} is outside 0.5 to 3.0", self.ui_scale));
+        }
+        let bindings = &self.key_bindings;
+        if [&bindings.move_up, &bindings.move_down, &bindings.move_left, &bindings.move_right].iter().any(|keys| keys.is_empty()) {
+            return Err("Every movement action needs at least one key".to_string());
+        }
+        if bindings.actions.values().flatten().any(|chord| !chord.is_valid()) {
+            return Err(format!("A key chord needs a key or modifier and combines at most {} keys", KeyChord::MAX_KEYS));
+        }
+        self.audio.validate()
+    }
+
+    /// Apply the server-side parts of the settings; UI scale and theme are applied by the browser
+    pub fn apply(&self, game: &mut GridGameWorld) {
+        game.input.set_bindings(self.key_bindings.clone());
+        game.audio.set_settings(self.audio.clone());
+        game.palette.set_color_vision(self.accessibility.color_vision);
+        game.tile_labels = self.accessibility.tile_labels;
+    }
+}
+
+/// Settings of every client, stored as one RON file per client ID
+#[derive(Debug, Clone)]
+pub struct SettingsStore {
+    directory: PathBuf,
+    cache: HashMap<String, PlayerSettings>,
+}
+
+impl SettingsStore {
+    pub fn new(directory: &Path) -> Self {
+        Self { directory: directory.to_path_buf(), cache: HashMap::new() }
+    }
+
+    /// Settings of a client; defaults when it never saved any or its file can't be read
+    pub fn get(&mut self, client_id: &str) -> PlayerSettings {
+        if let Some(settings) = self.cache.get(client_id) {
+            return settings.clone();
+        }
+        let settings = self.path_for(client_id).ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| ron::from_str::<PlayerSettings>(&content).ok())
+            .unwrap_or_default();
+        self.cache.insert(client_id.to_string(), settings.clone());
+        settings
+    }
+
+    /// Validate and save a client's settings
+    pub fn put(&mut self, client_id: &str, settings: PlayerSettings) -> Result<(), Box<dyn Error>> {
+        settings.validate()?;
+        let path = self.path_for(client_id)?;
+        fs::create_dir_all(&self.directory)?;
+        fs::write(&path, ron::ser::to_string_pretty(&settings, ron::ser::PrettyConfig::default())?)?;
+        self.cache.insert(client_id.to_string(), settings);
+        self.evict_oldest(&path)?;
+        Ok(())
+    }
+
+    /// Drop a client's cached settings, e.g. once it disconnected; its file stays
+    pub fn forget(&mut self, client_id: &str) {
+        self.cache.remove(client_id);
+    }
+
+    /// Remove the least recently saved files past `MAX_SETTINGS_FILES`, never the one just written
+    fn evict_oldest(&mut self, written: &Path) -> Result<(), Box<dyn Error>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "ron") && path != written {
+                files.push((fs::metadata(&path)?.modified()?, path));
+            }
+        }
+        if files.len() < MAX_SETTINGS_FILES {
+            return Ok(());
+        }
+        files.sort();
+        for (_, path) in &files[..files.len() + 1 - MAX_SETTINGS_FILES] {
+            fs::remove_file(path)?;
+            if let Some(client_id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                self.cache.remove(client_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Settings file of a client; IDs are restricted so they can't escape the directory
+    fn path_for(&self, client_id: &str) -> Result<PathBuf, String> {
+        let valid = !client_id.is_empty()
+            && client_id.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+        if !valid {
+            return Err(format!("Invalid client ID '{}'", client_id));
+        }
+        Ok(self.directory.join(format!("{}.ron", client_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::input_state::{Action, Modifiers};
+    use crate::economy::ZoneType;
+    use crate::selection::{AreaSelection, AreaTool};
+    use crate::input::{InputEvent, Key};
+
+    #[test]
+    fn test_settings_persist_per_client() {
+        let directory = std::env::temp_dir().join(format!("settings_test_{}", std::process::id()));
+        let mut store = SettingsStore::new(&directory);
+        assert_eq!(store.get("client_1"), PlayerSettings::default());
+
+        let mut key_bindings = KeyBindings { move_up: vec![Key::I], ..KeyBindings::default() };
+        key_bindings.actions.insert(Action::Undo, vec![KeyChord::new(Modifiers::CTRL, &[Key::G, Key::U])]);
+        let settings = PlayerSettings {
+            key_bindings,
+            ui_scale: 1.5,
+            theme: ColorTheme::HighContrast,
+            autosave_interval_seconds: 0,
+            audio: AudioSettings { music: 0.2, ..AudioSettings::default() },
+            accessibility: AccessibilitySettings { color_vision: ColorVision::Tritanopia, tile_labels: true },
+        };
+        store.put("client_1", settings.clone()).unwrap();
+
+        // A fresh store, as after a server restart, reads the file back
+        let mut reloaded = SettingsStore::new(&directory);
+        assert_eq!(reloaded.get("client_1"), settings);
+        assert_eq!(reloaded.get("client_2"), PlayerSettings::default());
+
+        let mut game = GridGameWorld::new();
+        settings.apply(&mut game);
+        game.input.begin_frame(&[InputEvent::KeyPress { key: Key::I }]);
+        assert_eq!(game.input.movement_step(), (0, -1));
+        assert_eq!(game.audio.settings().music, 0.2);
+        assert_eq!(game.palette.color("yellow"), ColorVision::Tritanopia.substitute("yellow"));
+        game.apply_area_tool(&AreaTool::Zone(ZoneType::Industrial), &AreaSelection::rectangle((0, 7), (0, 7))).unwrap();
+        assert_eq!(game.get_game_state().lines().nth(7).unwrap().chars().next(), Some('I'));
+
+        // Past the file limit the least recently saved settings are removed
+        for i in 0..MAX_SETTINGS_FILES {
+            store.put(&format!("crowd_{}", i), PlayerSettings::default()).unwrap();
+        }
+        let files = fs::read_dir(&directory).unwrap().count();
+        assert_eq!(files, MAX_SETTINGS_FILES);
+        assert_eq!(SettingsStore::new(&directory).get("client_1"), PlayerSettings::default());
+        store.forget("client_1");
+        assert_eq!(store.get("client_1"), PlayerSettings::default());
+
+        assert!(store.put("../escape", settings.clone()).is_err());
+        assert!(store.put("client_1", PlayerSettings { ui_scale: 10.0, ..settings.clone() }).is_err());
+        let mut too_many_keys = settings.clone();
+        too_many_keys.key_bindings.actions.insert(Action::Redo, vec![KeyChord::new(Modifiers::NONE, &[Key::A, Key::B, Key::C])]);
+        assert!(store.put("client_1", too_many_keys).is_err());
+
+        // Files saved before chords existed keep the default chords
+        let old: PlayerSettings = ron::from_str("(key_bindings: (move_up: [W], move_down: [S], move_left: [A], move_right: [D]))").unwrap();
+        assert_eq!(old.key_bindings.actions, KeyBindings::default_actions());
+        let _ = fs::remove_dir_all(directory);
+    }
+}
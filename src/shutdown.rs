@@ -0,0 +1,178 @@
+/// Graceful shutdown: Ctrl+C and SIGTERM stop the server loops, then cleanup hooks run and worker threads are joined
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How long server loops wait for a request before checking for shutdown again
+pub const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Set from the signal handler, which may only touch atomics
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// True once SIGINT or SIGTERM arrived, for loops that don't hold a `ShutdownToken`
+pub fn shutdown_signalled() -> bool {
+    SIGNALLED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+mod signals {
+    use super::SIGNALLED;
+    use std::sync::atomic::Ordering;
+
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn on_signal(_signum: i32) {
+        SIGNALLED.store(true, Ordering::SeqCst);
+    }
+
+    pub fn install() {
+        // Safety: the handler only stores to an atomic, which is async-signal-safe
+        unsafe {
+            signal(SIGINT, on_signal);
+            signal(SIGTERM, on_signal);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod signals {
+    pub fn install() {}
+}
+
+/// Cheap handle that server loops and worker threads poll to know when to stop
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownToken {
+    requested: Arc<AtomicBool>,
+}
+
+impl ShutdownToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether shutdown was requested through this token's controller or by a signal
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst) || shutdown_signalled()
+    }
+}
+
+type ShutdownHook = Box<dyn FnOnce() -> Result<(), Box<dyn Error>> + Send>;
+
+/// Owns the shutdown flag, cleanup hooks and worker threads of the process
+pub struct ShutdownController {
+    token: ShutdownToken,
+    hooks: Vec<(String, ShutdownHook)>,
+    threads: Vec<(String, JoinHandle<()>)>,
+}
+
+impl Default for ShutdownController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownController {
+    pub fn new() -> Self {
+        Self {
+            token: ShutdownToken::new(),
+            hooks: Vec::new(),
+            threads: Vec::new(),
+        }
+    }
+
+    /// Trap SIGINT and SIGTERM so they request shutdown instead of killing the process
+    pub fn install_signal_handlers(&self) {
+        signals::install();
+    }
+
+    pub fn token(&self) -> ShutdownToken {
+        self.token.clone()
+    }
+
+    pub fn request(&self) {
+        self.token.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.token.is_requested()
+    }
+
+    /// Register cleanup to run at shutdown, e.g. an autosave or a device shutdown
+    /// Hooks run in reverse registration order, so later subsystems stop before what they depend on
+    pub fn on_shutdown<F>(&mut self, name: &str, hook: F)
+    where
+        F: FnOnce() -> Result<(), Box<dyn Error>> + Send + 'static,
+    {
+        self.hooks.push((name.to_string(), Box::new(hook)));
+    }
+
+    /// Spawn a worker thread that is joined at shutdown; it should return once its token is requested
+    pub fn spawn<F>(&mut self, name: &str, worker: F)
+    where
+        F: FnOnce(ShutdownToken) + Send + 'static,
+    {
+        let token = self.token();
+        self.threads.push((name.to_string(), thread::spawn(move || worker(token))));
+    }
+
+    /// Request shutdown, run the hooks and join the worker threads
+    /// Returns one message per hook that failed or thread that panicked
+    pub fn shutdown(mut self) -> Vec<String> {
+        self.request();
+        let mut errors = Vec::new();
+        while let Some((name, hook)) = self.hooks.pop() {
+            if let Err(e) = hook() {
+                errors.push(format!("{}: {}", name, e));
+            }
+        }
+        for (name, handle) in self.threads.drain(..) {
+            if handle.join().is_err() {
+                errors.push(format!("{}: thread panicked", name));
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_shutdown_runs_hooks_and_joins_threads() {
+        let mut controller = ShutdownController::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        for name in ["devices", "autosave"] {
+            let order = order.clone();
+            controller.on_shutdown(name, move || {
+                order.lock().unwrap().push(name);
+                Ok(())
+            });
+        }
+        controller.on_shutdown("failing", || Err("disk full".into()));
+
+        let worker_order = order.clone();
+        controller.spawn("worker", move |token| {
+            while !token.is_requested() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            worker_order.lock().unwrap().push("worker");
+        });
+
+        let token = controller.token();
+        assert!(!token.is_requested());
+        let errors = controller.shutdown();
+        assert!(token.is_requested());
+        assert_eq!(errors, vec!["failing: disk full".to_string()]);
+        assert_eq!(*order.lock().unwrap(), vec!["autosave", "devices", "worker"]);
+    }
+}
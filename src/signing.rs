@@ -0,0 +1,238 @@
+/// Integrity protection for shared city files: a sealed file starts with a header line carrying an HMAC-SHA256
+/// signature made with the workshop key, and its body can also be encrypted with ChaCha20-Poly1305, so a blueprint or save downloaded
+/// from someone else is checked as untampered before it is loaded. Signing is off until a workshop key is configured
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// File the game server reads its signing configuration from
+pub const SIGNING_CONFIG_FILE: &str = "signing.ron";
+/// Start of the header line of a sealed file
+const HEADER: &str = "#citysig1";
+/// Bytes of the random nonce leading an encrypted body
+const NONCE_LEN: usize = 12;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How files are sealed and which ones load
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigningConfig {
+    /// Sign saved files and refuse to load files without a valid signature; needs a workshop key
+    pub enabled: bool,
+    /// Secret every shared file is signed and encrypted with, shared only with the players who trade files
+    pub workshop_key: String,
+    /// Encrypt file bodies as well as signing them
+    pub encrypt: bool,
+}
+
+impl SigningConfig {
+    /// Sign with a workshop key
+    pub fn with_key(workshop_key: &str) -> Self {
+        Self { enabled: true, workshop_key: workshop_key.to_string(), encrypt: false }
+    }
+
+    /// Configuration for development: files are written plain and loaded unchecked
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Signing can't be on without a key: a key everyone knows proves nothing about who made a file
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.workshop_key.is_empty() {
+            return Err("signing is enabled but no workshop_key is configured".to_string());
+        }
+        Ok(())
+    }
+
+    /// Read the configuration from a RON file; signing stays off when the file doesn't exist
+    pub fn load_or_default(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let config: Self = ron::from_str(&fs::read_to_string(path)?)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Why a sealed file was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The file has no signature header
+    Unsigned,
+    /// The signature doesn't match the content, or was made with another key
+    Mismatch,
+    /// The header or encrypted body can't be read
+    Malformed(String),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::Unsigned => write!(f, "file is not signed; only signed files can be loaded"),
+            SignatureError::Mismatch => write!(f, "signature does not match: the file was modified or signed with another workshop key"),
+            SignatureError::Malformed(reason) => write!(f, "sealed file is malformed: {}", reason),
+        }
+    }
+}
+
+impl Error for SignatureError {}
+
+/// Sign, and if configured encrypt, a file's content; disabled configurations return it unchanged
+pub fn seal(content: &str, config: &SigningConfig) -> String {
+    if !config.enabled {
+        return content.to_string();
+    }
+    let (mode, body) = if config.encrypt {
+        // A fresh nonce for every seal, so no two files ever share a keystream
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).expect("the operating system has no random number source");
+        let mut sealed = nonce.to_vec();
+        sealed.extend(cipher(config).encrypt(Nonce::from_slice(&nonce), content.as_bytes()).expect("ChaCha20-Poly1305 encrypts any file size"));
+        ("encrypted", to_hex(&sealed))
+    } else {
+        ("plain", content.to_string())
+    };
+    let mac = signer(config, mode, &body).finalize().into_bytes();
+    format!("{} {} {}\n{}", HEADER, mode, to_hex(&mac), body)
+}
+
+/// Verify a sealed file and return its content. Disabled configurations accept unsigned files and skip the check,
+/// but still decrypt encrypted bodies
+pub fn open(sealed: &str, config: &SigningConfig) -> Result<String, SignatureError> {
+    let Some(rest) = sealed.strip_prefix(HEADER) else {
+        return if config.enabled { Err(SignatureError::Unsigned) } else { Ok(sealed.to_string()) };
+    };
+    let (header, body) = rest.split_once('\n').unwrap_or((rest, ""));
+    let fields: Vec<&str> = header.split_whitespace().collect();
+    let [mode, mac] = fields.as_slice() else {
+        return Err(SignatureError::Malformed("expected a mode and a signature in the header".to_string()));
+    };
+    if config.enabled {
+        let given = from_hex(mac).map_err(SignatureError::Malformed)?;
+        // verify_slice compares in constant time, so the time taken doesn't reveal how much matched
+        signer(config, mode, body).verify_slice(&given).map_err(|_| SignatureError::Mismatch)?;
+    }
+    match *mode {
+        "plain" => Ok(body.to_string()),
+        "encrypted" => {
+            let bytes = from_hex(body.trim()).map_err(SignatureError::Malformed)?;
+            if bytes.len() < NONCE_LEN {
+                return Err(SignatureError::Malformed("encrypted body is shorter than its nonce".to_string()));
+            }
+            let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+            // The cipher checks its own tag too, so a body altered while signing is off still fails to open
+            let plain = cipher(config).decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| SignatureError::Mismatch)?;
+            String::from_utf8(plain).map_err(|_| SignatureError::Malformed("decrypted body is not text".to_string()))
+        }
+        other => Err(SignatureError::Malformed(format!("unknown mode '{}'", other))),
+    }
+}
+
+/// Write a file sealed with the configuration
+pub fn write_sealed(path: &Path, content: &str, config: &SigningConfig) -> Result<(), Box<dyn Error>> {
+    fs::write(path, seal(content, config))?;
+    Ok(())
+}
+
+/// Read a sealed file, refusing it unless its signature is valid where signing is on
+pub fn read_sealed(path: &Path, config: &SigningConfig) -> Result<String, Box<dyn Error>> {
+    Ok(open(&fs::read_to_string(path)?, config).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+// Signature over the mode and body, so a plain file can't be relabeled as encrypted or the other way round
+fn signer(config: &SigningConfig, mode: &str, body: &str) -> HmacSha256 {
+    let mut mac = mac_with_key(&derive_key(config, "sign"));
+    mac.update(format!("{}\n", mode).as_bytes());
+    mac.update(body.as_bytes());
+    mac
+}
+
+// Separate keys for signing and encrypting, both from the one workshop key
+fn derive_key(config: &SigningConfig, purpose: &str) -> [u8; 32] {
+    let mut mac = mac_with_key(config.workshop_key.as_bytes());
+    mac.update(purpose.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+fn mac_with_key(key: &[u8]) -> HmacSha256 {
+    <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC takes keys of any length")
+}
+
+fn cipher(config: &SigningConfig) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&derive_key(config, "encrypt").into())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return Err("expected an even number of hex digits".to_string());
+    }
+    (0..text.len()).step_by(2)
+        .map(|index| u8::from_str_radix(&text[index..index + 2], 16).map_err(|_| format!("'{}' is not hex", &text[index..index + 2])))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_derivation_matches_published_hmac_vectors() {
+        // RFC 4231 test case 2, through the same path that derives the signing and encryption keys
+        let jefe = SigningConfig::with_key("Jefe");
+        assert_eq!(
+            to_hex(&derive_key(&jefe, "what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // RFC 4231 test case 6, a key longer than a block
+        let mut mac = mac_with_key(&[0xaa; 131]);
+        mac.update(b"Test Using Larger Than Block-Size Key - Hash Key First");
+        assert_eq!(to_hex(&mac.finalize().into_bytes()), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+    }
+
+    #[test]
+    fn test_sealed_files_reject_tampering() {
+        let content = "(name: \"block\", width: 2, height: 1, entries: [])";
+        let keyed = SigningConfig::with_key("our workshop");
+        for config in [keyed.clone(), SigningConfig { encrypt: true, ..keyed.clone() }] {
+            let sealed = seal(content, &config);
+            assert_eq!(open(&sealed, &config), Ok(content.to_string()));
+            assert_eq!(config.encrypt, !sealed.contains("block"));
+
+            let mut tampered = sealed.clone();
+            let last = tampered.pop().unwrap();
+            tampered.push(if last == '0' { '1' } else { '0' });
+            assert_eq!(open(&tampered, &config), Err(SignatureError::Mismatch));
+            let other_key = SigningConfig { workshop_key: "other".to_string(), ..config.clone() };
+            assert_eq!(open(&sealed, &other_key), Err(SignatureError::Mismatch));
+        }
+
+        // Every seal draws a fresh nonce, and a changed ciphertext fails the cipher's own check even unsigned
+        let encrypted = SigningConfig { encrypt: true, ..keyed.clone() };
+        let (first, second) = (seal(content, &encrypted), seal(content, &encrypted));
+        assert_ne!(first, second);
+        assert_eq!(open(&second, &encrypted), Ok(content.to_string()));
+        let (header, body) = first.split_once('\n').unwrap();
+        let flipped = format!("{}\n{}{}", header, &body[..body.len() - 1], if body.ends_with('0') { '1' } else { '0' });
+        assert_eq!(open(&flipped, &SigningConfig { enabled: false, ..encrypted.clone() }), Err(SignatureError::Mismatch));
+        let truncated = format!("{}\n{}", header, &body[..2 * NONCE_LEN - 2]);
+        assert!(matches!(open(&truncated, &SigningConfig { enabled: false, ..encrypted }), Err(SignatureError::Malformed(_))));
+
+        // Without a configured key files are written plain and loaded unchecked
+        assert_eq!(open(content, &keyed), Err(SignatureError::Unsigned));
+        assert_eq!(open(content, &SigningConfig::default()), Ok(content.to_string()));
+        assert_eq!(seal(content, &SigningConfig::default()), content);
+        assert!(SigningConfig { workshop_key: String::new(), ..keyed }.validate().is_err());
+    }
+}
@@ -0,0 +1,221 @@
+/// Headless balance simulation: run a scenario at full speed for in-game years and sample the city's key
+/// metrics once per month, for comparing tuning changes in a spreadsheet
+use crate::agents::AgentComponent;
+use crate::construction::BuildingKind;
+use crate::economy::{TaxRates, ZoneComponent, ZoneType};
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::grid_game_systems::{GridGameWorld, GRID_HEIGHT, GRID_WIDTH};
+use crate::trade::{ConnectionKind, TradeConfig, TradeMarket};
+use crate::triggers::{Trigger, TriggerSystem};
+use crate::soak::{SeededRng, SOAK_TIMESTEP};
+use serde::Deserialize;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+pub const MONTHS_PER_YEAR: u32 = 12;
+/// Happiness lost when no building gets the goods it needs, scaled by the share going without
+pub const SHORTAGE_UNHAPPINESS: f32 = 0.3;
+/// Happiness lost when every citizen is out of work, scaled by the unemployment rate
+pub const UNEMPLOYMENT_UNHAPPINESS: f32 = 0.4;
+
+/// Column names of the CSV written by `write_csv`
+pub const CSV_HEADER: &str = "month,population,treasury,happiness,congestion,unemployment,commute";
+
+/// A building placed before the simulation starts
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScenarioBuilding {
+    pub kind: BuildingKind,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A connection to the outside world opened before the simulation starts
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScenarioConnection {
+    pub kind: ConnectionKind,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Starting conditions of a simulation, loaded from RON; missing fields keep the default map's values
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    /// Seed for the commutes of the extra citizens
+    pub seed: u64,
+    pub starting_balance: i64,
+    pub tax_rates: TaxRates,
+    /// Commuting citizens added on top of the default map's
+    pub citizens: u32,
+    pub buildings: Vec<ScenarioBuilding>,
+    /// Highway, rail and port connections on the map edge
+    pub connections: Vec<ScenarioConnection>,
+    /// Prices of the goods traded through the connections
+    pub trade: TradeConfig,
+    /// Scripted events of the scenario, e.g. mission goals and their rewards
+    pub triggers: Vec<Trigger>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            starting_balance: 10_000,
+            tax_rates: TaxRates::default(),
+            citizens: 20,
+            buildings: Vec::new(),
+            connections: Vec::new(),
+            trade: TradeConfig::default(),
+            triggers: Vec::new(),
+        }
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    /// The default map with this scenario's economy, citizens and buildings, on a fixed timestep
+    pub fn build_world(&self) -> Result<GridGameWorld, String> {
+        let mut game = GridGameWorld::new();
+        game.stable_id_seed = self.seed;
+        game.initialize_game();
+        game.set_fixed_timestep(Some(SOAK_TIMESTEP));
+        self.apply(&mut game)?;
+        Ok(game)
+    }
+
+    /// Give an initialized game this scenario's economy, triggers, citizens, buildings and connections
+    pub fn apply(&self, game: &mut GridGameWorld) -> Result<(), String> {
+        game.economy.treasury.balance = self.starting_balance;
+        game.economy.tax_rates = self.tax_rates.clone();
+        game.economy.market = TradeMarket::new(self.trade.clone());
+        game.triggers = TriggerSystem::new(self.triggers.clone());
+
+        let mut rng = SeededRng::new(self.seed);
+        let tile = |rng: &mut SeededRng| (rng.below(GRID_WIDTH), rng.below(GRID_HEIGHT));
+        for _ in 0..self.citizens {
+            let (home, work) = (tile(&mut rng), tile(&mut rng));
+            game.world.spawn((
+                GridPositionComponent { x: home.0, y: home.1 },
+                AgentComponent::new("Citizen", vec![work, home]),
+                RenderComponent { symbol: 'c', color: "cyan".to_string() },
+            )).map_err(|e| e.to_string())?;
+        }
+        for building in &self.buildings {
+            game.place_building(building.kind, building.x, building.y)
+                .map_err(|e| format!("Cannot place {:?} at ({}, {}): {}", building.kind, building.x, building.y, e))?;
+        }
+        for connection in &self.connections {
+            game.add_connection(connection.kind, connection.x, connection.y)
+                .map_err(|e| format!("Cannot open {:?} at ({}, {}): {}", connection.kind, connection.x, connection.y, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// City metrics at the end of one in-game month
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthlySample {
+    pub month: u32,
+    /// Residents of finished residential buildings
+    pub population: u32,
+    pub treasury: i64,
+    /// Service desirability with any happiness modifiers, averaged over residents, reduced by goods shortages and unemployment (0.0..=1.0)
+    pub happiness: f32,
+    /// Share of citizens standing on a tile with another citizen (0.0..=1.0)
+    pub congestion: f32,
+    /// Share of citizens without a job (0.0..=1.0)
+    pub unemployment: f32,
+    /// Mean commute of employed citizens in tiles
+    pub average_commute: f32,
+}
+
+impl MonthlySample {
+    pub fn take(month: u32, game: &GridGameWorld) -> Self {
+        let world = &game.world;
+        let population = world.entities_with_components(&[TypeId::of::<ZoneComponent>()]).into_iter()
+            .filter_map(|entity| world.get_component::<ZoneComponent>(entity))
+            .filter(|zone| zone.zone_type == ZoneType::Residential)
+            .map(|zone| zone.population)
+            .sum();
+
+        let mut citizens_per_tile: HashMap<(i32, i32), u32> = HashMap::new();
+        for entity in world.entities_with_components(&[TypeId::of::<AgentComponent>(), TypeId::of::<GridPositionComponent>()]) {
+            if let Some(pos) = world.get_component::<GridPositionComponent>(entity) {
+                *citizens_per_tile.entry((pos.x, pos.y)).or_default() += 1;
+            }
+        }
+        let citizens: u32 = citizens_per_tile.values().sum();
+        let crowded: u32 = citizens_per_tile.values().filter(|count| **count > 1).sum();
+
+        Self {
+            month,
+            population,
+            treasury: game.economy.treasury.balance,
+            happiness: game.happiness(),
+            congestion: if citizens > 0 { crowded as f32 / citizens as f32 } else { 0.0 },
+            unemployment: game.labor.stats().unemployment_rate,
+            average_commute: game.labor.stats().average_commute,
+        }
+    }
+
+    pub fn csv_row(&self) -> String {
+        format!("{},{},{},{:.3},{:.3},{:.3},{:.1}", self.month, self.population, self.treasury, self.happiness, self.congestion,
+            self.unemployment, self.average_commute)
+    }
+}
+
+/// Run `game` for whole in-game years, sampling after each month's budget is settled
+pub fn simulate(game: &mut GridGameWorld, years: u32) -> Result<Vec<MonthlySample>, String> {
+    let ticks_per_month = game.budget_system.ticks_per_month();
+    let mut samples = Vec::new();
+    for month in 1..=years * MONTHS_PER_YEAR {
+        for _ in 0..ticks_per_month {
+            game.update()?;
+        }
+        samples.push(MonthlySample::take(month, game));
+    }
+    Ok(samples)
+}
+
+/// Write samples as CSV with a header row
+pub fn write_csv(samples: &[MonthlySample], out: &mut dyn Write) -> std::io::Result<()> {
+    writeln!(out, "{}", CSV_HEADER)?;
+    for sample in samples {
+        writeln!(out, "{}", sample.csv_row())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trade::TradeSystem;
+
+    #[test]
+    fn test_scenario_samples_every_month() {
+        let scenario: Scenario = ron::from_str("(citizens: 5, starting_balance: 50000, buildings: [(kind: House, x: 8, y: 0)], \
+            connections: [(kind: Highway, x: 0, y: 4)], trade: (base_price: 12.0))").unwrap();
+        assert_eq!(scenario.seed, 1);
+        assert_eq!(scenario.trade.import_markup_percent, 25);
+        let mut game = scenario.build_world().unwrap();
+        assert_eq!(TradeSystem::capacity(&game.world), 100);
+        let samples = simulate(&mut game, 1).unwrap();
+        assert_eq!(samples.len(), 12);
+        assert_eq!(samples[11].month, 12);
+        assert!(samples.last().unwrap().population > 0);
+        assert!(samples.iter().all(|sample| (0.0..=1.0).contains(&sample.happiness) && (0.0..=1.0).contains(&sample.congestion)));
+
+        let mut csv = Vec::new();
+        write_csv(&samples[..1], &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().next(), Some(CSV_HEADER));
+        assert!(csv.lines().nth(1).unwrap().starts_with("1,"));
+    }
+}
@@ -0,0 +1,205 @@
+/// Soak testing: run the same seeded simulation twice in lockstep and compare state hashes after every
+/// system, so nondeterminism is caught at the tick and system where it first appears
+use crate::agents::AgentComponent;
+use crate::construction::BuildingKind;
+use crate::ecs::fnv1a;
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::grid_game_systems::{GridGameWorld, GRID_HEIGHT, GRID_WIDTH};
+
+/// Seconds each soak tick advances
+pub const SOAK_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// Name of the checkpoint that compares the worlds before the first tick
+pub const INITIAL_CHECKPOINT: &str = "setup";
+
+/// Small deterministic generator (SplitMix64), so scenarios depend on nothing but their seed
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`
+    pub fn below(&mut self, bound: i32) -> i32 {
+        (self.next_u64() % bound.max(1) as u64) as i32
+    }
+}
+
+/// Hash of the simulation state: the world's registered components, economy and stats
+pub fn state_fingerprint(game: &GridGameWorld) -> u64 {
+    let hash = game.world.state_hash();
+    let hash = fnv1a(serde_json::to_string(&game.economy).unwrap_or_default().as_bytes(), hash);
+    fnv1a(serde_json::to_string(&game.stats).unwrap_or_default().as_bytes(), hash)
+}
+
+/// Where two runs of the same simulation first disagreed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Tick being run, 0 for the initial state
+    pub tick: u32,
+    /// System after which the hashes differed
+    pub system: &'static str,
+    pub first_hash: u64,
+    pub second_hash: u64,
+}
+
+/// Outcome of a soak run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoakReport {
+    pub seed: u64,
+    /// Ticks run by both worlds, up to and including a divergent one
+    pub ticks: u32,
+    pub final_hash: u64,
+    pub divergence: Option<Divergence>,
+}
+
+impl SoakReport {
+    pub fn is_deterministic(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+/// A seeded scenario run twice for a number of ticks
+#[derive(Debug, Clone)]
+pub struct SoakTest {
+    seed: u64,
+    ticks: u32,
+    citizens: u32,
+    buildings: u32,
+}
+
+impl SoakTest {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, ticks: 1000, citizens: 20, buildings: 10 }
+    }
+
+    pub fn ticks(mut self, ticks: u32) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    /// Extra commuting citizens placed by the seed
+    pub fn citizens(mut self, citizens: u32) -> Self {
+        self.citizens = citizens;
+        self
+    }
+
+    /// Building placements attempted at tiles chosen by the seed
+    pub fn buildings(mut self, buildings: u32) -> Self {
+        self.buildings = buildings;
+        self
+    }
+
+    /// The default map plus the seeded citizens and buildings, on a fixed timestep
+    pub fn build_world(&self) -> GridGameWorld {
+        let mut rng = SeededRng::new(self.seed);
+        let mut game = GridGameWorld::new();
+        // Stable IDs end up in hashed references like a citizen's workplace, so they follow the seed too
+        game.stable_id_seed = self.seed;
+        game.initialize_game();
+        game.set_fixed_timestep(Some(SOAK_TIMESTEP));
+
+        let tile = |rng: &mut SeededRng| (rng.below(GRID_WIDTH), rng.below(GRID_HEIGHT));
+        for _ in 0..self.citizens {
+            let (start, work) = (tile(&mut rng), tile(&mut rng));
+            game.world.spawn((
+                GridPositionComponent { x: start.0, y: start.1 },
+                AgentComponent::new("Citizen", vec![work, start]),
+                RenderComponent { symbol: 'c', color: "cyan".to_string() },
+            )).expect("Seeded citizens start on the map");
+        }
+        let kinds = [BuildingKind::House, BuildingKind::Shop, BuildingKind::Factory];
+        for _ in 0..self.buildings {
+            let kind = kinds[rng.below(kinds.len() as i32) as usize];
+            let (x, y) = tile(&mut rng);
+            // Occupied or unaffordable tiles are skipped the same way in both runs
+            let _ = game.place_building(kind, x, y);
+        }
+        game
+    }
+
+    /// Build the scenario twice and run both copies in lockstep
+    pub fn run(&self) -> Result<SoakReport, String> {
+        self.run_worlds(self.build_world(), self.build_world())
+    }
+
+    /// Run two worlds in lockstep, stopping at the first checkpoint whose hashes differ
+    pub fn run_worlds(&self, mut first: GridGameWorld, mut second: GridGameWorld) -> Result<SoakReport, String> {
+        let mut report = SoakReport { seed: self.seed, ticks: 0, final_hash: state_fingerprint(&first), divergence: None };
+        let second_hash = state_fingerprint(&second);
+        if report.final_hash != second_hash {
+            report.divergence = Some(Divergence {
+                tick: 0,
+                system: INITIAL_CHECKPOINT,
+                first_hash: report.final_hash,
+                second_hash,
+            });
+            return Ok(report);
+        }
+
+        for tick in 1..=self.ticks {
+            let first_trace = Self::traced_tick(&mut first)?;
+            let second_trace = Self::traced_tick(&mut second)?;
+            report.ticks = tick;
+
+            let differing = first_trace.iter().zip(&second_trace).find(|(a, b)| a != b);
+            if let Some((&(system, first_hash), &(_, second_hash))) = differing {
+                report.divergence = Some(Divergence { tick, system, first_hash, second_hash });
+                return Ok(report);
+            }
+            if first_trace.len() != second_trace.len() {
+                return Err(format!("Tick {} ran different systems in the two worlds", tick));
+            }
+            report.final_hash = first_trace.last().map(|(_, hash)| *hash).unwrap_or(report.final_hash);
+        }
+        Ok(report)
+    }
+
+    /// Run one update, recording the state hash after every system
+    fn traced_tick(game: &mut GridGameWorld) -> Result<Vec<(&'static str, u64)>, String> {
+        let mut trace = Vec::new();
+        game.update_traced(&mut |system, game| trace.push((system, state_fingerprint(game))))?;
+        Ok(trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Key;
+
+    #[test]
+    fn test_seeded_simulation_is_deterministic() {
+        let report = SoakTest::new(42).ticks(300).run().unwrap();
+        assert_eq!(report.divergence, None);
+        assert_eq!(report.ticks, 300);
+
+        // Another seed builds another city
+        let other = SoakTest::new(7).ticks(10).run().unwrap();
+        assert_ne!(other.final_hash, SoakTest::new(42).ticks(10).run().unwrap().final_hash);
+    }
+
+    #[test]
+    fn test_divergence_names_tick_and_system() {
+        let soak = SoakTest::new(3).ticks(50);
+        let mut second = soak.build_world();
+        // Input isn't part of the hashed state, so the runs only differ once the player moves
+        second.queue_key_tap(Key::ArrowDown);
+
+        let divergence = soak.run_worlds(soak.build_world(), second).unwrap().divergence.unwrap();
+        assert_eq!((divergence.tick, divergence.system), (1, "player_input"));
+        assert_ne!(divergence.first_hash, divergence.second_hash);
+    }
+}
@@ -0,0 +1,306 @@
+/// Stable entity IDs: UUIDs naming an entity across save/load, replays and network peers, where an `Entity`
+/// is only meaningful inside the `World` that created it
+use crate::ecs::{Component, Entity, World};
+use crate::soak::SeededRng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::{Any, TypeId};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Component holding an entity's UUID, written as text like `3f2b9c1e-7d4a-4e0b-9a61-0c5d8e2f4b17`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StableId(pub u128);
+
+impl StableId {
+    /// Version 4 UUID of an entity drawn from a session seed; the same seed and entity always give the same ID,
+    /// so re-running recorded ticks after a debug restore hands out the IDs the original run did
+    pub fn derive(seed: u64, entity: Entity) -> Self {
+        let mut rng = SeededRng::new(seed ^ (entity as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93));
+        let (high, low) = (rng.next_u64(), rng.next_u64());
+        let high = (high & !0xF000) | 0x4000;
+        let low = (low & !(0xC0 << 56)) | (0x80 << 56);
+        Self(((high as u128) << 64) | low as u128)
+    }
+}
+
+/// A seed that differs between runs and machines, so IDs from different sessions or peers don't collide
+pub fn session_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or(0).hash(&mut hasher);
+    std::process::id().hash(&mut hasher);
+    hasher.finish()
+}
+
+impl fmt::Display for StableId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = format!("{:032x}", self.0);
+        write!(f, "{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+impl FromStr for StableId {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let groups: Vec<&str> = text.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] || !groups.iter().all(|group| group.chars().all(|c| c.is_ascii_hexdigit())) {
+            return Err(format!("'{}' is not a UUID", text));
+        }
+        u128::from_str_radix(&groups.concat(), 16).map(StableId).map_err(|e| e.to_string())
+    }
+}
+
+impl Serialize for StableId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for StableId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Component for StableId {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+}
+
+/// Reference from a component to another entity, e.g. a citizen's home
+/// It is saved as the target's `StableId` alone; after loading or merging worlds, `fix_up` finds the entity again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntityRef {
+    id: StableId,
+    entity: Option<Entity>,
+}
+
+impl EntityRef {
+    pub fn new(id: StableId, entity: Entity) -> Self {
+        Self { id, entity: Some(entity) }
+    }
+
+    /// Reference to an entity that already has a stable ID
+    pub fn to(world: &World, entity: Entity) -> Option<Self> {
+        StableIdSystem::get(world, entity).map(|id| Self::new(id, entity))
+    }
+
+    pub fn id(&self) -> StableId {
+        self.id
+    }
+
+    /// Target in this world; `None` until the reference is fixed up after loading
+    pub fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+
+    /// Point at the entity holding the ID in `table`; a missing target leaves the reference unresolved
+    pub fn fix_up(&mut self, table: &StableIdTable) -> bool {
+        self.entity = table.get(self.id);
+        self.entity.is_some()
+    }
+
+    /// Follow the target to its new entity after `World::merge`
+    pub fn remap(&mut self, entity_remap: &HashMap<Entity, Entity>) {
+        if let Some(entity) = self.entity.and_then(|entity| entity_remap.get(&entity)) {
+            self.entity = Some(*entity);
+        }
+    }
+}
+
+impl Serialize for EntityRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.id.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EntityRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self { id: StableId::deserialize(deserializer)?, entity: None })
+    }
+}
+
+/// Remap table from stable IDs to the entities holding them in one world
+#[derive(Debug, Clone, Default)]
+pub struct StableIdTable(HashMap<StableId, Entity>);
+
+impl StableIdTable {
+    pub fn build(world: &World) -> Self {
+        Self(world.entities_with_components(&[TypeId::of::<StableId>()]).into_iter()
+            .filter_map(|entity| StableIdSystem::get(world, entity).map(|id| (id, entity)))
+            .collect())
+    }
+
+    pub fn get(&self, id: StableId) -> Option<Entity> {
+        self.0.get(&id).copied()
+    }
+}
+
+/// Components holding references to other entities
+pub trait EntityRefs {
+    fn entity_refs(&self) -> Vec<&EntityRef>;
+    fn entity_refs_mut(&mut self) -> Vec<&mut EntityRef>;
+}
+
+/// A reference whose target no longer exists
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DanglingRef {
+    /// Entity holding the reference
+    pub entity: Entity,
+    pub component: &'static str,
+    pub target: StableId,
+}
+
+/// System that gives persistent gameplay entities their stable ID
+pub struct StableIdSystem;
+
+impl StableIdSystem {
+    /// Give every entity with any of the `persistent` components an ID if it has none; returns those entities
+    pub fn update(world: &mut World, seed: u64, persistent: &[TypeId]) -> Vec<Entity> {
+        let mut missing: Vec<Entity> = persistent.iter()
+            .flat_map(|component| world.entities_with_components(&[*component]))
+            .filter(|entity| !world.has_component::<StableId>(*entity))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing.retain(|&entity| world.add_component(entity, StableId::derive(seed, entity)).is_ok());
+        missing
+    }
+
+    /// Entity holding an ID, if it is still alive
+    pub fn find(world: &World, id: StableId) -> Option<Entity> {
+        world.entities_with_components(&[TypeId::of::<StableId>()]).into_iter()
+            .find(|&entity| world.get_component::<StableId>(entity).is_some_and(|current| *current == id))
+    }
+
+    pub fn get(world: &World, entity: Entity) -> Option<StableId> {
+        world.get_component::<StableId>(entity).map(|id| *id)
+    }
+
+    /// Fix up the references held by every `T` component, e.g. after loading; returns those left dangling
+    pub fn fix_up_refs<T: Component + EntityRefs>(world: &mut World, component: &'static str) -> Vec<DanglingRef> {
+        let table = StableIdTable::build(world);
+        let mut dangling = Vec::new();
+        for entity in world.entities_with_components(&[TypeId::of::<T>()]) {
+            let Some(mut holder) = world.get_component_mut::<T>(entity) else { continue };
+            for reference in holder.entity_refs_mut() {
+                if !reference.fix_up(&table) {
+                    dangling.push(DanglingRef { entity, component, target: reference.id() });
+                }
+            }
+        }
+        dangling
+    }
+
+    /// References held by `T` components whose target is gone or no longer holds the ID, without changing them
+    pub fn dangling_refs<T: Component + EntityRefs>(world: &World, component: &'static str) -> Vec<DanglingRef> {
+        let mut dangling = Vec::new();
+        for entity in world.entities_with_components(&[TypeId::of::<T>()]) {
+            let Some(holder) = world.get_component::<T>(entity) else { continue };
+            for reference in holder.entity_refs() {
+                let valid = reference.entity().is_some_and(|target| Self::get(world, target) == Some(reference.id()));
+                if !valid {
+                    dangling.push(DanglingRef { entity, component, target: reference.id() });
+                }
+            }
+        }
+        dangling
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::Name;
+    use crate::grid_game_components::GridPositionComponent;
+
+    #[test]
+    fn test_ids_are_derived_once_and_round_trip_as_text() {
+        let mut world = World::new();
+        let house = world.spawn((GridPositionComponent { x: 1, y: 1 }, Name::new("house"))).unwrap();
+        let marker = world.spawn((Name::new("marker"),)).unwrap();
+        let persistent = [TypeId::of::<GridPositionComponent>()];
+
+        assert_eq!(StableIdSystem::update(&mut world, 7, &persistent), vec![house]);
+        assert!(StableIdSystem::update(&mut world, 7, &persistent).is_empty());
+        let id = StableIdSystem::get(&world, house).unwrap();
+        assert_eq!(id, StableId::derive(7, house));
+        assert_ne!(id, StableId::derive(8, house));
+        assert_eq!(StableIdSystem::get(&world, marker), None);
+        assert_eq!(StableIdSystem::find(&world, id), Some(house));
+
+        let text = id.to_string();
+        assert_eq!((text.len(), &text[14..15]), (36, "4"));
+        assert_eq!(text.parse::<StableId>(), Ok(id));
+        assert_eq!(serde_json::to_string(&id).unwrap(), format!("\"{}\"", text));
+        assert_eq!(serde_json::from_str::<StableId>(&format!("\"{}\"", text)).unwrap(), id);
+        assert!("3f2b9c1e-7d4a-4e0b-9a61".parse::<StableId>().is_err());
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Commute {
+        work: EntityRef,
+    }
+
+    impl Component for Commute {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn clone_box(&self) -> Box<dyn Component> {
+            Box::new(self.clone())
+        }
+    }
+
+    impl EntityRefs for Commute {
+        fn entity_refs(&self) -> Vec<&EntityRef> {
+            vec![&self.work]
+        }
+
+        fn entity_refs_mut(&mut self) -> Vec<&mut EntityRef> {
+            vec![&mut self.work]
+        }
+    }
+
+    #[test]
+    fn test_references_load_by_id_and_report_missing_targets() {
+        let mut saved = World::new();
+        let factory = saved.spawn((StableId::derive(1, 0),)).unwrap();
+        let work = EntityRef::to(&saved, factory).unwrap();
+        let text = serde_json::to_string(&Commute { work }).unwrap();
+        assert_eq!(text, format!(r#"{{"work":"{}"}}"#, work.id()));
+
+        // The loaded world numbers its entities differently; the reference finds the factory by its ID
+        let mut loaded = World::new();
+        loaded.spawn((Name::new("filler"),)).unwrap();
+        let moved_factory = loaded.spawn((work.id(),)).unwrap();
+        let commute: Commute = serde_json::from_str(&text).unwrap();
+        assert_eq!(commute.work.entity(), None);
+        let citizen = loaded.spawn((commute,)).unwrap();
+        assert!(StableIdSystem::fix_up_refs::<Commute>(&mut loaded, "commute").is_empty());
+        assert_eq!(loaded.get_component::<Commute>(citizen).unwrap().work.entity(), Some(moved_factory));
+
+        loaded.destroy_entity(moved_factory);
+        let dangling = vec![DanglingRef { entity: citizen, component: "commute", target: work.id() }];
+        assert_eq!(StableIdSystem::dangling_refs::<Commute>(&loaded, "commute"), dangling);
+        assert_eq!(StableIdSystem::fix_up_refs::<Commute>(&mut loaded, "commute"), dangling);
+    }
+}
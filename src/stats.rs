@@ -0,0 +1,149 @@
+/// Long-running gameplay statistics accumulated from game events
+use crate::events::{EventQueue, GameEvent};
+use crate::signing::{self, SigningConfig};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Counters tracked over the lifetime of a city
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameStats {
+    /// Number of tiles the player has walked
+    pub tiles_walked: u64,
+    /// Number of buildings placed
+    pub buildings_placed: u64,
+    /// Number of buildings demolished
+    #[serde(default)]
+    pub buildings_demolished: u64,
+    /// Total money earned (never decreases on spending)
+    pub money_earned: i64,
+    /// Total citizens that moved into housing
+    pub citizens_housed: u64,
+}
+
+impl GameStats {
+    /// Create a new set of zeroed statistics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the counters from a single event
+    pub fn record_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::PlayerMoved { from, to } => {
+                let distance = (to.0 - from.0).abs() + (to.1 - from.1).abs();
+                self.tiles_walked += distance as u64;
+            }
+            GameEvent::BuildingPlaced { .. } => {
+                self.buildings_placed += 1;
+            }
+            GameEvent::BuildingDemolished { .. } => {
+                self.buildings_demolished += 1;
+            }
+            GameEvent::MoneyEarned { amount } => {
+                if *amount > 0 {
+                    self.money_earned += amount;
+                }
+            }
+            GameEvent::CitizensHoused { count } => {
+                self.citizens_housed += *count as u64;
+            }
+            GameEvent::BudgetReport(_) | GameEvent::LifetimeExpired { .. } | GameEvent::TriggerFired { .. } => {}
+        }
+    }
+
+    /// Path of the stats file stored next to a save file (`city.sav` -> `city.stats.ron`)
+    pub fn stats_path_for_save(save_path: &Path) -> PathBuf {
+        save_path.with_extension("stats.ron")
+    }
+
+    /// Write the statistics next to the given save file, sealed with the signing configuration
+    pub fn save_alongside(&self, save_path: &Path, signing: &SigningConfig) -> Result<(), Box<dyn Error>> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        signing::write_sealed(&Self::stats_path_for_save(save_path), &content, signing)
+    }
+
+    /// Load the statistics stored next to the given save file
+    /// Returns zeroed statistics if the save has no stats file yet
+    pub fn load_alongside(save_path: &Path, signing: &SigningConfig) -> Result<Self, Box<dyn Error>> {
+        let stats_path = Self::stats_path_for_save(save_path);
+        if !stats_path.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = signing::read_sealed(&stats_path, signing)?;
+        Ok(ron::from_str(&content)?)
+    }
+}
+
+/// System that feeds gameplay events into the statistics counters
+pub struct StatsSystem;
+
+impl StatsSystem {
+    /// Record all pending events without consuming them
+    pub fn update(stats: &mut GameStats, events: &EventQueue<GameEvent>) {
+        for event in events.iter() {
+            stats.record_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_record_events() {
+        let mut events = EventQueue::new();
+        events.push(GameEvent::PlayerMoved { from: (1, 1), to: (2, 1) });
+        events.push(GameEvent::PlayerMoved { from: (2, 1), to: (2, 3) });
+        events.push(GameEvent::BuildingPlaced { x: 4, y: 4, kind: "house".to_string() });
+        events.push(GameEvent::BuildingDemolished { x: 4, y: 4, kind: "house".to_string(), refund: 100 });
+        events.push(GameEvent::MoneyEarned { amount: 250 });
+        events.push(GameEvent::MoneyEarned { amount: -50 });
+        events.push(GameEvent::CitizensHoused { count: 3 });
+
+        let mut stats = GameStats::new();
+        StatsSystem::update(&mut stats, &events);
+
+        assert_eq!(stats.tiles_walked, 3);
+        assert_eq!(stats.buildings_placed, 1);
+        assert_eq!(stats.buildings_demolished, 1);
+        assert_eq!(stats.money_earned, 250);
+        assert_eq!(stats.citizens_housed, 3);
+    }
+
+    #[test]
+    fn test_save_and_load_alongside() {
+        let save_path = std::env::temp_dir().join(format!("stats_test_{}.sav", std::process::id()));
+        let stats = GameStats {
+            tiles_walked: 12,
+            buildings_placed: 2,
+            buildings_demolished: 1,
+            money_earned: 900,
+            citizens_housed: 7,
+        };
+
+        stats.save_alongside(&save_path, &SigningConfig::disabled()).unwrap();
+        let loaded = GameStats::load_alongside(&save_path, &SigningConfig::disabled()).unwrap();
+        assert_eq!(loaded, stats);
+
+        // A signed save refuses statistics edited by hand or signed with another key
+        let signing = SigningConfig::with_key("workshop");
+        stats.save_alongside(&save_path, &signing).unwrap();
+        assert_eq!(GameStats::load_alongside(&save_path, &signing).unwrap(), stats);
+        let stats_path = GameStats::stats_path_for_save(&save_path);
+        fs::write(&stats_path, fs::read_to_string(&stats_path).unwrap().replace("900", "90000")).unwrap();
+        assert!(GameStats::load_alongside(&save_path, &signing).is_err());
+
+        let _ = fs::remove_file(GameStats::stats_path_for_save(&save_path));
+    }
+
+    #[test]
+    fn test_load_missing_stats_file() {
+        let save_path = std::env::temp_dir().join("stats_test_missing.sav");
+        let loaded = GameStats::load_alongside(&save_path, &SigningConfig::disabled()).unwrap();
+        assert_eq!(loaded, GameStats::new());
+    }
+}
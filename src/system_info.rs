@@ -0,0 +1,204 @@
+/// System access diagnostics: what each system declares it depends on and which components it reads and writes,
+/// with its stage and the stage's average runtime, and the other systems it touches the same components as
+/// Systems built on the `System` trait have their access read off their iterator types; the others declare it by hand
+use crate::ecs::{AccessMode, EntIt, System, SystemDependencies, SystemMarker};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Components a query reads and writes, as (component name, mutable) pairs
+pub trait QueryAccess {
+    fn component_access() -> Vec<(&'static str, bool)>;
+}
+
+fn access<A: AccessMode>() -> (&'static str, bool) {
+    (component_name(std::any::type_name::<A::Component>()), A::is_mutable())
+}
+
+impl<A1: AccessMode, A2: AccessMode> QueryAccess for EntIt<'_, (A1, A2)> {
+    fn component_access() -> Vec<(&'static str, bool)> {
+        vec![access::<A1>(), access::<A2>()]
+    }
+}
+
+/// A single-component query, with `()` filling the second slot
+impl<A1: AccessMode> QueryAccess for EntIt<'_, (A1, ())> {
+    fn component_access() -> Vec<(&'static str, bool)> {
+        vec![access::<A1>()]
+    }
+}
+
+impl<A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> QueryAccess for EntIt<'_, (A1, A2, A3, A4)> {
+    fn component_access() -> Vec<(&'static str, bool)> {
+        vec![access::<A1>(), access::<A2>(), access::<A3>(), access::<A4>()]
+    }
+}
+
+// Type name without its module path
+fn component_name(type_name: &'static str) -> &'static str {
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
+/// What one system declares about itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemInfo {
+    pub name: &'static str,
+    /// Checkpoint of the update the system runs in
+    pub stage: &'static str,
+    pub dependencies: Vec<&'static str>,
+    pub reads: Vec<&'static str>,
+    pub writes: Vec<&'static str>,
+}
+
+impl SystemInfo {
+    /// A system that declares its access by hand, e.g.
+    /// `SystemInfo::new("AutotileSystem", "autotile").writes::<AutotileComponent>()`
+    pub fn new(name: &'static str, stage: &'static str) -> Self {
+        Self { name, stage, dependencies: Vec::new(), reads: Vec::new(), writes: Vec::new() }
+    }
+
+    /// A `System`, with its dependencies and component access taken from its associated types
+    pub fn of<S>(stage: &'static str) -> Self
+    where
+        S: System + SystemMarker,
+        S::Dependencies: SystemDependencies,
+        for<'w> S::Iterators<'w>: QueryAccess,
+    {
+        let mut info = Self::new(S::name(), stage);
+        info.dependencies = S::Dependencies::get_dependency_names();
+        for (name, mutable) in S::Iterators::component_access() {
+            if mutable { info.writes.push(name) } else { info.reads.push(name) }
+        }
+        info
+    }
+
+    pub fn after(mut self, dependencies: &[&'static str]) -> Self {
+        self.dependencies.extend_from_slice(dependencies);
+        self
+    }
+
+    pub fn reads<T: 'static>(mut self) -> Self {
+        self.reads.push(component_name(std::any::type_name::<T>()));
+        self
+    }
+
+    pub fn writes<T: 'static>(mut self) -> Self {
+        self.writes.push(component_name(std::any::type_name::<T>()));
+        self
+    }
+
+    /// Whether the two systems touch a component that at least one of them writes
+    pub fn conflicts_with(&self, other: &SystemInfo) -> bool {
+        self.writes.iter().any(|component| other.reads.contains(component) || other.writes.contains(component))
+            || other.writes.iter().any(|component| self.reads.contains(component))
+    }
+}
+
+/// A system's declared access with its measured runtime, as served by `/debug/systems`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemDescription {
+    pub name: &'static str,
+    pub stage: &'static str,
+    pub dependencies: Vec<&'static str>,
+    pub reads: Vec<&'static str>,
+    pub writes: Vec<&'static str>,
+    /// Average time of the whole stage the system runs in, not of the system itself; `None` before the stage has run
+    pub stage_average_runtime_seconds: Option<f64>,
+    pub conflicts: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct StageTiming {
+    total: Duration,
+    runs: u32,
+}
+
+/// Every system of the game in update order, and how long each stage has taken
+#[derive(Debug, Clone, Default)]
+pub struct SystemRegistry {
+    systems: Vec<SystemInfo>,
+    timings: BTreeMap<&'static str, StageTiming>,
+}
+
+impl SystemRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a system; one registered under the same name is replaced
+    pub fn register(&mut self, info: SystemInfo) {
+        match self.systems.iter_mut().find(|system| system.name == info.name) {
+            Some(existing) => *existing = info,
+            None => self.systems.push(info),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SystemInfo> {
+        self.systems.iter().find(|system| system.name == name)
+    }
+
+    /// Record how long a stage took in one update
+    pub fn record(&mut self, stage: &'static str, duration: Duration) {
+        let timing = self.timings.entry(stage).or_default();
+        timing.total += duration;
+        timing.runs += 1;
+    }
+
+    pub fn average_runtime(&self, stage: &str) -> Option<Duration> {
+        self.timings.get(stage).filter(|timing| timing.runs > 0).map(|timing| timing.total / timing.runs)
+    }
+
+    /// Every system with its access, the average runtime of its stage and its conflicts, in update order
+    pub fn describe(&self) -> Vec<SystemDescription> {
+        self.systems.iter()
+            .map(|system| SystemDescription {
+                name: system.name,
+                stage: system.stage,
+                dependencies: system.dependencies.clone(),
+                reads: system.reads.clone(),
+                writes: system.writes.clone(),
+                stage_average_runtime_seconds: self.average_runtime(system.stage).map(|average| average.as_secs_f64()),
+                conflicts: self.systems.iter()
+                    .filter(|other| other.name != system.name && system.conflicts_with(other))
+                    .map(|other| other.name)
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_game_components::{GridPositionComponent, InputComponent, PlayerComponent};
+    use crate::grid_game_systems::{GridCollisionSystem, GridInputSystem, GridMovementSystem, GridRenderSystem};
+
+    #[test]
+    fn test_access_is_read_from_system_types() {
+        let movement = SystemInfo::of::<GridMovementSystem>("player_input");
+        assert_eq!(movement.dependencies, vec!["GridInputSystem"]);
+        assert_eq!((movement.reads.clone(), movement.writes.clone()), (vec!["PlayerComponent"], vec!["GridPositionComponent"]));
+        let input = SystemInfo::of::<GridInputSystem>("console");
+        assert_eq!((input.reads.len(), input.writes.clone()), (0, vec!["InputComponent"]));
+        assert_eq!(SystemInfo::new("Manual", "tools").reads::<PlayerComponent>().writes::<InputComponent>().reads, vec!["PlayerComponent"]);
+
+        let mut registry = SystemRegistry::new();
+        for info in [input, movement, SystemInfo::of::<GridCollisionSystem>("player_input"), SystemInfo::of::<GridRenderSystem>("render")] {
+            registry.register(info);
+        }
+        registry.register(SystemInfo::new("Mover", "tools").writes::<GridPositionComponent>());
+        registry.record("player_input", Duration::from_millis(2));
+        registry.record("player_input", Duration::from_millis(4));
+
+        let described = registry.describe();
+        let names: Vec<&str> = described.iter().map(|system| system.name).collect();
+        assert_eq!(names, vec!["GridInputSystem", "GridMovementSystem", "GridCollisionSystem", "GridRenderSystem", "Mover"]);
+        assert_eq!(described[1].stage_average_runtime_seconds, Some(0.003));
+        assert_eq!(described[3].stage_average_runtime_seconds, None);
+        // Readers of a written component conflict with the writer, not with each other
+        assert_eq!(described[1].conflicts, vec!["GridCollisionSystem", "GridRenderSystem", "Mover"]);
+        assert_eq!(described[2].conflicts, vec!["GridMovementSystem", "Mover"]);
+        assert!(described[0].conflicts.is_empty());
+    }
+}
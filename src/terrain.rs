@@ -0,0 +1,254 @@
+/// Terrain: an elevation per tile and the water the player floods tiles with, the terraforming edits and what they
+/// cost, the slope limits roads and buildings are placed under, and the shading that shows the hills on the map
+use crate::autotile::{AutotileMap, TileKind};
+use crate::core::math::{Color, ShapeType, Vector2d};
+use crate::rendering::RenderCommand;
+use serde::{Deserialize, Serialize};
+
+/// Lowest and highest elevation a tile can be terraformed to
+pub const MIN_ELEVATION: i32 = -4;
+pub const MAX_ELEVATION: i32 = 4;
+/// Cost of raising or lowering a tile by one step
+pub const TERRAFORM_COST: i64 = 15;
+/// Cost of flooding a tile
+pub const WATER_COST: i64 = 40;
+/// Steepest step to a neighboring tile a building can stand on
+pub const MAX_BUILDING_SLOPE: i32 = 1;
+/// Steepest step to a neighboring tile a road or wall can be laid on
+pub const MAX_ROAD_SLOPE: i32 = 2;
+/// Lowest elevation a tunnel can be bored through
+pub const MIN_TUNNEL_ELEVATION: i32 = 1;
+
+/// Terraforming edits of a single tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TerrainEdit {
+    Raise,
+    Lower,
+    /// Bring the tile to an elevation, paying for every step
+    Level(i32),
+    /// Flood the tile
+    Water,
+}
+
+/// Terraforming tools, applied to every tile of a drag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainTool {
+    Raise,
+    Lower,
+    /// Level the dragged tiles to the elevation of the tile the drag started on
+    Level,
+    Water,
+}
+
+impl TerrainTool {
+    pub fn all() -> [TerrainTool; 4] {
+        [TerrainTool::Raise, TerrainTool::Lower, TerrainTool::Level, TerrainTool::Water]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerrainTool::Raise => "raise",
+            TerrainTool::Lower => "lower",
+            TerrainTool::Level => "level",
+            TerrainTool::Water => "water",
+        }
+    }
+
+    /// Parse a terrain tool from its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|tool| tool.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Edit the tool makes, leveling to `start_elevation`
+    pub fn edit(&self, start_elevation: i32) -> TerrainEdit {
+        match self {
+            TerrainTool::Raise => TerrainEdit::Raise,
+            TerrainTool::Lower => TerrainEdit::Lower,
+            TerrainTool::Level => TerrainEdit::Level(start_elevation),
+            TerrainTool::Water => TerrainEdit::Water,
+        }
+    }
+}
+
+/// Elevation and water of every tile, all flat dry land at first
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainMap {
+    width: i32,
+    height: i32,
+    elevation: Vec<i32>,
+    water: Vec<bool>,
+}
+
+impl TerrainMap {
+    pub fn new(width: i32, height: i32) -> Self {
+        let tiles = (width.max(0) * height.max(0)) as usize;
+        Self { width, height, elevation: vec![0; tiles], water: vec![false; tiles] }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        ((0..self.width).contains(&x) && (0..self.height).contains(&y)).then(|| (y * self.width + x) as usize)
+    }
+
+    /// Elevation of a tile, 0 outside the map
+    pub fn elevation(&self, x: i32, y: i32) -> i32 {
+        self.index(x, y).map_or(0, |index| self.elevation[index])
+    }
+
+    pub fn is_water(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).is_some_and(|index| self.water[index])
+    }
+
+    /// Elevations in row order, for clients drawing the terrain
+    pub fn elevations(&self) -> &[i32] {
+        &self.elevation
+    }
+
+    /// Water flags in row order
+    pub fn water(&self) -> &[bool] {
+        &self.water
+    }
+
+    /// Steepest step from a tile to one of its four neighbors on the map
+    pub fn slope(&self, x: i32, y: i32) -> i32 {
+        self.steepest_step(x, y, |_, _| true)
+    }
+
+    // Steepest step from a tile to the neighbors on the map that `counts` picks
+    fn steepest_step(&self, x: i32, y: i32, counts: impl Fn(i32, i32) -> bool) -> i32 {
+        let height = self.elevation(x, y);
+        AutotileMap::neighbors(x, y).into_iter()
+            .filter(|(_, nx, ny)| self.index(*nx, *ny).is_some() && counts(*nx, *ny))
+            .map(|(_, nx, ny)| (self.elevation(nx, ny) - height).abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Check that a tile is dry and no steeper than `max_slope`
+    pub fn check_buildable(&self, x: i32, y: i32, max_slope: i32) -> Result<(), String> {
+        if self.is_water(x, y) {
+            return Err(format!("Tile ({}, {}) is under water", x, y));
+        }
+        let slope = self.slope(x, y);
+        if slope > max_slope {
+            return Err(format!("Tile ({}, {}) is too steep (slope {}, at most {})", x, y, slope, max_slope));
+        }
+        Ok(())
+    }
+
+    /// Check that a road, wall, bridge or tunnel tile fits the terrain: bridges span water and tunnels bore through
+    /// raised ground, while roads and walls need dry ground within `MAX_ROAD_SLOPE` of their neighbors. A step down
+    /// to a tunnel doesn't count, so roads can lead into one from the foot of its hill
+    pub fn check_tile(&self, kind: TileKind, x: i32, y: i32, tiles: &AutotileMap) -> Result<(), String> {
+        match kind {
+            TileKind::Bridge if !self.is_water(x, y) => Err(format!("Tile ({}, {}) has no water to bridge", x, y)),
+            TileKind::Bridge => Ok(()),
+            TileKind::Tunnel if self.is_water(x, y) => Err(format!("Tile ({}, {}) is under water", x, y)),
+            TileKind::Tunnel if self.elevation(x, y) < MIN_TUNNEL_ELEVATION => {
+                Err(format!("Tile ({}, {}) is too low to tunnel through", x, y))
+            }
+            TileKind::Tunnel => Ok(()),
+            TileKind::Road | TileKind::Wall => {
+                if self.is_water(x, y) {
+                    return Err(format!("Tile ({}, {}) is under water", x, y));
+                }
+                let slope = self.steepest_step(x, y, |nx, ny| !matches!(tiles.get(nx, ny), Some((TileKind::Tunnel, _))));
+                if slope > MAX_ROAD_SLOPE {
+                    return Err(format!("Tile ({}, {}) is too steep (slope {}, at most {})", x, y, slope, MAX_ROAD_SLOPE));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Cost of an edit, or why it can't be made
+    pub fn cost(&self, edit: TerrainEdit, x: i32, y: i32) -> Result<i64, String> {
+        let index = self.index(x, y).ok_or_else(|| format!("Tile ({}, {}) is outside the map", x, y))?;
+        let elevation = self.elevation[index];
+        let target = match edit {
+            TerrainEdit::Water if self.water[index] => return Err(format!("Tile ({}, {}) is already under water", x, y)),
+            TerrainEdit::Water => return Ok(WATER_COST),
+            TerrainEdit::Raise => elevation + 1,
+            TerrainEdit::Lower => elevation - 1,
+            TerrainEdit::Level(target) => target,
+        };
+        if !(MIN_ELEVATION..=MAX_ELEVATION).contains(&target) {
+            return Err(format!("Elevation {} is outside {}..={}", target, MIN_ELEVATION, MAX_ELEVATION));
+        }
+        Ok((target - elevation).abs() as i64 * TERRAFORM_COST)
+    }
+
+    /// Make an edit; returns what it cost. Reshaping a flooded tile drains it
+    pub fn apply(&mut self, edit: TerrainEdit, x: i32, y: i32) -> Result<i64, String> {
+        let cost = self.cost(edit, x, y)?;
+        let index = self.index(x, y).expect("cost checked the tile is on the map");
+        match edit {
+            TerrainEdit::Water => self.water[index] = true,
+            TerrainEdit::Raise => self.elevation[index] += 1,
+            TerrainEdit::Lower => self.elevation[index] -= 1,
+            TerrainEdit::Level(target) => self.elevation[index] = target,
+        }
+        if edit != TerrainEdit::Water {
+            self.water[index] = false;
+        }
+        Ok(cost)
+    }
+
+    /// Shade every tile that isn't flat dry land: hills lighter and hollows darker the further they are from
+    /// ground level, water blue, all under the tilemaps and entities
+    pub fn render_commands(&self, cell_size: f32, z_order: i32) -> Vec<RenderCommand> {
+        let mut commands = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let elevation = self.elevation(x, y);
+                let color = if self.is_water(x, y) {
+                    Color::new(0.2, 0.45, 0.85, 0.6)
+                } else if elevation > 0 {
+                    Color::new(0.55, 0.75, 0.35, 0.15 * elevation as f32)
+                } else if elevation < 0 {
+                    Color::new(0.35, 0.25, 0.15, 0.15 * -elevation as f32)
+                } else {
+                    continue;
+                };
+                commands.extend(RenderCommand::shape(ShapeType::Rectangle { width: cell_size, height: cell_size })
+                    .at(Vector2d::new((x as f32 + 0.5) * cell_size, (y as f32 + 0.5) * cell_size))
+                    .fill(color)
+                    .z_order(z_order)
+                    .build());
+            }
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terraforming_costs_and_slopes() {
+        let mut terrain = TerrainMap::new(4, 3);
+        assert_eq!(terrain.apply(TerrainEdit::Raise, 1, 1), Ok(TERRAFORM_COST));
+        assert_eq!(terrain.apply(TerrainEdit::Level(3), 1, 1), Ok(2 * TERRAFORM_COST));
+        assert!(terrain.apply(TerrainEdit::Level(MAX_ELEVATION + 1), 1, 1).is_err());
+        assert_eq!(terrain.slope(2, 1), 3);
+        assert_eq!(terrain.slope(3, 2), 0);
+        assert!(terrain.check_buildable(2, 1, MAX_ROAD_SLOPE).is_err());
+        assert!(terrain.check_buildable(3, 2, MAX_BUILDING_SLOPE).is_ok());
+
+        assert_eq!(terrain.apply(TerrainEdit::Water, 3, 2), Ok(WATER_COST));
+        assert!(terrain.cost(TerrainEdit::Water, 3, 2).is_err());
+        assert!(terrain.check_buildable(3, 2, MAX_BUILDING_SLOPE).is_err());
+        terrain.apply(TerrainEdit::Lower, 3, 2).unwrap();
+        assert!(!terrain.is_water(3, 2));
+        assert_eq!(terrain.render_commands(40.0, 0).len(), 2);
+    }
+}
@@ -0,0 +1,550 @@
+/// Test utilities for gameplay systems: `TestWorld` holds a world with the resources the systems take (economy,
+/// event and notification queues, catalog, tile map), spawns bundles in one line, ticks a system N frames and
+/// asserts on components and events
+/// `run_system` runs a `System` on a world, building its iterators from its type; `ScriptedInputDevice` and
+/// `RecordingRenderingDevice` stand in for real input and rendering; `TestServer` boots the game server on an
+/// ephemeral port for HTTP tests; `assert_world_diff!` checks exactly which components a system run changed
+use crate::assets::AssetManifest;
+use crate::auth::AuthConfig;
+use crate::autotile::AutotileMap;
+use crate::catalog::BuildingCatalog;
+use crate::debug_tracker::WorldState;
+use crate::core::math::projection::Projection;
+use crate::core::math::Vector2d;
+use crate::ecs::{AccessMode, Bundle, Component, EntIt, Entity, System, World};
+use crate::economy::Economy;
+use crate::events::{EventQueue, GameEvent};
+use crate::grid_game_components::GridPositionComponent;
+use crate::grid_game_systems::{register_game_components, BASE_CELL_SIZE};
+use crate::input::{InputDevice, InputEvent, InputQueue, Key, MouseButton, QueuedInputDevice};
+use crate::notifications::Notification;
+use crate::rendering::{RenderCommand, RenderResult, RenderingDevice};
+use crate::shutdown::ShutdownController;
+use crate::web_ecs_game::WebEcsGameDemo;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Iterators a system can be handed, built from the world alone
+pub trait FromWorld<'w> {
+    fn from_world(world: &'w World) -> Self;
+}
+
+impl<'w, A1: AccessMode, A2: AccessMode> FromWorld<'w> for EntIt<'w, (A1, A2)> {
+    fn from_world(world: &'w World) -> Self {
+        world.iter_entities::<A1, A2>()
+    }
+}
+
+impl<'w, A1: AccessMode, A2: AccessMode, A3: AccessMode, A4: AccessMode> FromWorld<'w> for EntIt<'w, (A1, A2, A3, A4)> {
+    fn from_world(world: &'w World) -> Self {
+        world.iter_entities_4::<A1, A2, A3, A4>()
+    }
+}
+
+/// Run one update of a system over the entities its iterators ask for
+pub fn run_system<S>(system: &mut S, world: &World)
+where
+    S: System,
+    for<'w> S::Iterators<'w>: FromWorld<'w>,
+{
+    system.update(S::Iterators::from_world(world));
+}
+
+/// A world and the resources gameplay systems take, for tests
+pub struct TestWorld {
+    pub world: World,
+    pub economy: Economy,
+    pub events: EventQueue<GameEvent>,
+    pub notifications: EventQueue<Notification>,
+    pub catalog: BuildingCatalog,
+    pub tiles: AutotileMap,
+}
+
+impl TestWorld {
+    /// An empty world with the game's components registered and a default economy
+    pub fn new() -> Self {
+        let mut world = World::new();
+        register_game_components(&mut world);
+        Self {
+            world,
+            economy: Economy::default(),
+            events: EventQueue::new(),
+            notifications: EventQueue::new(),
+            catalog: BuildingCatalog::default(),
+            tiles: AutotileMap::new(BASE_CELL_SIZE),
+        }
+    }
+
+    pub fn with_balance(mut self, balance: i64) -> Self {
+        self.economy.treasury.balance = balance;
+        self
+    }
+
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        self.world.spawn(bundle).expect("Test bundles hold valid components")
+    }
+
+    /// Spawn a bundle on a tile
+    pub fn spawn_at<B: Bundle>(&mut self, x: i32, y: i32, bundle: B) -> Entity {
+        let entity = self.spawn(bundle);
+        self.world.add_component(entity, GridPositionComponent { x, y }).expect("Positions are valid components");
+        entity
+    }
+
+    /// Run `update` once per frame, returning what each frame returned
+    pub fn tick<R>(&mut self, frames: u32, mut update: impl FnMut(&mut Self) -> R) -> Vec<R> {
+        (0..frames).map(|_| update(self)).collect()
+    }
+
+    /// Run a `System` once over this world
+    pub fn run<S>(&mut self, system: &mut S)
+    where
+        S: System,
+        for<'w> S::Iterators<'w>: FromWorld<'w>,
+    {
+        run_system(system, &self.world);
+    }
+
+    /// A copy of an entity's component; panics naming the entity and type when it has none
+    pub fn component<T: Component + Clone>(&self, entity: Entity) -> T {
+        self.world.get_component::<T>(entity)
+            .map(|component| component.clone())
+            .unwrap_or_else(|| panic!("Entity {} has no {}", entity, std::any::type_name::<T>()))
+    }
+
+    pub fn position(&self, entity: Entity) -> (i32, i32) {
+        let pos = self.component::<GridPositionComponent>(entity);
+        (pos.x, pos.y)
+    }
+
+    /// Assert something about an entity's component
+    pub fn assert_component<T: Component + Clone + std::fmt::Debug>(&self, entity: Entity, check: impl FnOnce(&T) -> bool) {
+        let component = self.component::<T>(entity);
+        assert!(check(&component), "Unexpected {:?} on entity {}", component, entity);
+    }
+
+    /// The gameplay events raised since the last call
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        self.events.drain()
+    }
+
+    /// Assert that a gameplay event was raised since events were last taken
+    pub fn assert_event(&self, expected: &GameEvent) {
+        let events: Vec<&GameEvent> = self.events.iter().collect();
+        assert!(events.contains(&expected), "Expected {:?} among {:?}", expected, events);
+    }
+
+    /// Text of the notifications raised so far
+    pub fn messages(&self) -> Vec<String> {
+        self.notifications.iter().map(|notification| notification.message.clone()).collect()
+    }
+}
+
+impl Default for TestWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Input device playing back a script, one frame of events per poll, and nothing once the script runs out
+pub struct ScriptedInputDevice {
+    frames: VecDeque<Vec<InputEvent>>,
+    queue: InputQueue,
+    device: QueuedInputDevice,
+}
+
+impl ScriptedInputDevice {
+    pub fn new(device_id: u32) -> Self {
+        let queue = InputQueue::new();
+        Self { frames: VecDeque::new(), device: QueuedInputDevice::new(queue.clone(), device_id), queue }
+    }
+
+    /// Add the events of the next frame
+    pub fn frame(mut self, events: Vec<InputEvent>) -> Self {
+        self.frames.push_back(events);
+        self
+    }
+
+    /// Add a frame pressing a key and one releasing it
+    pub fn tap(self, key: Key) -> Self {
+        self.frame(vec![InputEvent::KeyPress { key: key.clone() }]).frame(vec![InputEvent::KeyRelease { key }])
+    }
+}
+
+impl InputDevice for ScriptedInputDevice {
+    fn initialize(&mut self) -> Result<(), Box<dyn Error>> {
+        self.device.initialize()
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<InputEvent>, Box<dyn Error>> {
+        for event in self.frames.pop_front().unwrap_or_default() {
+            self.queue.push(event);
+        }
+        self.device.poll_events()
+    }
+
+    fn is_key_pressed(&self, key: &Key) -> bool {
+        self.device.is_key_pressed(key)
+    }
+
+    fn is_mouse_button_pressed(&self, button: &MouseButton) -> bool {
+        self.device.is_mouse_button_pressed(button)
+    }
+
+    fn get_mouse_position(&self) -> Vector2d {
+        self.device.get_mouse_position()
+    }
+
+    fn is_ready(&self) -> bool {
+        self.device.is_ready()
+    }
+
+    fn device_name(&self) -> &str {
+        "Scripted Input"
+    }
+
+    fn device_id(&self) -> u32 {
+        self.device.device_id()
+    }
+
+    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        self.frames.clear();
+        self.device.shutdown()
+    }
+}
+
+/// Rendering device that only records the commands it's sent; clones share the recording, so a test keeps one
+/// to read while the other is handed to a rendering manager
+#[derive(Debug, Clone, Default)]
+pub struct RecordingRenderingDevice {
+    commands: Arc<Mutex<Vec<RenderCommand>>>,
+    is_initialized: bool,
+}
+
+impl RecordingRenderingDevice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn commands(&self) -> Vec<RenderCommand> {
+        self.commands.lock().map(|commands| commands.clone()).unwrap_or_default()
+    }
+}
+
+impl RenderingDevice for RecordingRenderingDevice {
+    fn initialize(&mut self) -> Result<(), Box<dyn Error>> {
+        self.is_initialized = true;
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: RenderCommand) -> Result<RenderResult, Box<dyn Error>> {
+        self.commands.lock().map_err(|e| e.to_string())?.push(command);
+        Ok(RenderResult::Success)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.is_initialized
+    }
+
+    fn device_name(&self) -> &str {
+        "RecordingRenderingDevice"
+    }
+
+    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        self.is_initialized = false;
+        Ok(())
+    }
+}
+
+/// One change between two states of a world, as `assert_world_diff!` expects them
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WorldChange {
+    Spawned(Entity),
+    Despawned(Entity),
+    /// A registered component added, removed or modified, by its registered name
+    Changed(Entity, String),
+}
+
+impl WorldChange {
+    pub fn spawned(entity: Entity) -> Self {
+        WorldChange::Spawned(entity)
+    }
+
+    pub fn despawned(entity: Entity) -> Self {
+        WorldChange::Despawned(entity)
+    }
+
+    pub fn changed(entity: Entity, component: &str) -> Self {
+        WorldChange::Changed(entity, component.to_string())
+    }
+}
+
+/// Every change from one world to another, sorted; registered components are compared, as the debug tracker
+/// does, and an entity with an unregistered component is an error rather than a change nobody would see
+pub fn world_changes(before: &World, after: &World) -> Result<Vec<WorldChange>, String> {
+    let unregistered: Vec<String> = [before, after].iter()
+        .flat_map(|world| world.get_all_entities().iter().map(move |&entity| (entity, world.unregistered_components(entity))))
+        .filter(|(_, components)| !components.is_empty())
+        .map(|(entity, components)| format!("entity {} has {}", entity, components.join(", ")))
+        .collect();
+    if !unregistered.is_empty() {
+        return Err(format!(
+            "Can't compare components that aren't registered with register_component:\n  {}",
+            unregistered.join("\n  "),
+        ));
+    }
+    let diff = WorldState::capture(before).diff(&WorldState::capture(after));
+    let mut changes: Vec<WorldChange> = diff.spawned.into_iter().map(WorldChange::Spawned)
+        .chain(diff.despawned.into_iter().map(WorldChange::Despawned))
+        .chain(diff.changed.into_iter().map(|(entity, component)| WorldChange::Changed(entity, component)))
+        .collect();
+    changes.sort();
+    Ok(changes)
+}
+
+/// Check that the worlds differ by exactly the expected changes, describing the missing and unexpected ones
+pub fn check_world_diff(before: &World, after: &World, mut expected: Vec<WorldChange>) -> Result<(), String> {
+    expected.sort();
+    let actual = world_changes(before, after)?;
+    if actual == expected {
+        return Ok(());
+    }
+    let (before_state, after_state) = (WorldState::capture(before), WorldState::capture(after));
+    let text = |state: &WorldState, entity: &Entity, component: &str| {
+        state.entities.get(entity).and_then(|components| components.get(component)).cloned().unwrap_or_else(|| "none".to_string())
+    };
+    let mut message = String::from("World changed other than expected");
+    for change in expected.iter().filter(|change| !actual.contains(change)) {
+        message.push_str(&format!("\n  missing:    {:?}", change));
+    }
+    for change in actual.iter().filter(|change| !expected.contains(change)) {
+        message.push_str(&format!("\n  unexpected: {:?}", change));
+        if let WorldChange::Changed(entity, component) = change {
+            message.push_str(&format!(
+                "\n    {} -> {}", text(&before_state, entity, component), text(&after_state, entity, component),
+            ));
+        }
+    }
+    Err(message)
+}
+
+/// Assert that exactly the listed changes happened between two worlds, and nothing else, e.g.
+/// `assert_world_diff!(before, test.world, [changed(citizen, "grid_position"), spawned(house)])`
+/// Changes are `changed(entity, "registered name")`, `spawned(entity)` and `despawned(entity)`
+#[macro_export]
+macro_rules! assert_world_diff {
+    ($before:expr, $after:expr, [$($kind:ident($($arg:expr),+)),* $(,)?]) => {
+        if let Err(message) = $crate::testing::check_world_diff(
+            &$before,
+            &$after,
+            vec![$($crate::testing::WorldChange::$kind($($arg),+)),*],
+        ) {
+            panic!("{}", message);
+        }
+    };
+}
+
+/// The game server running on an ephemeral port in a thread of its own, with its action log and settings in a temporary
+/// directory and requests from this machine trusted as admins; `stop_with` looks at the game once it stops, so tests can check the world the requests left behind
+pub struct TestServer {
+    address: SocketAddr,
+    shutdown: ShutdownController,
+    inspections: Sender<Inspection>,
+    thread: JoinHandle<Result<(), String>>,
+    directory: PathBuf,
+}
+
+/// Run on the server thread once the game stops, since the game can't leave its thread
+type Inspection = Box<dyn FnOnce(&WebEcsGameDemo) + Send>;
+
+/// Status and JSON body of a response; bodies that aren't JSON come back as a string
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResponse {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+impl TestServer {
+    pub fn start() -> Result<Self, Box<dyn Error>> {
+        let server = tiny_http::Server::http("127.0.0.1:0").map_err(|e| e.to_string())?;
+        let address = server.server_addr().to_ip().ok_or("The test server isn't listening on TCP")?;
+        let directory = std::env::temp_dir().join(format!("citybuilder-test-server-{}", address.port()));
+        let save_path = directory.join("city.sav");
+        let settings = directory.join("settings");
+        // An empty file for every texture the game can draw, so connecting clients have a manifest to preload
+        let textures = directory.join("textures");
+        fs::create_dir_all(&textures)?;
+        for projection in [Projection::TopDown, Projection::Isometric] {
+            for entry in AssetManifest::build(BuildingCatalog::builtin(), projection).textures {
+                fs::write(textures.join(format!("{}.png", entry.id)), b"")?;
+            }
+        }
+        let shutdown = ShutdownController::new();
+        let token = shutdown.token();
+        let (inspections, pending) = mpsc::channel::<Inspection>();
+        let thread = std::thread::spawn(move || {
+            // Tests drive the debug endpoints too, as the admin on this machine
+            let auth = AuthConfig { trust_localhost: true, ..AuthConfig::default() };
+            let mut game = WebEcsGameDemo::new(&address.to_string())
+                .with_save_path(&save_path)
+                .with_settings_directory(&settings)
+                .with_texture_directory(&textures)
+                .with_auth(auth);
+            game.serve(server, &token)?;
+            for inspect in pending {
+                inspect(&game);
+            }
+            Ok(())
+        });
+        Ok(Self { address, shutdown, inspections, thread, directory })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn get(&self, path: &str) -> Result<TestResponse, Box<dyn Error>> {
+        self.request("GET", path, None)
+    }
+
+    pub fn post(&self, path: &str, body: serde_json::Value) -> Result<TestResponse, Box<dyn Error>> {
+        self.request("POST", path, Some(body))
+    }
+
+    /// Send one request on a fresh connection and read the whole response
+    pub fn request(&self, method: &str, path: &str, body: Option<serde_json::Value>) -> Result<TestResponse, Box<dyn Error>> {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let mut stream = TcpStream::connect(self.address)?;
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method, path, self.address, body.len(), body,
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        let (head, body) = response.split_once("\r\n\r\n").ok_or("Response has no header end")?;
+        let status = head.split_whitespace().nth(1).ok_or("Response has no status")?.parse()?;
+        let body = serde_json::from_str(body).unwrap_or_else(|_| serde_json::Value::String(body.to_string()));
+        Ok(TestResponse { status, body })
+    }
+
+    /// Register a new client, returning its ID
+    pub fn connect(&self) -> Result<String, Box<dyn Error>> {
+        let response = self.post("/api/v1/connect", serde_json::json!({}))?;
+        response.body["clientId"].as_str().map(str::to_string).ok_or_else(|| format!("No client ID in {}", response.body).into())
+    }
+
+    /// Shut the server down
+    pub fn stop(self) -> Result<(), Box<dyn Error>> {
+        self.stop_with(|_| ())
+    }
+
+    /// Shut the server down and look at the game it ran
+    pub fn stop_with<R: Send + 'static>(self, inspect: impl FnOnce(&WebEcsGameDemo) -> R + Send + 'static) -> Result<R, Box<dyn Error>> {
+        let (result, received) = mpsc::channel();
+        self.inspections.send(Box::new(move |game| {
+            let _ = result.send(inspect(game));
+        }))?;
+        drop(self.inspections);
+        self.shutdown.request();
+        self.thread.join().map_err(|_| "The test server panicked")??;
+        let _ = fs::remove_dir_all(&self.directory);
+        Ok(received.recv()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{AgentComponent, AgentSystem};
+    use crate::avoidance::LocalAvoidance;
+    use crate::budgeted_system::{BudgetedSystem, WorkBudget};
+    use crate::pathfinding::PathPlanningSystem;
+    use crate::grid_game_components::PlayerComponent;
+    use crate::grid_game_systems::GridMovementSystem;
+
+    #[test]
+    fn test_test_world_spawns_ticks_and_asserts() {
+        let mut test = TestWorld::new().with_balance(500);
+        let citizen = test.spawn_at(0, 0, (AgentComponent::new("Ada", vec![(2, 0)]),));
+        test.spawn_at(1, 1, (PlayerComponent { name: "Player".to_string() },));
+
+        // Plan, start moving, then two steps
+        let mut planner = PathPlanningSystem::new(5, 5);
+        let mut avoidance = LocalAvoidance::default();
+        test.tick(4, |test| {
+            AgentSystem::update(&mut test.world, &mut avoidance);
+            planner.run_slice(&mut test.world, &mut WorkBudget::unlimited());
+        });
+        assert_eq!(test.position(citizen), (2, 0));
+        test.assert_component::<AgentComponent>(citizen, |agent| agent.name == "Ada");
+        test.run(&mut GridMovementSystem);
+        assert_eq!(test.economy.treasury.balance, 500);
+
+        test.events.push(GameEvent::MoneyEarned { amount: 5 });
+        test.assert_event(&GameEvent::MoneyEarned { amount: 5 });
+        assert_eq!(test.take_events().len(), 1);
+    }
+
+    #[test]
+    fn test_world_diff_lists_exactly_what_changed() {
+        let mut test = TestWorld::new();
+        let citizen = test.spawn_at(0, 0, (AgentComponent::new("Ada", vec![(2, 0)]),));
+        let player = test.spawn_at(1, 1, (PlayerComponent { name: "Player".to_string() },));
+
+        let before = test.world.snapshot();
+        AgentSystem::update(&mut test.world, &mut LocalAvoidance::default());
+        assert_world_diff!(before, test.world, [changed(citizen, "agent"), changed(citizen, "path_request")]);
+
+        test.world.destroy_entity(player);
+        let error = check_world_diff(&before, &test.world, vec![WorldChange::changed(citizen, "agent")]).unwrap_err();
+        assert!(error.contains(&format!("unexpected: Changed({}, \"path_request\")", citizen)), "{}", error);
+        assert!(error.contains(&format!("unexpected: Despawned({})", player)), "{}", error);
+
+        #[derive(Clone)]
+        struct Unregistered;
+        impl Component for Unregistered {
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+                self
+            }
+
+            fn clone_box(&self) -> Box<dyn Component> {
+                Box::new(self.clone())
+            }
+        }
+        test.world.add_component(citizen, Unregistered).unwrap();
+        let error = check_world_diff(&before, &test.world, vec![]).unwrap_err();
+        assert!(error.contains(&format!("entity {} has", citizen)) && error.contains("Unregistered"), "{}", error);
+    }
+
+    #[test]
+    fn test_fakes_play_back_input_and_record_rendering() {
+        let mut input = ScriptedInputDevice::new(7).tap(Key::W);
+        input.initialize().unwrap();
+        assert_eq!(input.poll_events().unwrap(), vec![InputEvent::KeyPress { key: Key::W }]);
+        assert!(input.is_key_pressed(&Key::W));
+        input.poll_events().unwrap();
+        assert!(!input.is_key_pressed(&Key::W));
+        assert!(input.poll_events().unwrap().is_empty());
+
+        let recording = RecordingRenderingDevice::new();
+        let mut device: Box<dyn RenderingDevice> = Box::new(recording.clone());
+        device.initialize().unwrap();
+        device.execute_command(RenderCommand::Clear { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }).unwrap();
+        assert_eq!(recording.commands().len(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+/// City history: key metrics sampled once per in-game day into fixed-size ring buffers, for budget and graph
+/// screens and for balance analysis. Old samples drop off once a buffer is full
+use crate::economy::{Economy, ZoneComponent, ZoneType};
+use crate::ecs::World;
+use serde::Serialize;
+use std::any::TypeId;
+use std::collections::VecDeque;
+
+/// In-game days in a month; the budget's month length decides how many ticks make up a day
+pub const DAYS_PER_MONTH: u32 = 30;
+/// In-game days in a year, 12 months of 30 days
+pub const DAYS_PER_YEAR: u32 = 12 * DAYS_PER_MONTH;
+/// Days of history kept per metric
+pub const HISTORY_DAYS: usize = 5 * DAYS_PER_YEAR as usize;
+
+/// Metrics recorded every day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// Residents of residential buildings
+    Population,
+    /// Treasury balance
+    Treasury,
+    /// Jobs in commercial and industrial buildings
+    Jobs,
+    /// Residential demand: jobs without a resident to fill them, negative when residents lack jobs
+    Demand,
+}
+
+impl Metric {
+    /// All metrics in a stable order
+    pub fn all() -> [Metric; 4] {
+        [Metric::Population, Metric::Treasury, Metric::Jobs, Metric::Demand]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Population => "population",
+            Metric::Treasury => "treasury",
+            Metric::Jobs => "jobs",
+            Metric::Demand => "demand",
+        }
+    }
+
+    /// Parse a metric from its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|metric| metric.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Parse a time range like `30d`, `6m` or `1y` into days; a bare number is days
+pub fn parse_range(range: &str) -> Result<u32, String> {
+    let (count, days_per_unit) = match range.char_indices().last() {
+        Some((index, 'd')) => (&range[..index], 1),
+        Some((index, 'm')) => (&range[..index], DAYS_PER_MONTH),
+        Some((index, 'y')) => (&range[..index], DAYS_PER_YEAR),
+        _ => (range, 1),
+    };
+    count.parse::<u32>()
+        .ok()
+        .and_then(|count| count.checked_mul(days_per_unit))
+        .ok_or_else(|| format!("Invalid range '{}', expected e.g. 30d, 6m or 1y", range))
+}
+
+/// One day's value of a metric
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Sample {
+    pub day: u32,
+    pub value: f64,
+}
+
+/// Ring buffer of daily samples keeping the most recent `capacity` days
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    capacity: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl TimeSeries {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), samples: VecDeque::new() }
+    }
+
+    /// Append a sample, dropping the oldest one when full
+    pub fn push(&mut self, sample: Sample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Samples of the last `days` days up to and including `today`, oldest first
+    pub fn range(&self, today: u32, days: u32) -> Vec<Sample> {
+        let first = today.saturating_sub(days.saturating_sub(1));
+        self.samples.iter().filter(|sample| sample.day >= first).copied().collect()
+    }
+}
+
+/// Records every metric once a day
+#[derive(Debug, Clone)]
+pub struct CityHistory {
+    ticks_per_day: u32,
+    ticks: u32,
+    day: u32,
+    series: Vec<(Metric, TimeSeries)>,
+}
+
+impl CityHistory {
+    /// Create a history sampling every `ticks_per_day` updates and keeping `capacity` days
+    pub fn new(ticks_per_day: u32, capacity: usize) -> Self {
+        Self {
+            ticks_per_day: ticks_per_day.max(1),
+            ticks: 0,
+            day: 0,
+            series: Metric::all().into_iter().map(|metric| (metric, TimeSeries::new(capacity))).collect(),
+        }
+    }
+
+    /// Days recorded so far; the latest samples belong to this day
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    pub fn series(&self, metric: Metric) -> &TimeSeries {
+        &self.series.iter().find(|(recorded, _)| *recorded == metric).expect("every metric has a series").1
+    }
+
+    /// Samples of a metric over the last `days` days
+    pub fn range(&self, metric: Metric, days: u32) -> Vec<Sample> {
+        self.series(metric).range(self.day, days)
+    }
+
+    /// Advance one tick, sampling every metric when a day has passed
+    pub fn update(&mut self, world: &World, economy: &Economy) -> bool {
+        self.ticks += 1;
+        if self.ticks < self.ticks_per_day {
+            return false;
+        }
+        self.ticks = 0;
+        self.day += 1;
+        self.record(world, economy);
+        true
+    }
+
+    // Measure the city and append today's samples
+    fn record(&mut self, world: &World, economy: &Economy) {
+        let (mut population, mut jobs) = (0i64, 0i64);
+        for entity in world.entities_with_components(&[TypeId::of::<ZoneComponent>()]) {
+            let Some(zone) = world.get_component::<ZoneComponent>(entity) else { continue };
+            match zone.zone_type {
+                ZoneType::Residential => population += zone.population as i64,
+                ZoneType::Commercial | ZoneType::Industrial => jobs += zone.population as i64,
+            }
+        }
+        let day = self.day;
+        for (metric, series) in &mut self.series {
+            let value = match metric {
+                Metric::Population => population,
+                Metric::Treasury => economy.treasury.balance,
+                Metric::Jobs => jobs,
+                Metric::Demand => jobs - population,
+            };
+            series.push(Sample { day, value: value as f64 });
+        }
+    }
+}
+
+impl Default for CityHistory {
+    fn default() -> Self {
+        Self::new(1, HISTORY_DAYS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_game_components::GridPositionComponent;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("1y"), Ok(DAYS_PER_YEAR));
+        assert_eq!(parse_range("6m"), Ok(180));
+        assert_eq!(parse_range("45d"), Ok(45));
+        assert_eq!(parse_range("7"), Ok(7));
+        assert!(parse_range("y").is_err());
+        assert!(parse_range("2w").is_err());
+    }
+
+    #[test]
+    fn test_daily_samples_fill_a_ring_buffer() {
+        let mut world = World::new();
+        let mut economy = Economy::new(500);
+        world.spawn((GridPositionComponent { x: 0, y: 0 }, ZoneComponent::new(ZoneType::Residential, 8))).unwrap();
+        world.spawn((GridPositionComponent { x: 1, y: 0 }, ZoneComponent::new(ZoneType::Commercial, 5))).unwrap();
+
+        let mut history = CityHistory::new(2, 3);
+        assert!(!history.update(&world, &economy));
+        assert!(history.update(&world, &economy));
+        assert_eq!(history.range(Metric::Population, 1), vec![Sample { day: 1, value: 8.0 }]);
+        assert_eq!(history.range(Metric::Demand, 1)[0].value, -3.0);
+
+        for day in 2..=5 {
+            economy.treasury.balance = day * 100;
+            history.update(&world, &economy);
+            history.update(&world, &economy);
+        }
+        // Only the last three days are kept
+        let treasury: Vec<f64> = history.range(Metric::Treasury, DAYS_PER_YEAR).iter().map(|sample| sample.value).collect();
+        assert_eq!(treasury, vec![300.0, 400.0, 500.0]);
+        assert_eq!(history.range(Metric::Treasury, 2).len(), 2);
+        assert_eq!(history.series(Metric::Jobs).len(), 3);
+    }
+}
@@ -0,0 +1,197 @@
+/// Editor tools: which tool is active, its keyboard shortcut and cursor ghost, and what its clicks and drags do
+use crate::autotile::TileKind;
+use crate::construction::BuildingKind;
+use crate::ecs::Entity;
+use crate::economy::ZoneType;
+use crate::input::Key;
+use crate::selection::AreaTool;
+use crate::terrain::TerrainTool;
+
+/// Name of the blueprint the copy and paste tools share
+pub const CLIPBOARD_BLUEPRINT: &str = "clipboard";
+
+/// Interactions the player can switch between
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tool {
+    /// Select the entity on a tile for the inspector
+    Inspect,
+    /// Lay road or wall tiles
+    Tile(TileKind),
+    Zone(ZoneType),
+    Bulldoze,
+    /// Place a building's construction site
+    Place(BuildingKind),
+    /// Copy the buildings in an area to the clipboard blueprint
+    Copy,
+    /// Stamp the clipboard blueprint
+    Paste,
+    /// Raise, lower, level or flood the dragged tiles
+    Terrain(TerrainTool),
+}
+
+impl Tool {
+    /// Parse the names used by the toolbar, e.g. `road`, `zone_residential`, `bulldoze`, `fire_station` or `raise`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        match name.as_str() {
+            "inspect" => Some(Tool::Inspect),
+            "bulldoze" | "demolish" => Some(Tool::Bulldoze),
+            "copy" => Some(Tool::Copy),
+            "paste" => Some(Tool::Paste),
+            _ => TileKind::from_name(&name).map(Tool::Tile)
+                .or_else(|| name.strip_prefix("zone_").and_then(ZoneType::from_name).map(Tool::Zone))
+                .or_else(|| BuildingKind::from_name(&name).map(Tool::Place))
+                .or_else(|| TerrainTool::from_name(&name).map(Tool::Terrain)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Tool::Inspect => "inspect".to_string(),
+            Tool::Tile(kind) => format!("{:?}", kind).to_lowercase(),
+            Tool::Zone(zone_type) => format!("zone_{:?}", zone_type).to_lowercase(),
+            Tool::Bulldoze => "bulldoze".to_string(),
+            Tool::Place(kind) => snake_case(&format!("{:?}", kind)),
+            Tool::Copy => "copy".to_string(),
+            Tool::Paste => "paste".to_string(),
+            Tool::Terrain(tool) => tool.name().to_string(),
+        }
+    }
+
+    /// Area tool a drag selection is handed to; other tools act on single tiles
+    pub fn area_tool(&self) -> Option<AreaTool> {
+        match self {
+            Tool::Zone(zone_type) => Some(AreaTool::Zone(*zone_type)),
+            Tool::Bulldoze => Some(AreaTool::Demolish),
+            Tool::Copy => Some(AreaTool::Blueprint(CLIPBOARD_BLUEPRINT.to_string())),
+            Tool::Terrain(tool) => Some(AreaTool::Terrain(*tool)),
+            _ => None,
+        }
+    }
+
+    /// Texture of the ghost drawn under the cursor, `None` for tools without one
+    pub fn ghost_texture(&self) -> Option<String> {
+        match self {
+            Tool::Inspect => None,
+            Tool::Tile(kind) => Some(kind.atlas_id().to_string()),
+            Tool::Place(kind) => Some(format!("building_{:?}", kind).to_lowercase()),
+            Tool::Paste => Some(format!("blueprint_{}", CLIPBOARD_BLUEPRINT)),
+            Tool::Zone(_) | Tool::Bulldoze | Tool::Copy | Tool::Terrain(_) => Some("selection".to_string()),
+        }
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, character) in name.chars().enumerate() {
+        if character.is_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.push(character.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// A switch from one tool to another, for the exit and enter hooks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolChange {
+    pub exited: Option<Tool>,
+    pub entered: Option<Tool>,
+}
+
+/// The active tool, the tile under the cursor and the keyboard shortcuts
+#[derive(Debug, Clone)]
+pub struct ToolState {
+    active: Option<Tool>,
+    shortcuts: Vec<(Key, Tool)>,
+    /// Tile under the cursor, for the ghost
+    pub hover: Option<(i32, i32)>,
+    /// Entity picked by the inspect tool
+    pub inspected: Option<Entity>,
+}
+
+impl Default for ToolState {
+    fn default() -> Self {
+        let mut shortcuts = vec![
+            (Key::I, Tool::Inspect),
+            (Key::R, Tool::Tile(TileKind::Road)),
+            (Key::T, Tool::Tile(TileKind::Wall)),
+            (Key::Z, Tool::Zone(ZoneType::Residential)),
+            (Key::X, Tool::Zone(ZoneType::Commercial)),
+            (Key::V, Tool::Zone(ZoneType::Industrial)),
+            (Key::B, Tool::Bulldoze),
+            (Key::C, Tool::Copy),
+            (Key::P, Tool::Paste),
+        ];
+        let digits = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9, Key::Key0];
+        shortcuts.extend(digits.into_iter().zip(BuildingKind::all()).map(|(key, kind)| (key, Tool::Place(kind))));
+        Self { active: None, shortcuts, hover: None, inspected: None }
+    }
+}
+
+impl ToolState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn active(&self) -> Option<&Tool> {
+        self.active.as_ref()
+    }
+
+    /// Switch tools; `None` when the tool was already active
+    pub fn set(&mut self, tool: Option<Tool>) -> Option<ToolChange> {
+        if self.active == tool {
+            return None;
+        }
+        let exited = std::mem::replace(&mut self.active, tool.clone());
+        Some(ToolChange { exited, entered: tool })
+    }
+
+    pub fn shortcuts(&self) -> &[(Key, Tool)] {
+        &self.shortcuts
+    }
+
+    /// Bind a key to a tool, replacing the key's previous tool
+    pub fn bind(&mut self, key: Key, tool: Tool) {
+        self.shortcuts.retain(|(bound, _)| *bound != key);
+        self.shortcuts.push((key, tool));
+    }
+
+    pub fn tool_for_key(&self, key: &Key) -> Option<&Tool> {
+        self.shortcuts.iter().find(|(bound, _)| bound == key).map(|(_, tool)| tool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_names_round_trip() {
+        let mut tools = vec![Tool::Inspect, Tool::Bulldoze, Tool::Copy, Tool::Paste];
+        tools.extend(TileKind::all().map(Tool::Tile));
+        tools.extend(ZoneType::all().map(Tool::Zone));
+        tools.extend(BuildingKind::all().map(Tool::Place));
+        tools.extend(TerrainTool::all().map(Tool::Terrain));
+        for tool in tools {
+            assert_eq!(Tool::from_name(&tool.name()), Some(tool));
+        }
+        assert_eq!(Tool::Place(BuildingKind::FireStation).name(), "fire_station");
+        assert_eq!(Tool::from_name("demolish"), Some(Tool::Bulldoze));
+        assert_eq!(Tool::from_name("zone_park"), None);
+    }
+
+    #[test]
+    fn test_switching_tools() {
+        let mut state = ToolState::new();
+        let change = state.set(Some(Tool::Tile(TileKind::Road))).unwrap();
+        assert_eq!((change.exited, change.entered), (None, Some(Tool::Tile(TileKind::Road))));
+        assert_eq!(state.set(Some(Tool::Tile(TileKind::Road))), None);
+
+        let change = state.set(None).unwrap();
+        assert_eq!(change.exited, Some(Tool::Tile(TileKind::Road)));
+        assert_eq!(state.tool_for_key(&Key::Key1), Some(&Tool::Place(BuildingKind::House)));
+        state.bind(Key::Key1, Tool::Inspect);
+        assert_eq!(state.tool_for_key(&Key::Key1), Some(&Tool::Inspect));
+    }
+}
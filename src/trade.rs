@@ -0,0 +1,222 @@
+/// Trade with the world outside the map: highway, rail and port connections on the map edge carry the city's
+/// surplus goods out and its shortfall in at a price that moves with what the city trades, settled with the budget
+use crate::economy::{ZoneComponent, ZoneType};
+use crate::ecs::{Component, World};
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+
+/// Goods made each month by one industrial worker
+pub const GOODS_PER_WORKER: u32 = 2;
+/// Goods sold each month by one commercial worker
+pub const GOODS_PER_CLERK: u32 = 2;
+
+/// Ways goods cross the map edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionKind {
+    Highway,
+    Rail,
+    Port,
+}
+
+impl ConnectionKind {
+    /// Goods the connection carries each month, in both directions together
+    pub fn capacity(&self) -> u32 {
+        match self {
+            ConnectionKind::Highway => 100,
+            ConnectionKind::Rail => 250,
+            ConnectionKind::Port => 400,
+        }
+    }
+
+    /// Character drawn on the connection's tile
+    pub fn symbol(&self) -> char {
+        match self {
+            ConnectionKind::Highway => 'h',
+            ConnectionKind::Rail => 'r',
+            ConnectionKind::Port => 'p',
+        }
+    }
+
+    /// Parse a connection kind from its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "highway" => Some(ConnectionKind::Highway),
+            "rail" => Some(ConnectionKind::Rail),
+            "port" => Some(ConnectionKind::Port),
+            _ => None,
+        }
+    }
+}
+
+/// Component for a connection point on the map edge
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalConnectionComponent {
+    pub kind: ConnectionKind,
+}
+
+impl Component for ExternalConnectionComponent {
+    fn validate(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// Prices of the outside market, set per scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TradeConfig {
+    /// Price of one unit of goods when the city trades evenly
+    pub base_price: f64,
+    /// Extra paid on imports over the export price, in percent
+    pub import_markup_percent: u32,
+    /// How far the price moves in a month of trading at full capacity in one direction, in percent
+    pub price_step_percent: u32,
+}
+
+impl Default for TradeConfig {
+    fn default() -> Self {
+        Self { base_price: 10.0, import_markup_percent: 25, price_step_percent: 10 }
+    }
+}
+
+/// The outside market's current price; importing drives it up and exporting drives it down, within half and
+/// double the base price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeMarket {
+    pub config: TradeConfig,
+    pub price: f64,
+}
+
+impl TradeMarket {
+    pub fn new(config: TradeConfig) -> Self {
+        Self { price: config.base_price, config }
+    }
+
+    /// Price the city pays per imported unit
+    pub fn import_price(&self) -> f64 {
+        self.price * (100 + self.config.import_markup_percent) as f64 / 100.0
+    }
+}
+
+impl Default for TradeMarket {
+    fn default() -> Self {
+        Self::new(TradeConfig::default())
+    }
+}
+
+/// One month of trade
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeReport {
+    pub produced: u32,
+    pub consumed: u32,
+    pub exported: u32,
+    pub imported: u32,
+    /// Goods the city needed but its connections couldn't bring in
+    pub shortage: u32,
+    /// Goods the connections could carry
+    pub capacity: u32,
+    /// Market price the month was settled at
+    pub price: f64,
+    /// Export revenue minus import costs
+    pub income: i64,
+}
+
+/// System that settles the city's trade once per budget cycle
+pub struct TradeSystem;
+
+impl TradeSystem {
+    /// Goods all connections on the map carry each month
+    pub fn capacity(world: &World) -> u32 {
+        world.entities_with_components(&[TypeId::of::<ExternalConnectionComponent>()])
+            .into_iter()
+            .filter_map(|entity| world.get_component::<ExternalConnectionComponent>(entity).map(|connection| connection.kind.capacity()))
+            .sum()
+    }
+
+    /// Balance the month's production against consumption through the connections and move the price
+    pub fn settle(world: &World, market: &mut TradeMarket) -> TradeReport {
+        let (mut produced, mut consumed) = (0, 0);
+        for entity in world.entities_with_components(&[TypeId::of::<ZoneComponent>()]) {
+            let Some(zone) = world.get_component::<ZoneComponent>(entity) else { continue };
+            match zone.zone_type {
+                ZoneType::Industrial => produced += zone.population * GOODS_PER_WORKER,
+                ZoneType::Commercial => consumed += zone.population * GOODS_PER_CLERK,
+                ZoneType::Residential => {}
+            }
+        }
+
+        let capacity = Self::capacity(world);
+        let exported = produced.saturating_sub(consumed).min(capacity);
+        let imported = consumed.saturating_sub(produced).min(capacity);
+        let price = market.price;
+        let income = (exported as f64 * price - imported as f64 * market.import_price()).round() as i64;
+
+        if capacity > 0 {
+            let pressure = (imported as f64 - exported as f64) / capacity as f64;
+            let base = market.config.base_price;
+            market.price = (price * (1.0 + pressure * market.config.price_step_percent as f64 / 100.0)).clamp(base / 2.0, base * 2.0);
+        }
+
+        TradeReport {
+            produced,
+            consumed,
+            exported,
+            imported,
+            shortage: consumed.saturating_sub(produced) - imported,
+            capacity,
+            price,
+            income,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_game_components::GridPositionComponent;
+
+    #[test]
+    fn test_surplus_is_exported_and_the_price_follows_trade() {
+        let mut world = World::new();
+        let mut market = TradeMarket::default();
+        world.spawn((GridPositionComponent { x: 1, y: 1 }, ZoneComponent::new(ZoneType::Industrial, 80))).unwrap();
+        world.spawn((GridPositionComponent { x: 2, y: 1 }, ZoneComponent::new(ZoneType::Commercial, 30))).unwrap();
+
+        // Without a connection nothing leaves the map
+        let isolated = TradeSystem::settle(&world, &mut market);
+        assert_eq!((isolated.produced, isolated.consumed, isolated.exported, isolated.income), (160, 60, 0, 0));
+        assert_eq!(market.price, 10.0);
+
+        world.spawn((GridPositionComponent { x: 0, y: 4 }, ExternalConnectionComponent { kind: ConnectionKind::Highway })).unwrap();
+        let report = TradeSystem::settle(&world, &mut market);
+        assert_eq!((report.exported, report.capacity, report.income), (100, 100, 1000));
+        assert_eq!(market.price, 9.0);
+    }
+
+    #[test]
+    fn test_deficit_is_imported_at_a_markup_up_to_capacity() {
+        let mut world = World::new();
+        let mut market = TradeMarket::default();
+        world.spawn((GridPositionComponent { x: 2, y: 1 }, ZoneComponent::new(ZoneType::Commercial, 200))).unwrap();
+        world.spawn((GridPositionComponent { x: 0, y: 4 }, ExternalConnectionComponent { kind: ConnectionKind::Rail })).unwrap();
+
+        let report = TradeSystem::settle(&world, &mut market);
+        assert_eq!((report.imported, report.shortage, report.income), (250, 150, -3125));
+        assert_eq!(market.price, 11.0);
+        for _ in 0..20 {
+            TradeSystem::settle(&world, &mut market);
+        }
+        assert_eq!(market.price, 20.0);
+    }
+}
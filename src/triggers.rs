@@ -0,0 +1,319 @@
+/// Scenario scripting: declarative triggers from the scenario RON, each a condition on the city and the actions
+/// to run once it is met, for campaign-style missions
+/// `TriggerSystem` checks the triggers every update and fires each one at most once; triggers with an objective
+/// label are the mission goals shown in the HUD, with their progress
+use crate::catalog::{BuildingCatalog, CityProgress};
+use crate::construction::{BuildingComponent, BuildingKind};
+use crate::demolition::DemolitionSystem;
+use crate::ecs::{Entity, World};
+use crate::economy::Economy;
+use crate::events::{EventQueue, GameEvent};
+use crate::grid_game_components::GridPositionComponent;
+use crate::notifications::Notification;
+use crate::signing::{self, SigningConfig};
+use crate::simulation::MONTHS_PER_YEAR;
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// When a trigger fires
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum TriggerCondition {
+    /// Residents living in finished housing reach this many
+    PopulationReached(u32),
+    /// The in-game date reaches this month; the game starts in month 1 of year 1
+    Date { year: u32, month: u32 },
+    /// At least `count` finished buildings, of `kind` if given, stand in the rectangle of tiles
+    AreaBuilt {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        count: u32,
+        #[serde(default)]
+        kind: Option<BuildingKind>,
+    },
+}
+
+impl TriggerCondition {
+    /// How far the city is towards the condition, as (current, target), `months` being the number of months
+    /// settled so far; a date counts months from month 1 of year 1, so the first month shows as 1
+    pub fn progress(&self, world: &World, months: u32) -> (u32, u32) {
+        match self {
+            TriggerCondition::PopulationReached(population) => (CityProgress::measure(world).population, *population),
+            TriggerCondition::Date { year, month } => {
+                (months + 1, year.saturating_sub(1) * MONTHS_PER_YEAR + (*month).max(1))
+            }
+            TriggerCondition::AreaBuilt { x, y, width, height, count, kind } => {
+                let built = world.entities_with_components(&[TypeId::of::<BuildingComponent>(), TypeId::of::<GridPositionComponent>()])
+                    .into_iter()
+                    .filter(|entity| {
+                        let (Some(building), Some(pos)) = (world.get_component::<BuildingComponent>(*entity), world.get_component::<GridPositionComponent>(*entity)) else {
+                            return false;
+                        };
+                        kind.is_none_or(|kind| kind == building.kind)
+                            && (*x..x + width).contains(&pos.x)
+                            && (*y..y + height).contains(&pos.y)
+                    })
+                    .count();
+                (built as u32, *count)
+            }
+        }
+    }
+
+    pub fn is_met(&self, world: &World, months: u32) -> bool {
+        let (current, target) = self.progress(world, months);
+        current >= target
+    }
+}
+
+/// What a trigger does when it fires
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum TriggerAction {
+    /// Show the player a message
+    ShowMessage(String),
+    /// Add money to the treasury; a negative amount takes it away
+    GrantMoney(i64),
+    /// Unlock a building kind whatever its catalog requirement
+    UnlockBuilding(BuildingKind),
+    /// Destroy every building within `radius` tiles of a tile, leaving rubble and refunding nothing
+    SpawnDisaster { x: i32, y: i32, radius: i32 },
+}
+
+/// A named condition and the actions to run once it is met
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Trigger {
+    pub name: String,
+    /// Goal shown to the player while the trigger is pending, e.g. "Grow the town to 500 people"
+    #[serde(default)]
+    pub objective: Option<String>,
+    pub when: TriggerCondition,
+    pub actions: Vec<TriggerAction>,
+}
+
+/// A mission goal as the HUD shows it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Objective {
+    /// Name of the trigger behind the objective
+    pub name: String,
+    pub label: String,
+    pub current: u32,
+    pub target: u32,
+    pub completed: bool,
+}
+
+/// Which triggers have fired, stored next to a save file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct TriggerProgress {
+    fired: Vec<String>,
+}
+
+/// System that fires scenario triggers as their conditions are met
+#[derive(Debug, Clone, Default)]
+pub struct TriggerSystem {
+    triggers: Vec<Trigger>,
+    fired: Vec<String>,
+}
+
+impl TriggerSystem {
+    pub fn new(triggers: Vec<Trigger>) -> Self {
+        Self { triggers, fired: Vec::new() }
+    }
+
+    /// Triggers that haven't fired yet
+    pub fn pending(&self) -> Vec<&Trigger> {
+        self.triggers.iter().filter(|trigger| !self.fired.contains(&trigger.name)).collect()
+    }
+
+    /// Names of the triggers fired so far, in firing order
+    pub fn fired(&self) -> &[String] {
+        &self.fired
+    }
+
+    /// The objectives of the scenario in its order, completed ones included
+    pub fn objectives(&self, world: &World, months: u32) -> Vec<Objective> {
+        self.triggers.iter()
+            .filter_map(|trigger| {
+                let label = trigger.objective.clone()?;
+                let (current, target) = trigger.when.progress(world, months);
+                let completed = self.fired.contains(&trigger.name);
+                // Completed objectives stay full even if the city shrinks back below them
+                let current = if completed { target } else { current.min(target) };
+                Some(Objective { name: trigger.name.clone(), label, current, target, completed })
+            })
+            .collect()
+    }
+
+    /// Path of the trigger file stored next to a save file (`city.sav` -> `city.triggers.ron`)
+    pub fn triggers_path_for_save(save_path: &Path) -> PathBuf {
+        save_path.with_extension("triggers.ron")
+    }
+
+    /// Write which triggers have fired next to the given save file
+    pub fn save_alongside(&self, save_path: &Path, signing: &SigningConfig) -> Result<(), Box<dyn Error>> {
+        let progress = TriggerProgress { fired: self.fired.clone() };
+        let content = ron::ser::to_string_pretty(&progress, ron::ser::PrettyConfig::default())?;
+        signing::write_sealed(&Self::triggers_path_for_save(save_path), &content, signing)
+    }
+
+    /// Mark the triggers fired in the given save as fired, so completed objectives stay completed and their
+    /// actions don't run again; a save without a trigger file leaves every trigger pending
+    pub fn load_alongside(&mut self, save_path: &Path, signing: &SigningConfig) -> Result<(), Box<dyn Error>> {
+        let path = Self::triggers_path_for_save(save_path);
+        if !path.exists() {
+            return Ok(());
+        }
+        let progress: TriggerProgress = ron::from_str(&signing::read_sealed(&path, signing)?)?;
+        self.fired = progress.fired;
+        Ok(())
+    }
+
+    /// Fire every pending trigger whose condition holds, in scenario order, returning the rubble of any disasters
+    pub fn update(
+        &mut self,
+        world: &mut World,
+        economy: &mut Economy,
+        catalog: &mut BuildingCatalog,
+        months: u32,
+        events: &mut EventQueue<GameEvent>,
+        notifications: &mut EventQueue<Notification>,
+    ) -> Vec<Entity> {
+        let mut rubble = Vec::new();
+        for trigger in &self.triggers {
+            if self.fired.contains(&trigger.name) || !trigger.when.is_met(world, months) {
+                continue;
+            }
+            for action in &trigger.actions {
+                match action {
+                    TriggerAction::ShowMessage(message) => notifications.push(Notification::info(message)),
+                    TriggerAction::GrantMoney(amount) => {
+                        economy.treasury.balance += amount;
+                        if *amount > 0 {
+                            events.push(GameEvent::MoneyEarned { amount: *amount });
+                        }
+                    }
+                    TriggerAction::UnlockBuilding(kind) => {
+                        if catalog.unlock(*kind) {
+                            notifications.push(Notification::info(&format!("{} unlocked", catalog.get(*kind).name)));
+                        }
+                    }
+                    TriggerAction::SpawnDisaster { x, y, radius } => {
+                        rubble.extend(Self::disaster(world, (*x, *y), *radius, events, notifications));
+                    }
+                }
+            }
+            events.push(GameEvent::TriggerFired { name: trigger.name.clone() });
+            self.fired.push(trigger.name.clone());
+        }
+        rubble
+    }
+
+    // Wreck the buildings and construction sites within `radius` tiles of the center
+    fn disaster(
+        world: &mut World,
+        (cx, cy): (i32, i32),
+        radius: i32,
+        events: &mut EventQueue<GameEvent>,
+        notifications: &mut EventQueue<Notification>,
+    ) -> Vec<Entity> {
+        let struck: Vec<Entity> = world.entities_with_components(&[TypeId::of::<GridPositionComponent>()])
+            .into_iter()
+            .filter(|entity| DemolitionSystem::building_kind(world, *entity).is_some())
+            .filter(|entity| {
+                world.get_component::<GridPositionComponent>(*entity)
+                    .is_some_and(|pos| (pos.x - cx).pow(2) + (pos.y - cy).pow(2) <= radius * radius)
+            })
+            .collect();
+        struck.into_iter()
+            .filter_map(|entity| DemolitionSystem::wreck(world, entity, events, notifications))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::demolition::RubbleComponent;
+
+    fn house_at(world: &mut World, x: i32, y: i32) -> Entity {
+        let house = world.create_entity();
+        world.add_component(house, GridPositionComponent { x, y }).unwrap();
+        BuildingKind::House.spawn_final(world, house).unwrap();
+        house
+    }
+
+    #[test]
+    fn test_triggers_parse_from_ron_and_fire_once() {
+        let triggers: Vec<Trigger> = ron::from_str(r#"[
+            (name: "first_houses", when: AreaBuilt(x: 0, y: 0, width: 4, height: 4, count: 2, kind: Some(House)),
+             actions: [ShowMessage("Welcome home"), GrantMoney(500), UnlockBuilding(School)]),
+            (name: "quake", when: Date(year: 2, month: 1), actions: [SpawnDisaster(x: 1, y: 1, radius: 1)]),
+        ]"#).unwrap();
+        let mut system = TriggerSystem::new(triggers);
+        let mut world = World::new();
+        let mut economy = Economy::new(0);
+        let mut catalog = BuildingCatalog::default();
+        let mut events = EventQueue::new();
+        let mut notifications = EventQueue::new();
+
+        house_at(&mut world, 1, 1);
+        house_at(&mut world, 9, 9);
+        system.update(&mut world, &mut economy, &mut catalog, 0, &mut events, &mut notifications);
+        assert!(system.fired().is_empty());
+
+        house_at(&mut world, 2, 1);
+        system.update(&mut world, &mut economy, &mut catalog, 11, &mut events, &mut notifications);
+        assert_eq!(system.fired(), ["first_houses"]);
+        assert_eq!(economy.treasury.balance, 500);
+        assert!(catalog.is_unlocked(BuildingKind::School));
+        assert_eq!(events.drain(), vec![
+            GameEvent::MoneyEarned { amount: 500 },
+            GameEvent::TriggerFired { name: "first_houses".to_string() },
+        ]);
+
+        // A year in, the disaster wrecks both nearby houses but not the distant one, and nothing fires twice
+        let rubble = system.update(&mut world, &mut economy, &mut catalog, 12, &mut events, &mut notifications);
+        assert_eq!(rubble.len(), 2);
+        assert!(rubble.iter().all(|entity| world.has_component::<RubbleComponent>(*entity)));
+        assert_eq!(world.entities_with_components(&[TypeId::of::<BuildingComponent>()]).len(), 1);
+        assert_eq!(economy.treasury.balance, 500);
+        assert_eq!(system.fired(), ["first_houses", "quake"]);
+        assert!(system.pending().is_empty());
+    }
+
+    #[test]
+    fn test_objectives_track_progress_and_survive_saves() {
+        let triggers: Vec<Trigger> = ron::from_str(r#"[
+            (name: "homes", objective: Some("Build 2 houses"), when: AreaBuilt(x: 0, y: 0, width: 8, height: 8, count: 2), actions: []),
+            (name: "spring", objective: Some("Reach spring"), when: Date(year: 1, month: 4), actions: []),
+            (name: "hidden", when: Date(year: 1, month: 1), actions: []),
+        ]"#).unwrap();
+        let mut system = TriggerSystem::new(triggers.clone());
+        let mut world = World::new();
+        house_at(&mut world, 3, 3);
+
+        let objectives = system.objectives(&world, 1);
+        assert_eq!(objectives.iter().map(|objective| (objective.label.as_str(), objective.current, objective.target)).collect::<Vec<_>>(),
+            vec![("Build 2 houses", 1, 2), ("Reach spring", 2, 4)]);
+        assert!(objectives.iter().all(|objective| !objective.completed));
+
+        house_at(&mut world, 4, 3);
+        system.update(&mut world, &mut Economy::new(0), &mut BuildingCatalog::default(), 1, &mut EventQueue::new(), &mut EventQueue::new());
+        assert_eq!(system.fired(), ["homes", "hidden"]);
+
+        let save_path = std::env::temp_dir().join(format!("triggers_test_{}.sav", std::process::id()));
+        system.save_alongside(&save_path, &SigningConfig::disabled()).unwrap();
+        let mut loaded = TriggerSystem::new(triggers);
+        loaded.load_alongside(&save_path, &SigningConfig::disabled()).unwrap();
+        let _ = fs::remove_file(TriggerSystem::triggers_path_for_save(&save_path));
+
+        // A completed objective stays completed after loading, even once its buildings are gone
+        let empty = World::new();
+        let homes = &loaded.objectives(&empty, 1)[0];
+        assert!(homes.completed);
+        assert_eq!((homes.current, homes.target), (2, 2));
+        assert_eq!(loaded.pending().len(), 1);
+    }
+}
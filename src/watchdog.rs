@@ -0,0 +1,155 @@
+/// Frame budget watchdog: watches how long each tick takes and, after several ticks in a row over budget, steps the
+/// presentation down a degradation level that sends clients fewer floating texts and, at the last level, no move
+/// animations; a long enough run under budget steps it back up
+/// Tick times are wall-clock, so only presentation degrades: the simulation runs the same at every level and stays
+/// reproducible from the action log
+/// A manual override pins the level, e.g. to profile a slow machine at full quality
+use serde::Serialize;
+use std::time::Duration;
+
+/// Tick time the watchdog aims for
+pub const DEFAULT_FRAME_BUDGET: Duration = Duration::from_millis(50);
+/// Consecutive ticks over budget before degrading one level
+pub const OVER_BUDGET_FRAMES: u32 = 5;
+/// Consecutive ticks under budget before recovering one level
+pub const RECOVERY_FRAMES: u32 = 120;
+/// Most degraded level
+pub const MAX_DEGRADATION: u8 = 3;
+
+/// What clients are sent at a degradation level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct QualitySettings {
+    /// Newest floating texts sent to clients, unlimited when `None`
+    pub particle_limit: Option<usize>,
+    /// Whether clients are sent the player's move animation, or snap to the new tile
+    pub move_animations: bool,
+}
+
+impl QualitySettings {
+    pub fn for_level(level: u8) -> Self {
+        match level {
+            0 => Self { particle_limit: None, move_animations: true },
+            1 => Self { particle_limit: Some(24), move_animations: true },
+            2 => Self { particle_limit: Some(8), move_animations: true },
+            _ => Self { particle_limit: Some(0), move_animations: false },
+        }
+    }
+}
+
+/// A change of the automatic degradation level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelChange {
+    Degraded(u8),
+    Recovered(u8),
+}
+
+/// Counts ticks over and under the frame budget and picks the degradation level
+#[derive(Debug, Clone)]
+pub struct FrameWatchdog {
+    budget: Duration,
+    over_budget: u32,
+    under_budget: u32,
+    automatic: u8,
+    manual: Option<u8>,
+}
+
+impl FrameWatchdog {
+    pub fn new(budget: Duration) -> Self {
+        Self { budget, over_budget: 0, under_budget: 0, automatic: 0, manual: None }
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+        self.over_budget = 0;
+        self.under_budget = 0;
+    }
+
+    /// Level in effect: the manual override if there is one, the automatic level otherwise
+    pub fn level(&self) -> u8 {
+        self.manual.unwrap_or(self.automatic)
+    }
+
+    /// Level the watchdog picked from tick times, kept up to date even while overridden
+    pub fn automatic_level(&self) -> u8 {
+        self.automatic
+    }
+
+    pub fn manual_level(&self) -> Option<u8> {
+        self.manual
+    }
+
+    /// Pin the level, or hand it back to the watchdog with `None`
+    pub fn set_manual_level(&mut self, level: Option<u8>) -> Result<(), String> {
+        if let Some(level) = level.filter(|level| *level > MAX_DEGRADATION) {
+            return Err(format!("Degradation level {} is above {}", level, MAX_DEGRADATION));
+        }
+        self.manual = level;
+        Ok(())
+    }
+
+    pub fn settings(&self) -> QualitySettings {
+        QualitySettings::for_level(self.level())
+    }
+
+    /// Record how long a tick took; returns the automatic level's change, if it changed
+    pub fn record(&mut self, duration: Duration) -> Option<LevelChange> {
+        if duration > self.budget {
+            self.under_budget = 0;
+            self.over_budget += 1;
+            if self.over_budget >= OVER_BUDGET_FRAMES && self.automatic < MAX_DEGRADATION {
+                self.over_budget = 0;
+                self.automatic += 1;
+                return Some(LevelChange::Degraded(self.automatic));
+            }
+        } else {
+            self.over_budget = 0;
+            self.under_budget += 1;
+            if self.under_budget >= RECOVERY_FRAMES && self.automatic > 0 {
+                self.under_budget = 0;
+                self.automatic -= 1;
+                return Some(LevelChange::Recovered(self.automatic));
+            }
+        }
+        None
+    }
+}
+
+impl Default for FrameWatchdog {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAME_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_degrades_and_recovers() {
+        let mut watchdog = FrameWatchdog::new(Duration::from_millis(10));
+        let slow = Duration::from_millis(25);
+        for _ in 1..OVER_BUDGET_FRAMES {
+            assert_eq!(watchdog.record(slow), None);
+        }
+        // A fast tick restarts the count
+        watchdog.record(Duration::from_millis(5));
+        for _ in 1..OVER_BUDGET_FRAMES {
+            watchdog.record(slow);
+        }
+        assert_eq!(watchdog.record(slow), Some(LevelChange::Degraded(1)));
+        assert_eq!(watchdog.settings(), QualitySettings::for_level(1));
+
+        watchdog.set_manual_level(Some(0)).unwrap();
+        assert!(watchdog.set_manual_level(Some(MAX_DEGRADATION + 1)).is_err());
+        assert_eq!((watchdog.level(), watchdog.automatic_level()), (0, 1));
+        watchdog.set_manual_level(None).unwrap();
+
+        let changes: Vec<LevelChange> = (0..RECOVERY_FRAMES).filter_map(|_| watchdog.record(Duration::ZERO)).collect();
+        assert_eq!(changes, vec![LevelChange::Recovered(0)]);
+        assert_eq!(watchdog.level(), 0);
+    }
+}
@@ -1,88 +1,551 @@
 /// Web client integration for the clean ECS grid game
-use crate::grid_game_systems::GridGameWorld;
-use crate::rendering::{render_global_grid};
-use tiny_http::{Server, Response, Header, Request, Method};
+use crate::grid_game_systems::{GridGameWorld, BASE_CELL_SIZE, GRID_HEIGHT, GRID_WIDTH};
+use crate::rendering::{render_global_grid, set_global_viewport, HeadlessRenderingDevice, ImageBuffer, WebClientRenderingDevice};
+use crate::rendering::rendering_manager::RenderingManager;
+use crate::rendering::command_builder::rejected_commands as rejected_render_commands;
+use crate::rendering::web_service_manager::{
+    bind_with_fallback, ClientMessage, ConnectionEvent, PendingRequest, RequestPool, RequestPoolStats, ServerMessage,
+    WebServiceManager, HEARTBEAT_INTERVAL, REQUEST_QUEUE_CAPACITY, REQUEST_WORKERS,
+};
+use crate::economy::{TaxRates, ZoneType};
+use crate::services::ServiceType;
+use crate::construction::{BuildingKind, ConstructionSystem, UnderConstructionComponent};
+use crate::grid_game_components::{GridPositionComponent, RenderComponent};
+use crate::agents::AgentComponent;
+use crate::animation::MOVE_ANIMATION_SECONDS;
+use crate::demolition::DemolitionSystem;
+use crate::autotile::TileKind;
+use crate::selection::{AreaSelection, AreaTool, SelectionShape};
+use crate::tools::Tool;
+use crate::pathfinding::PathComponent;
+use crate::ecs::{Entity, Name, Tags};
+use crate::api_middleware::{ApiError, ApiMiddleware};
+use crate::metrics::{Metrics, TickMetrics};
+use crate::chrome_trace::{variant_name, TraceMarker, TraceRecorder, TRACE_FRAMES};
+use crate::input_latency::{InputLatencyTracker, InputTiming};
+use crate::action_log::ActionLog;
+use crate::grid_diff::{GridDiffTracker, GridUpdate};
+use crate::settings::{PlayerSettings, SettingsStore, SETTINGS_DIRECTORY};
+use crate::game_rules::GameMode;
+use crate::debug_tracker::FrameRecord;
+use crate::recording::{self, RecordingFormat};
+use crate::floating_text::FloatingTextSystem;
+use crate::stable_id::{StableId, StableIdSystem};
+use crate::content::{Content, ContentPack, StringTable, BASE_CONTENT_DIRECTORY, MODS_DIRECTORY};
+use crate::shutdown::{ShutdownToken, SHUTDOWN_POLL_INTERVAL};
+use crate::input::{get_global_input_manager, Key};
+use crate::input::web_client_input_device::InputMessage;
+use crate::timeseries::{parse_range, Metric, DAYS_PER_MONTH};
+use crate::trade::{ExternalConnectionComponent, TradeSystem};
+use crate::modifiers::ModifierScope;
+use crate::logistics::{DeliveryComponent, Inventory};
+use crate::labor::Employment;
+use crate::terrain::TerrainTool;
+use crate::signing::{SigningConfig, SIGNING_CONFIG_FILE};
+use crate::crash::{self, CRASH_DIRECTORY};
+use crate::assets::{AssetManifest, PreloadTracker, TEXTURE_DIRECTORY};
+use crate::event_bridge::BridgedEvent;
+use crate::auth::{self, AuthConfig, Role};
+use crate::config::{GameConfig, GAME_CONFIG_FILE};
+use crate::stats::GameStats;
+use crate::simulation::Scenario;
+use crate::service_discovery::{self, DISCOVERY_FILE, GAME_SERVICE, RENDERING_SERVICE};
+use tiny_http::{Server, Response, Header, Method};
 use serde_json;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Save file the server's action log and progress are kept next to
+const SAVE_PATH: &str = "saves/city.sav";
+/// Largest audio or texture file served
+const MAX_ASSET_BYTES: u64 = 16 * 1024 * 1024;
+/// How often the progress next to the save is rewritten while serving
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(30);
 
 /// Web-based ECS game demo
 pub struct WebEcsGameDemo {
     game_world: GridGameWorld,
     address: String,
+    // The action log is appended next to this save
+    save_path: PathBuf,
+    // Textures are served from here, and only the ones found in it are put in the preload manifest
+    texture_directory: PathBuf,
+    // Browser sessions, so a refreshed tab resumes its client and the game pauses while none is connected
+    clients: WebServiceManager,
+    // Per-client settings, saved on disk so they survive reloads and server restarts
+    settings: SettingsStore,
+    // Content packs in load order, served at /api/v1/mods, and their merged UI text
+    content_packs: Vec<ContentPack>,
+    strings: StringTable,
+    // Grid text last sent to each client, so polls only carry the cells that changed
+    grid_diff: GridDiffTracker,
+    // Rate limiting and command body validation, applied before any request reaches the game
+    middleware: ApiMiddleware,
+    // Texture loading of each connected client, which gates its state polls
+    preload: PreloadTracker,
+    // Served at /metrics; traffic counters come from the request pool while it runs
+    metrics: Metrics,
+    tick_metrics: TickMetrics,
+    // System spans and gameplay events of the recent ticks, exported at /debug/trace
+    traces: TraceRecorder,
+    // How long input batches take from the client to the response frame, by pipeline stage
+    input_latency: InputLatencyTracker,
+    pool_stats: Option<Arc<RequestPoolStats>>,
+    started: Instant,
 }
 
 impl WebEcsGameDemo {
     pub fn new(address: &str) -> Self {
         let mut game_world = GridGameWorld::new();
         game_world.initialize_game();
+        let content = Content::discover(Path::new(BASE_CONTENT_DIRECTORY), Path::new(MODS_DIRECTORY)).unwrap_or_else(|e| {
+            eprintln!("⚠️ Warning: Failed to load content packs, using the built-in content: {}", e);
+            Content::default()
+        });
+        game_world.apply_content(&content);
+        game_world.events_director.enabled = true;
+        let config = GameConfig::load_or_default(Path::new(GAME_CONFIG_FILE)).unwrap_or_else(|e| {
+            eprintln!("⚠️ Warning: Failed to read {}, using the default configuration: {}", GAME_CONFIG_FILE, e);
+            GameConfig::default()
+        });
+        game_world.signing = SigningConfig::load_or_default(Path::new(SIGNING_CONFIG_FILE)).unwrap_or_else(|e| {
+            eprintln!("⚠️ Warning: Failed to read {}, shared files are neither signed nor checked: {}", SIGNING_CONFIG_FILE, e);
+            SigningConfig::default()
+        });
+        // Devices registered globally (the web client input device outside headless mode) feed the game's InputSystem
+        if let Ok(devices) = get_global_input_manager() {
+            if let Err(e) = game_world.set_input_manager(devices) {
+                eprintln!("⚠️ Warning: Failed to attach the global input devices: {}", e);
+            }
+        }
         
         Self {
             game_world,
             address: address.to_string(),
+            save_path: PathBuf::from(SAVE_PATH),
+            texture_directory: PathBuf::from(TEXTURE_DIRECTORY),
+            clients: WebServiceManager::new(address),
+            settings: SettingsStore::new(Path::new(SETTINGS_DIRECTORY)),
+            content_packs: content.packs,
+            strings: content.strings,
+            grid_diff: GridDiffTracker::new(),
+            middleware: ApiMiddleware::with_auth(config.auth),
+            preload: PreloadTracker::new(),
+            metrics: Metrics::new(),
+            tick_metrics: TickMetrics::new(),
+            traces: TraceRecorder::default(),
+            input_latency: InputLatencyTracker::default(),
+            pool_stats: None,
+            started: Instant::now(),
         }
     }
     
-    /// Process input by updating the ECS InputComponent based on current input state
-    fn update_ecs_input_from_javascript(&mut self, dx: i32, dy: i32) {
-        // Find the player entity with InputComponent and update it
-        for entity in self.game_world.world.get_all_entities() {
-            if self.game_world.world.has_component::<crate::grid_game_components::PlayerComponent>(*entity) {
-                if let Some(mut input_comp) = self.game_world.world.get_component_mut::<crate::grid_game_components::InputComponent>(*entity) {
-                    // Clear previous input
-                    input_comp.clear();
-                    
-                    // Set new input based on JavaScript input
-                    if dx < 0 { input_comp.move_left = true; }
-                    if dx > 0 { input_comp.move_right = true; }
-                    if dy < 0 { input_comp.move_up = true; }
-                    if dy > 0 { input_comp.move_down = true; }
-                    
-                    break;
-                }
-            }
-        }
+    /// Play a game mode other than the city builder
+    pub fn with_mode(mut self, mode: GameMode) -> Self {
+        self.game_world.set_rules(mode.rules());
+        self
+    }
+    
+    /// Play a scenario: its economy, buildings and connections, and the triggers behind its objectives
+    pub fn with_scenario(mut self, scenario: &Scenario) -> Result<Self, String> {
+        scenario.apply(&mut self.game_world)?;
+        Ok(self)
+    }
+    
+    /// Keep the action log next to another save, e.g. in a temporary directory for tests
+    pub fn with_save_path(mut self, save_path: &Path) -> Self {
+        self.save_path = save_path.to_path_buf();
+        self
+    }
+    
+    /// Keep the per-client settings in another directory, e.g. a temporary one for tests
+    pub fn with_settings_directory(mut self, directory: &Path) -> Self {
+        self.settings = SettingsStore::new(directory);
+        self
+    }
+    
+    /// Serve textures from another directory, e.g. one a test filled
+    pub fn with_texture_directory(mut self, directory: &Path) -> Self {
+        self.texture_directory = directory.to_path_buf();
+        self
+    }
+    
+    /// Give requests their roles by other rules than `game.ron`'s, e.g. trusting a test on this machine
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.middleware = ApiMiddleware::with_auth(auth);
+        self
+    }
+    
+    pub fn game_world(&self) -> &GridGameWorld {
+        &self.game_world
     }
     
     /// Start the web server and game loop
     pub fn run(&mut self) -> Result<(), String> {
+        self.run_until(&ShutdownToken::new())
+    }
+    
+    /// Run the web server and game loop until shutdown is requested
+    pub fn run_until(&mut self, shutdown: &ShutdownToken) -> Result<(), String> {
         println!("🚀 Starting Web ECS Game Demo");
         println!("==============================");
         
         
         // Test the global rendering manager by rendering a grid
-        if let Err(e) = render_global_grid(10, 8, 32.0) {
+        if let Err(e) = render_global_grid(GRID_WIDTH as u32, GRID_HEIGHT as u32, BASE_CELL_SIZE) {
             eprintln!("⚠️ Warning: Failed to render initial grid via global manager: {}", e);
         } else {
             println!("✅ Initial grid rendered via global rendering manager");
         }
         
-        let server = Server::http(&self.address)
+        let (server, bound) = bind_with_fallback(&self.address)
             .map_err(|e| format!("Failed to start HTTP server: {}", e))?;
+        let requested_port = self.address.rsplit_once(':').and_then(|(_, port)| port.parse::<u16>().ok());
+        if requested_port.is_some_and(|port| port != 0 && port != bound.port()) {
+            println!("⚠️ {} is not available, listening on {} instead", self.address, bound);
+        }
+        self.address = bound.to_string();
+        service_discovery::register_service(GAME_SERVICE, bound);
+        let services = service_discovery::registered_services();
         
-        println!("🌐 Web ECS Game server started on http://{}", &self.address);
-        println!("🎯 Open http://{} in your browser to play", &self.address);
+        let url = services.url(GAME_SERVICE).unwrap_or_else(|| format!("http://{}", bound));
+        println!("🌐 Web ECS Game server started on {}", url);
+        println!("🎯 Open {} in your browser to play", url);
         println!("📱 Use WASD keys to move the player");
         println!("🔧 Using ECS with JavaScript input libraries");
-        println!("📡 Rendering: http://localhost:8081 | Input: JavaScript InputManager");
+        println!("{}", services.summary());
+        if let Err(e) = services.save(Path::new(DISCOVERY_FILE)) {
+            eprintln!("⚠️ Warning: Failed to write {}: {}", DISCOVERY_FILE, e);
+        }
         println!("");
         
-        // HTTP server loop
-        for request in server.incoming_requests() {
-            if let Err(e) = self.handle_request(request) {
-                eprintln!("Error handling request: {}", e);
+        let result = self.serve(server, shutdown);
+        services.remove_saved(Path::new(DISCOVERY_FILE));
+        result
+    }
+    
+    /// Run the game loop on requests from a server that is already listening, until shutdown is requested
+    pub fn serve(&mut self, server: Server, shutdown: &ShutdownToken) -> Result<(), String> {
+        // Worker threads do the socket I/O, so a slow client can't stall the game; this thread only
+        // handles requests. It wakes up regularly so silent clients are dropped and shutdown is noticed
+        let requests = RequestPool::start(server, REQUEST_WORKERS, REQUEST_QUEUE_CAPACITY);
+        self.pool_stats = Some(requests.stats());
+        self.load_progress();
+        // The last ticks are kept so a crash bundle shows what led up to a panic
+        self.game_world.record_crash_frames(true);
+        // The log records the session's seed, so replays hand out the IDs its demolitions name
+        self.game_world.actions.set_stable_id_seed(self.game_world.stable_id_seed);
+        let mut progress_saved = Instant::now();
+        while !shutdown.is_requested() {
+            if let Some(request) = requests.recv_timeout(HEARTBEAT_INTERVAL.min(SHUTDOWN_POLL_INTERVAL)) {
+                if let Err(e) = self.handle_request(request) {
+                    eprintln!("Error handling request: {}", e);
+                }
+            }
+            
+            // Player commands are appended as they happen, so the log survives a crash
+            if let Err(e) = self.game_world.actions.append_alongside(&self.save_path) {
+                eprintln!("Failed to write action log: {}", e);
+            }
+            if progress_saved.elapsed() >= PROGRESS_SAVE_INTERVAL {
+                if let Err(e) = self.save_progress() {
+                    eprintln!("Failed to save progress: {}", e);
+                }
+                progress_saved = Instant::now();
+            }
+            
+            for client_id in self.clients.heartbeat(Instant::now()) {
+                println!("🔌 Client {} timed out", client_id);
             }
+            self.apply_connection_events();
         }
         
+        // Stop accepting connections and flush the responses still being written
+        requests.stop();
+        if let Err(e) = self.save_progress() {
+            eprintln!("Failed to save progress: {}", e);
+        }
+        println!("🛑 Web ECS Game server on {} stopped", self.address);
+        Ok(())
+    }
+    
+    /// Pick up the progress a previous run kept next to the save
+    fn load_progress(&mut self) {
+        match GameStats::load_alongside(&self.save_path, &self.game_world.signing) {
+            Ok(stats) => self.game_world.stats = stats,
+            Err(e) => eprintln!("⚠️ Warning: Failed to load the statistics next to {}: {}", self.save_path.display(), e),
+        }
+        if let Err(e) = self.game_world.triggers.load_alongside(&self.save_path, &self.game_world.signing) {
+            eprintln!("⚠️ Warning: Failed to load the scenario triggers next to {}: {}", self.save_path.display(), e);
+        }
+        let game = &mut self.game_world;
+        if let Err(e) = game.events_director.load_alongside(&self.save_path, &mut game.economy, &game.signing) {
+            eprintln!("⚠️ Warning: Failed to load the city events next to {}: {}", self.save_path.display(), e);
+        }
+    }
+    
+    /// Write the progress that isn't in the action log next to the save
+    fn save_progress(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = self.save_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.game_world.stats.save_alongside(&self.save_path, &self.game_world.signing)?;
+        self.game_world.triggers.save_alongside(&self.save_path, &self.game_world.signing)?;
+        self.game_world.events_director.save_alongside(&self.save_path, &self.game_world.signing)?;
         Ok(())
     }
     
+    /// Run one simulation update, recording its timings for /metrics
+    /// A panicking update writes a crash bundle before it takes the server down
+    fn tick(&mut self) {
+        let start = Instant::now();
+        let mut systems: Vec<(&'static str, Duration)> = Vec::new();
+        let mut last = start;
+        // Events and notifications are marked at the end of the system that raised them; the queues are
+        // cleared at the end of the tick, so only what was added since the previous system is new
+        let mut markers = Vec::new();
+        let (mut seen_events, mut seen_notifications) = (0, 0);
+        let result = crash::guard(&mut self.game_world, Path::new(CRASH_DIRECTORY), |game| game.update_traced(&mut |system, game| {
+            let now = Instant::now();
+            systems.push((system, now - last));
+            last = now;
+            let at = now - start;
+            markers.extend(game.events.iter().skip(seen_events).map(|event| {
+                let detail = format!("{:?}", event);
+                TraceMarker { name: variant_name(&detail).to_string(), category: "event", at, detail }
+            }));
+            markers.extend(game.notifications.iter().skip(seen_notifications).map(|notification| TraceMarker {
+                name: notification.message.clone(),
+                category: "notification",
+                at,
+                detail: format!("{:?}", notification.severity),
+            }));
+            (seen_events, seen_notifications) = (game.events.len(), game.notifications.len());
+        }));
+        if let Err(e) = result {
+            eprintln!("Error updating the game: {}", e);
+        }
+        self.tick_metrics.record(&mut self.metrics, last, last - start, &systems);
+        self.traces.record(self.game_world.tick, start, &systems, markers);
+        self.game_world.record_frame_time(last - start);
+        self.deliver_bridged_events();
+    }
+    
+    /// Move the gameplay events published this tick into their subscribers' message queues
+    fn deliver_bridged_events(&mut self) {
+        let clients: Vec<String> = self.game_world.bridge.pending_clients().into_iter().map(str::to_string).collect();
+        for client_id in clients {
+            for event in self.game_world.bridge.take(&client_id) {
+                self.clients.send_message(&client_id, ServerMessage::Event { topic: event.topic, payload: event.payload });
+            }
+        }
+    }
+    
+    /// Active tool with its cursor ghost, and the keyboard shortcuts for the toolbar
+    fn tool_state_json(&self) -> serde_json::Value {
+        let tools = &self.game_world.tools;
+        let shortcuts: Vec<serde_json::Value> = tools.shortcuts().iter()
+            .map(|(key, tool)| serde_json::json!({"key": key, "tool": tool.name()}))
+            .collect();
+        serde_json::json!({
+            "tool": tools.active().map(Tool::name),
+            "ghost": tools.active().and_then(Tool::ghost_texture),
+            "shortcuts": shortcuts
+        })
+    }
+    
+    /// Degradation level of the frame budget watchdog and what it changes
+    fn watchdog_json(&self) -> serde_json::Value {
+        let watchdog = &self.game_world.watchdog;
+        serde_json::json!({
+            "level": watchdog.level(),
+            "automatic": watchdog.automatic_level(),
+            "manual": watchdog.manual_level(),
+            "budgetSeconds": watchdog.budget().as_secs_f64(),
+            "settings": watchdog.settings()
+        })
+    }
+    
+    /// Every metric in the Prometheus text format, with gauges read at scrape time
+    fn render_metrics(&mut self) -> String {
+        let metrics = &mut self.metrics;
+        metrics.set("citybuilder_entities", "Live entities", &[], self.game_world.world.get_all_entities().len() as f64);
+        metrics.set("citybuilder_connected_clients", "Connected browser clients", &[], self.clients.client_count() as f64);
+        metrics.set("citybuilder_uptime_seconds", "Seconds since the server started", &[], self.started.elapsed().as_secs_f64());
+        if let Some(stats) = &self.pool_stats {
+            let counters = [
+                ("citybuilder_http_queued_total", "HTTP requests queued for the game thread", &stats.accepted),
+                ("citybuilder_http_busy_total", "HTTP requests turned away with 503 because the queue was full", &stats.rejected),
+                ("citybuilder_http_response_bytes_total", "HTTP response body bytes sent", &stats.bytes_sent),
+            ];
+            for (name, help, counter) in counters {
+                metrics.set_counter(name, help, &[], counter.load(Ordering::Relaxed) as f64);
+            }
+        }
+        for (command, rejected) in rejected_render_commands() {
+            metrics.set_counter(
+                "citybuilder_render_commands_rejected_total",
+                "Invalid render commands the rendering manager turned away",
+                &[("command", command)],
+                rejected as f64,
+            );
+        }
+        self.input_latency.export(metrics);
+        let watchdog = &self.game_world.watchdog;
+        metrics.set("citybuilder_degradation_level", "Simulation degradation level picked by the frame budget watchdog or set by hand", &[], watchdog.level() as f64);
+        metrics.set("citybuilder_frame_budget_seconds", "Tick time the frame budget watchdog aims for", &[], watchdog.budget().as_secs_f64());
+        metrics.set_counter("citybuilder_bridged_events_dropped_total", "Events dropped from full event bridge queues", &[], self.game_world.bridge.dropped() as f64);
+        let queries = self.game_world.world.query_cache_stats();
+        metrics.set_counter("citybuilder_query_cache_hits_total", "Entity queries answered from the query cache", &[], queries.hits as f64);
+        metrics.set_counter("citybuilder_query_cache_misses_total", "Entity queries recomputed from the component pools", &[], queries.misses as f64);
+        metrics.render()
+    }
+    
+    /// Push the resized viewport to rendering clients and redraw the grid to fit it
+    fn rerender_viewport(&self) {
+        let (content_width, content_height) = self.game_world.content_size();
+        let result = set_global_viewport(&self.game_world.camera, content_width, content_height)
+            .and_then(|_| render_global_grid(GRID_WIDTH as u32, GRID_HEIGHT as u32, BASE_CELL_SIZE));
+        if let Err(e) = result {
+            eprintln!("⚠️ Warning: Failed to re-render the resized view: {}", e);
+        }
+    }
+    
+    /// Auto-pause while no browser is connected and resume when one (re)connects
+    fn apply_connection_events(&mut self) {
+        for event in self.clients.drain_connection_events() {
+            match event {
+                ConnectionEvent::AllClientsDisconnected => self.game_world.set_auto_paused(true),
+                ConnectionEvent::ClientConnected { client_id } | ConnectionEvent::ClientReconnected { client_id } => {
+                    // A reconnecting page starts with an empty grid
+                    self.grid_diff.invalidate(&client_id);
+                    self.game_world.set_auto_paused(false)
+                }
+                ConnectionEvent::ClientDisconnected { client_id } => {
+                    self.grid_diff.invalidate(&client_id);
+                    self.preload.forget(&client_id);
+                    self.settings.forget(&client_id);
+                    self.game_world.bridge.forget(&client_id);
+                }
+            }
+        }
+    }
+    
+    /// The grid for a poll: the cells changed since update `ack` for a known client, otherwise the whole grid as
+    /// `gameState`; a known client is told the update's number as `gridSeq`, to acknowledge once it applied it
+    /// A known client resyncing from the whole grid is also sent the current frame as a `FullFrame` message
+    fn grid_update_json(&mut self, client: Option<&str>, ack: Option<u64>) -> serde_json::Value {
+        let game_state = self.game_world.get_game_state();
+        let Some(client) = client else {
+            return serde_json::json!({"gameState": game_state});
+        };
+        match self.grid_diff.update(client, ack, &game_state, &self.game_world.camera) {
+            (seq, GridUpdate::Full(game_state)) => serde_json::json!({
+                "gameState": game_state,
+                "gridSeq": seq,
+                "messages": [ServerMessage::FullFrame { commands: self.frame_commands() }]
+            }),
+            (seq, GridUpdate::Patches(patches)) => serde_json::json!({"gridPatches": patches, "gridSeq": seq}),
+        }
+    }
+    
+    /// The player's move animation for clients to finish playing locally (null when standing still, or when the
+    /// watchdog turned animations off)
+    fn player_animation(&self) -> serde_json::Value {
+        if !self.game_world.watchdog.settings().move_animations {
+            return serde_json::Value::Null;
+        }
+        match self.game_world.player_animation() {
+            Some((from, to, progress)) => serde_json::json!({
+                "from": {"x": from.x, "y": from.y},
+                "to": {"x": to.x, "y": to.y},
+                "progress": progress,
+                "durationMs": MOVE_ANIMATION_SECONDS * 1000.0
+            }),
+            None => serde_json::Value::Null,
+        }
+    }
+    
+    /// Floating texts at their current position in tiles, with their fade applied to the color; the watchdog's
+    /// particle limit keeps only the newest
+    fn floating_texts_json(&self) -> serde_json::Value {
+        FloatingTextSystem::newest(&self.game_world.world, self.game_world.watchdog.settings().particle_limit).into_iter()
+            .map(|text| {
+                let position = text.position();
+                serde_json::json!({
+                    "text": text.text,
+                    "x": position.x,
+                    "y": position.y,
+                    "color": [text.color.r, text.color.g, text.color.b, text.color.a * text.alpha()]
+                })
+            })
+            .collect()
+    }
+    
+    /// Everything a freshly (re)connected client needs to draw the current frame
+    fn full_frame(&self) -> serde_json::Value {
+        let player_pos = self.game_world.get_player_position().unwrap_or((0, 0));
+        let (view_width, view_height) = self.game_world.camera.view_dimensions();
+        serde_json::json!({
+            "gameState": self.game_world.get_game_state(),
+            "playerPosition": {"x": player_pos.0, "y": player_pos.1},
+            "console": self.game_world.console,
+            "paused": self.game_world.paused,
+            "mode": self.game_world.rules.name(),
+            "outcome": self.game_world.outcome,
+            "playerAnimation": self.player_animation(),
+            "viewport": {
+                "width": view_width,
+                "height": view_height,
+                "devicePixelRatio": self.game_world.camera.device_pixel_ratio(),
+                "cellSize": self.game_world.view_cell_size()
+            }
+        })
+    }
+    
+    /// The render commands of the current state, as replayed to (re)connecting clients in a `FullFrame`
+    fn frame_commands(&self) -> Vec<String> {
+        self.game_world.render_commands().into_iter().map(WebClientRenderingDevice::serialize_command).collect()
+    }
+    
+    /// Render the current state on the server, framed like the browser view
+    fn render_screenshot(&self) -> Result<ImageBuffer, Box<dyn std::error::Error>> {
+        let (width, height) = self.game_world.camera.backing_size();
+        let mut manager = RenderingManager::new(Box::new(HeadlessRenderingDevice::new(width, height)?));
+        manager.initialize()?;
+        
+        let (content_width, content_height) = self.game_world.content_size();
+        manager.set_viewport(&self.game_world.camera, content_width, content_height)?;
+        for command in self.game_world.render_commands() {
+            manager.execute_command(command)?;
+        }
+        manager.capture_frame()
+    }
+    
     /// Handle HTTP requests
-    fn handle_request(&mut self, request: Request) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_request(&mut self, request: PendingRequest) -> Result<(), Box<dyn std::error::Error>> {
         let method = request.method().clone();
         let url = request.url().to_string();
         
         println!("{} {}", method, url);
         
+        // Clients are told apart by IP, so several tabs of one browser share a rate limit
+        self.metrics.increment("citybuilder_http_requests_total", "HTTP requests handled", &[("method", method.as_str())], 1.0);
+        let client = request.remote_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+        let token = auth::request_token(request.header("Authorization"), request.header("Cookie"));
+        let role = match self.middleware.check(&client, token.as_deref(), &method, &url, request.body(), Instant::now()) {
+            Ok(role) => role,
+            Err(error) => {
+                println!("⛔ {} {} rejected: {}", method, url, error.message);
+                self.metrics.increment("citybuilder_http_rejected_total", "HTTP requests rejected by the middleware", &[("code", error.code)], 1.0);
+                request.respond(error.to_response())?;
+                return Ok(());
+            }
+        };
+        
         match (method, url.as_str()) {
             (Method::Get, "/") => {
                 // Serve the generic HTML template from web/game-template.html
@@ -113,35 +576,35 @@ impl WebEcsGameDemo {
                 // Parse the movement command
                 if let Ok(move_data) = serde_json::from_str::<serde_json::Value>(&body) {
                     if let Some(direction) = move_data["direction"].as_str() {
-                        let (dx, dy) = match direction {
-                            "up" => (0, -1),
-                            "down" => (0, 1),
-                            "left" => (-1, 0),
-                            "right" => (1, 0),
-                            _ => (0, 0),
+                        let key = match direction {
+                            "up" => Some(Key::ArrowUp),
+                            "down" => Some(Key::ArrowDown),
+                            "left" => Some(Key::ArrowLeft),
+                            "right" => Some(Key::ArrowRight),
+                            _ => None,
                         };
                         
-                        // Update ECS input state
-                        self.update_ecs_input_from_javascript(dx, dy);
-                        
-                        let moved = self.game_world.move_player(dx, dy);
-                        
-                        // Update the game systems after movement
-                        let _ = self.game_world.update();
+                        // Feed the key into the shared Input resource; the update moves the player
+                        let before = self.game_world.get_player_position();
+                        if let Some(key) = key {
+                            self.game_world.queue_key_tap(key);
+                        }
+                        self.tick();
+                        let moved = self.game_world.get_player_position() != before;
                         
                         // Send back the game state
-                        let game_state = self.game_world.get_game_state();
+                        let mut response_data = self.grid_update_json(move_data["clientId"].as_str(), move_data["ack"].as_u64());
                         let player_pos = self.game_world.get_player_position().unwrap_or((0, 0));
                         
-                        let response_data = serde_json::json!({
+                        merge_json(&mut response_data, serde_json::json!({
                             "success": moved,
-                            "gameState": game_state,
                             "playerPosition": {
                                 "x": player_pos.0,
                                 "y": player_pos.1
                             },
+                            "playerAnimation": self.player_animation(),
                             "inputMethod": "JavaScript Libraries + ECS"
-                        });
+                        }));
                         
                         let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                             .map_err(|_| "Failed to create header")?;
@@ -154,22 +617,29 @@ impl WebEcsGameDemo {
                     request.respond(response)?;
                 }
             }
-            (Method::Get, "/state") => {
+            (Method::Get, path) if path == "/state" || path.starts_with("/state?") => {
                 // For polling-based input, JavaScript will handle input and send via /move
-                // This endpoint just returns current game state
-                let game_state = self.game_world.get_game_state();
+                // This endpoint just returns current game state, as changed cells when the client is given
+                // A client still preloading textures only gets its progress, and the whole grid once it's done
+                let client = query_param(&url, "client");
+                if let Some(progress) = client.filter(|client| !self.preload.is_ready(client)).and_then(|client| self.preload.progress(client)) {
+                    respond_json(request, &serde_json::json!({"loading": progress}))?;
+                    return Ok(());
+                }
+                let ack = query_param(&url, "ack").and_then(|ack| ack.parse().ok());
+                let mut response_data = self.grid_update_json(client, ack);
                 let player_pos = self.game_world.get_player_position().unwrap_or((0, 0));
                 
-                let response_data = serde_json::json!({
-                    "gameState": game_state,
+                merge_json(&mut response_data, serde_json::json!({
                     "playerPosition": {
                         "x": player_pos.0,
                         "y": player_pos.1
                     },
                     "inputMethod": "JavaScript Libraries with ECS Backend",
                     "moved": false,
-                    "lastInput": "Polling mode - input via JavaScript"
-                });
+                    "lastInput": "Polling mode - input via JavaScript",
+                    "floatingTexts": self.floating_texts_json()
+                }));
                 
                 let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
                     .map_err(|_| "Failed to create header")?;
@@ -183,7 +653,8 @@ impl WebEcsGameDemo {
                     "ecsInputComponentActive": true,
                     "inputLibrary": "input-manager.js",
                     "renderingLibrary": "rendering-manager.js",
-                    "renderingPort": "localhost:8081"
+                    "renderingPort": service_discovery::registered_services().address(RENDERING_SERVICE).map(|address| address.to_string()),
+                    "services": service_discovery::registered_services().urls()
                 });
                 
                 let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
@@ -191,6 +662,858 @@ impl WebEcsGameDemo {
                 let response = Response::from_string(response_data.to_string()).with_header(header);
                 request.respond(response)?;
             }
+            (Method::Post, "/api/v1/input") => {
+                // Body: {"events": [{"KeyPress": {"key": "Backquote"}}, {"TextInput": {"text": "help"}}], "sentAt": 1712345678901}
+                // Browser messages share the endpoint: Resize, VisibilityChange and Paste
+                // `sentAt` is the client's Date.now() when it sent the batch, for latency measurements
+                let mut request = request;
+                let received = request.received_at();
+                let body = read_json_body(&mut request)?;
+                let messages: Vec<InputMessage> = serde_json::from_value(body["events"].clone()).unwrap_or_default();
+                
+                // The console and the frame debugger keys, and all typing while the console is open, are dropped for everyone but admins
+                let console_open = self.game_world.console.is_open();
+                for event in messages.iter().map(InputMessage::to_event) {
+                    if role == Role::Admin || !auth::is_admin_input(&event, console_open) {
+                        self.game_world.queue_input(event);
+                    }
+                }
+                self.tick();
+                let applied = SystemTime::now();
+                if self.game_world.take_viewport_change() {
+                    self.rerender_viewport();
+                }
+                
+                let mut response_data = self.full_frame();
+                response_data["accepted"] = serde_json::json!(messages.len());
+                response_data["clipboard"] = serde_json::json!(self.game_world.take_clipboard());
+                self.input_latency.record(&InputTiming {
+                    client_sent: body["sentAt"].as_f64().and_then(InputTiming::client_time),
+                    received,
+                    applied,
+                    dispatched: SystemTime::now(),
+                });
+                response_data["latency"] = serde_json::json!(self.input_latency.recent_report());
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/connect") => {
+                // Body: {"clientId": "client_1_ab12"} to resume a session, or {} for a new client
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let previous_id = body["clientId"].as_str();
+                
+                self.clients.retain_frame(self.frame_commands());
+                let client_id = self.clients.register_client(previous_id)?;
+                let reconnected = previous_id == Some(client_id.as_str());
+                self.apply_connection_events();
+                
+                // The browser applies UI scale and theme; key bindings, audio and accessibility take effect here
+                let settings = self.settings.get(&client_id);
+                settings.apply(&mut self.game_world);
+                
+                // State polls wait until the client has loaded every texture the game can draw
+                let manifest = AssetManifest::build(&self.game_world.catalog, self.game_world.camera.projection())
+                    .present_in(&self.texture_directory);
+                self.preload.start(&client_id, manifest.len());
+                let response_data = serde_json::json!({
+                    "clientId": client_id,
+                    "reconnected": reconnected,
+                    "role": role.name(),
+                    "settings": settings,
+                    "preload": {"manifest": manifest, "progress": self.preload.progress(&client_id)},
+                    "frame": self.full_frame(),
+                    "messages": self.clients.take_messages(&client_id)
+                });
+                respond_json(request, &response_data)?;
+            }
+            // Settings are only kept for clients the server handed an ID to
+            (Method::Get | Method::Put, path) if path.starts_with("/api/v1/settings")
+                && !self.clients.is_registered(query_param(path, "client").unwrap_or_default()) => {
+                request.respond(ApiError::not_found("unknown_client", "Connect before reading or saving settings").to_response())?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/settings") => {
+                let client_id = query_param(path, "client").unwrap_or_default();
+                let response_data = serde_json::to_value(self.settings.get(client_id))?;
+                respond_json(request, &response_data)?;
+            }
+            (Method::Put, path) if path.starts_with("/api/v1/settings") => {
+                // Body: PlayerSettings as JSON; fields left out keep their defaults
+                let client_id = query_param(path, "client").unwrap_or_default().to_string();
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                
+                let response_data = match serde_json::from_value::<PlayerSettings>(body) {
+                    Ok(settings) => match self.settings.put(&client_id, settings.clone()) {
+                        Ok(()) => {
+                            settings.apply(&mut self.game_world);
+                            serde_json::json!({"success": true, "settings": settings})
+                        }
+                        Err(error) => serde_json::json!({"success": false, "error": error.to_string()}),
+                    },
+                    Err(error) => serde_json::json!({"success": false, "error": error.to_string()}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/heartbeat") => {
+                // Body: {"clientId": "client_1_ab12", "pong": 7}; "pong" answers the last ping, if any
+                // The response carries the client's queued messages, including new pings
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let now = Instant::now();
+                let client_id = body["clientId"].as_str().unwrap_or_default().to_string();
+                
+                if let Some(nonce) = body["pong"].as_u64() {
+                    self.clients.record_pong(&client_id, nonce, now);
+                }
+                self.clients.heartbeat(now);
+                self.apply_connection_events();
+                
+                let response_data = serde_json::json!({
+                    "messages": self.clients.take_messages(&client_id),
+                    "paused": self.game_world.paused
+                });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/capture") => {
+                // Body: {"clientId": "...", "width": 800, "height": 600, "rgba": "<base64>"}, answering a CaptureFrame message
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let message = ClientMessage::FrameCaptured {
+                    client_id: body["clientId"].as_str().unwrap_or_default().to_string(),
+                    width: body["width"].as_u64().unwrap_or(0) as u32,
+                    height: body["height"].as_u64().unwrap_or(0) as u32,
+                    rgba: body["rgba"].as_str().unwrap_or_default().to_string(),
+                };
+                self.clients.handle_client_message(&message, Instant::now());
+                respond_json(request, &serde_json::json!({"success": true}))?;
+            }
+            (Method::Get, path) if path.starts_with("/debug/screenshot") => {
+                // Server-side render by default; ?source=client returns the browser's last canvas capture
+                // and asks connected browsers for a fresh one
+                let image = if query_param(path, "source") == Some("client") {
+                    self.clients.request_frame_capture();
+                    self.clients.latest_capture()
+                        .ok_or_else(|| "No browser has captured a frame yet; try again shortly".into())
+                } else {
+                    self.render_screenshot()
+                };
+                
+                match image {
+                    Ok(image) => {
+                        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+                        let path = PathBuf::from("screenshots").join(format!("screenshot_{}.png", timestamp));
+                        if let Err(e) = image.save_png(&path) {
+                            eprintln!("⚠️ Warning: Failed to save {}: {}", path.display(), e);
+                        }
+                        
+                        let header = Header::from_bytes(&b"Content-Type"[..], &b"image/png"[..])
+                            .map_err(|_| "Failed to create header")?;
+                        request.respond(Response::from_data(image.to_png()).with_header(header))?;
+                    }
+                    Err(e) => respond_json(request, &serde_json::json!({"error": e.to_string()}))?,
+                }
+            }
+            (Method::Get, "/metrics") => {
+                let header = Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+                    .map_err(|_| "Failed to create header")?;
+                request.respond(Response::from_string(self.render_metrics()).with_header(header))?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/actions") => {
+                // Action log export as JSON lines; ?since=TICK skips earlier commands
+                let since = query_param(path, "since").and_then(|tick| tick.parse().ok()).unwrap_or(0);
+                let body = ActionLog::to_jsonl(self.game_world.actions.since(since))?;
+                let header = Header::from_bytes(&b"Content-Type"[..], &b"application/x-ndjson"[..])
+                    .map_err(|_| "Failed to create header")?;
+                request.respond(Response::from_string(body).with_header(header))?;
+            }
+            (Method::Get, "/debug/memory") => {
+                respond_json(request, &serde_json::json!(self.game_world.world.memory_report()))?;
+            }
+            (Method::Get, "/debug/systems") => {
+                respond_json(request, &serde_json::json!(self.game_world.describe_systems()))?;
+            }
+            (Method::Post, "/debug/step") => {
+                // Body: {"action": "step", "ticks": 10}; "pause" and "resume" need no ticks
+                // The loop runs the requested ticks, and the response describes the last tick stepped so far
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                match body["action"].as_str() {
+                    Some("pause") => self.game_world.set_paused(true),
+                    Some("resume") => self.game_world.set_paused(false),
+                    _ => self.game_world.step(body["ticks"].as_u64().unwrap_or(1) as u32),
+                }
+                let last_step = self.game_world.debug_tracker.latest()
+                    .map(|frame| serde_json::json!({"tick": frame.tick, "changedBy": frame.changed_by()}));
+                let response_data = serde_json::json!({
+                    "paused": self.game_world.paused,
+                    "tick": self.game_world.tick,
+                    "stepsRemaining": self.game_world.steps_remaining(),
+                    "lastStep": last_step
+                });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/debug/timeline") => {
+                // Time-travel page over the debug tracker's stepped ticks, with the live game beside it
+                let html = fs::read_to_string("web/debug-timeline.html")
+                    .unwrap_or_else(|e| self.create_error_page(&format!("Error loading timeline: {}", e)));
+                let header = Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..])
+                    .map_err(|_| "Failed to create header")?;
+                request.respond(Response::from_string(html).with_header(header))?;
+            }
+            (Method::Get, "/debug/latency") => {
+                // Percentiles of the recent input batches' latency by pipeline stage
+                respond_json(request, &serde_json::json!(self.input_latency.report()))?;
+            }
+            (Method::Get, path) if path.starts_with("/debug/trace") => {
+                // System spans and gameplay events of the last ?frames=N ticks (all recorded by default), in the
+                // Chrome trace-event format for chrome://tracing or Perfetto
+                let frames = query_param(path, "frames").and_then(|frames| frames.parse().ok()).unwrap_or(TRACE_FRAMES);
+                let header = Header::from_bytes(&b"Content-Disposition"[..], &b"attachment; filename=\"citybuilder-trace.json\""[..])
+                    .map_err(|_| "Failed to create header")?;
+                let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .map_err(|_| "Failed to create header")?;
+                let response = Response::from_string(self.traces.chrome_trace(frames).to_string())
+                    .with_header(content_type)
+                    .with_header(header);
+                request.respond(response)?;
+            }
+            (Method::Get, path) if path.starts_with("/debug/frames") => {
+                // Recorded ticks with their checkpoints and diffs; ?tick=N returns that tick's full world state
+                let tracker = &self.game_world.debug_tracker;
+                let response_data = match query_param(path, "tick").and_then(|tick| tick.parse().ok()) {
+                    Some(tick) => match tracker.frame(tick) {
+                        Some(frame) => serde_json::to_value(frame)?,
+                        None => serde_json::json!({"error": format!("Tick {} was not recorded", tick)}),
+                    },
+                    None => {
+                        let frames: Vec<serde_json::Value> = tracker.frames().map(|frame| serde_json::json!({
+                            "tick": frame.tick,
+                            "keyframe": frame.state.is_some(),
+                            "changedBy": frame.changed_by(),
+                            "checkpoints": frame.checkpoints,
+                            "diff": frame.diff
+                        })).collect();
+                        serde_json::json!({
+                            "tick": self.game_world.tick,
+                            "paused": self.game_world.paused,
+                            "config": tracker.config(),
+                            "memory": tracker.memory_usage(),
+                            "frames": frames
+                        })
+                    }
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/debug/recording") => {
+                // Download the stepped ticks for `convert-recording` and offline analysis; ?format=ron for text
+                let format = match query_param(path, "format") {
+                    Some("ron") => RecordingFormat::Ron,
+                    _ => RecordingFormat::Binary,
+                };
+                let frames: Vec<FrameRecord> = self.game_world.debug_tracker.frames().cloned().collect();
+                let content_type = match format {
+                    RecordingFormat::Ron => &b"text/plain; charset=utf-8"[..],
+                    RecordingFormat::Binary => &b"application/octet-stream"[..],
+                };
+                let header = Header::from_bytes(&b"Content-Type"[..], content_type)
+                    .map_err(|_| "Failed to create header")?;
+                request.respond(Response::from_data(recording::encode(&frames, format)?).with_header(header))?;
+            }
+            (Method::Post, "/debug/tracker") => {
+                // Body: {"keyframe_interval": 10, "history_limit": 600}; either may be left out
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let tracker = &mut self.game_world.debug_tracker;
+                let mut config = tracker.config();
+                if let Some(interval) = body["keyframe_interval"].as_u64() {
+                    config.keyframe_interval = interval.max(1);
+                }
+                if let Some(limit) = body["history_limit"].as_u64() {
+                    config.history_limit = (limit as usize).max(1);
+                }
+                tracker.set_config(config);
+                respond_json(request, &serde_json::json!({"config": tracker.config(), "memory": tracker.memory_usage()}))?;
+            }
+            (Method::Post, "/debug/restore") => {
+                // Body: {"tick": 42}; the game pauses at the restored tick and clients redraw it
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let tick = body["tick"].as_u64().unwrap_or_default();
+                let response_data = match self.game_world.restore_frame(tick) {
+                    Ok(()) => serde_json::json!({"success": true, "tick": tick}),
+                    Err(error) => serde_json::json!({"success": false, "error": error}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/debug/clients") => {
+                let response_data = serde_json::json!({ "clients": self.clients.client_stats(Instant::now()) });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/preload") => {
+                // Body: {"clientId": "client_1_ab12", "loaded": 12, "failed": 1}, sent as textures finish loading
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let client_id = body["clientId"].as_str().unwrap_or_default();
+                let count = |field: &str| body[field].as_u64().unwrap_or(0) as usize;
+                let response_data = match self.preload.report(client_id, count("loaded"), count("failed")) {
+                    Ok(progress) => serde_json::json!({"success": true, "ready": progress.is_complete(), "progress": progress}),
+                    Err(error) => serde_json::json!({"success": false, "error": error}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/events") => {
+                let bridge = &self.game_world.bridge;
+                let response_data = serde_json::json!({"published": bridge.published_topics(), "accepted": bridge.accepted_topics()});
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/events/subscribe") => {
+                // Body: {"clientId": "client_1_ab12", "topics": ["MoneyEarned"], "unsubscribe": false}
+                // Subscribed events arrive with the heartbeat messages as {"Event": {"topic": ..., "payload": ...}}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let client_id = body["clientId"].as_str().unwrap_or_default();
+                let topics: Vec<&str> = body["topics"].as_array()
+                    .map(|topics| topics.iter().filter_map(|topic| topic.as_str()).collect())
+                    .unwrap_or_default();
+                let bridge = &mut self.game_world.bridge;
+                let response_data = if body["unsubscribe"].as_bool().unwrap_or(false) {
+                    bridge.unsubscribe(client_id, &topics);
+                    serde_json::json!({"success": true, "topics": bridge.subscriptions(client_id)})
+                } else {
+                    match bridge.subscribe(client_id, &topics) {
+                        Ok(subscribed) => serde_json::json!({"success": true, "topics": subscribed}),
+                        Err(error) => serde_json::json!({"success": false, "error": error}),
+                    }
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/events") => {
+                // Body: {"clientId": "client_1_ab12", "topic": "CitizensHoused", "payload": {"count": 2}}
+                // Only event types the bridge accepts become gameplay events, seen by the next update's consumers
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let response_data = match serde_json::from_value::<BridgedEvent>(body) {
+                    Ok(event) => match self.game_world.post_client_event(&event) {
+                        Ok(()) => serde_json::json!({"success": true}),
+                        Err(error) => serde_json::json!({"success": false, "error": error}),
+                    },
+                    Err(error) => serde_json::json!({"success": false, "error": error.to_string()}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/disconnect") => {
+                // Body: {"clientId": "client_1_ab12"}, sent when the page is hidden or unloaded
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let disconnected = body["clientId"].as_str()
+                    .is_some_and(|client_id| self.clients.disconnect_client(client_id));
+                self.apply_connection_events();
+                
+                let response_data = serde_json::json!({"success": disconnected, "paused": self.game_world.paused});
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/console") => {
+                let response_data = serde_json::to_value(&self.game_world.console)?;
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/stats") => {
+                // Return the accumulated gameplay statistics, with the residents' happiness right now
+                let mut response_data = serde_json::to_value(&self.game_world.stats)?;
+                response_data["happiness"] = serde_json::json!(self.game_world.happiness());
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/labor") => {
+                // Unemployment and commutes after the last round of job matching
+                respond_json(request, &serde_json::to_value(self.game_world.labor.stats())?)?;
+            }
+            (Method::Get, "/api/v1/watchdog") => {
+                respond_json(request, &self.watchdog_json())?;
+            }
+            (Method::Post, "/api/v1/watchdog") => {
+                // Body: {"level": 2} pins the degradation level; {"level": null} hands it back to the watchdog
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let level = body["level"].as_u64().map(|level| level.min(u8::MAX as u64) as u8);
+                let response_data = match self.game_world.set_quality_override(level) {
+                    Ok(()) => self.watchdog_json(),
+                    Err(error) => serde_json::json!({"success": false, "error": error}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/regions") => {
+                // Chunk activation and the aggregates standing in for inactive chunks
+                let regions = &self.game_world.regions;
+                let (active, inactive) = regions.counts();
+                let chunks = self.game_world.abstract_regions.iter().map(|(chunk, state)| {
+                    let mut entry = serde_json::to_value(state)?;
+                    entry["chunk"] = serde_json::json!([chunk.0, chunk.1]);
+                    Ok(entry)
+                }).collect::<Result<Vec<_>, serde_json::Error>>()?;
+                respond_json(request, &serde_json::json!({
+                    "config": regions.config(),
+                    "active": active,
+                    "inactive": inactive,
+                    "abstract": chunks,
+                }))?;
+            }
+            (Method::Get, "/api/v1/mods") => {
+                respond_json(request, &serde_json::json!({"packs": self.content_packs}))?;
+            }
+            (Method::Get, "/api/v1/strings") => {
+                respond_json(request, &serde_json::to_value(&self.strings)?)?;
+            }
+            (Method::Get, "/api/v1/catalog") => {
+                // Building definitions for the build menu, with what the city has unlocked so far
+                let catalog = &self.game_world.catalog;
+                let buildings = catalog.definitions().iter().map(|definition| {
+                    let mut entry = serde_json::to_value(definition)?;
+                    entry["unlocked"] = catalog.is_unlocked(definition.kind).into();
+                    entry["lockedReason"] = catalog.locked_reason(definition.kind).into();
+                    Ok(entry)
+                }).collect::<Result<Vec<_>, serde_json::Error>>()?;
+                respond_json(request, &serde_json::json!({"buildings": buildings}))?;
+            }
+            (Method::Get, "/api/v1/objectives") => {
+                // Scenario goals for the objectives HUD, with progress as current and target
+                let objectives = self.game_world.objectives();
+                respond_json(request, &serde_json::json!({"objectives": objectives}))?;
+            }
+            (Method::Get, "/api/v1/budget") => {
+                let response_data = serde_json::to_value(&self.game_world.economy)?;
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/policies") => {
+                // Every policy with its costs and effects, and whether it's in force
+                let economy = &self.game_world.economy;
+                let policies: Vec<serde_json::Value> = self.game_world.policies.definitions().iter()
+                    .map(|policy| serde_json::json!({
+                        "name": policy.name,
+                        "title": policy.title,
+                        "description": policy.description,
+                        "enactCost": policy.enact_cost,
+                        "monthlyCost": policy.monthly_cost,
+                        "effects": policy.effects.iter()
+                            .map(|effect| serde_json::json!({"target": effect.target.label(), "percent": effect.percent}))
+                            .collect::<Vec<_>>(),
+                        "enacted": economy.policies.iter().any(|enacted| enacted.name == policy.name),
+                    }))
+                    .collect();
+                respond_json(request, &serde_json::json!({"policies": policies}))?;
+            }
+            (Method::Get, "/api/v1/trade") => {
+                // Connections to the outside world, the market price and last month's trade
+                let world = &self.game_world.world;
+                let connections: Vec<serde_json::Value> = world.entities_with_components(&[std::any::TypeId::of::<ExternalConnectionComponent>(), std::any::TypeId::of::<GridPositionComponent>()])
+                    .into_iter()
+                    .filter_map(|entity| {
+                        let connection = world.get_component::<ExternalConnectionComponent>(entity)?;
+                        let pos = world.get_component::<GridPositionComponent>(entity)?;
+                        Some(serde_json::json!({"kind": connection.kind, "x": pos.x, "y": pos.y, "capacity": connection.kind.capacity()}))
+                    })
+                    .collect();
+                let economy = &self.game_world.economy;
+                let response_data = serde_json::json!({
+                    "connections": connections,
+                    "capacity": TradeSystem::capacity(world),
+                    "price": economy.market.price,
+                    "importPrice": economy.market.import_price(),
+                    "config": economy.market.config,
+                    "lastMonth": economy.last_report.as_ref().map(|report| &report.trade),
+                });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/terrain") => {
+                // Elevation and water of every tile in row order
+                let terrain = &self.game_world.terrain;
+                let response_data = serde_json::json!({
+                    "width": terrain.width(),
+                    "height": terrain.height(),
+                    "elevation": terrain.elevations(),
+                    "water": terrain.water(),
+                });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/budget/taxes") => {
+                // Body: {"zone": "residential", "rate": 12}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let zone = body["zone"].as_str().and_then(ZoneType::from_name);
+                let rate = body["rate"].as_f64().map(TaxRates::checked_rate);
+                
+                let response_data = match (zone, rate) {
+                    (Some(zone), Some(Ok(rate))) => {
+                        self.game_world.set_tax_rate(zone, rate);
+                        serde_json::json!({"success": true, "taxRates": self.game_world.economy.tax_rates})
+                    }
+                    (Some(_), Some(Err(e))) => serde_json::json!({"success": false, "error": e}),
+                    _ => serde_json::json!({"success": false, "error": "Expected zone and rate"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/budget/loans") => {
+                // Body: {"amount": 5000}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let amount = body["amount"].as_i64().unwrap_or(0);
+                
+                let result = self.game_world.take_loan(amount);
+                respond_json(request, &self.budget_action_response(result.map(|_| amount)))?;
+            }
+            (Method::Post, "/api/v1/budget/loans/repay") => {
+                // Body: {"index": 0, "amount": 1000}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let index = body["index"].as_u64().unwrap_or(0) as usize;
+                let amount = body["amount"].as_i64().unwrap_or(0);
+                
+                let result = self.game_world.repay_loan(index, amount);
+                respond_json(request, &self.budget_action_response(result))?;
+            }
+            (Method::Post, "/api/v1/policies/enact") => {
+                // Body: {"policy": "free_transit"}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let name = body["policy"].as_str().unwrap_or_default();
+                
+                let result = self.game_world.enact_policy(name);
+                respond_json(request, &self.budget_action_response(result))?;
+            }
+            (Method::Post, "/api/v1/policies/repeal") => {
+                // Body: {"policy": "free_transit"}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let name = body["policy"].as_str().unwrap_or_default();
+                
+                let result = self.game_world.repeal_policy(name);
+                respond_json(request, &self.budget_action_response(result.map(|_| 0)))?;
+            }
+            (Method::Post, "/api/v1/build") => {
+                // Body: {"kind": "house", "x": 4, "y": 6}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let kind = body["kind"].as_str().and_then(BuildingKind::from_name);
+                let x = body["x"].as_i64();
+                let y = body["y"].as_i64();
+                
+                let response_data = match (kind, x, y) {
+                    (Some(kind), Some(x), Some(y)) => match self.game_world.place_building(kind, x as i32, y as i32) {
+                        Ok(entity) => serde_json::json!({
+                            "success": true,
+                            "entity": entity,
+                            "stableId": StableIdSystem::get(&self.game_world.world, entity),
+                            "gameState": self.game_world.get_game_state()
+                        }),
+                        Err(error) => serde_json::json!({"success": false, "error": error}),
+                    },
+                    _ => serde_json::json!({"success": false, "error": "Expected kind, x and y"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/tiles") => {
+                // Body: {"kind": "road", "x": 4, "y": 6}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let kind = body["kind"].as_str().and_then(TileKind::from_name);
+                
+                let response_data = match (kind, body["x"].as_i64(), body["y"].as_i64()) {
+                    (Some(kind), Some(x), Some(y)) => match self.game_world.place_tile(kind, x as i32, y as i32) {
+                        Ok(entity) => serde_json::json!({
+                            "success": true,
+                            "entity": entity,
+                            "stableId": StableIdSystem::get(&self.game_world.world, entity),
+                            "gameState": self.game_world.get_game_state()
+                        }),
+                        Err(error) => serde_json::json!({"success": false, "error": error}),
+                    },
+                    _ => serde_json::json!({"success": false, "error": "Expected kind, x and y"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/inspect") => {
+                // Pick an entity by stable ID, entity ID, name or tile and describe it for the entity inspector
+                let by_stable_id = query_param(path, "id")
+                    .and_then(|value| value.parse::<StableId>().ok())
+                    .and_then(|id| StableIdSystem::find(&self.game_world.world, id));
+                let by_name = query_param(path, "name").and_then(|name| self.game_world.world.find_by_name(name));
+                let entity = match by_stable_id.or_else(|| query_param(path, "entity").and_then(|value| value.parse::<Entity>().ok())).or(by_name) {
+                    Some(entity) => Some(entity),
+                    None => {
+                        let x = query_param(path, "x").and_then(|value| value.parse::<i32>().ok());
+                        let y = query_param(path, "y").and_then(|value| value.parse::<i32>().ok());
+                        x.zip(y).and_then(|(x, y)| self.game_world.pick_entity(x, y))
+                    }
+                };
+                
+                let response_data = match entity.and_then(|entity| self.inspect_entity(entity)) {
+                    Some(info) => info,
+                    None => serde_json::json!({"error": "No entity found"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/placement") => {
+                // Run the placement validator for a building kind or a blueprint at a tile
+                let x = query_param(path, "x").and_then(|value| value.parse::<i32>().ok());
+                let y = query_param(path, "y").and_then(|value| value.parse::<i32>().ok());
+                let kind = query_param(path, "kind").and_then(BuildingKind::from_name);
+                let blueprint = query_param(path, "blueprint");
+                
+                let result = match (x, y, kind, blueprint) {
+                    (Some(x), Some(y), Some(kind), _) => self.game_world.validate_placement(kind, x, y),
+                    (Some(x), Some(y), None, Some(name)) => self.game_world.validate_blueprint(name, (x, y)),
+                    _ => Err("Expected x, y and a kind or blueprint".to_string()),
+                };
+                
+                let response_data = match result {
+                    Ok(()) => serde_json::json!({"valid": true}),
+                    Err(reason) => serde_json::json!({"valid": false, "reason": reason}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/demolish") => {
+                // Body: {"x": 4, "y": 6}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                
+                let response_data = match (body["x"].as_i64(), body["y"].as_i64()) {
+                    (Some(x), Some(y)) => match self.game_world.mark_for_demolition(x as i32, y as i32) {
+                        Ok(entity) => {
+                            // Applied right away; the simulation only advances with input
+                            self.game_world.demolish_marked();
+                            serde_json::json!({
+                                "success": true,
+                                "entity": entity,
+                                "stableId": StableIdSystem::get(&self.game_world.world, entity),
+                                "gameState": self.game_world.get_game_state()
+                            })
+                        }
+                        Err(error) => serde_json::json!({"success": false, "error": error}),
+                    },
+                    _ => serde_json::json!({"success": false, "error": "Expected x and y"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/tool") => {
+                respond_json(request, &self.tool_state_json())?;
+            }
+            (Method::Post, "/api/v1/tool") => {
+                // Body: {"tool": "zone_residential"}, or {"tool": null} to put the tool away
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                
+                let response_data = match body["tool"].as_str().map(Tool::from_name) {
+                    Some(None) => serde_json::json!({"success": false, "error": "Unknown tool"}),
+                    tool => {
+                        self.game_world.select_tool(tool.flatten());
+                        let mut response_data = self.tool_state_json();
+                        response_data["success"] = serde_json::json!(true);
+                        response_data
+                    }
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/blueprints") => {
+                let response_data = serde_json::json!({ "blueprints": self.game_world.blueprints.all() });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/blueprints/copy") => {
+                // Body: {"name": "block", "x1": 1, "y1": 1, "x2": 3, "y2": 2}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let name = body["name"].as_str().unwrap_or("clipboard").to_string();
+                let corners = (body["x1"].as_i64(), body["y1"].as_i64(), body["x2"].as_i64(), body["y2"].as_i64());
+                
+                let response_data = match corners {
+                    (Some(x1), Some(y1), Some(x2), Some(y2)) => {
+                        match self.game_world.copy_blueprint(&name, (x1 as i32, y1 as i32), (x2 as i32, y2 as i32)) {
+                            Ok(blueprint) => {
+                                let blueprint = serde_json::to_value(blueprint)?;
+                                serde_json::json!({"success": true, "blueprint": blueprint, "clipboard": self.game_world.take_clipboard()})
+                            }
+                            Err(error) => serde_json::json!({"success": false, "error": error}),
+                        }
+                    }
+                    _ => serde_json::json!({"success": false, "error": "Expected x1, y1, x2 and y2"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/blueprints/stamp") => {
+                // Body: {"name": "block", "x": 5, "y": 5}
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let name = body["name"].as_str().unwrap_or("clipboard").to_string();
+                
+                let response_data = match (body["x"].as_i64(), body["y"].as_i64()) {
+                    (Some(x), Some(y)) => match self.game_world.stamp_blueprint(&name, (x as i32, y as i32)) {
+                        Ok(entities) => serde_json::json!({
+                            "success": true,
+                            "entities": entities,
+                            "gameState": self.game_world.get_game_state()
+                        }),
+                        Err(error) => serde_json::json!({"success": false, "error": error}),
+                    },
+                    _ => serde_json::json!({"success": false, "error": "Expected x and y"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Post, "/api/v1/selection") => {
+                // Body: {"tool": "zone", "zone": "residential", "shape": "lasso", "path": [[1, 1], [4, 1], [4, 3]], "preview": false}
+                // Terraforming names its edit instead: {"tool": "terrain", "edit": "raise", ...}
+                // Applies the tool to the area the drag path covers, or only reports the covered tiles on preview
+                let mut request = request;
+                let body = read_json_body(&mut request)?;
+                let tool = match body["tool"].as_str() {
+                    Some("zone") => body["zone"].as_str().and_then(ZoneType::from_name).map(AreaTool::Zone),
+                    Some("demolish") => Some(AreaTool::Demolish),
+                    Some("blueprint") => Some(AreaTool::Blueprint(body["name"].as_str().unwrap_or("clipboard").to_string())),
+                    Some("terrain") => body["edit"].as_str().and_then(TerrainTool::from_name).map(AreaTool::Terrain),
+                    _ => None,
+                };
+                let shape = body["shape"].as_str().and_then(SelectionShape::from_name).unwrap_or_default();
+                let path: Vec<(i32, i32)> = body["path"].as_array()
+                    .map(|points| points.iter()
+                        .filter_map(|point| Some((point[0].as_i64()? as i32, point[1].as_i64()? as i32)))
+                        .collect())
+                    .unwrap_or_default();
+                
+                let response_data = match (tool, path.first(), path.last()) {
+                    (Some(tool), Some(first), Some(last)) => {
+                        let selection = match shape {
+                            SelectionShape::Rectangle => AreaSelection::rectangle(*first, *last),
+                            SelectionShape::Lasso => AreaSelection::lasso(&path),
+                        };
+                        let tiles: Vec<serde_json::Value> = selection.tiles.iter()
+                            .map(|&(x, y)| serde_json::json!({"x": x, "y": y, "valid": self.game_world.validate_area_tile(&tool, x, y)}))
+                            .collect();
+                        if body["preview"].as_bool().unwrap_or(false) {
+                            serde_json::json!({"success": true, "tiles": tiles})
+                        } else {
+                            match self.game_world.apply_area_tool(&tool, &selection) {
+                                Ok(applied) => {
+                                    self.game_world.demolish_marked();
+                                    let blueprint = match &tool {
+                                        AreaTool::Blueprint(name) => serde_json::json!(self.game_world.blueprints.get(name)),
+                                        _ => serde_json::Value::Null,
+                                    };
+                                    serde_json::json!({
+                                        "success": true,
+                                        "tiles": tiles,
+                                        "applied": applied,
+                                        "blueprint": blueprint,
+                                        "clipboard": self.game_world.take_clipboard(),
+                                        "gameState": self.game_world.get_game_state()
+                                    })
+                                }
+                                Err(error) => serde_json::json!({"success": false, "error": error}),
+                            }
+                        }
+                    }
+                    _ => serde_json::json!({"success": false, "error": "Expected a tool and a non-empty path"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, "/api/v1/construction") => {
+                // Return the build queue with the progress of every site
+                let world = &self.game_world.world;
+                let sites: Vec<serde_json::Value> = ConstructionSystem::build_queue(world)
+                    .into_iter()
+                    .filter_map(|entity| {
+                        let site = world.get_component::<UnderConstructionComponent>(entity)?;
+                        let pos = world.get_component::<GridPositionComponent>(entity)?;
+                        Some(serde_json::json!({
+                            "entity": entity,
+                            "kind": site.kind,
+                            "x": pos.x,
+                            "y": pos.y,
+                            "progress": site.fraction(),
+                            "stalled": site.stalled
+                        }))
+                    })
+                    .collect();
+                
+                let response_data = serde_json::json!({
+                    "sites": sites,
+                    "availableWorkers": ConstructionSystem::available_workers(world)
+                });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/coverage") => {
+                // Return one coverage layer as a row-major heatmap
+                let service = query_param(path, "service").and_then(ServiceType::from_name);
+                
+                let response_data = match service {
+                    Some(service) => {
+                        let coverage = &self.game_world.coverage;
+                        let (width, height) = coverage.dimensions();
+                        let color = service.overlay_color(&self.game_world.palette);
+                        serde_json::json!({
+                            "service": service,
+                            "color": [color.r, color.g, color.b],
+                            "width": width,
+                            "height": height,
+                            "values": coverage.layer(service)
+                        })
+                    }
+                    None => serde_json::json!({"error": "Unknown or missing service parameter"}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/timeseries") => {
+                // Return a metric's daily samples over a range such as 30d, 6m or 1y (default 1y)
+                let metric = query_param(path, "metric").and_then(Metric::from_name);
+                let range = parse_range(query_param(path, "range").unwrap_or("1y"));
+                
+                let response_data = match (metric, range) {
+                    (Some(metric), Ok(days)) => serde_json::json!({
+                        "metric": metric,
+                        "day": self.game_world.history.day(),
+                        "days": days,
+                        "samples": self.game_world.history.range(metric, days)
+                    }),
+                    (None, _) => serde_json::json!({"error": "Unknown or missing metric parameter"}),
+                    (_, Err(error)) => serde_json::json!({"error": error}),
+                };
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/notifications") => {
+                // Return notifications newer than the id the client has already seen
+                let since = query_param(path, "since")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(0);
+                
+                let notifications: Vec<serde_json::Value> = self.game_world.notification_buffer.since(since)
+                    .into_iter()
+                    .map(|entry| {
+                        let focus = entry.notification.link
+                            .and_then(|link| self.game_world.notification_focus_tile(&link))
+                            .map(|(x, y)| serde_json::json!({"x": x, "y": y}));
+                        let mut value = serde_json::to_value(&entry).unwrap_or_default();
+                        value["focus"] = focus.unwrap_or(serde_json::Value::Null);
+                        value
+                    })
+                    .collect();
+                
+                let response_data = serde_json::json!({ "notifications": notifications });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/api/v1/audio") => {
+                // Return audio commands newer than the id the client has already played
+                let since = query_param(path, "since")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(0);
+                let response_data = serde_json::json!({
+                    "settings": self.game_world.audio.settings(),
+                    "commands": self.game_world.audio.since(since)
+                });
+                respond_json(request, &response_data)?;
+            }
+            (Method::Get, path) if path.starts_with("/audio/") => {
+                // Stream audio assets from web/audio/; clients fetch each sound the first time it plays
+                self.serve_asset_file(path, "/audio/", Path::new("web/audio"), "audio/ogg", request)?;
+            }
+            (Method::Get, path) if path.starts_with("/textures/") => {
+                // Textures listed in the preload manifest clients get when they connect
+                self.serve_asset_file(path, "/textures/", &self.texture_directory, "image/png", request)?;
+            }
             (Method::Get, path) if path.starts_with("/js/") => {
                 // Serve JavaScript files from web/js/ directory
                 self.serve_static_file(path, "application/javascript", request)?;
@@ -223,6 +1546,82 @@ impl WebEcsGameDemo {
         Ok(())
     }
     
+    /// Describe an entity for the entity inspector, including any path it is following
+    fn inspect_entity(&self, entity: Entity) -> Option<serde_json::Value> {
+        let world = &self.game_world.world;
+        let pos = world.get_component::<GridPositionComponent>(entity)?;
+        
+        let symbol = world.get_component::<RenderComponent>(entity).map(|render| render.symbol.to_string());
+        let name = world.get_component::<Name>(entity).map(|name| name.as_str().to_string());
+        let tags: Vec<String> = world.get_component::<Tags>(entity)
+            .map(|tags| tags.iter().map(str::to_string).collect())
+            .unwrap_or_default();
+        let building = DemolitionSystem::building_kind(world, entity);
+        let agent = world.get_component::<AgentComponent>(entity).map(|agent| serde_json::json!({
+            "name": agent.name,
+            "state": agent.state,
+            "destinations": agent.destinations,
+            "home": agent.home
+        }));
+        let path = world.get_component::<PathComponent>(entity)
+            .map(|path| path.remaining().to_vec())
+            .unwrap_or_default();
+        let employment = world.get_component::<Employment>(entity).map(|employment| serde_json::json!({
+            "workplace": employment.workplace,
+            "commute": employment.commute
+        }));
+        let inventory = world.get_component::<Inventory>(entity).map(|inventory| inventory.clone());
+        let ticks_per_day = (self.game_world.budget_system.ticks_per_month() / DAYS_PER_MONTH).max(1);
+        // A building shows its own modifiers and those on the whole city
+        let modifiers: Vec<serde_json::Value> = self.game_world.economy.modifiers.modifiers().iter()
+            .filter(|modifier| building.is_some() && match modifier.scope {
+                ModifierScope::City => true,
+                ModifierScope::Building(id) => StableIdSystem::get(world, entity) == Some(id),
+            })
+            .map(|modifier| serde_json::json!({
+                "source": modifier.source,
+                "city": modifier.scope == ModifierScope::City,
+                "target": modifier.target.label(),
+                "percent": modifier.percent,
+                "daysLeft": modifier.remaining_ticks.map(|ticks| ticks.div_ceil(ticks_per_day))
+            }))
+            .collect();
+        let delivery = world.get_component::<DeliveryComponent>(entity).map(|delivery| serde_json::json!({
+            "good": delivery.good,
+            "amount": delivery.amount,
+            "from": delivery.from,
+            "to": delivery.to
+        }));
+        
+        Some(serde_json::json!({
+            "entity": entity,
+            "stableId": StableIdSystem::get(world, entity),
+            "name": name,
+            "tags": tags,
+            "position": {"x": pos.x, "y": pos.y},
+            "symbol": symbol,
+            "building": building,
+            "agent": agent,
+            "employment": employment,
+            "inventory": inventory,
+            "delivery": delivery,
+            "modifiers": modifiers,
+            "path": path
+        }))
+    }
+    
+    /// Build the JSON response for a treasury action (loan taken or repaid, policy enacted or repealed)
+    fn budget_action_response(&self, result: Result<i64, String>) -> serde_json::Value {
+        match result {
+            Ok(amount) => serde_json::json!({
+                "success": true,
+                "amount": amount,
+                "treasury": self.game_world.economy.treasury
+            }),
+            Err(error) => serde_json::json!({"success": false, "error": error}),
+        }
+    }
+    
     /// Serve the generic HTML template and configure it for the ECS game
     fn serve_generic_template(&self) -> Result<String, String> {
         // Read the generic template file
@@ -243,13 +1642,16 @@ impl WebEcsGameDemo {
                 gameType: 'ecs-grid-game',
                 initialState: {{'gameState': '{}', 'playerPosition': {{'x': {}, 'y': {}}}}},
                 enablePolling: true,
-                pollInterval: 100
+                pollInterval: 100,
+                // Where the rendering and input servers actually listen, from service discovery
+                services: {}
             }};
             
             // Override the default game template to work with ECS backend
             window.addEventListener('load', () => {{
                 console.log('🎮 ECS Grid Game loaded with JavaScript libraries');
                 console.log('🔗 API URL:', window.ECS_GAME_CONFIG.apiUrl);
+                console.log('🧭 Services:', window.ECS_GAME_CONFIG.services);
                 
                 // Initialize ECS-specific functionality
                 if (window.gameTemplate) {{
@@ -259,7 +1661,8 @@ impl WebEcsGameDemo {
         </script>"#, 
         game_state.replace('\n', "\\n").replace('\r', ""),
         player_pos.0, 
-        player_pos.1);
+        player_pos.1,
+        serde_json::to_string(&service_discovery::registered_services().urls()).map_err(|e| e.to_string())?);
         
         // Insert the ECS configuration before the closing body tag
         template_content = template_content.replace("</body>", &format!("{}\n</body>", ecs_game_config));
@@ -268,7 +1671,7 @@ impl WebEcsGameDemo {
     }
     
     /// Serve static files (JS, CSS, etc.) from the web directory
-    fn serve_static_file(&self, path: &str, content_type: &str, request: Request) -> Result<(), Box<dyn std::error::Error>> {
+    fn serve_static_file(&self, path: &str, content_type: &str, request: PendingRequest) -> Result<(), Box<dyn std::error::Error>> {
         let file_path = format!("web{}", path);
         
         match fs::read_to_string(&file_path) {
@@ -288,6 +1691,20 @@ impl WebEcsGameDemo {
         Ok(())
     }
     
+    /// Serve a binary asset named by the rest of `path` after `prefix` from `directory`; names can't leave it
+    fn serve_asset_file(&self, path: &str, prefix: &str, directory: &Path, content_type: &str, request: PendingRequest) -> Result<(), Box<dyn std::error::Error>> {
+        let data = asset_path(directory, path.trim_start_matches(prefix)).and_then(|path| read_asset(directory, &path));
+        match data {
+            Some(data) => {
+                let header = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+                    .map_err(|_| "Failed to create header")?;
+                request.respond(Response::from_data(data).with_header(header))?;
+            }
+            _ => request.respond(Response::from_string("404 Not Found").with_status_code(404))?,
+        }
+        Ok(())
+    }
+    
     /// Create a simple error page when template loading fails
     fn create_error_page(&self, error_message: &str) -> String {
         format!(r#"<!DOCTYPE html>
@@ -316,6 +1733,58 @@ impl WebEcsGameDemo {
     }
 }
 
+/// Respond to a request with a JSON body
+fn respond_json(request: PendingRequest, data: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .map_err(|_| "Failed to create header")?;
+    let response = Response::from_string(data.to_string()).with_header(header);
+    request.respond(response)?;
+    Ok(())
+}
+
+/// Read and parse the JSON body of a request (invalid JSON yields `null`)
+fn read_json_body(request: &mut PendingRequest) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let mut body = String::new();
+    std::io::Read::read_to_string(request.as_reader(), &mut body)?;
+    Ok(serde_json::from_str(&body).unwrap_or(serde_json::Value::Null))
+}
+
+/// Copy the fields of the `extra` object into `target`
+fn merge_json(target: &mut serde_json::Value, extra: serde_json::Value) {
+    if let (Some(target), serde_json::Value::Object(extra)) = (target.as_object_mut(), extra) {
+        target.extend(extra);
+    }
+}
+
+/// Path of an asset file in `directory`, or `None` for a name that could reach outside it:
+/// one with a path separator or `..`, or with anything but letters, digits, `_`, `-` and `.`
+fn asset_path(directory: &Path, name: &str) -> Option<PathBuf> {
+    let valid = !name.is_empty()
+        && !name.contains(['/', '\\'])
+        && !name.contains("..")
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' || ch == '.');
+    valid.then(|| directory.join(name))
+}
+
+/// Contents of an asset file, or `None` when it is missing, over `MAX_ASSET_BYTES`, or resolves (e.g. through a
+/// symlink) to somewhere outside `directory`
+fn read_asset(directory: &Path, path: &Path) -> Option<Vec<u8>> {
+    let path = fs::canonicalize(path).ok()?;
+    if !path.starts_with(fs::canonicalize(directory).ok()?) || fs::metadata(&path).ok()?.len() > MAX_ASSET_BYTES {
+        return None;
+    }
+    fs::read(path).ok()
+}
+
+/// Extract a query parameter value from a request URL
+fn query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    let query = url.split_once('?')?.1;
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
 /// Demonstrate the web ECS game
 pub fn demonstrate_web_ecs_game() {
     println!("🚀 Starting Web ECS Game Demo");
@@ -331,6 +1800,9 @@ pub fn demonstrate_web_ecs_game() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::economy::Economy;
+    use crate::testing::TestServer;
+    use serde_json::json;
     
     #[test]
     fn test_web_ecs_game_creation() {
@@ -339,6 +1811,136 @@ mod tests {
         assert!(true);
     }
     
+    #[test]
+    fn test_scenario_triggers_and_city_events_are_kept_with_the_progress() {
+        let scenario: Scenario = ron::from_str(r#"(triggers: [
+            (name: "start", objective: Some("Found the town"), when: Date(year: 1, month: 1), actions: []),
+            (name: "spring", objective: Some("Reach spring"), when: Date(year: 1, month: 4), actions: []),
+        ])"#).unwrap();
+        let directory = std::env::temp_dir().join(format!("citybuilder-scenario-{}", std::process::id()));
+        let save_path = directory.join("city.sav");
+        let mut game = WebEcsGameDemo::new("localhost:8000").with_save_path(&save_path).with_scenario(&scenario).unwrap();
+        game.tick();
+        assert_eq!(game.game_world.triggers.fired(), ["start"]);
+        assert!(game.game_world.events_director.enabled && !GridGameWorld::new().events_director.enabled);
+        let world = &mut game.game_world;
+        assert!(world.events_director.start("festival", &mut world.economy, &mut world.notifications));
+        game.save_progress().unwrap();
+        
+        let mut loaded = WebEcsGameDemo::new("localhost:8000").with_save_path(&save_path).with_scenario(&scenario).unwrap();
+        loaded.load_progress();
+        let _ = fs::remove_dir_all(&directory);
+        let objectives = loaded.game_world.objectives();
+        assert!(objectives[0].completed);
+        assert_eq!(loaded.game_world.events_director.active().len(), 1);
+        assert_eq!(loaded.game_world.economy.modifiers, game.game_world.economy.modifiers);
+        assert_eq!((objectives[1].current, objectives[1].target, objectives[1].completed), (1, 4, false));
+    }
+    
+    #[test]
+    fn test_query_param() {
+        assert_eq!(query_param("/api/v1/notifications?since=5", "since"), Some("5"));
+        assert_eq!(query_param("/api/v1/notifications?a=1&since=7", "since"), Some("7"));
+        assert_eq!(query_param("/api/v1/notifications", "since"), None);
+    }
+    
+    #[test]
+    fn test_audio_names_stay_in_the_audio_directory() {
+        let audio = Path::new("web/audio");
+        assert_eq!(asset_path(audio, "click.ogg"), Some(PathBuf::from("web/audio/click.ogg")));
+        for name in ["", "../Cargo.toml", "..", "sub/click.ogg", "..\\saves\\city.sav", "%2e%2e%2fCargo.toml", "/etc/passwd"] {
+            assert_eq!(asset_path(audio, name), None, "{}", name);
+        }
+        
+        // Files are read only if they resolve inside the directory
+        let directory = std::env::temp_dir().join(format!("citybuilder-assets-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("click.ogg"), b"ogg").unwrap();
+        assert_eq!(read_asset(&directory, &directory.join("click.ogg")), Some(b"ogg".to_vec()));
+        assert_eq!(read_asset(&directory.join("missing"), &directory.join("click.ogg")), None);
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(Path::new("Cargo.toml").canonicalize().unwrap(), directory.join("escape.ogg")).unwrap();
+            assert_eq!(read_asset(&directory, &directory.join("escape.ogg")), None);
+        }
+        let _ = fs::remove_dir_all(&directory);
+    }
+    
+    #[test]
+    fn test_http_api_registers_clients_moves_and_builds() {
+        let server = TestServer::start().unwrap();
+        let client_id = server.connect().unwrap();
+        
+        // State polls wait for the client's textures
+        assert!(server.get(&format!("/state?client={}", client_id)).unwrap().body["loading"].is_object());
+        let reconnect = server.post("/api/v1/connect", json!({"clientId": client_id})).unwrap();
+        assert_eq!(reconnect.body["reconnected"], json!(true));
+        assert_eq!(reconnect.body["messages"][0]["Welcome"]["client_id"], json!(client_id));
+        assert!(!reconnect.body["messages"][1]["FullFrame"]["commands"].as_array().unwrap().is_empty(), "{}", reconnect.body["messages"]);
+        let textures = reconnect.body["preload"]["manifest"]["textures"].as_array().map(Vec::len).unwrap();
+        let preload = server.post("/api/v1/preload", json!({"clientId": client_id, "loaded": textures})).unwrap();
+        assert_eq!(preload.body["ready"], json!(true));
+        
+        let sent_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let input = server.post("/api/v1/input", json!({"events": [{"KeyPress": {"key": "ArrowRight"}}], "sentAt": sent_at})).unwrap();
+        assert_eq!((input.status, input.body["accepted"].clone()), (200, json!(1)));
+        assert_eq!(input.body["latency"]["network"]["samples"], json!(1));
+        server.post("/api/v1/input", json!({"events": [{"KeyRelease": {"key": "ArrowRight"}}]})).unwrap();
+        let state = server.get(&format!("/state?client={}", client_id)).unwrap();
+        assert_eq!(state.body["playerPosition"], json!({"x": 2, "y": 1}));
+        assert!(state.body["messages"][0]["FullFrame"]["commands"].is_array(), "{}", state.body);
+        let patched = server.get(&format!("/state?client={}", client_id)).unwrap();
+        assert!(patched.body["messages"].is_null(), "{}", patched.body);
+        let latency = server.get("/debug/latency").unwrap().body;
+        assert_eq!((latency["apply"]["samples"].clone(), latency["network"]["samples"].clone()), (json!(2), json!(1)));
+        
+        let build = server.post("/api/v1/build", json!({"kind": "house", "x": 6, "y": 6})).unwrap();
+        assert_eq!(build.body["success"], json!(true), "{}", build.body);
+        let entity = build.body["entity"].as_u64().unwrap() as Entity;
+        
+        let (placed, under_construction, saved_stats) = server.stop_with(move |game| {
+            let world = &game.game_world().world;
+            let saved_stats = GameStats::load_alongside(&game.save_path, &game.game_world().signing).ok() == Some(game.game_world().stats.clone());
+            (game.game_world().entities_at(6, 6).contains(&entity), world.get_component::<UnderConstructionComponent>(entity).is_some(), saved_stats)
+        }).unwrap();
+        assert!(placed && under_construction);
+        assert!(saved_stats, "Statistics are saved next to the save on shutdown");
+    }
+    
+    #[test]
+    fn test_debug_trace_exports_recent_ticks() {
+        let server = TestServer::start().unwrap();
+        for _ in 0..2 {
+            server.post("/api/v1/input", json!({"events": []})).unwrap();
+        }
+        let trace = server.get("/debug/trace?frames=1").unwrap();
+        server.stop().unwrap();
+        
+        let events = trace.body["traceEvents"].as_array().unwrap();
+        let ticks = events.iter().filter(|event| event["cat"] == "tick").count();
+        let systems: Vec<&str> = events.iter().filter(|event| event["cat"] == "system").filter_map(|event| event["name"].as_str()).collect();
+        assert_eq!(ticks, 1);
+        assert!(systems.contains(&"coverage") && systems.contains(&"stats"), "{:?}", systems);
+    }
+    
+    #[test]
+    fn test_http_api_rejects_invalid_requests() {
+        let server = TestServer::start().unwrap();
+        let build = server.post("/api/v1/build", json!({"kind": "castle", "x": 6, "y": 6})).unwrap();
+        assert_eq!(build.body, json!({"success": false, "error": "Expected kind, x and y"}));
+        let taxes = server.post("/api/v1/budget/taxes", json!({"zone": "Residential"})).unwrap();
+        assert_eq!(taxes.status, 400, "{}", taxes.body);
+        assert_eq!(server.get("/api/v1/budget").unwrap().body["treasury"]["balance"], json!(Economy::default().treasury.balance));
+        
+        // Settings belong to clients the server registered
+        let unknown = server.request("PUT", "/api/v1/settings?client=ghost", Some(json!({"ui_scale": 1.5}))).unwrap();
+        assert_eq!((unknown.status, unknown.body["code"].clone()), (404, json!("unknown_client")));
+        let client_id = server.connect().unwrap();
+        let saved = server.request("PUT", &format!("/api/v1/settings?client={}", client_id), Some(json!({"ui_scale": 1.5}))).unwrap();
+        assert_eq!(saved.body["success"], json!(true), "{}", saved.body);
+        server.stop().unwrap();
+    }
+    
     #[test]
     fn test_template_generation() {
         let web_game = WebEcsGameDemo::new("localhost:8000");
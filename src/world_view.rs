@@ -0,0 +1,183 @@
+/// Read-only world snapshots for worker threads, and command buffers they fill for the main thread
+/// `World` keeps its components in `RefCell`s, so it can't be shared between threads; a snapshot copies
+/// the component types a task reads, and the task's changes come back as commands applied in one place
+use crate::ecs::{Component, Entity, InvalidComponent, World};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+struct SnapshotData {
+    entities: Vec<Entity>,
+    pools: HashMap<TypeId, HashMap<Entity, Box<dyn Component>>>,
+}
+
+/// Immutable copy of some component types of a world; cheap to clone and `Send + Sync`
+/// Only captured types can be read: other types look absent from every entity
+#[derive(Clone)]
+pub struct WorldSnapshotView {
+    data: Arc<SnapshotData>,
+}
+
+/// Chooses the component types a snapshot copies, e.g.
+/// `WorldSnapshotView::build(&world).with::<GridPositionComponent>().with::<ObstacleComponent>().finish()`
+pub struct SnapshotBuilder<'w> {
+    world: &'w World,
+    data: SnapshotData,
+}
+
+impl<'w> SnapshotBuilder<'w> {
+    /// Copy every component of type T
+    pub fn with<T: Component + 'static>(mut self) -> Self {
+        let pool = self.world.entities_with_components(&[TypeId::of::<T>()])
+            .into_iter()
+            .filter_map(|entity| self.world.get_component::<T>(entity).map(|component| (entity, component.clone_box())))
+            .collect();
+        self.data.pools.insert(TypeId::of::<T>(), pool);
+        self
+    }
+
+    pub fn finish(self) -> WorldSnapshotView {
+        WorldSnapshotView { data: Arc::new(self.data) }
+    }
+}
+
+impl WorldSnapshotView {
+    pub fn build(world: &World) -> SnapshotBuilder<'_> {
+        let data = SnapshotData { entities: world.get_all_entities().clone(), pools: HashMap::new() };
+        SnapshotBuilder { world, data }
+    }
+
+    /// Entities alive when the snapshot was taken, in creation order
+    pub fn entities(&self) -> &[Entity] {
+        &self.data.entities
+    }
+
+    pub fn get_component<T: Component + 'static>(&self, entity: Entity) -> Option<&T> {
+        self.data.pools.get(&TypeId::of::<T>())?.get(&entity)?.as_any().downcast_ref::<T>()
+    }
+
+    pub fn has_component<T: Component + 'static>(&self, entity: Entity) -> bool {
+        self.data.pools.get(&TypeId::of::<T>()).is_some_and(|pool| pool.contains_key(&entity))
+    }
+
+    /// Entities holding all the given component types, in creation order
+    pub fn entities_with_components(&self, component_types: &[TypeId]) -> Vec<Entity> {
+        let Some(pools) = component_types.iter()
+            .map(|type_id| self.data.pools.get(type_id))
+            .collect::<Option<Vec<_>>>() else {
+            return Vec::new();
+        };
+        let Some(driver) = pools.iter().min_by_key(|pool| pool.len()) else {
+            return self.data.entities.clone();
+        };
+        let mut result: Vec<Entity> = driver.keys()
+            .copied()
+            .filter(|entity| pools.iter().all(|pool| pool.contains_key(entity)))
+            .collect();
+        result.sort_unstable();
+        result
+    }
+}
+
+type Command = Box<dyn FnOnce(&mut World) -> Result<(), InvalidComponent> + Send>;
+
+/// World changes queued by a worker thread, applied later by the thread that owns the world
+#[derive(Default)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_component<T: Component + 'static>(&mut self, entity: Entity, component: T) {
+        self.commands.push(Box::new(move |world| world.add_component(entity, component)));
+    }
+
+    pub fn remove_component<T: Component + 'static>(&mut self, entity: Entity) {
+        self.commands.push(Box::new(move |world| {
+            world.remove_component::<T>(entity);
+            Ok(())
+        }));
+    }
+
+    pub fn destroy_entity(&mut self, entity: Entity) {
+        self.commands.push(Box::new(move |world| {
+            world.destroy_entity(entity);
+            Ok(())
+        }));
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Apply the commands in the order they were queued
+    /// A rejected component doesn't stop the rest; the first rejection is returned
+    pub fn apply(self, world: &mut World) -> Result<(), InvalidComponent> {
+        let mut first_error = None;
+        for command in self.commands {
+            if let Err(error) = command(world) {
+                first_error.get_or_insert(error);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_game_components::{GridPositionComponent, ObstacleComponent};
+    use crate::jobs::JobPool;
+    use crate::pathfinding::{find_path, PathComponent, PathRequestComponent, PathRequestStatus};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_plan_paths_on_worker_thread() {
+        assert_send_sync::<WorldSnapshotView>();
+        let mut world = World::new();
+        for y in 0..4 {
+            world.spawn((GridPositionComponent { x: 2, y }, ObstacleComponent { block_movement: true })).unwrap();
+        }
+        let walker = world.spawn((GridPositionComponent { x: 0, y: 0 }, PathRequestComponent::new((4, 0)))).unwrap();
+
+        let view = WorldSnapshotView::build(&world)
+            .with::<GridPositionComponent>()
+            .with::<ObstacleComponent>()
+            .with::<PathRequestComponent>()
+            .finish();
+        assert!(view.get_component::<PathComponent>(walker).is_none());
+
+        // The worker reads the snapshot and only queues its results
+        let jobs = JobPool::new(1);
+        let commands = jobs.submit(move || {
+            let mut commands = CommandBuffer::new();
+            let obstacles = view.entities_with_components(&[TypeId::of::<ObstacleComponent>()]);
+            let blocked: Vec<(i32, i32)> = obstacles.iter()
+                .filter_map(|entity| view.get_component::<GridPositionComponent>(*entity))
+                .map(|pos| (pos.x, pos.y))
+                .collect();
+            for entity in view.entities_with_components(&[TypeId::of::<PathRequestComponent>()]) {
+                let (Some(pos), Some(request)) = (view.get_component::<GridPositionComponent>(entity), view.get_component::<PathRequestComponent>(entity)) else { continue };
+                if let Some(waypoints) = find_path((pos.x, pos.y), request.goal, 5, 5, |x, y| blocked.contains(&(x, y))) {
+                    commands.add_component(entity, PathComponent::new(waypoints));
+                    commands.add_component(entity, PathRequestComponent { goal: request.goal, status: PathRequestStatus::Found });
+                }
+            }
+            commands
+        }).wait().unwrap();
+
+        assert_eq!(commands.len(), 2);
+        commands.apply(&mut world).unwrap();
+        assert_eq!(world.get_component::<PathComponent>(walker).unwrap().remaining().last(), Some(&(4, 0)));
+        assert_eq!(world.get_component::<PathRequestComponent>(walker).unwrap().status, PathRequestStatus::Found);
+    }
+}
@@ -0,0 +1,28 @@
+/**
+ * Rust City Builder - Access Tokens
+ *
+ * A page opened as `/#token=<token>` keeps the token and sends it as a bearer token with every request to
+ * the game server. It is also stored in the `citybuilder_token` cookie, so page loads, images and sounds carry it.
+ */
+(function () {
+    const match = window.location.hash.match(/(?:^#|&)token=([^&]+)/);
+    if (match) {
+        localStorage.setItem('citybuilderToken', decodeURIComponent(match[1]));
+        // Drop the token from the address bar so it isn't shared along with the link
+        history.replaceState(null, '', window.location.pathname + window.location.search);
+    }
+    const token = localStorage.getItem('citybuilderToken');
+    if (!token) return;
+    document.cookie = `citybuilder_token=${token}; path=/; SameSite=Strict`;
+
+    const send = window.fetch.bind(window);
+    window.fetch = (resource, options = {}) => {
+        const url = new URL(resource instanceof Request ? resource.url : resource, window.location.href);
+        if (url.origin !== window.location.origin) {
+            return send(resource, options);
+        }
+        const headers = new Headers(options.headers || (resource instanceof Request ? resource.headers : undefined));
+        headers.set('Authorization', `Bearer ${token}`);
+        return send(resource, { ...options, headers });
+    };
+})();
@@ -18,7 +18,8 @@ const InputEventType = {
     MOUSE_WHEEL: 'wheel',
     TOUCH_START: 'touchstart',
     TOUCH_END: 'touchend',
-    TOUCH_MOVE: 'touchmove'
+    TOUCH_MOVE: 'touchmove',
+    TEXT_INPUT: 'textinput'
 };
 
 /**
@@ -62,6 +63,21 @@ const MouseButton = {
     2: 'Right'
 };
 
+/**
+ * Input contexts and their priorities; callbacks in higher contexts see events first
+ */
+const InputContext = {
+    GAMEPLAY: 'Gameplay',
+    MENU: 'Menu',
+    TEXT_ENTRY: 'TextEntry'
+};
+
+const InputContextPriority = {
+    Gameplay: 0,
+    Menu: 10,
+    TextEntry: 20
+};
+
 /**
  * Main Input Manager class
  * Provides a unified interface for handling all types of input events
@@ -96,6 +112,9 @@ class InputManager {
         this.eventListeners = new Map();
         this.inputCallbacks = new Map();
         
+        // Active input contexts, Gameplay is always at the bottom
+        this.contextStack = [InputContext.GAMEPLAY];
+        
         // Input history for debugging
         this.inputHistory = [];
         this.maxHistorySize = 100;
@@ -165,6 +184,7 @@ class InputManager {
         // Keyboard events
         this.addEventListener('keydown', this.handleKeyDown.bind(this));
         this.addEventListener('keyup', this.handleKeyUp.bind(this));
+        this.addEventListener('compositionend', this.handleCompositionEnd.bind(this));
         
         // Mouse events
         this.addEventListener('mousedown', this.handleMouseDown.bind(this));
@@ -241,6 +261,20 @@ class InputManager {
             this.addToHistory('key_down', { key, timestamp: Date.now() });
             this.triggerCallback('keydown', { key, originalEvent: event });
         }
+        
+        // Printable characters (including repeats) are also delivered as composed text
+        if (event.key && event.key.length === 1 && !event.ctrlKey && !event.metaKey && !event.isComposing) {
+            this.triggerCallback('textinput', { text: event.key, key, originalEvent: event });
+        }
+    }
+    
+    /**
+     * Handle the end of an IME composition, delivering the composed text
+     * @param {CompositionEvent} event - Composition event
+     */
+    handleCompositionEnd(event) {
+        if (!this.isInputEnabled() || !event.data) return;
+        this.triggerCallback('textinput', { text: event.data, originalEvent: event });
     }
     
     /**
@@ -555,18 +589,62 @@ class InputManager {
         return this.touches.size > 0;
     }
     
+    // ============== INPUT CONTEXTS ==============
+    
+    /**
+     * Activate an input context (Menu, TextEntry, ...)
+     * @param {string} context - Context to push
+     */
+    pushContext(context) {
+        if (!this.contextStack.includes(context)) {
+            this.contextStack.push(context);
+        }
+    }
+    
+    /**
+     * Deactivate an input context; the Gameplay context cannot be removed
+     * @param {string} context - Context to pop (defaults to the most recently pushed one)
+     */
+    popContext(context = null) {
+        const index = context ? this.contextStack.lastIndexOf(context) : this.contextStack.length - 1;
+        if (index > 0) {
+            this.contextStack.splice(index, 1);
+        }
+    }
+    
+    /**
+     * Check if a context is on the stack
+     * @param {string} context - Context name
+     * @returns {boolean} True if active
+     */
+    isContextActive(context) {
+        return this.contextStack.includes(context);
+    }
+    
+    /**
+     * Get the highest-priority active context
+     * @returns {string} Context name
+     */
+    getActiveContext() {
+        return this.contextStack.reduce((top, context) =>
+            (InputContextPriority[context] || 0) > (InputContextPriority[top] || 0) ? context : top);
+    }
+    
     // ============== CALLBACK SYSTEM ==============
     
     /**
      * Register a callback for input events
+     * Callbacks run from the highest-priority context down and can call event.consume()
+     * to hide the event from lower-priority callbacks
      * @param {string} eventType - Type of event ('keydown', 'keyup', 'mousedown', etc.)
      * @param {Function} callback - Callback function to register
+     * @param {string} context - Input context the callback belongs to (defaults to Gameplay)
      */
-    onInput(eventType, callback) {
+    onInput(eventType, callback, context = InputContext.GAMEPLAY) {
         if (!this.inputCallbacks.has(eventType)) {
             this.inputCallbacks.set(eventType, []);
         }
-        this.inputCallbacks.get(eventType).push(callback);
+        this.inputCallbacks.get(eventType).push({ callback, context });
     }
     
     /**
@@ -577,7 +655,7 @@ class InputManager {
     offInput(eventType, callback) {
         if (this.inputCallbacks.has(eventType)) {
             const callbacks = this.inputCallbacks.get(eventType);
-            const index = callbacks.indexOf(callback);
+            const index = callbacks.findIndex(entry => entry.callback === callback);
             if (index > -1) {
                 callbacks.splice(index, 1);
             }
@@ -591,7 +669,20 @@ class InputManager {
      */
     triggerCallback(eventType, eventData) {
         if (this.inputCallbacks.has(eventType)) {
-            for (const callback of this.inputCallbacks.get(eventType)) {
+            eventData.consumed = false;
+            eventData.consume = () => { eventData.consumed = true; };
+            
+            // Text entry owns the keyboard while it is active
+            const isKeyboard = eventType === 'keydown' || eventType === 'keyup';
+            const keyboardOwner = isKeyboard && this.isContextActive(InputContext.TEXT_ENTRY);
+            
+            const entries = this.inputCallbacks.get(eventType)
+                .filter(entry => this.isContextActive(entry.context))
+                .filter(entry => !keyboardOwner || entry.context === InputContext.TEXT_ENTRY)
+                .sort((a, b) => (InputContextPriority[b.context] || 0) - (InputContextPriority[a.context] || 0));
+            
+            for (const { callback } of entries) {
+                if (eventData.consumed) break;
                 try {
                     callback(eventData);
                 } catch (error) {
@@ -740,6 +831,7 @@ class InputManager {
             activeTouches: this.touches.size,
             historySize: this.inputHistory.length,
             pointerLocked: this.pointerLocked,
+            contexts: [...this.contextStack],
             callbacksRegistered: Array.from(this.inputCallbacks.entries()).map(([type, callbacks]) => ({ type, count: callbacks.length }))
         };
     }
@@ -747,10 +839,11 @@ class InputManager {
 
 // Export for use as ES6 module or global
 if (typeof module !== 'undefined' && module.exports) {
-    module.exports = { InputManager, InputEventType, KeyMap, MouseButton };
+    module.exports = { InputManager, InputEventType, InputContext, KeyMap, MouseButton };
 } else if (typeof window !== 'undefined') {
     window.InputManager = InputManager;
     window.InputEventType = InputEventType;
+    window.InputContext = InputContext;
     window.KeyMap = KeyMap;
     window.MouseButton = MouseButton;
 }
\ No newline at end of file
@@ -23,6 +23,10 @@ class RenderingManager {
         this.ctx = canvas.getContext('2d');
         this.commandHistory = [];
         this.isReady = true;
+        // View transform from the server camera, applied before every command's own transform
+        this.viewTransform = [1, 0, 0, 1, 0, 0];
+        // Loaded images by texture/atlas ID; commands fall back to placeholders without one
+        this.textures = new Map();
         
         // Initialize with a clean canvas
         this.clear();
@@ -53,6 +57,39 @@ class RenderingManager {
         this.ctx.restore();
     }
     
+    /**
+     * Resize the canvas to the client viewport and store the view transform
+     * @param {Object} params - Viewport parameters
+     * @param {number} params.width - Viewport width in CSS pixels
+     * @param {number} params.height - Viewport height in CSS pixels
+     * @param {number} params.devicePixelRatio - Physical pixels per CSS pixel
+     * @param {Array} params.viewTransform - World to physical pixel matrix [a, b, c, d, e, f]
+     */
+    setViewport(params) {
+        const { width, height, devicePixelRatio, viewTransform } = params;
+        const dpr = devicePixelRatio || 1;
+        
+        this.canvas.width = Math.round(width * dpr);
+        this.canvas.height = Math.round(height * dpr);
+        this.canvas.style.width = width + 'px';
+        this.canvas.style.height = height + 'px';
+        if (viewTransform && viewTransform.length >= 6) {
+            this.viewTransform = viewTransform;
+        }
+    }
+    
+    /**
+     * Set the context transform to the view transform followed by an optional object transform
+     * @param {Array} transform - Object matrix [a, b, c, d, e, f] (optional)
+     */
+    applyTransform(transform = null) {
+        const v = this.viewTransform;
+        this.ctx.setTransform(v[0], v[1], v[2], v[3], v[4], v[5]);
+        if (transform && transform.length >= 6) {
+            this.ctx.transform(transform[0], transform[1], transform[2], transform[3], transform[4], transform[5]);
+        }
+    }
+    
     /**
      * Draw a grid with specified parameters
      * @param {Object} params - Grid parameters
@@ -70,6 +107,9 @@ class RenderingManager {
         // Clear and set background
         this.clear(backgroundColor[0], backgroundColor[1], backgroundColor[2], backgroundColor[3]);
         
+        this.ctx.save();
+        this.applyTransform();
+        
         // Set line style
         this.ctx.strokeStyle = `rgba(${lineColor[0] * 255}, ${lineColor[1] * 255}, ${lineColor[2] * 255}, ${lineColor[3]})`;
         this.ctx.lineWidth = 1;
@@ -91,6 +131,7 @@ class RenderingManager {
         }
         
         this.ctx.stroke();
+        this.ctx.restore();
     }
     
     /**
@@ -104,10 +145,8 @@ class RenderingManager {
         
         this.ctx.save();
         
-        // Apply transform matrix (assuming 2D affine transform)
-        if (transform && transform.length >= 6) {
-            this.ctx.setTransform(transform[0], transform[1], transform[2], transform[3], transform[4], transform[5]);
-        }
+        // Apply view and object transform matrices (2D affine)
+        this.applyTransform(transform);
         
         // Set color/tint
         if (color) {
@@ -130,6 +169,206 @@ class RenderingManager {
         this.ctx.restore();
     }
     
+    /**
+     * Draw a semi-transparent placement preview tinted by validity
+     * @param {Object} params - Ghost parameters
+     */
+    drawGhost(params) {
+        if (!this.isRenderingReady()) return;
+        
+        const { textureId, transform, size, color } = params;
+        
+        this.ctx.save();
+        
+        this.applyTransform(transform);
+        
+        const width = size ? size[0] : 32;
+        const height = size ? size[1] : 32;
+        const [r, g, b, a] = color || [0, 1, 0, 0.5];
+        
+        this.ctx.fillStyle = `rgba(${r * 255}, ${g * 255}, ${b * 255}, ${a})`;
+        this.ctx.fillRect(-width/2, -height/2, width, height);
+        this.ctx.strokeStyle = `rgba(${r * 255}, ${g * 255}, ${b * 255}, 1)`;
+        this.ctx.setLineDash([4, 4]);
+        this.ctx.strokeRect(-width/2, -height/2, width, height);
+        
+        this.ctx.fillStyle = 'white';
+        this.ctx.font = '12px monospace';
+        this.ctx.textAlign = 'center';
+        this.ctx.fillText(textureId || 'ghost', 0, 0);
+        
+        this.ctx.restore();
+    }
+    
+    /**
+     * Register an image for use as a texture or tile atlas
+     * @param {string} id - Texture ID used by render commands
+     * @param {CanvasImageSource} image - Loaded image
+     */
+    registerTexture(id, image) {
+        this.textures.set(id, image);
+    }
+    
+    /**
+     * Draw a layer of tiles from an atlas
+     * @param {Object} params - Tilemap parameters
+     * @param {string} params.atlasId - Texture ID of the atlas
+     * @param {number} params.atlasColumns - Number of tile columns in the atlas image
+     * @param {Array} params.tileSize - Tile size as [width, height]
+     * @param {number} params.columns - Number of cells per row in the layer
+     * @param {Array} params.tiles - Atlas index per cell in row-major order, null for empty cells
+     * @param {Array} params.transform - Layer transform [a, b, c, d, e, f]
+     * @param {boolean} params.isometric - Place cells as 2:1 diamonds instead of a square grid
+     */
+    drawTilemapLayer(params) {
+        if (!this.isRenderingReady()) return;
+        
+        const { atlasId, atlasColumns, tileSize, columns, tiles, transform, isometric } = params;
+        const [tileWidth, tileHeight] = tileSize;
+        const atlas = this.textures.get(atlasId);
+        
+        this.ctx.save();
+        this.applyTransform(transform);
+        
+        tiles.forEach((tile, cell) => {
+            if (tile === null || tile === undefined) return;
+            
+            const column = cell % columns;
+            const row = Math.floor(cell / columns);
+            // Isometric cells are diamonds whose top corner sits at (column - row, column + row) half-tiles
+            const x = isometric ? (column - row - 1) * tileWidth / 2 : column * tileWidth;
+            const y = isometric ? (column + row) * tileHeight / 2 : row * tileHeight;
+            if (atlas) {
+                const sourceX = (tile % atlasColumns) * tileWidth;
+                const sourceY = Math.floor(tile / atlasColumns) * tileHeight;
+                this.ctx.drawImage(atlas, sourceX, sourceY, tileWidth, tileHeight, x, y, tileWidth, tileHeight);
+            } else {
+                // Placeholder: a distinct color per tile index
+                this.ctx.fillStyle = `hsl(${(tile * 47) % 360}, 50%, 50%)`;
+                this.ctx.fillRect(x, y, tileWidth, tileHeight);
+            }
+        });
+        
+        this.ctx.restore();
+    }
+    
+    /**
+     * Draw connected line segments through a list of points
+     * @param {Object} params - Line strip parameters
+     * @param {Array} params.points - Points as [[x, y], ...]
+     * @param {Array} params.color - Line color as [r, g, b, a] (0-1)
+     * @param {number} params.width - Line width
+     * @param {boolean} params.closed - Connect the last point back to the first
+     */
+    drawLineStrip(params) {
+        if (!this.isRenderingReady()) return;
+        
+        const { points, color, width, closed } = params;
+        if (!points || points.length < 2) return;
+        
+        this.ctx.save();
+        this.applyTransform();
+        
+        this.ctx.beginPath();
+        this.ctx.moveTo(points[0][0], points[0][1]);
+        for (let i = 1; i < points.length; i++) {
+            this.ctx.lineTo(points[i][0], points[i][1]);
+        }
+        if (closed) {
+            this.ctx.closePath();
+        }
+        
+        this.ctx.strokeStyle = `rgba(${color[0] * 255}, ${color[1] * 255}, ${color[2] * 255}, ${color[3]})`;
+        this.ctx.lineWidth = width;
+        this.ctx.lineJoin = 'round';
+        this.ctx.lineCap = 'round';
+        this.ctx.stroke();
+        
+        this.ctx.restore();
+    }
+    
+    /**
+     * Draw a texture stretched to a size with its borders kept unscaled
+     * @param {Object} params - Nine-patch parameters
+     * @param {string} params.textureId - Texture ID of the panel image
+     * @param {Array} params.transform - Transform of the top-left corner [a, b, c, d, e, f]
+     * @param {Array} params.size - Panel size as [width, height]
+     * @param {Array} params.insets - Border widths as [left, top, right, bottom]
+     * @param {Array} params.color - Placeholder color as [r, g, b, a] (0-1)
+     */
+    drawNinePatch(params) {
+        if (!this.isRenderingReady()) return;
+        
+        const { textureId, transform, size, insets, color } = params;
+        const [width, height] = size;
+        const [left, top, right, bottom] = insets;
+        const image = this.textures.get(textureId);
+        
+        this.ctx.save();
+        this.applyTransform(transform);
+        
+        if (image) {
+            const sourceWidth = image.width;
+            const sourceHeight = image.height;
+            // Source and destination edges of the three columns and rows
+            const sx = [0, left, sourceWidth - right, sourceWidth];
+            const sy = [0, top, sourceHeight - bottom, sourceHeight];
+            const dx = [0, left, width - right, width];
+            const dy = [0, top, height - bottom, height];
+            
+            for (let row = 0; row < 3; row++) {
+                for (let col = 0; col < 3; col++) {
+                    const sw = sx[col + 1] - sx[col];
+                    const sh = sy[row + 1] - sy[row];
+                    const dw = dx[col + 1] - dx[col];
+                    const dh = dy[row + 1] - dy[row];
+                    if (sw > 0 && sh > 0 && dw > 0 && dh > 0) {
+                        this.ctx.drawImage(image, sx[col], sy[row], sw, sh, dx[col], dy[row], dw, dh);
+                    }
+                }
+            }
+        } else {
+            // Placeholder: filled panel with a border as thick as the insets
+            const [r, g, b, a] = color || [1, 1, 1, 1];
+            this.ctx.fillStyle = `rgba(${r * 255}, ${g * 255}, ${b * 255}, ${a * 0.8})`;
+            this.ctx.fillRect(0, 0, width, height);
+            this.ctx.strokeStyle = 'rgba(60, 60, 60, 1)';
+            this.ctx.lineWidth = Math.max(1, Math.min(left, top, right, bottom));
+            this.ctx.strokeRect(0, 0, width, height);
+        }
+        
+        this.ctx.restore();
+    }
+    
+    /**
+     * Draw a line of text centered on a point, with a dark outline so it reads over any tile
+     * @param {Object} params - Text parameters
+     * @param {string} params.text - Text to draw
+     * @param {Array} params.position - Center of the text as [x, y]
+     * @param {Array} params.color - Text color as [r, g, b, a] (0-1)
+     * @param {number} params.size - Text height in pixels
+     */
+    drawText(params) {
+        if (!this.isRenderingReady()) return;
+        
+        const { text, position, color, size } = params;
+        const [r, g, b, a] = color;
+        
+        this.ctx.save();
+        this.applyTransform();
+        
+        this.ctx.font = `bold ${size}px sans-serif`;
+        this.ctx.textAlign = 'center';
+        this.ctx.textBaseline = 'middle';
+        this.ctx.lineWidth = Math.max(1, size / 6);
+        this.ctx.strokeStyle = `rgba(0, 0, 0, ${a * 0.8})`;
+        this.ctx.strokeText(text, position[0], position[1]);
+        this.ctx.fillStyle = `rgba(${r * 255}, ${g * 255}, ${b * 255}, ${a})`;
+        this.ctx.fillText(text, position[0], position[1]);
+        
+        this.ctx.restore();
+    }
+    
     /**
      * Draw a shape (circle, rectangle, triangle, etc.)
      * @param {Object} params - Shape parameters
@@ -142,9 +381,7 @@ class RenderingManager {
         this.ctx.save();
         
         // Apply transform
-        if (transform && transform.length >= 6) {
-            this.ctx.setTransform(transform[0], transform[1], transform[2], transform[3], transform[4], transform[5]);
-        }
+        this.applyTransform(transform);
         
         // Begin path for the shape
         this.ctx.beginPath();
@@ -230,6 +467,10 @@ class RenderingManager {
                     this.clear(params.r, params.g, params.b, params.a);
                     break;
                 
+                case 'SetViewport':
+                    this.setViewport(params);
+                    break;
+                
                 case 'DrawGrid':
                     this.drawGrid(params);
                     break;
@@ -242,6 +483,26 @@ class RenderingManager {
                     this.drawShape(params);
                     break;
                 
+                case 'DrawGhost':
+                    this.drawGhost(params);
+                    break;
+                
+                case 'DrawTilemapLayer':
+                    this.drawTilemapLayer(params);
+                    break;
+                
+                case 'DrawLineStrip':
+                    this.drawLineStrip(params);
+                    break;
+                
+                case 'DrawNinePatch':
+                    this.drawNinePatch(params);
+                    break;
+                
+                case 'DrawText':
+                    this.drawText(params);
+                    break;
+                
                 default:
                     console.warn(`Unknown render command type: ${type}`);
             }